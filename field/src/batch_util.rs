@@ -1,5 +1,5 @@
 use crate::packable::Packable;
-use crate::packed::PackedField;
+use crate::packed::{packed_shifted_powers, PackedField};
 use crate::types::Field;
 
 const fn pack_with_leftovers_split_point<P: PackedField>(slice: &[P::Scalar]) -> usize {
@@ -63,3 +63,23 @@ pub fn batch_add_inplace<F: Field>(out: &mut [F], a: &[F]) {
         *x_out += *x_a;
     }
 }
+
+/// Multiplies the `i`th element of `out` by `start * base^i`, e.g. to move the coefficients of a
+/// polynomial to a coset.
+pub fn batch_multiply_by_powers<F: Field>(out: &mut [F], base: F, start: F) {
+    let (out_packed, out_leftovers) =
+        pack_slice_with_leftovers_mut::<<F as Packable>::Packing>(out);
+    let num_packed = out_packed.len() * <F as Packable>::Packing::WIDTH;
+
+    let powers = packed_shifted_powers::<<F as Packable>::Packing>(base, start);
+    for (x_out, power) in out_packed.iter_mut().zip(powers) {
+        *x_out *= power;
+    }
+    let leftover_start = start * base.exp_u64(num_packed as u64);
+    for (x_out, power) in out_leftovers
+        .iter_mut()
+        .zip(base.shifted_powers(leftover_start))
+    {
+        *x_out *= power;
+    }
+}
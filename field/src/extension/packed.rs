@@ -0,0 +1,231 @@
+use core::iter::{Product, Sum};
+use core::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use crate::extension::{Extendable, FieldExtension};
+use crate::packed::PackedField;
+
+/// `P::WIDTH` elements of the degree `D` extension of `P::Scalar`, stored as the `D` packed base
+/// field components of their coordinates. This lets extension field arithmetic be vectorized the
+/// same way as base field arithmetic.
+#[derive(Copy, Clone, Debug)]
+pub struct PackedExtension<P: PackedField, const D: usize>(pub [P; D])
+where
+    P::Scalar: Extendable<D>;
+
+impl<P: PackedField, const D: usize> PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    pub const ZEROS: Self = Self([P::ZEROS; D]);
+
+    pub fn ones() -> Self {
+        P::ONES.into()
+    }
+
+    pub const fn from_basefield_array(arr: [P; D]) -> Self {
+        Self(arr)
+    }
+
+    pub const fn to_basefield_array(self) -> [P; D] {
+        self.0
+    }
+
+    /// The packing of `x` in every lane.
+    pub fn broadcast(x: <P::Scalar as Extendable<D>>::Extension) -> Self {
+        Self(x.to_basefield_array().map(P::from))
+    }
+
+    pub fn scalar_mul(&self, scalar: P) -> Self {
+        Self(self.0.map(|x| x * scalar))
+    }
+
+    pub fn square(&self) -> Self {
+        *self * *self
+    }
+}
+
+impl<P: PackedField, const D: usize> From<P> for PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    fn from(x: P) -> Self {
+        let mut arr = [P::ZEROS; D];
+        arr[0] = x;
+        Self(arr)
+    }
+}
+
+impl<P: PackedField, const D: usize> Neg for PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self(self.0.map(|x| -x))
+    }
+}
+
+impl<P: PackedField, const D: usize> Add for PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        let mut arr = self.0;
+        arr.iter_mut().zip(&rhs.0).for_each(|(x, &y)| *x += y);
+        Self(arr)
+    }
+}
+
+impl<P: PackedField, const D: usize> AddAssign for PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<P: PackedField, const D: usize> Sum for PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZEROS, |acc, x| acc + x)
+    }
+}
+
+impl<P: PackedField, const D: usize> Sub for PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        let mut arr = self.0;
+        arr.iter_mut().zip(&rhs.0).for_each(|(x, &y)| *x -= y);
+        Self(arr)
+    }
+}
+
+impl<P: PackedField, const D: usize> SubAssign for PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<P: PackedField, const D: usize> Mul for PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        let mut res = [P::ZEROS; D];
+        let w = <P::Scalar as Extendable<D>>::W;
+        for i in 0..D {
+            for j in 0..D {
+                res[(i + j) % D] += if i + j < D {
+                    self.0[i] * rhs.0[j]
+                } else {
+                    self.0[i] * rhs.0[j] * w
+                }
+            }
+        }
+        Self(res)
+    }
+}
+
+impl<P: PackedField, const D: usize> MulAssign for PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<P: PackedField, const D: usize> Product for PackedExtension<P, D>
+where
+    P::Scalar: Extendable<D>,
+{
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ones(), |acc, x| acc * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::goldilocks_field::GoldilocksField;
+    use crate::ops::Square;
+    use crate::packable::Packable;
+    use crate::types::Sample;
+
+    type F = GoldilocksField;
+    type P = <F as Packable>::Packing;
+
+    fn pack<const D: usize>(xs: &[<F as Extendable<D>>::Extension]) -> PackedExtension<P, D>
+    where
+        F: Extendable<D>,
+    {
+        PackedExtension(core::array::from_fn(|k| {
+            let mut packed = P::ZEROS;
+            for (lane, x) in packed.as_slice_mut().iter_mut().zip(xs) {
+                *lane = x.to_basefield_array()[k];
+            }
+            packed
+        }))
+    }
+
+    fn unpack<const D: usize>(x: PackedExtension<P, D>) -> Vec<F>
+    where
+        F: Extendable<D>,
+    {
+        x.0.iter().flat_map(|p| p.as_slice().to_vec()).collect()
+    }
+
+    fn test_arithmetic<const D: usize>()
+    where
+        F: Extendable<D>,
+    {
+        let xs = <F as Extendable<D>>::Extension::rand_vec(P::WIDTH);
+        let ys = <F as Extendable<D>>::Extension::rand_vec(P::WIDTH);
+        let map = |f: fn(_, _) -> _| {
+            xs.iter()
+                .zip(&ys)
+                .map(|(&x, &y)| f(x, y))
+                .collect::<Vec<_>>()
+        };
+        let (x, y) = (pack::<D>(&xs), pack::<D>(&ys));
+
+        assert_eq!(unpack(x + y), unpack(pack::<D>(&map(|x, y| x + y))));
+        assert_eq!(unpack(x - y), unpack(pack::<D>(&map(|x, y| x - y))));
+        assert_eq!(unpack(x * y), unpack(pack::<D>(&map(|x, y| x * y))));
+        assert_eq!(unpack(-x), unpack(pack::<D>(&map(|x, _| -x))));
+        assert_eq!(
+            unpack(x.square()),
+            unpack(pack::<D>(&map(|x, _| x.square())))
+        );
+    }
+
+    #[test]
+    fn test_packed_extension_arithmetic() {
+        test_arithmetic::<2>();
+        test_arithmetic::<4>();
+        test_arithmetic::<5>();
+    }
+}
@@ -1,3 +1,4 @@
+use alloc::vec;
 use alloc::vec::Vec;
 use core::cmp::{max, min};
 
@@ -90,6 +91,176 @@ pub fn ifft_with_options<F: Field>(
     PolynomialCoeffs { coeffs: buffer }
 }
 
+/// Splits a length `n = c * 2^k` into its odd part `c` and `k`.
+fn split_mixed_radix_len(n: usize) -> (usize, usize) {
+    assert!(n > 0, "Cannot transform an empty polynomial");
+    let lg_2 = n.trailing_zeros() as usize;
+    (n >> lg_2, lg_2)
+}
+
+/// Evaluates `poly`, of length `n = c * 2^k` with `c` odd, on the subgroup of order `n` generated
+/// by [`Field::primitive_root_of_unity_of_order`]. For a power of two `n`, this is [`fft`].
+///
+/// The coefficients are split into `c` interleaved polynomials `P_r` of length `2^k`, with
+/// `P(x) = sum_r x^r P_r(x^c)`, each of which is evaluated with a radix-2 FFT. The results are then
+/// recombined with a naive DFT of size `c`, which is cheap for the small `c` this is meant for.
+pub fn mixed_radix_fft<F: Field>(poly: PolynomialCoeffs<F>) -> PolynomialValues<F> {
+    let n = poly.len();
+    let (c, lg_2) = split_mixed_radix_len(n);
+    if c == 1 {
+        return fft(poly);
+    }
+
+    let root_table = fft_root_table(1 << lg_2);
+    let parts = (0..c)
+        .map(|r| {
+            let coeffs = poly.coeffs.iter().skip(r).step_by(c).copied().collect();
+            fft_with_options(PolynomialCoeffs::new(coeffs), None, Some(&root_table)).values
+        })
+        .collect::<Vec<_>>();
+
+    let mask = (1 << lg_2) - 1;
+    let values = F::primitive_root_of_unity_of_order(n)
+        .powers()
+        .take(n)
+        .enumerate()
+        .map(|(i, x)| {
+            // Horner's rule for `sum_r x^r P_r(x^c)`.
+            parts
+                .iter()
+                .rev()
+                .fold(F::ZERO, |acc, part| acc * x + part[i & mask])
+        })
+        .collect();
+    PolynomialValues::new(values)
+}
+
+/// The inverse of [`mixed_radix_fft`]: interpolates `poly`, of length `n = c * 2^k` with `c` odd,
+/// from its values on the subgroup of order `n`. For a power of two `n`, this is [`ifft`].
+pub fn mixed_radix_ifft<F: Field>(poly: PolynomialValues<F>) -> PolynomialCoeffs<F> {
+    let n = poly.len();
+    let (c, lg_2) = split_mixed_radix_len(n);
+    if c == 1 {
+        return ifft(poly);
+    }
+
+    let half_n = 1 << lg_2;
+    let omega_inv = F::primitive_root_of_unity_of_order(n).inverse();
+    // `zeta_inv_powers[t]` is `zeta^-t`, with `zeta = omega^(2^k)` of order `c`.
+    let zeta_inv_powers = omega_inv
+        .exp_u64(half_n as u64)
+        .powers()
+        .take(c)
+        .collect::<Vec<_>>();
+    let c_inv = F::from_canonical_usize(c).inverse();
+
+    // With `i = j + t * 2^k`, the values are `v_i = sum_r zeta^(t * r) * (omega^(j * r) * E_r[j])`,
+    // where `E_r` are the evaluations of `P_r` on the subgroup of order `2^k`. An inverse DFT of size
+    // `c` over `t` thus recovers `omega^(j * r) * E_r[j]`.
+    let mut parts = vec![vec![F::ZERO; half_n]; c];
+    for (j, omega_inv_j) in omega_inv.powers().take(half_n).enumerate() {
+        let mut scale = c_inv;
+        for (r, part) in parts.iter_mut().enumerate() {
+            let sum = (0..c)
+                .map(|t| poly.values[j + t * half_n] * zeta_inv_powers[t * r % c])
+                .sum::<F>();
+            part[j] = sum * scale;
+            scale *= omega_inv_j;
+        }
+    }
+
+    let root_table = fft_root_table(half_n);
+    let mut coeffs = vec![F::ZERO; n];
+    for (r, part) in parts.into_iter().enumerate() {
+        let part_coeffs = ifft_with_options(PolynomialValues::new(part), None, Some(&root_table));
+        for (j, coeff) in part_coeffs.coeffs.into_iter().enumerate() {
+            coeffs[j * c + r] = coeff;
+        }
+    }
+    PolynomialCoeffs::new(coeffs)
+}
+
+/// Like [`fft_with_options`], but with the coefficients of `poly` given in bit-reversed order,
+/// which saves the bit-reversal permutation of the input. With a zero factor `r`, only the
+/// coefficients at multiples of `2^r` may be nonzero, which is how the coefficients of a low-degree
+/// extension are laid out in bit-reversed order.
+pub fn fft_bitrev_input<F: Field>(
+    poly: PolynomialCoeffs<F>,
+    zero_factor: Option<usize>,
+    root_table: Option<&FftRootTable<F>>,
+) -> PolynomialValues<F> {
+    let PolynomialCoeffs { coeffs: mut buffer } = poly;
+    let computed_root_table = root_table.is_none().then(|| fft_root_table(buffer.len()));
+    let used_root_table = root_table.or(computed_root_table.as_ref()).unwrap();
+    fft_classic_bitrev_input(&mut buffer, zero_factor.unwrap_or(0), used_root_table);
+    PolynomialValues::new(buffer)
+}
+
+/// Like [`ifft_with_options`], but returns the coefficients in bit-reversed order, which saves the
+/// bit-reversal permutation of the output. Together with [`fft_bitrev_input`], this gives
+/// interpolations and low-degree extensions without any permutation.
+pub fn ifft_bitrev_output<F: Field>(
+    poly: PolynomialValues<F>,
+    root_table: Option<&FftRootTable<F>>,
+) -> PolynomialCoeffs<F> {
+    let PolynomialValues { values: mut buffer } = poly;
+    let n = buffer.len();
+    let lg_n = log2_strict(n);
+    let computed_root_table = root_table.is_none().then(|| fft_root_table(n));
+    let used_root_table = root_table.or(computed_root_table.as_ref()).unwrap();
+    inverse_fft_dif(&mut buffer, used_root_table);
+
+    let n_inv = F::inverse_2exp(lg_n);
+    buffer.iter_mut().for_each(|c| *c *= n_inv);
+    PolynomialCoeffs { coeffs: buffer }
+}
+
+/// Decimation-in-frequency inverse FFT, without the division by `n`: the input is in natural
+/// order and the output in bit-reversed order. `root_table` is the table of the forward FFT.
+fn inverse_fft_dif<F: Field>(values: &mut [F], root_table: &FftRootTable<F>) {
+    let lg_n = log2_strict(values.len());
+    if root_table.len() != lg_n {
+        panic!(
+            "Expected root table of length {}, but it was {}.",
+            lg_n,
+            root_table.len()
+        );
+    }
+
+    let packed_width = <F as Packable>::Packing::WIDTH;
+    for lg_half_m in (0..lg_n).rev() {
+        let half_m = 1 << lg_half_m;
+        // The inverse of the `j`th power of a root of unity of order `2 * half_m` is minus its
+        // `(half_m - j)`th power.
+        let roots = &root_table[lg_half_m];
+        let inv_roots = (0..half_m)
+            .map(|j| if j == 0 { F::ONE } else { -roots[half_m - j] })
+            .collect::<Vec<_>>();
+
+        for block in values.chunks_exact_mut(2 * half_m) {
+            let (left, right) = block.split_at_mut(half_m);
+            if half_m >= packed_width {
+                dif_butterflies(
+                    <F as Packable>::Packing::pack_slice_mut(left),
+                    <F as Packable>::Packing::pack_slice_mut(right),
+                    <F as Packable>::Packing::pack_slice(&inv_roots),
+                );
+            } else {
+                dif_butterflies(left, right, &inv_roots);
+            }
+        }
+    }
+}
+
+#[inline]
+fn dif_butterflies<P: PackedField>(left: &mut [P], right: &mut [P], roots: &[P]) {
+    for ((u, v), &omega) in left.iter_mut().zip(right.iter_mut()).zip(roots) {
+        let (a, b) = (*u, *v);
+        *u = a + b;
+        *v = (a - b) * omega;
+    }
+}
+
 /// Generic FFT implementation that works with both scalar and packed inputs.
 #[unroll_for_loops]
 fn fft_classic_simd<P: PackedField>(
@@ -164,7 +335,11 @@ fn fft_classic_simd<P: PackedField>(
 /// definitely zero.
 pub(crate) fn fft_classic<F: Field>(values: &mut [F], r: usize, root_table: &FftRootTable<F>) {
     reverse_index_bits_in_place(values);
+    fft_classic_bitrev_input(values, r, root_table);
+}
 
+/// The butterflies of [`fft_classic`], on input which is already in bit-reversed order.
+fn fft_classic_bitrev_input<F: Field>(values: &mut [F], r: usize, root_table: &FftRootTable<F>) {
     let n = values.len();
     let lg_n = log2_strict(n);
 
@@ -205,12 +380,15 @@ pub(crate) fn fft_classic<F: Field>(values: &mut [F], r: usize, root_table: &Fft
 mod tests {
     use alloc::vec::Vec;
 
-    use plonky2_util::{log2_ceil, log2_strict};
+    use plonky2_util::{log2_ceil, log2_strict, reverse_index_bits_in_place};
 
-    use crate::fft::{fft, fft_with_options, ifft};
+    use crate::fft::{
+        fft, fft_bitrev_input, fft_with_options, ifft, ifft_bitrev_output, mixed_radix_fft,
+        mixed_radix_ifft,
+    };
     use crate::goldilocks_field::GoldilocksField;
     use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
-    use crate::types::Field;
+    use crate::types::{Field, Sample};
 
     #[test]
     fn fft_and_ifft() {
@@ -248,6 +426,63 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bitrev_fft() {
+        type F = GoldilocksField;
+        let lg_n = 9;
+        let coeffs = PolynomialCoeffs::new(F::rand_vec(1 << lg_n));
+        let mut bitrev_coeffs = coeffs.clone();
+        reverse_index_bits_in_place(&mut bitrev_coeffs.coeffs);
+
+        let values = fft(coeffs.clone());
+        assert_eq!(fft_bitrev_input(bitrev_coeffs.clone(), None, None), values);
+        assert_eq!(ifft_bitrev_output(values.clone(), None), bitrev_coeffs);
+
+        // Low-degree extensions, from coefficients and from values.
+        for r in 0..4 {
+            let lde_values = fft(coeffs.lde(r));
+            let mut bitrev_lde_coeffs = coeffs.lde(r);
+            reverse_index_bits_in_place(&mut bitrev_lde_coeffs.coeffs);
+            assert_eq!(
+                fft_bitrev_input(bitrev_lde_coeffs, Some(r), None),
+                lde_values
+            );
+            assert_eq!(values.clone().lde(r), lde_values);
+        }
+    }
+
+    #[test]
+    fn test_mixed_radix_fft() {
+        type F = GoldilocksField;
+        for (c, lg_2) in [(1, 4), (3, 0), (3, 4), (5, 3), (15, 2)] {
+            let n = c << lg_2;
+            let coeffs = PolynomialCoeffs::new(F::rand_vec(n));
+            let omega = F::primitive_root_of_unity_of_order(n);
+            let expected = omega
+                .powers()
+                .take(n)
+                .map(|x| evaluate_at_naive(&coeffs, x))
+                .collect::<Vec<_>>();
+
+            let values = mixed_radix_fft(coeffs.clone());
+            assert_eq!(values.values, expected);
+            assert_eq!(mixed_radix_ifft(values), coeffs);
+        }
+    }
+
+    #[test]
+    fn test_primitive_root_of_unity_of_order() {
+        type F = GoldilocksField;
+        for (c, lg_2) in [(1, 5), (3, 0), (3, 5), (5, 4), (15, 3)] {
+            let n = c << lg_2;
+            let omega = F::primitive_root_of_unity_of_order(n);
+            assert_eq!(F::generator_order(omega), n);
+            assert_eq!(omega.exp_u64(c as u64), F::primitive_root_of_unity(lg_2));
+            let omega_larger = F::primitive_root_of_unity_of_order(n << 3);
+            assert_eq!(omega_larger.exp_power_of_2(3), omega);
+        }
+    }
+
     fn evaluate_naive<F: Field>(coefficients: &PolynomialCoeffs<F>) -> PolynomialValues<F> {
         let degree = coefficients.len();
         let degree_padded = 1 << log2_ceil(degree);
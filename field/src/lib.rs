@@ -12,6 +12,7 @@ extern crate alloc;
 pub(crate) mod arch;
 
 pub mod batch_util;
+pub mod bn254_scalar;
 pub mod cosets;
 pub mod extension;
 pub mod fft;
@@ -22,6 +23,7 @@ pub mod ops;
 pub mod packable;
 pub mod packed;
 pub mod polynomial;
+pub mod reed_solomon;
 pub mod secp256k1_base;
 pub mod secp256k1_scalar;
 pub mod types;
@@ -125,3 +125,47 @@ unsafe impl<F: Field> PackedField for F {
         }
     }
 }
+
+/// An iterator over blocks of `P::WIDTH` successive powers of a base `b`, starting from `a`:
+/// `[a, a * b, ..., a * b^(WIDTH - 1)]`, then `[a * b^WIDTH, ...]`, and so on.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+#[derive(Clone, Debug)]
+pub struct PackedPowers<P: PackedField> {
+    step: P::Scalar,
+    current: P,
+}
+
+impl<P: PackedField> Iterator for PackedPowers<P> {
+    type Item = P;
+
+    fn next(&mut self) -> Option<P> {
+        let result = self.current;
+        self.current *= self.step;
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+/// The powers of `base`, in blocks of `P::WIDTH`. See [`PackedPowers`].
+pub fn packed_powers<P: PackedField>(base: P::Scalar) -> PackedPowers<P> {
+    packed_shifted_powers(base, P::Scalar::ONE)
+}
+
+/// The powers of `base` multiplied by `start`, in blocks of `P::WIDTH`. See [`PackedPowers`].
+pub fn packed_shifted_powers<P: PackedField>(base: P::Scalar, start: P::Scalar) -> PackedPowers<P> {
+    let mut current = P::default();
+    for (x, power) in current
+        .as_slice_mut()
+        .iter_mut()
+        .zip(base.shifted_powers(start))
+    {
+        *x = power;
+    }
+    PackedPowers {
+        step: base.exp_u64(P::WIDTH as u64),
+        current,
+    }
+}
@@ -11,8 +11,12 @@ use itertools::Itertools;
 use plonky2_util::log2_strict;
 use serde::{Deserialize, Serialize};
 
+use crate::batch_util::batch_multiply_by_powers;
 use crate::extension::{Extendable, FieldExtension};
-use crate::fft::{fft, fft_with_options, ifft, FftRootTable};
+use crate::fft::{
+    fft, fft_bitrev_input, fft_with_options, ifft, ifft_bitrev_output, mixed_radix_fft,
+    mixed_radix_ifft, FftRootTable,
+};
 use crate::types::Field;
 
 /// A polynomial in point-value form.
@@ -26,8 +30,11 @@ pub struct PolynomialValues<F: Field> {
 
 impl<F: Field> PolynomialValues<F> {
     pub fn new(values: Vec<F>) -> Self {
-        // Check that a subgroup exists of this size, which should be a power of two.
-        debug_assert!(log2_strict(values.len()) <= F::TWO_ADICITY);
+        // Check that a subgroup exists of this size, which should be a power of two, or a power of
+        // two times a small odd cofactor for mixed-radix FFTs.
+        debug_assert!(
+            !values.is_empty() && values.len().trailing_zeros() as usize <= F::TWO_ADICITY
+        );
         PolynomialValues { values }
     }
 
@@ -43,6 +50,11 @@ impl<F: Field> PolynomialValues<F> {
         self.values.iter().all(|x| x.is_zero())
     }
 
+    /// Whether all values are equal, i.e. whether the polynomial has degree zero (or is zero).
+    pub fn is_constant(&self) -> bool {
+        self.values.iter().all_equal()
+    }
+
     /// Returns the polynomial whole value is one at the given index, and zero elsewhere.
     pub fn selector(len: usize, index: usize) -> Self {
         let mut result = Self::zero(len);
@@ -59,16 +71,16 @@ impl<F: Field> PolynomialValues<F> {
         ifft(self)
     }
 
+    /// Interpolates `self`, seen as evaluations over the subgroup of order `self.len() = c * 2^k`.
+    /// See [`mixed_radix_ifft`].
+    pub fn mixed_radix_ifft(self) -> PolynomialCoeffs<F> {
+        mixed_radix_ifft(self)
+    }
+
     /// Returns the polynomial whose evaluation on the coset `shift*H` is `self`.
     pub fn coset_ifft(self, shift: F) -> PolynomialCoeffs<F> {
         let mut shifted_coeffs = self.ifft();
-        shifted_coeffs
-            .coeffs
-            .iter_mut()
-            .zip(shift.inverse().powers())
-            .for_each(|(c, r)| {
-                *c *= r;
-            });
+        batch_multiply_by_powers(&mut shifted_coeffs.coeffs, shift.inverse(), F::ONE);
         shifted_coeffs
     }
 
@@ -77,8 +89,14 @@ impl<F: Field> PolynomialValues<F> {
     }
 
     pub fn lde(self, rate_bits: usize) -> Self {
-        let coeffs = ifft(self).lde(rate_bits);
-        fft_with_options(coeffs, Some(rate_bits), None)
+        // In bit-reversed order, each coefficient of the low-degree extension is followed by
+        // `2^rate_bits - 1` zeros, so no permutation is needed.
+        let coeffs = ifft_bitrev_output(self, None).coeffs;
+        let mut lde_coeffs = vec![F::ZERO; coeffs.len() << rate_bits];
+        for (i, c) in coeffs.into_iter().enumerate() {
+            lde_coeffs[i << rate_bits] = c;
+        }
+        fft_bitrev_input(PolynomialCoeffs::new(lde_coeffs), Some(rate_bits), None)
     }
 
     /// Low-degree extend `Self` (seen as evaluations over the subgroup) onto a coset.
@@ -92,6 +110,10 @@ impl<F: Field> PolynomialValues<F> {
     }
 
     pub fn degree_plus_one(&self) -> usize {
+        // Constant columns are common, e.g. among selectors, and don't need an IFFT.
+        if self.is_constant() {
+            return usize::from(!self.is_zero());
+        }
         self.clone().ifft().degree_plus_one()
     }
 
@@ -135,6 +157,11 @@ impl<F: Field> PolynomialCoeffs<F> {
         self.coeffs.iter().all(|x| x.is_zero())
     }
 
+    /// Whether the polynomial has degree zero (or is zero).
+    pub fn is_constant(&self) -> bool {
+        self.coeffs.iter().skip(1).all(|x| x.is_zero())
+    }
+
     /// The number of coefficients. This does not filter out any zero coefficients, so it is not
     /// necessarily related to the degree.
     pub fn len(&self) -> usize {
@@ -263,6 +290,11 @@ impl<F: Field> PolynomialCoeffs<F> {
         fft(self)
     }
 
+    /// Evaluates `self` on the subgroup of order `self.len() = c * 2^k`. See [`mixed_radix_fft`].
+    pub fn mixed_radix_fft(self) -> PolynomialValues<F> {
+        mixed_radix_fft(self)
+    }
+
     pub fn fft_with_options(
         self,
         zero_factor: Option<usize>,
@@ -283,12 +315,8 @@ impl<F: Field> PolynomialCoeffs<F> {
         zero_factor: Option<usize>,
         root_table: Option<&FftRootTable<F>>,
     ) -> PolynomialValues<F> {
-        let modified_poly: Self = shift
-            .powers()
-            .zip(&self.coeffs)
-            .map(|(r, &c)| r * c)
-            .collect::<Vec<_>>()
-            .into();
+        let mut modified_poly = self.clone();
+        batch_multiply_by_powers(&mut modified_poly.coeffs, shift, F::ONE);
         modified_poly.fft_with_options(zero_factor, root_table)
     }
 
@@ -473,6 +501,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_constant_and_degree() {
+        type F = GoldilocksField;
+
+        let c = F::rand();
+        let constant = PolynomialValues::constant(c, 16);
+        assert!(constant.is_constant());
+        assert!(constant.clone().ifft().is_constant());
+        assert_eq!(constant.degree(), 0);
+        assert_eq!(constant.degree_plus_one(), 1);
+        assert_eq!(PolynomialValues::<F>::zero(16).degree_plus_one(), 0);
+
+        let selector = PolynomialValues::<F>::selector(16, 3);
+        assert!(!selector.is_constant());
+        assert!(!selector.clone().ifft().is_constant());
+        assert_eq!(selector.degree(), 15);
+    }
+
     #[test]
     fn test_coset_fft() {
         type F = GoldilocksField;
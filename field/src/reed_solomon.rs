@@ -0,0 +1,191 @@
+//! Reed–Solomon encoding over two-adic fields, built on the same coset low-degree extensions used
+//! by the FRI prover, together with a simple erasure decoder.
+//!
+//! A message of `n = 2^k` field elements is encoded into a codeword of `n << rate_bits` elements,
+//! the evaluations of the message polynomial over the coset `shift * H`, where `H` is the
+//! two-adic subgroup of order `n << rate_bits`. Any `n` symbols of the codeword determine the
+//! message.
+
+use alloc::vec::Vec;
+
+use anyhow::{ensure, Result};
+use plonky2_util::log2_strict;
+
+use crate::interpolation::{barycentric_weights, interpolate};
+use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
+use crate::types::Field;
+
+/// How a message is mapped to the polynomial whose evaluations form the codeword.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RsEncoding {
+    /// The message holds the evaluations of the polynomial over the subgroup of order `n`. When
+    /// `shift` is one, the message appears verbatim at every `2^rate_bits`-th codeword position.
+    Systematic,
+    /// The message holds the coefficients of the polynomial.
+    NonSystematic,
+}
+
+/// Systematically encodes `values` into a Reed–Solomon codeword over the coset `shift * H`, with
+/// `|H| = values.len() << rate_bits`.
+pub fn rs_encode<F: Field>(values: &[F], rate_bits: usize, shift: F) -> Vec<F> {
+    rs_encode_with_options(values, rate_bits, shift, RsEncoding::Systematic)
+}
+
+/// Encodes `values` into a Reed–Solomon codeword over the coset `shift * H`, with
+/// `|H| = values.len() << rate_bits`.
+pub fn rs_encode_with_options<F: Field>(
+    values: &[F],
+    rate_bits: usize,
+    shift: F,
+    encoding: RsEncoding,
+) -> Vec<F> {
+    log2_strict(values.len());
+    let coeffs = match encoding {
+        RsEncoding::Systematic => PolynomialValues::new(values.to_vec()).ifft(),
+        RsEncoding::NonSystematic => PolynomialCoeffs::new(values.to_vec()),
+    };
+    coeffs
+        .lde(rate_bits)
+        .coset_fft_with_options(shift, Some(rate_bits), None)
+        .values
+}
+
+/// Recovers the message from a systematically encoded codeword in which erased symbols are `None`.
+/// See [`rs_decode_erasures_with_options`].
+pub fn rs_decode_erasures<F: Field>(
+    codeword: &[Option<F>],
+    rate_bits: usize,
+    shift: F,
+) -> Result<Vec<F>> {
+    rs_decode_erasures_with_options(codeword, rate_bits, shift, RsEncoding::Systematic)
+}
+
+/// Recovers the message from a codeword in which erased symbols are `None`.
+///
+/// At least `codeword.len() >> rate_bits` symbols must be present. The message is interpolated
+/// from the first `n` of them in `O(n^2)`, and an error is returned if any of the remaining
+/// symbols is inconsistent with it.
+pub fn rs_decode_erasures_with_options<F: Field>(
+    codeword: &[Option<F>],
+    rate_bits: usize,
+    shift: F,
+    encoding: RsEncoding,
+) -> Result<Vec<F>> {
+    let codeword_bits = log2_strict(codeword.len());
+    ensure!(
+        rate_bits <= codeword_bits,
+        "Codeword is shorter than the rate allows"
+    );
+    let n = codeword.len() >> rate_bits;
+
+    let mut points = Vec::with_capacity(n);
+    let mut x = shift;
+    for &symbol in codeword {
+        if let Some(y) = symbol {
+            points.push((x, y));
+            if points.len() == n {
+                break;
+            }
+        }
+        x *= F::primitive_root_of_unity(codeword_bits);
+    }
+    ensure!(
+        points.len() == n,
+        "Too many erasures: need {} symbols, got {}",
+        n,
+        points.len()
+    );
+
+    let weights = barycentric_weights(&points);
+    let subgroup_evals = F::two_adic_subgroup(log2_strict(n))
+        .into_iter()
+        .map(|x| interpolate(&points, x, &weights))
+        .collect::<Vec<_>>();
+
+    let message = match encoding {
+        RsEncoding::Systematic => subgroup_evals,
+        RsEncoding::NonSystematic => PolynomialValues::new(subgroup_evals).ifft().coeffs,
+    };
+
+    let reencoded = rs_encode_with_options(&message, rate_bits, shift, encoding);
+    ensure!(
+        codeword
+            .iter()
+            .zip(&reencoded)
+            .all(|(&symbol, &y)| symbol.is_none_or(|s| s == y)),
+        "Codeword is inconsistent with a message of length {}",
+        n
+    );
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::goldilocks_field::GoldilocksField;
+    use crate::types::Sample;
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_rs_encode() {
+        let rate_bits = 2;
+        let values = F::rand_vec(16);
+
+        let codeword = rs_encode(&values, rate_bits, F::ONE);
+        assert_eq!(codeword.len(), 64);
+        for (i, &v) in values.iter().enumerate() {
+            assert_eq!(codeword[i << rate_bits], v);
+        }
+
+        let shift = F::coset_shift();
+        let coeffs = PolynomialValues::new(values.clone()).ifft();
+        let codeword = rs_encode(&values, rate_bits, shift);
+        assert_eq!(
+            codeword,
+            PolynomialValues::new(values)
+                .lde_onto_coset(rate_bits)
+                .values
+        );
+        let coset_codeword =
+            rs_encode_with_options(&coeffs.coeffs, rate_bits, shift, RsEncoding::NonSystematic);
+        assert_eq!(codeword, coset_codeword);
+    }
+
+    #[test]
+    fn test_rs_decode_erasures() {
+        let rate_bits = 1;
+        let shift = F::coset_shift();
+        for encoding in [RsEncoding::Systematic, RsEncoding::NonSystematic] {
+            let values = F::rand_vec(8);
+            let codeword = rs_encode_with_options(&values, rate_bits, shift, encoding);
+
+            // Erase every other symbol, keeping exactly as many as the message length.
+            let mut received = codeword.iter().copied().map(Some).collect::<Vec<_>>();
+            for i in (0..received.len()).step_by(2) {
+                received[i] = None;
+            }
+            let decoded =
+                rs_decode_erasures_with_options(&received, rate_bits, shift, encoding).unwrap();
+            assert_eq!(decoded, values);
+
+            // One more erasure is too many.
+            received[1] = None;
+            assert!(
+                rs_decode_erasures_with_options(&received, rate_bits, shift, encoding).is_err()
+            );
+        }
+
+        // A corrupted symbol beyond the first `n` present ones is detected.
+        let values = F::rand_vec(8);
+        let mut received = rs_encode(&values, rate_bits, shift)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        received[15] = Some(F::ZERO);
+        assert!(rs_decode_erasures(&received, rate_bits, shift).is_err());
+    }
+}
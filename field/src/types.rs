@@ -271,6 +271,43 @@ pub trait Field:
         base.exp_power_of_2(Self::TWO_ADICITY - n_log)
     }
 
+    /// Returns a primitive root of unity of order `n = c * 2^k`, with `c` odd dividing the order
+    /// of the multiplicative group. For a power of two `n`, this is `primitive_root_of_unity(k)`.
+    ///
+    /// The roots are compatible across orders, in the sense that the root of order `n * 2^r`
+    /// raised to the power `2^r` is the root of order `n`, and the root of order `n` raised to the
+    /// power `c` is `primitive_root_of_unity(k)`. When `c > 1`, the root of an extension field is
+    /// generally not the root of its base field, so roots of the base field should be embedded
+    /// rather than recomputed in the extension.
+    fn primitive_root_of_unity_of_order(n: usize) -> Self {
+        assert!(n > 0, "Roots of unity must have a positive order");
+        let n_log = n.trailing_zeros() as usize;
+        let c = n >> n_log;
+        let w = Self::primitive_root_of_unity(n_log);
+        if c == 1 {
+            return w;
+        }
+
+        let group_order = Self::order() - 1u32;
+        assert!(
+            (&group_order % c).is_zero(),
+            "The multiplicative group has no subgroup of order {n}"
+        );
+        // `h` generates the subgroup of order `c`.
+        let h = Self::MULTIPLICATIVE_GROUP_GENERATOR.exp_biguint(&(group_order / c));
+        // The root is `h^a * w^b`, with `a = 2^-k mod c` and `b = c^-1 mod 2^k`.
+        let two_pow_k_mod_c = (0..n_log).fold(1 % c, |acc, _| 2 * acc % c);
+        let a = (0..c)
+            .find(|&a| a * two_pow_k_mod_c % c == 1)
+            .expect("`c` is odd");
+        // Newton iteration for the inverse of `c` modulo `2^64`.
+        let c_inv = (0..6).fold(c as u64, |x, _| {
+            x.wrapping_mul(2u64.wrapping_sub((c as u64).wrapping_mul(x)))
+        });
+        let b = c_inv & ((1u64 << n_log) - 1);
+        h.exp_u64(a as u64) * w.exp_u64(b)
+    }
+
     /// Computes a multiplicative subgroup whose order is known in advance.
     fn cyclic_subgroup_known_order(generator: Self, order: usize) -> Vec<Self> {
         generator.powers().take(order).collect()
@@ -299,8 +336,7 @@ pub trait Field:
 
     /// Computes a coset of a multiplicative subgroup whose order is known in advance.
     fn cyclic_subgroup_coset_known_order(generator: Self, shift: Self, order: usize) -> Vec<Self> {
-        let subgroup = Self::cyclic_subgroup_known_order(generator, order);
-        subgroup.into_iter().map(|x| x * shift).collect()
+        generator.shifted_powers(shift).take(order).collect()
     }
 
     /// Returns `n % Self::characteristic()`.
@@ -437,6 +473,15 @@ pub trait Field:
         }
     }
 
+    /// The geometric series `1 + x + ... + x^(n - 1)` of `x = self`.
+    fn geometric_sum(&self, n: u64) -> Self {
+        if self.is_one() {
+            Self::from_noncanonical_u64(n)
+        } else {
+            (self.exp_u64(n) - Self::ONE) / (*self - Self::ONE)
+        }
+    }
+
     /// Representative `g` of the coset used in FRI, so that LDEs in FRI are done over `gH`.
     fn coset_shift() -> Self {
         Self::MULTIPLICATIVE_GROUP_GENERATOR
@@ -618,8 +663,11 @@ impl<F: Field> Powers<F> {
 
 #[cfg(test)]
 mod tests {
-    use super::Field;
+    use super::{Field, Sample};
+    use crate::batch_util::batch_multiply_by_powers;
     use crate::goldilocks_field::GoldilocksField;
+    use crate::packable::Packable;
+    use crate::packed::{packed_shifted_powers, PackedField};
 
     #[test]
     fn test_powers_nth() {
@@ -637,4 +685,44 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_packed_powers() {
+        type F = GoldilocksField;
+        type P = <F as Packable>::Packing;
+
+        let (base, start) = (F::rand(), F::rand());
+        let powers = base
+            .shifted_powers(start)
+            .take(8 * P::WIDTH)
+            .collect::<Vec<_>>();
+        let packed = packed_shifted_powers::<P>(base, start)
+            .take(8)
+            .flat_map(|p| p.as_slice().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(packed, powers);
+
+        // Lengths which are not multiples of the packing width leave scalar leftovers.
+        for n in [0, 1, 5, 8 * P::WIDTH - 1] {
+            let mut values = F::rand_vec(n);
+            let expected = values
+                .iter()
+                .zip(base.shifted_powers(start))
+                .map(|(&x, power)| x * power)
+                .collect::<Vec<_>>();
+            batch_multiply_by_powers(&mut values, base, start);
+            assert_eq!(values, expected);
+        }
+    }
+
+    #[test]
+    fn test_geometric_sum() {
+        type F = GoldilocksField;
+
+        let x = F::rand();
+        for n in [0, 1, 2, 17] {
+            assert_eq!(x.geometric_sum(n), x.powers().take(n as usize).sum());
+        }
+        assert_eq!(F::ONE.geometric_sum(5), F::from_canonical_u64(5));
+    }
 }
@@ -33,6 +33,28 @@ impl<F: Field> ZeroPolyOnCoset<F> {
         }
     }
 
+    /// Like [`Self::new`], but for a subgroup `H` of any order `n = c * 2^k`, see
+    /// [`Field::primitive_root_of_unity_of_order`], and a coset of size `2^coset_bits` with
+    /// `k <= coset_bits`. `Z_H` then has period `2^(coset_bits - k)` on the coset.
+    pub fn new_with_subgroup_order(n: usize, coset_bits: usize) -> Self {
+        let n_log = n.trailing_zeros() as usize;
+        assert!(n_log <= coset_bits);
+        let g_pow_n = F::coset_shift().exp_u64(n as u64);
+        let evals = F::primitive_root_of_unity(coset_bits)
+            .exp_u64(n as u64)
+            .powers()
+            .take(1 << (coset_bits - n_log))
+            .map(|x| g_pow_n * x - F::ONE)
+            .collect::<Vec<_>>();
+        let inverses = F::batch_multiplicative_inverse(&evals);
+        Self {
+            n: F::from_canonical_usize(n),
+            rate: evals.len(),
+            evals,
+            inverses,
+        }
+    }
+
     /// Returns `Z_H(g * w^i)`.
     pub fn eval(&self, i: usize) -> F {
         self.evals[i % self.rate]
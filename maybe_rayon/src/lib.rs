@@ -3,6 +3,8 @@
 #[cfg(not(feature = "parallel"))]
 extern crate alloc;
 
+use core::cmp::Ordering;
+
 #[cfg(feature = "parallel")]
 pub use rayon::{
     self,
@@ -10,6 +12,7 @@ pub use rayon::{
         IndexedParallelIterator, ParallelDrainFull, ParallelDrainRange, ParallelExtend,
         ParallelIterator,
     },
+    scope, Scope,
 };
 #[cfg(feature = "parallel")]
 use rayon::{
@@ -24,6 +27,7 @@ use {
     alloc::vec::Vec,
     core::{
         iter::{FlatMap, IntoIterator, Iterator},
+        marker::PhantomData,
         slice::{self, Chunks, ChunksExact, ChunksExactMut, ChunksMut},
     },
 };
@@ -233,6 +237,32 @@ impl<T: Send> MaybeParChunksMut<T> for [T] {
     }
 }
 
+pub trait MaybeParSort<T: Send> {
+    fn par_sort_unstable_by<F>(&mut self, compare: F)
+    where
+        F: Fn(&T, &T) -> Ordering + Sync;
+}
+
+#[cfg(feature = "parallel")]
+impl<T: ?Sized + ParallelSliceMut<U>, U: Send> MaybeParSort<U> for T {
+    fn par_sort_unstable_by<F>(&mut self, compare: F)
+    where
+        F: Fn(&U, &U) -> Ordering + Sync,
+    {
+        self.par_sort_unstable_by(compare)
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<T: Send> MaybeParSort<T> for [T] {
+    fn par_sort_unstable_by<F>(&mut self, compare: F)
+    where
+        F: Fn(&T, &T) -> Ordering + Sync,
+    {
+        self.sort_unstable_by(compare)
+    }
+}
+
 #[cfg(not(feature = "parallel"))]
 pub trait ParallelIteratorMock {
     type Item;
@@ -287,3 +317,31 @@ where
 {
     (oper_a(), oper_b())
 }
+
+/// A scope in which tasks borrowing from the enclosing stack frame can be spawned. Without the
+/// `parallel` feature, each task runs as soon as it is spawned.
+#[cfg(not(feature = "parallel"))]
+pub struct Scope<'scope> {
+    marker: PhantomData<&'scope mut &'scope ()>,
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<'scope> Scope<'scope> {
+    pub fn spawn<BODY>(&self, body: BODY)
+    where
+        BODY: FnOnce(&Scope<'scope>) + 'scope,
+    {
+        body(self)
+    }
+}
+
+/// Creates a [`Scope`], and returns once all the tasks spawned in it have completed.
+#[cfg(not(feature = "parallel"))]
+pub fn scope<'scope, OP, R>(op: OP) -> R
+where
+    OP: FnOnce(&Scope<'scope>) -> R,
+{
+    op(&Scope {
+        marker: PhantomData,
+    })
+}
@@ -0,0 +1,100 @@
+//! Checks that the shim gives the results of the corresponding sequential code, both with and
+//! without the `parallel` feature.
+
+use plonky2_maybe_rayon::*;
+
+fn data() -> Vec<u64> {
+    // A simple LCG, so that the data is unsorted and contains duplicates.
+    let mut x = 1u64;
+    (0..1000)
+        .map(|_| {
+            x = x
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (x >> 33) % 500
+        })
+        .collect()
+}
+
+#[test]
+fn test_par_iter() {
+    let v = data();
+    let expected = v.iter().map(|x| x * x).collect::<Vec<_>>();
+    assert_eq!(v.par_iter().map(|x| x * x).collect::<Vec<_>>(), expected);
+    assert_eq!(
+        v.clone().into_par_iter().map(|x| x * x).collect::<Vec<_>>(),
+        expected
+    );
+
+    let mut w = v.clone();
+    w.par_iter_mut().for_each(|x| *x *= *x);
+    assert_eq!(w, expected);
+
+    assert_eq!(v.par_iter().find_any(|&&x| x == v[500]), Some(&v[500]));
+}
+
+#[test]
+fn test_par_chunks() {
+    let v = data();
+    let sums = |chunks: &[&[u64]]| chunks.iter().map(|c| c.iter().sum()).collect::<Vec<u64>>();
+    assert_eq!(
+        v.par_chunks(7)
+            .map(|c| c.iter().sum())
+            .collect::<Vec<u64>>(),
+        sums(&v.chunks(7).collect::<Vec<_>>())
+    );
+    assert_eq!(
+        v.par_chunks_exact(7)
+            .map(|c| c.iter().sum())
+            .collect::<Vec<u64>>(),
+        sums(&v.chunks_exact(7).collect::<Vec<_>>())
+    );
+
+    let mut expected = v.clone();
+    expected
+        .chunks_exact_mut(7)
+        .enumerate()
+        .for_each(|(i, c)| c.iter_mut().for_each(|x| *x += i as u64));
+    let mut w = v.clone();
+    w.par_chunks_exact_mut(7)
+        .enumerate()
+        .for_each(|(i, c)| c.iter_mut().for_each(|x| *x += i as u64));
+    assert_eq!(w, expected);
+
+    let mut w = v;
+    w.par_chunks_mut(7)
+        .for_each(|c| c.iter_mut().for_each(|x| *x += 1));
+    assert!(w.iter().zip(data()).all(|(&x, y)| x == y + 1));
+}
+
+#[test]
+fn test_par_sort_unstable_by() {
+    let mut expected = data();
+    expected.sort_unstable_by(|a, b| b.cmp(a));
+    let mut v = data();
+    v.par_sort_unstable_by(|a, b| b.cmp(a));
+    assert_eq!(v, expected);
+}
+
+#[test]
+fn test_scope() {
+    let v = data();
+    let mut sums = vec![0; v.len().div_ceil(100)];
+    scope(|s| {
+        for (sum, chunk) in sums.iter_mut().zip(v.chunks(100)) {
+            s.spawn(move |_| *sum = chunk.iter().sum());
+        }
+    });
+    assert_eq!(
+        sums,
+        v.chunks(100).map(|c| c.iter().sum()).collect::<Vec<u64>>()
+    );
+}
+
+#[test]
+fn test_join() {
+    let v = data();
+    let (a, b) = join(|| v.iter().sum::<u64>(), || v.iter().max().copied());
+    assert_eq!(a, v.iter().sum::<u64>());
+    assert_eq!(b, v.iter().max().copied());
+}
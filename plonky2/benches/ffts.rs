@@ -2,7 +2,7 @@ mod allocator;
 
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use plonky2::field::goldilocks_field::GoldilocksField;
-use plonky2::field::polynomial::PolynomialCoeffs;
+use plonky2::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use plonky2::field::types::Field;
 use tynm::type_name;
 
@@ -37,9 +37,37 @@ pub(crate) fn bench_ldes<F: Field>(c: &mut Criterion) {
     }
 }
 
+/// Low-degree extensions of values, with and without bit-reversal permutations.
+pub(crate) fn bench_value_ldes<F: Field>(c: &mut Criterion) {
+    const RATE_BITS: usize = 3;
+
+    let mut group = c.benchmark_group(format!("value lde<{}>", type_name::<F>()));
+
+    for size_log in [13, 14, 15, 16] {
+        let orig_size = 1 << (size_log - RATE_BITS);
+        let lde_size = 1 << size_log;
+        let values = PolynomialValues::new(F::rand_vec(orig_size));
+
+        group.bench_with_input(BenchmarkId::new("permuted", lde_size), &lde_size, |b, _| {
+            b.iter(|| {
+                let coeffs = values.clone().ifft().lde(RATE_BITS);
+                coeffs.fft_with_options(Some(RATE_BITS), None)
+            });
+        });
+        group.bench_with_input(
+            BenchmarkId::new("bit-reversed", lde_size),
+            &lde_size,
+            |b, _| {
+                b.iter(|| values.clone().lde(RATE_BITS));
+            },
+        );
+    }
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     bench_ffts::<GoldilocksField>(c);
     bench_ldes::<GoldilocksField>(c);
+    bench_value_ldes::<GoldilocksField>(c);
 }
 
 criterion_group!(benches, criterion_benchmark);
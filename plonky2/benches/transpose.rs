@@ -3,6 +3,7 @@ mod allocator;
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::field::types::Sample;
+use plonky2::util::matrix::RowMajorMatrix;
 use plonky2::util::transpose;
 
 fn criterion_benchmark(c: &mut Criterion) {
@@ -22,6 +23,16 @@ fn criterion_benchmark(c: &mut Criterion) {
             b.iter(|| transpose(&matrix));
         });
     }
+    group.finish();
+
+    // The prover builds its Merkle leaves from LDEs with a flat, row-major transpose.
+    let mut group = c.benchmark_group("row-major transpose");
+    for height in [5, 50, 100, 150] {
+        group.bench_with_input(BenchmarkId::from_parameter(height), &height, |b, _| {
+            let matrix = (0..height).map(|_| F::rand_vec(WIDTH)).collect::<Vec<_>>();
+            b.iter(|| RowMajorMatrix::from_columns(&matrix));
+        });
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -114,7 +114,7 @@ fn dummy_lookup_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     let table = Arc::new(inps.zip_eq(tip5_table).collect());
     let tip5_idx = builder.add_lookup_table_from_pairs(table);
     let initial_a = builder.add_virtual_target();
-    builder.add_lookup_from_index(initial_a, tip5_idx);
+    builder.lookup(tip5_idx, initial_a);
     builder.register_public_input(initial_a);
 
     // 'size' is in degree, but we want the number of gates in the circuit.
@@ -162,7 +162,7 @@ fn dummy_many_rows_proof<
     let tip5_idx = builder.add_lookup_table_from_table(&inps, &tip5_table);
     let initial_a = builder.add_virtual_target();
 
-    let output = builder.add_lookup_from_index(initial_a, tip5_idx);
+    let output = builder.lookup(tip5_idx, initial_a);
     for _ in 0..514 {
         builder.add_lookup_from_index(output, 0);
     }
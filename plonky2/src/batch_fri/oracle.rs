@@ -20,9 +20,10 @@ use crate::hash::hash_types::RichField;
 use crate::iop::challenger::Challenger;
 use crate::plonk::config::GenericConfig;
 use crate::timed;
+use crate::util::matrix::RowMajorMatrix;
 use crate::util::reducing::ReducingFactor;
+use crate::util::reverse_bits;
 use crate::util::timing::TimingTree;
-use crate::util::{reverse_bits, transpose};
 
 /// Represents a batch FRI oracle, i.e. a batch of polynomials with different degrees which have
 /// been Merkle-ized in a [`BatchMerkleTree`].
@@ -97,7 +98,11 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
                     )
                 );
 
-                let mut leaf_group = timed!(timing, "transpose LDEs", transpose(&lde_values));
+                let mut leaf_group = timed!(
+                    timing,
+                    "transpose LDEs",
+                    RowMajorMatrix::from_columns(&lde_values).into_rows()
+                );
                 reverse_index_bits_in_place(&mut leaf_group);
                 leaves.push(leaf_group);
 
@@ -298,6 +303,7 @@ mod test {
             hiding: false,
             degree_bits: k0,
             reduction_arity_bits,
+            oracle_cap_heights: vec![],
         };
 
         let n0 = 1 << k0;
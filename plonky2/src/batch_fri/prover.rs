@@ -19,6 +19,7 @@ use crate::iop::challenger::Challenger;
 use crate::plonk::config::GenericConfig;
 use crate::plonk::plonk_common::reduce_with_powers;
 use crate::timed;
+use crate::util::matrix::RowMajorMatrix;
 use crate::util::timing::TimingTree;
 
 /// Builds a batch FRI proof.
@@ -103,8 +104,9 @@ pub(crate) fn batch_fri_committed_trees<
         let arity = 1 << arity_bits;
 
         reverse_index_bits_in_place(&mut final_values.values);
-        let chunked_values = final_values.values.par_chunks(arity).map(flatten).collect();
-        let tree = MerkleTree::<F, C::Hasher>::new(chunked_values, fri_params.config.cap_height);
+        let chunked_values = RowMajorMatrix::new(flatten(&final_values.values), D * arity);
+        let tree =
+            MerkleTree::<F, C::Hasher>::from_matrix(chunked_values, fri_params.config.cap_height);
 
         challenger.observe_cap(&tree.cap);
         trees.push(tree);
@@ -161,7 +163,7 @@ fn batch_fri_prover_query_rounds<
         .get_n_challenges(fri_params.config.num_query_rounds)
         .into_par_iter()
         .map(|rand| {
-            let x_index = rand.to_canonical_u64() as usize % n;
+            let x_index = (rand.to_canonical_u64() % n as u64) as usize;
             batch_fri_prover_query_round::<F, C, D>(
                 initial_merkle_trees,
                 trees,
@@ -260,6 +262,7 @@ mod tests {
             hiding: false,
             degree_bits: k,
             reduction_arity_bits,
+            oracle_cap_heights: vec![],
         };
 
         let n = 1 << k;
@@ -357,6 +360,7 @@ mod tests {
             hiding: false,
             degree_bits: k0,
             reduction_arity_bits,
+            oracle_cap_heights: vec![],
         };
 
         let n0 = 1 << k0;
@@ -0,0 +1,343 @@
+//! An incrementally verifiable Merkle accumulator, i.e. a cyclic circuit whose proofs attest that a
+//! Merkle root was obtained from an initial root by a sequence of leaf updates.
+//!
+//! Each step replaces the leaf at some index of a tree of fixed height, and verifies the proof of
+//! the previous step, if any. Appending a leaf is updating one which wasn't used yet, e.g. a zero
+//! leaf. Leaves are digests, which are used as is, like 4-element leaves of a native
+//! [`MerkleTree`](crate::hash::merkle_tree::MerkleTree) with a cap height of zero.
+//!
+//! The public inputs of a proof are its [`MerkleAccumulatorState`], followed by the verifier data
+//! of the circuit, as required by cyclic recursion.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use anyhow::{ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::field::types::{Field, PrimeField64};
+use crate::gates::noop::NoopGate;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
+use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{
+    CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget,
+};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use crate::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
+use crate::recursion::dummy_circuit::cyclic_base_proof;
+
+/// The number of public inputs of a [`MerkleAccumulatorState`].
+const NUM_STATE_PUBLIC_INPUTS: usize = 2 * NUM_HASH_OUT_ELTS + 1;
+
+/// The state proven by a proof of a [`MerkleAccumulatorCircuit`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MerkleAccumulatorState<F: Field> {
+    /// The root of the tree before the first update.
+    pub initial_root: HashOut<F>,
+    /// The root of the tree after the last update.
+    pub root: HashOut<F>,
+    /// The number of updates applied to the initial tree.
+    pub num_updates: u64,
+}
+
+impl<F: PrimeField64> MerkleAccumulatorState<F> {
+    /// Reads the state from the public inputs of a proof.
+    pub fn from_public_inputs(public_inputs: &[F]) -> Result<Self> {
+        ensure!(
+            public_inputs.len() >= NUM_STATE_PUBLIC_INPUTS,
+            "Not enough public inputs"
+        );
+        Ok(Self {
+            initial_root: HashOut::from_partial(&public_inputs[..NUM_HASH_OUT_ELTS]),
+            root: HashOut::from_partial(&public_inputs[NUM_HASH_OUT_ELTS..2 * NUM_HASH_OUT_ELTS]),
+            num_updates: public_inputs[2 * NUM_HASH_OUT_ELTS].to_canonical_u64(),
+        })
+    }
+}
+
+/// The targets of a [`MerkleAccumulatorState`], e.g. in the public inputs of a proof verified by
+/// another circuit.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MerkleAccumulatorStateTarget {
+    pub initial_root: HashOutTarget,
+    pub root: HashOutTarget,
+    pub num_updates: Target,
+}
+
+impl MerkleAccumulatorStateTarget {
+    /// Reads the state from the public inputs of a proof.
+    pub fn from_public_inputs(public_inputs: &[Target]) -> Self {
+        assert!(
+            public_inputs.len() >= NUM_STATE_PUBLIC_INPUTS,
+            "Not enough public inputs"
+        );
+        Self {
+            initial_root: HashOutTarget::from_vec(public_inputs[..NUM_HASH_OUT_ELTS].to_vec()),
+            root: HashOutTarget::from_vec(
+                public_inputs[NUM_HASH_OUT_ELTS..2 * NUM_HASH_OUT_ELTS].to_vec(),
+            ),
+            num_updates: public_inputs[2 * NUM_HASH_OUT_ELTS],
+        }
+    }
+}
+
+/// The replacement of the leaf at `index`, `old_leaf`, with `new_leaf`.
+#[derive(Clone, Debug)]
+pub struct MerkleUpdate<F: RichField, H: AlgebraicHasher<F>> {
+    pub index: usize,
+    pub old_leaf: HashOut<F>,
+    pub new_leaf: HashOut<F>,
+    /// The Merkle proof of the leaf, which is the same before and after the update.
+    pub proof: MerkleProof<F, H>,
+}
+
+/// A cyclic circuit applying one [`MerkleUpdate`] to a tree of the given height per proof.
+#[derive(Debug)]
+pub struct MerkleAccumulatorCircuit<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    pub data: CircuitData<F, C, D>,
+    pub height: usize,
+    /// Whether the proof verifies a previous proof, rather than being the first one.
+    has_previous: BoolTarget,
+    previous_proof: ProofWithPublicInputsTarget<D>,
+    verifier_data: VerifierCircuitTarget,
+    index_bits: Vec<BoolTarget>,
+    old_leaf: HashOutTarget,
+    new_leaf: HashOutTarget,
+    siblings: MerkleProofTarget,
+}
+
+impl<F, C, const D: usize> MerkleAccumulatorCircuit<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    /// Builds the circuit for trees with `2^height` leaves, with the standard recursion
+    /// configuration.
+    pub fn new(height: usize) -> Result<Self> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut common_data = cyclic_common_data::<F, C, D>(config.clone());
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let initial_root = builder.add_virtual_hash_public_input();
+        let root = builder.add_virtual_hash_public_input();
+        let num_updates = builder.add_virtual_public_input();
+        let verifier_data = builder.add_verifier_data_public_inputs();
+        common_data.num_public_inputs = builder.num_public_inputs();
+
+        let has_previous = builder.add_virtual_bool_target_safe();
+        let previous_proof = builder.add_virtual_proof_with_pis(&common_data);
+        let previous =
+            MerkleAccumulatorStateTarget::from_public_inputs(&previous_proof.public_inputs);
+
+        // Without a previous proof, the initial root is unconstrained, and updated directly.
+        builder.connect_hashes(initial_root, previous.initial_root);
+        let old_root = builder.select_hash(has_previous, previous.root, initial_root);
+        let one = builder.one();
+        let new_num_updates = builder.mul_add(has_previous.target, previous.num_updates, one);
+        builder.connect(num_updates, new_num_updates);
+
+        let index_bits = (0..height)
+            .map(|_| builder.add_virtual_bool_target_safe())
+            .collect::<Vec<_>>();
+        let old_leaf = builder.add_virtual_hash();
+        let new_leaf = builder.add_virtual_hash();
+        let siblings = MerkleProofTarget {
+            siblings: builder.add_virtual_hashes(height),
+        };
+        let computed_old_root = builder.merkle_root_from_proof::<C::Hasher>(
+            old_leaf.elements.to_vec(),
+            &index_bits,
+            &siblings,
+        );
+        builder.connect_hashes(computed_old_root, old_root);
+        let new_root = builder.merkle_root_from_proof::<C::Hasher>(
+            new_leaf.elements.to_vec(),
+            &index_bits,
+            &siblings,
+        );
+        builder.connect_hashes(root, new_root);
+
+        builder.conditionally_verify_cyclic_proof_or_dummy::<C>(
+            has_previous,
+            &previous_proof,
+            &common_data,
+        )?;
+
+        Ok(Self {
+            data: builder.build::<C>(),
+            height,
+            has_previous,
+            previous_proof,
+            verifier_data,
+            index_bits,
+            old_leaf,
+            new_leaf,
+            siblings,
+        })
+    }
+
+    /// Proves the first update of the tree with root `initial_root`.
+    pub fn prove_first(
+        &self,
+        initial_root: HashOut<F>,
+        update: &MerkleUpdate<F, C::Hasher>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let base_proof = cyclic_base_proof(
+            &self.data.common,
+            &self.data.verifier_only,
+            initial_root.elements.into_iter().enumerate().collect(),
+        );
+        self.prove(false, &base_proof, update)
+    }
+
+    /// Proves `update` of the tree resulting from the updates proven by `previous`.
+    pub fn prove_next(
+        &self,
+        previous: &ProofWithPublicInputs<F, C, D>,
+        update: &MerkleUpdate<F, C::Hasher>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        self.prove(true, previous, update)
+    }
+
+    fn prove(
+        &self,
+        has_previous: bool,
+        previous: &ProofWithPublicInputs<F, C, D>,
+        update: &MerkleUpdate<F, C::Hasher>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            update.index >> self.height == 0 && update.proof.len() == self.height,
+            "The update is for a tree of another height"
+        );
+
+        let mut pw = PartialWitness::new();
+        pw.set_bool_target(self.has_previous, has_previous)?;
+        pw.set_proof_with_pis_target(&self.previous_proof, previous)?;
+        pw.set_verifier_data_target(&self.verifier_data, &self.data.verifier_only)?;
+        for (i, &bit) in self.index_bits.iter().enumerate() {
+            pw.set_bool_target(bit, (update.index >> i) & 1 == 1)?;
+        }
+        pw.set_hash_target(self.old_leaf, update.old_leaf)?;
+        pw.set_hash_target(self.new_leaf, update.new_leaf)?;
+        for (&sibling_t, &sibling) in self.siblings.siblings.iter().zip(&update.proof.siblings) {
+            pw.set_hash_target(sibling_t, sibling)?;
+        }
+        self.data.prove(pw)
+    }
+
+    /// Verifies `proof`, including its verifier data, and returns the state it attests to.
+    pub fn verify(
+        &self,
+        proof: ProofWithPublicInputs<F, C, D>,
+    ) -> Result<MerkleAccumulatorState<F>> {
+        check_cyclic_proof_verifier_data(&proof, &self.data.verifier_only, &self.data.common)?;
+        let state = MerkleAccumulatorState::from_public_inputs(&proof.public_inputs)?;
+        self.data.verify(proof)?;
+        Ok(state)
+    }
+}
+
+/// Returns common data for a cyclic circuit which verifies proofs of itself, whose gates fit in
+/// that of a circuit verifying a recursive proof.
+fn cyclic_common_data<F, C, const D: usize>(config: CircuitConfig) -> CommonCircuitData<F, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    let builder = CircuitBuilder::<F, D>::new(config.clone());
+    let data = builder.build::<C>();
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    let proof = builder.add_virtual_proof_with_pis(&data.common);
+    let verifier_data = builder.add_virtual_verifier_data(data.common.config.fri_config.cap_height);
+    builder.verify_proof::<C>(&proof, &verifier_data, &data.common);
+    let data = builder.build::<C>();
+
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+    let proof = builder.add_virtual_proof_with_pis(&data.common);
+    let verifier_data = builder.add_virtual_verifier_data(data.common.config.fri_config.cap_height);
+    builder.verify_proof::<C>(&proof, &verifier_data, &data.common);
+    // The cyclic circuit selects between the previous proof and a dummy proof, so make sure the
+    // gate set includes `SelectGate`.
+    let condition = builder.add_virtual_bool_target_safe();
+    let (x, y) = (builder.add_virtual_target(), builder.add_virtual_target());
+    builder.select(condition, x, y);
+    while builder.num_gates() < 1 << 12 {
+        builder.add_gate(NoopGate, Vec::new());
+    }
+    builder.build::<C>().common
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_merkle_accumulator() -> Result<()> {
+        let height = 8;
+        let circuit = MerkleAccumulatorCircuit::<F, C, D>::new(height)?;
+
+        let mut leaves = vec![HashOut::<F>::ZERO; 1 << height];
+        let tree = |leaves: &[HashOut<F>]| {
+            MerkleTree::<F, <C as GenericConfig<D>>::Hasher>::new(
+                leaves.iter().map(|leaf| leaf.elements.to_vec()).collect(),
+                0,
+            )
+        };
+        let initial_root = tree(&leaves).cap.0[0];
+
+        let mut proof = None;
+        for (n, index) in [0, 1, 0, 200].into_iter().enumerate() {
+            let old_tree = tree(&leaves);
+            let update = MerkleUpdate {
+                index,
+                old_leaf: leaves[index],
+                new_leaf: HashOut::rand(),
+                proof: old_tree.prove(index),
+            };
+            leaves[index] = update.new_leaf;
+            let next_proof = match &proof {
+                None => circuit.prove_first(initial_root, &update)?,
+                Some(previous) => circuit.prove_next(previous, &update)?,
+            };
+
+            let state = circuit.verify(next_proof.clone())?;
+            assert_eq!(
+                state,
+                MerkleAccumulatorState {
+                    initial_root,
+                    root: tree(&leaves).cap.0[0],
+                    num_updates: n as u64 + 1,
+                }
+            );
+            proof = Some(next_proof);
+        }
+
+        // An update whose old leaf isn't in the tree can't be proven.
+        let update = MerkleUpdate {
+            index: 3,
+            old_leaf: HashOut::rand(),
+            new_leaf: HashOut::rand(),
+            proof: tree(&leaves).prove(3),
+        };
+        assert!(circuit.prove_next(&proof.unwrap(), &update).is_err());
+
+        Ok(())
+    }
+}
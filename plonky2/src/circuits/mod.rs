@@ -0,0 +1,5 @@
+//! Prebuilt circuits for common applications, which are built once and reused for many proofs.
+
+pub mod merkle_accumulator;
+pub mod range_proof;
+pub mod rollup;
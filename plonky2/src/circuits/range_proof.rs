@@ -0,0 +1,170 @@
+//! Standalone proofs that a committed value lies in the range `[0, 2^BITS)`.
+//!
+//! A proof of a [`RangeProofCircuit`] has the commitment to the value, as computed by
+//! [`CommitAndProve::commit`] on the single value, as its only public inputs. The value and the
+//! blinding factor of the commitment stay private.
+
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+use anyhow::{ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::gadgets::commitment::CommitAndProve;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+
+/// A circuit proving that the value behind a public commitment is less than `2^BITS`.
+#[derive(Debug)]
+pub struct RangeProofCircuit<F, C, const D: usize, const BITS: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    pub data: CircuitData<F, C, D>,
+    commitment: HashOutTarget,
+    value: Target,
+    blinding: HashOutTarget,
+}
+
+impl<F, C, const D: usize, const BITS: usize> RangeProofCircuit<F, C, D, BITS>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    /// Builds the circuit with the given configuration.
+    pub fn new(config: CircuitConfig) -> Self {
+        assert!(BITS < 64, "Values of {} bits don't fit in the field", BITS);
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let commitment = builder.add_virtual_hash_public_input();
+        let value = builder.add_virtual_target();
+        let blinding = builder.add_virtual_hash();
+        builder.range_check(value, BITS);
+        builder.open_commitment::<C::Hasher>(commitment, &[value], blinding);
+
+        Self {
+            data: builder.build::<C>(),
+            commitment,
+            value,
+            blinding,
+        }
+    }
+
+    /// Returns the circuit built with the standard recursion configuration, building it on the
+    /// first call only.
+    #[cfg(feature = "std")]
+    pub fn cached() -> Arc<Self>
+    where
+        F: 'static,
+        C: 'static,
+    {
+        use std::any::{Any, TypeId};
+        use std::collections::HashMap;
+        use std::sync::{Mutex, OnceLock};
+
+        type Cache = Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>;
+        // This static is shared by all instantiations, which are told apart by their `TypeId`.
+        static CACHE: OnceLock<Cache> = OnceLock::new();
+
+        let mut cache = CACHE.get_or_init(Default::default).lock().unwrap();
+        let circuit = cache
+            .entry(TypeId::of::<Self>())
+            .or_insert_with(|| Arc::new(Self::new(CircuitConfig::standard_recursion_config())))
+            .clone();
+        circuit.downcast().unwrap()
+    }
+
+    /// Returns the commitment to `value` with the blinding factor `blinding`, which proofs of
+    /// `value` attest to.
+    pub fn commit(value: u64, blinding: HashOut<F>) -> HashOut<F> {
+        CommitAndProve::<F, C::Hasher>::commit(&[F::from_canonical_u64(value)], blinding)
+    }
+
+    /// Proves that `value`, committed to with the blinding factor `blinding`, is less than
+    /// `2^BITS`.
+    pub fn prove(
+        &self,
+        value: u64,
+        blinding: HashOut<F>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            value >> BITS == 0,
+            "Value {} doesn't fit in {} bits",
+            value,
+            BITS
+        );
+        let mut pw = PartialWitness::new();
+        pw.set_target(self.value, F::from_canonical_u64(value))?;
+        pw.set_hash_target(self.blinding, blinding)?;
+        pw.set_hash_target(self.commitment, Self::commit(value, blinding))?;
+        self.data.prove(pw)
+    }
+
+    /// Verifies that `proof` attests to the value behind `commitment`.
+    pub fn verify(
+        &self,
+        proof: ProofWithPublicInputs<F, C, D>,
+        commitment: HashOut<F>,
+    ) -> Result<()> {
+        ensure!(
+            proof.public_inputs == commitment.elements,
+            "The proof is for another commitment"
+        );
+        self.data.verify(proof)
+    }
+
+    /// Adds a proof of this circuit to `builder` and verifies it, returning the proof, to be set
+    /// with `set_proof_with_pis_target`, and the commitment it attests to.
+    pub fn verify_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> (ProofWithPublicInputsTarget<D>, HashOutTarget) {
+        let proof = builder.add_virtual_proof_with_pis(&self.data.common);
+        let verifier_data = builder.constant_verifier_data(&self.data.verifier_only);
+        builder.verify_proof::<C>(&proof, &verifier_data, &self.data.common);
+        let commitment = HashOutTarget::from_vec(proof.public_inputs[..NUM_HASH_OUT_ELTS].to_vec());
+        (proof, commitment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type RangeProof = RangeProofCircuit<F, C, D, 16>;
+
+    #[test]
+    fn test_range_proof() -> Result<()> {
+        let circuit = RangeProof::cached();
+        assert!(Arc::ptr_eq(&circuit, &RangeProof::cached()));
+
+        let blinding = CommitAndProve::<F, <C as GenericConfig<D>>::Hasher>::sample_blinding();
+        let commitment = RangeProof::commit(12345, blinding);
+        let proof = circuit.prove(12345, blinding)?;
+        circuit.verify(proof.clone(), commitment)?;
+        assert!(circuit
+            .verify(proof.clone(), RangeProof::commit(12346, blinding))
+            .is_err());
+        assert!(circuit.prove(1 << 16, blinding).is_err());
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let (proof_t, commitment_t) = circuit.verify_circuit(&mut builder);
+        builder.register_public_inputs(&commitment_t.elements);
+        let outer = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_t, &proof)?;
+        let outer_proof = outer.prove(pw)?;
+        assert_eq!(outer_proof.public_inputs, commitment.elements);
+        outer.verify(outer_proof)
+    }
+}
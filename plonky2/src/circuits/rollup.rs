@@ -0,0 +1,526 @@
+//! A template for rollups, i.e. proofs that a batch of transitions, each updating one leaf of a
+//! Merkle tree of states, takes the tree from one root to another.
+//!
+//! Users only describe a single transition by implementing [`StateTransition`]. A
+//! [`RollupCircuit`] then proves batches of transitions, updating the Merkle tree and committing
+//! to the public data of the transitions, and [`RollupAggregationCircuit`]s merge the proofs of
+//! consecutive batches.
+//!
+//! The public inputs of all these proofs are a [`RollupPublicInputs`].
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+
+use anyhow::{ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
+use crate::hash::merkle_proofs::MerkleProofTarget;
+use crate::hash::merkle_tree::MerkleTree;
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+
+/// A transition of the state stored in one leaf of the rollup tree.
+pub trait StateTransition<F: RichField + Extendable<D>, const D: usize> {
+    /// The number of field elements of a leaf.
+    const LEAF_LEN: usize;
+
+    /// The private data of a transition, e.g. a signed transaction.
+    type Transition;
+
+    /// The targets of a [`Self::Transition`].
+    type TransitionTarget;
+
+    /// Adds the targets of a transition to `builder`.
+    fn add_virtual_transition(builder: &mut CircuitBuilder<F, D>) -> Self::TransitionTarget;
+
+    /// Sets the targets of a transition.
+    fn set_transition(
+        witness: &mut PartialWitness<F>,
+        target: &Self::TransitionTarget,
+        transition: &Self::Transition,
+    ) -> Result<()>;
+
+    /// Constrains `transition` to be valid for the leaf `old_leaf`, and returns the new leaf and
+    /// the public data of the transition, which the proof commits to.
+    fn apply_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        old_leaf: &[Target],
+        transition: &Self::TransitionTarget,
+    ) -> (Vec<Target>, Vec<Target>);
+
+    /// Applies `transition` to `old_leaf`, returning the new leaf and the public data, as
+    /// computed by [`Self::apply_circuit`], or an error if the transition is invalid.
+    fn apply(old_leaf: &[F], transition: &Self::Transition) -> Result<(Vec<F>, Vec<F>)>;
+}
+
+/// The public inputs of rollup proofs.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RollupPublicInputs<F: RichField> {
+    /// The root of the tree before the first transition.
+    pub old_root: HashOut<F>,
+    /// The root of the tree after the last transition.
+    pub new_root: HashOut<F>,
+    /// The commitment to the indices and public data of the transitions.
+    pub commitment: HashOut<F>,
+}
+
+impl<F: RichField> RollupPublicInputs<F> {
+    pub fn from_public_inputs(public_inputs: &[F]) -> Result<Self> {
+        ensure!(
+            public_inputs.len() == 3 * NUM_HASH_OUT_ELTS,
+            "Wrong number of public inputs"
+        );
+        let mut digests = public_inputs
+            .chunks(NUM_HASH_OUT_ELTS)
+            .map(HashOut::from_partial);
+        Ok(Self {
+            old_root: digests.next().unwrap(),
+            new_root: digests.next().unwrap(),
+            commitment: digests.next().unwrap(),
+        })
+    }
+
+    /// Returns the commitment of the proof merging proofs with commitments `left` and `right`.
+    pub fn merge_commitments<H: Hasher<F, Hash = HashOut<F>>>(
+        left: HashOut<F>,
+        right: HashOut<F>,
+    ) -> HashOut<F> {
+        H::hash_no_pad(&[left.elements, right.elements].concat())
+    }
+}
+
+/// The targets of a [`RollupPublicInputs`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RollupPublicInputsTarget {
+    pub old_root: HashOutTarget,
+    pub new_root: HashOutTarget,
+    pub commitment: HashOutTarget,
+}
+
+impl RollupPublicInputsTarget {
+    pub fn from_public_inputs(public_inputs: &[Target]) -> Self {
+        assert_eq!(public_inputs.len(), 3 * NUM_HASH_OUT_ELTS);
+        let mut digests = public_inputs
+            .chunks(NUM_HASH_OUT_ELTS)
+            .map(|chunk| HashOutTarget::from_vec(chunk.to_vec()));
+        Self {
+            old_root: digests.next().unwrap(),
+            new_root: digests.next().unwrap(),
+            commitment: digests.next().unwrap(),
+        }
+    }
+
+    fn register<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) {
+        builder.register_public_inputs(&self.old_root.elements);
+        builder.register_public_inputs(&self.new_root.elements);
+        builder.register_public_inputs(&self.commitment.elements);
+    }
+}
+
+/// The native state of a rollup, i.e. the leaves of its tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RollupState<F: RichField> {
+    pub leaves: Vec<Vec<F>>,
+}
+
+impl<F: RichField> RollupState<F> {
+    /// Returns a state of `2^height` copies of `leaf`.
+    pub fn new(height: usize, leaf: Vec<F>) -> Self {
+        Self {
+            leaves: vec![leaf; 1 << height],
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.leaves.len().trailing_zeros() as usize
+    }
+
+    pub fn tree<H: Hasher<F>>(&self) -> MerkleTree<F, H> {
+        MerkleTree::new(self.leaves.clone(), 0)
+    }
+
+    pub fn root<H: Hasher<F>>(&self) -> H::Hash {
+        self.tree::<H>().cap.0[0]
+    }
+}
+
+/// Configures and builds a [`RollupCircuit`] for a given [`StateTransition`].
+#[derive(Debug)]
+pub struct RollupCircuitBuilder<F, C, T, const D: usize> {
+    config: CircuitConfig,
+    height: usize,
+    batch_size: usize,
+    _phantom: PhantomData<(F, C, T)>,
+}
+
+impl<F, C, T, const D: usize> RollupCircuitBuilder<F, C, T, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+    T: StateTransition<F, D>,
+{
+    /// Starts building a rollup over trees with `2^height` leaves, with `batch_size` transitions
+    /// per proof, and the standard recursion configuration.
+    pub fn new(height: usize, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "Batches must not be empty");
+        Self {
+            config: CircuitConfig::standard_recursion_config(),
+            height,
+            batch_size,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_config(mut self, config: CircuitConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn build(self) -> RollupCircuit<F, C, T, D> {
+        let mut builder = CircuitBuilder::<F, D>::new(self.config);
+        let old_root = builder.add_virtual_hash();
+        let mut root = old_root;
+        let mut committed = Vec::new();
+        let transitions = (0..self.batch_size)
+            .map(|_| {
+                let index_bits = (0..self.height)
+                    .map(|_| builder.add_virtual_bool_target_safe())
+                    .collect::<Vec<_>>();
+                let proof = MerkleProofTarget {
+                    siblings: builder.add_virtual_hashes(self.height),
+                };
+                let old_leaf = builder.add_virtual_targets(T::LEAF_LEN);
+                let transition = T::add_virtual_transition(&mut builder);
+
+                let computed_root = builder.merkle_root_from_proof::<C::Hasher>(
+                    old_leaf.clone(),
+                    &index_bits,
+                    &proof,
+                );
+                builder.connect_hashes(computed_root, root);
+                let (new_leaf, public_data) =
+                    T::apply_circuit(&mut builder, &old_leaf, &transition);
+                assert_eq!(new_leaf.len(), T::LEAF_LEN);
+                root = builder.merkle_root_from_proof::<C::Hasher>(new_leaf, &index_bits, &proof);
+
+                committed.push(builder.le_sum(index_bits.iter()));
+                committed.extend(public_data);
+                TransitionTargets {
+                    index_bits,
+                    proof,
+                    old_leaf,
+                    transition,
+                }
+            })
+            .collect();
+        let commitment = builder.hash_n_to_hash_no_pad::<C::Hasher>(committed);
+        let public_inputs = RollupPublicInputsTarget {
+            old_root,
+            new_root: root,
+            commitment,
+        };
+        public_inputs.register(&mut builder);
+
+        RollupCircuit {
+            data: builder.build::<C>(),
+            height: self.height,
+            old_root,
+            transitions,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TransitionTargets<TT> {
+    index_bits: Vec<BoolTarget>,
+    proof: MerkleProofTarget,
+    old_leaf: Vec<Target>,
+    transition: TT,
+}
+
+/// A circuit proving a batch of transitions, built by a [`RollupCircuitBuilder`].
+#[derive(Debug)]
+pub struct RollupCircuit<F, C, T, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    T: StateTransition<F, D>,
+{
+    pub data: CircuitData<F, C, D>,
+    pub height: usize,
+    old_root: HashOutTarget,
+    transitions: Vec<TransitionTargets<T::TransitionTarget>>,
+}
+
+impl<F, C, T, const D: usize> RollupCircuit<F, C, T, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+    T: StateTransition<F, D>,
+{
+    pub fn batch_size(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Proves the batch of `(index, transition)` pairs, each applied to the leaf at `index`, and
+    /// applies them to `state`. On error, `state` is left unchanged.
+    pub fn prove(
+        &self,
+        state: &mut RollupState<F>,
+        batch: &[(usize, T::Transition)],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            batch.len() == self.batch_size(),
+            "Expected {} transitions, got {}",
+            self.batch_size(),
+            batch.len()
+        );
+        ensure!(
+            state.height() == self.height && state.leaves.len() == 1 << self.height,
+            "The state doesn't have 2^{} leaves",
+            self.height
+        );
+
+        let mut new_state = state.clone();
+        let mut pw = PartialWitness::new();
+        pw.set_hash_target(self.old_root, new_state.root::<C::Hasher>())?;
+        for (targets, (index, transition)) in self.transitions.iter().zip(batch) {
+            ensure!(*index < new_state.leaves.len(), "Leaf index out of range");
+            let proof = new_state.tree::<C::Hasher>().prove(*index);
+            let old_leaf = &new_state.leaves[*index];
+            let (new_leaf, _) = T::apply(old_leaf, transition)?;
+
+            for (i, &bit) in targets.index_bits.iter().enumerate() {
+                pw.set_bool_target(bit, (index >> i) & 1 == 1)?;
+            }
+            for (&sibling_t, &sibling) in targets.proof.siblings.iter().zip(&proof.siblings) {
+                pw.set_hash_target(sibling_t, sibling)?;
+            }
+            pw.set_target_arr(&targets.old_leaf, old_leaf)?;
+            T::set_transition(&mut pw, &targets.transition, transition)?;
+            new_state.leaves[*index] = new_leaf;
+        }
+
+        let proof = self.data.prove(pw)?;
+        *state = new_state;
+        Ok(proof)
+    }
+
+    /// Verifies `proof` and returns its public inputs.
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> Result<RollupPublicInputs<F>> {
+        let public_inputs = RollupPublicInputs::from_public_inputs(&proof.public_inputs)?;
+        self.data.verify(proof)?;
+        Ok(public_inputs)
+    }
+
+    /// Returns the commitment of a batch proof of `batch` applied to `state`, as computed by the
+    /// circuit.
+    pub fn commitment(
+        state: &RollupState<F>,
+        batch: &[(usize, T::Transition)],
+    ) -> Result<HashOut<F>> {
+        let mut leaves = state.leaves.clone();
+        let mut committed = Vec::new();
+        for (index, transition) in batch {
+            ensure!(*index < leaves.len(), "Leaf index out of range");
+            let (new_leaf, public_data) = T::apply(&leaves[*index], transition)?;
+            leaves[*index] = new_leaf;
+            committed.push(F::from_canonical_usize(*index));
+            committed.extend(public_data);
+        }
+        Ok(C::Hasher::hash_no_pad(&committed))
+    }
+
+    /// Returns a circuit merging two proofs of consecutive batches.
+    pub fn aggregation_circuit(&self) -> RollupAggregationCircuit<F, C, D> {
+        RollupAggregationCircuit::new(&self.data)
+    }
+}
+
+/// A circuit merging two rollup proofs of the same circuit, the second one starting from the root
+/// the first one ends with. Proofs of an aggregation circuit can be merged in turn by another
+/// aggregation circuit.
+#[derive(Debug)]
+pub struct RollupAggregationCircuit<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    pub data: CircuitData<F, C, D>,
+    left: ProofWithPublicInputsTarget<D>,
+    right: ProofWithPublicInputsTarget<D>,
+}
+
+impl<F, C, const D: usize> RollupAggregationCircuit<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    /// Builds a circuit merging proofs of `inner`, which is either a [`RollupCircuit`] or a
+    /// [`RollupAggregationCircuit`].
+    pub fn new(inner: &CircuitData<F, C, D>) -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(inner.common.config.clone());
+        let verifier_data = builder.constant_verifier_data(&inner.verifier_only);
+        let left = builder.add_virtual_proof_with_pis(&inner.common);
+        let right = builder.add_virtual_proof_with_pis(&inner.common);
+        builder.verify_proof::<C>(&left, &verifier_data, &inner.common);
+        builder.verify_proof::<C>(&right, &verifier_data, &inner.common);
+
+        let left_pis = RollupPublicInputsTarget::from_public_inputs(&left.public_inputs);
+        let right_pis = RollupPublicInputsTarget::from_public_inputs(&right.public_inputs);
+        builder.connect_hashes(left_pis.new_root, right_pis.old_root);
+        let commitment = builder.hash_n_to_hash_no_pad::<C::Hasher>(
+            [left_pis.commitment.elements, right_pis.commitment.elements].concat(),
+        );
+        let public_inputs = RollupPublicInputsTarget {
+            old_root: left_pis.old_root,
+            new_root: right_pis.new_root,
+            commitment,
+        };
+        public_inputs.register(&mut builder);
+
+        Self {
+            data: builder.build::<C>(),
+            left,
+            right,
+        }
+    }
+
+    /// Merges `left` and `right`, proofs of the inner circuit.
+    pub fn prove(
+        &self,
+        left: &ProofWithPublicInputs<F, C, D>,
+        right: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let left_pis = RollupPublicInputs::from_public_inputs(&left.public_inputs)?;
+        let right_pis = RollupPublicInputs::from_public_inputs(&right.public_inputs)?;
+        ensure!(
+            left_pis.new_root == right_pis.old_root,
+            "The proofs aren't of consecutive batches"
+        );
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&self.left, left)?;
+        pw.set_proof_with_pis_target(&self.right, right)?;
+        self.data.prove(pw)
+    }
+
+    /// Verifies `proof` and returns its public inputs.
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> Result<RollupPublicInputs<F>> {
+        let public_inputs = RollupPublicInputs::from_public_inputs(&proof.public_inputs)?;
+        self.data.verify(proof)?;
+        Ok(public_inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::{Field, PrimeField64};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    /// Deposits of 32-bit amounts into accounts holding a balance.
+    struct Deposit;
+
+    impl StateTransition<F, D> for Deposit {
+        const LEAF_LEN: usize = 1;
+        type Transition = u32;
+        type TransitionTarget = Target;
+
+        fn add_virtual_transition(builder: &mut CircuitBuilder<F, D>) -> Target {
+            builder.add_virtual_target()
+        }
+
+        fn set_transition(
+            witness: &mut PartialWitness<F>,
+            target: &Target,
+            transition: &u32,
+        ) -> Result<()> {
+            witness.set_target(*target, F::from_canonical_u32(*transition))
+        }
+
+        fn apply_circuit(
+            builder: &mut CircuitBuilder<F, D>,
+            old_leaf: &[Target],
+            transition: &Target,
+        ) -> (Vec<Target>, Vec<Target>) {
+            builder.range_check(*transition, 32);
+            let balance = builder.add(old_leaf[0], *transition);
+            builder.range_check(balance, 48);
+            (vec![balance], vec![*transition])
+        }
+
+        fn apply(old_leaf: &[F], transition: &u32) -> Result<(Vec<F>, Vec<F>)> {
+            let balance = old_leaf[0].to_canonical_u64() + *transition as u64;
+            ensure!(balance < 1 << 48, "Balance overflow");
+            let amount = F::from_canonical_u32(*transition);
+            Ok((vec![F::from_canonical_u64(balance)], vec![amount]))
+        }
+    }
+
+    #[test]
+    fn test_rollup() -> Result<()> {
+        let circuit = RollupCircuitBuilder::<F, C, Deposit, D>::new(4, 2).build();
+        let mut state = RollupState::new(4, vec![F::ZERO]);
+        let initial_root = state.root::<H>();
+
+        let batch = [(3, 100), (5, 7)];
+        let commitment = RollupCircuit::<F, C, Deposit, D>::commitment(&state, &batch)?;
+        let first = circuit.prove(&mut state, &batch)?;
+        let first_pis = circuit.verify(first.clone())?;
+        assert_eq!(
+            first_pis,
+            RollupPublicInputs {
+                old_root: initial_root,
+                new_root: state.root::<H>(),
+                commitment,
+            }
+        );
+
+        let second = circuit.prove(&mut state, &[(3, 1), (3, 2)])?;
+        let second_pis = circuit.verify(second.clone())?;
+        assert_eq!(state.leaves[3], vec![F::from_canonical_u32(103)]);
+        assert_eq!(state.leaves[5], vec![F::from_canonical_u32(7)]);
+
+        // A batch with an invalid transition is rejected, and doesn't change the state.
+        let before = state.clone();
+        let overflowing = [(0, u32::MAX); 2];
+        state.leaves[0] = vec![F::from_canonical_u64((1 << 48) - 1)];
+        let with_large_balance = state.clone();
+        assert!(circuit.prove(&mut state, &overflowing).is_err());
+        assert_eq!(state, with_large_balance);
+        state = before;
+
+        let aggregation = circuit.aggregation_circuit();
+        assert!(aggregation.prove(&second, &first).is_err());
+        let merged = aggregation.prove(&first, &second)?;
+        assert_eq!(
+            aggregation.verify(merged)?,
+            RollupPublicInputs {
+                old_root: initial_root,
+                new_root: state.root::<H>(),
+                commitment: RollupPublicInputs::merge_commitments::<H>(
+                    first_pis.commitment,
+                    second_pis.commitment
+                ),
+            }
+        );
+        Ok(())
+    }
+}
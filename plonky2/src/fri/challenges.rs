@@ -44,6 +44,7 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
         let fri_alpha = self.get_extension_challenge::<D>();
 
         // Recover the random betas used in the FRI reductions.
+        self.set_phase("fri commit phase");
         let fri_betas = commit_phase_merkle_caps
             .iter()
             .map(|cap| {
@@ -63,6 +64,7 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             }
         }
 
+        self.set_phase("fri final polynomial");
         self.observe_extension_elements(&final_poly.coeffs);
         // When this proof was generated in a circuit with a different final polynomial length,
         // the challenger needs to observe the full length of the final polynomial.
@@ -73,11 +75,13 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             }
         }
 
+        self.set_phase("fri proof of work");
         self.observe_element(pow_witness);
         let fri_pow_response = self.get_challenge();
 
+        self.set_phase("fri queries");
         let fri_query_indices = (0..num_fri_queries)
-            .map(|_| self.get_challenge().to_canonical_u64() as usize % lde_size)
+            .map(|_| (self.get_challenge().to_canonical_u64() % lde_size as u64) as usize)
             .collect();
 
         FriChallenges {
@@ -0,0 +1,173 @@
+//! A cache of low-degree extensions and Merkle trees, so that committing again to the same
+//! polynomials, e.g. the constant and sigma polynomials of a circuit which is built repeatedly, or
+//! columns which recur across proofs, doesn't recompute them.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+
+use crate::field::polynomial::PolynomialCoeffs;
+use crate::hash::hash_types::RichField;
+use crate::hash::merkle_tree::MerkleTree;
+use crate::plonk::config::{GenericHashOut, Hasher};
+
+/// A cache of the low-degree extensions of single polynomials and of the Merkle trees of
+/// unblinded batches, used by [`PolynomialBatch::from_coeffs_with_cache`] and
+/// [`PolynomialBatch::from_values_with_cache`].
+///
+/// Polynomials are identified by the `H` hash of their coefficients. Entries are never evicted, so
+/// the cache should only be used for polynomials which are expected to recur; it can be emptied
+/// with [`Self::clear`].
+///
+/// [`PolynomialBatch::from_coeffs_with_cache`]: crate::fri::oracle::PolynomialBatch::from_coeffs_with_cache
+/// [`PolynomialBatch::from_values_with_cache`]: crate::fri::oracle::PolynomialBatch::from_values_with_cache
+#[derive(Clone, Debug)]
+pub struct LdeCache<F: RichField, H: Hasher<F>> {
+    /// Bit-reversed LDEs, keyed by the hash of the polynomial and the rate.
+    ldes: HashMap<(Vec<u8>, usize), Vec<F>>,
+    /// Merkle trees, keyed by the hashes of the polynomials of the batch, the rate and the cap
+    /// height.
+    trees: HashMap<(Vec<u8>, usize, usize), MerkleTree<F, H>>,
+}
+
+impl<F: RichField, H: Hasher<F>> Default for LdeCache<F, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: RichField, H: Hasher<F>> LdeCache<F, H> {
+    pub fn new() -> Self {
+        Self {
+            ldes: HashMap::new(),
+            trees: HashMap::new(),
+        }
+    }
+
+    /// The number of cached low-degree extensions.
+    pub fn num_ldes(&self) -> usize {
+        self.ldes.len()
+    }
+
+    /// The number of cached Merkle trees.
+    pub fn num_trees(&self) -> usize {
+        self.trees.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.ldes.clear();
+        self.trees.clear();
+    }
+
+    /// The key identifying `polynomial` in the cache.
+    pub(crate) fn polynomial_key(polynomial: &PolynomialCoeffs<F>) -> Vec<u8> {
+        H::hash_no_pad(&polynomial.coeffs).to_bytes()
+    }
+
+    pub(crate) fn get_lde(&self, key: &[u8], rate_bits: usize) -> Option<&Vec<F>> {
+        self.ldes.get(&(key.to_vec(), rate_bits))
+    }
+
+    pub(crate) fn insert_lde(&mut self, key: Vec<u8>, rate_bits: usize, lde: Vec<F>) {
+        self.ldes.insert((key, rate_bits), lde);
+    }
+
+    pub(crate) fn get_tree(
+        &self,
+        keys: &[Vec<u8>],
+        rate_bits: usize,
+        cap_height: usize,
+    ) -> Option<&MerkleTree<F, H>> {
+        self.trees.get(&(keys.concat(), rate_bits, cap_height))
+    }
+
+    pub(crate) fn insert_tree(
+        &mut self,
+        keys: &[Vec<u8>],
+        rate_bits: usize,
+        cap_height: usize,
+        tree: MerkleTree<F, H>,
+    ) {
+        self.trees
+            .insert((keys.concat(), rate_bits, cap_height), tree);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::polynomial::PolynomialValues;
+    use crate::field::types::Sample;
+    use crate::fri::oracle::PolynomialBatch;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::util::timing::TimingTree;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    fn commit(
+        values: &[PolynomialValues<F>],
+        blinding: bool,
+        cache: Option<&mut LdeCache<F, H>>,
+    ) -> PolynomialBatch<F, C, D> {
+        let mut timing = TimingTree::default();
+        match cache {
+            Some(cache) => PolynomialBatch::from_values_with_cache(
+                values.to_vec(),
+                2,
+                blinding,
+                1,
+                &mut timing,
+                None,
+                cache,
+            ),
+            None => {
+                PolynomialBatch::from_values(values.to_vec(), 2, blinding, 1, &mut timing, None)
+            }
+        }
+    }
+
+    #[test]
+    fn cached_commitments_match() {
+        let values = (0..3)
+            .map(|_| PolynomialValues::new(F::rand_vec(1 << 6)))
+            .collect::<Vec<_>>();
+        let expected = commit(&values, false, None);
+
+        let mut cache = LdeCache::new();
+        assert_eq!(commit(&values, false, Some(&mut cache)), expected);
+        assert_eq!((cache.num_ldes(), cache.num_trees()), (3, 1));
+        // The second commitment is read off the cache.
+        assert_eq!(commit(&values, false, Some(&mut cache)), expected);
+        assert_eq!((cache.num_ldes(), cache.num_trees()), (3, 1));
+
+        // A batch sharing columns with the first one only extends the new column.
+        let mut other_values = values[1..].to_vec();
+        other_values.push(PolynomialValues::new(F::rand_vec(1 << 6)));
+        assert_eq!(
+            commit(&other_values, false, Some(&mut cache)),
+            commit(&other_values, false, None)
+        );
+        assert_eq!((cache.num_ldes(), cache.num_trees()), (4, 2));
+
+        cache.clear();
+        assert_eq!((cache.num_ldes(), cache.num_trees()), (0, 0));
+    }
+
+    #[test]
+    fn blinded_trees_are_not_cached() {
+        let values = (0..2)
+            .map(|_| PolynomialValues::new(F::rand_vec(1 << 6)))
+            .collect::<Vec<_>>();
+        let mut cache = LdeCache::new();
+        let first = commit(&values, true, Some(&mut cache));
+        let second = commit(&values, true, Some(&mut cache));
+        assert_eq!((cache.num_ldes(), cache.num_trees()), (2, 0));
+        // Both commitments are salted independently.
+        assert_ne!(first.merkle_tree.cap, second.merkle_tree.cap);
+        assert_eq!(first.get_lde_values(5, 1), second.get_lde_values(5, 1));
+    }
+}
@@ -9,8 +9,10 @@ use alloc::vec::Vec;
 use serde::Serialize;
 
 use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::util::log2_ceil;
 
 mod challenges;
+pub mod lde_cache;
 pub mod oracle;
 pub mod proof;
 pub mod prover;
@@ -41,6 +43,84 @@ pub struct FriConfig {
 }
 
 impl FriConfig {
+    /// Returns the configuration of [`CircuitConfig::standard_recursion_config`], with the number
+    /// of query rounds needed to reach `security_bits` bits of conjectured security, see
+    /// [`Self::security_bits`], at a rate of `2^{-rate_bits}`.
+    ///
+    /// Proven security is lower, and depends on the degree of the circuit; it should be checked
+    /// separately when it matters.
+    ///
+    /// [`CircuitConfig::standard_recursion_config`]: crate::plonk::circuit_data::CircuitConfig::standard_recursion_config
+    pub fn with_security_target(security_bits: usize, rate_bits: usize) -> Self {
+        assert!(rate_bits > 0, "The rate must be less than 1");
+        let proof_of_work_bits = 16;
+        Self {
+            rate_bits,
+            cap_height: 4,
+            proof_of_work_bits,
+            reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
+            num_query_rounds: security_bits
+                .saturating_sub(proof_of_work_bits as usize)
+                .div_ceil(rate_bits),
+        }
+    }
+
+    /// Returns the bits of security of FRI with this configuration, for polynomials of degree
+    /// `2^degree_bits` over the degree-`extension_degree` extension of a 64-bit field.
+    ///
+    /// Conjectured security is that of the ethSTARK paper's Conjecture 1, also checked by
+    /// `CircuitBuilder`: each query round gives `rate_bits` bits, on top of the grinding bits,
+    /// up to the size of the field.
+    ///
+    /// Proven security follows Theorem 2 of the ethSTARK paper, in the Johnson bound regime: with
+    /// `m >= 3`, an evaluation domain of size `n`, a rate `ρ` and a field `F`, the commit phase
+    /// errs with probability at most
+    /// `(m + 1/2)^7 n^2 / (3 ρ^{3/2} |F|) + (2m + 1)(n + 1) Σ arities / (√ρ |F|)`, and the query
+    /// phase with probability at most `α^{num_query_rounds}` scaled down by the grinding, where
+    /// `α = √ρ (1 + 1/2m)`. The best `m` is used.
+    #[cfg(feature = "std")]
+    pub fn security_bits(
+        &self,
+        degree_bits: usize,
+        extension_degree: usize,
+        proven: bool,
+    ) -> usize {
+        let field_bits = 64 * extension_degree;
+        if !proven {
+            let query_bits =
+                self.num_query_rounds * self.rate_bits + self.proof_of_work_bits as usize;
+            return query_bits.min(field_bits);
+        }
+
+        let field_bits = field_bits as f64;
+        let rate_bits = self.rate_bits as f64;
+        let lde_bits = (degree_bits + self.rate_bits) as f64;
+        let sum_arities = self
+            .fri_params(degree_bits, false)
+            .reduction_arity_bits
+            .iter()
+            .map(|&arity_bits| (1 << arity_bits) as f64)
+            .sum::<f64>();
+        let error_bits = |m: f64| {
+            let commit_bits_0 =
+                7.0 * (m + 0.5).log2() - 3f64.log2() + 1.5 * rate_bits + 2.0 * lde_bits
+                    - field_bits;
+            let commit_bits_1 = (2.0 * m + 1.0).log2()
+                + (lde_bits.exp2() + 1.0).log2()
+                + 0.5 * rate_bits
+                + sum_arities.log2()
+                - field_bits;
+            let alpha_bits = -0.5 * rate_bits + (1.0 + 0.5 / m).log2();
+            let query_bits =
+                self.num_query_rounds as f64 * alpha_bits - self.proof_of_work_bits as f64;
+            (commit_bits_0.exp2() + commit_bits_1.exp2() + query_bits.exp2()).log2()
+        };
+        let error_bits = (3..=64)
+            .map(|m| error_bits(m as f64))
+            .fold(f64::INFINITY, f64::min);
+        (-error_bits).max(0.0) as usize
+    }
+
     pub fn rate(&self) -> f64 {
         1.0 / ((1 << self.rate_bits) as f64)
     }
@@ -57,6 +137,7 @@ impl FriConfig {
             hiding,
             degree_bits,
             reduction_arity_bits,
+            oracle_cap_heights: Vec::new(),
         }
     }
 
@@ -83,6 +164,11 @@ pub struct FriParams {
     /// a 4-to-1 reduction, then a 2-to-1 reduction. After these reductions, the reduced polynomial
     /// is sent directly.
     pub reduction_arity_bits: Vec<usize>,
+
+    /// The height of the Merkle cap of each initial oracle, indexed like the oracles of the FRI
+    /// instance. Oracles without an entry, e.g. all of them if this is empty, use
+    /// `config.cap_height`, as do the trees of the commit phase.
+    pub oracle_cap_heights: Vec<usize>,
 }
 
 impl FriParams {
@@ -90,6 +176,22 @@ impl FriParams {
         self.reduction_arity_bits.iter().sum()
     }
 
+    /// The height of the Merkle cap of the initial oracle with the given index.
+    pub fn oracle_cap_height(&self, oracle_index: usize) -> usize {
+        self.oracle_cap_heights
+            .get(oracle_index)
+            .copied()
+            .unwrap_or(self.config.cap_height)
+    }
+
+    /// The height of the Merkle cap minimizing the size of a proof containing the cap of an
+    /// initial oracle and a Merkle path for each query. Raising the height by one adds
+    /// `2^cap_height` digests to the cap, and removes one from each of the `num_query_rounds`
+    /// paths.
+    pub fn optimal_oracle_cap_height(&self) -> usize {
+        log2_ceil(self.config.num_query_rounds).min(self.lde_bits())
+    }
+
     pub(crate) fn max_arity_bits(&self) -> Option<usize> {
         self.reduction_arity_bits.iter().copied().max()
     }
@@ -110,3 +212,37 @@ impl FriParams {
         1 << self.final_poly_bits()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::circuit_data::CircuitConfig;
+
+    #[test]
+    fn test_security_bits() {
+        let standard = CircuitConfig::standard_recursion_config().fri_config;
+        assert_eq!(FriConfig::with_security_target(100, 3), standard);
+        assert_eq!(standard.security_bits(12, 2, false), 100);
+        // Conjectured security is capped by the size of the field.
+        assert_eq!(standard.security_bits(12, 1, false), 64);
+
+        // Proven security is much lower, and decreases with the degree.
+        let proven = standard.security_bits(12, 2, true);
+        assert!((40..100).contains(&proven));
+        assert!(standard.security_bits(24, 2, true) <= proven);
+
+        for rate_bits in 1..=5 {
+            let config = FriConfig::with_security_target(128, rate_bits);
+            assert!(config.security_bits(20, 2, false) >= 128);
+            let fewer_queries = FriConfig {
+                num_query_rounds: config.num_query_rounds - 1,
+                ..config.clone()
+            };
+            assert!(fewer_queries.security_bits(20, 2, false) < 128);
+        }
+        assert!(
+            FriConfig::with_security_target(128, 3).security_bits(20, 2, true)
+                > standard.security_bits(20, 2, true)
+        );
+    }
+}
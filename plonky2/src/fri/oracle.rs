@@ -1,26 +1,29 @@
 #[cfg(not(feature = "std"))]
-use alloc::{format, vec::Vec};
+use alloc::{format, vec, vec::Vec};
 
 use itertools::Itertools;
 use plonky2_field::types::Field;
 use plonky2_maybe_rayon::*;
 
-use crate::field::extension::Extendable;
+use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::fft::FftRootTable;
 use crate::field::packed::PackedField;
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use crate::fri::lde_cache::LdeCache;
 use crate::fri::proof::FriProof;
 use crate::fri::prover::fri_proof;
-use crate::fri::structure::{FriBatchInfo, FriInstanceInfo};
+use crate::fri::structure::{FriBatchInfo, FriInstanceInfo, FriOpeningBatch, FriOpenings};
 use crate::fri::FriParams;
 use crate::hash::hash_types::RichField;
-use crate::hash::merkle_tree::MerkleTree;
+use crate::hash::merkle_tree::{merkle_cap_from_leaf_digests, MerkleCap, MerkleTree};
 use crate::iop::challenger::Challenger;
-use crate::plonk::config::GenericConfig;
+use crate::plonk::config::{GenericConfig, Hasher};
 use crate::timed;
+use crate::util::blinding::blinding_rand_vec;
+use crate::util::matrix::RowMajorMatrix;
 use crate::util::reducing::ReducingFactor;
 use crate::util::timing::TimingTree;
-use crate::util::{log2_strict, reverse_bits, reverse_index_bits_in_place, transpose};
+use crate::util::{log2_strict, reverse_bits, reverse_index_bits_in_place};
 
 /// Four (~64 bit) field elements gives ~128 bit security.
 pub const SALT_SIZE: usize = 4;
@@ -62,11 +65,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
     ) -> Self {
-        let coeffs = timed!(
-            timing,
-            "IFFT",
-            values.into_par_iter().map(|v| v.ifft()).collect::<Vec<_>>()
-        );
+        let coeffs = timed!(timing, "IFFT", Self::interpolate(values));
 
         Self::from_coeffs(
             coeffs,
@@ -88,18 +87,215 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         fft_root_table: Option<&FftRootTable<F>>,
     ) -> Self {
         let degree = polynomials[0].len();
-        let lde_values = timed!(
+        let mut lde_values = timed!(
             timing,
             "FFT + blinding",
             Self::lde_values(&polynomials, rate_bits, blinding, fft_root_table)
         );
 
-        let mut leaves = timed!(timing, "transpose LDEs", transpose(&lde_values));
-        reverse_index_bits_in_place(&mut leaves);
-        let merkle_tree = timed!(
+        // The leaves are in bit-reversed order. Reversing each LDE first lets the transpose write
+        // them directly into a single buffer.
+        lde_values
+            .par_iter_mut()
+            .for_each(|values| reverse_index_bits_in_place(values));
+        let merkle_tree = Self::merkle_tree(lde_values, cap_height, timing);
+
+        Self {
+            polynomials,
+            merkle_tree,
+            degree_log: log2_strict(degree),
+            rate_bits,
+            blinding,
+        }
+    }
+
+    /// Like [`Self::from_values`], but reuses the low-degree extensions and Merkle tree stored in
+    /// `cache` by previous commitments to the same polynomials, and stores the new ones.
+    pub fn from_values_with_cache(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        cache: &mut LdeCache<F, C::Hasher>,
+    ) -> Self {
+        let coeffs = timed!(timing, "IFFT", Self::interpolate(values));
+
+        Self::from_coeffs_with_cache(
+            coeffs,
+            rate_bits,
+            blinding,
+            cap_height,
             timing,
-            "build Merkle tree",
-            MerkleTree::new(leaves, cap_height)
+            fft_root_table,
+            cache,
+        )
+    }
+
+    /// Like [`Self::from_coeffs`], but reuses the low-degree extensions and Merkle tree stored in
+    /// `cache` by previous commitments to the same polynomials, and stores the new ones. The Merkle
+    /// tree of a blinded batch is salted anew, so only the low-degree extensions of its polynomials
+    /// are cached.
+    pub fn from_coeffs_with_cache(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        cache: &mut LdeCache<F, C::Hasher>,
+    ) -> Self {
+        let degree = polynomials[0].len();
+        let keys = timed!(
+            timing,
+            "hash polynomials",
+            polynomials
+                .par_iter()
+                .map(LdeCache::<F, C::Hasher>::polynomial_key)
+                .collect::<Vec<_>>()
+        );
+
+        let cached_tree = (!blinding)
+            .then(|| cache.get_tree(&keys, rate_bits, cap_height))
+            .flatten();
+        if let Some(merkle_tree) = cached_tree {
+            return Self {
+                merkle_tree: merkle_tree.clone(),
+                polynomials,
+                degree_log: log2_strict(degree),
+                rate_bits,
+                blinding,
+            };
+        }
+
+        let cached_ldes = keys
+            .iter()
+            .map(|key| cache.get_lde(key, rate_bits).cloned())
+            .collect::<Vec<_>>();
+        let salt_size = if blinding { SALT_SIZE } else { 0 };
+        let lde_values = timed!(
+            timing,
+            "FFT + blinding",
+            polynomials
+                .par_iter()
+                .zip(cached_ldes)
+                .map(|(p, cached)| {
+                    assert_eq!(p.len(), degree, "Polynomial degrees inconsistent");
+                    cached.unwrap_or_else(|| {
+                        let mut lde = Self::lde(p, rate_bits, fft_root_table);
+                        reverse_index_bits_in_place(&mut lde);
+                        lde
+                    })
+                })
+                .chain(
+                    (0..salt_size)
+                        .into_par_iter()
+                        .map(|_| blinding_rand_vec(degree << rate_bits)),
+                )
+                .collect::<Vec<_>>()
+        );
+        for (key, lde) in keys.iter().zip(&lde_values) {
+            if cache.get_lde(key, rate_bits).is_none() {
+                cache.insert_lde(key.clone(), rate_bits, lde.clone());
+            }
+        }
+
+        let merkle_tree = Self::merkle_tree(lde_values, cap_height, timing);
+        if !blinding {
+            cache.insert_tree(&keys, rate_bits, cap_height, merkle_tree.clone());
+        }
+
+        Self {
+            polynomials,
+            merkle_tree,
+            degree_log: log2_strict(degree),
+            rate_bits,
+            blinding,
+        }
+    }
+
+    /// Computes shard `shard` out of `num_shards` of the Merkle tree of the commitment
+    /// [`Self::from_coeffs`] would create, so that machines can share the work of committing to
+    /// `polynomials`. Each leaf holds a row of all polynomials, so shards are ranges of leaves
+    /// rather than of polynomials: shard `s` holds the subtrees under the `s`-th range of
+    /// `2^cap_height / num_shards` elements of the cap. Its leaves are the low-degree extensions on
+    /// a coset of a subgroup of size `1 / num_shards` of the LDE domain, so each shard only needs
+    /// FFTs of that size. The shards are assembled with [`Self::from_shards`].
+    ///
+    /// `num_shards` must be a power of two of at most `2^cap_height`.
+    pub fn commit_shard(
+        polynomials: &[PolynomialCoeffs<F>],
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        shard: usize,
+        num_shards: usize,
+    ) -> MerkleTree<F, C::Hasher> {
+        let shard_bits = log2_strict(num_shards);
+        assert!(
+            shard_bits <= cap_height,
+            "Cannot split a tree of cap height {cap_height} into {num_shards} shards"
+        );
+        assert!(shard < num_shards, "Shard {shard} out of {num_shards}");
+        let degree = polynomials[0].len();
+        let lde_bits = log2_strict(degree) + rate_bits;
+        let shard_size = 1 << (lde_bits - shard_bits);
+
+        // The leaves of shard `s` are the LDE values at indices `reverse_bits(s, shard_bits) +
+        // num_shards * i` in bit-reversed order of `i`, i.e. on the coset of the subgroup of order
+        // `shard_size` shifted by `shift`.
+        let shift = F::coset_shift()
+            * F::primitive_root_of_unity(lde_bits).exp_u64(reverse_bits(shard, shard_bits) as u64);
+        let shift_power = shift.exp_u64(shard_size as u64);
+        let salt_size = if blinding { SALT_SIZE } else { 0 };
+        let lde_values = polynomials
+            .par_iter()
+            .map(|p| {
+                assert_eq!(p.len(), degree, "Polynomial degrees inconsistent");
+                if p.is_constant() {
+                    return vec![p.coeffs[0]; shard_size];
+                }
+                // Reduce `p` modulo `X^shard_size - shift^shard_size`, which vanishes on the coset.
+                let mut reduced = vec![F::ZERO; shard_size];
+                for (chunk, power) in p.coeffs.chunks(shard_size).zip(shift_power.powers()) {
+                    for (r, &c) in reduced.iter_mut().zip(chunk) {
+                        *r += power * c;
+                    }
+                }
+                let mut values = PolynomialCoeffs::new(reduced)
+                    .coset_fft_with_options(shift, None, None)
+                    .values;
+                reverse_index_bits_in_place(&mut values);
+                values
+            })
+            .chain(
+                (0..salt_size)
+                    .into_par_iter()
+                    .map(|_| blinding_rand_vec(shard_size)),
+            )
+            .collect::<Vec<_>>();
+
+        MerkleTree::from_matrix(
+            RowMajorMatrix::from_columns(&lde_values),
+            cap_height - shard_bits,
+        )
+    }
+
+    /// Assembles the commitment to `polynomials` from all its shards, computed by
+    /// [`Self::commit_shard`] with the same parameters, in order.
+    pub fn from_shards(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        shards: Vec<MerkleTree<F, C::Hasher>>,
+    ) -> Self {
+        let degree = polynomials[0].len();
+        let merkle_tree = MerkleTree::from_subtrees(shards);
+        assert_eq!(
+            merkle_tree.leaves.height(),
+            degree << rate_bits,
+            "The shards don't cover the LDE domain"
         );
 
         Self {
@@ -111,6 +307,137 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         }
     }
 
+    /// Interpolates each of `values`.
+    pub(crate) fn interpolate(values: Vec<PolynomialValues<F>>) -> Vec<PolynomialCoeffs<F>> {
+        values
+            .into_par_iter()
+            .map(|v| {
+                // A constant column interpolates to its value, so skip its IFFT.
+                if v.is_constant() && !v.values.is_empty() {
+                    let mut coeffs = PolynomialCoeffs::zero(v.len());
+                    coeffs.coeffs[0] = v.values[0];
+                    coeffs
+                } else {
+                    v.ifft()
+                }
+            })
+            .collect()
+    }
+
+    /// Builds the Merkle tree whose leaves are the rows of the bit-reversed `lde_values`.
+    fn merkle_tree(
+        lde_values: Vec<Vec<F>>,
+        cap_height: usize,
+        timing: &mut TimingTree,
+    ) -> MerkleTree<F, C::Hasher> {
+        let leaves = timed!(
+            timing,
+            "transpose LDEs",
+            RowMajorMatrix::from_columns(&lde_values)
+        );
+        drop(lde_values);
+        timed!(
+            timing,
+            "build Merkle tree",
+            MerkleTree::from_matrix(leaves, cap_height)
+        )
+    }
+
+    /// Creates a list polynomial commitment for the extension field polynomials interpolating the
+    /// values in `values`. Each polynomial is committed to as its `D` base field components, so
+    /// that each leaf holds the `D` components of every value next to each other.
+    pub fn from_ext_values(
+        values: Vec<PolynomialValues<F::Extension>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        let components = values
+            .iter()
+            .flat_map(|v| ext_components(&v.values))
+            .map(PolynomialValues::new)
+            .collect();
+        Self::from_values(
+            components,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+        )
+    }
+
+    /// Creates a list polynomial commitment for the extension field polynomials `polynomials`, see
+    /// [`Self::from_ext_values`].
+    pub fn from_ext_coeffs(
+        polynomials: Vec<PolynomialCoeffs<F::Extension>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        let components = polynomials
+            .iter()
+            .flat_map(|p| ext_components(&p.coeffs))
+            .map(PolynomialCoeffs::new)
+            .collect();
+        Self::from_coeffs(
+            components,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+        )
+    }
+
+    /// Computes the Merkle cap of the commitment [`Self::from_values`] would create, without
+    /// keeping the polynomials, their low-degree extensions nor the Merkle tree.
+    pub fn cap_from_values(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+    ) -> MerkleCap<F, C::Hasher> {
+        let coeffs = timed!(
+            timing,
+            "IFFT",
+            values.into_par_iter().map(|v| v.ifft()).collect::<Vec<_>>()
+        );
+        let lde_bits = log2_strict(coeffs[0].len()) + rate_bits;
+        let lde_values = timed!(
+            timing,
+            "FFT + blinding",
+            Self::lde_values(&coeffs, rate_bits, blinding, fft_root_table)
+        );
+        drop(coeffs);
+
+        // Hash each leaf as it is read off the LDEs, rather than transposing them first.
+        let leaf_digests = timed!(
+            timing,
+            "hash leaves",
+            (0..1 << lde_bits)
+                .into_par_iter()
+                .map(|i| {
+                    let index = reverse_bits(i, lde_bits);
+                    let leaf = lde_values.iter().map(|v| v[index]).collect::<Vec<_>>();
+                    C::Hasher::hash_or_noop(&leaf)
+                })
+                .collect::<Vec<_>>()
+        );
+        drop(lde_values);
+        timed!(
+            timing,
+            "compute Merkle cap",
+            merkle_cap_from_leaf_digests(leaf_digests, cap_height)
+        )
+    }
+
     pub(crate) fn lde_values(
         polynomials: &[PolynomialCoeffs<F>],
         rate_bits: usize,
@@ -126,26 +453,63 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             .par_iter()
             .map(|p| {
                 assert_eq!(p.len(), degree, "Polynomial degrees inconsistent");
-                p.lde(rate_bits)
-                    .coset_fft_with_options(F::coset_shift(), Some(rate_bits), fft_root_table)
-                    .values
+                Self::lde(p, rate_bits, fft_root_table)
             })
             .chain(
                 (0..salt_size)
                     .into_par_iter()
-                    .map(|_| F::rand_vec(degree << rate_bits)),
+                    .map(|_| blinding_rand_vec(degree << rate_bits)),
             )
             .collect()
     }
 
+    /// The values of `polynomial` on the coset of the LDE domain, in natural order.
+    fn lde(
+        polynomial: &PolynomialCoeffs<F>,
+        rate_bits: usize,
+        fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Vec<F> {
+        // A constant polynomial has the same value on the whole coset.
+        if polynomial.is_constant() {
+            return vec![polynomial.coeffs[0]; polynomial.len() << rate_bits];
+        }
+        polynomial
+            .lde(rate_bits)
+            .coset_fft_with_options(F::coset_shift(), Some(rate_bits), fft_root_table)
+            .values
+    }
+
     /// Fetches LDE values at the `index * step`th point.
     pub fn get_lde_values(&self, index: usize, step: usize) -> &[F] {
         let index = index * step;
         let index = reverse_bits(index, self.degree_log + self.rate_bits);
-        let slice = &self.merkle_tree.leaves[index];
+        let slice = self.merkle_tree.leaves.row(index);
         &slice[..slice.len() - if self.blinding { SALT_SIZE } else { 0 }]
     }
 
+    /// The `index`th polynomial of a commitment to extension field polynomials, see
+    /// [`Self::from_ext_values`].
+    pub fn get_ext_polynomial(&self, index: usize) -> PolynomialCoeffs<F::Extension> {
+        let components = &self.polynomials[index * D..(index + 1) * D];
+        let coeffs = (0..components[0].len())
+            .map(|j| {
+                F::Extension::from_basefield_array(core::array::from_fn(|i| {
+                    components[i].coeffs[j]
+                }))
+            })
+            .collect();
+        PolynomialCoeffs::new(coeffs)
+    }
+
+    /// Like `get_lde_values`, but for a commitment to extension field polynomials, see
+    /// [`Self::from_ext_values`].
+    pub fn get_lde_ext_values(&self, index: usize, step: usize) -> Vec<F::Extension> {
+        self.get_lde_values(index, step)
+            .chunks_exact(D)
+            .map(|chunk| F::Extension::from_basefield_array(core::array::from_fn(|i| chunk[i])))
+            .collect()
+    }
+
     /// Like `get_lde_values`, but fetches LDE values from a batch of `P::WIDTH` points, and returns
     /// packed values.
     pub fn get_lde_values_packed<P>(&self, index_start: usize, step: usize) -> Vec<P>
@@ -172,6 +536,26 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             .collect_vec()
     }
 
+    /// Evaluates the polynomials of each batch of `instance` at its point, giving the openings
+    /// proven by [`Self::prove_openings`].
+    pub fn eval_openings(instance: &FriInstanceInfo<F, D>, oracles: &[&Self]) -> FriOpenings<F, D> {
+        let batches = instance
+            .batches
+            .iter()
+            .map(|FriBatchInfo { point, polynomials }| FriOpeningBatch {
+                values: polynomials
+                    .par_iter()
+                    .map(|fri_poly| {
+                        oracles[fri_poly.oracle_index].polynomials[fri_poly.polynomial_index]
+                            .to_extension::<D>()
+                            .eval(*point)
+                    })
+                    .collect(),
+            })
+            .collect();
+        FriOpenings { batches }
+    }
+
     /// Produces a batch opening proof.
     pub fn prove_openings(
         instance: &FriInstanceInfo<F, D>,
@@ -194,7 +578,8 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         // where `alpha` is a random challenge in the extension field.
         // The final polynomial is then computed as `final_poly = sum_i alpha^(k_i) (F_i(X) - F_i(z_i))/(X-z_i)`
         // where the `k_i`s are chosen such that each power of `alpha` appears only once in the final sum.
-        // There are usually two batches for the openings at `zeta` and `g * zeta`.
+        // There are usually two batches for the openings at `zeta` and `g * zeta`, but the points
+        // can be arbitrary.
         // The oracles used in Plonky2 are given in `FRI_ORACLES` in `plonky2/src/plonk/plonk_common.rs`.
         for FriBatchInfo { point, polynomials } in &instance.batches {
             // Collect the coefficients of all the polynomials in `polynomials`.
@@ -236,3 +621,392 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         fri_proof
     }
 }
+
+/// Splits extension field elements into the `D` vectors of their base field components.
+fn ext_components<F: RichField + Extendable<D>, const D: usize>(
+    values: &[F::Extension],
+) -> Vec<Vec<F>> {
+    let mut components = vec![Vec::with_capacity(values.len()); D];
+    for value in values {
+        for (component, x) in components.iter_mut().zip(value.to_basefield_array()) {
+            component.push(x);
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::types::Sample;
+
+    use super::*;
+    use crate::fri::reduction_strategies::FriReductionStrategy;
+    use crate::fri::structure::{
+        FriInstanceInfoTarget, FriOpeningBatchTarget, FriOracleInfo, FriPolynomialInfo,
+    };
+    use crate::fri::verifier::verify_fri_proof;
+    use crate::fri::witness_util::{set_fri_openings_target, set_fri_proof_target};
+    use crate::fri::FriConfig;
+    use crate::iop::challenger::RecursiveChallenger;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::util::serialization::{Buffer, Read, Write};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    fn fri_params(k: usize) -> FriParams {
+        let reduction_arity_bits = vec![1, 2, 1];
+        FriParams {
+            config: FriConfig {
+                rate_bits: 1,
+                cap_height: 0,
+                proof_of_work_bits: 0,
+                reduction_strategy: FriReductionStrategy::Fixed(reduction_arity_bits.clone()),
+                num_query_rounds: 10,
+            },
+            hiding: false,
+            degree_bits: k,
+            reduction_arity_bits,
+            oracle_cap_heights: vec![],
+        }
+    }
+
+    #[test]
+    fn test_open_at_multiple_points() -> Result<()> {
+        let mut timing = TimingTree::default();
+        let k = 9;
+        let fri_params = fri_params(k);
+
+        let commit = |num_polys: usize, timing: &mut TimingTree| {
+            let values = (0..num_polys)
+                .map(|_| PolynomialValues::new(F::rand_vec(1 << k)))
+                .collect();
+            PolynomialBatch::<F, C, D>::from_values(
+                values,
+                fri_params.config.rate_bits,
+                false,
+                fri_params.config.cap_height,
+                timing,
+                None,
+            )
+        };
+        let oracle_a = commit(3, &mut timing);
+        let oracle_b = commit(2, &mut timing);
+        let oracles = vec![
+            FriOracleInfo {
+                num_polys: 3,
+                blinding: false,
+            },
+            FriOracleInfo {
+                num_polys: 2,
+                blinding: false,
+            },
+        ];
+
+        // Everything is opened at the first point, and overlapping subsets at the others.
+        let batch_polys = [
+            [
+                FriPolynomialInfo::from_range(0, 0..3),
+                FriPolynomialInfo::from_range(1, 0..2),
+            ]
+            .concat(),
+            [
+                FriPolynomialInfo::from_range(0, 0..1),
+                FriPolynomialInfo::from_range(1, 1..2),
+            ]
+            .concat(),
+            FriPolynomialInfo::from_range(0, 1..3),
+        ];
+
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_cap(&oracle_a.merkle_tree.cap);
+        challenger.observe_cap(&oracle_b.merkle_tree.cap);
+        let mut instance = FriInstanceInfo::new(oracles.clone());
+        for polys in &batch_polys {
+            instance.open_at(challenger.get_extension_challenge::<D>(), polys.clone());
+        }
+        let openings = PolynomialBatch::eval_openings(&instance, &[&oracle_a, &oracle_b]);
+        challenger.observe_openings(&openings);
+        let mut verifier_challenger = challenger.clone();
+
+        let proof = PolynomialBatch::prove_openings(
+            &instance,
+            &[&oracle_a, &oracle_b],
+            &mut challenger,
+            &fri_params,
+            None,
+            None,
+            &mut timing,
+        );
+        let challenges = verifier_challenger.fri_challenges::<C, D>(
+            &proof.commit_phase_merkle_caps,
+            &proof.final_poly,
+            proof.pow_witness,
+            k,
+            &fri_params.config,
+            None,
+            None,
+        );
+        let caps = [
+            oracle_a.merkle_tree.cap.clone(),
+            oracle_b.merkle_tree.cap.clone(),
+        ];
+        verify_fri_proof::<F, C, D>(
+            &instance,
+            &openings,
+            &challenges,
+            &caps,
+            &proof,
+            &fri_params,
+        )?;
+
+        let mut wrong_openings = PolynomialBatch::eval_openings(&instance, &[&oracle_a, &oracle_b]);
+        wrong_openings.batches[2].values[1] += <F as Extendable<D>>::Extension::ONE;
+        assert!(verify_fri_proof::<F, C, D>(
+            &instance,
+            &wrong_openings,
+            &challenges,
+            &caps,
+            &proof,
+            &fri_params,
+        )
+        .is_err());
+
+        // The same openings, checked in a circuit.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let cap_targets = caps.map(|cap| builder.constant_merkle_cap(&cap));
+        let mut challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        challenger.observe_cap(&cap_targets[0]);
+        challenger.observe_cap(&cap_targets[1]);
+        let mut instance_target = FriInstanceInfoTarget::new(oracles);
+        for polys in &batch_polys {
+            let point = challenger.get_extension_challenge(&mut builder);
+            instance_target.open_at(point, polys.clone());
+        }
+        let openings_target = builder.add_virtual_fri_openings(&instance_target);
+        challenger.observe_openings(&openings_target);
+        let proof_target = builder.add_virtual_fri_proof(&[3, 2], &fri_params);
+        let challenges_target = challenger.fri_challenges(
+            &mut builder,
+            &proof_target.commit_phase_merkle_caps,
+            &proof_target.final_poly,
+            proof_target.pow_witness,
+            &fri_params.config,
+        );
+        builder.verify_fri_proof::<C>(
+            &instance_target,
+            &openings_target,
+            &challenges_target,
+            &cap_targets,
+            &proof_target,
+            &fri_params,
+        );
+
+        let mut pw = PartialWitness::new();
+        set_fri_openings_target(&mut pw, &openings_target, &openings)?;
+        set_fri_proof_target(&mut pw, &proof_target, &proof)?;
+        let data = builder.build::<C>();
+        data.verify(data.prove(pw)?)
+    }
+
+    #[test]
+    fn test_commitment_shards() {
+        let mut timing = TimingTree::default();
+        let k = 6;
+        let rate_bits = 1;
+        let cap_height = 3;
+        let polynomials = vec![
+            PolynomialCoeffs::new(F::rand_vec(1 << k)),
+            PolynomialValues::constant(F::rand(), 1 << k).ifft(),
+            PolynomialCoeffs::new(F::rand_vec(1 << k)),
+        ];
+        let expected = PolynomialBatch::<F, C, D>::from_coeffs(
+            polynomials.clone(),
+            rate_bits,
+            false,
+            cap_height,
+            &mut timing,
+            None,
+        );
+
+        // With 8 shards, the shards are smaller than the polynomials, which are reduced first.
+        for num_shards in [1, 2, 8] {
+            let shards = (0..num_shards)
+                .map(|shard| {
+                    PolynomialBatch::<F, C, D>::commit_shard(
+                        &polynomials,
+                        rate_bits,
+                        false,
+                        cap_height,
+                        shard,
+                        num_shards,
+                    )
+                })
+                .collect();
+            let oracle = PolynomialBatch::<F, C, D>::from_shards(
+                polynomials.clone(),
+                rate_bits,
+                false,
+                shards,
+            );
+            assert_eq!(oracle, expected);
+        }
+    }
+
+    #[test]
+    fn test_constant_columns() {
+        let mut timing = TimingTree::default();
+        let k = 6;
+        let rate_bits = 2;
+        let c = F::rand();
+        let values = vec![
+            PolynomialValues::constant(c, 1 << k),
+            PolynomialValues::zero(1 << k),
+            PolynomialValues::new(F::rand_vec(1 << k)),
+        ];
+        let oracle = PolynomialBatch::<F, C, D>::from_values(
+            values.clone(),
+            rate_bits,
+            false,
+            0,
+            &mut timing,
+            None,
+        );
+        for (poly, v) in oracle.polynomials.iter().zip(values) {
+            assert_eq!(poly, &v.ifft());
+        }
+
+        let subgroup = F::two_adic_subgroup(k + rate_bits);
+        for (i, &x) in subgroup.iter().enumerate() {
+            let x = F::coset_shift() * x;
+            let expected = [c, F::ZERO, oracle.polynomials[2].eval(x)];
+            assert_eq!(oracle.get_lde_values(i, 1), expected);
+        }
+
+        // Constant polynomials are serialized without their zero coefficients.
+        let mut bytes = Vec::new();
+        bytes.write_polynomial_batch(&oracle).unwrap();
+        let random_oracle = PolynomialBatch::<F, C, D>::from_values(
+            (0..3)
+                .map(|_| PolynomialValues::new(F::rand_vec(1 << k)))
+                .collect(),
+            rate_bits,
+            false,
+            0,
+            &mut timing,
+            None,
+        );
+        let mut random_bytes = Vec::new();
+        random_bytes.write_polynomial_batch(&random_oracle).unwrap();
+        assert_eq!(
+            random_bytes.len() - bytes.len(),
+            8 * ((1 << k) - 1 + (1 << k))
+        );
+        let mut buffer = Buffer::new(&bytes);
+        assert_eq!(buffer.read_polynomial_batch::<F, C, D>().unwrap(), oracle);
+    }
+
+    #[test]
+    fn test_ext_polynomial_batch() -> Result<()> {
+        type FE = <F as Extendable<D>>::Extension;
+        let mut timing = TimingTree::default();
+        let k = 9;
+        let fri_params = fri_params(k);
+
+        let polys = (0..2)
+            .map(|_| PolynomialCoeffs::new(FE::rand_vec(1 << k)))
+            .collect::<Vec<_>>();
+        let subgroup = F::two_adic_subgroup(k);
+        let values = polys
+            .iter()
+            .map(|p| PolynomialValues::new(subgroup.iter().map(|&x| p.eval_base::<D>(x)).collect()))
+            .collect();
+        let oracle = PolynomialBatch::<F, C, D>::from_ext_values(
+            values,
+            fri_params.config.rate_bits,
+            false,
+            fri_params.config.cap_height,
+            &mut timing,
+            None,
+        );
+        let oracle_from_coeffs = PolynomialBatch::<F, C, D>::from_ext_coeffs(
+            polys.clone(),
+            fri_params.config.rate_bits,
+            false,
+            fri_params.config.cap_height,
+            &mut timing,
+            None,
+        );
+        assert_eq!(oracle.merkle_tree.cap, oracle_from_coeffs.merkle_tree.cap);
+        assert_eq!(oracle.get_ext_polynomial(1), polys[1]);
+        assert_eq!(
+            oracle.get_lde_ext_values(0, 1),
+            polys
+                .iter()
+                .map(|p| p.eval_base::<D>(F::coset_shift()))
+                .collect::<Vec<_>>()
+        );
+
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_cap(&oracle.merkle_tree.cap);
+        let zeta = challenger.get_extension_challenge::<D>();
+        let mut instance = FriInstanceInfo::new(vec![FriOracleInfo {
+            num_polys: 2 * D,
+            blinding: false,
+        }]);
+        instance.open_at(zeta, FriPolynomialInfo::from_ext_range::<D>(0, 0..2));
+        let openings = PolynomialBatch::eval_openings(&instance, &[&oracle]);
+        let expected = polys.iter().map(|p| p.eval(zeta)).collect::<Vec<_>>();
+        assert_eq!(openings.batches[0].ext_values(0..2 * D), expected);
+
+        challenger.observe_openings(&openings);
+        let mut verifier_challenger = challenger.clone();
+        let proof = PolynomialBatch::prove_openings(
+            &instance,
+            &[&oracle],
+            &mut challenger,
+            &fri_params,
+            None,
+            None,
+            &mut timing,
+        );
+        let challenges = verifier_challenger.fri_challenges::<C, D>(
+            &proof.commit_phase_merkle_caps,
+            &proof.final_poly,
+            proof.pow_witness,
+            k,
+            &fri_params.config,
+            None,
+            None,
+        );
+        verify_fri_proof::<F, C, D>(
+            &instance,
+            &openings,
+            &challenges,
+            core::slice::from_ref(&oracle.merkle_tree.cap),
+            &proof,
+            &fri_params,
+        )?;
+
+        // Recombine the opened components in a circuit.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let openings_target = FriOpeningBatchTarget {
+            values: builder.add_virtual_extension_targets(2 * D),
+        };
+        let ext_values = openings_target.ext_values(&mut builder, 0..2 * D);
+        for (value, expected) in ext_values.into_iter().zip(expected) {
+            let expected = builder.constant_extension(expected);
+            builder.connect_extension(value, expected);
+        }
+        let mut pw = PartialWitness::new();
+        pw.set_extension_targets(&openings_target.values, &openings.batches[0].values)?;
+        let data = builder.build::<C>();
+        data.verify(data.prove(pw)?)
+    }
+}
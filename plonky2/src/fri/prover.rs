@@ -1,24 +1,31 @@
 #[cfg(not(feature = "std"))]
-use alloc::vec;
-#[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec,
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, BTreeSet};
 
 use plonky2_field::types::Field;
 use plonky2_maybe_rayon::*;
 
-use crate::field::extension::{flatten, unflatten, Extendable};
+use crate::field::batch_util::batch_multiply_by_powers;
+use crate::field::extension::{flatten, Extendable};
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::fri::proof::{FriInitialTreeProof, FriProof, FriQueryRound, FriQueryStep};
 use crate::fri::{FriConfig, FriParams};
 use crate::hash::hash_types::{RichField, NUM_HASH_OUT_ELTS};
 use crate::hash::hashing::PlonkyPermutation;
-use crate::hash::merkle_tree::MerkleTree;
+use crate::hash::merkle_proofs::MerkleProof;
+use crate::hash::merkle_tree::{merkle_tree_prove, MerkleCap, MerkleTree};
 use crate::iop::challenger::Challenger;
-use crate::plonk::config::GenericConfig;
+use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::plonk_common::reduce_with_powers;
 use crate::timed;
-use crate::util::reverse_index_bits_in_place;
+use crate::util::matrix::RowMajorMatrix;
 use crate::util::timing::TimingTree;
+use crate::util::{log2_strict, reverse_bits, reverse_index_bits_in_place};
 
 /// Builds a FRI proof.
 pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
@@ -37,10 +44,10 @@ pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
     assert_eq!(lde_polynomial_coeffs.len(), n);
 
     // Commit phase
-    let (trees, final_coeffs) = timed!(
+    let (layers, final_coeffs) = timed!(
         timing,
         "fold codewords in the commitment phase",
-        fri_committed_trees::<F, C, D>(
+        fri_committed_layers::<F, C, D>(
             lde_polynomial_coeffs,
             lde_polynomial_values,
             challenger,
@@ -58,11 +65,16 @@ pub fn fri_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const
     );
 
     // Query phase
-    let query_round_proofs =
-        fri_prover_query_rounds::<F, C, D>(initial_merkle_trees, &trees, challenger, n, fri_params);
+    let query_round_proofs = fri_prover_query_rounds::<F, C, D>(
+        initial_merkle_trees,
+        &layers,
+        challenger,
+        n,
+        fri_params,
+    );
 
     FriProof {
-        commit_phase_merkle_caps: trees.iter().map(|t| t.cap.clone()).collect(),
+        commit_phase_merkle_caps: layers.iter().map(|l| l.cap.clone()).collect(),
         query_round_proofs,
         final_poly: final_coeffs,
         pow_witness,
@@ -81,42 +93,133 @@ pub fn final_poly_coeff_len(mut degree_bits: usize, reduction_arity_bits: &Vec<u
     1 << degree_bits
 }
 
-fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+/// A layer of the FRI commit phase. Its evaluations are dropped once they are committed to, and the
+/// opened values are recomputed from the coefficients of the layer's polynomial.
+struct FriCommittedLayer<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> {
+    arity_bits: usize,
+    /// The number of leaves of the layer's Merkle tree, each holding `arity` evaluations.
+    num_leaves: usize,
+    /// The digests of the layer's Merkle tree, as in [`MerkleTree::digests`].
+    digests: Vec<H::Hash>,
+    cap: MerkleCap<F, H>,
+    /// The non-zero coefficients of the layer's polynomial.
+    coeffs: PolynomialCoeffs<F::Extension>,
+    /// The shift of the coset on which the layer's polynomial is evaluated.
+    shift: F,
+}
+
+impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriCommittedLayer<F, H, D> {
+    /// Commits to `values`, the evaluations of `coeffs` on the coset `shift * H`, grouped in
+    /// cosets of the `arity`-th roots of unity.
+    fn new(
+        mut values: PolynomialValues<F::Extension>,
+        coeffs: PolynomialCoeffs<F::Extension>,
+        shift: F,
+        arity_bits: usize,
+        cap_height: usize,
+    ) -> Self {
+        reverse_index_bits_in_place(&mut values.values);
+        let leaves = RowMajorMatrix::new(flatten(&values.values), D << arity_bits);
+        drop(values);
+        let num_leaves = leaves.height();
+        let MerkleTree { digests, cap, .. } = MerkleTree::<F, H>::from_matrix(leaves, cap_height);
+
+        Self {
+            arity_bits,
+            num_leaves,
+            digests,
+            cap,
+            coeffs,
+            shift,
+        }
+    }
+
+    /// Recomputes the evaluations held by the leaf at `leaf_index`.
+    fn evals(&self, leaf_index: usize) -> Vec<F::Extension> {
+        let arity = 1 << self.arity_bits;
+        let leaf_bits = log2_strict(self.num_leaves);
+        // The leaf holds the evaluations at `x0 * eta^reverse_bits(r)` for `r < arity`, where `eta`
+        // is a primitive `arity`-th root of unity.
+        let shift: F::Extension = self.shift.into();
+        let x0 = shift
+            * F::Extension::primitive_root_of_unity(leaf_bits + self.arity_bits)
+                .exp_u64(reverse_bits(leaf_index, leaf_bits) as u64);
+
+        // Writing P(x) = sum_{s<r} x^s * P_s(x^r), we have
+        // P(x0 * eta^t) = sum_{s<r} eta^(s * t) * x0^s * P_s(x0^r), which is a DFT.
+        let y = x0.exp_power_of_2(self.arity_bits);
+        let mut sums = vec![F::Extension::ZERO; arity];
+        for chunk in self.coeffs.coeffs.chunks_exact(arity).rev() {
+            for (sum, &c) in sums.iter_mut().zip(chunk) {
+                *sum = *sum * y + c;
+            }
+        }
+        batch_multiply_by_powers(&mut sums, x0, F::Extension::ONE);
+        let mut evals = PolynomialCoeffs::new(sums).fft().values;
+        reverse_index_bits_in_place(&mut evals);
+        evals
+    }
+
+    /// Creates a Merkle proof for the leaf at `leaf_index`.
+    fn prove(&self, leaf_index: usize) -> MerkleProof<F, H> {
+        let cap_height = log2_strict(self.cap.len());
+        let siblings =
+            merkle_tree_prove::<F, H>(leaf_index, self.num_leaves, cap_height, &self.digests);
+        MerkleProof { siblings }
+    }
+}
+
+type FriCommittedLayers<F, C, const D: usize> = (
+    Vec<FriCommittedLayer<F, <C as GenericConfig<D>>::Hasher, D>>,
+    PolynomialCoeffs<<F as Extendable<D>>::Extension>,
+);
+
+fn fri_committed_layers<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
     mut coeffs: PolynomialCoeffs<F::Extension>,
     mut values: PolynomialValues<F::Extension>,
     challenger: &mut Challenger<F, C::Hasher>,
     fri_params: &FriParams,
     final_poly_coeff_len: Option<usize>,
     max_num_query_steps: Option<usize>,
-) -> FriCommitedTrees<F, C, D> {
-    let mut trees = Vec::with_capacity(fri_params.reduction_arity_bits.len());
+) -> FriCommittedLayers<F, C, D> {
+    let mut layers = Vec::with_capacity(fri_params.reduction_arity_bits.len());
+    let rate_bits = fri_params.config.rate_bits;
+
+    // The coefficients being removed here should always be zero.
+    coeffs.coeffs.truncate(coeffs.len() >> rate_bits);
 
+    challenger.set_phase("fri commit phase");
     let mut shift = F::MULTIPLICATIVE_GROUP_GENERATOR;
-    for arity_bits in &fri_params.reduction_arity_bits {
+    for &arity_bits in &fri_params.reduction_arity_bits {
         let arity = 1 << arity_bits;
 
-        reverse_index_bits_in_place(&mut values.values);
-        let chunked_values = values
-            .values
-            .par_chunks(arity)
-            .map(|chunk: &[F::Extension]| flatten(chunk))
-            .collect();
-        let tree = MerkleTree::<F, C::Hasher>::new(chunked_values, fri_params.config.cap_height);
-
-        challenger.observe_cap(&tree.cap);
-        trees.push(tree);
+        // The layer keeps the coefficients, and drops the evaluations once committed.
+        let layer = FriCommittedLayer::new(
+            values,
+            coeffs,
+            shift,
+            arity_bits,
+            fri_params.config.cap_height,
+        );
+        challenger.observe_cap(&layer.cap);
 
         let beta = challenger.get_extension_challenge::<D>();
         // P(x) = sum_{i<r} x^i * P_i(x^r) becomes sum_{i<r} beta^i * P_i(x).
         coeffs = PolynomialCoeffs::new(
-            coeffs
+            layer
+                .coeffs
                 .coeffs
                 .par_chunks_exact(arity)
                 .map(|chunk| reduce_with_powers(chunk, beta))
                 .collect::<Vec<_>>(),
         );
+        layers.push(layer);
         shift = shift.exp_u64(arity as u64);
-        values = coeffs.coset_fft(shift.into())
+        values = coeffs.lde(rate_bits).coset_fft(shift.into());
     }
 
     // When verifying this proof in a circuit with a different number of query steps,
@@ -131,11 +234,7 @@ fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
         }
     }
 
-    // The coefficients being removed here should always be zero.
-    coeffs
-        .coeffs
-        .truncate(coeffs.len() >> fri_params.config.rate_bits);
-
+    challenger.set_phase("fri final polynomial");
     challenger.observe_extension_elements(&coeffs.coeffs);
     // When verifying this proof in a circuit with a different final polynomial length,
     // the challenger needs to observe the full length of the final polynomial.
@@ -146,7 +245,7 @@ fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
         }
     }
 
-    (trees, coeffs)
+    (layers, coeffs)
 }
 
 /// Performs the proof-of-work (a.k.a. grinding) step of the FRI protocol. Returns the PoW witness.
@@ -194,6 +293,7 @@ pub(crate) fn fri_proof_of_work<
         .expect("Proof of work failed. This is highly unlikely!");
 
     // Recompute pow_response using our normal Challenger code, and make sure it matches.
+    challenger.set_phase("fri proof of work");
     challenger.observe_element(pow_witness);
     let pow_response = challenger.get_challenge();
     let leading_zeros = pow_response.to_canonical_u64().leading_zeros();
@@ -207,17 +307,52 @@ fn fri_prover_query_rounds<
     const D: usize,
 >(
     initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
-    trees: &[MerkleTree<F, C::Hasher>],
+    layers: &[FriCommittedLayer<F, C::Hasher, D>],
     challenger: &mut Challenger<F, C::Hasher>,
     n: usize,
     fri_params: &FriParams,
 ) -> Vec<FriQueryRound<F, C::Hasher, D>> {
-    challenger
+    challenger.set_phase("fri queries");
+    let x_indices = challenger
         .get_n_challenges(fri_params.config.num_query_rounds)
+        .into_iter()
+        .map(|rand| (rand.to_canonical_u64() % n as u64) as usize)
+        .collect::<Vec<_>>();
+
+    // The later layers are small, so queries mostly open the same leaves there. Each distinct leaf
+    // of a layer is opened once, and its opening is shared by the queries which need it.
+    let mut leaf_indices = x_indices.clone();
+    let layer_openings = layers
+        .iter()
+        .map(|layer| {
+            leaf_indices
+                .iter_mut()
+                .for_each(|index| *index >>= layer.arity_bits);
+            leaf_indices
+                .iter()
+                .copied()
+                .collect::<BTreeSet<_>>()
+                .into_par_iter()
+                .map(|index| {
+                    let step = FriQueryStep {
+                        evals: layer.evals(index),
+                        merkle_proof: layer.prove(index),
+                    };
+                    (index, step)
+                })
+                .collect::<BTreeMap<_, _>>()
+        })
+        .collect::<Vec<_>>();
+
+    x_indices
         .into_par_iter()
-        .map(|rand| {
-            let x_index = rand.to_canonical_u64() as usize % n;
-            fri_prover_query_round::<F, C, D>(initial_merkle_trees, trees, x_index, fri_params)
+        .map(|x_index| {
+            fri_prover_query_round::<F, C, D>(
+                initial_merkle_trees,
+                layers,
+                &layer_openings,
+                x_index,
+            )
         })
         .collect()
 }
@@ -228,27 +363,22 @@ fn fri_prover_query_round<
     const D: usize,
 >(
     initial_merkle_trees: &[&MerkleTree<F, C::Hasher>],
-    trees: &[MerkleTree<F, C::Hasher>],
+    layers: &[FriCommittedLayer<F, C::Hasher, D>],
+    layer_openings: &[BTreeMap<usize, FriQueryStep<F, C::Hasher, D>>],
     mut x_index: usize,
-    fri_params: &FriParams,
 ) -> FriQueryRound<F, C::Hasher, D> {
-    let mut query_steps = Vec::new();
     let initial_proof = initial_merkle_trees
         .iter()
         .map(|t| (t.get(x_index).to_vec(), t.prove(x_index)))
         .collect::<Vec<_>>();
-    for (i, tree) in trees.iter().enumerate() {
-        let arity_bits = fri_params.reduction_arity_bits[i];
-        let evals = unflatten(tree.get(x_index >> arity_bits));
-        let merkle_proof = tree.prove(x_index >> arity_bits);
-
-        query_steps.push(FriQueryStep {
-            evals,
-            merkle_proof,
-        });
-
-        x_index >>= arity_bits;
-    }
+    let query_steps = layers
+        .iter()
+        .zip(layer_openings)
+        .map(|(layer, openings)| {
+            x_index >>= layer.arity_bits;
+            openings[&x_index].clone()
+        })
+        .collect();
     FriQueryRound {
         initial_trees_proof: FriInitialTreeProof {
             evals_proofs: initial_proof,
@@ -256,3 +386,47 @@ fn fri_prover_query_round<
         steps: query_steps,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::extension::unflatten;
+    use crate::field::types::Sample;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    #[test]
+    fn test_committed_layer_openings() {
+        let (degree_bits, rate_bits, arity_bits, cap_height) = (6, 2, 3, 1);
+        let coeffs =
+            PolynomialCoeffs::new(<F as Extendable<D>>::Extension::rand_vec(1 << degree_bits));
+        let shift = F::MULTIPLICATIVE_GROUP_GENERATOR.exp_u64(1 << 5);
+        let mut values = coeffs.lde(rate_bits).coset_fft(shift.into());
+
+        // The layer's openings must match those of a tree keeping its evaluations.
+        let layer = FriCommittedLayer::<F, H, D>::new(
+            values.clone(),
+            coeffs,
+            shift,
+            arity_bits,
+            cap_height,
+        );
+        reverse_index_bits_in_place(&mut values.values);
+        let leaves = values
+            .values
+            .chunks(1 << arity_bits)
+            .map(flatten::<F, D>)
+            .collect::<Vec<_>>();
+        let tree = MerkleTree::<F, H>::new(leaves, cap_height);
+
+        assert_eq!(layer.cap, tree.cap);
+        for i in 0..layer.num_leaves {
+            assert_eq!(layer.evals(i), unflatten::<F, D>(tree.get(i)));
+            assert_eq!(layer.prove(i), tree.prove(i));
+        }
+    }
+}
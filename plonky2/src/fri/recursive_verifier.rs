@@ -1,6 +1,8 @@
 #[cfg(not(feature = "std"))]
-use alloc::{format, vec::Vec};
+use alloc::{collections::BTreeMap, format, vec::Vec};
 use core::ops::RangeInclusive;
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
 
 use itertools::Itertools;
 
@@ -9,9 +11,12 @@ use crate::fri::proof::{
     FriChallengesTarget, FriInitialTreeProofTarget, FriProofTarget, FriQueryRoundTarget,
     FriQueryStepTarget,
 };
-use crate::fri::structure::{FriBatchInfoTarget, FriInstanceInfoTarget, FriOpeningsTarget};
+use crate::fri::structure::{
+    FriBatchInfoTarget, FriInstanceInfoTarget, FriOpeningBatchTarget, FriOpeningsTarget,
+};
 use crate::fri::{FriConfig, FriParams};
 use crate::gates::coset_interpolation::CosetInterpolationGate;
+use crate::gates::coset_interpolation_chunk::CosetInterpolationChunkGate;
 use crate::gates::gate::Gate;
 use crate::gates::random_access::RandomAccessGate;
 use crate::hash::hash_types::{MerkleCapTarget, RichField};
@@ -48,12 +53,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let start = self.exp_from_bits_const_base(g_inv, x_index_within_coset_bits.iter().rev());
         let coset_start = self.mul(start, x);
 
-        // The answer is gotten by interpolating {(x*g^i, P(x*g^i))} and evaluating at beta.
+        // The answer is gotten by interpolating {(x*g^i, P(x*g^i))} and evaluating at beta. If the
+        // arity is too large for a single interpolation gate, split the interpolation into chunks.
         let interpolation_gate = <CosetInterpolationGate<F, D>>::with_max_degree(
             arity_bits,
             self.config.max_quotient_degree_factor,
         );
-        self.interpolate_coset(interpolation_gate, coset_start, &evals, beta)
+        if self.fits_in_config(&interpolation_gate) {
+            self.interpolate_coset(interpolation_gate, coset_start, &evals, beta)
+        } else {
+            self.interpolate_coset_chunked(arity_bits, coset_start, &evals, beta)
+        }
+    }
+
+    /// Whether a single `CosetInterpolationGate` fits in the wires of this circuit's config.
+    fn fits_in_config(&self, interpolation_gate: &CosetInterpolationGate<F, D>) -> bool {
+        interpolation_gate.num_wires() <= self.config.num_wires
+            && interpolation_gate.num_routed_wires() <= self.config.num_routed_wires
     }
 
     /// Make sure we have enough wires and routed wires to do the FRI checks efficiently. This check
@@ -69,8 +85,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             self.config.max_quotient_degree_factor,
         );
 
-        let interpolation_wires = interpolation_gate.num_wires();
-        let interpolation_routed_wires = interpolation_gate.num_routed_wires();
+        // Large arities are handled by chunked interpolation, which only needs enough wires for
+        // chunks of a single point.
+        let (interpolation_wires, interpolation_routed_wires) =
+            if self.fits_in_config(&interpolation_gate) {
+                (
+                    interpolation_gate.num_wires(),
+                    interpolation_gate.num_routed_wires(),
+                )
+            } else {
+                let chunk_gate = CosetInterpolationChunkGate::<F, D>::new(
+                    max_fri_arity_bits,
+                    0,
+                    1,
+                    self.config.max_quotient_degree_factor,
+                );
+                (chunk_gate.num_wires(), chunk_gate.num_routed_wires())
+            };
 
         let min_wires = random_access.num_wires().max(interpolation_wires);
         let min_routed_wires = random_access
@@ -276,12 +307,13 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         x_index_bits: &[BoolTarget],
         proof: &FriInitialTreeProofTarget,
         initial_merkle_caps: &[MerkleCapTarget],
-        cap_index: Target,
+        cap_indices: &[Target],
     ) {
-        for (i, ((evals, merkle_proof), cap)) in proof
+        for (i, (((evals, merkle_proof), cap), &cap_index)) in proof
             .evals_proofs
             .iter()
             .zip(initial_merkle_caps)
+            .zip(cap_indices)
             .enumerate()
         {
             with_context!(
@@ -305,13 +337,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         n_index: Target,
         proof: &FriInitialTreeProofTarget,
         initial_merkle_caps: &[MerkleCapTarget],
-        cap_index: Target,
+        cap_indices: &[Target],
     ) {
         let one = self.one();
-        for (i, ((evals, merkle_proof), cap)) in proof
+        for (i, (((evals, merkle_proof), cap), &cap_index)) in proof
             .evals_proofs
             .iter()
             .zip(initial_merkle_caps)
+            .zip(cap_indices)
             .enumerate()
         {
             with_context!(
@@ -344,7 +377,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let degree_log = params.degree_bits;
         debug_assert_eq!(
             degree_log,
-            params.config.cap_height + proof.evals_proofs[0].1.siblings.len()
+            params.oracle_cap_height(0) + proof.evals_proofs[0].1.siblings.len()
                 - params.config.rate_bits
         );
         let subgroup_x = self.convert_to_ext(subgroup_x);
@@ -398,6 +431,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         let cap_index =
             self.le_sum(x_index_bits[x_index_bits.len() - params.config.cap_height..].iter());
+        let initial_cap_indices = self.initial_cap_indices(
+            params,
+            initial_merkle_caps.len(),
+            cap_index,
+            |builder, cap_height| {
+                builder.le_sum(x_index_bits[x_index_bits.len() - cap_height..].iter())
+            },
+        );
         with_context!(
             self,
             "check FRI initial proof",
@@ -405,7 +446,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 &x_index_bits,
                 &round_proof.initial_trees_proof,
                 initial_merkle_caps,
-                cap_index
+                &initial_cap_indices,
             )
         );
 
@@ -506,6 +547,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         C::Hasher: AlgebraicHasher<F>,
     {
         assert!(*log_n_range.start() > params.config.cap_height);
+        assert!((0..initial_merkle_caps.len())
+            .all(|i| *log_n_range.start() > params.oracle_cap_height(i)));
         let n_index = {
             let min_log_n = self.constant(F::from_canonical_usize(*log_n_range.start()));
             self.sub(log_n, min_log_n)
@@ -516,14 +559,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         Self::assert_noncanonical_indices_ok(&params.config);
         let mut x_index_bits = self.low_bits(x_index, *log_n_range.end(), F::BITS);
 
-        let cap_indices: Vec<_> = log_n_range
-            .clone()
-            .map(|n| {
-                let slice_start = n - params.config.cap_height;
-                self.le_sum(x_index_bits[slice_start..n].iter())
-            })
-            .collect();
-        let cap_index = self.random_access(n_index, cap_indices);
+        let cap_index_for_height = |builder: &mut Self, cap_height: usize| {
+            let cap_indices: Vec<_> = log_n_range
+                .clone()
+                .map(|n| {
+                    let slice_start = n - cap_height;
+                    builder.le_sum(x_index_bits[slice_start..n].iter())
+                })
+                .collect();
+            builder.random_access(n_index, cap_indices)
+        };
+        let cap_index = cap_index_for_height(self, params.config.cap_height);
+        let initial_cap_indices = self.initial_cap_indices(
+            params,
+            initial_merkle_caps.len(),
+            cap_index,
+            cap_index_for_height,
+        );
         with_context!(
             self,
             "check FRI initial proof",
@@ -533,7 +585,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 n_index,
                 &round_proof.initial_trees_proof,
                 initial_merkle_caps,
-                cap_index,
+                &initial_cap_indices,
             )
         );
 
@@ -650,6 +702,26 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Thus ambiguous elements contribute a negligible amount to soundness error.
     ///
     /// Here we compare the probabilities as a sanity check, to verify the claim above.
+    /// The cap index of the query in each initial oracle. The index is only computed once for each
+    /// cap height, starting from `cap_index`, the index for the height of the commit-phase caps.
+    fn initial_cap_indices(
+        &mut self,
+        params: &FriParams,
+        num_oracles: usize,
+        cap_index: Target,
+        mut cap_index_for_height: impl FnMut(&mut Self, usize) -> Target,
+    ) -> Vec<Target> {
+        let mut cap_indices_by_height = BTreeMap::from([(params.config.cap_height, cap_index)]);
+        (0..num_oracles)
+            .map(|i| {
+                let cap_height = params.oracle_cap_height(i);
+                *cap_indices_by_height
+                    .entry(cap_height)
+                    .or_insert_with(|| cap_index_for_height(self, cap_height))
+            })
+            .collect()
+    }
+
     pub(crate) fn assert_noncanonical_indices_ok(config: &FriConfig) {
         let num_ambiguous_elems = u64::MAX - F::ORDER + 1;
         let query_error = config.rate();
@@ -658,6 +730,21 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 "A non-negligible portion of field elements are in the range that permits non-canonical encodings. Need to do more analysis or enforce canonical encodings.");
     }
 
+    /// Adds virtual targets for the opened values of each batch of `instance`.
+    pub fn add_virtual_fri_openings(
+        &mut self,
+        instance: &FriInstanceInfoTarget<D>,
+    ) -> FriOpeningsTarget<D> {
+        let batches = instance
+            .batches
+            .iter()
+            .map(|batch| FriOpeningBatchTarget {
+                values: self.add_virtual_extension_targets(batch.polynomials.len()),
+            })
+            .collect();
+        FriOpeningsTarget { batches }
+    }
+
     pub fn add_virtual_fri_proof(
         &mut self,
         num_leaves_per_oracle: &[usize],
@@ -691,7 +778,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let mut merkle_proof_len = params.lde_bits() - cap_height;
 
         let initial_trees_proof =
-            self.add_virtual_fri_initial_trees_proof(num_leaves_per_oracle, merkle_proof_len);
+            self.add_virtual_fri_initial_trees_proof(num_leaves_per_oracle, params);
 
         let mut steps = Vec::with_capacity(params.reduction_arity_bits.len());
         for &arity_bits in &params.reduction_arity_bits {
@@ -709,13 +796,16 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     fn add_virtual_fri_initial_trees_proof(
         &mut self,
         num_leaves_per_oracle: &[usize],
-        initial_merkle_proof_len: usize,
+        params: &FriParams,
     ) -> FriInitialTreeProofTarget {
         let evals_proofs = num_leaves_per_oracle
             .iter()
-            .map(|&num_oracle_leaves| {
+            .enumerate()
+            .map(|(i, &num_oracle_leaves)| {
+                let cap_height = params.oracle_cap_height(i);
+                assert!(params.lde_bits() >= cap_height);
                 let leaves = self.add_virtual_targets(num_oracle_leaves);
-                let merkle_proof = self.add_virtual_merkle_proof(initial_merkle_proof_len);
+                let merkle_proof = self.add_virtual_merkle_proof(params.lde_bits() - cap_height);
                 (leaves, merkle_proof)
             })
             .collect();
@@ -5,9 +5,10 @@
 use alloc::vec::Vec;
 use core::ops::Range;
 
-use crate::field::extension::Extendable;
+use crate::field::extension::{Extendable, FieldExtension};
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
 
 /// Describes an instance of a FRI-based batch opening.
 #[derive(Clone, Debug)]
@@ -18,6 +19,23 @@ pub struct FriInstanceInfo<F: RichField + Extendable<D>, const D: usize> {
     pub batches: Vec<FriBatchInfo<F, D>>,
 }
 
+impl<F: RichField + Extendable<D>, const D: usize> FriInstanceInfo<F, D> {
+    /// An instance over the given oracles, without any openings yet.
+    pub const fn new(oracles: Vec<FriOracleInfo>) -> Self {
+        Self {
+            oracles,
+            batches: Vec::new(),
+        }
+    }
+
+    /// Opens `polynomials` at `point`, which can be any point outside of the LDE domain. Returns
+    /// the index of the new batch, which is also the index of its values in [`FriOpenings`].
+    pub fn open_at(&mut self, point: F::Extension, polynomials: Vec<FriPolynomialInfo>) -> usize {
+        self.batches.push(FriBatchInfo { point, polynomials });
+        self.batches.len() - 1
+    }
+}
+
 /// Describes an instance of a FRI-based batch opening.
 #[derive(Debug)]
 pub struct FriInstanceInfoTarget<const D: usize> {
@@ -27,6 +45,26 @@ pub struct FriInstanceInfoTarget<const D: usize> {
     pub batches: Vec<FriBatchInfoTarget<D>>,
 }
 
+impl<const D: usize> FriInstanceInfoTarget<D> {
+    /// An instance over the given oracles, without any openings yet.
+    pub const fn new(oracles: Vec<FriOracleInfo>) -> Self {
+        Self {
+            oracles,
+            batches: Vec::new(),
+        }
+    }
+
+    /// Opens `polynomials` at `point`, see [`FriInstanceInfo::open_at`].
+    pub fn open_at(
+        &mut self,
+        point: ExtensionTarget<D>,
+        polynomials: Vec<FriPolynomialInfo>,
+    ) -> usize {
+        self.batches.push(FriBatchInfoTarget { point, polynomials });
+        self.batches.len() - 1
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct FriOracleInfo {
     pub num_polys: usize,
@@ -67,6 +105,20 @@ impl FriPolynomialInfo {
             })
             .collect()
     }
+
+    /// The base field components of the extension field polynomials in `ext_polynomial_indices`,
+    /// for an oracle committing to extension field polynomials as in
+    /// [`PolynomialBatch::from_ext_values`](crate::fri::oracle::PolynomialBatch::from_ext_values).
+    /// Their opened values can be recombined with [`FriOpeningBatch::ext_values`].
+    pub fn from_ext_range<const D: usize>(
+        oracle_index: usize,
+        ext_polynomial_indices: Range<usize>,
+    ) -> Vec<FriPolynomialInfo> {
+        Self::from_range(
+            oracle_index,
+            ext_polynomial_indices.start * D..ext_polynomial_indices.end * D,
+        )
+    }
 }
 
 /// Opened values of each polynomial.
@@ -81,6 +133,23 @@ pub struct FriOpeningBatch<F: RichField + Extendable<D>, const D: usize> {
     pub values: Vec<F::Extension>,
 }
 
+impl<F: RichField + Extendable<D>, const D: usize> FriOpeningBatch<F, D> {
+    /// The values of extension field polynomials whose base field components were opened at
+    /// `range` of this batch, see [`FriPolynomialInfo::from_ext_range`].
+    pub fn ext_values(&self, range: Range<usize>) -> Vec<F::Extension> {
+        self.values[range]
+            .chunks_exact(D)
+            .map(|components| {
+                components
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &c)| ext_basis_element::<F, D>(i) * c)
+                    .sum()
+            })
+            .collect()
+    }
+}
+
 /// Opened values of each polynomial.
 #[derive(Debug)]
 pub struct FriOpeningsTarget<const D: usize> {
@@ -92,3 +161,34 @@ pub struct FriOpeningsTarget<const D: usize> {
 pub struct FriOpeningBatchTarget<const D: usize> {
     pub values: Vec<ExtensionTarget<D>>,
 }
+
+impl<const D: usize> FriOpeningBatchTarget<D> {
+    /// See [`FriOpeningBatch::ext_values`].
+    pub fn ext_values<F: RichField + Extendable<D>>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        range: Range<usize>,
+    ) -> Vec<ExtensionTarget<D>> {
+        self.values[range]
+            .chunks_exact(D)
+            .map(|components| {
+                components[1..]
+                    .iter()
+                    .enumerate()
+                    .fold(components[0], |acc, (i, &c)| {
+                        let basis_element =
+                            builder.constant_extension(ext_basis_element::<F, D>(i + 1));
+                        builder.mul_add_extension(basis_element, c, acc)
+                    })
+            })
+            .collect()
+    }
+}
+
+/// The `i`th element of the basis of `F::Extension` over `F`, such that an extension field
+/// polynomial is the combination of its base field components with these coefficients.
+fn ext_basis_element<F: RichField + Extendable<D>, const D: usize>(i: usize) -> F::Extension {
+    let mut arr = [F::ZERO; D];
+    arr[i] = F::ONE;
+    F::Extension::from_basefield_array(arr)
+}
@@ -60,7 +60,7 @@ where
         }
         for (i, (leaf, merkle_proof)) in initial_trees_proof.evals_proofs.iter().enumerate() {
             ensure!(leaf.len() == leaf_len[i]);
-            ensure!(merkle_proof.len() + cap_height == params.lde_bits());
+            ensure!(merkle_proof.len() + params.oracle_cap_height(i) == params.lde_bits());
         }
 
         ensure!(steps.len() == params.reduction_arity_bits.len());
@@ -4,6 +4,7 @@ use plonky2_field::types::Field;
 
 use crate::field::extension::Extendable;
 use crate::fri::proof::{FriProof, FriProofTarget};
+use crate::fri::structure::{FriOpenings, FriOpeningsTarget};
 use crate::hash::hash_types::{HashOut, RichField};
 use crate::iop::witness::WitnessWrite;
 use crate::plonk::config::AlgebraicHasher;
@@ -133,3 +134,34 @@ where
 
     Ok(())
 }
+
+/// Set the targets in a `FriOpeningsTarget` to their corresponding values in a `FriOpenings`.
+pub fn set_fri_openings_target<F, W, const D: usize>(
+    witness: &mut W,
+    fri_openings_target: &FriOpeningsTarget<D>,
+    fri_openings: &FriOpenings<F, D>,
+) -> Result<()>
+where
+    F: RichField + Extendable<D>,
+    W: WitnessWrite<F> + ?Sized,
+{
+    if fri_openings_target.batches.len() != fri_openings.batches.len() {
+        return Err(anyhow!(
+            "fri_openings' target has a different number of batches than the openings"
+        ));
+    }
+    for (bt, b) in fri_openings_target
+        .batches
+        .iter()
+        .zip_eq(&fri_openings.batches)
+    {
+        if bt.values.len() != b.values.len() {
+            return Err(anyhow!(
+                "fri_openings->batches' target length is different from the openings length"
+            ));
+        }
+        witness.set_extension_targets(&bt.values, &b.values)?;
+    }
+
+    Ok(())
+}
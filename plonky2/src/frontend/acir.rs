@@ -0,0 +1,323 @@
+//! A backend for ACIR-shaped constraint systems over the native field, i.e. programs with the
+//! structure of ACIR, the circuit representation emitted by the Noir compiler, but whose
+//! coefficients are elements of the circuit's field.
+//!
+//! The types of this module are this crate's own, and mirror the subset of ACIR made of
+//! constraints which this crate's gadgets can express: arithmetic expressions, and the range, AND
+//! and XOR black box functions. The unconstrained parts of a program, e.g. Brillig calls, don't add
+//! any constraint: their outputs are part of the witness given to the prover through an
+//! [`AcirWitnessMap`].
+//!
+//! This is not a Noir backend: serialized ACIR isn't parsed, and Noir programs are defined over the
+//! BN254 scalar field. Their coefficients can't be represented in a smaller field such as
+//! Goldilocks, nor does their arithmetic reduce modulo the same prime, so they can only be
+//! translated into these types when they were written for the circuit's field to begin with.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::WitnessWrite;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+use crate::plonk::config::GenericConfig;
+
+/// The index of a variable of an ACIR program.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct AcirWitness(pub u32);
+
+/// The polynomial `sum q_M * a * b + sum q_L * a + q_c` of degree at most 2.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Expression<F: Field> {
+    /// The terms `(q_M, a, b)`.
+    pub mul_terms: Vec<(F, AcirWitness, AcirWitness)>,
+    /// The terms `(q_L, a)`.
+    pub linear_combinations: Vec<(F, AcirWitness)>,
+    pub q_c: F,
+}
+
+/// An input of a black box function, which must fit in `num_bits` bits.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FunctionInput {
+    pub witness: AcirWitness,
+    pub num_bits: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum BlackBoxFuncCall {
+    /// Checks that `input` fits in `input.num_bits` bits.
+    Range { input: FunctionInput },
+    /// Constrains `output` to be the bitwise AND of `lhs` and `rhs`.
+    And {
+        lhs: FunctionInput,
+        rhs: FunctionInput,
+        output: AcirWitness,
+    },
+    /// Constrains `output` to be the bitwise XOR of `lhs` and `rhs`.
+    Xor {
+        lhs: FunctionInput,
+        rhs: FunctionInput,
+        output: AcirWitness,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum AcirOpcode<F: Field> {
+    /// Constrains the expression to be zero.
+    AssertZero(Expression<F>),
+    BlackBoxFuncCall(BlackBoxFuncCall),
+}
+
+/// An ACIR program, whose variables are the witnesses `0..=current_witness_index`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct AcirCircuit<F: Field> {
+    pub current_witness_index: u32,
+    pub opcodes: Vec<AcirOpcode<F>>,
+    pub private_parameters: BTreeSet<AcirWitness>,
+    pub public_parameters: BTreeSet<AcirWitness>,
+    pub return_values: BTreeSet<AcirWitness>,
+}
+
+/// The targets holding the witnesses of an ACIR program in a plonky2 circuit.
+#[derive(Clone, Debug)]
+pub struct AcirWitnessMap {
+    targets: Vec<Target>,
+}
+
+impl AcirWitnessMap {
+    /// The target holding `witness`.
+    pub fn target(&self, witness: AcirWitness) -> Target {
+        self.targets[witness.0 as usize]
+    }
+
+    /// Sets the targets of the given witnesses, solved by executing the program. The witnesses
+    /// which aren't computed by constraints, e.g. the parameters of the program and the outputs of
+    /// its unconstrained functions, must all be given.
+    pub fn set_witness<F: Field>(
+        &self,
+        witness: &mut impl WitnessWrite<F>,
+        values: &BTreeMap<AcirWitness, F>,
+    ) -> Result<()> {
+        for (&w, &value) in values {
+            ensure!(
+                (w.0 as usize) < self.targets.len(),
+                "ACIR witness {} is out of the program's range",
+                w.0
+            );
+            witness.set_target(self.target(w), value)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds the constraints of the ACIR program `circuit`, and registers its public parameters
+    /// followed by its return values as public inputs. Fails if a black box function is called
+    /// with inputs too wide for the field.
+    pub fn add_acir_circuit(&mut self, circuit: &AcirCircuit<F>) -> Result<AcirWitnessMap> {
+        let targets = self.add_virtual_targets(circuit.current_witness_index as usize + 1);
+        let witness_map = AcirWitnessMap { targets };
+        let target = |w: AcirWitness| -> Result<Target> {
+            ensure!(
+                w.0 <= circuit.current_witness_index,
+                "ACIR witness {} is out of the program's range",
+                w.0
+            );
+            Ok(witness_map.target(w))
+        };
+
+        for opcode in &circuit.opcodes {
+            match opcode {
+                AcirOpcode::AssertZero(expression) => {
+                    let mut acc = self.constant(expression.q_c);
+                    for &(q_m, a, b) in &expression.mul_terms {
+                        acc = self.arithmetic(q_m, F::ONE, target(a)?, target(b)?, acc);
+                    }
+                    for &(q_l, a) in &expression.linear_combinations {
+                        acc = self.mul_const_add(q_l, target(a)?, acc);
+                    }
+                    self.assert_zero(acc);
+                }
+                &AcirOpcode::BlackBoxFuncCall(BlackBoxFuncCall::Range { input }) => {
+                    // Every field element fits in `F::BITS` bits.
+                    if (input.num_bits as usize) < F::BITS {
+                        self.range_check(target(input.witness)?, input.num_bits as usize);
+                    }
+                }
+                &AcirOpcode::BlackBoxFuncCall(BlackBoxFuncCall::And { lhs, rhs, output }) => {
+                    let (lhs, rhs) = self.acir_bitwise_inputs(lhs, rhs, &target)?;
+                    let bits = lhs
+                        .into_iter()
+                        .zip(rhs)
+                        .map(|(a, b)| self.and(a, b))
+                        .collect::<Vec<_>>();
+                    let result = self.le_sum(bits.into_iter());
+                    self.connect(result, target(output)?);
+                }
+                &AcirOpcode::BlackBoxFuncCall(BlackBoxFuncCall::Xor { lhs, rhs, output }) => {
+                    let (lhs, rhs) = self.acir_bitwise_inputs(lhs, rhs, &target)?;
+                    let bits = lhs
+                        .into_iter()
+                        .zip(rhs)
+                        .map(|(a, b)| self.xor(a, b))
+                        .collect::<Vec<_>>();
+                    let result = self.le_sum(bits.into_iter());
+                    self.connect(result, target(output)?);
+                }
+            }
+        }
+
+        for &w in circuit
+            .public_parameters
+            .iter()
+            .chain(&circuit.return_values)
+        {
+            self.register_public_input(target(w)?);
+        }
+
+        Ok(witness_map)
+    }
+
+    /// Splits the inputs of a bitwise black box function into bits, which also range checks them.
+    fn acir_bitwise_inputs(
+        &mut self,
+        lhs: FunctionInput,
+        rhs: FunctionInput,
+        target: &impl Fn(AcirWitness) -> Result<Target>,
+    ) -> Result<(Vec<BoolTarget>, Vec<BoolTarget>)> {
+        ensure!(
+            lhs.num_bits == rhs.num_bits,
+            "Inputs of bitwise functions must have the same number of bits"
+        );
+        let num_bits = lhs.num_bits as usize;
+        // Decompositions into as many bits as the order of the field aren't unique.
+        ensure!(
+            num_bits < F::BITS,
+            "Inputs of bitwise functions must have less than {} bits",
+            F::BITS
+        );
+        Ok((
+            self.split_le(target(lhs.witness)?, num_bits),
+            self.split_le(target(rhs.witness)?, num_bits),
+        ))
+    }
+}
+
+/// Compiles the ACIR program `circuit` into a plonky2 circuit, see
+/// [`CircuitBuilder::add_acir_circuit`].
+pub fn build_acir_circuit<F, C, const D: usize>(
+    config: CircuitConfig,
+    circuit: &AcirCircuit<F>,
+) -> Result<(CircuitData<F, C, D>, AcirWitnessMap)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let mut builder = CircuitBuilder::new(config);
+    let witness_map = builder.add_acir_circuit(circuit)?;
+    Ok((builder.build(), witness_map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// The program `fn main(x: u8, y: pub u8) -> pub Field { (x * y + 3) + (x & y) + (x ^ y) }`,
+    /// whose witnesses are `[x, y, x & y, x ^ y, return value]`.
+    fn program() -> AcirCircuit<F> {
+        let input = |w| FunctionInput {
+            witness: AcirWitness(w),
+            num_bits: 8,
+        };
+        AcirCircuit {
+            current_witness_index: 4,
+            opcodes: vec![
+                AcirOpcode::BlackBoxFuncCall(BlackBoxFuncCall::Range { input: input(0) }),
+                AcirOpcode::BlackBoxFuncCall(BlackBoxFuncCall::Range { input: input(1) }),
+                AcirOpcode::BlackBoxFuncCall(BlackBoxFuncCall::And {
+                    lhs: input(0),
+                    rhs: input(1),
+                    output: AcirWitness(2),
+                }),
+                AcirOpcode::BlackBoxFuncCall(BlackBoxFuncCall::Xor {
+                    lhs: input(0),
+                    rhs: input(1),
+                    output: AcirWitness(3),
+                }),
+                AcirOpcode::AssertZero(Expression {
+                    mul_terms: vec![(F::ONE, AcirWitness(0), AcirWitness(1))],
+                    linear_combinations: vec![
+                        (F::ONE, AcirWitness(2)),
+                        (F::ONE, AcirWitness(3)),
+                        (F::NEG_ONE, AcirWitness(4)),
+                    ],
+                    q_c: F::from_canonical_u64(3),
+                }),
+            ],
+            private_parameters: BTreeSet::from([AcirWitness(0)]),
+            public_parameters: BTreeSet::from([AcirWitness(1)]),
+            return_values: BTreeSet::from([AcirWitness(4)]),
+        }
+    }
+
+    fn solved_witness(x: u64, y: u64, output: u64) -> BTreeMap<AcirWitness, F> {
+        [x, y, x & y, x ^ y, output]
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (AcirWitness(i as u32), F::from_canonical_u64(value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_acir_circuit() -> Result<()> {
+        let (data, witness_map) =
+            build_acir_circuit::<F, C, D>(CircuitConfig::standard_recursion_config(), &program())?;
+
+        let (x, y) = (0b1011_0110, 0b0110_0011);
+        let output = x * y + 3 + (x & y) + (x ^ y);
+        let mut pw = PartialWitness::new();
+        witness_map.set_witness(&mut pw, &solved_witness(x, y, output))?;
+        let proof = data.prove(pw)?;
+        assert_eq!(
+            proof.public_inputs,
+            [F::from_canonical_u64(y), F::from_canonical_u64(output)]
+        );
+        verify(proof, &data.verifier_only, &data.common)?;
+
+        // A wrong return value must be rejected.
+        let mut pw = PartialWitness::new();
+        witness_map.set_witness(&mut pw, &solved_witness(x, y, output + 1))?;
+        assert!(data.prove(pw).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_acir_circuit_serde() -> Result<()> {
+        let circuit = program();
+        let json = serde_json::to_string(&circuit)?;
+        assert_eq!(serde_json::from_str::<AcirCircuit<F>>(&json)?, circuit);
+        Ok(())
+    }
+}
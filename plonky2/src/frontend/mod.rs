@@ -0,0 +1,4 @@
+//! Adapters compiling programs written for other proving systems into plonky2 circuits.
+
+pub mod acir;
+pub mod r1cs;
@@ -0,0 +1,229 @@
+//! An importer for rank-1 constraint systems over the circuit's field, so that they can be proven
+//! with plonky2 and aggregated through recursion.
+//!
+//! The constraint system must be defined over the same field as the circuit: its coefficients and
+//! assignments are elements of `F`, and its constraints are checked modulo `F`'s order. Systems
+//! produced by circom or bellman are over BN254 or BLS12-381, whose scalar fields are much larger
+//! than e.g. Goldilocks, and no parser for their `.r1cs` files is provided. Reducing their
+//! coefficients into `F` doesn't preserve their meaning, e.g. `-1` and `p - 1` for the BN254
+//! modulus `p` differ in `F`, so they can only be imported if they were written for `F`.
+//!
+//! Each constraint `(A·z) * (B·z) = C·z` is lowered to arithmetic operations: the linear
+//! combinations are accumulated with at most one operation per term, and the product is checked
+//! with one more operation. When [`CircuitConfig::optimize`] is set, these operations go through
+//! the optimization pass, which folds constant terms, merges linear combinations shared by
+//! several constraints and packs the operations into as few gates as possible.
+//!
+//! [`CircuitConfig::optimize`]: crate::plonk::circuit_data::CircuitConfig::optimize
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A sparse linear combination of the variables `z`, as pairs `(i, coefficient)` standing for
+/// `coefficient * z_i`.
+pub type LinearCombination<F> = Vec<(usize, F)>;
+
+/// The constraint `(A·z) * (B·z) = C·z`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct R1csConstraint<F: Field> {
+    pub a: LinearCombination<F>,
+    pub b: LinearCombination<F>,
+    pub c: LinearCombination<F>,
+}
+
+/// A rank-1 constraint system over the variables `z_0, ..., z_{num_variables - 1}`. Following the
+/// usual convention, `z_0` is the constant `1`, and `z_1, ..., z_{num_public_inputs}` are public.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct R1cs<F: Field> {
+    pub num_variables: usize,
+    pub num_public_inputs: usize,
+    pub constraints: Vec<R1csConstraint<F>>,
+}
+
+/// The targets holding the variables of an imported [`R1cs`].
+#[derive(Clone, Debug)]
+pub struct R1csVariables {
+    targets: Vec<Target>,
+}
+
+impl R1csVariables {
+    /// The target holding `z_i`.
+    pub fn target(&self, i: usize) -> Target {
+        self.targets[i]
+    }
+
+    /// Sets the targets of the variables to the full assignment `z`, including `z_0 = 1`.
+    pub fn set_witness<F: Field>(&self, witness: &mut impl WitnessWrite<F>, z: &[F]) -> Result<()> {
+        ensure!(
+            z.len() == self.targets.len(),
+            "Expected an assignment of {} variables, got {}",
+            self.targets.len(),
+            z.len()
+        );
+        ensure!(z[0] == F::ONE, "The first variable must be 1");
+        for (&target, &value) in self.targets.iter().zip(z).skip(1) {
+            witness.set_target(target, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds the constraints of `r1cs`, and registers its public variables as public inputs. Fails
+    /// if a constraint refers to a variable out of the R1CS's range.
+    pub fn add_r1cs(&mut self, r1cs: &R1cs<F>) -> Result<R1csVariables> {
+        ensure!(r1cs.num_variables > 0, "The R1CS has no constant variable");
+        ensure!(
+            r1cs.num_public_inputs < r1cs.num_variables,
+            "The R1CS has more public inputs than variables"
+        );
+
+        let mut targets = Vec::with_capacity(r1cs.num_variables);
+        targets.push(self.one());
+        targets.extend(self.add_virtual_targets(r1cs.num_variables - 1));
+        self.register_public_inputs(&targets[1..=r1cs.num_public_inputs]);
+        let variables = R1csVariables { targets };
+
+        for constraint in &r1cs.constraints {
+            let a = self.r1cs_linear_combination(&constraint.a, &variables)?;
+            let b = self.r1cs_linear_combination(&constraint.b, &variables)?;
+            let c = self.r1cs_linear_combination(&constraint.c, &variables)?;
+            let difference = self.arithmetic(F::ONE, F::NEG_ONE, a, b, c);
+            self.assert_zero(difference);
+        }
+
+        Ok(variables)
+    }
+
+    /// Adds the constraints of `r1cs` like [`Self::add_r1cs`], and returns a witness holding the
+    /// full assignment `z` of its variables.
+    pub fn import_r1cs(
+        &mut self,
+        r1cs: &R1cs<F>,
+        z: &[F],
+    ) -> Result<(R1csVariables, PartialWitness<F>)> {
+        let variables = self.add_r1cs(r1cs)?;
+        let mut witness = PartialWitness::new();
+        variables.set_witness(&mut witness, z)?;
+        Ok((variables, witness))
+    }
+
+    fn r1cs_linear_combination(
+        &mut self,
+        terms: &LinearCombination<F>,
+        variables: &R1csVariables,
+    ) -> Result<Target> {
+        // Gather the constant terms first, so that they don't cost an operation.
+        let mut constant = F::ZERO;
+        let mut variable_terms = Vec::with_capacity(terms.len());
+        for &(i, coefficient) in terms {
+            ensure!(
+                i < variables.targets.len(),
+                "Variable {i} is out of the R1CS's range"
+            );
+            if i == 0 {
+                constant += coefficient;
+            } else {
+                variable_terms.push((i, coefficient));
+            }
+        }
+
+        let mut acc = (constant != F::ZERO).then(|| self.constant(constant));
+        for (i, coefficient) in variable_terms {
+            let target = variables.target(i);
+            acc = Some(match acc {
+                None if coefficient == F::ONE => target,
+                None => self.mul_const(coefficient, target),
+                Some(acc) => self.mul_const_add(coefficient, target, acc),
+            });
+        }
+        Ok(acc.unwrap_or_else(|| self.zero()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// The constraints of `out = x^3 + x + 5`, over the variables `[1, out, x, x^2, x^3]`.
+    fn cubic() -> R1cs<F> {
+        let one = F::ONE;
+        R1cs {
+            num_variables: 5,
+            num_public_inputs: 1,
+            constraints: vec![
+                R1csConstraint {
+                    a: vec![(2, one)],
+                    b: vec![(2, one)],
+                    c: vec![(3, one)],
+                },
+                R1csConstraint {
+                    a: vec![(3, one)],
+                    b: vec![(2, one)],
+                    c: vec![(4, one)],
+                },
+                R1csConstraint {
+                    a: vec![(4, one), (2, one), (0, F::from_canonical_u64(5))],
+                    b: vec![(0, one)],
+                    c: vec![(1, one)],
+                },
+            ],
+        }
+    }
+
+    fn assignment(x: F, out: F) -> Vec<F> {
+        vec![F::ONE, out, x, x * x, x * x * x]
+    }
+
+    fn test_import_r1cs(config: CircuitConfig) -> Result<()> {
+        let x = F::from_canonical_u64(3);
+        let out = F::from_canonical_u64(35);
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let (_, pw) = builder.import_r1cs(&cubic(), &assignment(x, out))?;
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs, [out]);
+        verify(proof, &data.verifier_only, &data.common)?;
+
+        // An assignment violating the constraints must be rejected.
+        let mut builder = CircuitBuilder::<F, D>::new(data.common.config.clone());
+        let (_, pw) = builder.import_r1cs(&cubic(), &assignment(x, out + F::ONE))?;
+        let data = builder.build::<C>();
+        assert!(data.prove(pw).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_r1cs_standard() -> Result<()> {
+        test_import_r1cs(CircuitConfig::standard_recursion_config())
+    }
+
+    #[test]
+    fn test_import_r1cs_optimized() -> Result<()> {
+        test_import_r1cs(CircuitConfig {
+            optimize: true,
+            ..CircuitConfig::standard_recursion_config()
+        })
+    }
+}
@@ -82,13 +82,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             return result;
         }
 
-        // Otherwise, we must actually perform the operation using an ArithmeticExtensionGate slot.
-        let result = self.add_base_arithmetic_operation(operation);
+        // If the circuit is to be optimized, defer the placement of the operation until the circuit
+        // is built. Otherwise, we must actually perform the operation using an ArithmeticGate slot.
+        let result = if self.config.optimize {
+            let result = self.add_virtual_target();
+            self.pending_base_arithmetic_operations
+                .push((operation, result));
+            result
+        } else {
+            self.add_base_arithmetic_operation(operation)
+        };
         self.base_arithmetic_results.insert(operation, result);
         result
     }
 
-    fn add_base_arithmetic_operation(&mut self, operation: BaseArithmeticOperation<F>) -> Target {
+    pub(crate) fn add_base_arithmetic_operation(
+        &mut self,
+        operation: BaseArithmeticOperation<F>,
+    ) -> Target {
         let gate = ArithmeticGate::new_from_config(&self.config);
         let constants = vec![operation.const_0, operation.const_1];
         let (gate, i) = self.find_slot(gate, &constants, &constants);
@@ -353,6 +364,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         BoolTarget::new_unsafe(self.add(res_minus_b2, b2.target))
     }
 
+    /// Computes the logical XOR through the arithmetic expression: `b1 + b2 - 2 * b1 * b2`.
+    pub fn xor(&mut self, b1: BoolTarget, b2: BoolTarget) -> BoolTarget {
+        let res_minus_b2 = self.arithmetic(-F::TWO, F::ONE, b1.target, b2.target, b1.target);
+        BoolTarget::new_unsafe(self.add(res_minus_b2, b2.target))
+    }
+
     /// Outputs `x` if `b` is true, and else `y`, through the formula: `b*x + (1-b)*y`.
     pub fn _if(&mut self, b: BoolTarget, x: Target, y: Target) -> Target {
         let not_b = self.not(b);
@@ -432,9 +449,9 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Equ
 /// Represents a base arithmetic operation in the circuit. Used to memoize results.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub(crate) struct BaseArithmeticOperation<F: Field64> {
-    const_0: F,
-    const_1: F,
-    multiplicand_0: Target,
-    multiplicand_1: Target,
-    addend: Target,
+    pub(crate) const_0: F,
+    pub(crate) const_1: F,
+    pub(crate) multiplicand_0: Target,
+    pub(crate) multiplicand_1: Target,
+    pub(crate) addend: Target,
 }
@@ -11,7 +11,9 @@ use anyhow::Result;
 use crate::field::extension::{Extendable, FieldExtension, OEF};
 use crate::field::types::{Field, Field64};
 use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
+use crate::gates::exponentiation_extension::ExponentiationExtensionGate;
 use crate::gates::multiplication_extension::MulExtensionGate;
+use crate::gates::multiplication_extension_algebra::MulExtensionAlgebraGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::{ExtensionAlgebraTarget, ExtensionTarget};
 use crate::iop::generator::{GeneratedValues, SimpleGenerator};
@@ -109,6 +111,31 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         ExtensionTarget::from_range(gate, MulExtensionGate::<D>::wires_ith_output(i))
     }
 
+    fn compute_mul_add_ext_algebra_operation(
+        &mut self,
+        a: ExtensionAlgebraTarget<D>,
+        b: ExtensionAlgebraTarget<D>,
+        c: ExtensionAlgebraTarget<D>,
+    ) -> ExtensionAlgebraTarget<D> {
+        let gate = MulExtensionAlgebraGate::new_from_config(&self.config);
+        let (gate, i) = self.find_slot(gate, &[], &[]);
+        for j in 0..D {
+            let wires = |range| ExtensionTarget::from_range(gate, range);
+            let multiplicand_0 =
+                wires(MulExtensionAlgebraGate::<D>::wires_ith_multiplicand_0(i, j));
+            let multiplicand_1 =
+                wires(MulExtensionAlgebraGate::<D>::wires_ith_multiplicand_1(i, j));
+            let addend = wires(MulExtensionAlgebraGate::<D>::wires_ith_addend(i, j));
+            self.connect_extension(a.0[j], multiplicand_0);
+            self.connect_extension(b.0[j], multiplicand_1);
+            self.connect_extension(c.0[j], addend);
+        }
+
+        ExtensionAlgebraTarget(core::array::from_fn(|j| {
+            ExtensionTarget::from_range(gate, MulExtensionAlgebraGate::<D>::wires_ith_output(i, j))
+        }))
+    }
+
     /// Checks for special cases where the value of
     /// `const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend`
     /// can be determined without adding an `ArithmeticGate`.
@@ -281,6 +308,15 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         b: ExtensionAlgebraTarget<D>,
         c: ExtensionAlgebraTarget<D>,
     ) -> ExtensionAlgebraTarget<D> {
+        // Products involving constants are left to `arithmetic_extension`, which can fold them.
+        let has_constant =
+            a.0.iter()
+                .chain(&b.0)
+                .any(|&t| self.target_as_constant_ext(t).is_some());
+        if !has_constant && MulExtensionAlgebraGate::<D>::num_ops(&self.config) > 0 {
+            return self.compute_mul_add_ext_algebra_operation(a, b, c);
+        }
+
         let mut inner = vec![vec![]; D];
         let mut inner_w = vec![vec![]; D];
         for i in 0..D {
@@ -477,6 +513,62 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         product
     }
 
+    /// Exponentiates `base` to the power of `exponent`, given by its little-endian bits, using
+    /// [`ExponentiationExtensionGate`]s. Several exponentiations with the same number of bits share
+    /// gates, and exponents with more bits than fit in a single operation are split into chained
+    /// operations.
+    pub fn exp_from_bits_extension(
+        &mut self,
+        base: ExtensionTarget<D>,
+        exponent_bits: impl IntoIterator<Item = impl Borrow<BoolTarget>>,
+    ) -> ExtensionTarget<D> {
+        let mut exponent_bits: Vec<BoolTarget> =
+            exponent_bits.into_iter().map(|b| *b.borrow()).collect();
+        if exponent_bits.is_empty() {
+            return self.one_extension();
+        }
+
+        let num_power_bits =
+            exponent_bits
+                .len()
+                .min(ExponentiationExtensionGate::<D>::max_power_bits(
+                    &self.config,
+                ));
+        let gate = ExponentiationExtensionGate::new_from_config(&self.config, num_power_bits);
+        // Pad the most significant end, so that only the first operation has leading zeros.
+        let _false = self._false();
+        exponent_bits.resize(exponent_bits.len().next_multiple_of(num_power_bits), _false);
+
+        let mut product = self.one_extension();
+        for chunk in exponent_bits.chunks(num_power_bits).rev() {
+            let (row, i) = self.find_slot(gate.clone(), &[], &[]);
+            self.connect_extension(
+                base,
+                ExtensionTarget::from_range(row, gate.wires_ith_base(i)),
+            );
+            self.connect_extension(
+                product,
+                ExtensionTarget::from_range(row, gate.wires_ith_initial(i)),
+            );
+            for (j, bit) in chunk.iter().enumerate() {
+                self.connect(bit.target, Target::wire(row, gate.wire_ith_power_bit(i, j)));
+            }
+            product = ExtensionTarget::from_range(row, gate.wires_ith_output(i));
+        }
+        product
+    }
+
+    /// Exponentiates `base` to the power of `exponent`, where `exponent < 2^max_bits`.
+    pub fn exp_var_bits(
+        &mut self,
+        base: ExtensionTarget<D>,
+        exponent: Target,
+        max_bits: usize,
+    ) -> ExtensionTarget<D> {
+        let exponent_bits = self.split_le(exponent, max_bits);
+        self.exp_from_bits_extension(base, exponent_bits)
+    }
+
     /// Computes `x / y`. Results in an unsatisfiable instance if `y = 0`.
     pub fn div_extension(
         &mut self,
@@ -619,12 +711,15 @@ mod tests {
     use anyhow::Result;
 
     use crate::field::extension::algebra::ExtensionAlgebra;
-    use crate::field::types::Sample;
+    use crate::field::types::{Field, Sample};
     use crate::iop::ext_target::ExtensionAlgebraTarget;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
-    use crate::plonk::config::{GenericConfig, KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::config::{
+        GenericConfig, KeccakGoldilocksConfig, PoseidonGoldilocksConfig,
+        PoseidonGoldilocksQuinticConfig,
+    };
     use crate::plonk::verifier::verify;
 
     #[test]
@@ -663,6 +758,70 @@ mod tests {
         verify(proof, &data.verifier_only, &data.common)
     }
 
+    #[test]
+    fn test_extension_native_circuit() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        // A circuit computing `x^3 + c * x + 1 / x` over the quadratic extension.
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input_extension();
+        let c = FF::rand();
+        let ct = builder.constant_extension(c);
+        let x_cubed = builder.exp_u64_extension(x, 3);
+        let cx = builder.mul_extension(ct, x);
+        let one = builder.one_extension();
+        let inv_x = builder.div_extension(one, x);
+        let y = builder.add_many_extension([x_cubed, cx, inv_x]);
+        builder.register_public_input_extension(y);
+        let data = builder.build::<C>();
+
+        let xv = FF::rand();
+        let mut pw = PartialWitness::new();
+        pw.set_extension_target(x, xv)?;
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_input_extension(0), xv);
+        assert_eq!(
+            proof.public_input_extension(D),
+            xv.cube() + c * xv + xv.inverse()
+        );
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_exp_var_bits() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let config = CircuitConfig::standard_recursion_config();
+
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // The 50-bit exponent doesn't fit in a single operation, while the shorter ones share gates.
+        for (exponent, max_bits) in [(0, 4), (13, 4), (1000, 20), (54321, 20), (3 << 47, 50)] {
+            let base = FF::rand();
+            let base_t = builder.add_virtual_extension_target();
+            let exponent_t = builder.add_virtual_target();
+            pw.set_extension_target(base_t, base)?;
+            pw.set_target(exponent_t, F::from_canonical_u64(exponent))?;
+
+            let pow = builder.exp_var_bits(base_t, exponent_t, max_bits);
+            let expected = builder.constant_extension(base.exp_u64(exponent));
+            builder.connect_extension(pow, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
     #[test]
     fn test_div_extension() -> Result<()> {
         const D: usize = 2;
@@ -690,6 +849,43 @@ mod tests {
         verify(proof, &data.verifier_only, &data.common)
     }
 
+    #[test]
+    fn test_quintic_extension_arithmetic() -> Result<()> {
+        const D: usize = 5;
+        type C = PoseidonGoldilocksQuinticConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let config = CircuitConfig::standard_recursion_config();
+
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let [x, y, z] = FF::rand_array();
+        let [xt, yt, zt] = [x, y, z].map(|v| {
+            let t = builder.add_virtual_extension_target();
+            pw.set_extension_target(t, v).unwrap();
+            t
+        });
+
+        let mul_add = builder.mul_add_extension(xt, yt, zt);
+        let quotient = builder.div_extension(xt, yt);
+        let pow = builder.exp_u64_extension(zt, 11);
+        for (computed, expected) in [
+            (mul_add, x * y + z),
+            (quotient, x / y),
+            (pow, z.exp_u64(11)),
+        ] {
+            let expected = builder.constant_extension(expected);
+            builder.connect_extension(computed, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
     #[test]
     fn test_mul_algebra() -> Result<()> {
         const D: usize = 2;
@@ -0,0 +1,240 @@
+//! Arithmetic on 64-bit and 128-bit unsigned integers, represented by little-endian 32-bit limbs.
+//!
+//! Limbs are combined with [`CircuitBuilder::mul_add_u32`], which computes `x y + z` for 32-bit
+//! `x`, `y` and `z`. The result is below `2^64 - 2^32 < p`, so it is computed exactly in the native
+//! field, then split into its low and high 32-bit halves.
+
+use anyhow::Result;
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::WitnessWrite;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+const LIMB_BITS: usize = 32;
+
+/// A 64-bit unsigned integer, as little-endian 32-bit limbs, which are assumed to be range-checked.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct U64Target(pub [Target; 2]);
+
+impl U64Target {
+    /// Sets the limbs of this target to those of `value`.
+    pub fn set_witness<F: Field>(
+        &self,
+        witness: &mut impl WitnessWrite<F>,
+        value: u64,
+    ) -> Result<()> {
+        witness.set_target(self.0[0], F::from_canonical_u32(value as u32))?;
+        witness.set_target(
+            self.0[1],
+            F::from_canonical_u32((value >> LIMB_BITS) as u32),
+        )
+    }
+}
+
+/// A 128-bit unsigned integer, as little-endian 32-bit limbs, which are assumed to be
+/// range-checked.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct U128Target(pub [Target; 4]);
+
+impl U128Target {
+    /// The integer `lo + 2^64 hi`.
+    pub fn from_u64s(lo: U64Target, hi: U64Target) -> Self {
+        Self([lo.0[0], lo.0[1], hi.0[0], hi.0[1]])
+    }
+
+    /// Sets the limbs of this target to those of `value`.
+    pub fn set_witness<F: Field>(
+        &self,
+        witness: &mut impl WitnessWrite<F>,
+        value: u128,
+    ) -> Result<()> {
+        for (i, &limb) in self.0.iter().enumerate() {
+            let limb_value = (value >> (LIMB_BITS * i)) as u32;
+            witness.set_target(limb, F::from_canonical_u32(limb_value))?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Returns a range-checked `U64Target`, to be set with [`U64Target::set_witness`].
+    pub fn add_virtual_u64_target(&mut self) -> U64Target {
+        U64Target(core::array::from_fn(|_| self.add_virtual_u32_limb()))
+    }
+
+    /// Returns a range-checked `U128Target`, to be set with [`U128Target::set_witness`].
+    pub fn add_virtual_u128_target(&mut self) -> U128Target {
+        U128Target(core::array::from_fn(|_| self.add_virtual_u32_limb()))
+    }
+
+    fn add_virtual_u32_limb(&mut self) -> Target {
+        let limb = self.add_virtual_target();
+        self.range_check(limb, LIMB_BITS);
+        limb
+    }
+
+    pub fn constant_u64(&mut self, x: u64) -> U64Target {
+        U64Target(core::array::from_fn(|i| {
+            self.constant(F::from_canonical_u32((x >> (LIMB_BITS * i)) as u32))
+        }))
+    }
+
+    pub fn constant_u128(&mut self, x: u128) -> U128Target {
+        U128Target(core::array::from_fn(|i| {
+            self.constant(F::from_canonical_u32((x >> (LIMB_BITS * i)) as u32))
+        }))
+    }
+
+    pub fn connect_u64(&mut self, x: U64Target, y: U64Target) {
+        for (l, r) in x.0.into_iter().zip(y.0) {
+            self.connect(l, r);
+        }
+    }
+
+    pub fn connect_u128(&mut self, x: U128Target, y: U128Target) {
+        for (l, r) in x.0.into_iter().zip(y.0) {
+            self.connect(l, r);
+        }
+    }
+
+    /// Returns the low and high 32-bit halves of `x y + z`, where `x`, `y` and `z` are assumed to
+    /// be 32-bit integers.
+    pub fn mul_add_u32(&mut self, x: Target, y: Target, z: Target) -> (Target, Target) {
+        let result = self.mul_add(x, y, z);
+        let (low, high) = self.split_low_high(result, LIMB_BITS, 2 * LIMB_BITS);
+
+        // `low + 2^32 high` ranges over `[0, 2^64)`, so values below `2^32 - 1` have a second
+        // decomposition, of `result + p`. Those have `high = 2^32 - 1` and `low > 0`, while the
+        // largest result, `2^64 - 2^32`, has `high = 2^32 - 1` and `low = 0`.
+        let max_limb = self.constant(F::from_canonical_u32(u32::MAX));
+        let high_is_max = self.is_equal(high, max_limb);
+        let low_if_high_is_max = self.mul(high_is_max.target, low);
+        self.assert_zero(low_if_high_is_max);
+
+        (low, high)
+    }
+
+    /// Returns `x + y + carry_in` modulo `2^32` and its carry, where `x` and `y` are assumed to be
+    /// 32-bit integers.
+    pub fn add_u32_with_carry(
+        &mut self,
+        x: Target,
+        y: Target,
+        carry_in: BoolTarget,
+    ) -> (Target, BoolTarget) {
+        let sum = self.add_many([x, y, carry_in.target]);
+        let (low, high) = self.split_low_high(sum, LIMB_BITS, LIMB_BITS + 1);
+        // `split_low_high` range-checks `high` to a single bit.
+        (low, BoolTarget::new_unsafe(high))
+    }
+
+    /// Returns the 128-bit product of `a` and `b`, as its low and high 64-bit halves.
+    pub fn mul_u64_wide(&mut self, a: U64Target, b: U64Target) -> (U64Target, U64Target) {
+        let [a0, a1] = a.0;
+        let [b0, b1] = b.0;
+        let zero = self.zero();
+        let no_carry = self._false();
+
+        let (r0, c0) = self.mul_add_u32(a0, b0, zero);
+        let (s0, s1) = self.mul_add_u32(a0, b1, c0);
+        let (r1, c1) = self.mul_add_u32(a1, b0, s0);
+        let (t0, r3) = self.mul_add_u32(a1, b1, s1);
+        let (r2, carry) = self.add_u32_with_carry(t0, c1, no_carry);
+        // The product is below `2^128`, so this doesn't overflow.
+        let r3 = self.add(r3, carry.target);
+
+        (U64Target([r0, r1]), U64Target([r2, r3]))
+    }
+
+    /// Returns `a + b + carry_in` modulo `2^128` and its carry.
+    pub fn add_u128_with_carry(
+        &mut self,
+        a: U128Target,
+        b: U128Target,
+        carry_in: BoolTarget,
+    ) -> (U128Target, BoolTarget) {
+        let mut carry = carry_in;
+        let limbs = core::array::from_fn(|i| {
+            let (limb, carry_out) = self.add_u32_with_carry(a.0[i], b.0[i], carry);
+            carry = carry_out;
+            limb
+        });
+        (U128Target(limbs), carry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_mul_u64_wide() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = OsRng;
+        let values = [(u64::MAX, u64::MAX), (rng.gen(), rng.gen())];
+        let mut targets = Vec::new();
+        for (a_val, b_val) in values {
+            let a = builder.add_virtual_u64_target();
+            let b = builder.add_virtual_u64_target();
+            let (lo, hi) = builder.mul_u64_wide(a, b);
+            let product = a_val as u128 * b_val as u128;
+            let expected = builder.constant_u128(product);
+            builder.connect_u128(U128Target::from_u64s(lo, hi), expected);
+            targets.push((a, b));
+        }
+
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        for ((a, b), (a_val, b_val)) in targets.into_iter().zip(values) {
+            a.set_witness(&mut pw, a_val)?;
+            b.set_witness(&mut pw, b_val)?;
+        }
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_add_u128_with_carry() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = OsRng;
+        let a_val = rng.gen::<u128>() | (1 << 127);
+        let b_val = rng.gen::<u128>();
+        let a = builder.add_virtual_u128_target();
+        let b = builder.add_virtual_u128_target();
+        let _true = builder._true();
+        let (sum, carry) = builder.add_u128_with_carry(a, b, _true);
+
+        let (expected_sum, overflow_1) = a_val.overflowing_add(b_val);
+        let (expected_sum, overflow_2) = expected_sum.overflowing_add(1);
+        let expected_sum = builder.constant_u128(expected_sum);
+        let expected_carry = builder.constant_bool(overflow_1 || overflow_2);
+        builder.connect_u128(sum, expected_sum);
+        builder.connect(carry.target, expected_carry.target);
+
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        a.set_witness(&mut pw, a_val)?;
+        b.set_witness(&mut pw, b_val)?;
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
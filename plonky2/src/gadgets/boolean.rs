@@ -0,0 +1,171 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::util::{log2_ceil, log_floor};
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the logical AND of all the provided [`BoolTarget`]s. Returns `true` if `bits` is
+    /// empty.
+    ///
+    /// Small inputs are reduced with a balanced tree of `and`s. Larger ones are packed into field
+    /// elements of up to `log2(|F|)` bits each, which are then compared against all-ones.
+    pub fn and_many(&mut self, bits: &[BoolTarget]) -> BoolTarget {
+        if bits.len() <= self.num_base_arithmetic_ops_per_gate() {
+            return self
+                .reduce_balanced(bits.to_vec(), Self::and)
+                .unwrap_or_else(|| self._true());
+        }
+
+        let all_ones = self.packed_bits_all_equal(bits, true);
+        self.and_many(&all_ones)
+    }
+
+    /// Computes the logical OR of all the provided [`BoolTarget`]s. Returns `false` if `bits` is
+    /// empty.
+    ///
+    /// Small inputs are reduced with a balanced tree of `or`s. Larger ones are packed into field
+    /// elements of up to `log2(|F|)` bits each, which are then compared against zero.
+    pub fn or_many(&mut self, bits: &[BoolTarget]) -> BoolTarget {
+        if bits.len() <= self.num_base_arithmetic_ops_per_gate() {
+            return self
+                .reduce_balanced(bits.to_vec(), Self::or)
+                .unwrap_or_else(|| self._false());
+        }
+
+        let all_zeros = self.packed_bits_all_equal(bits, false);
+        let none_set = self.and_many(&all_zeros);
+        self.not(none_set)
+    }
+
+    /// Computes the logical XOR of all the provided [`BoolTarget`]s, i.e. their parity. Returns
+    /// `false` if `bits` is empty.
+    ///
+    /// Small inputs are reduced with a balanced tree of `xor`s. For larger ones, it is cheaper to
+    /// sum the bits and extract the least significant bit of the result.
+    pub fn xor_many(&mut self, bits: &[BoolTarget]) -> BoolTarget {
+        if bits.len() <= self.num_base_arithmetic_ops_per_gate() {
+            return self
+                .reduce_balanced(bits.to_vec(), Self::xor)
+                .unwrap_or_else(|| self._false());
+        }
+
+        let count = self.popcount(bits);
+        self.split_le(count, log2_ceil(bits.len() + 1))[0]
+    }
+
+    /// Returns the number of `true` values among the provided [`BoolTarget`]s, summed with a
+    /// balanced tree of additions.
+    pub fn popcount(&mut self, bits: &[BoolTarget]) -> Target {
+        let terms = bits.iter().map(|b| b.target).collect();
+        self.reduce_balanced(terms, Self::add)
+            .unwrap_or_else(|| self.zero())
+    }
+
+    /// Packs `bits` into chunks of up to `log2(|F|)` bits, and returns for each chunk whether all
+    /// of its bits are equal to `value`.
+    fn packed_bits_all_equal(&mut self, bits: &[BoolTarget], value: bool) -> Vec<BoolTarget> {
+        let max_bits = log_floor(F::ORDER, 2);
+        let mut results = Vec::with_capacity(bits.len().div_ceil(max_bits));
+        for chunk in bits.chunks(max_bits) {
+            let packed = self.le_sum(chunk.iter());
+            let expected = if value {
+                F::from_canonical_u64(u64::MAX >> (64 - chunk.len()))
+            } else {
+                F::ZERO
+            };
+            let expected = self.constant(expected);
+            results.push(self.is_equal(packed, expected));
+        }
+        results
+    }
+
+    /// Reduces `items` with `op`, pairing up neighbouring items at each level so that the
+    /// reduction tree has logarithmic depth. Returns `None` if `items` is empty.
+    fn reduce_balanced<T: Copy>(
+        &mut self,
+        mut items: Vec<T>,
+        mut op: impl FnMut(&mut Self, T, T) -> T,
+    ) -> Option<T> {
+        while items.len() > 1 {
+            let mut next = Vec::with_capacity(items.len().div_ceil(2));
+            for pair in items.chunks(2) {
+                match *pair {
+                    [x, y] => next.push(op(self, x, y)),
+                    [x] => next.push(x),
+                    _ => unreachable!(),
+                }
+            }
+            items = next;
+        }
+        items.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_bool_reductions() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut rng = OsRng;
+        // Cover the empty case, both the tree and packed paths, and inputs spanning several
+        // packed chunks.
+        let mut inputs = [0, 1, 2, 5, 20, 21, 70, 150]
+            .iter()
+            .map(|&n| (0..n).map(|_| rng.gen()).collect::<Vec<bool>>())
+            .collect::<Vec<_>>();
+        inputs.push(vec![true; 100]);
+        inputs.push(vec![false; 100]);
+
+        for values in inputs {
+            let bits = values
+                .iter()
+                .map(|&v| {
+                    let b = builder.add_virtual_bool_target_safe();
+                    pw.set_bool_target(b, v)?;
+                    Ok(b)
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let and = builder.and_many(&bits);
+            let expected = builder.constant_bool(values.iter().all(|&v| v));
+            builder.connect(and.target, expected.target);
+
+            let or = builder.or_many(&bits);
+            let expected = builder.constant_bool(values.iter().any(|&v| v));
+            builder.connect(or.target, expected.target);
+
+            let count = values.iter().filter(|&&v| v).count();
+            let xor = builder.xor_many(&bits);
+            let expected = builder.constant_bool(count % 2 == 1);
+            builder.connect(xor.target, expected.target);
+
+            let popcount = builder.popcount(&bits);
+            let expected = builder.constant(F::from_canonical_usize(count));
+            builder.connect(popcount, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
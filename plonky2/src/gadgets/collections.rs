@@ -0,0 +1,548 @@
+//! Fixed-capacity stack and queue gadgets.
+//!
+//! While every operation applied to a [`StackGadget`] or [`QueueGadget`] is unconditional, its
+//! pointers are known when building the circuit, so popping an element simply returns the
+//! [`Target`] which was pushed and consistency is enforced by copy constraints alone, without any
+//! gates. Once a conditional operation (`push_if`, `pop_if`) is applied, the affected pointer
+//! becomes a target, and subsequent accesses through it select slots with one-hot flags, at a cost
+//! linear in the capacity.
+//!
+//! # Cost
+//!
+//! Once a pointer is dynamic, every `push`, `pop` or `peek` through it, conditional or not, costs
+//! `O(capacity)` gates, so `n` operations on a collection of capacity `n` cost `O(n^2)` gates.
+//! These gadgets are therefore meant for collections of small capacity, up to a few dozen
+//! elements. They don't implement a memory-checking argument, which would make dynamic accesses to
+//! large collections cost a constant number of gates each.
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A position in the slots of a [`StackGadget`] or [`QueueGadget`].
+#[derive(Copy, Clone, Debug)]
+enum Pointer {
+    /// A position known when building the circuit.
+    Static(usize),
+    /// A position only known when proving, after a conditional operation.
+    Dynamic(Target),
+}
+
+impl Pointer {
+    fn target<F: RichField + Extendable<D>, const D: usize>(
+        self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Target {
+        match self {
+            Self::Static(i) => builder.constant(F::from_canonical_usize(i)),
+            Self::Dynamic(t) => t,
+        }
+    }
+
+    /// Moves the pointer one position forward (or backward if `backward` is set) if `cond` holds.
+    fn step_if<F: RichField + Extendable<D>, const D: usize>(
+        self,
+        builder: &mut CircuitBuilder<F, D>,
+        cond: BoolTarget,
+        backward: bool,
+    ) -> Self {
+        let t = self.target(builder);
+        Self::Dynamic(if backward {
+            builder.sub(t, cond.target)
+        } else {
+            builder.add(t, cond.target)
+        })
+    }
+}
+
+/// A last-in first-out stack of at most `capacity` targets. Accesses after a conditional operation
+/// cost `O(capacity)` gates each, see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct StackGadget {
+    slots: Vec<Option<Target>>,
+    len: Pointer,
+}
+
+impl StackGadget {
+    /// Creates an empty stack which can hold up to `capacity` elements.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: vec![None; capacity],
+            len: Pointer::Static(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the number of elements in the stack.
+    pub fn len<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Target {
+        self.len.target(builder)
+    }
+
+    /// Returns whether the stack is empty.
+    pub fn is_empty<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> BoolTarget {
+        match self.len {
+            Pointer::Static(n) => builder.constant_bool(n == 0),
+            Pointer::Dynamic(t) => {
+                let zero = builder.zero();
+                builder.is_equal(t, zero)
+            }
+        }
+    }
+
+    /// Pushes `value` onto the stack. Panics, or makes the circuit unsatisfiable if the length is
+    /// dynamic, if the stack is full.
+    pub fn push<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        value: Target,
+    ) {
+        match self.len {
+            Pointer::Static(n) => {
+                assert!(n < self.capacity(), "Stack overflow");
+                self.slots[n] = Some(value);
+                self.len = Pointer::Static(n + 1);
+            }
+            Pointer::Dynamic(_) => {
+                let _true = builder._true();
+                self.push_if(builder, _true, value);
+            }
+        }
+    }
+
+    /// Pushes `value` onto the stack if `cond` is true. The circuit is unsatisfiable if `cond` is
+    /// true and the stack is full. This costs `O(capacity)` gates, and makes the length dynamic.
+    pub fn push_if<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        cond: BoolTarget,
+        value: Target,
+    ) {
+        let index = self.len.target(builder);
+        write_slot(builder, &mut self.slots, index, cond, value);
+        self.len = self.len.step_if(builder, cond, false);
+    }
+
+    /// Pops the top element of the stack. Panics, or makes the circuit unsatisfiable if the length
+    /// is dynamic, if the stack is empty.
+    pub fn pop<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Target {
+        match self.len {
+            Pointer::Static(n) => {
+                assert!(n > 0, "Stack underflow");
+                self.len = Pointer::Static(n - 1);
+                self.slots[n - 1].expect("Slot below the stack length should be written")
+            }
+            Pointer::Dynamic(_) => {
+                let _true = builder._true();
+                self.pop_if(builder, _true)
+            }
+        }
+    }
+
+    /// Pops the top element of the stack if `cond` is true. The circuit is unsatisfiable if `cond`
+    /// is true and the stack is empty. If `cond` is false, the returned value is unspecified. This
+    /// costs `O(capacity)` gates, and makes the length dynamic.
+    pub fn pop_if<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        cond: BoolTarget,
+    ) -> Target {
+        let value = self.peek_if(builder, cond);
+        self.len = self.len.step_if(builder, cond, true);
+        value
+    }
+
+    /// Returns the top element of the stack without removing it. Panics, or makes the circuit
+    /// unsatisfiable if the length is dynamic, if the stack is empty.
+    pub fn peek<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Target {
+        if let Pointer::Static(n) = self.len {
+            assert!(n > 0, "Stack underflow");
+        }
+        let _true = builder._true();
+        self.peek_if(builder, _true)
+    }
+
+    fn peek_if<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        cond: BoolTarget,
+    ) -> Target {
+        match self.len {
+            Pointer::Static(0) => {
+                builder.assert_zero(cond.target);
+                builder.zero()
+            }
+            Pointer::Static(n) => {
+                self.slots[n - 1].expect("Slot below the stack length should be written")
+            }
+            Pointer::Dynamic(len) => {
+                let index = builder.add_const(len, F::NEG_ONE);
+                read_slot(builder, &self.slots, index, cond)
+            }
+        }
+    }
+}
+
+/// A first-in first-out queue which can be pushed to at most `capacity` times over its lifetime.
+/// Accesses after a conditional operation cost `O(capacity)` gates each, see the
+/// [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct QueueGadget {
+    slots: Vec<Option<Target>>,
+    head: Pointer,
+    tail: Pointer,
+}
+
+impl QueueGadget {
+    /// Creates an empty queue which can be pushed to up to `capacity` times.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: vec![None; capacity],
+            head: Pointer::Static(0),
+            tail: Pointer::Static(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Target {
+        match (self.head, self.tail) {
+            (Pointer::Static(head), Pointer::Static(tail)) => {
+                builder.constant(F::from_canonical_usize(tail - head))
+            }
+            (head, tail) => {
+                let head = head.target(builder);
+                let tail = tail.target(builder);
+                builder.sub(tail, head)
+            }
+        }
+    }
+
+    /// Returns whether the queue is empty.
+    pub fn is_empty<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> BoolTarget {
+        match (self.head, self.tail) {
+            (Pointer::Static(head), Pointer::Static(tail)) => builder.constant_bool(head == tail),
+            (head, tail) => {
+                let head = head.target(builder);
+                let tail = tail.target(builder);
+                builder.is_equal(head, tail)
+            }
+        }
+    }
+
+    /// Pushes `value` to the back of the queue. Panics, or makes the circuit unsatisfiable if the
+    /// tail is dynamic, if the capacity is exhausted.
+    pub fn push<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        value: Target,
+    ) {
+        match self.tail {
+            Pointer::Static(n) => {
+                assert!(n < self.capacity(), "Queue overflow");
+                self.slots[n] = Some(value);
+                self.tail = Pointer::Static(n + 1);
+            }
+            Pointer::Dynamic(_) => {
+                let _true = builder._true();
+                self.push_if(builder, _true, value);
+            }
+        }
+    }
+
+    /// Pushes `value` to the back of the queue if `cond` is true. The circuit is unsatisfiable if
+    /// `cond` is true and the capacity is exhausted. This costs `O(capacity)` gates, and makes the
+    /// tail dynamic.
+    pub fn push_if<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        cond: BoolTarget,
+        value: Target,
+    ) {
+        let index = self.tail.target(builder);
+        write_slot(builder, &mut self.slots, index, cond, value);
+        self.tail = self.tail.step_if(builder, cond, false);
+    }
+
+    /// Pops the front element of the queue. Panics, or makes the circuit unsatisfiable if a pointer
+    /// is dynamic, if the queue is empty.
+    pub fn pop<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Target {
+        match (self.head, self.tail) {
+            (Pointer::Static(head), Pointer::Static(tail)) => {
+                assert!(head < tail, "Queue underflow");
+                self.head = Pointer::Static(head + 1);
+                self.slots[head].expect("Slot between head and tail should be written")
+            }
+            _ => {
+                let _true = builder._true();
+                self.pop_if(builder, _true)
+            }
+        }
+    }
+
+    /// Pops the front element of the queue if `cond` is true. The circuit is unsatisfiable if
+    /// `cond` is true and the queue is empty. If `cond` is false, the returned value is
+    /// unspecified. This costs `O(capacity)` gates, and makes the head dynamic.
+    pub fn pop_if<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        cond: BoolTarget,
+    ) -> Target {
+        // The head never passes the tail, so a nonempty queue has `head < tail`.
+        let is_empty = self.is_empty(builder);
+        let underflow = builder.and(cond, is_empty);
+        builder.assert_zero(underflow.target);
+
+        let value = match self.head {
+            Pointer::Static(head) if head < self.capacity() => {
+                self.slots[head].unwrap_or_else(|| builder.zero())
+            }
+            head => {
+                let index = head.target(builder);
+                read_slot(builder, &self.slots, index, cond)
+            }
+        };
+        self.head = self.head.step_if(builder, cond, false);
+        value
+    }
+}
+
+/// Returns, for each slot, whether `index` points to it, along with whether `index` points to any
+/// slot at all.
+fn slot_flags<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    index: Target,
+    num_slots: usize,
+) -> (Vec<BoolTarget>, BoolTarget) {
+    let flags = (0..num_slots)
+        .map(|i| {
+            let i = builder.constant(F::from_canonical_usize(i));
+            builder.is_equal(index, i)
+        })
+        .collect::<Vec<_>>();
+    // At most one flag is set, so their sum is boolean.
+    let in_range = BoolTarget::new_unsafe(builder.add_many(flags.iter().map(|f| f.target)));
+    (flags, in_range)
+}
+
+/// Asserts that `cond` implies `in_range`, i.e. that `cond * (1 - in_range) = 0`.
+fn assert_in_range_if<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    cond: BoolTarget,
+    in_range: BoolTarget,
+) {
+    let out_of_range = builder.arithmetic(
+        F::NEG_ONE,
+        F::ONE,
+        cond.target,
+        in_range.target,
+        cond.target,
+    );
+    builder.assert_zero(out_of_range);
+}
+
+fn write_slot<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    slots: &mut [Option<Target>],
+    index: Target,
+    cond: BoolTarget,
+    value: Target,
+) {
+    let (flags, in_range) = slot_flags(builder, index, slots.len());
+    assert_in_range_if(builder, cond, in_range);
+    for (slot, flag) in slots.iter_mut().zip(flags) {
+        let write = builder.and(cond, flag);
+        let old = slot.unwrap_or_else(|| builder.zero());
+        *slot = Some(builder.select(write, value, old));
+    }
+}
+
+fn read_slot<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    slots: &[Option<Target>],
+    index: Target,
+    cond: BoolTarget,
+) -> Target {
+    let (flags, in_range) = slot_flags(builder, index, slots.len());
+    assert_in_range_if(builder, cond, in_range);
+    let zero = builder.zero();
+    slots
+        .iter()
+        .zip(flags)
+        .fold(zero, |acc, (slot, flag)| match slot {
+            Some(slot) => builder.mul_add(flag.target, *slot, acc),
+            None => acc,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_static_stack_uses_no_gates() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let values = builder.add_virtual_targets(4);
+        let mut stack = StackGadget::new(4);
+        for &v in &values {
+            stack.push(&mut builder, v);
+        }
+        assert_eq!(stack.pop(&mut builder), values[3]);
+        assert_eq!(stack.pop(&mut builder), values[2]);
+        stack.push(&mut builder, values[0]);
+        assert_eq!(stack.peek(&mut builder), values[0]);
+
+        let mut queue = QueueGadget::new(4);
+        for &v in &values {
+            queue.push(&mut builder, v);
+        }
+        assert_eq!(queue.pop(&mut builder), values[0]);
+        assert_eq!(queue.pop(&mut builder), values[1]);
+
+        assert_eq!(builder.num_gates(), 0);
+    }
+
+    #[test]
+    fn test_conditional_stack_and_queue() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let capacity = 6;
+        let mut stack = StackGadget::new(capacity);
+        let mut queue = QueueGadget::new(capacity);
+        let mut native_stack = Vec::new();
+        let mut native_queue = VecDeque::new();
+
+        let mut rng = OsRng;
+        for round in 0..capacity {
+            let value = F::from_canonical_usize(round);
+            let value_t = builder.constant(value);
+            let push = rng.gen::<bool>();
+            let push_t = builder.add_virtual_bool_target_safe();
+            pw.set_bool_target(push_t, push)?;
+
+            stack.push_if(&mut builder, push_t, value_t);
+            queue.push_if(&mut builder, push_t, value_t);
+            if push {
+                native_stack.push(value);
+                native_queue.push_back(value);
+            }
+
+            let pop = rng.gen::<bool>() && !native_stack.is_empty();
+            let pop_t = builder.add_virtual_bool_target_safe();
+            pw.set_bool_target(pop_t, pop)?;
+
+            let stack_out = stack.pop_if(&mut builder, pop_t);
+            let queue_out = queue.pop_if(&mut builder, pop_t);
+            if pop {
+                let expected = builder.constant(native_stack.pop().unwrap());
+                builder.connect(stack_out, expected);
+                let expected = builder.constant(native_queue.pop_front().unwrap());
+                builder.connect(queue_out, expected);
+            }
+        }
+
+        // Unconditional operations keep working after the pointers became dynamic.
+        let x = builder.constant(F::from_canonical_usize(100));
+        stack.push(&mut builder, x);
+        let top = stack.pop(&mut builder);
+        builder.connect(top, x);
+
+        let expected = builder.constant(F::from_canonical_usize(native_stack.len()));
+        let len = stack.len(&mut builder);
+        builder.connect(len, expected);
+        let expected = builder.constant(F::from_canonical_usize(native_queue.len()));
+        let len = queue.len(&mut builder);
+        builder.connect(len, expected);
+        let expected = builder.constant_bool(native_stack.is_empty());
+        let is_empty = stack.is_empty(&mut builder);
+        builder.connect(is_empty.target, expected.target);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_stack_underflow() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut stack = StackGadget::new(2);
+        let push_t = builder.add_virtual_bool_target_safe();
+        pw.set_bool_target(push_t, false)?;
+        let one = builder.one();
+        stack.push_if(&mut builder, push_t, one);
+        stack.pop(&mut builder);
+
+        let data = builder.build::<C>();
+        assert!(data.prove(pw).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_queue_underflow() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let mut queue = QueueGadget::new(2);
+        let push_t = builder.add_virtual_bool_target_safe();
+        pw.set_bool_target(push_t, false)?;
+        let one = builder.one();
+        queue.push_if(&mut builder, push_t, one);
+        queue.pop(&mut builder);
+
+        let data = builder.build::<C>();
+        assert!(data.prove(pw).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+//! Hiding commitments to private values, which bind a circuit's witness to a digest published
+//! outside of it.
+//!
+//! The commitment to `values` with the blinding factor `blinding` is the hash of the elements of
+//! `blinding` followed by `values`, without padding, so the number of values must be fixed by the
+//! application, as it is by a circuit opening the commitment. The blinding factor must be sampled at
+//! random, e.g. with [`CommitAndProve::sample_blinding`], and kept private for the digest to hide
+//! the values.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::AlgebraicHasher;
+use crate::util::blinding::blinding_rand;
+
+/// Native side of commitments opened in circuits with [`CircuitBuilder::open_commitment`].
+#[derive(Copy, Clone, Debug)]
+pub struct CommitAndProve<F: RichField, H: AlgebraicHasher<F>>(PhantomData<(F, H)>);
+
+impl<F: RichField, H: AlgebraicHasher<F>> CommitAndProve<F, H> {
+    /// Samples a blinding factor, see [`blinding_rand`].
+    pub fn sample_blinding() -> HashOut<F> {
+        blinding_rand()
+    }
+
+    /// Returns the commitment to `values` with the blinding factor `blinding`.
+    pub fn commit(values: &[F], blinding: HashOut<F>) -> HashOut<F> {
+        let inputs = blinding
+            .elements
+            .iter()
+            .chain(values)
+            .copied()
+            .collect::<Vec<_>>();
+        H::hash_no_pad(&inputs)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Returns the commitment to `values` with the blinding factor `blinding`, as
+    /// [`CommitAndProve::commit`] does.
+    pub fn commit_values<H: AlgebraicHasher<F>>(
+        &mut self,
+        values: &[Target],
+        blinding: HashOutTarget,
+    ) -> HashOutTarget {
+        let inputs = blinding.elements.iter().chain(values).copied().collect();
+        self.hash_n_to_hash_no_pad::<H>(inputs)
+    }
+
+    /// Asserts that `digest` is the commitment to `values` with the blinding factor `blinding`.
+    pub fn open_commitment<H: AlgebraicHasher<F>>(
+        &mut self,
+        digest: HashOutTarget,
+        values: &[Target],
+        blinding: HashOutTarget,
+    ) {
+        let commitment = self.commit_values::<H>(values, blinding);
+        self.connect_hashes(digest, commitment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    #[test]
+    fn test_open_commitment() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let digest_t = builder.add_virtual_hash_public_input();
+        let values_t = builder.add_virtual_targets(10);
+        let blinding_t = builder.add_virtual_hash();
+        builder.open_commitment::<H>(digest_t, &values_t, blinding_t);
+        let data = builder.build::<C>();
+
+        let values = F::rand_vec(10);
+        let blinding = CommitAndProve::<F, H>::sample_blinding();
+        let digest = CommitAndProve::<F, H>::commit(&values, blinding);
+        let prove = |digest| {
+            let mut pw = PartialWitness::new();
+            pw.set_hash_target(digest_t, digest)?;
+            pw.set_target_arr(&values_t, &values)?;
+            pw.set_hash_target(blinding_t, blinding)?;
+            data.prove(pw)
+        };
+
+        let proof = prove(digest)?;
+        assert_eq!(proof.public_inputs, digest.elements);
+        data.verify(proof)?;
+
+        // A commitment with another blinding factor doesn't open to the same values.
+        let other_digest =
+            CommitAndProve::<F, H>::commit(&values, CommitAndProve::<F, H>::sample_blinding());
+        assert_ne!(other_digest, digest);
+        assert!(prove(other_digest).is_err());
+
+        Ok(())
+    }
+}
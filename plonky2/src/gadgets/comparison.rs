@@ -0,0 +1,130 @@
+use crate::field::extension::Extendable;
+use crate::gates::comparison::ComparisonGate;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Returns `a < b`, where `a` and `b` are range-checked to have at most `num_bits` bits.
+    pub fn is_less_than(&mut self, a: Target, b: Target, num_bits: usize) -> BoolTarget {
+        let gate = ComparisonGate::new_from_config(&self.config, num_bits);
+        let (row, i) = self.find_slot(gate, &[], &[]);
+        self.connect(a, Target::wire(row, ComparisonGate::wire_ith_a(i)));
+        self.connect(b, Target::wire(row, ComparisonGate::wire_ith_b(i)));
+        BoolTarget::new_unsafe(Target::wire(row, ComparisonGate::wire_ith_result(i)))
+    }
+
+    /// Returns `a <= b`, where `a` and `b` are range-checked to have at most `num_bits` bits.
+    pub fn is_less_than_or_equal(&mut self, a: Target, b: Target, num_bits: usize) -> BoolTarget {
+        let b_less_than_a = self.is_less_than(b, a, num_bits);
+        self.not(b_less_than_a)
+    }
+
+    /// Asserts that `a < b`, where `a` and `b` are range-checked to have at most `num_bits` bits.
+    pub fn assert_less_than(&mut self, a: Target, b: Target, num_bits: usize) {
+        let less_than = self.is_less_than(a, b, num_bits);
+        self.assert_one(less_than.target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::gates::gate::Gate;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    #[test]
+    fn test_is_less_than() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let num_bits = 32;
+        let mut rng = OsRng;
+        let mut pairs = (0..8)
+            .map(|_| (rng.gen::<u32>() as u64, rng.gen::<u32>() as u64))
+            .collect::<Vec<_>>();
+        pairs.extend([(5, 5), (0, 1), (u32::MAX as u64, 0), (0, u32::MAX as u64)]);
+
+        for &(a, b) in &pairs {
+            let at = builder.add_virtual_target();
+            let bt = builder.add_virtual_target();
+            pw.set_target(at, F::from_canonical_u64(a))?;
+            pw.set_target(bt, F::from_canonical_u64(b))?;
+
+            let less_than = builder.is_less_than(at, bt, num_bits);
+            let expected = builder.constant_bool(a < b);
+            builder.connect(less_than.target, expected.target);
+
+            let less_than_or_equal = builder.is_less_than_or_equal(at, bt, num_bits);
+            let expected = builder.constant_bool(a <= b);
+            builder.connect(less_than_or_equal.target, expected.target);
+        }
+
+        // Comparisons are packed into shared rows.
+        let gate = ComparisonGate::new_from_config(&builder.config, num_bits);
+        let gate_id = <ComparisonGate as Gate<F, D>>::id(&gate);
+        let comparison_rows = builder
+            .gate_instances
+            .iter()
+            .filter(|g| g.gate_ref.0.id() == gate_id)
+            .count();
+        assert_eq!(comparison_rows, (2 * pairs.len()).div_ceil(gate.num_ops));
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_is_less_than_partial_gate() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // A single comparison leaves the other operations of its gate unused, i.e. all-zero.
+        let gate = ComparisonGate::new_from_config(&builder.config, 16);
+        assert!(gate.num_ops > 1);
+        let a = builder.constant(F::from_canonical_u64(7));
+        let b = builder.constant(F::from_canonical_u64(300));
+        let less_than = builder.is_less_than(a, b, 16);
+        builder.assert_one(less_than.target);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        data.verify(proof)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_is_less_than_out_of_range() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // 256 doesn't fit in 8 bits.
+        let a = builder.constant(F::from_canonical_u64(256));
+        let b = builder.constant(F::from_canonical_u64(3));
+        builder.is_less_than(a, b, 8);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new()).unwrap();
+        data.verify(proof).unwrap();
+    }
+}
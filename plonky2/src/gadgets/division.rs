@@ -0,0 +1,208 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use anyhow::{ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the Euclidean division of `x` by `y`, returning `(q, r)` such that `x = q * y + r`
+    /// and `0 <= r < y`. Both `x` and `y` must have at most `num_bits` bits.
+    ///
+    /// The quotient and remainder are range-checked, so they are the canonical ones. In particular,
+    /// the constraint `r < y` implies `y != 0`: dividing by zero, or by a `y` with more than
+    /// `num_bits` bits, makes the circuit unsatisfiable, and witness generation fails.
+    pub fn div_rem(&mut self, x: Target, y: Target, num_bits: usize) -> (Target, Target) {
+        // Make sure `q * y + r` can't wrap around the field for any range-checked `q, y, r`, given
+        // that `r < y`.
+        let max_divisor = (1u128 << num_bits) - 1;
+        assert!(
+            max_divisor * max_divisor + max_divisor - 1 < F::ORDER as u128,
+            "{num_bits}-bit division is not supported in this field"
+        );
+
+        let (q, r) = self.add_div_rem_generator(x, y);
+        self.range_check(q, num_bits);
+        // This also range-checks `r` and `y`.
+        self.assert_less_than(r, y, num_bits);
+
+        let computed_x = self.mul_add(q, y, r);
+        self.connect(computed_x, x);
+        (q, r)
+    }
+
+    /// Computes `x mod m` for a nonzero constant `m`, where `x` must have at most `num_bits` bits.
+    /// The result is range-checked to be the canonical remainder, in `[0, m)`.
+    pub fn rem_constant(&mut self, x: Target, m: u64, num_bits: usize) -> Target {
+        assert_ne!(m, 0, "Division by zero");
+        let m_bits = 64 - (m - 1).leading_zeros() as usize;
+        let max_quotient = (1u128 << num_bits) - 1;
+        assert!(
+            max_quotient * m as u128 + m as u128 - 1 < F::ORDER as u128,
+            "{num_bits}-bit reduction modulo {m} is not supported in this field"
+        );
+
+        let m_target = self.constant(F::from_canonical_u64(m));
+        let (q, r) = self.add_div_rem_generator(x, m_target);
+        self.range_check(q, num_bits);
+        if m > 1 {
+            let m_minus_one = self.constant(F::from_canonical_u64(m - 1));
+            let r_is_too_large = self.is_less_than(m_minus_one, r, m_bits);
+            self.assert_zero(r_is_too_large.target);
+        } else {
+            self.assert_zero(r);
+        }
+
+        let computed_x = self.mul_const_add(F::from_canonical_u64(m), q, r);
+        self.connect(computed_x, x);
+        r
+    }
+
+    fn add_div_rem_generator(&mut self, x: Target, y: Target) -> (Target, Target) {
+        let quotient = self.add_virtual_target();
+        let remainder = self.add_virtual_target();
+        self.add_simple_generator(DivRemGenerator {
+            numerator: x,
+            denominator: y,
+            quotient,
+            remainder,
+        });
+        (quotient, remainder)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DivRemGenerator {
+    numerator: Target,
+    denominator: Target,
+    quotient: Target,
+    remainder: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for DivRemGenerator {
+    fn id(&self) -> String {
+        "DivRemGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.numerator, self.denominator]
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let numerator = witness.get_target(self.numerator).to_canonical_u64();
+        let denominator = witness.get_target(self.denominator).to_canonical_u64();
+        ensure!(denominator != 0, "Division by zero");
+
+        out_buffer.set_target(
+            self.quotient,
+            F::from_canonical_u64(numerator / denominator),
+        )?;
+        out_buffer.set_target(
+            self.remainder,
+            F::from_canonical_u64(numerator % denominator),
+        )
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target(self.numerator)?;
+        dst.write_target(self.denominator)?;
+        dst.write_target(self.quotient)?;
+        dst.write_target(self.remainder)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let numerator = src.read_target()?;
+        let denominator = src.read_target()?;
+        let quotient = src.read_target()?;
+        let remainder = src.read_target()?;
+        Ok(Self {
+            numerator,
+            denominator,
+            quotient,
+            remainder,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_div_rem() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let cases = [
+            (17, 5),
+            (5, 17),
+            (0, 3),
+            (42, 1),
+            (u32::MAX as u64, u32::MAX as u64),
+            (u32::MAX as u64, 65537),
+        ];
+        for (x, y) in cases {
+            let xt = builder.add_virtual_target();
+            let yt = builder.add_virtual_target();
+            pw.set_target(xt, F::from_canonical_u64(x))?;
+            pw.set_target(yt, F::from_canonical_u64(y))?;
+
+            let (q, r) = builder.div_rem(xt, yt, 32);
+            let expected_q = builder.constant(F::from_canonical_u64(x / y));
+            let expected_r = builder.constant(F::from_canonical_u64(x % y));
+            builder.connect(q, expected_q);
+            builder.connect(r, expected_r);
+
+            let r = builder.rem_constant(xt, 10, 32);
+            let expected_r = builder.constant(F::from_canonical_u64(x % 10));
+            builder.connect(r, expected_r);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_div_rem_by_zero() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let xt = builder.add_virtual_target();
+        let yt = builder.add_virtual_target();
+        pw.set_target(xt, F::from_canonical_u64(7))?;
+        pw.set_target(yt, F::ZERO)?;
+        builder.div_rem(xt, yt, 32);
+
+        let data = builder.build::<C>();
+        assert!(data.prove(pw).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use core::ops::{Add, Mul, Neg, Sub};
+
+use hashbrown::HashMap;
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A polynomial in [`Target`]s, written with the usual operators, e.g. `x * y + 3 * z - w` for
+/// `x, z: Expr<F>` and targets `y` and `w`. No constraint is added until the expression is
+/// materialized with [`CircuitBuilder::materialize`], which lowers it as a whole into as few
+/// arithmetic operations as possible, rather than one per binary operation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Expr<F: Field> {
+    /// The monomials of the polynomial, as their sorted factors, with their nonzero coefficients.
+    /// The constant term is the monomial without factors.
+    terms: Vec<(Vec<Target>, F)>,
+}
+
+impl<F: Field> Expr<F> {
+    pub fn constant(c: F) -> Self {
+        Self::from_terms(vec![(vec![], c)])
+    }
+
+    /// Merges the terms with the same monomial, and drops those which cancel out.
+    fn from_terms(terms: Vec<(Vec<Target>, F)>) -> Self {
+        let mut indices = HashMap::<Vec<Target>, usize>::new();
+        let mut merged: Vec<(Vec<Target>, F)> = Vec::with_capacity(terms.len());
+        for (monomial, c) in terms {
+            match indices.get(&monomial) {
+                Some(&i) => merged[i].1 += c,
+                None => {
+                    indices.insert(monomial.clone(), merged.len());
+                    merged.push((monomial, c));
+                }
+            }
+        }
+        merged.retain(|&(_, c)| c != F::ZERO);
+        Self { terms: merged }
+    }
+}
+
+/// An order on targets, used to sort the factors of monomials.
+fn target_key(target: &Target) -> (usize, usize, usize) {
+    match *target {
+        Target::Wire(wire) => (0, wire.row, wire.column),
+        Target::VirtualTarget { index } => (1, index, 0),
+    }
+}
+
+impl<F: Field> From<Target> for Expr<F> {
+    fn from(target: Target) -> Self {
+        Self {
+            terms: vec![(vec![target], F::ONE)],
+        }
+    }
+}
+
+impl<F: Field> From<F> for Expr<F> {
+    fn from(c: F) -> Self {
+        Self::constant(c)
+    }
+}
+
+impl<F: Field> Neg for Expr<F> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            terms: self.terms.into_iter().map(|(m, c)| (m, -c)).collect(),
+        }
+    }
+}
+
+impl<F: Field, T: Into<Expr<F>>> Add<T> for Expr<F> {
+    type Output = Self;
+
+    fn add(self, rhs: T) -> Self {
+        let mut terms = self.terms;
+        terms.extend(rhs.into().terms);
+        Self::from_terms(terms)
+    }
+}
+
+impl<F: Field, T: Into<Expr<F>>> Sub<T> for Expr<F> {
+    type Output = Self;
+
+    fn sub(self, rhs: T) -> Self {
+        self + -rhs.into()
+    }
+}
+
+impl<F: Field, T: Into<Expr<F>>> Mul<T> for Expr<F> {
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self {
+        let rhs = rhs.into();
+        let mut terms = Vec::with_capacity(self.terms.len() * rhs.terms.len());
+        for (m_0, c_0) in &self.terms {
+            for (m_1, c_1) in &rhs.terms {
+                let mut monomial = [m_0.as_slice(), m_1].concat();
+                monomial.sort_by_key(target_key);
+                terms.push((monomial, *c_0 * *c_1));
+            }
+        }
+        Self::from_terms(terms)
+    }
+}
+
+impl<F: Field> Mul<Expr<F>> for u64 {
+    type Output = Expr<F>;
+
+    fn mul(self, rhs: Expr<F>) -> Expr<F> {
+        rhs * F::from_noncanonical_u64(self)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Returns a target holding the value of `expr`.
+    ///
+    /// Each `ArithmeticGate` operation computes `c_0 * a * b + c_1 * addend`, so the first
+    /// operation takes two terms of `expr`, e.g. a product and a linear term, and each other
+    /// operation adds one term to the accumulated result. Monomials of degree more than 2 first
+    /// have their other factors multiplied together.
+    pub fn materialize(&mut self, expr: &Expr<F>) -> Target {
+        let one = self.one();
+        let zero = self.zero();
+
+        let mut products = Vec::new();
+        let mut linear_terms = Vec::new();
+        for (monomial, c) in &expr.terms {
+            match monomial.as_slice() {
+                [] => linear_terms.push((*c, one)),
+                &[x] => linear_terms.push((*c, x)),
+                [factors @ .., last] => {
+                    let product = self.mul_many(factors);
+                    products.push((*c, product, *last));
+                }
+            }
+        }
+
+        let mut products = products.into_iter();
+        let mut linear_terms = linear_terms.into_iter();
+        let mut acc = None;
+        loop {
+            let (c_0, a, b) = match products.next() {
+                Some(product) => product,
+                None => match linear_terms.next() {
+                    Some((c, x)) => (c, x, one),
+                    None => break,
+                },
+            };
+            let (c_1, addend) = match acc {
+                Some(acc) => (F::ONE, acc),
+                None => linear_terms.next().unwrap_or((F::ZERO, zero)),
+            };
+            acc = Some(self.arithmetic(c_0, c_1, a, b, addend));
+        }
+        acc.unwrap_or(zero)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_materialize() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let [x, y, z, w] = builder.add_virtual_target_arr();
+
+        let e = Expr::from(x) * y + 3 * Expr::from(z) - w;
+        let e = builder.materialize(&e);
+        // Against 4 operations with `mul`, `mul_const`, `add` and `sub`.
+        assert_eq!(builder.base_arithmetic_results.len(), 2);
+
+        // `(x + 1) * (y - x) + x * y * z - 5 = x * y - x^2 + y - x + x * y * z - 5`.
+        let f = (Expr::from(x) + F::ONE) * (Expr::from(y) - x) + Expr::from(x) * y * z
+            - F::from_canonical_u64(5);
+        let f = builder.materialize(&f);
+        builder.register_public_inputs(&[e, f]);
+
+        // Expressions which cancel out, or are a single target, don't need any operation.
+        let zero = builder.materialize(&(Expr::from(x) * y - Expr::from(y) * x));
+        assert_eq!(zero, builder.zero());
+        assert_eq!(builder.materialize(&(Expr::from(x) + z - z)), x);
+
+        let data = builder.build::<C>();
+        let [x_val, y_val, z_val, w_val] = F::rand_array();
+        let mut pw = PartialWitness::new();
+        pw.set_target_arr(&[x, y, z, w], &[x_val, y_val, z_val, w_val])?;
+        let proof = data.prove(pw)?;
+        assert_eq!(
+            proof.public_inputs,
+            [
+                x_val * y_val + F::from_canonical_u64(3) * z_val - w_val,
+                (x_val + F::ONE) * (y_val - x_val) + x_val * y_val * z_val
+                    - F::from_canonical_u64(5),
+            ]
+        );
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
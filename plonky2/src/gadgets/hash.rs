@@ -1,6 +1,6 @@
 use crate::field::extension::Extendable;
 use crate::hash::hash_types::RichField;
-use crate::iop::target::BoolTarget;
+use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::AlgebraicHasher;
 
@@ -23,4 +23,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) -> H::AlgebraicPermutation {
         H::permute_swapped(inputs, swap, self)
     }
+
+    /// Apply a cryptographic permutation, overwrite the first elements of the state with `block`,
+    /// then apply the permutation again.
+    pub fn permute_absorb_permute<H: AlgebraicHasher<F>>(
+        &mut self,
+        inputs: H::AlgebraicPermutation,
+        block: &[Target],
+    ) -> H::AlgebraicPermutation {
+        H::permute_absorb_permute(inputs, block, self)
+    }
 }
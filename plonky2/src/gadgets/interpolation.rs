@@ -4,6 +4,7 @@ use alloc::vec;
 use plonky2_field::extension::Extendable;
 
 use crate::gates::coset_interpolation::CosetInterpolationGate;
+use crate::gates::coset_interpolation_chunk::CosetInterpolationChunkGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
@@ -35,6 +36,63 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         eval
     }
+
+    /// Like `interpolate_coset`, but splits the interpolation across several
+    /// `CosetInterpolationChunkGate`s, so that subgroups too large to fit in the routed wires of a
+    /// single `CosetInterpolationGate` can be handled.
+    pub(crate) fn interpolate_coset_chunked(
+        &mut self,
+        subgroup_bits: usize,
+        coset_shift: Target,
+        values: &[ExtensionTarget<D>],
+        evaluation_point: ExtensionTarget<D>,
+    ) -> ExtensionTarget<D> {
+        let num_points = 1 << subgroup_bits;
+        debug_assert_eq!(values.len(), num_points);
+        let max_chunk_len = CosetInterpolationChunkGate::<F, D>::max_chunk_len(&self.config);
+        assert!(
+            max_chunk_len > 0,
+            "Not enough wires for CosetInterpolationChunkGate"
+        );
+        let num_chunks = num_points.div_ceil(max_chunk_len);
+        let chunk_len = num_points.div_ceil(num_chunks);
+
+        let shift_inv = self.inverse(coset_shift);
+        let shifted_evaluation_point = self.scalar_mul_ext(shift_inv, evaluation_point);
+
+        let mut eval = self.zero_extension();
+        let mut prod = self.one_extension();
+        for (i, chunk) in values.chunks(chunk_len).enumerate() {
+            let gate = CosetInterpolationChunkGate::<F, D>::new(
+                subgroup_bits,
+                i * chunk_len,
+                chunk.len(),
+                self.config.max_quotient_degree_factor,
+            );
+            let row = self.num_gates();
+            self.connect_extension(
+                shifted_evaluation_point,
+                ExtensionTarget::from_range(row, gate.wires_shifted_evaluation_point()),
+            );
+            self.connect_extension(
+                eval,
+                ExtensionTarget::from_range(row, gate.wires_initial_eval()),
+            );
+            self.connect_extension(
+                prod,
+                ExtensionTarget::from_range(row, gate.wires_initial_prod()),
+            );
+            for (j, &v) in chunk.iter().enumerate() {
+                self.connect_extension(v, ExtensionTarget::from_range(row, gate.wires_value(j)));
+            }
+
+            eval = ExtensionTarget::from_range(row, gate.wires_final_eval());
+            prod = ExtensionTarget::from_range(row, gate.wires_final_prod());
+            self.add_gate(gate, vec![]);
+        }
+
+        eval
+    }
 }
 
 #[cfg(test)]
@@ -111,4 +169,55 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_interpolate_chunked() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+        let config = CircuitConfig::standard_recursion_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // Too many points for a single `CosetInterpolationGate` in the standard config.
+        let subgroup_bits = 6;
+        let len = 1 << subgroup_bits;
+        let coset_shift = F::rand();
+        let g = F::primitive_root_of_unity(subgroup_bits);
+        let points = F::cyclic_subgroup_coset_known_order(g, coset_shift, len);
+        let values = FF::rand_vec(len);
+
+        let homogeneous_points = points
+            .iter()
+            .zip(values.iter())
+            .map(|(&a, &b)| (<FF as FieldExtension<D>>::from_basefield(a), b))
+            .collect::<Vec<_>>();
+
+        let true_interpolant = interpolant(&homogeneous_points);
+
+        let z = FF::rand();
+        let true_eval = true_interpolant.eval(z);
+
+        let coset_shift_target = builder.constant(coset_shift);
+        let value_targets = values
+            .iter()
+            .map(|&v| builder.constant_extension(v))
+            .collect::<Vec<_>>();
+        let zt = builder.constant_extension(z);
+
+        let eval = builder.interpolate_coset_chunked(
+            subgroup_bits,
+            coset_shift_target,
+            &value_targets,
+            zt,
+        );
+        let true_eval_target = builder.constant_extension(true_eval);
+        builder.connect_extension(eval, true_eval_target);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
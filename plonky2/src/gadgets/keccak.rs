@@ -0,0 +1,194 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// The number of bytes absorbed by each Keccak-256 permutation.
+const KECCAK_RATE_BYTES: usize = 136;
+
+/// The rotation offsets of the `rho` step, indexed by `x` then `y`.
+const ROTATION_OFFSETS: [[usize; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// The round constants of the `iota` step.
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808A,
+    0x8000000080008000,
+    0x000000000000808B,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008A,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000A,
+    0x000000008000808B,
+    0x800000000000008B,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800A,
+    0x800000008000000A,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// A 64-bit Keccak lane, as little-endian bits.
+type Lane = [BoolTarget; 64];
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the Keccak-256 hash of `input`, as used by Ethereum, i.e. with the original
+    /// Keccak padding rather than the SHA-3 one. The input and output are bytes; input bytes are
+    /// range-checked.
+    pub fn keccak256(&mut self, input: &[Target]) -> [Target; 32] {
+        let bits = input
+            .iter()
+            .flat_map(|&byte| self.split_le(byte, 8))
+            .collect::<Vec<_>>();
+        let hash = self.keccak256_bits(&bits);
+        core::array::from_fn(|i| self.le_sum(hash[8 * i..8 * (i + 1)].iter()))
+    }
+
+    /// Like [`Self::keccak256`], but on bits: each byte is given by its 8 little-endian bits.
+    pub fn keccak256_bits(&mut self, input: &[BoolTarget]) -> [BoolTarget; 256] {
+        assert!(
+            input.len().is_multiple_of(8),
+            "The input must be made of bytes"
+        );
+        let _false = self._false();
+        let _true = self._true();
+
+        // Pad with `0x01`, zeros and `0x80`, to a multiple of the rate.
+        let num_bytes = input.len() / 8;
+        let padded_len = (num_bytes + 1).next_multiple_of(KECCAK_RATE_BYTES);
+        let mut padded = input.to_vec();
+        padded.resize(8 * padded_len, _false);
+        padded[8 * num_bytes] = _true;
+        padded[8 * padded_len - 1] = _true;
+
+        let mut state = [[_false; 64]; 25];
+        for block in padded.chunks(8 * KECCAK_RATE_BYTES) {
+            for (lane, bits) in state.iter_mut().zip(block.chunks(64)) {
+                for (bit, &input_bit) in lane.iter_mut().zip(bits) {
+                    *bit = self.xor(*bit, input_bit);
+                }
+            }
+            self.keccak_f(&mut state);
+        }
+
+        core::array::from_fn(|i| state[i / 64][i % 64])
+    }
+
+    /// The Keccak-f[1600] permutation, on lanes indexed by `x + 5 * y`.
+    fn keccak_f(&mut self, state: &mut [Lane; 25]) {
+        for round_constant in ROUND_CONSTANTS {
+            // Theta.
+            let parities: [Lane; 5] = core::array::from_fn(|x| {
+                core::array::from_fn(|z| {
+                    let column = (0..5).map(|y| state[x + 5 * y][z]).collect::<Vec<_>>();
+                    self.xor_many(&column)
+                })
+            });
+            for x in 0..5 {
+                for z in 0..64 {
+                    let d = self.xor(
+                        parities[(x + 4) % 5][z],
+                        parities[(x + 1) % 5][(z + 63) % 64],
+                    );
+                    for y in 0..5 {
+                        state[x + 5 * y][z] = self.xor(state[x + 5 * y][z], d);
+                    }
+                }
+            }
+
+            // Rho and pi.
+            let mut rotated = *state;
+            for x in 0..5 {
+                for y in 0..5 {
+                    let offset = ROTATION_OFFSETS[x][y];
+                    rotated[y + 5 * ((2 * x + 3 * y) % 5)] =
+                        core::array::from_fn(|z| state[x + 5 * y][(z + 64 - offset) % 64]);
+                }
+            }
+
+            // Chi.
+            for y in 0..5 {
+                for x in 0..5 {
+                    for z in 0..64 {
+                        let b = rotated[(x + 1) % 5 + 5 * y][z].target;
+                        let c = rotated[(x + 2) % 5 + 5 * y][z].target;
+                        // `!b & c = c - b * c`.
+                        let not_b_and_c =
+                            BoolTarget::new_unsafe(self.arithmetic(F::NEG_ONE, F::ONE, b, c, c));
+                        state[x + 5 * y][z] = self.xor(rotated[x + 5 * y][z], not_b_and_c);
+                    }
+                }
+            }
+
+            // Iota.
+            for z in (0..64).filter(|z| (round_constant >> z) & 1 == 1) {
+                state[0][z] = self.not(state[0][z]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use keccak_hash::keccak;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_keccak256() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // A constant input, and a variable one.
+        let empty = builder.keccak256(&[]);
+        let input = builder.add_virtual_targets(64);
+        let hash = builder.keccak256(&input);
+        builder.register_public_inputs(&empty);
+        builder.register_public_inputs(&hash);
+
+        let data = builder.build::<C>();
+        let input_bytes: [u8; 64] = core::array::from_fn(|_| OsRng.gen());
+        let mut pw = PartialWitness::new();
+        for (&target, &byte) in input.iter().zip(&input_bytes) {
+            pw.set_target(target, F::from_canonical_u8(byte))?;
+        }
+        let proof = data.prove(pw)?;
+
+        let expected = [keccak([]).0, keccak(input_bytes).0]
+            .concat()
+            .into_iter()
+            .map(F::from_canonical_u8)
+            .collect::<Vec<_>>();
+        assert_eq!(proof.public_inputs, expected);
+        data.verify(proof)
+    }
+}
@@ -1,5 +1,5 @@
 #[cfg(not(feature = "std"))]
-use alloc::{borrow::ToOwned, vec};
+use alloc::{borrow::ToOwned, vec, vec::Vec};
 
 use crate::field::extension::Extendable;
 use crate::gates::lookup::LookupGate;
@@ -46,20 +46,58 @@ pub const OTHER_TABLE: [u16; 256] = [
 /// This is a smaller lookup table with arbitrary values.
 pub const SMALLER_TABLE: [u16; 8] = [2, 24, 56, 100, 128, 16, 20, 49];
 
+/// A lookup table registered in a [`CircuitBuilder`]. Registering the same table again returns the
+/// same handle, so gadgets can register the tables they need without duplicating their rows.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct LookupTableHandle {
+    index: usize,
+}
+
+impl LookupTableHandle {
+    /// The index of the LUT within the builder's stored lookup tables.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
-    /// Adds a lookup table to the list of stored lookup tables `self.luts` based on a table of (input, output) pairs. It returns the index of the LUT within `self.luts`.
-    pub fn add_lookup_table_from_pairs(&mut self, table: LookupTable) -> usize {
-        self.update_luts_from_pairs(table)
+    /// Adds a lookup table to the list of stored lookup tables `self.luts` based on a table of (input, output) pairs.
+    pub fn add_lookup_table_from_pairs(&mut self, table: LookupTable) -> LookupTableHandle {
+        LookupTableHandle {
+            index: self.update_luts_from_pairs(table),
+        }
+    }
+
+    /// Adds a lookup table to the list of stored lookup tables `self.luts` based on a table, represented as a slice `&[u16]` of inputs and a slice `&[u16]` of outputs.
+    pub fn add_lookup_table_from_table(&mut self, inps: &[u16], outs: &[u16]) -> LookupTableHandle {
+        LookupTableHandle {
+            index: self.update_luts_from_table(inps, outs),
+        }
+    }
+
+    /// Adds a lookup table to the list of stored lookup tables `self.luts` based on a function,
+    /// which may be a closure, evaluated at each of `inputs`.
+    pub fn add_lookup_table_from_fn(
+        &mut self,
+        f: impl Fn(u16) -> u16,
+        inputs: &[u16],
+    ) -> LookupTableHandle {
+        LookupTableHandle {
+            index: self.update_luts_from_fn(f, inputs),
+        }
     }
 
-    /// Adds a lookup table to the list of stored lookup tables `self.luts` based on a table, represented as a slice `&[u16]` of inputs and a slice `&[u16]` of outputs. It returns the index of the LUT within `self.luts`.
-    pub fn add_lookup_table_from_table(&mut self, inps: &[u16], outs: &[u16]) -> usize {
-        self.update_luts_from_table(inps, outs)
+    /// Looks `input` up in `table`, returning the corresponding output.
+    pub fn lookup(&mut self, table: LookupTableHandle, input: Target) -> Target {
+        self.add_lookup_from_index(input, table.index)
     }
 
-    /// Adds a lookup table to the list of stored lookup tables `self.luts` based on a function. It returns the index of the LUT within `self.luts`.
-    pub fn add_lookup_table_from_fn(&mut self, f: fn(u16) -> u16, inputs: &[u16]) -> usize {
-        self.update_luts_from_fn(f, inputs)
+    /// Looks each of `inputs` up in `table`, returning the corresponding outputs.
+    pub fn lookups(&mut self, table: LookupTableHandle, inputs: &[Target]) -> Vec<Target> {
+        inputs
+            .iter()
+            .map(|&input| self.lookup(table, input))
+            .collect()
     }
 
     /// Adds a lookup (input, output) pair to the stored lookups. Takes a `Target` input and returns a `Target` output.
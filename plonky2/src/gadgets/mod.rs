@@ -4,12 +4,23 @@
 
 pub mod arithmetic;
 pub mod arithmetic_extension;
+pub mod arithmetic_u64;
+pub mod boolean;
+pub mod collections;
+pub mod commitment;
+pub mod comparison;
+pub mod division;
+pub mod expression;
 pub mod hash;
 pub mod interpolation;
+pub mod keccak;
 pub mod lookup;
+pub mod nonnative;
 pub mod polynomial;
 pub mod random_access;
 pub mod range_check;
 pub mod select;
+pub mod sha256;
+pub mod shuffle;
 pub mod split_base;
 pub mod split_join;
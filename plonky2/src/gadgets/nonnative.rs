@@ -0,0 +1,530 @@
+//! Arithmetic over a prime field other than the native one, e.g. the scalar field of BN254.
+//!
+//! Elements are represented by 16-bit limbs, range-checked with a lookup table, so that products
+//! of limbs and their sums can be computed without overflowing the native field. Each operation
+//! first computes the columns `X_k` of the unreduced integer `X = Σ X_k 2^(16 k)`, then has a
+//! generator provide the quotient `q` and remainder `r` of `X` by the order `p`, and checks that
+//! `X - q p - r = 0` as an integer, by propagating carries from one column to the next. Every
+//! [`NonNativeTarget`] is checked to be canonical, i.e. less than `p`.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use anyhow::Result;
+use num::{BigUint, Zero};
+
+use crate::field::extension::Extendable;
+use crate::field::types::{Field, PrimeField};
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+const LIMB_BITS: usize = 16;
+
+/// Columns are kept below `2^MAX_COLUMN_BITS`, so that carries fit in two limbs.
+const MAX_COLUMN_BITS: usize = 40;
+
+/// Carries are offset by this amount to be range-checked as two nonnegative limbs.
+const CARRY_OFFSET: u64 = 1 << 31;
+
+/// An element of the field `FF`, as little-endian 16-bit limbs.
+#[derive(Clone, Debug)]
+pub struct NonNativeTarget<FF: PrimeField> {
+    limbs: Vec<Target>,
+    _phantom: PhantomData<FF>,
+}
+
+impl<FF: PrimeField> NonNativeTarget<FF> {
+    /// The number of limbs of an element of `FF`.
+    pub fn num_limbs() -> usize {
+        FF::BITS.div_ceil(LIMB_BITS)
+    }
+
+    pub fn limbs(&self) -> &[Target] {
+        &self.limbs
+    }
+
+    /// Creates an element from its little-endian 16-bit limbs. The caller must ensure that the
+    /// limbs are range-checked and that they represent a canonical element.
+    pub(crate) fn from_limbs_unchecked(limbs: Vec<Target>) -> Self {
+        assert_eq!(limbs.len(), Self::num_limbs());
+        Self {
+            limbs,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Sets the limbs of this target to those of `value`.
+    pub fn set_witness<F: Field>(
+        &self,
+        witness: &mut impl WitnessWrite<F>,
+        value: FF,
+    ) -> Result<()> {
+        let limbs = biguint_to_limbs(&value.to_canonical_biguint(), self.limbs.len());
+        for (&target, limb) in self.limbs.iter().zip(limbs) {
+            witness.set_target(target, F::from_canonical_u64(limb))?;
+        }
+        Ok(())
+    }
+}
+
+fn biguint_to_limbs(x: &BigUint, num_limbs: usize) -> Vec<u64> {
+    let mut limbs = x
+        .iter_u32_digits()
+        .flat_map(|d| [d as u64 & 0xffff, d as u64 >> LIMB_BITS])
+        .collect::<Vec<_>>();
+    limbs.resize(num_limbs, 0);
+    limbs
+}
+
+fn limbs_to_biguint(limbs: &[u64]) -> BigUint {
+    limbs
+        .iter()
+        .rev()
+        .fold(BigUint::zero(), |acc, &limb| (acc << LIMB_BITS) + limb)
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Returns a canonical element of `FF`, to be set with [`NonNativeTarget::set_witness`].
+    pub fn add_virtual_nonnative_target<FF: PrimeField>(&mut self) -> NonNativeTarget<FF> {
+        let limbs = self.add_virtual_targets(NonNativeTarget::<FF>::num_limbs());
+        self.range_check_limbs(&limbs);
+        let x = NonNativeTarget {
+            limbs,
+            _phantom: PhantomData,
+        };
+        self.assert_canonical_nonnative(&x);
+        x
+    }
+
+    pub fn constant_nonnative<FF: PrimeField>(&mut self, x: FF) -> NonNativeTarget<FF> {
+        let limbs = biguint_to_limbs(
+            &x.to_canonical_biguint(),
+            NonNativeTarget::<FF>::num_limbs(),
+        )
+        .into_iter()
+        .map(|limb| self.constant(F::from_canonical_u64(limb)))
+        .collect();
+        NonNativeTarget {
+            limbs,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn connect_nonnative<FF: PrimeField>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+        y: &NonNativeTarget<FF>,
+    ) {
+        for (&l, &r) in x.limbs.iter().zip(&y.limbs) {
+            self.connect(l, r);
+        }
+    }
+
+    pub fn mul_nonnative<FF: PrimeField>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+        y: &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF> {
+        let num_columns = x.limbs.len() + y.limbs.len() - 1;
+        let mut columns = vec![self.zero(); num_columns];
+        let mut bounds = vec![0; num_columns];
+        for (i, &x_i) in x.limbs.iter().enumerate() {
+            for (j, &y_j) in y.limbs.iter().enumerate() {
+                columns[i + j] = self.mul_add(x_i, y_j, columns[i + j]);
+                bounds[i + j] += 0xffff * 0xffff;
+            }
+        }
+        self.reduce_nonnative(columns, bounds)
+    }
+
+    /// Returns `Σ c_i x_i + constant` for the pairs `(c_i, x_i)` of `terms`.
+    pub fn linear_combination_nonnative<FF: PrimeField>(
+        &mut self,
+        terms: &[(FF, &NonNativeTarget<FF>)],
+        constant: FF,
+    ) -> NonNativeTarget<FF> {
+        let num_limbs = NonNativeTarget::<FF>::num_limbs();
+        let num_columns = 2 * num_limbs - 1;
+        let constant_limbs = biguint_to_limbs(&constant.to_canonical_biguint(), num_columns);
+        let mut columns = constant_limbs
+            .iter()
+            .map(|&limb| self.constant(F::from_canonical_u64(limb)))
+            .collect::<Vec<_>>();
+        let mut bounds = constant_limbs;
+        for &(c, x) in terms {
+            let c_limbs = biguint_to_limbs(&c.to_canonical_biguint(), num_limbs);
+            for (i, &x_i) in x.limbs.iter().enumerate() {
+                for (j, &c_j) in c_limbs.iter().enumerate().filter(|(_, &c_j)| c_j != 0) {
+                    columns[i + j] =
+                        self.mul_const_add(F::from_canonical_u64(c_j), x_i, columns[i + j]);
+                    bounds[i + j] += c_j * 0xffff;
+                }
+            }
+        }
+        self.reduce_nonnative(columns, bounds)
+    }
+
+    /// Returns the canonical element congruent to the integer `Σ columns[k] 2^(16 k)`, where
+    /// `columns[k]` holds an integer at most `bounds[k]`.
+    fn reduce_nonnative<FF: PrimeField>(
+        &mut self,
+        columns: Vec<Target>,
+        bounds: Vec<u64>,
+    ) -> NonNativeTarget<FF> {
+        assert!(
+            bounds.iter().all(|&b| b < 1 << MAX_COLUMN_BITS),
+            "Non-native columns may overflow"
+        );
+        let max_value = limbs_to_biguint(&bounds);
+        let max_quotient = max_value / FF::order();
+        let num_quotient_limbs = (max_quotient.bits() as usize).div_ceil(LIMB_BITS);
+
+        let num_limbs = NonNativeTarget::<FF>::num_limbs();
+        let quotient = self.add_virtual_targets(num_quotient_limbs);
+        let remainder = self.add_virtual_targets(num_limbs);
+        self.add_simple_generator(NonNativeReductionGenerator::<FF> {
+            columns: columns.clone(),
+            quotient: quotient.clone(),
+            remainder: remainder.clone(),
+            _phantom: PhantomData,
+        });
+        self.range_check_limbs(&quotient);
+        self.range_check_limbs(&remainder);
+
+        // The columns of `X - q p - r`.
+        let order_limbs = biguint_to_limbs(&FF::order(), num_limbs);
+        let num_columns = columns
+            .len()
+            .max(num_quotient_limbs + num_limbs - 1)
+            .max(num_limbs);
+        let mut differences = columns;
+        differences.resize(num_columns, self.zero());
+        for (i, &q_i) in quotient.iter().enumerate() {
+            for (j, &p_j) in order_limbs.iter().enumerate().filter(|(_, &p_j)| p_j != 0) {
+                differences[i + j] =
+                    self.mul_const_add(-F::from_canonical_u64(p_j), q_i, differences[i + j]);
+            }
+        }
+        for (k, &r_k) in remainder.iter().enumerate() {
+            differences[k] = self.sub(differences[k], r_k);
+        }
+        self.assert_zero_limbs(differences);
+
+        let x = NonNativeTarget {
+            limbs: remainder,
+            _phantom: PhantomData,
+        };
+        self.assert_canonical_nonnative(&x);
+        x
+    }
+
+    /// Checks that `x < p`, by checking that `x + d = p - 1` for some `d` with 16-bit limbs.
+    fn assert_canonical_nonnative<FF: PrimeField>(&mut self, x: &NonNativeTarget<FF>) {
+        let num_limbs = x.limbs.len();
+        let complement = self.add_virtual_targets(num_limbs);
+        self.add_simple_generator(NonNativeComplementGenerator::<FF> {
+            x: x.limbs.clone(),
+            complement: complement.clone(),
+            _phantom: PhantomData,
+        });
+        self.range_check_limbs(&complement);
+
+        let max_limbs = biguint_to_limbs(&(FF::order() - 1u32), num_limbs);
+        let differences = (0..num_limbs)
+            .map(|k| {
+                let sum = self.add(x.limbs[k], complement[k]);
+                self.add_const(sum, -F::from_canonical_u64(max_limbs[k]))
+            })
+            .collect();
+        self.assert_zero_limbs(differences);
+    }
+
+    /// Checks that `Σ columns[k] 2^(16 k) = 0` as an integer, where each column holds an integer
+    /// less than `2^47` in absolute value. The carry out of each column is offset and split into
+    /// two limbs, so that its range check is sound.
+    fn assert_zero_limbs(&mut self, columns: Vec<Target>) {
+        let num_carries = columns.len() - 1;
+        let carry_limbs = self.add_virtual_targets(2 * num_carries);
+        self.add_simple_generator(NonNativeCarryGenerator {
+            columns: columns.clone(),
+            carry_limbs: carry_limbs.clone(),
+        });
+        self.range_check_limbs(&carry_limbs);
+
+        let base = F::from_canonical_u64(1 << LIMB_BITS);
+        let offset = F::from_canonical_u64(CARRY_OFFSET);
+        let mut carry_in = None;
+        for (k, column) in columns.into_iter().enumerate() {
+            let mut sum = match carry_in {
+                Some(carry) => self.add(column, carry),
+                None => column,
+            };
+            if k < num_carries {
+                let carry = self.mul_const_add(base, carry_limbs[2 * k + 1], carry_limbs[2 * k]);
+                let carry = self.add_const(carry, -offset);
+                sum = self.mul_const_add(-base, carry, sum);
+                carry_in = Some(carry);
+            }
+            self.assert_zero(sum);
+        }
+    }
+
+    /// Checks that each of `limbs` is less than `2^16`, with a lookup in the identity table.
+    fn range_check_limbs(&mut self, limbs: &[Target]) {
+        if limbs.is_empty() {
+            return;
+        }
+        let inputs = (0..=u16::MAX).collect::<Vec<_>>();
+        let lut = self.add_lookup_table_from_fn(|x| x, &inputs);
+        for &limb in limbs {
+            self.lookup(lut, limb);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NonNativeReductionGenerator<FF: PrimeField> {
+    columns: Vec<Target>,
+    quotient: Vec<Target>,
+    remainder: Vec<Target>,
+    _phantom: PhantomData<FF>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize, FF: PrimeField> SimpleGenerator<F, D>
+    for NonNativeReductionGenerator<FF>
+{
+    fn id(&self) -> String {
+        "NonNativeReductionGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.columns.clone()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let columns = witness
+            .get_targets(&self.columns)
+            .into_iter()
+            .map(|x| x.to_canonical_u64())
+            .collect::<Vec<_>>();
+        let value = limbs_to_biguint(&columns);
+        let order = FF::order();
+
+        let quotient = biguint_to_limbs(&(&value / &order), self.quotient.len());
+        let remainder = biguint_to_limbs(&(value % order), self.remainder.len());
+        for (&target, limb) in self.quotient.iter().zip(quotient) {
+            out_buffer.set_target(target, F::from_canonical_u64(limb))?;
+        }
+        for (&target, limb) in self.remainder.iter().zip(remainder) {
+            out_buffer.set_target(target, F::from_canonical_u64(limb))?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.columns)?;
+        dst.write_target_vec(&self.quotient)?;
+        dst.write_target_vec(&self.remainder)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let columns = src.read_target_vec()?;
+        let quotient = src.read_target_vec()?;
+        let remainder = src.read_target_vec()?;
+        Ok(Self {
+            columns,
+            quotient,
+            remainder,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NonNativeComplementGenerator<FF: PrimeField> {
+    x: Vec<Target>,
+    complement: Vec<Target>,
+    _phantom: PhantomData<FF>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize, FF: PrimeField> SimpleGenerator<F, D>
+    for NonNativeComplementGenerator<FF>
+{
+    fn id(&self) -> String {
+        "NonNativeComplementGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.x.clone()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let x = witness
+            .get_targets(&self.x)
+            .into_iter()
+            .map(|x| x.to_canonical_u64())
+            .collect::<Vec<_>>();
+        let x = limbs_to_biguint(&x);
+        // A non-canonical `x` has no valid complement; the proof will then fail.
+        let max = FF::order() - 1u32;
+        let complement = if x <= max { max - x } else { BigUint::zero() };
+
+        let complement = biguint_to_limbs(&complement, self.complement.len());
+        for (&target, limb) in self.complement.iter().zip(complement) {
+            out_buffer.set_target(target, F::from_canonical_u64(limb))?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.x)?;
+        dst.write_target_vec(&self.complement)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let x = src.read_target_vec()?;
+        let complement = src.read_target_vec()?;
+        Ok(Self {
+            x,
+            complement,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NonNativeCarryGenerator {
+    columns: Vec<Target>,
+    /// The low and high limbs of each offset carry.
+    carry_limbs: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for NonNativeCarryGenerator
+{
+    fn id(&self) -> String {
+        "NonNativeCarryGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.columns.clone()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let mut carry = 0i128;
+        for (k, limbs) in self.carry_limbs.chunks(2).enumerate() {
+            // Columns hold small integers of either sign.
+            let column = witness.get_target(self.columns[k]).to_canonical_u64();
+            let column = if column > F::ORDER / 2 {
+                column as i128 - F::ORDER as i128
+            } else {
+                column as i128
+            };
+            carry = (column + carry) >> LIMB_BITS;
+
+            let offset_carry = (carry + CARRY_OFFSET as i128) as u64;
+            out_buffer.set_target(limbs[0], F::from_canonical_u64(offset_carry & 0xffff))?;
+            out_buffer.set_target(
+                limbs[1],
+                F::from_noncanonical_u64(offset_carry >> LIMB_BITS),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.columns)?;
+        dst.write_target_vec(&self.carry_limbs)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let columns = src.read_target_vec()?;
+        let carry_limbs = src.read_target_vec()?;
+        Ok(Self {
+            columns,
+            carry_limbs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::bn254_scalar::Bn254Scalar;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type FF = Bn254Scalar;
+
+    #[test]
+    fn test_nonnative_arithmetic() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_nonnative_target::<FF>();
+        let y = builder.add_virtual_nonnative_target::<FF>();
+
+        let c = FF::rand();
+        let d = FF::rand();
+        let product = builder.mul_nonnative(&x, &y);
+        let combination = builder.linear_combination_nonnative(&[(c, &x), (-FF::ONE, &y)], d);
+        let x_val = FF::rand();
+        let y_val = FF::rand();
+        let expected_product = builder.constant_nonnative(x_val * y_val);
+        let expected_combination = builder.constant_nonnative(c * x_val - y_val + d);
+        builder.connect_nonnative(&product, &expected_product);
+        builder.connect_nonnative(&combination, &expected_combination);
+
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        x.set_witness(&mut pw, x_val)?;
+        y.set_witness(&mut pw, y_val)?;
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_nonnative_rejects_noncanonical() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_nonnative_target::<FF>();
+
+        let data = builder.build::<C>();
+        // `p` itself fits in the limbs, but isn't a canonical element.
+        let order = biguint_to_limbs(&FF::order(), x.limbs.len());
+        let mut pw = PartialWitness::new();
+        for (&target, limb) in x.limbs.iter().zip(order) {
+            pw.set_target(target, F::from_canonical_u64(limb)).unwrap();
+        }
+        assert!(data.prove(pw).is_err());
+    }
+}
@@ -1,4 +1,8 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
 use crate::field::extension::Extendable;
+use crate::gates::select::SelectGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::{BoolTarget, Target};
@@ -34,6 +38,52 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let tmp = self.mul_sub(b.target, y, y);
         self.mul_sub(b.target, x, tmp)
     }
+
+    /// Like `select`, but performs the selection in a [`SelectGate`], which packs several
+    /// selections per row. This is cheaper than `select` in circuits with many selections, but
+    /// adds a gate type to the circuit.
+    pub fn select_packed(&mut self, b: BoolTarget, x: Target, y: Target) -> Target {
+        if let Some(b) = self.target_as_constant(b.target) {
+            return if b.is_one() { x } else { y };
+        }
+        if x == y {
+            return x;
+        }
+
+        let gate = SelectGate::new_from_config(&self.config);
+        let (row, i) = self.find_slot(gate, &[], &[]);
+        self.connect(
+            b.target,
+            Target::wire(row, SelectGate::wire_ith_condition(i)),
+        );
+        self.connect(x, Target::wire(row, SelectGate::wire_ith_x(i)));
+        self.connect(y, Target::wire(row, SelectGate::wire_ith_y(i)));
+        Target::wire(row, SelectGate::wire_ith_output(i))
+    }
+
+    /// Selects `xs` or `ys` element-wise based on `b`, i.e., this returns
+    /// `if b { xs } else { ys }`.
+    pub fn select_vec(&mut self, b: BoolTarget, xs: &[Target], ys: &[Target]) -> Vec<Target> {
+        assert_eq!(xs.len(), ys.len(), "Vectors must have the same length");
+        xs.iter()
+            .zip(ys)
+            .map(|(&x, &y)| self.select(b, x, y))
+            .collect()
+    }
+
+    /// Like `select_vec`, but uses `select_packed`.
+    pub fn select_vec_packed(
+        &mut self,
+        b: BoolTarget,
+        xs: &[Target],
+        ys: &[Target],
+    ) -> Vec<Target> {
+        assert_eq!(xs.len(), ys.len(), "Vectors must have the same length");
+        xs.iter()
+            .zip(ys)
+            .map(|(&x, &y)| self.select_packed(b, x, y))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -41,6 +91,8 @@ mod tests {
     use anyhow::Result;
 
     use crate::field::types::Sample;
+    use crate::gates::gate::Gate;
+    use crate::gates::select::SelectGate;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
@@ -77,4 +129,47 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_select_vec_packed() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let len = 30;
+        let (xs, ys) = (F::rand_vec(len), F::rand_vec(len));
+        let xts = builder.add_virtual_targets(len);
+        let yts = builder.add_virtual_targets(len);
+        let bt = builder.add_virtual_bool_target_safe();
+        let not_bt = builder.not(bt);
+
+        pw.set_target_arr(&xts, &xs)?;
+        pw.set_target_arr(&yts, &ys)?;
+        pw.set_bool_target(bt, true)?;
+
+        let should_be_xs = builder.select_vec_packed(bt, &xts, &yts);
+        let should_be_ys = builder.select_vec_packed(not_bt, &xts, &yts);
+        for i in 0..len {
+            builder.connect(should_be_xs[i], xts[i]);
+            builder.connect(should_be_ys[i], yts[i]);
+        }
+
+        // All selections are packed into shared `SelectGate` rows.
+        let gate = SelectGate::new_from_config(&builder.config);
+        let select_id = <SelectGate as Gate<F, D>>::id(&gate);
+        let select_rows = builder
+            .gate_instances
+            .iter()
+            .filter(|g| g.gate_ref.0.id() == select_id)
+            .count();
+        assert_eq!(select_rows, (2 * len).div_ceil(gate.num_ops));
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
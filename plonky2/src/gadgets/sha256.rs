@@ -0,0 +1,282 @@
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::{BoolTarget, Target};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::util::log2_ceil;
+
+/// The initial hash value.
+const INITIAL_HASH: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The round constants.
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// A 32-bit word, as little-endian bits.
+type Word = [BoolTarget; 32];
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Computes the SHA-256 hash of `input`. The input and output are bytes; input bytes are
+    /// range-checked.
+    pub fn sha256(&mut self, input: &[Target]) -> [Target; 32] {
+        let bits = input
+            .iter()
+            .flat_map(|&byte| self.split_le(byte, 8))
+            .collect::<Vec<_>>();
+        let hash = self.sha256_bits(&bits);
+        core::array::from_fn(|i| self.le_sum(hash[8 * i..8 * (i + 1)].iter()))
+    }
+
+    /// Like [`Self::sha256`], but on bits: each byte is given by its 8 little-endian bits.
+    pub fn sha256_bits(&mut self, input: &[BoolTarget]) -> [BoolTarget; 256] {
+        assert!(
+            input.len().is_multiple_of(8),
+            "The input must be made of bytes"
+        );
+
+        // Pad with `0x80`, zeros and the big-endian length in bits, to a multiple of 64 bytes.
+        let num_bytes = input.len() / 8;
+        let padded_len = (num_bytes + 9).next_multiple_of(64);
+        let mut padded = input.to_vec();
+        padded.extend(self.constant_bytes_bits(&[0x80]));
+        let zeros = self.constant_bytes_bits(&vec![0; padded_len - num_bytes - 9]);
+        padded.extend(zeros);
+        padded.extend(self.constant_bytes_bits(&(8 * num_bytes as u64).to_be_bytes()));
+
+        let mut state = INITIAL_HASH.map(|h| self.constant_word(h));
+        for block in padded.chunks(512) {
+            let words = core::array::from_fn(|i| bytes_bits_to_word(&block[32 * i..32 * (i + 1)]));
+            state = self.sha256_compress(state, words);
+        }
+
+        core::array::from_fn(|i| {
+            let word = &state[i / 32];
+            let byte = (i % 32) / 8;
+            word[8 * (3 - byte) + i % 8]
+        })
+    }
+
+    /// The SHA-256 compression function.
+    fn sha256_compress(&mut self, state: [Word; 8], block: [Word; 16]) -> [Word; 8] {
+        let mut schedule = block.to_vec();
+        for i in 16..64 {
+            let shifted = self.shift_right(&schedule[i - 15], 3);
+            let s0 = self.xor_words(&[
+                rotate_right(&schedule[i - 15], 7),
+                rotate_right(&schedule[i - 15], 18),
+                shifted,
+            ]);
+            let shifted = self.shift_right(&schedule[i - 2], 10);
+            let s1 = self.xor_words(&[
+                rotate_right(&schedule[i - 2], 17),
+                rotate_right(&schedule[i - 2], 19),
+                shifted,
+            ]);
+            let w = self.add_words(&[schedule[i - 16], s0, schedule[i - 7], s1], 0);
+            schedule.push(w);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (&w, round_constant) in schedule.iter().zip(ROUND_CONSTANTS) {
+            let sigma_1 = self.xor_words(&[
+                rotate_right(&e, 6),
+                rotate_right(&e, 11),
+                rotate_right(&e, 25),
+            ]);
+            // `ch = (e & f) ^ (!e & g) = g + e * (f - g)`.
+            let ch = core::array::from_fn(|i| {
+                let f_minus_g = self.sub(f[i].target, g[i].target);
+                BoolTarget::new_unsafe(self.mul_add(e[i].target, f_minus_g, g[i].target))
+            });
+            let temp_1 = self.add_words(&[h, sigma_1, ch, w], round_constant);
+
+            let sigma_0 = self.xor_words(&[
+                rotate_right(&a, 2),
+                rotate_right(&a, 13),
+                rotate_right(&a, 22),
+            ]);
+            // `maj = (a & b) ^ (a & c) ^ (b & c) = a * b + c * (a ^ b)`.
+            let maj = core::array::from_fn(|i| {
+                let a_and_b = self.and(a[i], b[i]);
+                let a_xor_b = self.xor(a[i], b[i]);
+                BoolTarget::new_unsafe(self.mul_add(c[i].target, a_xor_b.target, a_and_b.target))
+            });
+            let temp_2 = self.add_words(&[sigma_0, maj], 0);
+
+            h = g;
+            g = f;
+            f = e;
+            e = self.add_words(&[d, temp_1], 0);
+            d = c;
+            c = b;
+            b = a;
+            a = self.add_words(&[temp_1, temp_2], 0);
+        }
+
+        let working = [a, b, c, d, e, f, g, h];
+        core::array::from_fn(|i| self.add_words(&[state[i], working[i]], 0))
+    }
+
+    /// Returns the little-endian bits of the given bytes, as constants.
+    fn constant_bytes_bits(&mut self, bytes: &[u8]) -> Vec<BoolTarget> {
+        bytes
+            .iter()
+            .flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+            .map(|bit| self.constant_bool(bit))
+            .collect()
+    }
+
+    fn constant_word(&mut self, word: u32) -> Word {
+        core::array::from_fn(|i| self.constant_bool((word >> i) & 1 == 1))
+    }
+
+    fn shift_right(&mut self, word: &Word, n: usize) -> Word {
+        let _false = self._false();
+        core::array::from_fn(|i| word.get(i + n).copied().unwrap_or(_false))
+    }
+
+    fn xor_words(&mut self, words: &[Word]) -> Word {
+        core::array::from_fn(|i| {
+            let bits = words.iter().map(|word| word[i]).collect::<Vec<_>>();
+            self.xor_many(&bits)
+        })
+    }
+
+    /// Returns the sum of the given words and of `constant`, modulo `2^32`.
+    fn add_words(&mut self, words: &[Word], constant: u32) -> Word {
+        let mut sum = self.constant(F::from_canonical_u32(constant));
+        for word in words {
+            let value = self.le_sum(word.iter());
+            sum = self.add(sum, value);
+        }
+        let num_bits = 32 + log2_ceil(words.len() + 1);
+        let bits = self.split_le(sum, num_bits);
+        core::array::from_fn(|i| bits[i])
+    }
+}
+
+fn rotate_right(word: &Word, n: usize) -> Word {
+    core::array::from_fn(|i| word[(i + n) % 32])
+}
+
+/// Converts 4 bytes, given by their little-endian bits, into a big-endian word.
+fn bytes_bits_to_word(bits: &[BoolTarget]) -> Word {
+    core::array::from_fn(|i| bits[8 * (3 - i / 8) + i % 8])
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::util::encoding::from_hex;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// A native SHA-256, to compute expected values in tests.
+    pub(crate) fn sha256(input: &[u8]) -> [u8; 32] {
+        let mut padded = input.to_vec();
+        padded.push(0x80);
+        padded.resize((input.len() + 9).next_multiple_of(64) - 8, 0);
+        padded.extend((8 * input.len() as u64).to_be_bytes());
+
+        let mut state = INITIAL_HASH;
+        for block in padded.chunks(64) {
+            let mut w = block
+                .chunks(4)
+                .map(|word| u32::from_be_bytes(word.try_into().unwrap()))
+                .collect::<Vec<_>>();
+            for i in 16..64 {
+                let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+                let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+                w.push(
+                    w[i - 16]
+                        .wrapping_add(s0)
+                        .wrapping_add(w[i - 7])
+                        .wrapping_add(s1),
+                );
+            }
+            let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+            for i in 0..64 {
+                let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+                let ch = (e & f) ^ (!e & g);
+                let temp_1 = h
+                    .wrapping_add(s1)
+                    .wrapping_add(ch)
+                    .wrapping_add(ROUND_CONSTANTS[i])
+                    .wrapping_add(w[i]);
+                let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+                let maj = (a & b) ^ (a & c) ^ (b & c);
+                let temp_2 = s0.wrapping_add(maj);
+                h = g;
+                g = f;
+                f = e;
+                e = d.wrapping_add(temp_1);
+                d = c;
+                c = b;
+                b = a;
+                a = temp_1.wrapping_add(temp_2);
+            }
+            for (s, x) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+                *s = s.wrapping_add(x);
+            }
+        }
+        core::array::from_fn(|i| state[i / 4].to_be_bytes()[i % 4])
+    }
+
+    #[test]
+    fn test_native_sha256() -> Result<()> {
+        assert_eq!(
+            sha256(b"abc").to_vec(),
+            from_hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")?
+        );
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq").to_vec(),
+            from_hex("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sha256() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // A two-block input.
+        let input = builder.add_virtual_targets(64);
+        let hash = builder.sha256(&input);
+        builder.register_public_inputs(&hash);
+
+        let data = builder.build::<C>();
+        let input_bytes: [u8; 64] = core::array::from_fn(|_| OsRng.gen());
+        let mut pw = PartialWitness::new();
+        for (&target, &byte) in input.iter().zip(&input_bytes) {
+            pw.set_target(target, F::from_canonical_u8(byte))?;
+        }
+        let proof = data.prove(pw)?;
+
+        let expected = sha256(&input_bytes).map(F::from_canonical_u8);
+        assert_eq!(proof.public_inputs, expected);
+        data.verify(proof)
+    }
+}
@@ -0,0 +1,176 @@
+//! Verifiable shuffles, i.e. proofs that some targets are a permutation of others, without
+//! revealing the permutation.
+//!
+//! The check is the randomized permutation argument: `outputs` is a permutation of `inputs` iff
+//! the polynomials `prod_i (X - inputs[i])` and `prod_i (X - outputs[i])` are equal, which is
+//! tested at a challenge derived by hashing all the inputs and outputs. The challenge lies in the
+//! extension field, so a false claim passes with probability at most `n / |F^D|`.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use anyhow::{ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::AlgebraicHasher;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Asserts that `outputs` is a permutation of `inputs`.
+    ///
+    /// `permutation_witness` holds the indices of the permutation, with
+    /// `outputs[i] = inputs[permutation_witness[i]]`, and is used to generate the outputs which
+    /// aren't set otherwise. It is left unconstrained, so it stays private.
+    pub fn assert_shuffle<H: AlgebraicHasher<F>>(
+        &mut self,
+        inputs: &[Target],
+        outputs: &[Target],
+        permutation_witness: &[Target],
+    ) {
+        assert_eq!(inputs.len(), outputs.len());
+        assert_eq!(inputs.len(), permutation_witness.len());
+
+        self.add_simple_generator(ShuffleGenerator {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            permutation: permutation_witness.to_vec(),
+        });
+
+        let challenge = self.hash_n_to_m_no_pad::<H>([inputs, outputs].concat(), D);
+        let challenge = ExtensionTarget(challenge.try_into().unwrap());
+        let inputs_product = self.shuffle_product(challenge, inputs);
+        let outputs_product = self.shuffle_product(challenge, outputs);
+        self.connect_extension(inputs_product, outputs_product);
+    }
+
+    /// Evaluates `prod_i (challenge - xs[i])`.
+    fn shuffle_product(
+        &mut self,
+        challenge: ExtensionTarget<D>,
+        xs: &[Target],
+    ) -> ExtensionTarget<D> {
+        let terms = xs
+            .iter()
+            .map(|&x| {
+                let x = self.convert_to_ext(x);
+                self.sub_extension(challenge, x)
+            })
+            .collect::<Vec<_>>();
+        self.mul_many_extension(terms)
+    }
+}
+
+/// Sets the outputs of a shuffle which aren't set yet from its inputs and permutation.
+#[derive(Debug, Default)]
+pub struct ShuffleGenerator {
+    inputs: Vec<Target>,
+    outputs: Vec<Target>,
+    permutation: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for ShuffleGenerator {
+    fn id(&self) -> String {
+        "ShuffleGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        [self.inputs.as_slice(), &self.permutation].concat()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let n = self.inputs.len();
+        let mut used = vec![false; n];
+        for (&output, &index) in self.outputs.iter().zip(&self.permutation) {
+            let index = witness.get_target(index).to_canonical_u64() as usize;
+            ensure!(
+                index < n && !used[index],
+                "The shuffle witness is not a permutation"
+            );
+            used[index] = true;
+            if witness.try_get_target(output).is_none() {
+                out_buffer.set_target(output, witness.get_target(self.inputs[index]))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.inputs)?;
+        dst.write_target_vec(&self.outputs)?;
+        dst.write_target_vec(&self.permutation)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let inputs = src.read_target_vec()?;
+        let outputs = src.read_target_vec()?;
+        let permutation = src.read_target_vec()?;
+        Ok(Self {
+            inputs,
+            outputs,
+            permutation,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    #[test]
+    fn test_shuffle() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let inputs = builder.add_virtual_targets(5);
+        let outputs = builder.add_virtual_targets(5);
+        let permutation = builder.add_virtual_targets(5);
+        builder.assert_shuffle::<H>(&inputs, &outputs, &permutation);
+        builder.register_public_inputs(&inputs);
+        builder.register_public_inputs(&outputs);
+        let data = builder.build::<C>();
+
+        let values = [3, 1, 4, 1, 5].map(F::from_canonical_u64);
+        let prove = |sigma: [u64; 5], outputs_override: Option<[u64; 5]>| {
+            let mut pw = PartialWitness::new();
+            pw.set_target_arr(&inputs, &values)?;
+            pw.set_target_arr(&permutation, &sigma.map(F::from_canonical_u64))?;
+            if let Some(overridden) = outputs_override {
+                pw.set_target_arr(&outputs, &overridden.map(F::from_canonical_u64))?;
+            }
+            data.prove(pw)
+        };
+
+        let proof = prove([4, 2, 0, 3, 1], None)?;
+        let expected = [5, 4, 3, 1, 1].map(F::from_canonical_u64);
+        assert_eq!(proof.public_inputs[5..], expected);
+        data.verify(proof)?;
+
+        // Not a permutation.
+        assert!(prove([4, 2, 0, 3, 3], None).is_err());
+        // Outputs which aren't a permutation of the inputs.
+        assert!(prove([0, 1, 2, 3, 4], Some([3, 1, 4, 1, 6])).is_err());
+        Ok(())
+    }
+}
@@ -233,10 +233,15 @@ impl<F: RichField + Extendable<D>, const B: usize, const D: usize> SimpleGenerat
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
 
     use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Field;
     use crate::gates::base_sum::BaseSumGate;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{
+        test_eval_fns, test_generated_witness_with_inputs, test_low_degree,
+    };
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
     #[test]
@@ -251,4 +256,15 @@ mod tests {
         type F = <C as GenericConfig<D>>::F;
         test_eval_fns::<F, C, _, D>(BaseSumGate::<6>::new(11))
     }
+
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        // The sum must have 11 limbs in base 6.
+        test_generated_witness_with_inputs::<F, C, _, D>(BaseSumGate::<6>::new(11), |_| {
+            F::from_canonical_u64(OsRng.gen_range(0..6u64.pow(11)))
+        })
+    }
 }
@@ -0,0 +1,453 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops::Range;
+
+use anyhow::Result;
+
+use crate::field::extension::Extendable;
+use crate::field::packed::PackedField;
+use crate::field::types::Field;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use crate::plonk::plonk_common::{reduce_with_powers, reduce_with_powers_ext_circuit};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+use crate::util::log_floor;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// A gate which computes `result = a < b` for two inputs `a`, `b` of at most `num_bits` bits. If
+/// the config has enough wires, it can support several such comparisons in one gate.
+///
+/// Both inputs are range-checked by decomposing them into chunks of `chunk_bits` bits. The
+/// comparison itself is proven by decomposing `a - b + result * 2^num_bits` in the same way, which
+/// only fits in `num_bits` bits if `result` is set exactly when `a < b`. An all-zero row satisfies
+/// the constraints, so unused operations in a partially filled gate need no special handling.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ComparisonGate {
+    pub num_bits: usize,
+    pub chunk_bits: usize,
+    /// Number of comparisons performed by the gate.
+    pub num_ops: usize,
+}
+
+impl ComparisonGate {
+    pub const fn new(num_bits: usize, chunk_bits: usize, num_ops: usize) -> Self {
+        assert!(num_bits > 0 && num_bits < 63, "unsupported number of bits");
+        assert!(chunk_bits > 0);
+        Self {
+            num_bits,
+            chunk_bits,
+            num_ops,
+        }
+    }
+
+    /// Uses the largest chunks whose range checks fit in the config's degree bound, and as many
+    /// comparisons as fit in the config's wires.
+    pub fn new_from_config(config: &CircuitConfig, num_bits: usize) -> Self {
+        let chunk_bits = log_floor(config.max_quotient_degree_factor as u64, 2) as usize;
+        let gate = Self::new(num_bits, chunk_bits, 1);
+        let num_ops = (config.num_routed_wires / Self::ROUTED_WIRES_PER_OP)
+            .min(config.num_wires / gate.wires_per_op());
+        Self { num_ops, ..gate }
+    }
+
+    const ROUTED_WIRES_PER_OP: usize = 3;
+
+    /// Number of chunks used to decompose a `num_bits`-bit value.
+    pub const fn num_chunks(&self) -> usize {
+        self.num_bits.div_ceil(self.chunk_bits)
+    }
+
+    /// Number of bits of the `i`th chunk. All chunks have `chunk_bits` bits, except perhaps the
+    /// most significant one.
+    const fn chunk_size_bits(&self, i: usize) -> usize {
+        if i + 1 < self.num_chunks() {
+            self.chunk_bits
+        } else {
+            self.num_bits - self.chunk_bits * (self.num_chunks() - 1)
+        }
+    }
+
+    const fn wires_per_op(&self) -> usize {
+        Self::ROUTED_WIRES_PER_OP + 3 * self.num_chunks()
+    }
+
+    pub(crate) const fn wire_ith_a(i: usize) -> usize {
+        Self::ROUTED_WIRES_PER_OP * i
+    }
+    pub(crate) const fn wire_ith_b(i: usize) -> usize {
+        Self::ROUTED_WIRES_PER_OP * i + 1
+    }
+    /// The boolean result `a < b` of the `i`th comparison.
+    pub(crate) const fn wire_ith_result(i: usize) -> usize {
+        Self::ROUTED_WIRES_PER_OP * i + 2
+    }
+
+    const fn start_chunks(&self, i: usize) -> usize {
+        Self::ROUTED_WIRES_PER_OP * self.num_ops + 3 * self.num_chunks() * i
+    }
+
+    /// The chunks of `a` for the `i`th comparison.
+    const fn wires_ith_a_chunks(&self, i: usize) -> Range<usize> {
+        let start = self.start_chunks(i);
+        start..start + self.num_chunks()
+    }
+
+    /// The chunks of `b` for the `i`th comparison.
+    const fn wires_ith_b_chunks(&self, i: usize) -> Range<usize> {
+        let start = self.start_chunks(i) + self.num_chunks();
+        start..start + self.num_chunks()
+    }
+
+    /// `a - b + result * 2^num_bits`, in chunks, for the `i`th comparison.
+    const fn wires_ith_diff_chunks(&self, i: usize) -> Range<usize> {
+        let start = self.start_chunks(i) + 2 * self.num_chunks();
+        start..start + self.num_chunks()
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for ComparisonGate {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.num_bits)?;
+        dst.write_usize(self.chunk_bits)?;
+        dst.write_usize(self.num_ops)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let num_bits = src.read_usize()?;
+        let chunk_bits = src.read_usize()?;
+        let num_ops = src.read_usize()?;
+        Ok(Self {
+            num_bits,
+            chunk_bits,
+            num_ops,
+        })
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let chunk_base = F::Extension::from_canonical_u64(1 << self.chunk_bits);
+        let two_n = F::Extension::from_canonical_u64(1 << self.num_bits);
+        let range_check = |i: usize, chunk: F::Extension| -> F::Extension {
+            (0..1 << self.chunk_size_bits(i))
+                .map(|k| chunk - F::Extension::from_canonical_usize(k))
+                .product()
+        };
+
+        let mut constraints = Vec::with_capacity(self.num_ops * (4 + 3 * self.num_chunks()));
+        for i in 0..self.num_ops {
+            let a = vars.local_wires[Self::wire_ith_a(i)];
+            let b = vars.local_wires[Self::wire_ith_b(i)];
+            let result = vars.local_wires[Self::wire_ith_result(i)];
+            let a_chunks = &vars.local_wires[self.wires_ith_a_chunks(i)];
+            let b_chunks = &vars.local_wires[self.wires_ith_b_chunks(i)];
+            let diff_chunks = &vars.local_wires[self.wires_ith_diff_chunks(i)];
+
+            constraints.push(reduce_with_powers(a_chunks, chunk_base) - a);
+            constraints.push(reduce_with_powers(b_chunks, chunk_base) - b);
+            let diff = a - b + result * two_n;
+            constraints.push(reduce_with_powers(diff_chunks, chunk_base) - diff);
+            constraints.push(result * (result - F::Extension::ONE));
+
+            for chunks in [a_chunks, b_chunks, diff_chunks] {
+                for (j, &chunk) in chunks.iter().enumerate() {
+                    constraints.push(range_check(j, chunk));
+                }
+            }
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let chunk_base = builder.constant(F::from_canonical_u64(1 << self.chunk_bits));
+        let two_n = F::from_canonical_u64(1 << self.num_bits);
+
+        let mut constraints = Vec::with_capacity(self.num_ops * (4 + 3 * self.num_chunks()));
+        for i in 0..self.num_ops {
+            let a = vars.local_wires[Self::wire_ith_a(i)];
+            let b = vars.local_wires[Self::wire_ith_b(i)];
+            let result = vars.local_wires[Self::wire_ith_result(i)];
+            let a_chunks = &vars.local_wires[self.wires_ith_a_chunks(i)];
+            let b_chunks = &vars.local_wires[self.wires_ith_b_chunks(i)];
+            let diff_chunks = &vars.local_wires[self.wires_ith_diff_chunks(i)];
+
+            let computed_a = reduce_with_powers_ext_circuit(builder, a_chunks, chunk_base);
+            constraints.push(builder.sub_extension(computed_a, a));
+            let computed_b = reduce_with_powers_ext_circuit(builder, b_chunks, chunk_base);
+            constraints.push(builder.sub_extension(computed_b, b));
+
+            // diff_chunks_sum - (a - b + result * 2^n)
+            let computed_diff = reduce_with_powers_ext_circuit(builder, diff_chunks, chunk_base);
+            let a_minus_b = builder.sub_extension(a, b);
+            let diff = builder.mul_const_add_extension(two_n, result, a_minus_b);
+            constraints.push(builder.sub_extension(computed_diff, diff));
+            constraints.push(builder.mul_sub_extension(result, result, result));
+
+            for chunks in [a_chunks, b_chunks, diff_chunks] {
+                for (j, &chunk) in chunks.iter().enumerate() {
+                    let mut acc = builder.one_extension();
+                    (0..1 << self.chunk_size_bits(j)).for_each(|k| {
+                        // acc' = acc (x - k) = acc x + (-k) acc
+                        let neg_k = -F::from_canonical_usize(k);
+                        acc = builder.arithmetic_extension(F::ONE, neg_k, acc, chunk, acc)
+                    });
+                    constraints.push(acc);
+                }
+            }
+        }
+
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        (0..self.num_ops)
+            .map(|i| {
+                WitnessGeneratorRef::new(
+                    ComparisonGenerator {
+                        row,
+                        gate: *self,
+                        i,
+                    }
+                    .adapter(),
+                )
+            })
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * self.wires_per_op()
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    // Bounded by the range-checks (x-0)*(x-1)*...*(x-2^chunk_bits+1).
+    fn degree(&self) -> usize {
+        (1 << self.chunk_bits.min(self.num_bits)).max(2)
+    }
+
+    // For each comparison, 3 decomposition checks, a boolean check of the result, and the
+    // range-checks of the chunks.
+    fn num_constraints(&self) -> usize {
+        self.num_ops * (4 + 3 * self.num_chunks())
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for ComparisonGate {
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        let chunk_base = F::from_canonical_u64(1 << self.chunk_bits);
+        let two_n = F::from_canonical_u64(1 << self.num_bits);
+        let range_check = |i: usize, chunk: P| -> P {
+            (0..1 << self.chunk_size_bits(i))
+                .map(|k| chunk - F::from_canonical_usize(k))
+                .product()
+        };
+
+        for i in 0..self.num_ops {
+            let a = vars.local_wires[Self::wire_ith_a(i)];
+            let b = vars.local_wires[Self::wire_ith_b(i)];
+            let result = vars.local_wires[Self::wire_ith_result(i)];
+            let a_chunks = vars.local_wires.view(self.wires_ith_a_chunks(i));
+            let b_chunks = vars.local_wires.view(self.wires_ith_b_chunks(i));
+            let diff_chunks = vars.local_wires.view(self.wires_ith_diff_chunks(i));
+
+            yield_constr.one(reduce_with_powers(a_chunks, chunk_base) - a);
+            yield_constr.one(reduce_with_powers(b_chunks, chunk_base) - b);
+            let diff = a - b + result * two_n;
+            yield_constr.one(reduce_with_powers(diff_chunks, chunk_base) - diff);
+            yield_constr.one(result * (result - P::ONES));
+
+            for chunks in [a_chunks, b_chunks, diff_chunks] {
+                for (j, &chunk) in chunks.iter().enumerate() {
+                    yield_constr.one(range_check(j, chunk));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ComparisonGenerator {
+    row: usize,
+    gate: ComparisonGate,
+    i: usize,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for ComparisonGenerator {
+    fn id(&self) -> String {
+        "ComparisonGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        [
+            ComparisonGate::wire_ith_a(self.i),
+            ComparisonGate::wire_ith_b(self.i),
+        ]
+        .iter()
+        .map(|&i| Target::wire(self.row, i))
+        .collect()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let get_wire = |wire: usize| -> u64 {
+            witness
+                .get_target(Target::wire(self.row, wire))
+                .to_canonical_u64()
+        };
+        let mut set_chunks = |wires: Range<usize>, value: u64| -> Result<()> {
+            let mask = (1 << self.gate.chunk_bits) - 1;
+            for (j, wire) in wires.enumerate() {
+                let chunk = (value >> (j * self.gate.chunk_bits)) & mask;
+                out_buffer
+                    .set_target(Target::wire(self.row, wire), F::from_canonical_u64(chunk))?;
+            }
+            Ok(())
+        };
+
+        let a = get_wire(ComparisonGate::wire_ith_a(self.i));
+        let b = get_wire(ComparisonGate::wire_ith_b(self.i));
+        let two_n = 1u64 << self.gate.num_bits;
+        debug_assert!(a < two_n && b < two_n, "inputs exceed the bit width");
+
+        let diff = two_n + a - b;
+        set_chunks(self.gate.wires_ith_a_chunks(self.i), a)?;
+        set_chunks(self.gate.wires_ith_b_chunks(self.i), b)?;
+        set_chunks(self.gate.wires_ith_diff_chunks(self.i), diff & (two_n - 1))?;
+
+        out_buffer.set_target(
+            Target::wire(self.row, ComparisonGate::wire_ith_result(self.i)),
+            F::from_bool(a < b),
+        )
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)?;
+        dst.write_usize(self.gate.num_bits)?;
+        dst.write_usize(self.gate.chunk_bits)?;
+        dst.write_usize(self.gate.num_ops)?;
+        dst.write_usize(self.i)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        let num_bits = src.read_usize()?;
+        let chunk_bits = src.read_usize()?;
+        let num_ops = src.read_usize()?;
+        let i = src.read_usize()?;
+        Ok(Self {
+            row,
+            gate: ComparisonGate::new(num_bits, chunk_bits, num_ops),
+            i,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::gates::gate_testing::{
+        test_eval_fns, test_generated_witness_with_inputs, test_low_degree,
+    };
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn wire_indices() {
+        let gate = ComparisonGate::new(8, 3, 2);
+        assert_eq!(gate.num_chunks(), 3);
+        assert_eq!(gate.chunk_size_bits(2), 2);
+
+        // The exact indices aren't really important, but we want to make sure we don't have any
+        // overlaps or gaps.
+        assert_eq!(ComparisonGate::wire_ith_a(0), 0);
+        assert_eq!(ComparisonGate::wire_ith_result(1), 5);
+        assert_eq!(gate.wires_ith_a_chunks(0), 6..9);
+        assert_eq!(gate.wires_ith_b_chunks(0), 9..12);
+        assert_eq!(gate.wires_ith_diff_chunks(0), 12..15);
+        assert_eq!(gate.wires_ith_diff_chunks(1), 21..24);
+        assert_eq!(
+            <ComparisonGate as Gate<GoldilocksField, 2>>::num_wires(&gate),
+            24
+        );
+    }
+
+    #[test]
+    fn new_from_config() {
+        let config = CircuitConfig::standard_recursion_config();
+        let gate = ComparisonGate::new_from_config(&config, 32);
+        assert_eq!(gate.chunk_bits, 3);
+        assert_eq!(gate.num_chunks(), 11);
+        assert_eq!(gate.num_ops, 3);
+    }
+
+    #[test]
+    fn low_degree() {
+        test_low_degree::<GoldilocksField, _, 4>(ComparisonGate::new(8, 2, 2));
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_eval_fns::<F, C, _, D>(ComparisonGate::new(8, 2, 2))
+    }
+
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        // The compared values must fit in 8 bits.
+        test_generated_witness_with_inputs::<F, C, _, D>(ComparisonGate::new(8, 2, 2), |_| {
+            F::from_canonical_u64(OsRng.gen_range(0..1 << 8))
+        })
+    }
+}
@@ -11,10 +11,13 @@ use core::ops::Range;
 use anyhow::Result;
 
 use crate::field::extension::algebra::ExtensionAlgebra;
+use crate::field::extension::packed::PackedExtension;
 use crate::field::extension::{Extendable, FieldExtension, OEF};
 use crate::field::interpolation::barycentric_weights;
+use crate::field::packed::PackedField;
 use crate::field::types::Field;
 use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
 use crate::gates::util::StridedConstraintConsumer;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::{ExtensionAlgebraTarget, ExtensionTarget};
@@ -24,7 +27,10 @@ use crate::iop::wire::Wire;
 use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CommonCircuitData;
-use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 /// One of the instantiations of `InterpolationGate`: allows constraints of variable
@@ -250,51 +256,14 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for CosetInterpola
 
     fn eval_unfiltered_base_one(
         &self,
-        vars: EvaluationVarsBase<F>,
-        mut yield_constr: StridedConstraintConsumer<F>,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
     ) {
-        let shift = vars.local_wires[self.wire_shift()];
-        let evaluation_point = vars.get_local_ext(self.wires_evaluation_point());
-        let shifted_evaluation_point = vars.get_local_ext(self.wires_shifted_evaluation_point());
-        yield_constr.many(
-            (evaluation_point - shifted_evaluation_point.scalar_mul(shift)).to_basefield_array(),
-        );
-
-        let domain = F::two_adic_subgroup(self.subgroup_bits);
-        let values = (0..self.num_points())
-            .map(|i| vars.get_local_ext(self.wires_value(i)))
-            .collect::<Vec<_>>();
-        let weights = &self.barycentric_weights;
-
-        let (mut computed_eval, mut computed_prod) = partial_interpolate(
-            &domain[..self.degree()],
-            &values[..self.degree()],
-            &weights[..self.degree()],
-            shifted_evaluation_point,
-            F::Extension::ZERO,
-            F::Extension::ONE,
-        );
-
-        for i in 0..self.num_intermediates() {
-            let intermediate_eval = vars.get_local_ext(self.wires_intermediate_eval(i));
-            let intermediate_prod = vars.get_local_ext(self.wires_intermediate_prod(i));
-            yield_constr.many((intermediate_eval - computed_eval).to_basefield_array());
-            yield_constr.many((intermediate_prod - computed_prod).to_basefield_array());
-
-            let start_index = 1 + (self.degree() - 1) * (i + 1);
-            let end_index = (start_index + self.degree() - 1).min(self.num_points());
-            (computed_eval, computed_prod) = partial_interpolate(
-                &domain[start_index..end_index],
-                &values[start_index..end_index],
-                &weights[start_index..end_index],
-                shifted_evaluation_point,
-                intermediate_eval,
-                intermediate_prod,
-            );
-        }
+        panic!("use eval_unfiltered_base_packed instead");
+    }
 
-        let evaluation_value = vars.get_local_ext(self.wires_evaluation_value());
-        yield_constr.many((evaluation_value - computed_eval).to_basefield_array());
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
     }
 
     fn eval_unfiltered_circuit(
@@ -396,6 +365,59 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for CosetInterpola
     }
 }
 
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for CosetInterpolationGate<F, D>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        let shift = vars.local_wires[self.wire_shift()];
+        let evaluation_point = vars.get_local_ext(self.wires_evaluation_point());
+        let shifted_evaluation_point = vars.get_local_ext(self.wires_shifted_evaluation_point());
+        yield_constr.many(
+            (evaluation_point - shifted_evaluation_point.scalar_mul(shift)).to_basefield_array(),
+        );
+
+        let domain = F::two_adic_subgroup(self.subgroup_bits);
+        let values = (0..self.num_points())
+            .map(|i| vars.get_local_ext(self.wires_value(i)))
+            .collect::<Vec<_>>();
+        let weights = &self.barycentric_weights;
+
+        let (mut computed_eval, mut computed_prod) = partial_interpolate_packed(
+            &domain[..self.degree()],
+            &values[..self.degree()],
+            &weights[..self.degree()],
+            shifted_evaluation_point,
+            PackedExtension::ZEROS,
+            PackedExtension::ones(),
+        );
+
+        for i in 0..self.num_intermediates() {
+            let intermediate_eval = vars.get_local_ext(self.wires_intermediate_eval(i));
+            let intermediate_prod = vars.get_local_ext(self.wires_intermediate_prod(i));
+            yield_constr.many((intermediate_eval - computed_eval).to_basefield_array());
+            yield_constr.many((intermediate_prod - computed_prod).to_basefield_array());
+
+            let start_index = 1 + (self.degree() - 1) * (i + 1);
+            let end_index = (start_index + self.degree() - 1).min(self.num_points());
+            (computed_eval, computed_prod) = partial_interpolate_packed(
+                &domain[start_index..end_index],
+                &values[start_index..end_index],
+                &weights[start_index..end_index],
+                shifted_evaluation_point,
+                intermediate_eval,
+                intermediate_prod,
+            );
+        }
+
+        let evaluation_value = vars.get_local_ext(self.wires_evaluation_value());
+        yield_constr.many((evaluation_value - computed_eval).to_basefield_array());
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct InterpolationGenerator<F: RichField + Extendable<D>, const D: usize> {
     row: usize,
@@ -550,7 +572,7 @@ pub fn interpolate_over_base_domain<F: Field + Extendable<D>, const D: usize>(
 /// accumulated values, a partial evaluation and a partial product. This partially updates the
 /// accumulated values, so that starting with an initial evaluation of 0 and a partial evaluation
 /// of 1 and running over the whole domain is a full interpolation.
-fn partial_interpolate<F: Field + Extendable<D>, const D: usize>(
+pub(crate) fn partial_interpolate<F: Field + Extendable<D>, const D: usize>(
     domain: &[F],
     values: &[F::Extension],
     barycentric_weights: &[F],
@@ -579,7 +601,40 @@ fn partial_interpolate<F: Field + Extendable<D>, const D: usize>(
     )
 }
 
-fn partial_interpolate_ext_algebra<F: OEF<D>, const D: usize>(
+/// Like [`partial_interpolate`], but evaluates the interpolant at `P::WIDTH` points at once.
+pub(crate) fn partial_interpolate_packed<P: PackedField, const D: usize>(
+    domain: &[P::Scalar],
+    values: &[PackedExtension<P, D>],
+    barycentric_weights: &[P::Scalar],
+    x: PackedExtension<P, D>,
+    initial_eval: PackedExtension<P, D>,
+    initial_partial_prod: PackedExtension<P, D>,
+) -> (PackedExtension<P, D>, PackedExtension<P, D>)
+where
+    P::Scalar: Extendable<D>,
+{
+    let n = domain.len();
+    assert_ne!(n, 0);
+    assert_eq!(n, values.len());
+    assert_eq!(n, barycentric_weights.len());
+
+    let weighted_values = values
+        .iter()
+        .zip(barycentric_weights.iter())
+        .map(|(&value, &weight)| value.scalar_mul(weight.into()));
+
+    weighted_values.zip(domain.iter()).fold(
+        (initial_eval, initial_partial_prod),
+        |(eval, terms_partial_prod), (val, &x_i)| {
+            let term = x - P::from(x_i).into();
+            let next_eval = eval * term + val * terms_partial_prod;
+            let next_terms_partial_prod = terms_partial_prod * term;
+            (next_eval, next_terms_partial_prod)
+        },
+    )
+}
+
+pub(crate) fn partial_interpolate_ext_algebra<F: OEF<D>, const D: usize>(
     domain: &[F::BaseField],
     values: &[ExtensionAlgebra<F, D>],
     barycentric_weights: &[F::BaseField],
@@ -608,7 +663,10 @@ fn partial_interpolate_ext_algebra<F: OEF<D>, const D: usize>(
     )
 }
 
-fn partial_interpolate_ext_algebra_target<F: RichField + Extendable<D>, const D: usize>(
+pub(crate) fn partial_interpolate_ext_algebra_target<
+    F: RichField + Extendable<D>,
+    const D: usize,
+>(
     builder: &mut CircuitBuilder<F, D>,
     domain: &[F],
     values: &[ExtensionAlgebraTarget<D>],
@@ -651,7 +709,7 @@ mod tests {
     use super::*;
     use crate::field::goldilocks_field::GoldilocksField;
     use crate::field::types::Sample;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generated_witness, test_low_degree};
     use crate::hash::hash_types::HashOut;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -801,6 +859,19 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        for degree in 2..=4 {
+            test_generated_witness::<F, C, _, D>(CosetInterpolationGate::with_max_degree(
+                2, degree,
+            ))?;
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_gate_constraint() {
         const D: usize = 2;
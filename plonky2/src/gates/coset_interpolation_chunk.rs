@@ -0,0 +1,573 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use anyhow::Result;
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::interpolation::barycentric_weights;
+use crate::gates::coset_interpolation::{
+    partial_interpolate, partial_interpolate_ext_algebra, partial_interpolate_ext_algebra_target,
+};
+use crate::gates::gate::Gate;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// Performs part of a Barycentric interpolation over the multiplicative subgroup of size
+/// `1<<subgroup_bits`, covering the `len` points starting at index `start`.
+///
+/// This is used for subgroups too large for a single [`CosetInterpolationGate`] to fit in the
+/// routed wires, e.g. for FRI arities of `2^6` with the standard recursion config. Using the
+/// notation of [`CosetInterpolationGate`], this gate has as routed wires
+/// - the shifted evaluation point $z$
+/// - the initial accumulators $e\[start\]$ and $p\[start\]$
+/// - the values $v\[start\], \ldots, v\[start + len - 1\]$
+/// - the final accumulators $e\[start + len\]$ and $p\[start + len\]$
+///
+/// so that chaining gates covering the whole subgroup, starting from $e = 0$ and $p = 1$, yields
+/// the interpolated value as the final $e$. As with [`CosetInterpolationGate`], the degree of the
+/// constraints is bounded by storing every $(d - 1)$'th intermediate accumulator in non-routed
+/// wires.
+///
+/// [`CosetInterpolationGate`]: crate::gates::coset_interpolation::CosetInterpolationGate
+#[derive(Clone, Debug, Default)]
+pub struct CosetInterpolationChunkGate<F: RichField + Extendable<D>, const D: usize> {
+    pub subgroup_bits: usize,
+    pub start: usize,
+    pub len: usize,
+    pub degree: usize,
+    pub barycentric_weights: Vec<F>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CosetInterpolationChunkGate<F, D> {
+    pub fn new(subgroup_bits: usize, start: usize, len: usize, max_degree: usize) -> Self {
+        assert!(max_degree > 1, "need at least quadratic constraints");
+        assert!(len > 0, "chunk must be nonempty");
+        assert!(
+            start + len <= 1 << subgroup_bits,
+            "chunk exceeds the subgroup"
+        );
+
+        let n_intermediates = Self::num_intermediates_for(len, max_degree);
+        // Find the minimum degree which doesn't require more intermediate values, as in
+        // `CosetInterpolationGate`.
+        let degree = len.div_ceil(n_intermediates + 1) + 1;
+
+        let barycentric_weights = barycentric_weights(
+            &F::two_adic_subgroup(subgroup_bits)
+                .into_iter()
+                .map(|x| (x, F::ZERO))
+                .collect::<Vec<_>>(),
+        )[start..start + len]
+            .to_vec();
+
+        Self {
+            subgroup_bits,
+            start,
+            len,
+            degree,
+            barycentric_weights,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The largest chunk length for which this gate fits in the given config, when its degree is
+    /// bounded by `config.max_quotient_degree_factor`. Returns 0 if no chunk fits.
+    pub fn max_chunk_len(config: &CircuitConfig) -> usize {
+        let max_degree = config.max_quotient_degree_factor;
+        let max_len = (config.num_routed_wires / D).saturating_sub(5);
+        (1..=max_len)
+            .rev()
+            .find(|&len| {
+                let num_wires = D * (5 + len + 2 * Self::num_intermediates_for(len, max_degree));
+                num_wires <= config.num_wires
+            })
+            .unwrap_or(0)
+    }
+
+    const fn num_intermediates_for(len: usize, degree: usize) -> usize {
+        (len - 1) / (degree - 1)
+    }
+
+    /// Wire indices of the shifted point to evaluate the interpolant at.
+    pub(crate) const fn wires_shifted_evaluation_point(&self) -> Range<usize> {
+        0..D
+    }
+
+    /// Wire indices of the partial evaluation this chunk starts from.
+    pub(crate) const fn wires_initial_eval(&self) -> Range<usize> {
+        D..2 * D
+    }
+
+    /// Wire indices of the partial product this chunk starts from.
+    pub(crate) const fn wires_initial_prod(&self) -> Range<usize> {
+        2 * D..3 * D
+    }
+
+    const fn start_values(&self) -> usize {
+        3 * D
+    }
+
+    /// Wire indices of the `i`th interpolant value in this chunk.
+    pub(crate) fn wires_value(&self, i: usize) -> Range<usize> {
+        debug_assert!(i < self.len);
+        let start = self.start_values() + i * D;
+        start..start + D
+    }
+
+    const fn start_final(&self) -> usize {
+        self.start_values() + self.len * D
+    }
+
+    /// Wire indices of the partial evaluation after this chunk.
+    pub(crate) const fn wires_final_eval(&self) -> Range<usize> {
+        let start = self.start_final();
+        start..start + D
+    }
+
+    /// Wire indices of the partial product after this chunk.
+    pub(crate) const fn wires_final_prod(&self) -> Range<usize> {
+        let start = self.start_final() + D;
+        start..start + D
+    }
+
+    const fn start_intermediates(&self) -> usize {
+        self.start_final() + 2 * D
+    }
+
+    pub const fn num_routed_wires(&self) -> usize {
+        self.start_intermediates()
+    }
+
+    const fn num_intermediates(&self) -> usize {
+        Self::num_intermediates_for(self.len, self.degree)
+    }
+
+    /// The wires corresponding to the i'th intermediate evaluation.
+    const fn wires_intermediate_eval(&self, i: usize) -> Range<usize> {
+        debug_assert!(i < self.num_intermediates());
+        let start = self.start_intermediates() + D * i;
+        start..start + D
+    }
+
+    /// The wires corresponding to the i'th intermediate product.
+    const fn wires_intermediate_prod(&self, i: usize) -> Range<usize> {
+        debug_assert!(i < self.num_intermediates());
+        let start = self.start_intermediates() + D * (self.num_intermediates() + i);
+        start..start + D
+    }
+
+    /// End of wire indices, exclusive.
+    const fn end(&self) -> usize {
+        self.start_intermediates() + D * 2 * self.num_intermediates()
+    }
+
+    /// The range of indices, within this chunk, of the points handled before the `i`th
+    /// intermediate value (or before the final value, for `i = num_intermediates()`).
+    fn segment(&self, i: usize) -> Range<usize> {
+        let start = (self.degree - 1) * i;
+        let end = (start + self.degree - 1).min(self.len);
+        start..end
+    }
+
+    /// The points of the subgroup covered by this chunk.
+    fn domain(&self) -> Vec<F> {
+        F::two_adic_subgroup(self.subgroup_bits)[self.start..self.start + self.len].to_vec()
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D>
+    for CosetInterpolationChunkGate<F, D>
+{
+    fn id(&self) -> String {
+        format!("{self:?}<D={D}>")
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.subgroup_bits)?;
+        dst.write_usize(self.start)?;
+        dst.write_usize(self.len)?;
+        dst.write_usize(self.degree)?;
+        dst.write_field_vec(&self.barycentric_weights)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let subgroup_bits = src.read_usize()?;
+        let start = src.read_usize()?;
+        let len = src.read_usize()?;
+        let degree = src.read_usize()?;
+        let barycentric_weights: Vec<F> = src.read_field_vec(len)?;
+        Ok(Self {
+            subgroup_bits,
+            start,
+            len,
+            degree,
+            barycentric_weights,
+            _phantom: PhantomData,
+        })
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        let shifted_evaluation_point =
+            vars.get_local_ext_algebra(self.wires_shifted_evaluation_point());
+        let domain = self.domain();
+        let values = (0..self.len)
+            .map(|i| vars.get_local_ext_algebra(self.wires_value(i)))
+            .collect::<Vec<_>>();
+        let weights = &self.barycentric_weights;
+
+        let mut eval = vars.get_local_ext_algebra(self.wires_initial_eval());
+        let mut prod = vars.get_local_ext_algebra(self.wires_initial_prod());
+        for i in 0..=self.num_intermediates() {
+            let segment = self.segment(i);
+            let (computed_eval, computed_prod) = partial_interpolate_ext_algebra(
+                &domain[segment.clone()],
+                &values[segment.clone()],
+                &weights[segment],
+                shifted_evaluation_point,
+                eval,
+                prod,
+            );
+
+            let (eval_wires, prod_wires) = if i < self.num_intermediates() {
+                (
+                    self.wires_intermediate_eval(i),
+                    self.wires_intermediate_prod(i),
+                )
+            } else {
+                (self.wires_final_eval(), self.wires_final_prod())
+            };
+            eval = vars.get_local_ext_algebra(eval_wires);
+            prod = vars.get_local_ext_algebra(prod_wires);
+            constraints.extend((eval - computed_eval).to_basefield_array());
+            constraints.extend((prod - computed_prod).to_basefield_array());
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        let shifted_evaluation_point = vars.get_local_ext(self.wires_shifted_evaluation_point());
+        let domain = self.domain();
+        let values = (0..self.len)
+            .map(|i| vars.get_local_ext(self.wires_value(i)))
+            .collect::<Vec<_>>();
+        let weights = &self.barycentric_weights;
+
+        let mut eval = vars.get_local_ext(self.wires_initial_eval());
+        let mut prod = vars.get_local_ext(self.wires_initial_prod());
+        for i in 0..=self.num_intermediates() {
+            let segment = self.segment(i);
+            let (computed_eval, computed_prod) = partial_interpolate(
+                &domain[segment.clone()],
+                &values[segment.clone()],
+                &weights[segment],
+                shifted_evaluation_point,
+                eval,
+                prod,
+            );
+
+            let (eval_wires, prod_wires) = if i < self.num_intermediates() {
+                (
+                    self.wires_intermediate_eval(i),
+                    self.wires_intermediate_prod(i),
+                )
+            } else {
+                (self.wires_final_eval(), self.wires_final_prod())
+            };
+            eval = vars.get_local_ext(eval_wires);
+            prod = vars.get_local_ext(prod_wires);
+            yield_constr.many((eval - computed_eval).to_basefield_array());
+            yield_constr.many((prod - computed_prod).to_basefield_array());
+        }
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        let shifted_evaluation_point =
+            vars.get_local_ext_algebra(self.wires_shifted_evaluation_point());
+        let domain = self.domain();
+        let values = (0..self.len)
+            .map(|i| vars.get_local_ext_algebra(self.wires_value(i)))
+            .collect::<Vec<_>>();
+        let weights = &self.barycentric_weights;
+
+        let mut eval = vars.get_local_ext_algebra(self.wires_initial_eval());
+        let mut prod = vars.get_local_ext_algebra(self.wires_initial_prod());
+        for i in 0..=self.num_intermediates() {
+            let segment = self.segment(i);
+            let (computed_eval, computed_prod) = partial_interpolate_ext_algebra_target(
+                builder,
+                &domain[segment.clone()],
+                &values[segment.clone()],
+                &weights[segment],
+                shifted_evaluation_point,
+                eval,
+                prod,
+            );
+
+            let (eval_wires, prod_wires) = if i < self.num_intermediates() {
+                (
+                    self.wires_intermediate_eval(i),
+                    self.wires_intermediate_prod(i),
+                )
+            } else {
+                (self.wires_final_eval(), self.wires_final_prod())
+            };
+            eval = vars.get_local_ext_algebra(eval_wires);
+            prod = vars.get_local_ext_algebra(prod_wires);
+            constraints.extend(
+                builder
+                    .sub_ext_algebra(eval, computed_eval)
+                    .to_ext_target_array(),
+            );
+            constraints.extend(
+                builder
+                    .sub_ext_algebra(prod, computed_prod)
+                    .to_ext_target_array(),
+            );
+        }
+
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        let gen = InterpolationChunkGenerator::<F, D>::new(row, self.clone());
+        vec![WitnessGeneratorRef::new(gen.adapter())]
+    }
+
+    fn num_wires(&self) -> usize {
+        self.end()
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        self.degree
+    }
+
+    fn num_constraints(&self) -> usize {
+        // 2 * D constraints for each intermediate value, plus 2 * D constraints for the final
+        // values.
+        2 * D * (self.num_intermediates() + 1)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct InterpolationChunkGenerator<F: RichField + Extendable<D>, const D: usize> {
+    row: usize,
+    gate: CosetInterpolationChunkGate<F, D>,
+    interpolation_domain: Vec<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> InterpolationChunkGenerator<F, D> {
+    fn new(row: usize, gate: CosetInterpolationChunkGate<F, D>) -> Self {
+        let interpolation_domain = gate.domain();
+        InterpolationChunkGenerator {
+            row,
+            gate,
+            interpolation_domain,
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for InterpolationChunkGenerator<F, D>
+{
+    fn id(&self) -> String {
+        "InterpolationChunkGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        let local_targets = |columns: Range<usize>| {
+            columns.map(|column| {
+                Target::Wire(Wire {
+                    row: self.row,
+                    column,
+                })
+            })
+        };
+
+        let mut deps = Vec::with_capacity((3 + self.gate.len) * D);
+        deps.extend(local_targets(self.gate.wires_shifted_evaluation_point()));
+        deps.extend(local_targets(self.gate.wires_initial_eval()));
+        deps.extend(local_targets(self.gate.wires_initial_prod()));
+        for i in 0..self.gate.len {
+            deps.extend(local_targets(self.gate.wires_value(i)));
+        }
+        deps
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let local_wire = |column| Wire {
+            row: self.row,
+            column,
+        };
+
+        let get_local_ext = |wire_range: Range<usize>| {
+            debug_assert_eq!(wire_range.len(), D);
+            let values = wire_range
+                .map(|column| witness.get_wire(local_wire(column)))
+                .collect::<Vec<_>>();
+            let arr = values.try_into().unwrap();
+            F::Extension::from_basefield_array(arr)
+        };
+
+        let shifted_evaluation_point = get_local_ext(self.gate.wires_shifted_evaluation_point());
+        let domain = &self.interpolation_domain;
+        let values = (0..self.gate.len)
+            .map(|i| get_local_ext(self.gate.wires_value(i)))
+            .collect::<Vec<_>>();
+        let weights = &self.gate.barycentric_weights;
+
+        let mut eval = get_local_ext(self.gate.wires_initial_eval());
+        let mut prod = get_local_ext(self.gate.wires_initial_prod());
+        for i in 0..=self.gate.num_intermediates() {
+            let segment = self.gate.segment(i);
+            (eval, prod) = partial_interpolate(
+                &domain[segment.clone()],
+                &values[segment.clone()],
+                &weights[segment],
+                shifted_evaluation_point,
+                eval,
+                prod,
+            );
+
+            let (eval_wires, prod_wires) = if i < self.gate.num_intermediates() {
+                (
+                    self.gate.wires_intermediate_eval(i),
+                    self.gate.wires_intermediate_prod(i),
+                )
+            } else {
+                (self.gate.wires_final_eval(), self.gate.wires_final_prod())
+            };
+            out_buffer.set_ext_wires(eval_wires.map(local_wire), eval)?;
+            out_buffer.set_ext_wires(prod_wires.map(local_wire), prod)?;
+        }
+
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)?;
+        self.gate.serialize(dst, common_data)
+    }
+
+    fn deserialize(src: &mut Buffer, common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        let gate = CosetInterpolationChunkGate::deserialize(src, common_data)?;
+        Ok(Self::new(row, gate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::gates::gate_testing::{test_eval_fns, test_generated_witness, test_low_degree};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn wire_indices() {
+        let gate = CosetInterpolationChunkGate::<GoldilocksField, 2>::new(3, 2, 5, 3);
+        assert_eq!(gate.degree(), 3);
+
+        // The exact indices aren't really important, but we want to make sure we don't have any
+        // overlaps or gaps.
+        assert_eq!(gate.wires_shifted_evaluation_point(), 0..2);
+        assert_eq!(gate.wires_initial_eval(), 2..4);
+        assert_eq!(gate.wires_initial_prod(), 4..6);
+        assert_eq!(gate.wires_value(0), 6..8);
+        assert_eq!(gate.wires_value(4), 14..16);
+        assert_eq!(gate.wires_final_eval(), 16..18);
+        assert_eq!(gate.wires_final_prod(), 18..20);
+        assert_eq!(gate.wires_intermediate_eval(0), 20..22);
+        assert_eq!(gate.wires_intermediate_eval(1), 22..24);
+        assert_eq!(gate.wires_intermediate_prod(0), 24..26);
+        assert_eq!(gate.wires_intermediate_prod(1), 26..28);
+        assert_eq!(gate.num_wires(), 28);
+        assert_eq!(gate.num_constraints(), 12);
+    }
+
+    #[test]
+    fn max_chunk_len() {
+        let config = CircuitConfig::standard_recursion_config();
+        let max_len = CosetInterpolationChunkGate::<GoldilocksField, 2>::max_chunk_len(&config);
+        assert_eq!(max_len, 35);
+
+        // An arity of 2^6 can be interpolated with two chunks.
+        let gate = CosetInterpolationChunkGate::<GoldilocksField, 2>::new(
+            6,
+            32,
+            32,
+            config.max_quotient_degree_factor,
+        );
+        assert_eq!(gate.degree(), 8);
+        assert!(gate.num_routed_wires() <= config.num_routed_wires);
+        assert!(gate.num_wires() <= config.num_wires);
+    }
+
+    #[test]
+    fn low_degree() {
+        test_low_degree::<GoldilocksField, _, 4>(CosetInterpolationChunkGate::new(3, 2, 5, 3));
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        for degree in 2..=4 {
+            test_eval_fns::<F, C, _, D>(CosetInterpolationChunkGate::new(3, 3, 5, degree))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        for degree in 2..=4 {
+            test_generated_witness::<F, C, _, D>(CosetInterpolationChunkGate::new(
+                3, 3, 5, degree,
+            ))?;
+        }
+        Ok(())
+    }
+}
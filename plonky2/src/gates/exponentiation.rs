@@ -326,7 +326,9 @@ mod tests {
     use super::*;
     use crate::field::goldilocks_field::GoldilocksField;
     use crate::field::types::Sample;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{
+        test_eval_fns, test_generated_witness_with_inputs, test_low_degree,
+    };
     use crate::hash::hash_types::HashOut;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
     use crate::util::log2_ceil;
@@ -369,6 +371,22 @@ mod tests {
         ))
     }
 
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = ExponentiationGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        let wire_base = gate.wire_base();
+        test_generated_witness_with_inputs::<F, C, _, D>(gate, |column| {
+            if column == wire_base {
+                F::rand()
+            } else {
+                F::from_bool(OsRng.gen())
+            }
+        })
+    }
+
     #[test]
     fn test_gate_constraint() {
         const D: usize = 2;
@@ -0,0 +1,419 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops::Range;
+
+use anyhow::Result;
+
+use crate::field::extension::algebra::ExtensionAlgebra;
+use crate::field::extension::packed::PackedExtension;
+use crate::field::extension::Extendable;
+use crate::field::packed::PackedField;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::{ExtensionAlgebraTarget, ExtensionTarget};
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// A gate for raising extension field values to variable powers. Each operation computes
+/// `output = initial^(2^num_power_bits) * base^exponent`, where `exponent` is given by its
+/// `num_power_bits` little-endian bits. The `initial` value allows chaining several operations to
+/// support exponents with more bits than fit in a single operation; it should be set to one
+/// otherwise. If the config has enough wires, it supports several independent operations in one
+/// gate.
+#[derive(Debug, Clone, Default)]
+pub struct ExponentiationExtensionGate<const D: usize> {
+    pub num_power_bits: usize,
+    /// Number of exponentiations performed by the gate.
+    pub num_ops: usize,
+}
+
+impl<const D: usize> ExponentiationExtensionGate<D> {
+    pub const fn new(num_power_bits: usize, num_ops: usize) -> Self {
+        Self {
+            num_power_bits,
+            num_ops,
+        }
+    }
+
+    /// Creates a gate with as many operations as fit in the given config. Panics if a single
+    /// operation with `num_power_bits` bits doesn't fit.
+    pub fn new_from_config(config: &CircuitConfig, num_power_bits: usize) -> Self {
+        assert!(
+            0 < num_power_bits && num_power_bits <= Self::max_power_bits(config),
+            "Cannot exponentiate with {num_power_bits} bits in a single operation"
+        );
+        let num_ops = (config.num_routed_wires / Self::routed_wires_per_op(num_power_bits))
+            .min(config.num_wires / Self::wires_per_op(num_power_bits));
+        Self::new(num_power_bits, num_ops)
+    }
+
+    /// The maximum number of exponent bits a single operation can have for the given config.
+    pub const fn max_power_bits(config: &CircuitConfig) -> usize {
+        // The base, initial value and output are routed, along with the bits.
+        let max_for_routed_wires = config.num_routed_wires - 3 * D;
+        // Each bit but the last also needs an intermediate value.
+        let max_for_wires = (config.num_wires - 2 * D) / (D + 1);
+        if max_for_routed_wires < max_for_wires {
+            max_for_routed_wires
+        } else {
+            max_for_wires
+        }
+    }
+
+    const fn routed_wires_per_op(num_power_bits: usize) -> usize {
+        3 * D + num_power_bits
+    }
+
+    const fn wires_per_op(num_power_bits: usize) -> usize {
+        Self::routed_wires_per_op(num_power_bits) + (num_power_bits - 1) * D
+    }
+
+    const fn start_ith_routed(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_ops);
+        i * Self::routed_wires_per_op(self.num_power_bits)
+    }
+
+    pub(crate) const fn wires_ith_base(&self, i: usize) -> Range<usize> {
+        let start = self.start_ith_routed(i);
+        start..start + D
+    }
+
+    pub(crate) const fn wires_ith_initial(&self, i: usize) -> Range<usize> {
+        let start = self.start_ith_routed(i) + D;
+        start..start + D
+    }
+
+    /// The `j`th bit of the `i`th exponent, in little-endian order.
+    pub(crate) const fn wire_ith_power_bit(&self, i: usize, j: usize) -> usize {
+        debug_assert!(j < self.num_power_bits);
+        self.start_ith_routed(i) + 2 * D + j
+    }
+
+    pub(crate) const fn wires_ith_output(&self, i: usize) -> Range<usize> {
+        let start = self.start_ith_routed(i) + 2 * D + self.num_power_bits;
+        start..start + D
+    }
+
+    /// The value accumulated after the `j`th most significant bit of the `i`th exponent. The
+    /// last accumulated value is the output.
+    fn wires_ith_intermediate_value(&self, i: usize, j: usize) -> Range<usize> {
+        debug_assert!(j < self.num_power_bits);
+        if j == self.num_power_bits - 1 {
+            return self.wires_ith_output(i);
+        }
+        let start = self.num_ops * Self::routed_wires_per_op(self.num_power_bits)
+            + (i * (self.num_power_bits - 1) + j) * D;
+        start..start + D
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for ExponentiationExtensionGate<D> {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.num_power_bits)?;
+        dst.write_usize(self.num_ops)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let num_power_bits = src.read_usize()?;
+        let num_ops = src.read_usize()?;
+        Ok(Self::new(num_power_bits, num_ops))
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let one = ExtensionAlgebra::one();
+        let mut constraints = Vec::with_capacity(self.num_ops * self.num_power_bits * D);
+        for i in 0..self.num_ops {
+            let base = vars.get_local_ext_algebra(self.wires_ith_base(i));
+            let base_minus_one = base - one;
+            let mut prev = vars.get_local_ext_algebra(self.wires_ith_initial(i));
+            for j in 0..self.num_power_bits {
+                // The bits are in LE order, but we accumulate in BE order.
+                let bit = vars.local_wires[self.wire_ith_power_bit(i, self.num_power_bits - j - 1)];
+                let current = vars.get_local_ext_algebra(self.wires_ith_intermediate_value(i, j));
+                let computed = prev * prev * (base_minus_one.scalar_mul(bit) + one);
+                constraints.extend((current - computed).to_basefield_array());
+                prev = current;
+            }
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let zero = builder.zero_extension();
+        let mut one = ExtensionAlgebraTarget([zero; D]);
+        one.0[0] = builder.one_extension();
+
+        let mut constraints = Vec::with_capacity(self.num_ops * self.num_power_bits * D);
+        for i in 0..self.num_ops {
+            let base = vars.get_local_ext_algebra(self.wires_ith_base(i));
+            let base_minus_one = builder.sub_ext_algebra(base, one);
+            let mut prev = vars.get_local_ext_algebra(self.wires_ith_initial(i));
+            for j in 0..self.num_power_bits {
+                // The bits are in LE order, but we accumulate in BE order.
+                let bit = vars.local_wires[self.wire_ith_power_bit(i, self.num_power_bits - j - 1)];
+                let current = vars.get_local_ext_algebra(self.wires_ith_intermediate_value(i, j));
+                let mul_by = builder.scalar_mul_add_ext_algebra(bit, base_minus_one, one);
+                let square = builder.mul_ext_algebra(prev, prev);
+                let computed = builder.mul_ext_algebra(square, mul_by);
+                let diff = builder.sub_ext_algebra(current, computed);
+                constraints.extend(diff.to_ext_target_array());
+                prev = current;
+            }
+        }
+
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        (0..self.num_ops)
+            .map(|i| {
+                WitnessGeneratorRef::new(
+                    ExponentiationExtensionGenerator {
+                        row,
+                        gate: self.clone(),
+                        i,
+                    }
+                    .adapter(),
+                )
+            })
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * Self::wires_per_op(self.num_power_bits)
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        4
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops * self.num_power_bits * D
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for ExponentiationExtensionGate<D>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        for i in 0..self.num_ops {
+            let base = vars.get_local_ext(self.wires_ith_base(i));
+            let base_minus_one = base - PackedExtension::ones();
+            let mut prev = vars.get_local_ext(self.wires_ith_initial(i));
+            for j in 0..self.num_power_bits {
+                // The bits are in LE order, but we accumulate in BE order.
+                let bit = vars.local_wires[self.wire_ith_power_bit(i, self.num_power_bits - j - 1)];
+                let current = vars.get_local_ext(self.wires_ith_intermediate_value(i, j));
+                let computed =
+                    prev * prev * (base_minus_one.scalar_mul(bit) + PackedExtension::ones());
+                yield_constr.many((current - computed).to_basefield_array());
+                prev = current;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExponentiationExtensionGenerator<const D: usize> {
+    row: usize,
+    gate: ExponentiationExtensionGate<D>,
+    i: usize,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for ExponentiationExtensionGenerator<D>
+{
+    fn id(&self) -> String {
+        "ExponentiationExtensionGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.gate
+            .wires_ith_base(self.i)
+            .chain(self.gate.wires_ith_initial(self.i))
+            .chain((0..self.gate.num_power_bits).map(|j| self.gate.wire_ith_power_bit(self.i, j)))
+            .map(|column| Target::wire(self.row, column))
+            .collect()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let get_extension = |range: Range<usize>| -> F::Extension {
+            witness.get_extension_target(ExtensionTarget::from_range(self.row, range))
+        };
+
+        let num_power_bits = self.gate.num_power_bits;
+        let base = get_extension(self.gate.wires_ith_base(self.i));
+        let mut current = get_extension(self.gate.wires_ith_initial(self.i));
+        for j in 0..num_power_bits {
+            let bit = witness.get_target(Target::wire(
+                self.row,
+                self.gate.wire_ith_power_bit(self.i, num_power_bits - j - 1),
+            ));
+            current *= current;
+            if bit == F::ONE {
+                current *= base;
+            }
+            let intermediate_value_target = ExtensionTarget::from_range(
+                self.row,
+                self.gate.wires_ith_intermediate_value(self.i, j),
+            );
+            out_buffer.set_extension_target(intermediate_value_target, current)?;
+        }
+
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)?;
+        self.gate.serialize(dst, common_data)?;
+        dst.write_usize(self.i)
+    }
+
+    fn deserialize(src: &mut Buffer, common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        let gate = ExponentiationExtensionGate::deserialize(src, common_data)?;
+        let i = src.read_usize()?;
+        Ok(Self { row, gate, i })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Sample};
+    use crate::gates::gate_testing::{
+        test_eval_fns, test_generated_witness_with_inputs, test_low_degree,
+    };
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn wire_indices() {
+        let gate = ExponentiationExtensionGate::<2> {
+            num_power_bits: 3,
+            num_ops: 2,
+        };
+
+        assert_eq!(gate.wires_ith_base(0), 0..2);
+        assert_eq!(gate.wires_ith_initial(0), 2..4);
+        assert_eq!(gate.wire_ith_power_bit(0, 2), 6);
+        assert_eq!(gate.wires_ith_output(0), 7..9);
+        assert_eq!(gate.wires_ith_base(1), 9..11);
+        assert_eq!(gate.wires_ith_output(1), 16..18);
+        assert_eq!(gate.wires_ith_intermediate_value(0, 0), 18..20);
+        assert_eq!(gate.wires_ith_intermediate_value(1, 1), 24..26);
+        assert_eq!(gate.wires_ith_intermediate_value(1, 2), 16..18);
+        assert_eq!(
+            <ExponentiationExtensionGate<2> as Gate<GoldilocksField, 2>>::num_wires(&gate),
+            26
+        );
+    }
+
+    #[test]
+    fn new_from_config() {
+        let config = CircuitConfig::standard_recursion_config();
+        let max_power_bits = ExponentiationExtensionGate::<2>::max_power_bits(&config);
+        let gate = ExponentiationExtensionGate::<2>::new_from_config(&config, max_power_bits);
+        assert_eq!(gate.num_ops, 1);
+        assert!(
+            <ExponentiationExtensionGate<2> as Gate<GoldilocksField, 2>>::num_wires(&gate)
+                <= config.num_wires
+        );
+        let gate = ExponentiationExtensionGate::<2>::new_from_config(&config, 8);
+        assert!(gate.num_ops > 1);
+    }
+
+    #[test]
+    fn low_degree() {
+        let gate = ExponentiationExtensionGate::new_from_config(
+            &CircuitConfig::standard_recursion_config(),
+            5,
+        );
+        test_low_degree::<GoldilocksField, _, 4>(gate);
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = ExponentiationExtensionGate::new_from_config(
+            &CircuitConfig::standard_recursion_config(),
+            5,
+        );
+        test_eval_fns::<F, C, _, D>(gate)
+    }
+
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = ExponentiationExtensionGate::new_from_config(
+            &CircuitConfig::standard_recursion_config(),
+            5,
+        );
+        let power_bits = (0..gate.num_ops)
+            .flat_map(|i| (0..gate.num_power_bits).map(move |j| (i, j)))
+            .map(|(i, j)| gate.wire_ith_power_bit(i, j))
+            .collect::<Vec<_>>();
+        test_generated_witness_with_inputs::<F, C, _, D>(gate, |column| {
+            if power_bits.contains(&column) {
+                F::from_bool(OsRng.gen())
+            } else {
+                F::rand()
+            }
+        })
+    }
+}
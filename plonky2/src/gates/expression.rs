@@ -0,0 +1,389 @@
+//! A small expression language for gate constraints.
+//!
+//! A gate implementing [`ExpressionGate`] describes its constraints once, as [`GateExpr`]s over
+//! its wires, its local constants and the public inputs hash. `eval_unfiltered`,
+//! `eval_unfiltered_base_batch` and `eval_unfiltered_circuit` can then all be delegated to the
+//! provided `*_exprs` methods, which interpret the same expressions, so that the native and
+//! recursive evaluations of the gate can't drift apart.
+
+#[cfg(not(feature = "std"))]
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::iter::Sum;
+use core::ops::{Add, Mul, Neg, Sub};
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::packable::Packable;
+use crate::field::packed::PackedField;
+use crate::field::types::Field;
+use crate::gates::gate::Gate;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBaseBatch, EvaluationVarsBasePacked,
+};
+
+/// A polynomial expression over the local wires and constants of a gate, and the public inputs
+/// hash, along with its degree.
+#[derive(Clone, Debug)]
+pub struct GateExpr<F: Field> {
+    node: Arc<Node<F>>,
+    degree: usize,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Var {
+    Wire(usize),
+    LocalConstant(usize),
+    PublicInputsHash(usize),
+}
+
+#[derive(Debug)]
+enum Node<F: Field> {
+    Constant(F),
+    Var(Var),
+    Add(GateExpr<F>, GateExpr<F>),
+    Sub(GateExpr<F>, GateExpr<F>),
+    Mul(GateExpr<F>, GateExpr<F>),
+    Neg(GateExpr<F>),
+}
+
+impl<F: Field> GateExpr<F> {
+    fn new(node: Node<F>, degree: usize) -> Self {
+        Self {
+            node: Arc::new(node),
+            degree,
+        }
+    }
+
+    /// A constant.
+    pub fn constant(c: F) -> Self {
+        Self::new(Node::Constant(c), 0)
+    }
+
+    /// The value of the `i`-th local wire.
+    pub fn wire(i: usize) -> Self {
+        Self::new(Node::Var(Var::Wire(i)), 1)
+    }
+
+    /// The value of the `i`-th local constant, selectors excluded.
+    pub fn local_constant(i: usize) -> Self {
+        Self::new(Node::Var(Var::LocalConstant(i)), 1)
+    }
+
+    /// The `i`-th element of the public inputs hash.
+    pub fn public_inputs_hash(i: usize) -> Self {
+        Self::new(Node::Var(Var::PublicInputsHash(i)), 0)
+    }
+
+    /// The degree of this expression in the wires and local constants.
+    pub const fn degree(&self) -> usize {
+        self.degree
+    }
+
+    fn as_constant(&self) -> Option<F> {
+        match &*self.node {
+            Node::Constant(c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    fn as_product(&self) -> Option<(&Self, &Self)> {
+        match &*self.node {
+            Node::Mul(a, b) if a.as_constant().is_none() && b.as_constant().is_none() => {
+                Some((a, b))
+            }
+            _ => None,
+        }
+    }
+
+    fn eval_with<T, V, C>(&self, var: &V, constant: &C) -> T
+    where
+        T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Neg<Output = T>,
+        V: Fn(Var) -> T,
+        C: Fn(F) -> T,
+    {
+        match &*self.node {
+            Node::Constant(c) => constant(*c),
+            Node::Var(v) => var(*v),
+            Node::Add(a, b) => a.eval_with(var, constant) + b.eval_with(var, constant),
+            Node::Sub(a, b) => a.eval_with(var, constant) - b.eval_with(var, constant),
+            Node::Mul(a, b) => a.eval_with(var, constant) * b.eval_with(var, constant),
+            Node::Neg(a) => -a.eval_with(var, constant),
+        }
+    }
+
+    /// Evaluates this expression at an extension field point.
+    pub fn eval<const D: usize>(&self, vars: EvaluationVars<F, D>) -> F::Extension
+    where
+        F: RichField + Extendable<D>,
+    {
+        self.eval_with(
+            &|v| match v {
+                Var::Wire(i) => vars.local_wires[i],
+                Var::LocalConstant(i) => vars.local_constants[i],
+                Var::PublicInputsHash(i) => {
+                    F::Extension::from_basefield(vars.public_inputs_hash.elements[i])
+                }
+            },
+            &F::Extension::from_basefield,
+        )
+    }
+
+    /// Evaluates this expression at packed base field points.
+    pub fn eval_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: &EvaluationVarsBasePacked<P>,
+    ) -> P {
+        self.eval_with(
+            &|v| match v {
+                Var::Wire(i) => vars.local_wires[i],
+                Var::LocalConstant(i) => vars.local_constants[i],
+                Var::PublicInputsHash(i) => P::from(vars.public_inputs_hash.elements[i]),
+            },
+            &P::from,
+        )
+    }
+
+    /// Evaluates this expression in a circuit. Products feeding a sum or a difference are fused
+    /// into a single arithmetic operation.
+    pub fn eval_circuit<const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &EvaluationTargets<D>,
+    ) -> ExtensionTarget<D>
+    where
+        F: RichField + Extendable<D>,
+    {
+        match &*self.node {
+            Node::Constant(c) => builder.constant_extension(F::Extension::from_basefield(*c)),
+            Node::Var(Var::Wire(i)) => vars.local_wires[*i],
+            Node::Var(Var::LocalConstant(i)) => vars.local_constants[*i],
+            Node::Var(Var::PublicInputsHash(i)) => {
+                builder.convert_to_ext(vars.public_inputs_hash.elements[*i])
+            }
+            Node::Add(a, b) => {
+                if let Some(c) = b.as_constant() {
+                    let a = a.eval_circuit(builder, vars);
+                    builder.add_const_extension(a, c)
+                } else if let Some(c) = a.as_constant() {
+                    let b = b.eval_circuit(builder, vars);
+                    builder.add_const_extension(b, c)
+                } else if let Some((x, y)) = a.as_product() {
+                    let x = x.eval_circuit(builder, vars);
+                    let y = y.eval_circuit(builder, vars);
+                    let b = b.eval_circuit(builder, vars);
+                    builder.mul_add_extension(x, y, b)
+                } else if let Some((x, y)) = b.as_product() {
+                    let x = x.eval_circuit(builder, vars);
+                    let y = y.eval_circuit(builder, vars);
+                    let a = a.eval_circuit(builder, vars);
+                    builder.mul_add_extension(x, y, a)
+                } else {
+                    let a = a.eval_circuit(builder, vars);
+                    let b = b.eval_circuit(builder, vars);
+                    builder.add_extension(a, b)
+                }
+            }
+            Node::Sub(a, b) => {
+                if let Some(c) = b.as_constant() {
+                    let a = a.eval_circuit(builder, vars);
+                    builder.add_const_extension(a, -c)
+                } else if let Some((x, y)) = a.as_product() {
+                    let x = x.eval_circuit(builder, vars);
+                    let y = y.eval_circuit(builder, vars);
+                    let b = b.eval_circuit(builder, vars);
+                    builder.mul_sub_extension(x, y, b)
+                } else if let Some((x, y)) = b.as_product() {
+                    let x = x.eval_circuit(builder, vars);
+                    let y = y.eval_circuit(builder, vars);
+                    let a = a.eval_circuit(builder, vars);
+                    builder.arithmetic_extension(F::NEG_ONE, F::ONE, x, y, a)
+                } else {
+                    let a = a.eval_circuit(builder, vars);
+                    let b = b.eval_circuit(builder, vars);
+                    builder.sub_extension(a, b)
+                }
+            }
+            Node::Mul(a, b) => {
+                if let Some(c) = a.as_constant() {
+                    let b = b.eval_circuit(builder, vars);
+                    builder.mul_const_extension(c, b)
+                } else if let Some(c) = b.as_constant() {
+                    let a = a.eval_circuit(builder, vars);
+                    builder.mul_const_extension(c, a)
+                } else {
+                    let a = a.eval_circuit(builder, vars);
+                    let b = b.eval_circuit(builder, vars);
+                    builder.mul_extension(a, b)
+                }
+            }
+            Node::Neg(a) => {
+                let a = a.eval_circuit(builder, vars);
+                builder.mul_const_extension(F::NEG_ONE, a)
+            }
+        }
+    }
+}
+
+impl<F: Field> From<F> for GateExpr<F> {
+    fn from(c: F) -> Self {
+        Self::constant(c)
+    }
+}
+
+impl<F: Field> Neg for GateExpr<F> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let degree = self.degree;
+        Self::new(Node::Neg(self), degree)
+    }
+}
+
+impl<F: Field, T: Into<GateExpr<F>>> Add<T> for GateExpr<F> {
+    type Output = Self;
+
+    fn add(self, rhs: T) -> Self {
+        let rhs = rhs.into();
+        let degree = self.degree.max(rhs.degree);
+        Self::new(Node::Add(self, rhs), degree)
+    }
+}
+
+impl<F: Field, T: Into<GateExpr<F>>> Sub<T> for GateExpr<F> {
+    type Output = Self;
+
+    fn sub(self, rhs: T) -> Self {
+        let rhs = rhs.into();
+        let degree = self.degree.max(rhs.degree);
+        Self::new(Node::Sub(self, rhs), degree)
+    }
+}
+
+impl<F: Field, T: Into<GateExpr<F>>> Mul<T> for GateExpr<F> {
+    type Output = Self;
+
+    // The degree of a product is the sum of the degrees of its factors.
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, rhs: T) -> Self {
+        let rhs = rhs.into();
+        let degree = self.degree + rhs.degree;
+        Self::new(Node::Mul(self, rhs), degree)
+    }
+}
+
+impl<F: Field> Sum for GateExpr<F> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|acc, x| acc + x)
+            .unwrap_or_else(|| Self::constant(F::ZERO))
+    }
+}
+
+/// A gate whose constraints are given as [`GateExpr`]s. Its `Gate` evaluation methods should
+/// delegate to the `*_exprs` methods of this trait, e.g.
+/// `fn eval_unfiltered(&self, vars) -> .. { self.eval_unfiltered_exprs(vars) }`.
+pub trait ExpressionGate<F: RichField + Extendable<D>, const D: usize>: Gate<F, D> {
+    /// The constraints of the gate, in the order they are yielded.
+    fn constraint_exprs(&self) -> Vec<GateExpr<F>>;
+
+    /// The maximal degree of the constraints of the gate.
+    fn exprs_degree(&self) -> usize {
+        self.constraint_exprs()
+            .iter()
+            .map(GateExpr::degree)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// An implementation of [`Gate::eval_unfiltered`] interpreting the constraint expressions.
+    fn eval_unfiltered_exprs(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        self.constraint_exprs()
+            .iter()
+            .map(|constraint| constraint.eval(vars))
+            .collect()
+    }
+
+    /// An implementation of [`Gate::eval_unfiltered_base_batch`] interpreting the constraint
+    /// expressions, which are built once for the whole batch.
+    fn eval_unfiltered_base_batch_exprs(&self, vars_batch: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        let constraints = self.constraint_exprs();
+        let mut res = vec![F::ZERO; vars_batch.len() * constraints.len()];
+        let (vars_packed_iter, vars_leftovers_iter) = vars_batch.pack::<<F as Packable>::Packing>();
+        let leftovers_start = vars_batch.len() - vars_leftovers_iter.len();
+        for (i, vars_packed) in vars_packed_iter.enumerate() {
+            StridedConstraintConsumer::new(
+                &mut res[..],
+                vars_batch.len(),
+                <F as Packable>::Packing::WIDTH * i,
+            )
+            .many(constraints.iter().map(|c| c.eval_base_packed(&vars_packed)));
+        }
+        for (i, vars_leftovers) in vars_leftovers_iter.enumerate() {
+            StridedConstraintConsumer::new(&mut res[..], vars_batch.len(), leftovers_start + i)
+                .many(
+                    constraints
+                        .iter()
+                        .map(|c| c.eval_base_packed(&vars_leftovers)),
+                );
+        }
+        res
+    }
+
+    /// An implementation of [`Gate::eval_unfiltered_circuit`] interpreting the constraint
+    /// expressions.
+    fn eval_unfiltered_circuit_exprs(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        self.constraint_exprs()
+            .iter()
+            .map(|constraint| constraint.eval_circuit(builder, &vars))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Sample;
+    use crate::hash::hash_types::HashOut;
+
+    type F = GoldilocksField;
+    type FE = <F as Extendable<2>>::Extension;
+
+    #[test]
+    fn degrees() {
+        let x = GateExpr::<F>::wire(0);
+        let c = GateExpr::local_constant(0);
+        let h = GateExpr::public_inputs_hash(0);
+        assert_eq!((x.clone() * F::TWO + h.clone()).degree(), 1);
+        assert_eq!((c.clone() * x.clone() * x.clone() - h).degree(), 3);
+        assert_eq!((-(c * x.clone()) + x).degree(), 2);
+    }
+
+    #[test]
+    fn eval() {
+        let wires = FE::rand_vec(2);
+        let constants = FE::rand_vec(1);
+        let public_inputs_hash = HashOut::rand();
+        let vars = EvaluationVars::<F, 2> {
+            local_constants: &constants,
+            local_wires: &wires,
+            public_inputs_hash: &public_inputs_hash,
+        };
+
+        let expr = GateExpr::local_constant(0) * GateExpr::wire(0) * GateExpr::wire(1)
+            - GateExpr::public_inputs_hash(3)
+            + F::TWO;
+        let expected = constants[0] * wires[0] * wires[1]
+            - <FE as FieldExtension<2>>::from_basefield(public_inputs_hash.elements[3])
+            + <FE as FieldExtension<2>>::from_basefield(F::TWO);
+        assert_eq!(expr.eval(vars), expected);
+    }
+}
@@ -1,14 +1,42 @@
 #[cfg(not(feature = "std"))]
-use alloc::{vec, vec::Vec};
+use alloc::{format, sync::Arc, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::sync::Arc;
 
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Context, Result};
 
 use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::packable::Packable;
+use crate::field::packed::PackedField;
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::field::types::{Field, Sample};
-use crate::gates::gate::Gate;
+use crate::gates::arithmetic_base::ArithmeticGate;
+use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
+use crate::gates::base_sum::BaseSumGate;
+use crate::gates::comparison::ComparisonGate;
+use crate::gates::constant::ConstantGate;
+use crate::gates::coset_interpolation::CosetInterpolationGate;
+use crate::gates::coset_interpolation_chunk::CosetInterpolationChunkGate;
+use crate::gates::exponentiation::ExponentiationGate;
+use crate::gates::exponentiation_extension::ExponentiationExtensionGate;
+use crate::gates::gate::{Gate, GateRef};
+use crate::gates::lookup::LookupGate;
+use crate::gates::lookup_table::{LookupTable, LookupTableGate};
+use crate::gates::multiplication_extension::MulExtensionGate;
+use crate::gates::multiplication_extension_algebra::MulExtensionAlgebraGate;
+use crate::gates::noop::NoopGate;
+use crate::gates::poseidon::PoseidonGate;
+use crate::gates::poseidon_mds::PoseidonMdsGate;
+use crate::gates::poseidon_sponge::PoseidonSpongeGate;
+use crate::gates::public_input::PublicInputGate;
+use crate::gates::random_access::RandomAccessGate;
+use crate::gates::reducing::ReducingGate;
+use crate::gates::reducing_extension::ReducingExtensionGate;
+use crate::gates::select::SelectGate;
 use crate::hash::hash_types::{HashOut, RichField};
-use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::iop::generator::GeneratedValues;
+use crate::iop::target::Target;
+use crate::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CircuitConfig;
 use crate::plonk::config::GenericConfig;
@@ -94,57 +122,146 @@ pub fn test_eval_fns<
 >(
     gate: G,
 ) -> Result<()> {
-    // Test that `eval_unfiltered` and `eval_unfiltered_base` are coherent.
-    let wires_base = F::rand_vec(gate.num_wires());
-    let constants_base = F::rand_vec(gate.num_constants());
-    let wires = wires_base
-        .iter()
-        .map(|&x| F::Extension::from_basefield(x))
-        .collect::<Vec<_>>();
-    let constants = constants_base
-        .iter()
-        .map(|&x| F::Extension::from_basefield(x))
-        .collect::<Vec<_>>();
-    let public_inputs_hash = HashOut::rand();
+    check_eval_fns::<F, C, G, D>(&gate)
+}
 
-    // Batch of 1.
-    let vars_base_batch =
-        EvaluationVarsBaseBatch::new(1, &constants_base, &wires_base, &public_inputs_hash);
-    let vars = EvaluationVars {
-        local_constants: &constants,
-        local_wires: &wires,
-        public_inputs_hash: &public_inputs_hash,
-    };
+/// Runs [`test_eval_fns`] on each of `gates`, e.g. the [`builtin_gates`] or the gates of a
+/// third-party crate, and returns an error naming the first gate whose evaluators diverge.
+pub fn test_gates_eval_fns<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    gates: &[GateRef<F, D>],
+) -> Result<()> {
+    for gate in gates {
+        check_eval_fns::<F, C, _, D>(&*gate.0)
+            .with_context(|| format!("Inconsistent evaluators for {}", gate.0.id()))?;
+    }
+    Ok(())
+}
 
+/// Returns an instance of every gate of this library, i.e. of every gate registered in
+/// [`GateRegistry::default`](crate::util::serialization::GateRegistry::default), with parameters
+/// suited to `config`.
+pub fn builtin_gates<F: RichField + Extendable<D>, const D: usize>(
+    config: &CircuitConfig,
+) -> Vec<GateRef<F, D>> {
+    let lut: LookupTable = Arc::new((0..8).map(|i| (i, i * i)).collect());
+    vec![
+        GateRef::new(ArithmeticGate::new_from_config(config)),
+        GateRef::new(ArithmeticExtensionGate::new_from_config(config)),
+        GateRef::new(BaseSumGate::<2>::new_from_config::<F>(config)),
+        GateRef::new(ComparisonGate::new_from_config(config, 32)),
+        GateRef::new(ConstantGate::new(config.num_constants)),
+        GateRef::new(CosetInterpolationChunkGate::<F, D>::new(
+            4,
+            0,
+            8,
+            config.max_quotient_degree_factor,
+        )),
+        GateRef::new(CosetInterpolationGate::<F, D>::with_max_degree(
+            4,
+            config.max_quotient_degree_factor,
+        )),
+        GateRef::new(ExponentiationExtensionGate::new_from_config(config, 8)),
+        GateRef::new(ExponentiationGate::<F, D>::new_from_config(config)),
+        GateRef::new(LookupGate::new_from_table(config, lut.clone())),
+        GateRef::new(LookupTableGate::new_from_table(config, lut, 0)),
+        GateRef::new(MulExtensionAlgebraGate::new_from_config(config)),
+        GateRef::new(MulExtensionGate::new_from_config(config)),
+        GateRef::new(NoopGate),
+        GateRef::new(PoseidonMdsGate::<F, D>::new()),
+        GateRef::new(PoseidonGate::<F, D>::new()),
+        GateRef::new(PoseidonSpongeGate::<F, D>::new()),
+        GateRef::new(PublicInputGate),
+        GateRef::new(RandomAccessGate::<F, D>::new_from_config(config, 4)),
+        GateRef::new(ReducingExtensionGate::new(
+            ReducingExtensionGate::<D>::max_coeffs_len(config.num_wires, config.num_routed_wires),
+        )),
+        GateRef::new(ReducingGate::new(ReducingGate::<D>::max_coeffs_len(
+            config.num_wires,
+            config.num_routed_wires,
+        ))),
+        GateRef::new(SelectGate::new_from_config(config)),
+    ]
+}
+
+fn check_eval_fns<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    G: Gate<F, D> + ?Sized,
+    const D: usize,
+>(
+    gate: &G,
+) -> Result<()> {
+    // Test that `eval_unfiltered` and `eval_unfiltered_base_batch` are coherent, on a batch with
+    // both packed points and leftovers.
+    let batch_size = 2 * <F as Packable>::Packing::WIDTH + 1;
+    let wires_base = F::rand_vec(gate.num_wires() * batch_size);
+    let constants_base = F::rand_vec(gate.num_constants() * batch_size);
+    let public_inputs_hash = HashOut::rand();
+    let vars_base_batch = EvaluationVarsBaseBatch::new(
+        batch_size,
+        &constants_base,
+        &wires_base,
+        &public_inputs_hash,
+    );
     let evals_base = gate.eval_unfiltered_base_batch(vars_base_batch);
-    let evals = gate.eval_unfiltered(vars);
-    // This works because we have a batch of 1.
-    ensure!(
-        evals
-            == evals_base
-                .into_iter()
-                .map(F::Extension::from_basefield)
+
+    for i in 0..batch_size {
+        let point_values = |batch: &[F]| {
+            batch
+                .iter()
+                .skip(i)
+                .step_by(batch_size)
+                .map(|&x| F::Extension::from_basefield(x))
                 .collect::<Vec<_>>()
-    );
+        };
+        let wires = point_values(&wires_base);
+        let constants = point_values(&constants_base);
+        let vars = EvaluationVars {
+            local_constants: &constants,
+            local_wires: &wires,
+            public_inputs_hash: &public_inputs_hash,
+        };
+        let evals = gate.eval_unfiltered(vars);
+        ensure!(evals == point_values(&evals_base));
+    }
 
     // Test that `eval_unfiltered` and `eval_unfiltered_recursively` are coherent.
     let wires = F::Extension::rand_vec(gate.num_wires());
     let constants = F::Extension::rand_vec(gate.num_constants());
+    test_recursive_evals::<F, C, G, D>(gate, &wires, &constants, public_inputs_hash)
+}
 
+/// Checks that `eval_unfiltered_circuit` computes the same values as `eval_unfiltered` at the
+/// given point, by proving it.
+fn test_recursive_evals<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    G: Gate<F, D> + ?Sized,
+    const D: usize,
+>(
+    gate: &G,
+    wires: &[F::Extension],
+    constants: &[F::Extension],
+    public_inputs_hash: HashOut<F>,
+) -> Result<()> {
     let config = CircuitConfig::standard_recursion_config();
     let mut pw = PartialWitness::new();
     let mut builder = CircuitBuilder::<F, D>::new(config);
 
     let wires_t = builder.add_virtual_extension_targets(wires.len());
     let constants_t = builder.add_virtual_extension_targets(constants.len());
-    pw.set_extension_targets(&wires_t, &wires)?;
-    pw.set_extension_targets(&constants_t, &constants)?;
+    pw.set_extension_targets(&wires_t, wires)?;
+    pw.set_extension_targets(&constants_t, constants)?;
     let public_inputs_hash_t = builder.add_virtual_hash();
     pw.set_hash_target(public_inputs_hash_t, public_inputs_hash)?;
 
     let vars = EvaluationVars {
-        local_constants: &constants,
-        local_wires: &wires,
+        local_constants: constants,
+        local_wires: wires,
         public_inputs_hash: &public_inputs_hash,
     };
     let evals = gate.eval_unfiltered(vars);
@@ -161,3 +278,230 @@ pub fn test_eval_fns<
     let proof = data.prove(pw)?;
     verify::<F, C, D>(proof, &data.verifier_only, &data.common)
 }
+
+/// Like [`test_generated_witness_with_inputs`], with random inputs.
+pub fn test_generated_witness<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    G: Gate<F, D>,
+    const D: usize,
+>(
+    gate: G,
+) -> Result<()> {
+    test_generated_witness_with_inputs::<F, C, G, D>(gate, |_| F::rand())
+}
+
+/// Tests that the generators of `gate` and its constraints agree, on random constants:
+/// - the wires computed by the generators from inputs given by `sample_input`, which is called
+///   with the index of each wire the generators need and nothing sets, satisfy all constraints,
+///   natively and in a circuit;
+/// - changing any wire computed by the generators violates some constraint, i.e. the constraints
+///   determine the generated values.
+///
+/// Wires which are neither inputs nor generated are zero, as is the public inputs hash. Gates
+/// whose generators only accept some inputs, e.g. bits or indices in range, need a
+/// `sample_input` returning such inputs.
+pub fn test_generated_witness_with_inputs<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    G: Gate<F, D>,
+    const D: usize,
+>(
+    gate: G,
+    mut sample_input: impl FnMut(usize) -> F,
+) -> Result<()> {
+    let num_wires = gate.num_wires();
+    let constants = F::rand_vec(gate.num_constants());
+    let representative_map = (0..num_wires).collect::<Vec<_>>();
+    let mut witness = PartitionWitness::new(num_wires, 1, &representative_map);
+    let mut generated = vec![false; num_wires];
+
+    let mut pending = gate.generators(0, &constants);
+    while !pending.is_empty() {
+        let mut progress = false;
+        let mut still_pending = Vec::new();
+        for generator in pending {
+            let mut buffer = GeneratedValues::empty();
+            let done = generator.0.run(&witness, &mut buffer);
+            for (target, value) in buffer.target_values {
+                let Target::Wire(wire) = target else {
+                    bail!("{} generated a virtual target", generator.0.id());
+                };
+                ensure!(
+                    wire.row == 0 && wire.column < num_wires,
+                    "{} generated a wire outside of its gate",
+                    generator.0.id()
+                );
+                if witness.set_target_returning_rep(target, value)?.is_some() {
+                    generated[wire.column] = true;
+                    progress = true;
+                }
+            }
+            if !done {
+                still_pending.push(generator);
+            }
+        }
+        pending = still_pending;
+
+        if !progress && !pending.is_empty() {
+            // The pending generators need an input.
+            let input = pending
+                .iter()
+                .flat_map(|generator| generator.0.watch_list())
+                .find(|&target| witness.try_get_target(target).is_none());
+            let Some(Target::Wire(wire)) = input else {
+                bail!(
+                    "The generators {:?} are stuck",
+                    pending.iter().map(|g| g.0.id()).collect::<Vec<_>>()
+                );
+            };
+            ensure!(wire.row == 0 && wire.column < num_wires);
+            witness.set_target(Target::Wire(wire), sample_input(wire.column))?;
+        }
+    }
+
+    let wires = (0..num_wires)
+        .map(|column| {
+            witness
+                .try_get_target(Target::wire(0, column))
+                .unwrap_or(F::ZERO)
+        })
+        .collect::<Vec<_>>();
+    let public_inputs_hash = HashOut::ZERO;
+    let evals_base = gate.eval_unfiltered_base_batch(EvaluationVarsBaseBatch::new(
+        1,
+        &constants,
+        &wires,
+        &public_inputs_hash,
+    ));
+    if let Some(i) = evals_base.iter().position(|&eval| eval != F::ZERO) {
+        bail!("Constraint {i} is not satisfied by the generated witness");
+    }
+
+    let wires_ext = wires
+        .iter()
+        .map(|&x| F::Extension::from_basefield(x))
+        .collect::<Vec<_>>();
+    let constants_ext = constants
+        .iter()
+        .map(|&x| F::Extension::from_basefield(x))
+        .collect::<Vec<_>>();
+    for column in (0..num_wires).filter(|&column| generated[column]) {
+        let mut perturbed = wires_ext.clone();
+        perturbed[column] += F::Extension::ONE;
+        let evals = gate.eval_unfiltered(EvaluationVars {
+            local_constants: &constants_ext,
+            local_wires: &perturbed,
+            public_inputs_hash: &public_inputs_hash,
+        });
+        ensure!(
+            evals.iter().any(|&eval| eval != F::Extension::ZERO),
+            "Changing the generated wire {column} does not violate any constraint"
+        );
+    }
+
+    test_recursive_evals::<F, C, G, D>(&gate, &wires_ext, &constants_ext, public_inputs_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashSet;
+
+    use super::*;
+    use crate::iop::ext_target::ExtensionTarget;
+    use crate::iop::generator::WitnessGeneratorRef;
+    use crate::plonk::circuit_data::CommonCircuitData;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::util::serialization::{Buffer, GateRegistry, IoResult};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn builtin_gates_eval_fns() -> Result<()> {
+        let gates = builtin_gates::<F, D>(&CircuitConfig::standard_recursion_config());
+
+        // Every gate of the library is checked.
+        let registry = GateRegistry::<F, D>::default();
+        assert!(gates.iter().all(|gate| registry.contains(gate)));
+        let gate_types = gates
+            .iter()
+            .map(|gate| gate.0.as_any().type_id())
+            .collect::<HashSet<_>>();
+        assert_eq!(gate_types.len(), registry.num_gates());
+
+        test_gates_eval_fns::<F, C, D>(&gates)
+    }
+
+    /// A gate constraining `wire_1 = wire_0^2`, whose recursive evaluator forgets the square.
+    #[derive(Debug)]
+    struct DivergentGate;
+
+    impl Gate<F, D> for DivergentGate {
+        fn id(&self) -> String {
+            "DivergentGate".into()
+        }
+
+        fn serialize(
+            &self,
+            _dst: &mut Vec<u8>,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<()> {
+            Ok(())
+        }
+
+        fn deserialize(
+            _src: &mut Buffer,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<Self> {
+            Ok(Self)
+        }
+
+        fn eval_unfiltered(
+            &self,
+            vars: EvaluationVars<F, D>,
+        ) -> Vec<<F as Extendable<D>>::Extension> {
+            vec![vars.local_wires[1] - vars.local_wires[0] * vars.local_wires[0]]
+        }
+
+        fn eval_unfiltered_circuit(
+            &self,
+            builder: &mut CircuitBuilder<F, D>,
+            vars: EvaluationTargets<D>,
+        ) -> Vec<ExtensionTarget<D>> {
+            vec![builder.sub_extension(vars.local_wires[1], vars.local_wires[0])]
+        }
+
+        fn generators(
+            &self,
+            _row: usize,
+            _local_constants: &[F],
+        ) -> Vec<WitnessGeneratorRef<F, D>> {
+            Vec::new()
+        }
+
+        fn num_wires(&self) -> usize {
+            2
+        }
+
+        fn num_constants(&self) -> usize {
+            0
+        }
+
+        fn degree(&self) -> usize {
+            2
+        }
+
+        fn num_constraints(&self) -> usize {
+            1
+        }
+    }
+
+    #[test]
+    fn divergent_gate_eval_fns() {
+        let gates = [GateRef::new(NoopGate), GateRef::new(DivergentGate)];
+        let err = test_gates_eval_fns::<F, C, D>(&gates).unwrap_err();
+        assert!(format!("{err}").contains("DivergentGate"));
+    }
+}
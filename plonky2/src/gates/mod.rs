@@ -26,21 +26,28 @@
 pub mod arithmetic_base;
 pub mod arithmetic_extension;
 pub mod base_sum;
+pub mod comparison;
 pub mod constant;
 pub mod coset_interpolation;
+pub mod coset_interpolation_chunk;
 pub mod exponentiation;
+pub mod exponentiation_extension;
+pub mod expression;
 pub mod gate;
 pub mod lookup;
 pub mod lookup_table;
 pub mod multiplication_extension;
+pub mod multiplication_extension_algebra;
 pub mod noop;
 pub mod packed_util;
 pub mod poseidon;
 pub mod poseidon_mds;
+pub mod poseidon_sponge;
 pub mod public_input;
 pub mod random_access;
 pub mod reducing;
 pub mod reducing_extension;
+pub mod select;
 pub(crate) mod selectors;
 pub mod util;
 
@@ -0,0 +1,357 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops::{Add, Mul, Range, Sub};
+
+use anyhow::Result;
+
+use crate::field::extension::algebra::ExtensionAlgebra;
+use crate::field::extension::packed::PackedExtension;
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::packed::PackedField;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::{ExtensionAlgebraTarget, ExtensionTarget};
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// A gate which can perform a full multiply-add `result = x.y + z` on [`ExtensionAlgebraTarget`]s,
+/// i.e. on elements of `F_D[Y]/(Y^D - W)` whose `D` components are [`ExtensionTarget`]s.
+///
+/// Such products are what the recursive verifier spends most of its time on when evaluating gate
+/// constraints, as every extension wire opened at an extension point is an algebra element. A
+/// single operation uses `4 D^2` routed wires, where the same product built from
+/// `ArithmeticExtensionGate` slots uses `8 D^2` of them, and its constraints have degree 2.
+#[derive(Debug, Clone)]
+pub struct MulExtensionAlgebraGate<const D: usize> {
+    /// Number of multiply-adds performed by the gate.
+    pub num_ops: usize,
+}
+
+impl<const D: usize> MulExtensionAlgebraGate<D> {
+    pub const fn new_from_config(config: &CircuitConfig) -> Self {
+        Self {
+            num_ops: Self::num_ops(config),
+        }
+    }
+
+    /// Determine the maximum number of operations that can fit in one gate for the given config.
+    pub(crate) const fn num_ops(config: &CircuitConfig) -> usize {
+        let wires_per_op = 4 * D * D;
+        config.num_routed_wires / wires_per_op
+    }
+
+    const fn wires_component(i: usize, operand: usize, j: usize) -> Range<usize> {
+        let start = 4 * D * D * i + operand * D * D + j * D;
+        start..start + D
+    }
+    pub(crate) const fn wires_ith_multiplicand_0(i: usize, j: usize) -> Range<usize> {
+        Self::wires_component(i, 0, j)
+    }
+    pub(crate) const fn wires_ith_multiplicand_1(i: usize, j: usize) -> Range<usize> {
+        Self::wires_component(i, 1, j)
+    }
+    pub(crate) const fn wires_ith_addend(i: usize, j: usize) -> Range<usize> {
+        Self::wires_component(i, 2, j)
+    }
+    pub(crate) const fn wires_ith_output(i: usize, j: usize) -> Range<usize> {
+        Self::wires_component(i, 3, j)
+    }
+}
+
+/// Computes `a.b + c` in `T[Y]/(Y^D - W)`, where `mul_w` multiplies a component by `W`.
+///
+/// For `D = 2` the cross term uses the Karatsuba identity
+/// `a0.b1 + a1.b0 = (a0 + a1).(b0 + b1) - a0.b0 - a1.b1`, trading a multiplication for three
+/// additions.
+fn mul_add_components<T, const D: usize>(
+    a: [T; D],
+    b: [T; D],
+    c: [T; D],
+    mul_w: impl Fn(T) -> T,
+) -> [T; D]
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+{
+    let mut res = c;
+    if D == 2 {
+        let p0 = a[0] * b[0];
+        let p1 = a[1] * b[1];
+        let cross = (a[0] + a[1]) * (b[0] + b[1]) - p0 - p1;
+        res[0] = res[0] + p0 + mul_w(p1);
+        res[1] = res[1] + cross;
+        return res;
+    }
+
+    for i in 0..D {
+        for j in 0..D {
+            let p = a[i] * b[j];
+            res[(i + j) % D] = res[(i + j) % D] + if i + j < D { p } else { mul_w(p) };
+        }
+    }
+    res
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for MulExtensionAlgebraGate<D> {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.num_ops)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let num_ops = src.read_usize()?;
+        Ok(Self { num_ops })
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let w = F::Extension::from_basefield(F::W);
+        let get = |range: fn(usize, usize) -> Range<usize>, i: usize| -> [_; D] {
+            core::array::from_fn(|j| vars.get_local_ext_algebra(range(i, j)))
+        };
+
+        let mut constraints = Vec::with_capacity(self.num_ops * D * D);
+        for i in 0..self.num_ops {
+            let computed_output = mul_add_components(
+                get(Self::wires_ith_multiplicand_0, i),
+                get(Self::wires_ith_multiplicand_1, i),
+                get(Self::wires_ith_addend, i),
+                |x: ExtensionAlgebra<F::Extension, D>| x.scalar_mul(w),
+            );
+            let output = get(Self::wires_ith_output, i);
+            for (out, computed) in output.into_iter().zip(computed_output) {
+                constraints.extend((out - computed).to_basefield_array());
+            }
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let w = builder.constant_extension(F::Extension::from_basefield(F::W));
+        let get = |range: fn(usize, usize) -> Range<usize>, i: usize| -> [_; D] {
+            core::array::from_fn(|j| vars.get_local_ext_algebra(range(i, j)))
+        };
+
+        let mut constraints = Vec::with_capacity(self.num_ops * D * D);
+        for i in 0..self.num_ops {
+            let multiplicand_0 = get(Self::wires_ith_multiplicand_0, i);
+            let multiplicand_1 = get(Self::wires_ith_multiplicand_1, i);
+            let mut computed_output = get(Self::wires_ith_addend, i);
+            let output = get(Self::wires_ith_output, i);
+
+            // Karatsuba doesn't pay off in-circuit, where additions cost as much as
+            // multiplications, so we use the schoolbook product with `W` folded into `y`.
+            let multiplicand_1_w: [ExtensionAlgebraTarget<D>; D] = core::array::from_fn(|k| {
+                if k == 0 {
+                    multiplicand_1[0]
+                } else {
+                    builder.scalar_mul_ext_algebra(w, multiplicand_1[k])
+                }
+            });
+            for j in 0..D {
+                for k in 0..D {
+                    let y = if j + k < D {
+                        multiplicand_1[k]
+                    } else {
+                        multiplicand_1_w[k]
+                    };
+                    let acc = &mut computed_output[(j + k) % D];
+                    *acc = builder.mul_add_ext_algebra(multiplicand_0[j], y, *acc);
+                }
+            }
+
+            for (out, computed) in output.into_iter().zip(computed_output) {
+                let diff = builder.sub_ext_algebra(out, computed);
+                constraints.extend(diff.to_ext_target_array());
+            }
+        }
+
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        (0..self.num_ops)
+            .map(|i| WitnessGeneratorRef::new(MulExtensionAlgebraGenerator { row, i }.adapter()))
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * 4 * D * D
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        2
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops * D * D
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for MulExtensionAlgebraGate<D>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        let get =
+            |range: fn(usize, usize) -> Range<usize>, i: usize| -> [PackedExtension<P, D>; D] {
+                core::array::from_fn(|j| vars.get_local_ext(range(i, j)))
+            };
+
+        for i in 0..self.num_ops {
+            let computed_output = mul_add_components(
+                get(Self::wires_ith_multiplicand_0, i),
+                get(Self::wires_ith_multiplicand_1, i),
+                get(Self::wires_ith_addend, i),
+                |x: PackedExtension<P, D>| x.scalar_mul(F::W.into()),
+            );
+            let output = get(Self::wires_ith_output, i);
+            for (out, computed) in output.into_iter().zip(computed_output) {
+                yield_constr.many((out - computed).to_basefield_array());
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MulExtensionAlgebraGenerator<const D: usize> {
+    row: usize,
+    i: usize,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for MulExtensionAlgebraGenerator<D>
+{
+    fn id(&self) -> String {
+        "MulExtensionAlgebraGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        (0..D)
+            .flat_map(|j| {
+                MulExtensionAlgebraGate::<D>::wires_ith_multiplicand_0(self.i, j)
+                    .chain(MulExtensionAlgebraGate::<D>::wires_ith_multiplicand_1(
+                        self.i, j,
+                    ))
+                    .chain(MulExtensionAlgebraGate::<D>::wires_ith_addend(self.i, j))
+            })
+            .map(|i| Target::wire(self.row, i))
+            .collect()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let get = |range: fn(usize, usize) -> Range<usize>| -> [F::Extension; D] {
+            core::array::from_fn(|j| {
+                witness
+                    .get_extension_target(ExtensionTarget::from_range(self.row, range(self.i, j)))
+            })
+        };
+
+        let computed_output = mul_add_components(
+            get(MulExtensionAlgebraGate::<D>::wires_ith_multiplicand_0),
+            get(MulExtensionAlgebraGate::<D>::wires_ith_multiplicand_1),
+            get(MulExtensionAlgebraGate::<D>::wires_ith_addend),
+            |x: F::Extension| x.scalar_mul(F::W),
+        );
+
+        for (j, value) in computed_output.into_iter().enumerate() {
+            let output_target = ExtensionTarget::from_range(
+                self.row,
+                MulExtensionAlgebraGate::<D>::wires_ith_output(self.i, j),
+            );
+            out_buffer.set_extension_target(output_target, value)?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)?;
+        dst.write_usize(self.i)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        let i = src.read_usize()?;
+        Ok(Self { row, i })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::gates::gate_testing::{test_eval_fns, test_generated_witness, test_low_degree};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn low_degree() {
+        let gate =
+            MulExtensionAlgebraGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_low_degree::<GoldilocksField, _, 4>(gate);
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate =
+            MulExtensionAlgebraGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_eval_fns::<F, C, _, D>(gate)
+    }
+
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate =
+            MulExtensionAlgebraGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_generated_witness::<F, C, _, D>(gate)
+    }
+}
@@ -10,8 +10,10 @@ use core::marker::PhantomData;
 use anyhow::Result;
 
 use crate::field::extension::Extendable;
+use crate::field::packed::PackedField;
 use crate::field::types::Field;
 use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
 use crate::gates::poseidon_mds::PoseidonMdsGate;
 use crate::gates::util::StridedConstraintConsumer;
 use crate::hash::hash_types::RichField;
@@ -24,7 +26,10 @@ use crate::iop::wire::Wire;
 use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CommonCircuitData;
-use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 /// Evaluates a full Poseidon permutation with 12 state elements.
@@ -203,83 +208,14 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for PoseidonGate<F
 
     fn eval_unfiltered_base_one(
         &self,
-        vars: EvaluationVarsBase<F>,
-        mut yield_constr: StridedConstraintConsumer<F>,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
     ) {
-        // Assert that `swap` is binary.
-        let swap = vars.local_wires[Self::WIRE_SWAP];
-        yield_constr.one(swap * swap.sub_one());
-
-        // Assert that each delta wire is set properly: `delta_i = swap * (rhs - lhs)`.
-        for i in 0..4 {
-            let input_lhs = vars.local_wires[Self::wire_input(i)];
-            let input_rhs = vars.local_wires[Self::wire_input(i + 4)];
-            let delta_i = vars.local_wires[Self::wire_delta(i)];
-            yield_constr.one(swap * (input_rhs - input_lhs) - delta_i);
-        }
-
-        // Compute the possibly-swapped input layer.
-        let mut state = [F::ZERO; SPONGE_WIDTH];
-        for i in 0..4 {
-            let delta_i = vars.local_wires[Self::wire_delta(i)];
-            let input_lhs = Self::wire_input(i);
-            let input_rhs = Self::wire_input(i + 4);
-            state[i] = vars.local_wires[input_lhs] + delta_i;
-            state[i + 4] = vars.local_wires[input_rhs] - delta_i;
-        }
-        for i in 8..SPONGE_WIDTH {
-            state[i] = vars.local_wires[Self::wire_input(i)];
-        }
-
-        let mut round_ctr = 0;
-
-        // First set of full rounds.
-        for r in 0..poseidon::HALF_N_FULL_ROUNDS {
-            <F as Poseidon>::constant_layer(&mut state, round_ctr);
-            if r != 0 {
-                for i in 0..SPONGE_WIDTH {
-                    let sbox_in = vars.local_wires[Self::wire_full_sbox_0(r, i)];
-                    yield_constr.one(state[i] - sbox_in);
-                    state[i] = sbox_in;
-                }
-            }
-            <F as Poseidon>::sbox_layer(&mut state);
-            state = <F as Poseidon>::mds_layer(&state);
-            round_ctr += 1;
-        }
-
-        // Partial rounds.
-        <F as Poseidon>::partial_first_constant_layer(&mut state);
-        state = <F as Poseidon>::mds_partial_layer_init(&state);
-        for r in 0..(poseidon::N_PARTIAL_ROUNDS - 1) {
-            let sbox_in = vars.local_wires[Self::wire_partial_sbox(r)];
-            yield_constr.one(state[0] - sbox_in);
-            state[0] = <F as Poseidon>::sbox_monomial(sbox_in);
-            state[0] += F::from_canonical_u64(<F as Poseidon>::FAST_PARTIAL_ROUND_CONSTANTS[r]);
-            state = <F as Poseidon>::mds_partial_layer_fast(&state, r);
-        }
-        let sbox_in = vars.local_wires[Self::wire_partial_sbox(poseidon::N_PARTIAL_ROUNDS - 1)];
-        yield_constr.one(state[0] - sbox_in);
-        state[0] = <F as Poseidon>::sbox_monomial(sbox_in);
-        state = <F as Poseidon>::mds_partial_layer_fast(&state, poseidon::N_PARTIAL_ROUNDS - 1);
-        round_ctr += poseidon::N_PARTIAL_ROUNDS;
-
-        // Second set of full rounds.
-        for r in 0..poseidon::HALF_N_FULL_ROUNDS {
-            <F as Poseidon>::constant_layer(&mut state, round_ctr);
-            for i in 0..SPONGE_WIDTH {
-                let sbox_in = vars.local_wires[Self::wire_full_sbox_1(r, i)];
-                yield_constr.one(state[i] - sbox_in);
-                state[i] = sbox_in;
-            }
-            <F as Poseidon>::sbox_layer(&mut state);
-            state = <F as Poseidon>::mds_layer(&state);
-            round_ctr += 1;
-        }
+        panic!("use eval_unfiltered_base_packed instead");
+    }
 
-        for i in 0..SPONGE_WIDTH {
-            yield_constr.one(state[i] - vars.local_wires[Self::wire_output(i)]);
-        }
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
     }
 
     fn eval_unfiltered_circuit(
@@ -420,6 +356,94 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for PoseidonGate<F
     }
 }
 
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for PoseidonGate<F, D>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        // Assert that `swap` is binary.
+        let swap = vars.local_wires[Self::WIRE_SWAP];
+        yield_constr.one(swap * (swap - P::ONES));
+
+        // Assert that each delta wire is set properly: `delta_i = swap * (rhs - lhs)`.
+        for i in 0..4 {
+            let input_lhs = vars.local_wires[Self::wire_input(i)];
+            let input_rhs = vars.local_wires[Self::wire_input(i + 4)];
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            yield_constr.one(swap * (input_rhs - input_lhs) - delta_i);
+        }
+
+        // Compute the possibly-swapped input layer.
+        let mut state = [P::ZEROS; SPONGE_WIDTH];
+        for i in 0..4 {
+            let delta_i = vars.local_wires[Self::wire_delta(i)];
+            let input_lhs = Self::wire_input(i);
+            let input_rhs = Self::wire_input(i + 4);
+            state[i] = vars.local_wires[input_lhs] + delta_i;
+            state[i + 4] = vars.local_wires[input_rhs] - delta_i;
+        }
+        for i in 8..SPONGE_WIDTH {
+            state[i] = vars.local_wires[Self::wire_input(i)];
+        }
+
+        let mut round_ctr = 0;
+
+        // First set of full rounds.
+        for r in 0..poseidon::HALF_N_FULL_ROUNDS {
+            <F as Poseidon>::constant_layer_packed_field(&mut state, round_ctr);
+            if r != 0 {
+                for i in 0..SPONGE_WIDTH {
+                    let sbox_in = vars.local_wires[Self::wire_full_sbox_0(r, i)];
+                    yield_constr.one(state[i] - sbox_in);
+                    state[i] = sbox_in;
+                }
+            }
+            <F as Poseidon>::sbox_layer_packed_field(&mut state);
+            state = <F as Poseidon>::mds_layer_packed_field(&state);
+            round_ctr += 1;
+        }
+
+        // Partial rounds.
+        <F as Poseidon>::partial_first_constant_layer_packed_field(&mut state);
+        state = <F as Poseidon>::mds_partial_layer_init_packed_field(&state);
+        for r in 0..(poseidon::N_PARTIAL_ROUNDS - 1) {
+            let sbox_in = vars.local_wires[Self::wire_partial_sbox(r)];
+            yield_constr.one(state[0] - sbox_in);
+            state[0] = <F as Poseidon>::sbox_monomial_packed_field(sbox_in);
+            state[0] += F::from_canonical_u64(<F as Poseidon>::FAST_PARTIAL_ROUND_CONSTANTS[r]);
+            state = <F as Poseidon>::mds_partial_layer_fast_packed_field(&state, r);
+        }
+        let sbox_in = vars.local_wires[Self::wire_partial_sbox(poseidon::N_PARTIAL_ROUNDS - 1)];
+        yield_constr.one(state[0] - sbox_in);
+        state[0] = <F as Poseidon>::sbox_monomial_packed_field(sbox_in);
+        state = <F as Poseidon>::mds_partial_layer_fast_packed_field(
+            &state,
+            poseidon::N_PARTIAL_ROUNDS - 1,
+        );
+        round_ctr += poseidon::N_PARTIAL_ROUNDS;
+
+        // Second set of full rounds.
+        for r in 0..poseidon::HALF_N_FULL_ROUNDS {
+            <F as Poseidon>::constant_layer_packed_field(&mut state, round_ctr);
+            for i in 0..SPONGE_WIDTH {
+                let sbox_in = vars.local_wires[Self::wire_full_sbox_1(r, i)];
+                yield_constr.one(state[i] - sbox_in);
+                state[i] = sbox_in;
+            }
+            <F as Poseidon>::sbox_layer_packed_field(&mut state);
+            state = <F as Poseidon>::mds_layer_packed_field(&state);
+            round_ctr += 1;
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            yield_constr.one(state[i] - vars.local_wires[Self::wire_output(i)]);
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct PoseidonGenerator<F: RichField + Extendable<D> + Poseidon, const D: usize> {
     row: usize,
@@ -546,9 +570,14 @@ impl<F: RichField + Extendable<D> + Poseidon, const D: usize> SimpleGenerator<F,
 mod tests {
     use anyhow::Result;
     use plonky2_field::goldilocks_field::GoldilocksField;
+    use rand::rngs::OsRng;
+    use rand::Rng;
 
     use super::*;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::field::types::Sample;
+    use crate::gates::gate_testing::{
+        test_eval_fns, test_generated_witness_with_inputs, test_low_degree,
+    };
     use crate::iop::generator::generate_partial_witness;
     use crate::iop::witness::PartialWitness;
     use crate::plonk::circuit_data::CircuitConfig;
@@ -647,4 +676,19 @@ mod tests {
         let gate = PoseidonGate::<F, 2>::new();
         test_eval_fns::<F, C, _, D>(gate)
     }
+
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = PoseidonGate::<F, 2>::new();
+        test_generated_witness_with_inputs::<F, C, _, D>(gate, |column| {
+            if column == PoseidonGate::<F, D>::WIRE_SWAP {
+                F::from_bool(OsRng.gen())
+            } else {
+                F::rand()
+            }
+        })
+    }
 }
@@ -12,8 +12,11 @@ use anyhow::Result;
 
 use crate::field::extension::algebra::ExtensionAlgebra;
 use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::packed::PackedField;
+use crate::field::polynomial::PolynomialCoeffs;
 use crate::field::types::Field;
 use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
 use crate::gates::util::StridedConstraintConsumer;
 use crate::hash::hash_types::RichField;
 use crate::hash::poseidon::{Poseidon, SPONGE_WIDTH};
@@ -23,7 +26,10 @@ use crate::iop::target::Target;
 use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CommonCircuitData;
-use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 /// Poseidon MDS Gate
@@ -109,6 +115,27 @@ impl<F: RichField + Extendable<D> + Poseidon, const D: usize> PoseidonMdsGate<F,
     fn mds_layer_algebra_circuit(
         builder: &mut CircuitBuilder<F, D>,
         state: &[ExtensionAlgebraTarget<D>; SPONGE_WIDTH],
+    ) -> [ExtensionAlgebraTarget<D>; SPONGE_WIDTH] {
+        if !Self::supports_ntt() {
+            return Self::mds_layer_algebra_circuit_dense(builder, state);
+        }
+
+        // The MDS matrix has base field entries, so it acts on each extension component separately.
+        let mut result = [builder.zero_ext_algebra(); SPONGE_WIDTH];
+        for k in 0..D {
+            let outputs = Self::mds_layer_ntt_circuit(builder, &state.map(|x| x.0[k]));
+            for (res, out) in result.iter_mut().zip(outputs) {
+                res.0[k] = out;
+            }
+        }
+
+        result
+    }
+
+    /// Like `mds_layer_algebra_circuit`, with one row of the MDS matrix at a time.
+    fn mds_layer_algebra_circuit_dense(
+        builder: &mut CircuitBuilder<F, D>,
+        state: &[ExtensionAlgebraTarget<D>; SPONGE_WIDTH],
     ) -> [ExtensionAlgebraTarget<D>; SPONGE_WIDTH] {
         let mut result = [builder.zero_ext_algebra(); SPONGE_WIDTH];
 
@@ -118,6 +145,106 @@ impl<F: RichField + Extendable<D> + Poseidon, const D: usize> PoseidonMdsGate<F,
 
         result
     }
+
+    /// Whether `F` has roots of unity of order `SPONGE_WIDTH`, as needed by
+    /// `mds_layer_ntt_circuit`.
+    fn supports_ntt() -> bool {
+        (F::ORDER - 1) % SPONGE_WIDTH as u64 == 0
+    }
+
+    /// Recursive version of `mds_layer`, exploiting the structure `circ(c) + diag(d)` of the MDS
+    /// matrix. Row `r` of `circ(c) v` is `sum_i c_i v_{i + r}`, i.e. `circ(c) v` is the cyclic
+    /// convolution of `v` with `c'_i = c_{-i}`, which is computed in the NTT domain as
+    /// `NTT^-1(NTT(c') * NTT(v))` with `NTT(c')` precomputed. Using a Good-Thomas NTT for
+    /// `SPONGE_WIDTH = 3 * 4`, this takes about 30% fewer arithmetic operations than the
+    /// row-by-row product.
+    fn mds_layer_ntt_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        v: &[ExtensionTarget<D>; SPONGE_WIDTH],
+    ) -> [ExtensionTarget<D>; SPONGE_WIDTH] {
+        let omega = F::primitive_root_of_unity_of_order(SPONGE_WIDTH);
+        // `NTT(c')`, scaled by `1 / SPONGE_WIDTH` to account for the unscaled inverse NTT.
+        let c_ntt = PolynomialCoeffs::new(
+            (0..SPONGE_WIDTH)
+                .map(|i| {
+                    F::from_canonical_u64(
+                        <F as Poseidon>::MDS_MATRIX_CIRC[(SPONGE_WIDTH - i) % SPONGE_WIDTH],
+                    )
+                })
+                .collect(),
+        )
+        .mixed_radix_fft()
+        .values;
+        let width_inv = F::from_canonical_usize(SPONGE_WIDTH).inverse();
+
+        let v_ntt = Self::ntt_circuit(builder, v, omega, [F::ONE; SPONGE_WIDTH]);
+        let scales = core::array::from_fn(|k| c_ntt[k] * width_inv);
+        let mut result = Self::ntt_circuit(builder, &v_ntt, omega.inverse(), scales);
+
+        for (r, res) in result.iter_mut().enumerate() {
+            let d = <F as Poseidon>::MDS_MATRIX_DIAG[r];
+            if d != 0 {
+                let d = builder.constant_extension(F::Extension::from_canonical_u64(d));
+                *res = builder.mul_add_extension(d, v[r], *res);
+            }
+        }
+
+        result
+    }
+
+    /// Computes `X_k = sum_n s_n x_n omega^(n k)` for a root of unity `omega` of order
+    /// `SPONGE_WIDTH = 12` and scales `s_n`, with the Good-Thomas algorithm: writing
+    /// `n = 4 n_1 + 3 n_2` and `k = 4 k_1 + 9 k_2` modulo 12,
+    /// `omega^(n k) = (omega^4)^(n_1 k_1) (omega^3)^(n_2 k_2)`, so the NTT splits into NTTs of size
+    /// 3 and 4 without twiddle factors. The scales are folded into the size 3 NTTs.
+    fn ntt_circuit(
+        builder: &mut CircuitBuilder<F, D>,
+        x: &[ExtensionTarget<D>; SPONGE_WIDTH],
+        omega: F,
+        scales: [F; SPONGE_WIDTH],
+    ) -> [ExtensionTarget<D>; SPONGE_WIDTH] {
+        debug_assert_eq!(SPONGE_WIDTH, 12);
+        let w = omega.exp_u64(4);
+        let i = omega.exp_u64(3);
+        let [i, neg_i] =
+            [i, -i].map(|c| builder.constant_extension(F::Extension::from_basefield(c)));
+
+        // Size 3 NTTs over `n_1`, for each `n_2`.
+        let mut y = [[builder.zero_extension(); 4]; 3];
+        for n_2 in 0..4 {
+            let [n_a, n_b, n_c] = [0, 1, 2].map(|n_1| (4 * n_1 + 3 * n_2) % 12);
+            let [a, b, c] = [x[n_a], x[n_b], x[n_c]];
+            let a = if scales[n_a] == F::ONE {
+                a
+            } else {
+                let s_a = builder.constant_extension(F::Extension::from_basefield(scales[n_a]));
+                builder.mul_extension(s_a, a)
+            };
+            for (k_1, y_k_1) in y.iter_mut().enumerate() {
+                let coeff_b = scales[n_b] * w.exp_u64(k_1 as u64);
+                let coeff_c = scales[n_c] * w.exp_u64(2 * k_1 as u64);
+                let coeff_b = builder.constant_extension(F::Extension::from_basefield(coeff_b));
+                let coeff_c = builder.constant_extension(F::Extension::from_basefield(coeff_c));
+                let partial = builder.mul_add_extension(coeff_b, b, a);
+                y_k_1[n_2] = builder.mul_add_extension(coeff_c, c, partial);
+            }
+        }
+
+        // Size 4 NTTs over `n_2`, for each `k_1`.
+        let mut result = [builder.zero_extension(); SPONGE_WIDTH];
+        for (k_1, [a, b, c, d]) in y.into_iter().enumerate() {
+            let a_plus_c = builder.add_extension(a, c);
+            let a_minus_c = builder.sub_extension(a, c);
+            let b_plus_d = builder.add_extension(b, d);
+            let b_minus_d = builder.sub_extension(b, d);
+            result[(4 * k_1) % 12] = builder.add_extension(a_plus_c, b_plus_d);
+            result[(4 * k_1 + 9) % 12] = builder.mul_add_extension(i, b_minus_d, a_minus_c);
+            result[(4 * k_1 + 18) % 12] = builder.sub_extension(a_plus_c, b_plus_d);
+            result[(4 * k_1 + 27) % 12] = builder.mul_add_extension(neg_i, b_minus_d, a_minus_c);
+        }
+
+        result
+    }
 }
 
 impl<F: RichField + Extendable<D> + Poseidon, const D: usize> Gate<F, D> for PoseidonMdsGate<F, D> {
@@ -155,23 +282,14 @@ impl<F: RichField + Extendable<D> + Poseidon, const D: usize> Gate<F, D> for Pos
 
     fn eval_unfiltered_base_one(
         &self,
-        vars: EvaluationVarsBase<F>,
-        mut yield_constr: StridedConstraintConsumer<F>,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
     ) {
-        let inputs: [_; SPONGE_WIDTH] = (0..SPONGE_WIDTH)
-            .map(|i| vars.get_local_ext(Self::wires_input(i)))
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap();
-
-        let computed_outputs = F::mds_layer_field(&inputs);
+        panic!("use eval_unfiltered_base_packed instead");
+    }
 
-        yield_constr.many(
-            (0..SPONGE_WIDTH)
-                .map(|i| vars.get_local_ext(Self::wires_output(i)))
-                .zip(computed_outputs)
-                .flat_map(|(out, computed_out)| (out - computed_out).to_basefield_array()),
-        )
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
     }
 
     fn eval_unfiltered_circuit(
@@ -220,6 +338,32 @@ impl<F: RichField + Extendable<D> + Poseidon, const D: usize> Gate<F, D> for Pos
     }
 }
 
+impl<F: RichField + Extendable<D> + Poseidon, const D: usize> PackedEvaluableBase<F, D>
+    for PoseidonMdsGate<F, D>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        let inputs: [_; SPONGE_WIDTH] = (0..SPONGE_WIDTH)
+            .map(|i| vars.get_local_ext(Self::wires_input(i)))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        // The MDS matrix has base field entries, so it acts on each extension component separately.
+        let computed_outputs: [[P; SPONGE_WIDTH]; D] = core::array::from_fn(|k| {
+            F::mds_layer_packed_field(&inputs.map(|x| x.to_basefield_array()[k]))
+        });
+
+        for i in 0..SPONGE_WIDTH {
+            let out = vars.get_local_ext(Self::wires_output(i));
+            yield_constr.many((0..D).map(|k| out.0[k] - computed_outputs[k][i]));
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct PoseidonMdsGenerator<const D: usize> {
     row: usize,
@@ -279,8 +423,11 @@ impl<F: RichField + Extendable<D> + Poseidon, const D: usize> SimpleGenerator<F,
 
 #[cfg(test)]
 mod tests {
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generated_witness, test_low_degree};
     use crate::gates::poseidon_mds::PoseidonMdsGate;
+    use crate::iop::ext_target::ExtensionAlgebraTarget;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
     #[test]
@@ -300,4 +447,41 @@ mod tests {
         let gate = PoseidonMdsGate::<F, D>::new();
         test_eval_fns::<F, C, _, D>(gate)
     }
+
+    #[test]
+    fn generated_witness() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = PoseidonMdsGate::<F, D>::new();
+        test_generated_witness::<F, C, _, D>(gate)
+    }
+
+    #[test]
+    fn ntt_circuit_cost() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type G = PoseidonMdsGate<F, D>;
+        assert!(G::supports_ntt());
+
+        let num_gates = |dense: bool| {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let state = core::array::from_fn(|_| {
+                ExtensionAlgebraTarget(builder.add_virtual_extension_targets(D).try_into().unwrap())
+            });
+            if dense {
+                G::mds_layer_algebra_circuit_dense(&mut builder, &state);
+            } else {
+                G::mds_layer_algebra_circuit(&mut builder, &state);
+            }
+            builder.num_gates()
+        };
+        let (dense, ntt) = (num_gates(true), num_gates(false));
+        assert!(
+            4 * ntt < 3 * dense,
+            "{ntt} gates with the NTT, {dense} without"
+        );
+    }
 }
@@ -0,0 +1,685 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use anyhow::Result;
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::packed::PackedField;
+use crate::field::types::Field;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::hash::poseidon;
+use crate::hash::poseidon::{Poseidon, SPONGE_RATE, SPONGE_WIDTH};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// Evaluates two chained Poseidon permutations, absorbing a block of `SPONGE_RATE` elements in
+/// between as in the overwrite-mode sponge: the rate of the first permutation's output is replaced
+/// by the block before applying the second permutation.
+///
+/// This halves the number of rows used to hash long inputs, such as the Merkle leaves opened by a
+/// recursive verifier, but requires 248 wires; see [`PoseidonSpongeGate::is_supported`]. The
+/// 135 wires of the [standard recursion config](CircuitConfig::standard_recursion_config) aren't
+/// enough, so with it hashing falls back to one [`PoseidonGate`](crate::gates::poseidon::PoseidonGate)
+/// per permutation. Use [`CircuitConfig::wide_poseidon_config`] to benefit from this gate.
+#[derive(Debug, Default)]
+pub struct PoseidonSpongeGate<F: RichField + Extendable<D>, const D: usize>(PhantomData<F>);
+
+/// The number of S-box input wires of a single permutation.
+const PERMUTATION_WIRES: usize =
+    SPONGE_WIDTH * (poseidon::N_FULL_ROUNDS_TOTAL - 1) + poseidon::N_PARTIAL_ROUNDS;
+
+/// The offset, within a permutation's wires, of the input of the `i`-th S-box of the `round`-th
+/// round of the first set of full rounds.
+const fn offset_full_sbox_0(round: usize, i: usize) -> usize {
+    debug_assert!(
+        round != 0,
+        "First round S-box inputs are not stored as wires"
+    );
+    debug_assert!(round < poseidon::HALF_N_FULL_ROUNDS);
+    debug_assert!(i < SPONGE_WIDTH);
+    SPONGE_WIDTH * (round - 1) + i
+}
+
+const START_PARTIAL: usize = SPONGE_WIDTH * (poseidon::HALF_N_FULL_ROUNDS - 1);
+
+/// The offset, within a permutation's wires, of the input of the S-box of the `round`-th partial
+/// round.
+const fn offset_partial_sbox(round: usize) -> usize {
+    debug_assert!(round < poseidon::N_PARTIAL_ROUNDS);
+    START_PARTIAL + round
+}
+
+const START_FULL_1: usize = START_PARTIAL + poseidon::N_PARTIAL_ROUNDS;
+
+/// The offset, within a permutation's wires, of the input of the `i`-th S-box of the `round`-th
+/// round of the second set of full rounds.
+const fn offset_full_sbox_1(round: usize, i: usize) -> usize {
+    debug_assert!(round < poseidon::HALF_N_FULL_ROUNDS);
+    debug_assert!(i < SPONGE_WIDTH);
+    START_FULL_1 + SPONGE_WIDTH * round + i
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PoseidonSpongeGate<F, D> {
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+
+    /// Returns whether the given config has enough wires for this gate.
+    pub const fn is_supported(config: &CircuitConfig) -> bool {
+        config.num_wires >= Self::end() && config.num_routed_wires >= Self::START_CAPACITY
+    }
+
+    /// The wire index for the `i`th input to the first permutation.
+    pub(crate) const fn wire_input(i: usize) -> usize {
+        debug_assert!(i < SPONGE_WIDTH);
+        i
+    }
+
+    /// The wire index for the `i`th element of the block absorbed before the second permutation.
+    pub(crate) const fn wire_block(i: usize) -> usize {
+        debug_assert!(i < SPONGE_RATE);
+        SPONGE_WIDTH + i
+    }
+
+    /// The wire index for the `i`th output of the second permutation.
+    pub(crate) const fn wire_output(i: usize) -> usize {
+        debug_assert!(i < SPONGE_WIDTH);
+        SPONGE_WIDTH + SPONGE_RATE + i
+    }
+
+    const START_CAPACITY: usize = 2 * SPONGE_WIDTH + SPONGE_RATE;
+
+    /// A wire which stores the `i`th capacity element output by the first permutation.
+    const fn wire_capacity(i: usize) -> usize {
+        debug_assert!(i < SPONGE_WIDTH - SPONGE_RATE);
+        Self::START_CAPACITY + i
+    }
+
+    const START_PERMUTATIONS: usize = Self::START_CAPACITY + SPONGE_WIDTH - SPONGE_RATE;
+
+    /// The wire index for the S-box input at `offset` of the `permutation`-th permutation.
+    const fn wire_sbox(permutation: usize, offset: usize) -> usize {
+        debug_assert!(permutation < 2);
+        Self::START_PERMUTATIONS + PERMUTATION_WIRES * permutation + offset
+    }
+
+    /// End of wire indices, exclusive.
+    const fn end() -> usize {
+        Self::START_PERMUTATIONS + 2 * PERMUTATION_WIRES
+    }
+}
+
+/// Applies the Poseidon permutation to `state`. The input of each S-box which is stored in a wire
+/// is passed to `sbox_input` along with its offset among the permutation's wires, and replaced
+/// by the returned value.
+fn permute_with_sbox_inputs<F, P, const E: usize>(
+    mut state: [P; SPONGE_WIDTH],
+    mut sbox_input: impl FnMut(usize, P) -> P,
+) -> [P; SPONGE_WIDTH]
+where
+    F: Poseidon,
+    P: FieldExtension<E, BaseField = F>,
+{
+    let mut round_ctr = 0;
+
+    // First set of full rounds.
+    for r in 0..poseidon::HALF_N_FULL_ROUNDS {
+        <F as Poseidon>::constant_layer_field(&mut state, round_ctr);
+        if r != 0 {
+            for i in 0..SPONGE_WIDTH {
+                state[i] = sbox_input(offset_full_sbox_0(r, i), state[i]);
+            }
+        }
+        <F as Poseidon>::sbox_layer_field(&mut state);
+        state = <F as Poseidon>::mds_layer_field(&state);
+        round_ctr += 1;
+    }
+
+    // Partial rounds.
+    <F as Poseidon>::partial_first_constant_layer(&mut state);
+    state = <F as Poseidon>::mds_partial_layer_init(&state);
+    for r in 0..(poseidon::N_PARTIAL_ROUNDS - 1) {
+        let sbox_in = sbox_input(offset_partial_sbox(r), state[0]);
+        state[0] = <F as Poseidon>::sbox_monomial(sbox_in);
+        state[0] += P::from_canonical_u64(<F as Poseidon>::FAST_PARTIAL_ROUND_CONSTANTS[r]);
+        state = <F as Poseidon>::mds_partial_layer_fast_field(&state, r);
+    }
+    let sbox_in = sbox_input(
+        offset_partial_sbox(poseidon::N_PARTIAL_ROUNDS - 1),
+        state[0],
+    );
+    state[0] = <F as Poseidon>::sbox_monomial(sbox_in);
+    state = <F as Poseidon>::mds_partial_layer_fast_field(&state, poseidon::N_PARTIAL_ROUNDS - 1);
+    round_ctr += poseidon::N_PARTIAL_ROUNDS;
+
+    // Second set of full rounds.
+    for r in 0..poseidon::HALF_N_FULL_ROUNDS {
+        <F as Poseidon>::constant_layer_field(&mut state, round_ctr);
+        for i in 0..SPONGE_WIDTH {
+            state[i] = sbox_input(offset_full_sbox_1(r, i), state[i]);
+        }
+        <F as Poseidon>::sbox_layer_field(&mut state);
+        state = <F as Poseidon>::mds_layer_field(&state);
+        round_ctr += 1;
+    }
+
+    state
+}
+
+/// Like `permute_with_sbox_inputs`, but over `PackedField`s.
+fn permute_with_sbox_inputs_packed<F, P, const D: usize>(
+    mut state: [P; SPONGE_WIDTH],
+    mut sbox_input: impl FnMut(usize, P) -> P,
+) -> [P; SPONGE_WIDTH]
+where
+    F: RichField + Extendable<D>,
+    P: PackedField<Scalar = F>,
+{
+    let mut round_ctr = 0;
+
+    // First set of full rounds.
+    for r in 0..poseidon::HALF_N_FULL_ROUNDS {
+        <F as Poseidon>::constant_layer_packed_field(&mut state, round_ctr);
+        if r != 0 {
+            for i in 0..SPONGE_WIDTH {
+                state[i] = sbox_input(offset_full_sbox_0(r, i), state[i]);
+            }
+        }
+        <F as Poseidon>::sbox_layer_packed_field(&mut state);
+        state = <F as Poseidon>::mds_layer_packed_field(&state);
+        round_ctr += 1;
+    }
+
+    // Partial rounds.
+    <F as Poseidon>::partial_first_constant_layer_packed_field(&mut state);
+    state = <F as Poseidon>::mds_partial_layer_init_packed_field(&state);
+    for r in 0..(poseidon::N_PARTIAL_ROUNDS - 1) {
+        let sbox_in = sbox_input(offset_partial_sbox(r), state[0]);
+        state[0] = <F as Poseidon>::sbox_monomial_packed_field(sbox_in);
+        state[0] += F::from_canonical_u64(<F as Poseidon>::FAST_PARTIAL_ROUND_CONSTANTS[r]);
+        state = <F as Poseidon>::mds_partial_layer_fast_packed_field(&state, r);
+    }
+    let sbox_in = sbox_input(
+        offset_partial_sbox(poseidon::N_PARTIAL_ROUNDS - 1),
+        state[0],
+    );
+    state[0] = <F as Poseidon>::sbox_monomial_packed_field(sbox_in);
+    state = <F as Poseidon>::mds_partial_layer_fast_packed_field(
+        &state,
+        poseidon::N_PARTIAL_ROUNDS - 1,
+    );
+    round_ctr += poseidon::N_PARTIAL_ROUNDS;
+
+    // Second set of full rounds.
+    for r in 0..poseidon::HALF_N_FULL_ROUNDS {
+        <F as Poseidon>::constant_layer_packed_field(&mut state, round_ctr);
+        for i in 0..SPONGE_WIDTH {
+            state[i] = sbox_input(offset_full_sbox_1(r, i), state[i]);
+        }
+        <F as Poseidon>::sbox_layer_packed_field(&mut state);
+        state = <F as Poseidon>::mds_layer_packed_field(&state);
+        round_ctr += 1;
+    }
+
+    state
+}
+
+/// Like `permute_with_sbox_inputs`, but in the recursive case.
+fn permute_with_sbox_inputs_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    mut state: [ExtensionTarget<D>; SPONGE_WIDTH],
+    mut sbox_input: impl FnMut(
+        &mut CircuitBuilder<F, D>,
+        usize,
+        ExtensionTarget<D>,
+    ) -> ExtensionTarget<D>,
+) -> [ExtensionTarget<D>; SPONGE_WIDTH] {
+    let mut round_ctr = 0;
+
+    // First set of full rounds.
+    for r in 0..poseidon::HALF_N_FULL_ROUNDS {
+        <F as Poseidon>::constant_layer_circuit(builder, &mut state, round_ctr);
+        if r != 0 {
+            for i in 0..SPONGE_WIDTH {
+                state[i] = sbox_input(builder, offset_full_sbox_0(r, i), state[i]);
+            }
+        }
+        <F as Poseidon>::sbox_layer_circuit(builder, &mut state);
+        state = <F as Poseidon>::mds_layer_circuit(builder, &state);
+        round_ctr += 1;
+    }
+
+    // Partial rounds.
+    <F as Poseidon>::partial_first_constant_layer_circuit(builder, &mut state);
+    state = <F as Poseidon>::mds_partial_layer_init_circuit(builder, &state);
+    for r in 0..(poseidon::N_PARTIAL_ROUNDS - 1) {
+        let sbox_in = sbox_input(builder, offset_partial_sbox(r), state[0]);
+        state[0] = <F as Poseidon>::sbox_monomial_circuit(builder, sbox_in);
+        let c = <F as Poseidon>::FAST_PARTIAL_ROUND_CONSTANTS[r];
+        let c = builder.constant_extension(F::Extension::from_canonical_u64(c));
+        state[0] = builder.add_extension(state[0], c);
+        state = <F as Poseidon>::mds_partial_layer_fast_circuit(builder, &state, r);
+    }
+    let sbox_in = sbox_input(
+        builder,
+        offset_partial_sbox(poseidon::N_PARTIAL_ROUNDS - 1),
+        state[0],
+    );
+    state[0] = <F as Poseidon>::sbox_monomial_circuit(builder, sbox_in);
+    state = <F as Poseidon>::mds_partial_layer_fast_circuit(
+        builder,
+        &state,
+        poseidon::N_PARTIAL_ROUNDS - 1,
+    );
+    round_ctr += poseidon::N_PARTIAL_ROUNDS;
+
+    // Second set of full rounds.
+    for r in 0..poseidon::HALF_N_FULL_ROUNDS {
+        <F as Poseidon>::constant_layer_circuit(builder, &mut state, round_ctr);
+        for i in 0..SPONGE_WIDTH {
+            state[i] = sbox_input(builder, offset_full_sbox_1(r, i), state[i]);
+        }
+        <F as Poseidon>::sbox_layer_circuit(builder, &mut state);
+        state = <F as Poseidon>::mds_layer_circuit(builder, &state);
+        round_ctr += 1;
+    }
+
+    state
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for PoseidonSpongeGate<F, D> {
+    fn id(&self) -> String {
+        format!("{self:?}<WIDTH={SPONGE_WIDTH}>")
+    }
+
+    fn serialize(
+        &self,
+        _dst: &mut Vec<u8>,
+        _common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn deserialize(_src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        Ok(PoseidonSpongeGate::new())
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        let mut state: [F::Extension; SPONGE_WIDTH] =
+            core::array::from_fn(|i| vars.local_wires[Self::wire_input(i)]);
+        for permutation in 0..2 {
+            state = permute_with_sbox_inputs(state, |offset, computed| {
+                let sbox_in = vars.local_wires[Self::wire_sbox(permutation, offset)];
+                constraints.push(computed - sbox_in);
+                sbox_in
+            });
+
+            if permutation == 0 {
+                // Absorb the block, and constrain the capacity elements we keep.
+                for i in 0..SPONGE_RATE {
+                    state[i] = vars.local_wires[Self::wire_block(i)];
+                }
+                for i in SPONGE_RATE..SPONGE_WIDTH {
+                    let capacity = vars.local_wires[Self::wire_capacity(i - SPONGE_RATE)];
+                    constraints.push(state[i] - capacity);
+                    state[i] = capacity;
+                }
+            }
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            constraints.push(state[i] - vars.local_wires[Self::wire_output(i)]);
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        let mut state: [ExtensionTarget<D>; SPONGE_WIDTH] =
+            core::array::from_fn(|i| vars.local_wires[Self::wire_input(i)]);
+        for permutation in 0..2 {
+            state =
+                permute_with_sbox_inputs_circuit(builder, state, |builder, offset, computed| {
+                    let sbox_in = vars.local_wires[Self::wire_sbox(permutation, offset)];
+                    constraints.push(builder.sub_extension(computed, sbox_in));
+                    sbox_in
+                });
+
+            if permutation == 0 {
+                for i in 0..SPONGE_RATE {
+                    state[i] = vars.local_wires[Self::wire_block(i)];
+                }
+                for i in SPONGE_RATE..SPONGE_WIDTH {
+                    let capacity = vars.local_wires[Self::wire_capacity(i - SPONGE_RATE)];
+                    constraints.push(builder.sub_extension(state[i], capacity));
+                    state[i] = capacity;
+                }
+            }
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            constraints
+                .push(builder.sub_extension(state[i], vars.local_wires[Self::wire_output(i)]));
+        }
+
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        let gen = PoseidonSpongeGenerator::<F, D> {
+            row,
+            _phantom: PhantomData,
+        };
+        vec![WitnessGeneratorRef::new(gen.adapter())]
+    }
+
+    fn num_wires(&self) -> usize {
+        Self::end()
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        7
+    }
+
+    fn num_constraints(&self) -> usize {
+        2 * PERMUTATION_WIRES + (SPONGE_WIDTH - SPONGE_RATE) + SPONGE_WIDTH
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for PoseidonSpongeGate<F, D>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        let mut state: [P; SPONGE_WIDTH] =
+            core::array::from_fn(|i| vars.local_wires[Self::wire_input(i)]);
+        for permutation in 0..2 {
+            state = permute_with_sbox_inputs_packed(state, |offset, computed| {
+                let sbox_in = vars.local_wires[Self::wire_sbox(permutation, offset)];
+                yield_constr.one(computed - sbox_in);
+                sbox_in
+            });
+
+            if permutation == 0 {
+                for i in 0..SPONGE_RATE {
+                    state[i] = vars.local_wires[Self::wire_block(i)];
+                }
+                for i in SPONGE_RATE..SPONGE_WIDTH {
+                    let capacity = vars.local_wires[Self::wire_capacity(i - SPONGE_RATE)];
+                    yield_constr.one(state[i] - capacity);
+                    state[i] = capacity;
+                }
+            }
+        }
+
+        for i in 0..SPONGE_WIDTH {
+            yield_constr.one(state[i] - vars.local_wires[Self::wire_output(i)]);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PoseidonSpongeGenerator<F: RichField + Extendable<D> + Poseidon, const D: usize> {
+    row: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D> + Poseidon, const D: usize> SimpleGenerator<F, D>
+    for PoseidonSpongeGenerator<F, D>
+{
+    fn id(&self) -> String {
+        "PoseidonSpongeGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        (0..SPONGE_WIDTH)
+            .map(|i| PoseidonSpongeGate::<F, D>::wire_input(i))
+            .chain((0..SPONGE_RATE).map(|i| PoseidonSpongeGate::<F, D>::wire_block(i)))
+            .map(|column| Target::wire(self.row, column))
+            .collect()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let local_wire = |column| Wire {
+            row: self.row,
+            column,
+        };
+
+        let mut state: [F; SPONGE_WIDTH] = core::array::from_fn(|i| {
+            witness.get_wire(local_wire(PoseidonSpongeGate::<F, D>::wire_input(i)))
+        });
+        let mut sbox_inputs = Vec::with_capacity(2 * PERMUTATION_WIRES);
+        for permutation in 0..2 {
+            state = permute_with_sbox_inputs(state, |offset, sbox_in| {
+                sbox_inputs.push((
+                    PoseidonSpongeGate::<F, D>::wire_sbox(permutation, offset),
+                    sbox_in,
+                ));
+                sbox_in
+            });
+
+            if permutation == 0 {
+                for i in 0..SPONGE_RATE {
+                    state[i] =
+                        witness.get_wire(local_wire(PoseidonSpongeGate::<F, D>::wire_block(i)));
+                }
+                for i in SPONGE_RATE..SPONGE_WIDTH {
+                    out_buffer.set_wire(
+                        local_wire(PoseidonSpongeGate::<F, D>::wire_capacity(i - SPONGE_RATE)),
+                        state[i],
+                    )?;
+                }
+            }
+        }
+
+        for (column, sbox_in) in sbox_inputs {
+            out_buffer.set_wire(local_wire(column), sbox_in)?;
+        }
+        for i in 0..SPONGE_WIDTH {
+            out_buffer.set_wire(
+                local_wire(PoseidonSpongeGate::<F, D>::wire_output(i)),
+                state[i],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        Ok(Self {
+            row,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2_field::goldilocks_field::GoldilocksField;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::gates::gate_testing::{test_eval_fns, test_generated_witness, test_low_degree};
+    use crate::hash::hashing::hash_n_to_m_no_pad;
+    use crate::hash::poseidon::{PoseidonHash, PoseidonPermutation};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn wire_indices() {
+        type Gate = PoseidonSpongeGate<GoldilocksField, 4>;
+
+        assert_eq!(Gate::wire_input(11), 11);
+        assert_eq!(Gate::wire_block(0), 12);
+        assert_eq!(Gate::wire_output(0), 20);
+        assert_eq!(Gate::wire_capacity(0), 32);
+        assert_eq!(Gate::wire_sbox(0, 0), 36);
+        assert_eq!(Gate::wire_sbox(1, 0), 142);
+        assert_eq!(Gate::end(), 248);
+    }
+
+    #[test]
+    fn low_degree() {
+        type F = GoldilocksField;
+        let gate = PoseidonSpongeGate::<F, 4>::new();
+        test_low_degree(gate)
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = PoseidonSpongeGate::<F, 2>::new();
+        test_eval_fns::<F, C, _, D>(gate)
+    }
+
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = PoseidonSpongeGate::<F, 2>::new();
+        test_generated_witness::<F, C, _, D>(gate)
+    }
+
+    #[test]
+    fn test_hash_with_sponge_gate() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let inputs = F::rand_vec(6 * SPONGE_RATE);
+        let expected = hash_n_to_m_no_pad::<F, PoseidonPermutation<F>>(&inputs, 4);
+
+        let num_poseidon_rows = |config: CircuitConfig| -> Result<usize> {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let inputs_t = builder.add_virtual_targets(inputs.len());
+            let outputs_t = builder.hash_n_to_m_no_pad::<PoseidonHash>(inputs_t.clone(), 4);
+            for (&output_t, &output) in outputs_t.iter().zip(&expected) {
+                let output = builder.constant(output);
+                builder.connect(output_t, output);
+            }
+            let (data, profile) = builder.build_with_profile::<C>();
+            let mut pw = PartialWitness::new();
+            pw.set_target_arr(&inputs_t, &inputs)?;
+            data.verify(data.prove(pw)?)?;
+            Ok(profile
+                .gate_counts
+                .iter()
+                .filter(|(id, _)| id.starts_with("Poseidon"))
+                .map(|(_, &count)| count)
+                .sum())
+        };
+
+        let standard_config = CircuitConfig::standard_recursion_config();
+        assert!(!PoseidonSpongeGate::<F, D>::is_supported(&standard_config));
+        let wide_config = CircuitConfig::wide_poseidon_config();
+        assert!(PoseidonSpongeGate::<F, D>::is_supported(&wide_config));
+
+        // 6 permutations are needed, which fit in 3 rows when the sponge gate is supported.
+        assert_eq!(num_poseidon_rows(standard_config)?, 6);
+        assert_eq!(num_poseidon_rows(wide_config)?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_verifier_with_sponge_gate() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let x_cubed = builder.exp_u64(x, 3);
+        builder.register_public_input(x_cubed);
+        let inner = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::TWO)?;
+        let inner_proof = inner.prove(pw)?;
+
+        // Returns the number of rows used by Poseidon gates to verify `inner_proof`, and whether
+        // the proof of the verifier circuit is valid.
+        let num_poseidon_rows = |config: CircuitConfig| -> Result<usize> {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let proof_t = builder.add_virtual_proof_with_pis(&inner.common);
+            let verifier_data_t =
+                builder.add_virtual_verifier_data(inner.common.config.fri_config.cap_height);
+            builder.verify_proof::<C>(&proof_t, &verifier_data_t, &inner.common);
+            let (data, profile) = builder.build_with_profile::<C>();
+            let mut pw = PartialWitness::new();
+            pw.set_proof_with_pis_target(&proof_t, &inner_proof)?;
+            pw.set_verifier_data_target(&verifier_data_t, &inner.verifier_only)?;
+            data.verify(data.prove(pw)?)?;
+            Ok(profile
+                .gate_counts
+                .iter()
+                .filter(|(id, _)| id.starts_with("Poseidon"))
+                .map(|(_, &count)| count)
+                .sum())
+        };
+
+        // Hashing the opened Merkle leaves takes about half as many rows with the sponge gate,
+        // which saves over a third of the Poseidon rows.
+        let standard_rows = num_poseidon_rows(CircuitConfig::standard_recursion_config())?;
+        let wide_rows = num_poseidon_rows(CircuitConfig::wide_poseidon_config())?;
+        assert!(3 * wide_rows < 2 * standard_rows);
+        Ok(())
+    }
+}
@@ -428,7 +428,9 @@ mod tests {
     use super::*;
     use crate::field::goldilocks_field::GoldilocksField;
     use crate::field::types::Sample;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{
+        test_eval_fns, test_generated_witness_with_inputs, test_low_degree,
+    };
     use crate::hash::hash_types::HashOut;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -445,6 +447,25 @@ mod tests {
         test_eval_fns::<F, C, _, D>(RandomAccessGate::new(4, 4, 1))
     }
 
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        // Extra constants are copied to wires by the circuit builder, not by generators.
+        let gate = RandomAccessGate::new(4, 4, 0);
+        let access_indices = (0..gate.num_copies)
+            .map(|copy| gate.wire_access_index(copy))
+            .collect::<Vec<_>>();
+        test_generated_witness_with_inputs::<F, C, _, D>(gate, |column| {
+            if access_indices.contains(&column) {
+                F::from_canonical_usize(OsRng.gen_range(0..1 << 4))
+            } else {
+                F::rand()
+            }
+        })
+    }
+
     #[test]
     fn test_gate_constraint() {
         const D: usize = 2;
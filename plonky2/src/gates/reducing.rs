@@ -9,8 +9,10 @@ use core::ops::Range;
 
 use anyhow::Result;
 
-use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::extension::Extendable;
+use crate::field::packed::PackedField;
 use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
 use crate::gates::util::StridedConstraintConsumer;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
@@ -19,7 +21,10 @@ use crate::iop::target::Target;
 use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CommonCircuitData;
-use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 /// Computes `sum alpha^i c_i` for a vector `c_i` of `num_coeffs` elements of the base field.
@@ -106,24 +111,14 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for ReducingGate<D
 
     fn eval_unfiltered_base_one(
         &self,
-        vars: EvaluationVarsBase<F>,
-        mut yield_constr: StridedConstraintConsumer<F>,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
     ) {
-        let alpha = vars.get_local_ext(Self::wires_alpha());
-        let old_acc = vars.get_local_ext(Self::wires_old_acc());
-        let coeffs = self
-            .wires_coeffs()
-            .map(|i| vars.local_wires[i])
-            .collect::<Vec<_>>();
-        let accs = (0..self.num_coeffs)
-            .map(|i| vars.get_local_ext(self.wires_accs(i)))
-            .collect::<Vec<_>>();
+        panic!("use eval_unfiltered_base_packed instead");
+    }
 
-        let mut acc = old_acc;
-        for i in 0..self.num_coeffs {
-            yield_constr.many((acc * alpha + coeffs[i].into() - accs[i]).to_basefield_array());
-            acc = accs[i];
-        }
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
     }
 
     fn eval_unfiltered_circuit(
@@ -184,6 +179,30 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for ReducingGate<D
     }
 }
 
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for ReducingGate<D> {
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        let alpha = vars.get_local_ext(Self::wires_alpha());
+        let old_acc = vars.get_local_ext(Self::wires_old_acc());
+        let coeffs = self
+            .wires_coeffs()
+            .map(|i| vars.local_wires[i])
+            .collect::<Vec<_>>();
+        let accs = (0..self.num_coeffs)
+            .map(|i| vars.get_local_ext(self.wires_accs(i)))
+            .collect::<Vec<_>>();
+
+        let mut acc = old_acc;
+        for i in 0..self.num_coeffs {
+            yield_constr.many((acc * alpha + coeffs[i].into() - accs[i]).to_basefield_array());
+            acc = accs[i];
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ReducingGenerator<const D: usize> {
     row: usize,
@@ -254,7 +273,7 @@ mod tests {
     use anyhow::Result;
 
     use crate::field::goldilocks_field::GoldilocksField;
-    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::gate_testing::{test_eval_fns, test_generated_witness, test_low_degree};
     use crate::gates::reducing::ReducingGate;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -270,4 +289,12 @@ mod tests {
         type F = <C as GenericConfig<D>>::F;
         test_eval_fns::<F, C, _, D>(ReducingGate::new(22))
     }
+
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_generated_witness::<F, C, _, D>(ReducingGate::new(22))
+    }
 }
@@ -0,0 +1,227 @@
+#[cfg(not(feature = "std"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use anyhow::Result;
+
+use crate::field::extension::Extendable;
+use crate::gates::expression::{ExpressionGate, GateExpr};
+use crate::gates::gate::Gate;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGeneratorRef};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+};
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+/// A gate which can perform selections `output = b ? x : y`, computed as `b * (x - y) + y`. If the
+/// config has enough routed wires, it can support several such operations in one gate.
+///
+/// The condition `b` is assumed to be boolean; this gate does not constrain it.
+///
+/// It is used by [`CircuitBuilder::select_packed`]; [`CircuitBuilder::select`] uses arithmetic
+/// gates instead.
+#[derive(Debug, Clone)]
+pub struct SelectGate {
+    /// Number of selections performed by the gate.
+    pub num_ops: usize,
+}
+
+impl SelectGate {
+    pub const fn new_from_config(config: &CircuitConfig) -> Self {
+        Self {
+            num_ops: Self::num_ops(config),
+        }
+    }
+
+    /// Determine the maximum number of operations that can fit in one gate for the given config.
+    pub(crate) const fn num_ops(config: &CircuitConfig) -> usize {
+        let wires_per_op = 4;
+        config.num_routed_wires / wires_per_op
+    }
+
+    pub(crate) const fn wire_ith_condition(i: usize) -> usize {
+        4 * i
+    }
+    pub(crate) const fn wire_ith_x(i: usize) -> usize {
+        4 * i + 1
+    }
+    pub(crate) const fn wire_ith_y(i: usize) -> usize {
+        4 * i + 2
+    }
+    pub(crate) const fn wire_ith_output(i: usize) -> usize {
+        4 * i + 3
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for SelectGate {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.num_ops)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let num_ops = src.read_usize()?;
+        Ok(Self { num_ops })
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        self.eval_unfiltered_exprs(vars)
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_batch_exprs instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_exprs(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        self.eval_unfiltered_circuit_exprs(builder, vars)
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<WitnessGeneratorRef<F, D>> {
+        (0..self.num_ops)
+            .map(|i| WitnessGeneratorRef::new(SelectGenerator { row, i }.adapter()))
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * 4
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        ExpressionGate::<F, D>::exprs_degree(self)
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> ExpressionGate<F, D> for SelectGate {
+    fn constraint_exprs(&self) -> Vec<GateExpr<F>> {
+        (0..self.num_ops)
+            .map(|i| {
+                let b = GateExpr::wire(Self::wire_ith_condition(i));
+                let x = GateExpr::wire(Self::wire_ith_x(i));
+                let y = GateExpr::wire(Self::wire_ith_y(i));
+                let output = GateExpr::wire(Self::wire_ith_output(i));
+                let computed_output = b * (x - y.clone()) + y;
+
+                output - computed_output
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SelectGenerator {
+    row: usize,
+    i: usize,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for SelectGenerator {
+    fn id(&self) -> String {
+        "SelectGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        [
+            SelectGate::wire_ith_condition(self.i),
+            SelectGate::wire_ith_x(self.i),
+            SelectGate::wire_ith_y(self.i),
+        ]
+        .iter()
+        .map(|&i| Target::wire(self.row, i))
+        .collect()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let get_wire = |wire: usize| -> F { witness.get_target(Target::wire(self.row, wire)) };
+
+        let b = get_wire(SelectGate::wire_ith_condition(self.i));
+        let x = get_wire(SelectGate::wire_ith_x(self.i));
+        let y = get_wire(SelectGate::wire_ith_y(self.i));
+
+        let output_target = Target::wire(self.row, SelectGate::wire_ith_output(self.i));
+
+        let computed_output = b * (x - y) + y;
+
+        out_buffer.set_target(output_target, computed_output)
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.row)?;
+        dst.write_usize(self.i)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let row = src.read_usize()?;
+        let i = src.read_usize()?;
+        Ok(Self { row, i })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::gates::gate_testing::{test_eval_fns, test_generated_witness, test_low_degree};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn low_degree() {
+        let gate = SelectGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_low_degree::<GoldilocksField, _, 4>(gate);
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = SelectGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_eval_fns::<F, C, _, D>(gate)
+    }
+
+    #[test]
+    fn generated_witness() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = SelectGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_generated_witness::<F, C, _, D>(gate)
+    }
+}
@@ -77,7 +77,8 @@ impl<F: RichField, H: Hasher<F>> BatchMerkleTree<F, H> {
                 fill_digests_buf::<F, H>(
                     &mut digests_buf[digests_buf_pos..(digests_buf_pos + num_tmp_digests)],
                     tmp_cap_buf,
-                    &cur[..],
+                    cur.len(),
+                    &|i| H::hash_or_noop(&cur[i]),
                     next_cap_height,
                 );
             } else {
@@ -98,7 +99,8 @@ impl<F: RichField, H: Hasher<F>> BatchMerkleTree<F, H> {
                 fill_digests_buf::<F, H>(
                     &mut digests_buf[digests_buf_pos..(digests_buf_pos + num_tmp_digests)],
                     tmp_cap_buf,
-                    &new_leaves[..],
+                    new_leaves.len(),
+                    &|i| H::hash_or_noop(&new_leaves[i]),
                     next_cap_height,
                 );
             }
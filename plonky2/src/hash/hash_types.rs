@@ -1,6 +1,7 @@
 #[cfg(not(feature = "std"))]
 use alloc::vec::Vec;
 use core::fmt;
+use core::str::FromStr;
 
 use anyhow::ensure;
 use serde::de::{self, Visitor};
@@ -11,6 +12,7 @@ use crate::field::types::{Field, PrimeField64, Sample};
 use crate::hash::poseidon::Poseidon;
 use crate::iop::target::Target;
 use crate::plonk::config::GenericHashOut;
+use crate::util::encoding::{decode_field_elements, from_hex, to_hex};
 
 /// A prime order field with the features we need to use it as a base field in our argument system.
 pub trait RichField: PrimeField64 + Poseidon {}
@@ -91,16 +93,19 @@ impl<F: RichField> GenericHashOut<F> for HashOut<F> {
             .collect()
     }
 
+    /// Missing elements, e.g. those dropped by a truncated hash, are set to zero.
     fn from_bytes(bytes: &[u8]) -> Self {
-        HashOut {
-            elements: bytes
-                .chunks(8)
-                .take(NUM_HASH_OUT_ELTS)
-                .map(|x| F::from_canonical_u64(u64::from_le_bytes(x.try_into().unwrap())))
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap(),
-        }
+        let elements = bytes
+            .chunks(8)
+            .take(NUM_HASH_OUT_ELTS)
+            .map(|x| F::from_canonical_u64(u64::from_le_bytes(x.try_into().unwrap())))
+            .collect::<Vec<_>>();
+        Self::from_partial(&elements)
+    }
+
+    fn try_from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let elements = decode_field_elements(bytes)?;
+        Self::try_from(elements.as_slice())
     }
 
     fn to_vec(&self) -> Vec<F> {
@@ -108,6 +113,21 @@ impl<F: RichField> GenericHashOut<F> for HashOut<F> {
     }
 }
 
+/// Formats the hash as the hexadecimal representation of its canonical encoding.
+impl<F: RichField> fmt::Display for HashOut<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&to_hex(&self.to_bytes()))
+    }
+}
+
+impl<F: RichField> FromStr for HashOut<F> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from_bytes(&from_hex(s)?)
+    }
+}
+
 impl<F: Field> Default for HashOut<F> {
     fn default() -> Self {
         Self::ZERO
@@ -181,6 +201,16 @@ impl<F: RichField, const N: usize> GenericHashOut<F> for BytesHash<N> {
         Self(bytes.try_into().unwrap())
     }
 
+    fn try_from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        ensure!(
+            bytes.len() == N,
+            "Expected a hash of {} bytes, got {}",
+            N,
+            bytes.len()
+        );
+        Ok(Self(bytes.try_into().unwrap()))
+    }
+
     fn to_vec(&self) -> Vec<F> {
         self.0
             // Chunks of 7 bytes since 8 bytes would allow collisions.
@@ -194,6 +224,27 @@ impl<F: RichField, const N: usize> GenericHashOut<F> for BytesHash<N> {
     }
 }
 
+impl<const N: usize> fmt::Display for BytesHash<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&to_hex(&self.0))
+    }
+}
+
+impl<const N: usize> FromStr for BytesHash<N> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = from_hex(s)?;
+        ensure!(
+            bytes.len() == N,
+            "Expected a hash of {} bytes, got {}",
+            N,
+            bytes.len()
+        );
+        Ok(Self(bytes.try_into().unwrap()))
+    }
+}
+
 impl<const N: usize> Serialize for BytesHash<N> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
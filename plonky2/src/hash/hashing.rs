@@ -8,12 +8,12 @@ use crate::field::types::Field;
 use crate::hash::hash_types::{HashOut, HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
 use crate::iop::target::Target;
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::config::AlgebraicHasher;
+use crate::plonk::config::{AlgebraicHasher, Hasher};
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     pub fn hash_or_noop<H: AlgebraicHasher<F>>(&mut self, inputs: Vec<Target>) -> HashOutTarget {
         let zero = self.zero();
-        if inputs.len() <= NUM_HASH_OUT_ELTS {
+        if inputs.len() * 8 <= H::HASH_SIZE {
             HashOutTarget::from_partial(&inputs, zero)
         } else {
             self.hash_n_to_hash_no_pad::<H>(inputs)
@@ -24,7 +24,18 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         &mut self,
         inputs: Vec<Target>,
     ) -> HashOutTarget {
-        HashOutTarget::from_vec(self.hash_n_to_m_no_pad::<H>(inputs, NUM_HASH_OUT_ELTS))
+        let hash = HashOutTarget::from_vec(self.hash_n_to_m_no_pad::<H>(inputs, NUM_HASH_OUT_ELTS));
+        self.truncate_hash::<H>(hash)
+    }
+
+    /// Zeroes the elements of `hash` which are dropped by `H`, matching its native digests.
+    pub(crate) fn truncate_hash<H: Hasher<F>>(&mut self, hash: HashOutTarget) -> HashOutTarget {
+        let num_elements = H::HASH_SIZE / 8;
+        if num_elements >= NUM_HASH_OUT_ELTS {
+            return hash;
+        }
+        let zero = self.zero();
+        HashOutTarget::from_partial(&hash.elements[..num_elements], zero)
     }
 
     pub fn hash_n_to_m_no_pad<H: AlgebraicHasher<F>>(
@@ -35,13 +46,18 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let zero = self.zero();
         let mut state = H::AlgebraicPermutation::new(core::iter::repeat(zero));
 
-        // Absorb all input chunks.
-        for input_chunk in inputs.chunks(H::AlgebraicPermutation::RATE) {
+        // Absorb all input chunks, two at a time so that hashers can evaluate both permutations
+        // in a single gate.
+        let mut input_chunks = inputs.chunks(H::AlgebraicPermutation::RATE).peekable();
+        while let Some(input_chunk) = input_chunks.next() {
             // Overwrite the first r elements with the inputs. This differs from a standard sponge,
             // where we would xor or add in the inputs. This is a well-known variant, though,
             // sometimes called "overwrite mode".
             state.set_from_slice(input_chunk, 0);
-            state = self.permute::<H>(state);
+            state = match input_chunks.next() {
+                Some(next_chunk) => self.permute_absorb_permute::<H>(state, next_chunk),
+                None => self.permute::<H>(state),
+            };
         }
 
         // Squeeze until we have the desired number of outputs.
@@ -1,5 +1,6 @@
 #[cfg(not(feature = "std"))]
 use alloc::{vec, vec::Vec};
+use core::cmp::Ordering;
 use core::ops::RangeInclusive;
 
 use anyhow::{ensure, Result};
@@ -9,7 +10,7 @@ use serde::{Deserialize, Serialize};
 use crate::field::extension::Extendable;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField, NUM_HASH_OUT_ELTS};
 use crate::hash::hashing::PlonkyPermutation;
-use crate::hash::merkle_tree::MerkleCap;
+use crate::hash::merkle_tree::{hash_children, LeafHasher, MerkleCap};
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::VerifierCircuitTarget;
@@ -38,6 +39,14 @@ pub struct MerkleProofTarget {
     pub siblings: Vec<HashOutTarget>,
 }
 
+/// A Merkle proof for a tree hashed with a byte-oriented hash function such as Keccak-256 or
+/// SHA-256, where each node is the hash of the concatenation of its two children, as in SSZ.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BytesMerkleProofTarget {
+    /// The digest of each sibling subtree, as bytes, starting from the bottommost layer.
+    pub siblings: Vec<[Target; 32]>,
+}
+
 /// Verifies that the given leaf data is present at the given index in the Merkle tree with the
 /// given root.
 pub fn verify_merkle_proof<F: RichField, H: Hasher<F>>(
@@ -67,6 +76,62 @@ pub fn verify_merkle_proof_to_cap<F: RichField, H: Hasher<F>>(
     )
 }
 
+/// Verifies that the given leaf data is present at the given index in the Merkle tree with the
+/// given cap, whose nodes have `1 << arity_bits` children.
+pub fn verify_merkle_proof_to_cap_with_arity<F: RichField, H: Hasher<F>>(
+    leaf_data: Vec<F>,
+    leaf_index: usize,
+    merkle_cap: &MerkleCap<F, H>,
+    proof: &MerkleProof<F, H>,
+    arity_bits: usize,
+) -> Result<()> {
+    verify_merkle_proof_to_cap_with_options(
+        leaf_data,
+        leaf_index,
+        merkle_cap,
+        proof,
+        arity_bits,
+        LeafHasher::Raw,
+    )
+}
+
+/// Verifies that the given leaf data is present at the given index in the Merkle tree with the
+/// given cap, whose nodes have `1 << arity_bits` children and whose leaves are hashed with
+/// `leaf_hasher`.
+pub fn verify_merkle_proof_to_cap_with_options<F: RichField, H: Hasher<F>>(
+    leaf_data: Vec<F>,
+    mut leaf_index: usize,
+    merkle_cap: &MerkleCap<F, H>,
+    proof: &MerkleProof<F, H>,
+    arity_bits: usize,
+    leaf_hasher: LeafHasher,
+) -> Result<()> {
+    ensure!(
+        arity_bits > 0,
+        "Merkle trees must have an arity of at least 2"
+    );
+    let arity = 1 << arity_bits;
+    ensure!(
+        proof.siblings.len().is_multiple_of(arity - 1),
+        "Invalid Merkle proof length."
+    );
+
+    let mut current_digest = leaf_hasher.hash_leaf::<F, H>(&leaf_data);
+    for siblings in proof.siblings.chunks_exact(arity - 1) {
+        let child_index = leaf_index & (arity - 1);
+        leaf_index >>= arity_bits;
+        let mut children = siblings.to_vec();
+        children.insert(child_index, current_digest);
+        current_digest = hash_children::<F, H>(&children);
+    }
+    ensure!(
+        merkle_cap.0.get(leaf_index) == Some(&current_digest),
+        "Invalid Merkle proof."
+    );
+
+    Ok(())
+}
+
 /// Verifies that the given leaf data is present at the given index in the Field Merkle tree with the
 /// given cap.
 pub fn verify_batch_merkle_proof_to_cap<F: RichField, H: Hasher<F>>(
@@ -139,6 +204,287 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
+    /// Verifies that the given leaf data is present at the given index in the Merkle tree with the
+    /// given cap, whose nodes have `1 << arity_bits` children. The index is given by its
+    /// little-endian bits.
+    pub fn verify_merkle_proof_to_cap_with_arity<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_data: Vec<Target>,
+        leaf_index_bits: &[BoolTarget],
+        merkle_cap: &MerkleCapTarget,
+        proof: &MerkleProofTarget,
+        arity_bits: usize,
+    ) {
+        self.verify_merkle_proof_to_cap_with_options::<H>(
+            leaf_data,
+            leaf_index_bits,
+            merkle_cap,
+            proof,
+            arity_bits,
+            LeafHasher::Raw,
+        );
+    }
+
+    /// Verifies that the given leaf data is present at the given index in the Merkle tree with the
+    /// given cap, whose nodes have `1 << arity_bits` children and whose leaves are hashed with
+    /// `leaf_hasher`. The index is given by its little-endian bits.
+    pub fn verify_merkle_proof_to_cap_with_options<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_data: Vec<Target>,
+        leaf_index_bits: &[BoolTarget],
+        merkle_cap: &MerkleCapTarget,
+        proof: &MerkleProofTarget,
+        arity_bits: usize,
+        leaf_hasher: LeafHasher,
+    ) {
+        assert!(
+            arity_bits > 0,
+            "Merkle trees must have an arity of at least 2"
+        );
+        let arity = 1 << arity_bits;
+        assert!(proof.siblings.len().is_multiple_of(arity - 1));
+        let num_layers = proof.siblings.len() / (arity - 1);
+        let (path_bits, cap_bits) = leaf_index_bits.split_at(num_layers * arity_bits);
+
+        let mut state = self.hash_leaf::<H>(leaf_hasher, leaf_data);
+        for (bits, siblings) in path_bits
+            .chunks_exact(arity_bits)
+            .zip(proof.siblings.chunks_exact(arity - 1))
+        {
+            if arity_bits == 1 {
+                state = self.merkle_hash_pair::<H>(state, siblings[0], bits[0]);
+                continue;
+            }
+
+            // Child `j` is the current node if the child index is `j`, and otherwise one of the
+            // siblings, which skip the current node.
+            let child_index = self.le_sum(bits.iter());
+            let children = (0..arity)
+                .flat_map(|j| {
+                    let candidates = (0..arity)
+                        .map(|index| match index.cmp(&j) {
+                            Ordering::Less => siblings[j - 1],
+                            Ordering::Equal => state,
+                            Ordering::Greater => siblings[j],
+                        })
+                        .collect();
+                    self.random_access_hash(child_index, candidates).elements
+                })
+                .collect();
+            state = self.hash_n_to_hash_no_pad::<H>(children);
+        }
+
+        let cap_index = self.le_sum(cap_bits.iter());
+        for i in 0..NUM_HASH_OUT_ELTS {
+            let result = self.random_access(
+                cap_index,
+                merkle_cap.0.iter().map(|h| h.elements[i]).collect(),
+            );
+            self.connect(result, state.elements[i]);
+        }
+    }
+
+    /// Hashes `leaf` into a leaf digest, as `LeafHasher::hash_leaf` does.
+    pub fn hash_leaf<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_hasher: LeafHasher,
+        leaf: Vec<Target>,
+    ) -> HashOutTarget {
+        let prefix = match leaf_hasher {
+            LeafHasher::Raw => return self.hash_or_noop::<H>(leaf),
+            LeafHasher::LengthPrefixed => F::from_canonical_usize(leaf.len()),
+            LeafHasher::DomainSeparated(tag) => F::from_noncanonical_u64(tag),
+        };
+        let mut inputs = Vec::with_capacity(leaf.len() + 1);
+        inputs.push(self.constant(prefix));
+        inputs.extend(leaf);
+        self.hash_n_to_hash_no_pad::<H>(inputs)
+    }
+
+    /// Computes the root of the Merkle tree with the given leaves, which must be a power of two in
+    /// number. This matches the root of a native `MerkleTree` with a cap height of zero.
+    pub fn merkle_root_from_leaves<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaves: Vec<Vec<Target>>,
+    ) -> HashOutTarget {
+        assert!(
+            leaves.len().is_power_of_two(),
+            "Number of leaves must be a power of two"
+        );
+
+        let _false = self._false();
+        let mut layer = leaves
+            .into_iter()
+            .map(|leaf| self.hash_or_noop::<H>(leaf))
+            .collect::<Vec<_>>();
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|pair| self.merkle_hash_pair::<H>(pair[0], pair[1], _false))
+                .collect();
+        }
+        layer[0]
+    }
+
+    /// Computes the root of the Merkle tree in which the given leaf data is at the given index,
+    /// according to `proof`. The index is given by its little-endian bits, one per sibling.
+    pub fn merkle_root_from_proof<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_data: Vec<Target>,
+        leaf_index_bits: &[BoolTarget],
+        proof: &MerkleProofTarget,
+    ) -> HashOutTarget {
+        assert_eq!(leaf_index_bits.len(), proof.siblings.len());
+        let mut state = self.hash_or_noop::<H>(leaf_data);
+        for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
+            state = self.merkle_hash_pair::<H>(state, sibling, bit);
+        }
+        state
+    }
+
+    /// Verifies that the given leaf data is present at the given index in a Merkle tree with the
+    /// given root, whose depth is only known at proving time. This allows a single circuit to
+    /// verify proofs for trees of any depth up to `max_depth`, such as append-only logs.
+    ///
+    /// `leaf_index_bits` and `proof.siblings` must both have `max_depth` entries, of which only the
+    /// first `depth` are used; the remaining siblings can be set to anything, while the remaining
+    /// index bits are constrained to be zero. Proving fails if `depth > max_depth`.
+    ///
+    /// # Security
+    ///
+    /// `depth` is otherwise a free witness, and leaves are hashed with `hash_or_noop` as in
+    /// [`MerkleTree`](crate::hash::merkle_tree::MerkleTree), so they aren't domain-separated from
+    /// internal nodes: a leaf of 4 elements is its own digest, and a leaf of 8 elements hashes like
+    /// a pair of child digests. A prover free to pick `depth` could thus present an internal node
+    /// as a leaf of a shallower tree. The caller must constrain `depth`, e.g. by committing to it
+    /// along with `merkle_root` or exposing it as a public input, unless the leaf length rules out
+    /// such confusion.
+    pub fn verify_merkle_proof_variable_depth<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_data: Vec<Target>,
+        leaf_index_bits: &[BoolTarget],
+        merkle_root: HashOutTarget,
+        proof: &MerkleProofTarget,
+        depth: Target,
+        max_depth: usize,
+    ) {
+        assert_eq!(leaf_index_bits.len(), max_depth);
+        assert_eq!(proof.siblings.len(), max_depth);
+
+        let zero = self.zero();
+        let mut state = self.hash_or_noop::<H>(leaf_data);
+        // Whether the current layer is below the root, i.e. whether `i < depth`.
+        let mut active = self._true();
+        for (i, (&bit, &sibling)) in leaf_index_bits.iter().zip(&proof.siblings).enumerate() {
+            let i = self.constant(F::from_canonical_usize(i));
+            let at_root = self.is_equal(depth, i);
+            active = BoolTarget::new_unsafe(self.sub(active.target, at_root.target));
+
+            let parent = self.merkle_hash_pair::<H>(state, sibling, bit);
+            state = self.select_hash(active, parent, state);
+
+            // Index bits above the root must be zero.
+            let unused_bit =
+                self.arithmetic(F::NEG_ONE, F::ONE, active.target, bit.target, bit.target);
+            self.connect(unused_bit, zero);
+        }
+
+        // Ensure that `depth` was found in `0..=max_depth`, which also makes the `active` flags
+        // boolean.
+        let max_depth = self.constant(F::from_canonical_usize(max_depth));
+        let at_root = self.is_equal(depth, max_depth);
+        let past_root = self.sub(active.target, at_root.target);
+        self.connect(past_root, zero);
+
+        self.connect_hashes(state, merkle_root);
+    }
+
+    /// Verifies that the given 32-byte leaf is present at the given index in the Keccak-256 Merkle
+    /// tree with the given root. The index is given by its little-endian bits, of which there
+    /// must be one per sibling.
+    pub fn verify_keccak_merkle_proof_target(
+        &mut self,
+        leaf: [Target; 32],
+        leaf_index_bits: &[BoolTarget],
+        merkle_root: [Target; 32],
+        proof: &BytesMerkleProofTarget,
+    ) {
+        self.verify_bytes_merkle_proof(
+            leaf,
+            leaf_index_bits,
+            merkle_root,
+            proof,
+            Self::keccak256_bits,
+        );
+    }
+
+    /// Verifies that the given 32-byte leaf is present at the given index in the SHA-256 Merkle
+    /// tree with the given root, e.g. an SSZ Merkle branch. The index is given by its
+    /// little-endian bits, of which there must be one per sibling.
+    pub fn verify_sha256_merkle_proof_target(
+        &mut self,
+        leaf: [Target; 32],
+        leaf_index_bits: &[BoolTarget],
+        merkle_root: [Target; 32],
+        proof: &BytesMerkleProofTarget,
+    ) {
+        self.verify_bytes_merkle_proof(
+            leaf,
+            leaf_index_bits,
+            merkle_root,
+            proof,
+            Self::sha256_bits,
+        );
+    }
+
+    /// Verifies a Merkle proof whose nodes are hashed by `hash`, which works on bytes given by
+    /// their little-endian bits.
+    fn verify_bytes_merkle_proof(
+        &mut self,
+        leaf: [Target; 32],
+        leaf_index_bits: &[BoolTarget],
+        merkle_root: [Target; 32],
+        proof: &BytesMerkleProofTarget,
+        hash: fn(&mut Self, &[BoolTarget]) -> [BoolTarget; 256],
+    ) {
+        assert_eq!(leaf_index_bits.len(), proof.siblings.len());
+
+        let mut state = self.bytes_to_bits(&leaf);
+        for (&bit, sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
+            let sibling = self.bytes_to_bits(sibling);
+            // The current node is on the right if the bit is set.
+            let mut input = Vec::with_capacity(512);
+            for (&current, &sibling) in state.iter().zip(&sibling) {
+                input.push(BoolTarget::new_unsafe(self.select(
+                    bit,
+                    sibling.target,
+                    current.target,
+                )));
+            }
+            for (&current, &sibling) in state.iter().zip(&sibling) {
+                input.push(BoolTarget::new_unsafe(self.select(
+                    bit,
+                    current.target,
+                    sibling.target,
+                )));
+            }
+            state = hash(self, &input).to_vec();
+        }
+
+        for (byte_bits, &root_byte) in state.chunks(8).zip(&merkle_root) {
+            let byte = self.le_sum(byte_bits.iter());
+            self.connect(byte, root_byte);
+        }
+    }
+
+    /// Splits bytes into their little-endian bits, range-checking them.
+    fn bytes_to_bits(&mut self, bytes: &[Target]) -> Vec<BoolTarget> {
+        bytes
+            .iter()
+            .flat_map(|&byte| self.split_le(byte, 8))
+            .collect()
+    }
+
     /// Same as `verify_merkle_proof_to_cap`, except with the final "cap index" as separate parameter,
     /// rather than being contained in `leaf_index_bits`.
     pub(crate) fn verify_merkle_proof_to_cap_with_cap_index<H: AlgebraicHasher<F>>(
@@ -151,25 +497,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) {
         debug_assert!(H::AlgebraicPermutation::RATE >= NUM_HASH_OUT_ELTS);
 
-        let zero = self.zero();
         let mut state: HashOutTarget = self.hash_or_noop::<H>(leaf_data);
         debug_assert_eq!(state.elements.len(), NUM_HASH_OUT_ELTS);
 
         for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
-            debug_assert_eq!(sibling.elements.len(), NUM_HASH_OUT_ELTS);
-
-            let mut perm_inputs = H::AlgebraicPermutation::default();
-            perm_inputs.set_from_slice(&state.elements, 0);
-            perm_inputs.set_from_slice(&sibling.elements, NUM_HASH_OUT_ELTS);
-            // Ensure the rest of the state, if any, is zero:
-            perm_inputs.set_from_iter(core::iter::repeat(zero), 2 * NUM_HASH_OUT_ELTS);
-            let perm_outs = self.permute_swapped::<H>(perm_inputs, bit);
-            let hash_outs = perm_outs.squeeze()[0..NUM_HASH_OUT_ELTS]
-                .try_into()
-                .unwrap();
-            state = HashOutTarget {
-                elements: hash_outs,
-            };
+            state = self.merkle_hash_pair::<H>(state, sibling, bit);
         }
 
         for i in 0..NUM_HASH_OUT_ELTS {
@@ -196,7 +528,6 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) {
         debug_assert!(H::AlgebraicPermutation::RATE >= NUM_HASH_OUT_ELTS);
 
-        let zero = self.zero();
         let mut state: HashOutTarget = self.hash_or_noop::<H>(leaf_data);
         debug_assert_eq!(state.elements.len(), NUM_HASH_OUT_ELTS);
 
@@ -204,20 +535,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let mut final_states = vec![state; num_log_n];
 
         for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
-            debug_assert_eq!(sibling.elements.len(), NUM_HASH_OUT_ELTS);
-
-            let mut perm_inputs = H::AlgebraicPermutation::default();
-            perm_inputs.set_from_slice(&state.elements, 0);
-            perm_inputs.set_from_slice(&sibling.elements, NUM_HASH_OUT_ELTS);
-            // Ensure the rest of the state, if any, is zero:
-            perm_inputs.set_from_iter(core::iter::repeat(zero), 2 * NUM_HASH_OUT_ELTS);
-            let perm_outs = self.permute_swapped::<H>(perm_inputs, bit);
-            let hash_outs = perm_outs.squeeze()[0..NUM_HASH_OUT_ELTS]
-                .try_into()
-                .unwrap();
-            state = HashOutTarget {
-                elements: hash_outs,
-            };
+            state = self.merkle_hash_pair::<H>(state, sibling, bit);
             // Store state at specific indices
             for n in 0..num_log_n - 1 {
                 final_states[n] = final_states[n + 1];
@@ -251,27 +569,13 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) {
         debug_assert!(H::AlgebraicPermutation::RATE >= NUM_HASH_OUT_ELTS);
 
-        let zero = self.zero();
         let mut state: HashOutTarget = self.hash_or_noop::<H>(leaf_data[0].clone());
         debug_assert_eq!(state.elements.len(), NUM_HASH_OUT_ELTS);
 
         let mut current_height = leaf_heights[0];
         let mut leaf_data_index = 1;
         for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
-            debug_assert_eq!(sibling.elements.len(), NUM_HASH_OUT_ELTS);
-
-            let mut perm_inputs = H::AlgebraicPermutation::default();
-            perm_inputs.set_from_slice(&state.elements, 0);
-            perm_inputs.set_from_slice(&sibling.elements, NUM_HASH_OUT_ELTS);
-            // Ensure the rest of the state, if any, is zero:
-            perm_inputs.set_from_iter(core::iter::repeat(zero), 2 * NUM_HASH_OUT_ELTS);
-            let perm_outs = self.permute_swapped::<H>(perm_inputs, bit);
-            let hash_outs = perm_outs.squeeze()[0..NUM_HASH_OUT_ELTS]
-                .try_into()
-                .unwrap();
-            state = HashOutTarget {
-                elements: hash_outs,
-            };
+            state = self.merkle_hash_pair::<H>(state, sibling, bit);
             current_height -= 1;
 
             if leaf_data_index < leaf_heights.len()
@@ -294,6 +598,28 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Hashes two sibling digests into their parent digest, swapping them first if `swap` is true.
+    fn merkle_hash_pair<H: AlgebraicHasher<F>>(
+        &mut self,
+        left: HashOutTarget,
+        right: HashOutTarget,
+        swap: BoolTarget,
+    ) -> HashOutTarget {
+        let zero = self.zero();
+        let mut perm_inputs = H::AlgebraicPermutation::default();
+        perm_inputs.set_from_slice(&left.elements, 0);
+        perm_inputs.set_from_slice(&right.elements, NUM_HASH_OUT_ELTS);
+        // Ensure the rest of the state, if any, is zero:
+        perm_inputs.set_from_iter(core::iter::repeat(zero), 2 * NUM_HASH_OUT_ELTS);
+        let perm_outs = self.permute_swapped::<H>(perm_inputs, swap);
+        let hash_outs = perm_outs.squeeze()[0..NUM_HASH_OUT_ELTS]
+            .try_into()
+            .unwrap();
+        self.truncate_hash::<H>(HashOutTarget {
+            elements: hash_outs,
+        })
+    }
+
     pub fn connect_hashes(&mut self, x: HashOutTarget, y: HashOutTarget) {
         for i in 0..NUM_HASH_OUT_ELTS {
             self.connect(x.elements[i], y.elements[i]);
@@ -318,7 +644,9 @@ mod tests {
     use rand::Rng;
 
     use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
     use crate::field::types::Field;
+    use crate::gadgets::sha256::tests::sha256;
     use crate::hash::merkle_tree::MerkleTree;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_data::CircuitConfig;
@@ -359,9 +687,9 @@ mod tests {
         let i_c = builder.constant(F::from_canonical_usize(i));
         let i_bits = builder.split_le(i_c, log_n);
 
-        let data = builder.add_virtual_targets(tree.leaves[i].len());
+        let data = builder.add_virtual_targets(tree.get(i).len());
         for j in 0..data.len() {
-            pw.set_target(data[j], tree.leaves[i][j])?;
+            pw.set_target(data[j], tree.get(i)[j])?;
         }
 
         builder.verify_merkle_proof_to_cap::<<C as GenericConfig<D>>::InnerHasher>(
@@ -373,4 +701,311 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_recursive_merkle_proof_with_arity() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let log_n = 6;
+        let n = 1 << log_n;
+        for (arity_bits, cap_height) in [(2, 2), (3, 0)] {
+            let config = CircuitConfig::standard_recursion_config();
+            let mut pw = PartialWitness::new();
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+
+            let tree =
+                MerkleTree::<F, H>::new_with_arity(random_data::<F>(n, 7), cap_height, arity_bits);
+            let i: usize = OsRng.gen_range(0..n);
+            let proof = tree.prove(i);
+
+            let proof_t = MerkleProofTarget {
+                siblings: builder.add_virtual_hashes(proof.siblings.len()),
+            };
+            for (&sibling_t, &sibling) in proof_t.siblings.iter().zip(&proof.siblings) {
+                pw.set_hash_target(sibling_t, sibling)?;
+            }
+            let cap_t = builder.add_virtual_cap(cap_height);
+            pw.set_cap_target(&cap_t, &tree.cap)?;
+
+            let i_t = builder.add_virtual_target();
+            pw.set_target(i_t, F::from_canonical_usize(i))?;
+            let i_bits = builder.split_le(i_t, log_n);
+
+            let data_t = builder.add_virtual_targets(tree.get(i).len());
+            pw.set_target_arr(&data_t, tree.get(i))?;
+
+            builder.verify_merkle_proof_to_cap_with_arity::<H>(
+                data_t, &i_bits, &cap_t, &proof_t, arity_bits,
+            );
+
+            let data = builder.build::<C>();
+            let proof = data.prove(pw)?;
+            data.verify(proof)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_merkle_proof_with_leaf_hasher() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let log_n = 4;
+        for leaf_hasher in [LeafHasher::LengthPrefixed, LeafHasher::DomainSeparated(3)] {
+            let config = CircuitConfig::standard_recursion_config();
+            let mut pw = PartialWitness::new();
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+
+            let tree = MerkleTree::<F, H>::new_with_options(
+                random_data::<F>(1 << log_n, 2),
+                0,
+                1,
+                leaf_hasher,
+            );
+            let i: usize = OsRng.gen_range(0..1 << log_n);
+            let proof = tree.prove(i);
+
+            let proof_t = MerkleProofTarget {
+                siblings: builder.add_virtual_hashes(proof.siblings.len()),
+            };
+            for (&sibling_t, &sibling) in proof_t.siblings.iter().zip(&proof.siblings) {
+                pw.set_hash_target(sibling_t, sibling)?;
+            }
+            let root_t = builder.add_virtual_hash();
+            pw.set_hash_target(root_t, tree.cap.0[0])?;
+
+            let i_t = builder.add_virtual_target();
+            pw.set_target(i_t, F::from_canonical_usize(i))?;
+            let i_bits = builder.split_le(i_t, log_n);
+
+            let data_t = builder.add_virtual_targets(tree.get(i).len());
+            pw.set_target_arr(&data_t, tree.get(i))?;
+
+            builder.verify_merkle_proof_to_cap_with_options::<H>(
+                data_t,
+                &i_bits,
+                &MerkleCapTarget(vec![root_t]),
+                &proof_t,
+                1,
+                leaf_hasher,
+            );
+
+            let data = builder.build::<C>();
+            let proof = data.prove(pw)?;
+            data.verify(proof)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merkle_root_from_leaves() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let leaves = random_data::<F>(16, 7);
+        let tree = MerkleTree::<F, H>::new(leaves.clone(), 0);
+
+        let leaves_t = leaves
+            .iter()
+            .map(|leaf| builder.add_virtual_targets(leaf.len()))
+            .collect::<Vec<_>>();
+        for (leaf_t, leaf) in leaves_t.iter().zip(&leaves) {
+            pw.set_target_arr(leaf_t, leaf)?;
+        }
+        let root_t = builder.merkle_root_from_leaves::<H>(leaves_t);
+        let expected_root = builder.constant_hash(tree.cap.0[0]);
+        builder.connect_hashes(root_t, expected_root);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_merkle_proof_variable_depth() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let max_depth = 6;
+        let leaf_len = 7;
+        let leaf_t = builder.add_virtual_targets(leaf_len);
+        let index_t = builder.add_virtual_target();
+        let index_bits = builder.split_le(index_t, max_depth);
+        let root_t = builder.add_virtual_hash();
+        let proof_t = MerkleProofTarget {
+            siblings: builder.add_virtual_hashes(max_depth),
+        };
+        let depth_t = builder.add_virtual_target();
+        builder.verify_merkle_proof_variable_depth::<H>(
+            leaf_t.clone(),
+            &index_bits,
+            root_t,
+            &proof_t,
+            depth_t,
+            max_depth,
+        );
+        let data = builder.build::<C>();
+
+        // The same circuit verifies proofs from trees of different depths.
+        for depth in [0, 3, max_depth] {
+            let tree = MerkleTree::<F, H>::new(random_data::<F>(1 << depth, leaf_len), 0);
+            let i = OsRng.gen_range(0..1 << depth);
+            let proof = tree.prove(i);
+
+            let mut pw = PartialWitness::new();
+            pw.set_target_arr(&leaf_t, tree.get(i))?;
+            pw.set_target(index_t, F::from_canonical_usize(i))?;
+            pw.set_hash_target(root_t, tree.cap.0[0])?;
+            for (j, &sibling_t) in proof_t.siblings.iter().enumerate() {
+                let sibling = proof.siblings.get(j).copied().unwrap_or_default();
+                pw.set_hash_target(sibling_t, sibling)?;
+            }
+            pw.set_target(depth_t, F::from_canonical_usize(depth))?;
+
+            let proof = data.prove(pw)?;
+            data.verify(proof)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merkle_proof_variable_depth_too_deep() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let max_depth = 2;
+        // A valid proof for a tree of depth `max_depth`, but claimed to be deeper.
+        let tree = MerkleTree::<F, H>::new(random_data::<F>(1 << max_depth, 7), 0);
+        let proof = tree.prove(0);
+
+        let leaf_t = builder.constants(tree.get(0));
+        let index_bits = vec![builder._false(); max_depth];
+        let root_t = builder.constant_hash(tree.cap.0[0]);
+        let proof_t = MerkleProofTarget {
+            siblings: proof
+                .siblings
+                .iter()
+                .map(|&h| builder.constant_hash(h))
+                .collect(),
+        };
+        let depth_t = builder.constant(F::from_canonical_usize(max_depth + 1));
+        builder.verify_merkle_proof_variable_depth::<H>(
+            leaf_t,
+            &index_bits,
+            root_t,
+            &proof_t,
+            depth_t,
+            max_depth,
+        );
+
+        let data = builder.build::<C>();
+        assert!(data.prove(PartialWitness::new()).is_err());
+        Ok(())
+    }
+
+    type VerifyBytesMerkleProof = fn(
+        &mut CircuitBuilder<GoldilocksField, 2>,
+        [Target; 32],
+        &[BoolTarget],
+        [Target; 32],
+        &BytesMerkleProofTarget,
+    );
+
+    /// Proves membership of a random leaf in a random tree of depth 3 hashed with `hash`, returning
+    /// whether proving succeeded with the correct index and with a wrong one.
+    fn prove_bytes_merkle_proof(
+        hash: fn(&[u8]) -> [u8; 32],
+        verify_proof: VerifyBytesMerkleProof,
+    ) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let depth = 3;
+        let mut layers = vec![(0..1 << depth)
+            .map(|_| OsRng.gen::<[u8; 32]>())
+            .collect::<Vec<_>>()];
+        while layers.last().unwrap().len() > 1 {
+            let layer = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| hash(&[pair[0], pair[1]].concat()))
+                .collect();
+            layers.push(layer);
+        }
+        let i = OsRng.gen_range(0..1 << depth);
+        let siblings = (0..depth)
+            .map(|j| layers[j][(i >> j) ^ 1])
+            .collect::<Vec<_>>();
+
+        let leaf_t = builder.add_virtual_target_arr();
+        let index_t = builder.add_virtual_target();
+        let index_bits = builder.split_le(index_t, depth);
+        let root_t = builder.add_virtual_target_arr();
+        let proof_t = BytesMerkleProofTarget {
+            siblings: (0..depth)
+                .map(|_| builder.add_virtual_target_arr())
+                .collect(),
+        };
+        verify_proof(&mut builder, leaf_t, &index_bits, root_t, &proof_t);
+        let data = builder.build::<C>();
+
+        let set_bytes = |pw: &mut PartialWitness<F>, targets: &[Target; 32], bytes: &[u8; 32]| {
+            let bytes = bytes.map(F::from_canonical_u8);
+            pw.set_target_arr(targets, &bytes)
+        };
+        let witness = |index: usize| -> Result<PartialWitness<F>> {
+            let mut pw = PartialWitness::new();
+            set_bytes(&mut pw, &leaf_t, &layers[0][i])?;
+            pw.set_target(index_t, F::from_canonical_usize(index))?;
+            set_bytes(&mut pw, &root_t, &layers[depth][0])?;
+            for (sibling_t, sibling) in proof_t.siblings.iter().zip(&siblings) {
+                set_bytes(&mut pw, sibling_t, sibling)?;
+            }
+            Ok(pw)
+        };
+
+        let proof = data.prove(witness(i)?)?;
+        data.verify(proof)?;
+        assert!(data.prove(witness(i ^ 1)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_keccak_merkle_proof() -> Result<()> {
+        prove_bytes_merkle_proof(
+            |input| keccak_hash::keccak(input).0,
+            CircuitBuilder::verify_keccak_merkle_proof_target,
+        )
+    }
+
+    #[test]
+    fn test_sha256_merkle_proof() -> Result<()> {
+        prove_bytes_merkle_proof(sha256, CircuitBuilder::verify_sha256_merkle_proof_target)
+    }
 }
@@ -1,15 +1,20 @@
 #[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 use core::mem::MaybeUninit;
-use core::slice;
+use core::ops::Range;
+use core::str::FromStr;
+use core::{fmt, slice};
 
+use anyhow::ensure;
 use plonky2_maybe_rayon::*;
 use serde::{Deserialize, Serialize};
 
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::MerkleProof;
 use crate::plonk::config::{GenericHashOut, Hasher};
+use crate::util::encoding::{from_hex, to_hex};
 use crate::util::log2_strict;
+use crate::util::matrix::RowMajorMatrix;
 
 /// The Merkle cap of height `h` of a Merkle tree is the `h`-th layer (from the root) of the tree.
 /// It can be used in place of the root to verify Merkle paths, which are `h` elements shorter.
@@ -40,12 +45,77 @@ impl<F: RichField, H: Hasher<F>> MerkleCap<F, H> {
     pub fn flatten(&self) -> Vec<F> {
         self.0.iter().flat_map(|&h| h.to_vec()).collect()
     }
+
+    /// The concatenation of the canonical encodings of the hashes, i.e. the SSZ encoding of the
+    /// cap as a list of fixed-size hashes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.iter().flat_map(H::hash_to_bytes).collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        ensure!(
+            bytes.len().is_multiple_of(H::HASH_SIZE),
+            "A Merkle cap of {} bytes isn't made of hashes of {} bytes",
+            bytes.len(),
+            H::HASH_SIZE
+        );
+        let hashes = bytes
+            .chunks_exact(H::HASH_SIZE)
+            .map(H::hash_from_bytes)
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self(hashes))
+    }
+}
+
+/// Formats the cap as the hexadecimal representation of [`MerkleCap::to_bytes`].
+impl<F: RichField, H: Hasher<F>> fmt::Display for MerkleCap<F, H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&to_hex(&self.to_bytes()))
+    }
+}
+
+impl<F: RichField, H: Hasher<F>> FromStr for MerkleCap<F, H> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bytes(&from_hex(s)?)
+    }
+}
+
+/// How the leaves of a Merkle tree are hashed into the digests of the bottom layer.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum LeafHasher {
+    /// Leaves that fit in a digest are used as is, and longer ones are hashed without padding, as
+    /// with `Hasher::hash_or_noop`.
+    #[default]
+    Raw,
+    /// Leaves are always hashed, with their length prepended, so that leaves of different lengths
+    /// never share a digest.
+    LengthPrefixed,
+    /// Leaves are always hashed, with the given domain tag prepended, which separates their digests
+    /// from those of internal nodes and of trees using other tags.
+    DomainSeparated(u64),
+}
+
+impl LeafHasher {
+    /// Hashes `leaf` into a leaf digest.
+    pub fn hash_leaf<F: RichField, H: Hasher<F>>(self, leaf: &[F]) -> H::Hash {
+        let prefix = match self {
+            Self::Raw => return H::hash_or_noop(leaf),
+            Self::LengthPrefixed => F::from_canonical_usize(leaf.len()),
+            Self::DomainSeparated(tag) => F::from_noncanonical_u64(tag),
+        };
+        let mut inputs = Vec::with_capacity(leaf.len() + 1);
+        inputs.push(prefix);
+        inputs.extend_from_slice(leaf);
+        H::hash_no_pad(&inputs)
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MerkleTree<F: RichField, H: Hasher<F>> {
-    /// The data in the leaves of the Merkle tree.
-    pub leaves: Vec<Vec<F>>,
+    /// The data in the leaves of the Merkle tree, one leaf per row.
+    pub leaves: RowMajorMatrix<F>,
 
     /// The digests in the tree. Consists of `cap.len()` sub-trees, each corresponding to one
     /// element in `cap`. Each subtree is contiguous and located at
@@ -55,18 +125,28 @@ pub struct MerkleTree<F: RichField, H: Hasher<F>> {
     /// left_child_digest and right_child_digest are H::Hash and left_child_subtree and
     /// right_child_subtree recurse. Observe that the digest of a node is stored by its _parent_.
     /// Consequently, the digests of the roots are not stored here (they can be found in `cap`).
+    /// In trees of higher arity, the digests of a node's children are stored between the subtrees
+    /// of its first and second halves of children, so binary trees keep the layout above.
     pub digests: Vec<H::Hash>,
 
     /// The Merkle cap.
     pub cap: MerkleCap<F, H>,
+
+    /// The base-2 logarithm of the number of children of each node.
+    pub arity_bits: usize,
+
+    /// How the leaves are hashed.
+    pub leaf_hasher: LeafHasher,
 }
 
 impl<F: RichField, H: Hasher<F>> Default for MerkleTree<F, H> {
     fn default() -> Self {
         Self {
-            leaves: Vec::new(),
+            leaves: RowMajorMatrix::default(),
             digests: Vec::new(),
             cap: MerkleCap::default(),
+            arity_bits: 1,
+            leaf_hasher: LeafHasher::Raw,
         }
     }
 }
@@ -83,13 +163,18 @@ pub(crate) fn capacity_up_to_mut<T>(v: &mut Vec<T>, len: usize) -> &mut [MaybeUn
     }
 }
 
+/// Fills the digests of the subtree whose leaves are those in `leaves`, where `leaf_digest` hashes
+/// the leaf of the given index, and returns the digest of its root.
 pub(crate) fn fill_subtree<F: RichField, H: Hasher<F>>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
-    leaves: &[Vec<F>],
+    leaves: Range<usize>,
+    leaf_digest: &(impl Fn(usize) -> H::Hash + Sync),
 ) -> H::Hash {
     assert_eq!(leaves.len(), digests_buf.len() / 2 + 1);
     if digests_buf.is_empty() {
-        H::hash_or_noop(&leaves[0])
+        leaf_digest(leaves.start)
+    } else if leaves.len() <= BATCHED_SUBTREE_LEAVES {
+        fill_subtree_batched::<F, H>(digests_buf, leaves, leaf_digest)
     } else {
         // Layout is: left recursive output || left child digest
         //             || right child digest || right recursive output.
@@ -99,11 +184,11 @@ pub(crate) fn fill_subtree<F: RichField, H: Hasher<F>>(
         let (left_digest_mem, left_digests_buf) = left_digests_buf.split_last_mut().unwrap();
         let (right_digest_mem, right_digests_buf) = right_digests_buf.split_first_mut().unwrap();
         // Split `leaves` between both children.
-        let (left_leaves, right_leaves) = leaves.split_at(leaves.len() / 2);
+        let mid = leaves.start + leaves.len() / 2;
 
         let (left_digest, right_digest) = plonky2_maybe_rayon::join(
-            || fill_subtree::<F, H>(left_digests_buf, left_leaves),
-            || fill_subtree::<F, H>(right_digests_buf, right_leaves),
+            || fill_subtree::<F, H>(left_digests_buf, leaves.start..mid, leaf_digest),
+            || fill_subtree::<F, H>(right_digests_buf, mid..leaves.end, leaf_digest),
         );
 
         left_digest_mem.write(left_digest);
@@ -112,40 +197,165 @@ pub(crate) fn fill_subtree<F: RichField, H: Hasher<F>>(
     }
 }
 
+/// Subtrees with at most this many leaves are filled layer by layer, so that the nodes of each layer,
+/// whose children are sibling subtrees, can be compressed together with `Hasher::two_to_one_batch`.
+const BATCHED_SUBTREE_LEAVES: usize = 64;
+
+/// Same as `fill_subtree`, computing the digests one layer at a time.
+fn fill_subtree_batched<F: RichField, H: Hasher<F>>(
+    digests_buf: &mut [MaybeUninit<H::Hash>],
+    leaves: Range<usize>,
+    leaf_digest: &(impl Fn(usize) -> H::Hash + Sync),
+) -> H::Hash {
+    let mut layers = vec![leaves.map(leaf_digest).collect::<Vec<_>>()];
+    while layers[layers.len() - 1].len() > 1 {
+        let next_layer = H::two_to_one_batch(&layers[layers.len() - 1]);
+        layers.push(next_layer);
+    }
+    let root = layers.pop().unwrap()[0];
+    write_subtree_digests(digests_buf, &layers, 0);
+    root
+}
+
+/// Writes the digests of the `index`-th subtree whose nodes, from the leaves up to the children of
+/// its root, are in `layers`, with the layout of `fill_subtree`.
+fn write_subtree_digests<T: Copy>(
+    digests_buf: &mut [MaybeUninit<T>],
+    layers: &[Vec<T>],
+    index: usize,
+) {
+    if let Some((children, descendants)) = layers.split_last() {
+        let (left_digests_buf, right_digests_buf) = digests_buf.split_at_mut(digests_buf.len() / 2);
+        let (left_digest_mem, left_digests_buf) = left_digests_buf.split_last_mut().unwrap();
+        let (right_digest_mem, right_digests_buf) = right_digests_buf.split_first_mut().unwrap();
+
+        write_subtree_digests(left_digests_buf, descendants, 2 * index);
+        left_digest_mem.write(children[2 * index]);
+        right_digest_mem.write(children[2 * index + 1]);
+        write_subtree_digests(right_digests_buf, descendants, 2 * index + 1);
+    }
+}
+
+/// Fills the digests and the cap of the Merkle tree with `num_leaves` leaves, where `leaf_digest`
+/// hashes the leaf of the given index.
 pub(crate) fn fill_digests_buf<F: RichField, H: Hasher<F>>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
     cap_buf: &mut [MaybeUninit<H::Hash>],
-    leaves: &[Vec<F>],
+    num_leaves: usize,
+    leaf_digest: &(impl Fn(usize) -> H::Hash + Sync),
     cap_height: usize,
 ) {
     // Special case of a tree that's all cap. The usual case will panic because we'll try to split
     // an empty slice into chunks of `0`. (We would not need this if there was a way to split into
     // `blah` chunks as opposed to chunks _of_ `blah`.)
     if digests_buf.is_empty() {
-        debug_assert_eq!(cap_buf.len(), leaves.len());
-        cap_buf
-            .par_iter_mut()
-            .zip(leaves)
-            .for_each(|(cap_buf, leaf)| {
-                cap_buf.write(H::hash_or_noop(leaf));
-            });
+        debug_assert_eq!(cap_buf.len(), num_leaves);
+        cap_buf.par_iter_mut().enumerate().for_each(|(i, cap_buf)| {
+            cap_buf.write(leaf_digest(i));
+        });
         return;
     }
 
     let subtree_digests_len = digests_buf.len() >> cap_height;
-    let subtree_leaves_len = leaves.len() >> cap_height;
+    let subtree_leaves_len = num_leaves >> cap_height;
     let digests_chunks = digests_buf.par_chunks_exact_mut(subtree_digests_len);
-    let leaves_chunks = leaves.par_chunks_exact(subtree_leaves_len);
     assert_eq!(digests_chunks.len(), cap_buf.len());
-    assert_eq!(digests_chunks.len(), leaves_chunks.len());
-    digests_chunks.zip(cap_buf).zip(leaves_chunks).for_each(
-        |((subtree_digests, subtree_cap), subtree_leaves)| {
+    assert_eq!(digests_chunks.len() * subtree_leaves_len, num_leaves);
+    digests_chunks
+        .zip(cap_buf)
+        .enumerate()
+        .for_each(|(i, (subtree_digests, subtree_cap))| {
             // We have `1 << cap_height` sub-trees, one for each entry in `cap`. They are totally
             // independent, so we schedule one task for each. `digests_buf` and `leaves` are split
             // into `1 << cap_height` slices, one for each sub-tree.
-            subtree_cap.write(fill_subtree::<F, H>(subtree_digests, subtree_leaves));
-        },
+            let subtree_leaves = i * subtree_leaves_len..(i + 1) * subtree_leaves_len;
+            subtree_cap.write(fill_subtree::<F, H>(
+                subtree_digests,
+                subtree_leaves,
+                leaf_digest,
+            ));
+        });
+}
+
+/// Hashes the digests of a node's children into the digest of the node. Pairs are compressed with
+/// `two_to_one`, while wider nodes hash the concatenation of their children's digests.
+pub(crate) fn hash_children<F: RichField, H: Hasher<F>>(children: &[H::Hash]) -> H::Hash {
+    if let [left, right] = children {
+        H::two_to_one(*left, *right)
+    } else {
+        let inputs = children.iter().flat_map(|h| h.to_vec()).collect::<Vec<_>>();
+        H::hash_no_pad(&inputs)
+    }
+}
+
+/// Same as `fill_subtree`, for a tree whose nodes have `1 << arity_bits` children.
+fn fill_subtree_with_arity<F: RichField, H: Hasher<F>>(
+    digests_buf: &mut [MaybeUninit<H::Hash>],
+    leaves: Range<usize>,
+    leaf_digest: &(impl Fn(usize) -> H::Hash + Sync),
+    arity_bits: usize,
+) -> H::Hash {
+    if digests_buf.is_empty() {
+        return leaf_digest(leaves.start);
+    }
+
+    let arity = 1 << arity_bits;
+    let child_digests_len = (digests_buf.len() - arity) / arity;
+    let child_leaves_len = leaves.len() >> arity_bits;
+    // Layout is: first half of the recursive outputs || child digests
+    //             || second half of the recursive outputs.
+    let (first_half, rest) = digests_buf.split_at_mut(arity / 2 * child_digests_len);
+    let (child_digests_mem, second_half) = rest.split_at_mut(arity);
+    let mut children = Vec::with_capacity(arity);
+    for half in [first_half, second_half] {
+        if child_digests_len == 0 {
+            children.extend((0..arity / 2).map(|_| <&mut [_]>::default()));
+        } else {
+            children.extend(half.chunks_exact_mut(child_digests_len));
+        }
+    }
+
+    let child_digests = children
+        .par_iter_mut()
+        .enumerate()
+        .map(|(i, child_buf)| {
+            let start = leaves.start + i * child_leaves_len;
+            fill_subtree_with_arity::<F, H>(
+                child_buf,
+                start..start + child_leaves_len,
+                leaf_digest,
+                arity_bits,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for (mem, &digest) in child_digests_mem.iter_mut().zip(&child_digests) {
+        mem.write(digest);
+    }
+    hash_children::<F, H>(&child_digests)
+}
+
+/// Computes the cap of height `cap_height` of the Merkle tree whose leaves have the given digests,
+/// without storing the rest of the tree.
+pub(crate) fn merkle_cap_from_leaf_digests<F: RichField, H: Hasher<F>>(
+    mut digests: Vec<H::Hash>,
+    cap_height: usize,
+) -> MerkleCap<F, H> {
+    let log2_leaves_len = log2_strict(digests.len());
+    assert!(
+        cap_height <= log2_leaves_len,
+        "cap_height={} should be at most log2(leaves.len())={}",
+        cap_height,
+        log2_leaves_len
     );
+
+    while digests.len() > 1 << cap_height {
+        digests = digests
+            .par_chunks_exact(2)
+            .map(|pair| H::two_to_one(pair[0], pair[1]))
+            .collect();
+    }
+    MerkleCap(digests)
 }
 
 pub(crate) fn merkle_tree_prove<F: RichField, H: Hasher<F>>(
@@ -189,17 +399,127 @@ pub(crate) fn merkle_tree_prove<F: RichField, H: Hasher<F>>(
         .collect()
 }
 
+/// Same as `merkle_tree_prove`, for a tree whose nodes have `1 << arity_bits` children. The
+/// siblings of each layer are listed in order, skipping the node on the path.
+pub(crate) fn merkle_tree_prove_with_arity<F: RichField, H: Hasher<F>>(
+    leaf_index: usize,
+    leaves_len: usize,
+    cap_height: usize,
+    arity_bits: usize,
+    digests: &[H::Hash],
+) -> Vec<H::Hash> {
+    let arity = 1 << arity_bits;
+    let num_layers = (log2_strict(leaves_len) - cap_height) / arity_bits;
+    let path_bits = num_layers * arity_bits;
+    debug_assert_eq!(leaf_index >> (cap_height + path_bits), 0);
+
+    let tree_len = digests.len() >> cap_height;
+    let tree_index = leaf_index >> path_bits;
+    let mut node = &digests[tree_len * tree_index..tree_len * (tree_index + 1)];
+
+    // Walk down from the cap, collecting the siblings of each layer.
+    let mut layers = Vec::with_capacity(num_layers);
+    for layer in (0..num_layers).rev() {
+        let child_index = (leaf_index >> (layer * arity_bits)) & (arity - 1);
+        let child_len = (node.len() - arity) / arity;
+        let digests_start = arity / 2 * child_len;
+        let child_digests = &node[digests_start..digests_start + arity];
+        layers.push(
+            child_digests
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i != child_index)
+                .map(|(_, &h)| h)
+                .collect::<Vec<_>>(),
+        );
+
+        let child_start = if child_index < arity / 2 {
+            child_index * child_len
+        } else {
+            digests_start + arity + (child_index - arity / 2) * child_len
+        };
+        node = &node[child_start..child_start + child_len];
+    }
+    layers.into_iter().rev().flatten().collect()
+}
+
 impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
+    /// Creates a Merkle tree with the given leaves, which must all have the same length.
     pub fn new(leaves: Vec<Vec<F>>, cap_height: usize) -> Self {
-        let log2_leaves_len = log2_strict(leaves.len());
+        Self::from_matrix(RowMajorMatrix::from_rows(leaves), cap_height)
+    }
+
+    /// Creates a Merkle tree whose nodes have `1 << arity_bits` children, with the given leaves,
+    /// which must all have the same length. Higher arities give shorter paths, at the cost of
+    /// wider hashes.
+    pub fn new_with_arity(leaves: Vec<Vec<F>>, cap_height: usize, arity_bits: usize) -> Self {
+        Self::from_matrix_with_arity(RowMajorMatrix::from_rows(leaves), cap_height, arity_bits)
+    }
+
+    /// Creates a Merkle tree whose nodes have `1 << arity_bits` children and whose leaves are
+    /// hashed with `leaf_hasher`, with the given leaves, which must all have the same length.
+    pub fn new_with_options(
+        leaves: Vec<Vec<F>>,
+        cap_height: usize,
+        arity_bits: usize,
+        leaf_hasher: LeafHasher,
+    ) -> Self {
+        Self::from_matrix_with_options(
+            RowMajorMatrix::from_rows(leaves),
+            cap_height,
+            arity_bits,
+            leaf_hasher,
+        )
+    }
+
+    /// Creates a Merkle tree whose leaves are the rows of `leaves`.
+    pub fn from_matrix(leaves: RowMajorMatrix<F>, cap_height: usize) -> Self {
+        Self::from_matrix_with_options(leaves, cap_height, 1, LeafHasher::Raw)
+    }
+
+    /// Creates a Merkle tree whose nodes have `1 << arity_bits` children, and whose leaves are the
+    /// rows of `leaves`. The number of layers below the cap, `log2(leaves.height()) - cap_height`,
+    /// must be a multiple of `arity_bits`.
+    pub fn from_matrix_with_arity(
+        leaves: RowMajorMatrix<F>,
+        cap_height: usize,
+        arity_bits: usize,
+    ) -> Self {
+        Self::from_matrix_with_options(leaves, cap_height, arity_bits, LeafHasher::Raw)
+    }
+
+    /// Creates a Merkle tree whose nodes have `1 << arity_bits` children, and whose leaves are the
+    /// rows of `leaves`, hashed with `leaf_hasher`. The number of layers below the cap,
+    /// `log2(leaves.height()) - cap_height`, must be a multiple of `arity_bits`.
+    pub fn from_matrix_with_options(
+        leaves: RowMajorMatrix<F>,
+        cap_height: usize,
+        arity_bits: usize,
+        leaf_hasher: LeafHasher,
+    ) -> Self {
+        assert!(
+            arity_bits > 0,
+            "Merkle trees must have an arity of at least 2"
+        );
+        let log2_leaves_len = log2_strict(leaves.height());
         assert!(
             cap_height <= log2_leaves_len,
             "cap_height={} should be at most log2(leaves.len())={}",
             cap_height,
             log2_leaves_len
         );
+        let num_layers_bits = log2_leaves_len - cap_height;
+        assert!(
+            num_layers_bits.is_multiple_of(arity_bits),
+            "log2(leaves.len()) - cap_height={} should be a multiple of arity_bits={}",
+            num_layers_bits,
+            arity_bits
+        );
 
-        let num_digests = 2 * (leaves.len() - (1 << cap_height));
+        // Each subtree of `2^num_layers_bits` leaves stores the digests of all its non-root nodes.
+        let arity = 1 << arity_bits;
+        let subtree_digests_len = arity * ((1 << num_layers_bits) - 1) / (arity - 1);
+        let num_digests = subtree_digests_len << cap_height;
         let mut digests = Vec::with_capacity(num_digests);
 
         let len_cap = 1 << cap_height;
@@ -207,11 +527,40 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
 
         let digests_buf = capacity_up_to_mut(&mut digests, num_digests);
         let cap_buf = capacity_up_to_mut(&mut cap, len_cap);
-        fill_digests_buf::<F, H>(digests_buf, cap_buf, &leaves[..], cap_height);
+        let leaf_digest = |i| leaf_hasher.hash_leaf::<F, H>(leaves.row(i));
+        if arity_bits == 1 {
+            fill_digests_buf::<F, H>(
+                digests_buf,
+                cap_buf,
+                leaves.height(),
+                &leaf_digest,
+                cap_height,
+            );
+        } else if digests_buf.is_empty() {
+            // The tree is all cap.
+            cap_buf.par_iter_mut().enumerate().for_each(|(i, cap_buf)| {
+                cap_buf.write(leaf_digest(i));
+            });
+        } else {
+            let subtree_leaves_len = leaves.height() >> cap_height;
+            digests_buf
+                .par_chunks_exact_mut(subtree_digests_len)
+                .zip(cap_buf)
+                .enumerate()
+                .for_each(|(i, (subtree_digests, subtree_cap))| {
+                    let subtree_leaves = i * subtree_leaves_len..(i + 1) * subtree_leaves_len;
+                    subtree_cap.write(fill_subtree_with_arity::<F, H>(
+                        subtree_digests,
+                        subtree_leaves,
+                        &leaf_digest,
+                        arity_bits,
+                    ));
+                });
+        }
 
         unsafe {
-            // SAFETY: `fill_digests_buf` and `cap` initialized the spare capacity up to
-            // `num_digests` and `len_cap`, resp.
+            // SAFETY: `fill_digests_buf` or `fill_subtree_with_arity` initialized the spare
+            // capacity up to `num_digests` and `len_cap`, resp.
             digests.set_len(num_digests);
             cap.set_len(len_cap);
         }
@@ -220,18 +569,73 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
             leaves,
             digests,
             cap: MerkleCap(cap),
+            arity_bits,
+            leaf_hasher,
+        }
+    }
+
+    /// Assembles the Merkle tree whose leaves are the leaves of `trees` one after the other, e.g.
+    /// trees built on different machines. As the subtrees under a cap are stored one after the
+    /// other, this is the tree with cap height `cap_height + log2(trees.len())` built from all the
+    /// leaves, if each tree has cap height `cap_height`. The trees must have the same shape, arity
+    /// and leaf hasher, and their number must be a power of two.
+    pub fn from_subtrees(trees: Vec<Self>) -> Self {
+        let first = &trees[0];
+        assert!(
+            trees.len().is_power_of_two(),
+            "The number of subtrees must be a power of two"
+        );
+        assert!(
+            trees
+                .iter()
+                .all(|tree| tree.leaves.height() == first.leaves.height()
+                    && tree.leaves.width() == first.leaves.width()
+                    && tree.cap.len() == first.cap.len()
+                    && tree.arity_bits == first.arity_bits
+                    && tree.leaf_hasher == first.leaf_hasher),
+            "The subtrees must have the same shape"
+        );
+        let (width, arity_bits, leaf_hasher) =
+            (first.leaves.width(), first.arity_bits, first.leaf_hasher);
+        let values = trees
+            .iter()
+            .flat_map(|tree| tree.leaves.values())
+            .copied()
+            .collect();
+        let mut digests = Vec::with_capacity(trees.len() * first.digests.len());
+        let mut cap = Vec::with_capacity(trees.len() * first.cap.len());
+        for tree in trees {
+            digests.extend(tree.digests);
+            cap.extend(tree.cap.0);
+        }
+
+        Self {
+            leaves: RowMajorMatrix::new(values, width),
+            digests,
+            cap: MerkleCap(cap),
+            arity_bits,
+            leaf_hasher,
         }
     }
 
     pub fn get(&self, i: usize) -> &[F] {
-        &self.leaves[i]
+        self.leaves.row(i)
     }
 
     /// Create a Merkle proof from a leaf index.
     pub fn prove(&self, leaf_index: usize) -> MerkleProof<F, H> {
         let cap_height = log2_strict(self.cap.len());
-        let siblings =
-            merkle_tree_prove::<F, H>(leaf_index, self.leaves.len(), cap_height, &self.digests);
+        let siblings = if self.arity_bits == 1 {
+            merkle_tree_prove::<F, H>(leaf_index, self.leaves.height(), cap_height, &self.digests)
+        } else {
+            merkle_tree_prove_with_arity::<F, H>(
+                leaf_index,
+                self.leaves.height(),
+                cap_height,
+                self.arity_bits,
+                &self.digests,
+            )
+        };
 
         MerkleProof { siblings }
     }
@@ -240,11 +644,18 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
 #[cfg(test)]
 pub(crate) mod tests {
     use anyhow::Result;
+    use itertools::Itertools;
 
     use super::*;
     use crate::field::extension::Extendable;
-    use crate::hash::merkle_proofs::verify_merkle_proof_to_cap;
+    use crate::hash::hash_types::HashOut;
+    use crate::hash::merkle_proofs::{
+        verify_merkle_proof_to_cap, verify_merkle_proof_to_cap_with_arity,
+        verify_merkle_proof_to_cap_with_options,
+    };
+    use crate::hash::poseidon::TruncatedPoseidonHash;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::util::serialization::{Buffer, Read, Write};
 
     pub(crate) fn random_data<F: RichField>(n: usize, k: usize) -> Vec<Vec<F>> {
         (0..n).map(|_| F::rand_vec(k)).collect()
@@ -266,6 +677,56 @@ pub(crate) mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_cap_hex_round_trip() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let hash_size = <H as Hasher<F>>::HASH_SIZE;
+        let tree = MerkleTree::<F, H>::new(random_data::<F>(16, 3), 2);
+        let hex = tree.cap.to_string();
+        assert_eq!(hex.len(), 2 + 2 * 4 * hash_size);
+        assert_eq!(hex.parse::<MerkleCap<F, H>>()?, tree.cap);
+
+        let hash = tree.cap.0[0];
+        assert_eq!(hash.to_string().parse::<HashOut<F>>()?, hash);
+        assert_eq!(hash.to_string(), &hex[..2 + 2 * hash_size]);
+
+        // Non-canonical field elements and truncated hashes are rejected.
+        let non_canonical = "0x".to_string() + &"ff".repeat(hash_size);
+        assert!(non_canonical.parse::<HashOut<F>>().is_err());
+        assert!(non_canonical.parse::<MerkleCap<F, H>>().is_err());
+        assert!(hex[..hex.len() - 2].parse::<MerkleCap<F, H>>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_hash_cap_round_trip() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = TruncatedPoseidonHash<2>;
+
+        let tree = MerkleTree::<F, H>::new(random_data::<F>(16, 7), 2);
+        for (i, leaf) in tree.leaves.rows().enumerate() {
+            verify_merkle_proof_to_cap(leaf.to_vec(), i, &tree.cap, &tree.prove(i))?;
+        }
+
+        // Only the first two elements of each digest are kept, and encoded.
+        assert!(tree
+            .cap
+            .0
+            .iter()
+            .all(|h| h.elements[2..] == HashOut::<F>::ZERO.elements[2..]));
+        let bytes = tree.cap.to_bytes();
+        assert_eq!(bytes.len(), 4 * 16);
+        assert_eq!(MerkleCap::<F, H>::from_bytes(&bytes)?, tree.cap);
+        assert!(<H as Hasher<F>>::hash_from_bytes(&tree.cap.0[0].to_bytes()).is_err());
+        Ok(())
+    }
+
     #[test]
     #[should_panic]
     fn test_cap_height_too_big() {
@@ -295,6 +756,117 @@ pub(crate) mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_merkle_cap_from_leaf_digests() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let log_n = 8;
+        let leaves = random_data::<F>(1 << log_n, 7);
+        let digests = leaves
+            .iter()
+            .map(|leaf| H::hash_or_noop(leaf))
+            .collect::<Vec<_>>();
+        for cap_height in [0, 3, log_n] {
+            let tree = MerkleTree::<F, H>::new(leaves.clone(), cap_height);
+            assert_eq!(
+                merkle_cap_from_leaf_digests::<F, H>(digests.clone(), cap_height),
+                tree.cap
+            );
+        }
+    }
+
+    #[test]
+    fn test_merkle_trees_with_arity() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let log_n = 6;
+        let leaves = random_data::<F>(1 << log_n, 7);
+        for (arity_bits, cap_height) in [(1, 2), (2, 0), (2, 2), (2, 6), (3, 0), (3, 3)] {
+            let tree = MerkleTree::<F, H>::new_with_arity(leaves.clone(), cap_height, arity_bits);
+            assert_eq!(tree.cap.height(), cap_height);
+            let num_layers = (log_n - cap_height) / arity_bits;
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = tree.prove(i);
+                assert_eq!(proof.len(), num_layers * ((1 << arity_bits) - 1));
+                verify_merkle_proof_to_cap_with_arity(
+                    leaf.clone(),
+                    i,
+                    &tree.cap,
+                    &proof,
+                    arity_bits,
+                )?;
+                let wrong_index = (i + 1) % leaves.len();
+                assert!(verify_merkle_proof_to_cap_with_arity(
+                    leaf.clone(),
+                    wrong_index,
+                    &tree.cap,
+                    &proof,
+                    arity_bits,
+                )
+                .is_err());
+            }
+        }
+
+        // A binary tree is the same whichever constructor is used.
+        assert_eq!(
+            MerkleTree::<F, H>::new_with_arity(leaves.clone(), 2, 1),
+            MerkleTree::<F, H>::new(leaves, 2)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_leaf_hashers() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let leaf_hashers = [
+            LeafHasher::Raw,
+            LeafHasher::LengthPrefixed,
+            LeafHasher::DomainSeparated(1),
+            LeafHasher::DomainSeparated(2),
+        ];
+        // Short leaves fit in a digest, so are only hashed by the hardened strategies.
+        let leaves = random_data::<F>(1 << 4, 3);
+        let caps = leaf_hashers.map(|leaf_hasher| {
+            let tree = MerkleTree::<F, H>::new_with_options(leaves.clone(), 0, 2, leaf_hasher);
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = tree.prove(i);
+                for other in leaf_hashers {
+                    let result = verify_merkle_proof_to_cap_with_options(
+                        leaf.clone(),
+                        i,
+                        &tree.cap,
+                        &proof,
+                        2,
+                        other,
+                    );
+                    assert_eq!(result.is_ok(), other == leaf_hasher);
+                }
+            }
+
+            let mut bytes = Vec::new();
+            bytes.write_merkle_tree(&tree).unwrap();
+            assert_eq!(Buffer::new(&bytes).read_merkle_tree().unwrap(), tree);
+            tree.cap
+        });
+        assert!(caps.iter().tuple_combinations().all(|(a, b)| a != b));
+
+        let raw_tree = MerkleTree::<F, H>::new_with_options(leaves.clone(), 0, 1, LeafHasher::Raw);
+        assert_eq!(raw_tree, MerkleTree::<F, H>::new(leaves, 0));
+
+        Ok(())
+    }
+
     #[test]
     fn test_merkle_trees() -> Result<()> {
         const D: usize = 2;
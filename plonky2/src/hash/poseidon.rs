@@ -5,6 +5,7 @@
 use alloc::{vec, vec::Vec};
 use core::fmt::Debug;
 
+use plonky2_field::packable::Packable;
 use plonky2_field::packed::PackedField;
 use unroll::unroll_for_loops;
 
@@ -13,12 +14,14 @@ use crate::field::types::{Field, PrimeField64};
 use crate::gates::gate::Gate;
 use crate::gates::poseidon::PoseidonGate;
 use crate::gates::poseidon_mds::PoseidonMdsGate;
-use crate::hash::hash_types::{HashOut, RichField};
+use crate::gates::poseidon_sponge::PoseidonSpongeGate;
+use crate::hash::hash_types::{HashOut, RichField, NUM_HASH_OUT_ELTS};
 use crate::hash::hashing::{compress, hash_n_to_hash_no_pad, PlonkyPermutation};
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{AlgebraicHasher, Hasher};
+use crate::util::encoding::decode_field_elements;
 
 pub const SPONGE_RATE: usize = 8;
 pub const SPONGE_CAPACITY: usize = 4;
@@ -695,6 +698,16 @@ pub trait Poseidon: PrimeField64 {
         x3 * x4
     }
 
+    /// Same as `sbox_monomial` for `PackedField`.
+    #[inline(always)]
+    fn sbox_monomial_packed_field<P: PackedField<Scalar = Self>>(x: P) -> P {
+        // x |--> x^7
+        let x2 = x.square();
+        let x4 = x2.square();
+        let x3 = x * x2;
+        x3 * x4
+    }
+
     /// Recursive version of `sbox_monomial`.
     fn sbox_monomial_circuit<const D: usize>(
         builder: &mut CircuitBuilder<Self, D>,
@@ -726,6 +739,13 @@ pub trait Poseidon: PrimeField64 {
         }
     }
 
+    /// Same as `sbox_layer` for `PackedField`.
+    fn sbox_layer_packed_field<P: PackedField<Scalar = Self>>(state: &mut [P; SPONGE_WIDTH]) {
+        for i in 0..SPONGE_WIDTH {
+            state[i] = Self::sbox_monomial_packed_field(state[i]);
+        }
+    }
+
     /// Recursive version of `sbox_layer`.
     fn sbox_layer_circuit<const D: usize>(
         builder: &mut CircuitBuilder<Self, D>,
@@ -801,6 +821,152 @@ pub trait Poseidon: PrimeField64 {
     }
 }
 
+/// Poseidon over `PackedField`s, which runs `P::WIDTH` independent permutations at once, one per
+/// lane of `P`.
+pub trait PoseidonPacked: Poseidon {
+    /// Same as `constant_layer` for `PackedField`.
+    fn constant_layer_packed<P: PackedField<Scalar = Self>>(
+        state: &mut [P; SPONGE_WIDTH],
+        round_ctr: usize,
+    ) {
+        for i in 0..SPONGE_WIDTH {
+            state[i] += Self::from_canonical_u64(ALL_ROUND_CONSTANTS[i + SPONGE_WIDTH * round_ctr]);
+        }
+    }
+
+    /// Same as `mds_layer` for `PackedField`.
+    fn mds_layer_packed<P: PackedField<Scalar = Self>>(
+        state: &[P; SPONGE_WIDTH],
+    ) -> [P; SPONGE_WIDTH] {
+        let mut result = [P::ZEROS; SPONGE_WIDTH];
+
+        for r in 0..SPONGE_WIDTH {
+            for i in 0..SPONGE_WIDTH {
+                result[r] += state[(i + r) % SPONGE_WIDTH]
+                    * Self::from_canonical_u64(Self::MDS_MATRIX_CIRC[i]);
+            }
+            result[r] += state[r] * Self::from_canonical_u64(Self::MDS_MATRIX_DIAG[r]);
+        }
+
+        result
+    }
+
+    /// Same as `mds_partial_layer_init` for `PackedField`.
+    fn mds_partial_layer_init_packed<P: PackedField<Scalar = Self>>(
+        state: &[P; SPONGE_WIDTH],
+    ) -> [P; SPONGE_WIDTH] {
+        let mut result = [P::ZEROS; SPONGE_WIDTH];
+        result[0] = state[0];
+
+        for r in 1..SPONGE_WIDTH {
+            for c in 1..SPONGE_WIDTH {
+                let t =
+                    Self::from_canonical_u64(Self::FAST_PARTIAL_ROUND_INITIAL_MATRIX[r - 1][c - 1]);
+                result[c] += state[r] * t;
+            }
+        }
+        result
+    }
+
+    /// Same as `mds_partial_layer_fast` for `PackedField`.
+    fn mds_partial_layer_fast_packed<P: PackedField<Scalar = Self>>(
+        state: &[P; SPONGE_WIDTH],
+        r: usize,
+    ) -> [P; SPONGE_WIDTH] {
+        let mds0to0 = Self::MDS_MATRIX_CIRC[0] + Self::MDS_MATRIX_DIAG[0];
+        let mut d = state[0] * Self::from_canonical_u64(mds0to0);
+        for i in 1..SPONGE_WIDTH {
+            d += state[i] * Self::from_canonical_u64(Self::FAST_PARTIAL_ROUND_W_HATS[r][i - 1]);
+        }
+
+        let mut result = [P::ZEROS; SPONGE_WIDTH];
+        result[0] = d;
+        for i in 1..SPONGE_WIDTH {
+            let t = Self::from_canonical_u64(Self::FAST_PARTIAL_ROUND_VS[r][i - 1]);
+            result[i] = state[0] * t + state[i];
+        }
+        result
+    }
+
+    /// Same as `full_rounds` for `PackedField`.
+    fn full_rounds_packed<P: PackedField<Scalar = Self>>(
+        state: &mut [P; SPONGE_WIDTH],
+        round_ctr: &mut usize,
+    ) {
+        for _ in 0..HALF_N_FULL_ROUNDS {
+            Self::constant_layer_packed(state, *round_ctr);
+            Self::sbox_layer_packed_field(state);
+            *state = Self::mds_layer_packed(state);
+            *round_ctr += 1;
+        }
+    }
+
+    /// Same as `partial_rounds` for `PackedField`.
+    fn partial_rounds_packed<P: PackedField<Scalar = Self>>(
+        state: &mut [P; SPONGE_WIDTH],
+        round_ctr: &mut usize,
+    ) {
+        for i in 0..SPONGE_WIDTH {
+            state[i] += Self::from_canonical_u64(Self::FAST_PARTIAL_FIRST_ROUND_CONSTANT[i]);
+        }
+        *state = Self::mds_partial_layer_init_packed(state);
+
+        for i in 0..N_PARTIAL_ROUNDS {
+            state[0] = Self::sbox_monomial_packed_field(state[0]);
+            state[0] += Self::from_canonical_u64(Self::FAST_PARTIAL_ROUND_CONSTANTS[i]);
+            *state = Self::mds_partial_layer_fast_packed(state, i);
+        }
+        *round_ctr += N_PARTIAL_ROUNDS;
+    }
+
+    /// Applies the Poseidon permutation to each lane of `input`.
+    fn poseidon_packed<P: PackedField<Scalar = Self>>(
+        input: [P; SPONGE_WIDTH],
+    ) -> [P; SPONGE_WIDTH] {
+        let mut state = input;
+        let mut round_ctr = 0;
+
+        Self::full_rounds_packed(&mut state, &mut round_ctr);
+        Self::partial_rounds_packed(&mut state, &mut round_ctr);
+        Self::full_rounds_packed(&mut state, &mut round_ctr);
+        debug_assert_eq!(round_ctr, N_ROUNDS);
+
+        state
+    }
+}
+
+impl<F: Poseidon> PoseidonPacked for F {}
+
+/// Compresses consecutive pairs of `inputs` as `compress` does, running `P::WIDTH` permutations at
+/// once.
+pub fn compress_packed<F, P>(inputs: &[HashOut<F>]) -> Vec<HashOut<F>>
+where
+    F: Poseidon,
+    P: PackedField<Scalar = F>,
+{
+    assert!(
+        inputs.len().is_multiple_of(2),
+        "Cannot compress an odd number of digests"
+    );
+    let mut outputs = Vec::with_capacity(inputs.len() / 2);
+    for chunk in inputs.chunks(2 * P::WIDTH) {
+        // Lane `j` holds the `j`-th pair of the chunk, followed by zeros, as in `compress`.
+        let mut state = [P::ZEROS; SPONGE_WIDTH];
+        for (j, pair) in chunk.chunks_exact(2).enumerate() {
+            for i in 0..NUM_HASH_OUT_ELTS {
+                state[i].as_slice_mut()[j] = pair[0].elements[i];
+                state[i + NUM_HASH_OUT_ELTS].as_slice_mut()[j] = pair[1].elements[i];
+            }
+        }
+
+        let state = F::poseidon_packed(state);
+        outputs.extend((0..chunk.len() / 2).map(|j| HashOut {
+            elements: core::array::from_fn(|i| state[i].as_slice()[j]),
+        }));
+    }
+    outputs
+}
+
 #[derive(Copy, Clone, Default, Debug, PartialEq)]
 pub struct PoseidonPermutation<T> {
     state: [T; SPONGE_WIDTH],
@@ -884,6 +1050,10 @@ impl<F: RichField> Hasher<F> for PoseidonHash {
     fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
         compress::<F, Self::Permutation>(left, right)
     }
+
+    fn two_to_one_batch(inputs: &[Self::Hash]) -> Vec<Self::Hash> {
+        compress_packed::<F, <F as Packable>::Packing>(inputs)
+    }
 }
 
 impl<F: RichField> AlgebraicHasher<F> for PoseidonHash {
@@ -917,6 +1087,113 @@ impl<F: RichField> AlgebraicHasher<F> for PoseidonHash {
             (0..SPONGE_WIDTH).map(|i| Target::wire(gate, PoseidonGate::<F, D>::wire_output(i))),
         )
     }
+
+    fn permute_absorb_permute<const D: usize>(
+        inputs: Self::AlgebraicPermutation,
+        block: &[Target],
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Self::AlgebraicPermutation
+    where
+        F: RichField + Extendable<D>,
+    {
+        // Fall back to two separate permutations if we don't have enough wires for
+        // PoseidonSpongeGate, or if the block only partially overwrites the rate.
+        if !PoseidonSpongeGate::<F, D>::is_supported(&builder.config) || block.len() != SPONGE_RATE
+        {
+            let mut state = builder.permute::<Self>(inputs);
+            state.set_from_slice(block, 0);
+            return builder.permute::<Self>(state);
+        }
+
+        let gate_type = PoseidonSpongeGate::<F, D>::new();
+        let gate = builder.add_gate(gate_type, vec![]);
+
+        // Route input and block wires.
+        let inputs = inputs.as_ref();
+        for i in 0..SPONGE_WIDTH {
+            let in_wire = PoseidonSpongeGate::<F, D>::wire_input(i);
+            builder.connect(inputs[i], Target::wire(gate, in_wire));
+        }
+        for (i, &b) in block.iter().enumerate() {
+            let block_wire = PoseidonSpongeGate::<F, D>::wire_block(i);
+            builder.connect(b, Target::wire(gate, block_wire));
+        }
+
+        // Collect output wires.
+        Self::AlgebraicPermutation::new(
+            (0..SPONGE_WIDTH)
+                .map(|i| Target::wire(gate, PoseidonSpongeGate::<F, D>::wire_output(i))),
+        )
+    }
+}
+
+/// Poseidon hash function with digests truncated to their first `N` elements, e.g. ~128-bit
+/// digests with `N = 2`. This shrinks Merkle caps and paths, and thus proofs, at the cost of
+/// collision resistance. The dropped elements are zero, so digests are still `HashOut`s, which
+/// can be verified recursively.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TruncatedPoseidonHash<const N: usize>;
+
+impl<const N: usize> TruncatedPoseidonHash<N> {
+    fn truncate<F: Field>(hash: HashOut<F>) -> HashOut<F> {
+        HashOut::from_partial(&hash.elements[..N])
+    }
+}
+
+impl<F: RichField, const N: usize> Hasher<F> for TruncatedPoseidonHash<N> {
+    const HASH_SIZE: usize = {
+        assert!(
+            N > 0 && N <= NUM_HASH_OUT_ELTS,
+            "Digests must have between 1 and 4 elements"
+        );
+        N * 8
+    };
+    type Hash = HashOut<F>;
+    type Permutation = PoseidonPermutation<F>;
+
+    fn hash_no_pad(input: &[F]) -> Self::Hash {
+        Self::truncate(hash_n_to_hash_no_pad::<F, Self::Permutation>(input))
+    }
+
+    fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
+        Self::truncate(compress::<F, Self::Permutation>(left, right))
+    }
+
+    fn hash_from_bytes(bytes: &[u8]) -> anyhow::Result<Self::Hash> {
+        let elements = decode_field_elements(bytes)?;
+        anyhow::ensure!(
+            elements.len() == N,
+            "Expected a hash of {N} elements, got {} elements",
+            elements.len()
+        );
+        Ok(HashOut::from_partial(&elements))
+    }
+}
+
+impl<F: RichField, const N: usize> AlgebraicHasher<F> for TruncatedPoseidonHash<N> {
+    type AlgebraicPermutation = PoseidonPermutation<Target>;
+
+    fn permute_swapped<const D: usize>(
+        inputs: Self::AlgebraicPermutation,
+        swap: BoolTarget,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Self::AlgebraicPermutation
+    where
+        F: RichField + Extendable<D>,
+    {
+        PoseidonHash::permute_swapped(inputs, swap, builder)
+    }
+
+    fn permute_absorb_permute<const D: usize>(
+        inputs: Self::AlgebraicPermutation,
+        block: &[Target],
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Self::AlgebraicPermutation
+    where
+        F: RichField + Extendable<D>,
+    {
+        PoseidonHash::permute_absorb_permute(inputs, block, builder)
+    }
 }
 
 #[cfg(test)]
@@ -955,4 +1232,37 @@ pub(crate) mod test_helpers {
             assert_eq!(output[i], output_naive[i]);
         }
     }
+
+    pub(crate) fn check_packed_consistency<F, P>()
+    where
+        F: Poseidon,
+        P: PackedField<Scalar = F>,
+    {
+        let inputs = (0..P::WIDTH)
+            .map(|_| F::rand_array::<SPONGE_WIDTH>())
+            .collect::<Vec<_>>();
+        let mut packed_input = [P::ZEROS; SPONGE_WIDTH];
+        for (j, input) in inputs.iter().enumerate() {
+            for i in 0..SPONGE_WIDTH {
+                packed_input[i].as_slice_mut()[j] = input[i];
+            }
+        }
+        let packed_output = F::poseidon_packed(packed_input);
+        for (j, &input) in inputs.iter().enumerate() {
+            let output = F::poseidon(input);
+            for i in 0..SPONGE_WIDTH {
+                assert_eq!(packed_output[i].as_slice()[j], output[i]);
+            }
+        }
+
+        // An odd number of pairs leaves some lanes of the last chunk unused.
+        let digests = (0..2 * (2 * P::WIDTH + 1))
+            .map(|_| HashOut::<F>::from_vec(F::rand_vec(NUM_HASH_OUT_ELTS)))
+            .collect::<Vec<_>>();
+        let expected = digests
+            .chunks_exact(2)
+            .map(|pair| compress::<F, PoseidonPermutation<F>>(pair[0], pair[1]))
+            .collect::<Vec<_>>();
+        assert_eq!(compress_packed::<F, P>(&digests), expected);
+    }
 }
@@ -448,8 +448,11 @@ mod tests {
     use alloc::{vec, vec::Vec};
 
     use crate::field::goldilocks_field::GoldilocksField as F;
+    use crate::field::packable::Packable;
     use crate::field::types::{Field, PrimeField64};
-    use crate::hash::poseidon::test_helpers::{check_consistency, check_test_vectors};
+    use crate::hash::poseidon::test_helpers::{
+        check_consistency, check_packed_consistency, check_test_vectors,
+    };
 
     #[test]
     fn test_vectors() {
@@ -493,4 +496,10 @@ mod tests {
     fn consistency() {
         check_consistency::<F>();
     }
+
+    #[test]
+    fn packed_consistency() {
+        check_packed_consistency::<F, F>();
+        check_packed_consistency::<F, <F as Packable>::Packing>();
+    }
 }
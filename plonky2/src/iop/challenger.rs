@@ -1,5 +1,55 @@
+//! Fiat-Shamir transcripts, both natively ([`Challenger`]) and in-circuit
+//! ([`RecursiveChallenger`]).
+//!
+//! Both challengers implement the same duplex sponge over `H`'s permutation, so a circuit which
+//! replays a prover's transcript with a [`RecursiveChallenger`] derives exactly the challenges
+//! that the prover derived natively with a [`Challenger`]. The transcript semantics are:
+//!
+//! - `observe_*` methods buffer prover messages. Observing anything discards challenges that were
+//!   squeezed but not yet consumed, since they would not reflect the new messages.
+//! - `get_*` methods first absorb all buffered messages, `RATE` elements at a time, overwriting
+//!   the rate portion of the sponge state and applying the permutation after each chunk (a
+//!   duplexing). Challenges are then taken from the rate portion of the state, last element
+//!   first; once those are exhausted, the permutation is applied again to squeeze more.
+//! - `compact` absorbs pending messages and discards pending challenges, returning the sponge
+//!   state. A challenger can be resumed from such a state with `from_state`.
+//!
+//! A [`Challenger`] can also record its transcript, labelled by phase, see
+//! [`Challenger::record_transcript`]. Comparing the transcripts of a prover and a verifier with
+//! [`Transcript::first_divergence`] points at the first message or challenge on which they
+//! disagree.
+//!
+//! An application circuit can use a [`RecursiveChallenger`] to implement its own interactive
+//! argument reductions, e.g. to derive a random linear combination of some of its targets:
+//!
+//! ```rust
+//! use plonky2::iop::challenger::RecursiveChallenger;
+//! use plonky2::plonk::circuit_builder::CircuitBuilder;
+//! use plonky2::plonk::circuit_data::CircuitConfig;
+//! use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+//!
+//! const D: usize = 2;
+//! type C = PoseidonGoldilocksConfig;
+//! type F = <C as GenericConfig<D>>::F;
+//! type H = <C as GenericConfig<D>>::Hasher;
+//!
+//! let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+//! let values = builder.add_virtual_targets(4);
+//!
+//! let mut challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+//! challenger.observe_elements(&values);
+//! let alpha = challenger.get_challenge(&mut builder);
+//!
+//! let combination = values
+//!     .iter()
+//!     .rev()
+//!     .fold(builder.zero(), |acc, &v| builder.mul_add(acc, alpha, v));
+//! builder.register_public_input(combination);
+//! ```
+
 #[cfg(not(feature = "std"))]
 use alloc::{vec, vec::Vec};
+use core::fmt;
 use core::marker::PhantomData;
 
 use crate::field::extension::{Extendable, FieldExtension};
@@ -17,6 +67,7 @@ pub struct Challenger<F: RichField, H: Hasher<F>> {
     pub(crate) sponge_state: H::Permutation,
     pub(crate) input_buffer: Vec<F>,
     output_buffer: Vec<F>,
+    transcript: Option<Transcript<F>>,
 }
 
 /// Observes prover messages, and generates verifier challenges based on the transcript.
@@ -33,6 +84,39 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             sponge_state: H::Permutation::new(core::iter::repeat(F::ZERO)),
             input_buffer: Vec::with_capacity(H::Permutation::RATE),
             output_buffer: Vec::with_capacity(H::Permutation::RATE),
+            transcript: None,
+        }
+    }
+
+    /// Starts recording all observed elements and derived challenges, discarding anything
+    /// recorded so far. The recording can be retrieved with [`Self::take_transcript`].
+    pub fn record_transcript(&mut self) {
+        self.transcript = Some(Transcript::default());
+    }
+
+    /// Stops recording, and returns the transcript recorded since [`Self::record_transcript`] was
+    /// called, if it was.
+    pub fn take_transcript(&mut self) -> Option<Transcript<F>> {
+        self.transcript.take()
+    }
+
+    /// Labels the events recorded from now on with `label`, until the next call. This does
+    /// nothing if the transcript isn't being recorded.
+    pub fn set_phase(&mut self, label: &'static str) {
+        if let Some(transcript) = &mut self.transcript {
+            transcript.phases.push(TranscriptPhase {
+                label,
+                events: Vec::new(),
+            });
+        }
+    }
+
+    fn record(&mut self, event: TranscriptEvent<F>) {
+        if let Some(transcript) = &mut self.transcript {
+            if transcript.phases.is_empty() {
+                transcript.phases.push(TranscriptPhase::default());
+            }
+            transcript.phases.last_mut().unwrap().events.push(event);
         }
     }
 
@@ -40,6 +124,7 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
         // Any buffered outputs are now invalid, since they wouldn't reflect this input.
         self.output_buffer.clear();
 
+        self.record(TranscriptEvent::Observed(element));
         self.input_buffer.push(element);
 
         if self.input_buffer.len() == H::Permutation::RATE {
@@ -86,9 +171,12 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
             self.duplexing();
         }
 
-        self.output_buffer
+        let challenge = self
+            .output_buffer
             .pop()
-            .expect("Output buffer should be non-empty")
+            .expect("Output buffer should be non-empty");
+        self.record(TranscriptEvent::Challenge(challenge));
+        challenge
     }
 
     pub fn get_n_challenges(&mut self, n: usize) -> Vec<F> {
@@ -158,10 +246,115 @@ impl<F: RichField, H: AlgebraicHasher<F>> Default for Challenger<F, H> {
     }
 }
 
+/// A Fiat-Shamir transcript recorded by a [`Challenger`], see
+/// [`Challenger::record_transcript`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Transcript<F> {
+    /// The phases of the transcript, in order.
+    pub phases: Vec<TranscriptPhase<F>>,
+}
+
+/// The events recorded by a [`Challenger`] between two calls to [`Challenger::set_phase`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TranscriptPhase<F> {
+    /// The label given to [`Challenger::set_phase`], or an empty label for the events recorded
+    /// before the first call.
+    pub label: &'static str,
+    pub events: Vec<TranscriptEvent<F>>,
+}
+
+/// An element of a [`Transcript`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TranscriptEvent<F> {
+    /// A prover message observed by the challenger.
+    Observed(F),
+    /// A challenge derived by the challenger.
+    Challenge(F),
+}
+
+/// The first point at which two transcripts differ, see [`Transcript::first_divergence`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TranscriptDivergence<F> {
+    /// The index of the first differing event, counting from the start of the transcripts.
+    pub position: usize,
+    /// The phase label and event at that position in each transcript, or `None` if the transcript
+    /// ends before it.
+    pub left: Option<(&'static str, TranscriptEvent<F>)>,
+    pub right: Option<(&'static str, TranscriptEvent<F>)>,
+}
+
+impl<F: Copy + PartialEq> Transcript<F> {
+    /// All events of the transcript, in order, along with the label of their phase.
+    pub fn events(&self) -> impl Iterator<Item = (&'static str, TranscriptEvent<F>)> + '_ {
+        self.phases
+            .iter()
+            .flat_map(|phase| phase.events.iter().map(|&event| (phase.label, event)))
+    }
+
+    /// Finds the first event, or phase label, on which `self` and `other` disagree. Returns `None`
+    /// if the transcripts are identical up to their phase boundaries.
+    pub fn first_divergence(&self, other: &Self) -> Option<TranscriptDivergence<F>> {
+        let mut left = self.events();
+        let mut right = other.events();
+        let mut position = 0;
+        loop {
+            match (left.next(), right.next()) {
+                (None, None) => return None,
+                (l, r) if l == r => position += 1,
+                (left, right) => {
+                    return Some(TranscriptDivergence {
+                        position,
+                        left,
+                        right,
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl<F: fmt::Display> fmt::Display for TranscriptEvent<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Observed(x) => write!(f, "observed {x}"),
+            Self::Challenge(x) => write!(f, "challenge {x}"),
+        }
+    }
+}
+
+impl<F: fmt::Display> fmt::Display for Transcript<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for phase in &self.phases {
+            writeln!(f, "[{}]", phase.label)?;
+            for event in &phase.events {
+                writeln!(f, "  {event}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F: fmt::Display> fmt::Display for TranscriptDivergence<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side =
+            |f: &mut fmt::Formatter<'_>, event: &Option<(&str, TranscriptEvent<F>)>| match event {
+                Some((label, event)) => write!(f, "{event} in phase `{label}`"),
+                None => write!(f, "end of transcript"),
+            };
+        write!(f, "transcripts diverge at event {}: ", self.position)?;
+        side(f, &self.left)?;
+        write!(f, " vs ")?;
+        side(f, &self.right)
+    }
+}
+
 /// A recursive version of `Challenger`. The main difference is that `RecursiveChallenger`'s input
 /// buffer can grow beyond `H::Permutation::RATE`. This is so that `observe_element` etc do not need access
 /// to the `CircuitBuilder`.
-#[derive(Debug)]
+///
+/// Given the same observations, it produces the same challenges as `Challenger`; see the
+/// [module documentation](self) for the transcript semantics.
+#[derive(Clone, Debug)]
 pub struct RecursiveChallenger<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
 {
     sponge_state: H::AlgebraicPermutation,
@@ -173,6 +366,7 @@ pub struct RecursiveChallenger<F: RichField + Extendable<D>, H: AlgebraicHasher<
 impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
     RecursiveChallenger<F, H, D>
 {
+    /// Creates a challenger with an all-zero sponge state and an empty transcript.
     pub fn new(builder: &mut CircuitBuilder<F, D>) -> Self {
         let zero = builder.zero();
         Self {
@@ -183,6 +377,7 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         }
     }
 
+    /// Resumes a transcript from a sponge state, such as one returned by `compact`.
     pub fn from_state(sponge_state: H::AlgebraicPermutation) -> Self {
         Self {
             sponge_state,
@@ -192,6 +387,8 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         }
     }
 
+    /// Appends `target` to the transcript. This adds no gates; the input is only absorbed when
+    /// the next challenge is requested.
     pub fn observe_element(&mut self, target: Target) {
         // Any buffered outputs are now invalid, since they wouldn't reflect this input.
         self.output_buffer.clear();
@@ -199,32 +396,38 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         self.input_buffer.push(target);
     }
 
+    /// Appends each of `targets` to the transcript, in order.
     pub fn observe_elements(&mut self, targets: &[Target]) {
         for &target in targets {
             self.observe_element(target);
         }
     }
 
+    /// Appends the elements of `hash` to the transcript.
     pub fn observe_hash(&mut self, hash: &HashOutTarget) {
         self.observe_elements(&hash.elements)
     }
 
+    /// Appends each hash of `cap` to the transcript.
     pub fn observe_cap(&mut self, cap: &MerkleCapTarget) {
         for hash in &cap.0 {
             self.observe_hash(hash)
         }
     }
 
+    /// Appends the `D` base field limbs of `element` to the transcript.
     pub fn observe_extension_element(&mut self, element: ExtensionTarget<D>) {
         self.observe_elements(&element.0);
     }
 
+    /// Appends the limbs of each of `elements` to the transcript, in order.
     pub fn observe_extension_elements(&mut self, elements: &[ExtensionTarget<D>]) {
         for &element in elements {
             self.observe_extension_element(element);
         }
     }
 
+    /// Derives a challenge from the transcript so far, absorbing any pending inputs first.
     pub fn get_challenge(&mut self, builder: &mut CircuitBuilder<F, D>) -> Target {
         self.absorb_buffered_inputs(builder);
 
@@ -239,6 +442,7 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
             .expect("Output buffer should be non-empty")
     }
 
+    /// Derives `n` challenges, as if by calling `get_challenge` `n` times.
     pub fn get_n_challenges(
         &mut self,
         builder: &mut CircuitBuilder<F, D>,
@@ -247,6 +451,7 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         (0..n).map(|_| self.get_challenge(builder)).collect()
     }
 
+    /// Derives four challenges and returns them as a hash.
     pub fn get_hash(&mut self, builder: &mut CircuitBuilder<F, D>) -> HashOutTarget {
         HashOutTarget {
             elements: [
@@ -258,6 +463,7 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         }
     }
 
+    /// Derives `D` challenges and returns them as the limbs of an extension field element.
     pub fn get_extension_challenge(
         &mut self,
         builder: &mut CircuitBuilder<F, D>,
@@ -265,6 +471,18 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         self.get_n_challenges(builder, D).try_into().unwrap()
     }
 
+    /// Derives `n` extension field challenges, as if by calling `get_extension_challenge` `n`
+    /// times.
+    pub fn get_n_extension_challenges(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        n: usize,
+    ) -> Vec<ExtensionTarget<D>> {
+        (0..n)
+            .map(|_| self.get_extension_challenge(builder))
+            .collect()
+    }
+
     /// Absorb any buffered inputs. After calling this, the input buffer will be empty, and the
     /// output buffer will be full.
     fn absorb_buffered_inputs(&mut self, builder: &mut CircuitBuilder<F, D>) {
@@ -285,6 +503,8 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         self.input_buffer.clear();
     }
 
+    /// Absorbs any pending inputs and discards any pending challenges, then returns the sponge
+    /// state. The transcript can be resumed from this state with `from_state`.
     pub fn compact(&mut self, builder: &mut CircuitBuilder<F, D>) -> H::AlgebraicPermutation {
         self.absorb_buffered_inputs(builder);
         self.output_buffer.clear();
@@ -297,8 +517,8 @@ mod tests {
     #[cfg(not(feature = "std"))]
     use alloc::vec::Vec;
 
-    use crate::field::types::Sample;
-    use crate::iop::challenger::{Challenger, RecursiveChallenger};
+    use crate::field::types::{Field, Sample};
+    use crate::iop::challenger::{Challenger, RecursiveChallenger, TranscriptEvent};
     use crate::iop::generator::generate_partial_witness;
     use crate::iop::target::Target;
     use crate::iop::witness::{PartialWitness, Witness};
@@ -374,4 +594,114 @@ mod tests {
 
         assert_eq!(outputs_per_round, recursive_output_values_per_round);
     }
+
+    #[test]
+    fn test_transcript_divergence() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+
+        let inputs = F::rand_vec(10);
+        let run = |inputs: &[F]| {
+            let mut challenger = Challenger::<F, H>::new();
+            challenger.record_transcript();
+            challenger.set_phase("first");
+            challenger.observe_elements(&inputs[..5]);
+            challenger.get_n_challenges(2);
+            challenger.set_phase("second");
+            challenger.observe_elements(&inputs[5..]);
+            challenger.get_challenge();
+            challenger.take_transcript().unwrap()
+        };
+        let transcript = run(&inputs);
+        assert_eq!(transcript.phases.len(), 2);
+        assert_eq!(transcript.events().count(), 13);
+        assert_eq!(transcript.first_divergence(&run(&inputs)), None);
+
+        let mut other_inputs = inputs.clone();
+        other_inputs[7] += F::ONE;
+        let divergence = transcript.first_divergence(&run(&other_inputs)).unwrap();
+        assert_eq!(divergence.position, 9);
+        assert_eq!(
+            divergence.left,
+            Some(("second", TranscriptEvent::Observed(inputs[7])))
+        );
+        assert_eq!(
+            divergence.right,
+            Some(("second", TranscriptEvent::Observed(other_inputs[7])))
+        );
+
+        // A transcript which stops early diverges where it ends.
+        let mut truncated = transcript.clone();
+        truncated.phases.pop();
+        let divergence = transcript.first_divergence(&truncated).unwrap();
+        assert_eq!(divergence.position, 7);
+        assert_eq!(divergence.right, None);
+
+        // Nothing is recorded unless asked for.
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_elements(&inputs);
+        challenger.get_challenge();
+        assert_eq!(challenger.take_transcript(), None);
+    }
+
+    /// Tests that extension challenges and resumed transcripts agree between `Challenger` and
+    /// `RecursiveChallenger`.
+    #[test]
+    fn test_extension_and_resume_consistency() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+
+        let base_inputs = F::rand_vec(11);
+        let ext_inputs = FF::rand_vec(3);
+
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_elements(&base_inputs);
+        challenger.observe_extension_elements::<D>(&ext_inputs);
+        let challenges = challenger.get_n_extension_challenges::<D>(5);
+        let state = challenger.compact();
+        let mut resumed = Challenger::<F, H> {
+            sponge_state: state,
+            input_buffer: Vec::new(),
+            output_buffer: Vec::new(),
+            transcript: None,
+        };
+        resumed.observe_element(base_inputs[0]);
+        let resumed_challenges = resumed.get_n_challenges(3);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let base_targets = builder.constants(&base_inputs);
+        let ext_targets = ext_inputs
+            .iter()
+            .map(|&x| builder.constant_extension(x))
+            .collect::<Vec<_>>();
+        let mut recursive_challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        recursive_challenger.observe_elements(&base_targets);
+        recursive_challenger.observe_extension_elements(&ext_targets);
+        let recursive_challenges = recursive_challenger.get_n_extension_challenges(&mut builder, 5);
+        let state = recursive_challenger.compact(&mut builder);
+        let mut recursive_resumed = RecursiveChallenger::<F, H, D>::from_state(state);
+        recursive_resumed.observe_element(base_targets[0]);
+        let recursive_resumed_challenges = recursive_resumed.get_n_challenges(&mut builder, 3);
+
+        let circuit = builder.build::<C>();
+        let witness =
+            generate_partial_witness(PartialWitness::new(), &circuit.prover_only, &circuit.common)
+                .unwrap();
+        let recursive_challenges = recursive_challenges
+            .iter()
+            .map(|&t| witness.get_extension_target(t))
+            .collect::<Vec<_>>();
+
+        assert_eq!(challenges, recursive_challenges);
+        assert_eq!(
+            resumed_challenges,
+            witness.get_targets(&recursive_resumed_challenges)
+        );
+    }
 }
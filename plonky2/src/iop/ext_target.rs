@@ -14,6 +14,14 @@ use crate::plonk::circuit_builder::CircuitBuilder;
 /// This is typically used in recursion settings, where the outer circuit must verify
 /// a proof satisfying an inner circuit's statement, which is verified using arithmetic
 /// in an extension of the base field.
+///
+/// Circuits computing over the extension, e.g. to get ~128-bit field semantics from the
+/// quadratic extension of Goldilocks, use extension targets as their values. Wires can't hold
+/// extension field elements: the wire field is always the base field `F`, and no
+/// [`CircuitConfig`](crate::plonk::circuit_data::CircuitConfig) option changes it. Each
+/// extension value thus takes `D` wires, but each extension operation is still a single gate
+/// operation (see `ArithmeticExtensionGate`), and extension values can be witnessed, made public
+/// and read back from proofs directly.
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct ExtensionTarget<const D: usize>(pub [Target; D]);
 
@@ -129,6 +137,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.constant_ext_algebra(ExtensionAlgebra::ZERO)
     }
 
+    /// Registers the `D` components of the given extension target as public inputs.
+    pub fn register_public_input_extension(&mut self, target: ExtensionTarget<D>) {
+        self.register_public_inputs(&target.to_target_array());
+    }
+
+    /// Registers the given extension targets as public inputs.
+    pub fn register_public_inputs_extension(&mut self, targets: &[ExtensionTarget<D>]) {
+        targets
+            .iter()
+            .for_each(|&t| self.register_public_input_extension(t));
+    }
+
+    pub fn add_virtual_public_input_extension(&mut self) -> ExtensionTarget<D> {
+        let t = self.add_virtual_extension_target();
+        self.register_public_input_extension(t);
+        t
+    }
+
     pub fn convert_to_ext(&mut self, t: Target) -> ExtensionTarget<D> {
         let zero = self.zero();
         t.to_ext_target(zero)
@@ -9,6 +9,7 @@ use core::fmt::Debug;
 use core::marker::PhantomData;
 
 use anyhow::{anyhow, Result};
+use hashbrown::{HashMap, HashSet};
 
 use crate::field::extension::Extendable;
 use crate::field::types::Field;
@@ -16,9 +17,12 @@ use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::iop::wire::Wire;
-use crate::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
+use crate::iop::witness::{
+    ConflictingAssignment, PartialWitness, PartitionWitness, Witness, WitnessWrite,
+};
 use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
 use crate::plonk::config::GenericConfig;
+use crate::util::blinding::blinding_rand;
 use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 /// Given a `PartitionWitness` that has only inputs set, populates the rest of the witness using the
@@ -34,8 +38,7 @@ pub fn generate_partial_witness<
     common_data: &'a CommonCircuitData<F, D>,
 ) -> Result<PartitionWitness<'a, F>> {
     let config = &common_data.config;
-    let generators = &prover_data.generators;
-    let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
+    let num_generators = prover_data.generators.len();
 
     let mut witness = PartitionWitness::new(
         config.num_wires,
@@ -47,13 +50,33 @@ pub fn generate_partial_witness<
         witness.set_target(t, v)?;
     }
 
-    // Build a list of "pending" generators which are queued to be run. Initially, all generators
-    // are queued.
-    let mut pending_generator_indices: Vec<_> = (0..generators.len()).collect();
+    // Initially, all generators are queued.
+    run_generators(
+        &mut witness,
+        prover_data,
+        (0..num_generators).collect(),
+        &mut vec![false; num_generators],
+        None,
+    )?;
+
+    Ok(witness)
+}
+
+/// Runs the generators in `pending_generator_indices`, and those watching the targets they
+/// populate, until no progress can be made. Generators flagged in `generator_is_expired` are never
+/// run, and an error is returned if any other generator didn't finish. If `generator_outputs` is
+/// given, the representatives of the targets written by each generator are recorded in it.
+fn run_generators<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    witness: &mut PartitionWitness<F>,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    mut pending_generator_indices: Vec<usize>,
+    generator_is_expired: &mut [bool],
+    mut generator_outputs: Option<&mut [Vec<usize>]>,
+) -> Result<()> {
+    let generators = &prover_data.generators;
+    let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
 
-    // We also track a list of "expired" generators which have already returned false.
-    let mut generator_is_expired = vec![false; generators.len()];
-    let mut remaining_generators = generators.len();
+    let mut remaining_generators = generator_is_expired.iter().filter(|&&e| !e).count();
 
     let mut buffer = GeneratedValues::empty();
 
@@ -66,7 +89,7 @@ pub fn generate_partial_witness<
                 continue;
             }
 
-            let finished = generators[generator_idx].0.run(&witness, &mut buffer);
+            let finished = generators[generator_idx].0.run(witness, &mut buffer);
             if finished {
                 generator_is_expired[generator_idx] = true;
                 remaining_generators -= 1;
@@ -76,6 +99,10 @@ pub fn generate_partial_witness<
             // targets' representatives.
             let mut new_target_reps = Vec::with_capacity(buffer.target_values.len());
             for (t, v) in buffer.target_values.drain(..) {
+                if let Some(outputs) = generator_outputs.as_deref_mut() {
+                    outputs[generator_idx]
+                        .push(witness.representative_map[witness.target_index(t)]);
+                }
                 let reps = witness.set_target_returning_rep(t, v)?;
                 new_target_reps.extend(reps);
             }
@@ -100,7 +127,161 @@ pub fn generate_partial_witness<
         return Err(anyhow!("{} generators weren't run", remaining_generators));
     }
 
-    Ok(witness)
+    Ok(())
+}
+
+/// A witness generated once, which can then be updated for new input values by rerunning only the
+/// generators downstream of the inputs which changed. This speeds up proving many statements which
+/// only differ in a few inputs.
+#[derive(Debug)]
+pub struct IncrementalWitness<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    witness: PartitionWitness<'a, F>,
+    /// The representatives of the targets written by each generator.
+    generator_outputs: Vec<Vec<usize>>,
+    /// Generators without dependencies, such as those setting constants or drawing blinding
+    /// values.
+    source_generator_indices: Vec<usize>,
+}
+
+impl<'a, F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    IncrementalWitness<'a, F, C, D>
+{
+    /// Generates the witness for `inputs`, recording which targets each generator writes.
+    pub fn new(
+        inputs: PartialWitness<F>,
+        prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+        common_data: &'a CommonCircuitData<F, D>,
+    ) -> Result<Self> {
+        let num_generators = prover_data.generators.len();
+        let mut witness = PartitionWitness::new(
+            common_data.config.num_wires,
+            common_data.degree(),
+            &prover_data.representative_map,
+        );
+        for (t, v) in inputs.target_values.into_iter() {
+            witness.set_target(t, v)?;
+        }
+
+        let mut generator_outputs = vec![Vec::new(); num_generators];
+        run_generators(
+            &mut witness,
+            prover_data,
+            (0..num_generators).collect(),
+            &mut vec![false; num_generators],
+            Some(&mut generator_outputs),
+        )?;
+
+        let source_generator_indices = (0..num_generators)
+            .filter(|&i| prover_data.generators[i].0.watch_list().is_empty())
+            .collect();
+
+        Ok(Self {
+            prover_data,
+            witness,
+            generator_outputs,
+            source_generator_indices,
+        })
+    }
+
+    /// The current witness.
+    pub const fn witness(&self) -> &PartitionWitness<'a, F> {
+        &self.witness
+    }
+
+    /// Sets new values for some inputs and regenerates the targets depending on them, returning
+    /// the number of generators which were rerun. Inputs which aren't given keep their value.
+    ///
+    /// Generators without dependencies are rerun on every update, so that blinding values are
+    /// fresh for each proof, but only the targets downstream of values which actually changed are
+    /// regenerated. If the update fails, the witness should be generated from scratch.
+    pub fn update(&mut self, inputs: PartialWitness<F>) -> Result<usize> {
+        let generators = &self.prover_data.generators;
+        let num_generators = generators.len();
+
+        let mut buffer = GeneratedValues::empty();
+        for &i in &self.source_generator_indices {
+            generators[i].0.run(&self.witness, &mut buffer);
+        }
+
+        // The new value of each representative, along with the target it was assigned through.
+        let mut new_values: HashMap<usize, (Target, F)> = HashMap::new();
+        for (target, value) in inputs.target_values.into_iter().chain(buffer.target_values) {
+            let rep = self.witness.representative_map[self.witness.target_index(target)];
+            if let Some(&(_, old_value)) = new_values.get(&rep) {
+                if old_value != value {
+                    return Err(anyhow::Error::msg(ConflictingAssignment {
+                        target,
+                        old_value,
+                        new_value: value,
+                    }));
+                }
+            }
+            new_values.insert(rep, (target, value));
+        }
+        new_values.retain(|&rep, &mut (_, value)| self.witness.values[rep] != Some(value));
+
+        let mut generators_by_output: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, outputs) in self.generator_outputs.iter().enumerate() {
+            for &rep in outputs {
+                generators_by_output.entry(rep).or_default().push(i);
+            }
+        }
+
+        // Every generator watching or writing a changed representative must be rerun, and all the
+        // representatives they wrote are changed in turn. Source generators already ran above.
+        let mut is_source = vec![false; num_generators];
+        for &i in &self.source_generator_indices {
+            is_source[i] = true;
+        }
+        let mut affected = vec![false; num_generators];
+        let mut changed_reps: HashSet<usize> = new_values.keys().copied().collect();
+        let mut queue: Vec<usize> = changed_reps.iter().copied().collect();
+        while let Some(rep) = queue.pop() {
+            let watchers = self.prover_data.generator_indices_by_watches.get(&rep);
+            let writers = generators_by_output.get(&rep);
+            for &i in watchers.into_iter().chain(writers).flatten() {
+                if is_source[i] || affected[i] {
+                    continue;
+                }
+                affected[i] = true;
+                for &output in &self.generator_outputs[i] {
+                    if changed_reps.insert(output) {
+                        queue.push(output);
+                    }
+                }
+            }
+        }
+
+        for &rep in &changed_reps {
+            self.witness.values[rep] = None;
+        }
+        for (rep, (_, value)) in new_values {
+            self.witness.values[rep] = Some(value);
+        }
+
+        let pending_generator_indices: Vec<usize> =
+            (0..num_generators).filter(|&i| affected[i]).collect();
+        for &i in &pending_generator_indices {
+            self.generator_outputs[i].clear();
+        }
+        let num_rerun = pending_generator_indices.len();
+        let mut generator_is_expired: Vec<bool> = affected.iter().map(|&a| !a).collect();
+        run_generators(
+            &mut self.witness,
+            self.prover_data,
+            pending_generator_indices,
+            &mut generator_is_expired,
+            Some(&mut self.generator_outputs),
+        )?;
+
+        Ok(num_rerun)
+    }
 }
 
 /// A generator participates in the generation of the witness.
@@ -331,7 +512,7 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for Ran
         _witness: &PartitionWitness<F>,
         out_buffer: &mut GeneratedValues<F>,
     ) -> Result<()> {
-        let random_value = F::rand();
+        let random_value = blinding_rand();
         out_buffer.set_target(self.target, random_value)
     }
 
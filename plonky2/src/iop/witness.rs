@@ -1,5 +1,6 @@
 #[cfg(not(feature = "std"))]
 use alloc::{vec, vec::Vec};
+use core::fmt;
 use core::iter::zip;
 
 use anyhow::{anyhow, Result};
@@ -18,6 +19,7 @@ use crate::iop::wire::Wire;
 use crate::plonk::circuit_data::{VerifierCircuitTarget, VerifierOnlyCircuitData};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
 use crate::plonk::proof::{Proof, ProofTarget, ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use crate::util::matrix::ColMajorMatrix;
 
 pub trait WitnessWrite<F: Field> {
     fn set_target(&mut self, target: Target, value: F) -> Result<()>;
@@ -279,14 +281,15 @@ pub trait Witness<F: Field>: WitnessWrite<F> {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct MatrixWitness<F: Field> {
-    pub(crate) wire_values: Vec<Vec<F>>,
+    /// The values of the wires, with one column per wire and one row per gate.
+    pub(crate) wire_values: ColMajorMatrix<F>,
 }
 
 impl<F: Field> MatrixWitness<F> {
     pub fn get_wire(&self, gate: usize, input: usize) -> F {
-        self.wire_values[input][gate]
+        self.wire_values.get(gate, input)
     }
 }
 
@@ -327,6 +330,29 @@ impl<F: Field> Witness<F> for PartialWitness<F> {
     }
 }
 
+/// The error returned by [`PartitionWitness`] when a target is assigned a value differing from
+/// the one already held by its partition, i.e. when the witness breaks a copy constraint. It can be
+/// recovered with `anyhow::Error::downcast_ref`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ConflictingAssignment<F: Field> {
+    /// The target whose assignment conflicted.
+    pub target: Target,
+    /// The value previously assigned to the target's partition.
+    pub old_value: F,
+    /// The value the target was being assigned.
+    pub new_value: F,
+}
+
+impl<F: Field> fmt::Display for ConflictingAssignment<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Partition containing {:?} was set twice with different values: {} != {}",
+            self.target, self.old_value, self.new_value
+        )
+    }
+}
+
 /// `PartitionWitness` holds a disjoint-set forest of the targets respecting a circuit's copy constraints.
 /// The value of a target is defined to be the value of its root in the forest.
 #[derive(Clone, Debug)]
@@ -354,12 +380,11 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
         let rep_value = &mut self.values[rep_index];
         if let Some(old_value) = *rep_value {
             if value != old_value {
-                return Err(anyhow!(
-                    "Partition containing {:?} was set twice with different values: {} != {}",
+                return Err(anyhow::Error::msg(ConflictingAssignment {
                     target,
                     old_value,
-                    value
-                ));
+                    new_value: value,
+                }));
             }
 
             Ok(None)
@@ -374,15 +399,12 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
     }
 
     pub fn full_witness(self) -> MatrixWitness<F> {
-        let mut wire_values = vec![vec![F::ZERO; self.degree]; self.num_wires];
-        for i in 0..self.degree {
-            for j in 0..self.num_wires {
-                let t = Target::Wire(Wire { row: i, column: j });
-                if let Some(x) = self.try_get_target(t) {
-                    wire_values[j][i] = x;
-                }
-            }
-        }
+        // Each wire column is filled contiguously, as that is the layout in which the wire
+        // polynomials are low-degree extended, rather than scattering rows across all columns.
+        let wire_values = ColMajorMatrix::from_fn(self.num_wires, self.degree, |row, column| {
+            self.try_get_target(Target::Wire(Wire { row, column }))
+                .unwrap_or(F::ZERO)
+        });
 
         MatrixWitness { wire_values }
     }
@@ -12,7 +12,9 @@ pub extern crate alloc;
 pub use plonky2_field as field;
 
 pub mod batch_fri;
+pub mod circuits;
 pub mod fri;
+pub mod frontend;
 pub mod gadgets;
 pub mod gates;
 pub mod hash;
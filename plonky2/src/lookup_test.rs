@@ -92,9 +92,9 @@ fn test_one_lookup() -> anyhow::Result<()> {
     let out_a = table[look_val_a].1;
     let out_b = table[look_val_b].1;
     let table_index = builder.add_lookup_table_from_pairs(table);
-    let output_a = builder.add_lookup_from_index(initial_a, table_index);
+    let output_a = builder.lookup(table_index, initial_a);
 
-    let output_b = builder.add_lookup_from_index(initial_b, table_index);
+    let output_b = builder.lookup(table_index, initial_b);
 
     builder.register_public_input(initial_a);
     builder.register_public_input(initial_b);
@@ -150,9 +150,9 @@ fn test_two_luts() -> anyhow::Result<()> {
     let other_table = OTHER_TABLE.to_vec();
 
     let table_index = builder.add_lookup_table_from_pairs(table);
-    let output_a = builder.add_lookup_from_index(initial_a, table_index);
+    let output_a = builder.lookup(table_index, initial_a);
 
-    let output_b = builder.add_lookup_from_index(initial_b, table_index);
+    let output_b = builder.lookup(table_index, initial_b);
     let sum = builder.add(output_a, output_b);
 
     let s = first_out + second_out;
@@ -161,7 +161,7 @@ fn test_two_luts() -> anyhow::Result<()> {
     let table2: LookupTable = Arc::new((0..256).zip_eq(other_table).collect());
     let table2_index = builder.add_lookup_table_from_pairs(table2);
 
-    let output_final = builder.add_lookup_from_index(sum, table2_index);
+    let output_final = builder.lookup(table2_index, sum);
 
     builder.register_public_input(initial_a);
     builder.register_public_input(initial_b);
@@ -222,13 +222,13 @@ fn test_different_inputs() -> anyhow::Result<()> {
 
     let table2: LookupTable = Arc::new((0..256).zip_eq(other_table).collect());
     let small_index = builder.add_lookup_table_from_pairs(table.clone());
-    let output_a = builder.add_lookup_from_index(initial_a, small_index);
+    let output_a = builder.lookup(small_index, initial_a);
 
-    let output_b = builder.add_lookup_from_index(initial_b, small_index);
+    let output_b = builder.lookup(small_index, initial_b);
     let sum = builder.add(output_a, output_b);
 
     let other_index = builder.add_lookup_table_from_pairs(table2.clone());
-    let output_final = builder.add_lookup_from_index(sum, other_index);
+    let output_final = builder.lookup(other_index, sum);
 
     builder.register_public_input(initial_a);
     builder.register_public_input(initial_b);
@@ -299,13 +299,13 @@ fn test_many_lookups() -> anyhow::Result<()> {
     let out_b = table[look_val_b].1;
 
     let tip5_index = builder.add_lookup_table_from_pairs(table);
-    let output_a = builder.add_lookup_from_index(initial_a, tip5_index);
+    let output_a = builder.lookup(tip5_index, initial_a);
 
-    let output_b = builder.add_lookup_from_index(initial_b, tip5_index);
+    let output_b = builder.lookup(tip5_index, initial_b);
     let sum = builder.add(output_a, output_b);
 
     for _ in 0..514 {
-        builder.add_lookup_from_index(initial_a, tip5_index);
+        builder.lookup(tip5_index, initial_a);
     }
 
     let other_table = OTHER_TABLE.to_vec();
@@ -316,7 +316,7 @@ fn test_many_lookups() -> anyhow::Result<()> {
     let out_final = table2[s as usize].1;
 
     let other_index = builder.add_lookup_table_from_pairs(table2);
-    let output_final = builder.add_lookup_from_index(sum, other_index);
+    let output_final = builder.lookup(other_index, sum);
 
     builder.register_public_input(initial_a);
     builder.register_public_input(initial_b);
@@ -378,14 +378,14 @@ fn test_same_luts() -> anyhow::Result<()> {
     let table: LookupTable = Arc::new((0..256).zip_eq(tip5_table).collect());
 
     let table_index = builder.add_lookup_table_from_pairs(table.clone());
-    let output_a = builder.add_lookup_from_index(initial_a, table_index);
+    let output_a = builder.lookup(table_index, initial_a);
 
-    let output_b = builder.add_lookup_from_index(initial_b, table_index);
+    let output_b = builder.lookup(table_index, initial_b);
     let sum = builder.add(output_a, output_b);
 
     let table2_index = builder.add_lookup_table_from_pairs(table);
 
-    let output_final = builder.add_lookup_from_index(sum, table2_index);
+    let output_final = builder.lookup(table2_index, sum);
 
     builder.register_public_input(initial_a);
     builder.register_public_input(initial_b);
@@ -433,8 +433,8 @@ fn test_big_lut() -> anyhow::Result<()> {
     let look_val_a = 51;
     let look_val_b = 2;
 
-    let output_a = builder.add_lookup_from_index(initial_a, lut_index);
-    let output_b = builder.add_lookup_from_index(initial_b, lut_index);
+    let output_a = builder.lookup(lut_index, initial_a);
+    let output_b = builder.lookup(lut_index, initial_b);
 
     builder.register_public_input(output_a);
     builder.register_public_input(output_b);
@@ -473,7 +473,7 @@ fn test_many_lookups_on_big_lut() -> anyhow::Result<()> {
     let inputs = (0..LUT_SIZE)
         .map(|_| {
             let input_target = builder.add_virtual_target();
-            _ = builder.add_lookup_from_index(input_target, lut_index);
+            _ = builder.lookup(lut_index, input_target);
             input_target
         })
         .collect::<Vec<_>>();
@@ -484,8 +484,8 @@ fn test_many_lookups_on_big_lut() -> anyhow::Result<()> {
     let look_val_a = 51;
     let look_val_b = 2;
 
-    let output_a = builder.add_lookup_from_index(initial_a, lut_index);
-    let output_b = builder.add_lookup_from_index(initial_b, lut_index);
+    let output_a = builder.lookup(lut_index, initial_a);
+    let output_b = builder.lookup(lut_index, initial_b);
     let sum = builder.add(output_a, output_b);
 
     builder.register_public_input(sum);
@@ -509,6 +509,48 @@ fn test_many_lookups_on_big_lut() -> anyhow::Result<()> {
     data.verify(proof)
 }
 
+#[test]
+fn test_lookup_table_handles() -> anyhow::Result<()> {
+    init_logger();
+
+    let config = CircuitConfig::standard_recursion_config();
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+
+    // Two gadgets registering the same table, once from a closure and once from pairs with a
+    // repeated row, share it.
+    let offset = 3;
+    let inputs = (0..16).collect::<Vec<u16>>();
+    let table = builder.add_lookup_table_from_fn(|i| i * i + offset, &inputs);
+    let pairs = inputs
+        .iter()
+        .chain(&[5])
+        .map(|&i| (i, i * i + offset))
+        .collect::<Vec<_>>();
+    assert_eq!(builder.add_lookup_table_from_pairs(Arc::new(pairs)), table);
+    assert_eq!(builder.num_luts(), 1);
+    assert_eq!(builder.get_lut(table.index()).len(), inputs.len());
+
+    // Constants are deduplicated too.
+    let constants = builder.constants(&[F::ONE, F::TWO, F::ONE]);
+    assert_eq!(constants[0], constants[2]);
+    assert_ne!(constants[0], constants[1]);
+
+    let looking_in = builder.add_virtual_targets(2);
+    let outputs = builder.lookups(table, &looking_in);
+    builder.register_public_inputs(&outputs);
+    let data = builder.build::<C>();
+
+    let mut pw = PartialWitness::new();
+    pw.set_target(looking_in[0], F::from_canonical_u16(4))?;
+    pw.set_target(looking_in[1], F::from_canonical_u16(15))?;
+    let proof = data.prove(pw)?;
+    assert_eq!(
+        proof.public_inputs,
+        vec![F::from_canonical_u16(19), F::from_canonical_u16(228)]
+    );
+    data.verify(proof)
+}
+
 fn init_logger() {
     #[cfg(feature = "std")]
     {
@@ -1,23 +1,21 @@
 //! Logic for building plonky2 circuits.
 
 #[cfg(not(feature = "std"))]
-use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
-use core::cmp::max;
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec, vec::Vec};
+use core::marker::PhantomData;
 #[cfg(feature = "std")]
 use std::{collections::BTreeMap, sync::Arc};
 
 use hashbrown::{HashMap, HashSet};
 use itertools::Itertools;
-use log::{debug, info, warn, Level};
+use log::{debug, info, warn};
 #[cfg(feature = "timing")]
 use web_time::Instant;
 
 use crate::field::cosets::get_unique_coset_shifts;
 use crate::field::extension::{Extendable, FieldExtension};
-use crate::field::fft::fft_root_table;
 use crate::field::polynomial::PolynomialValues;
 use crate::field::types::Field;
-use crate::fri::oracle::PolynomialBatch;
 use crate::fri::{FriConfig, FriParams};
 use crate::gadgets::arithmetic::BaseArithmeticOperation;
 use crate::gadgets::arithmetic_extension::ExtensionArithmeticOperation;
@@ -41,18 +39,17 @@ use crate::iop::generator::{
 use crate::iop::target::{BoolTarget, Target};
 use crate::iop::wire::Wire;
 use crate::plonk::circuit_data::{
-    CircuitConfig, CircuitData, CommonCircuitData, MockCircuitData, ProverCircuitData,
-    ProverOnlyCircuitData, VerifierCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
+    CircuitConfig, CircuitData, CircuitLayout, CommonCircuitData, MockCircuitData,
+    ProverCircuitData, VerifierCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
 };
-use crate::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
 use crate::plonk::copy_constraint::CopyConstraint;
+use crate::plonk::custom_argument::{CustomArgument, CustomArgumentRef};
 use crate::plonk::permutation_argument::Forest;
 use crate::plonk::plonk_common::PlonkOracle;
-use crate::timed;
-use crate::util::context_tree::ContextTree;
+use crate::util::context_tree::{ContextCounts, ContextProfile, ContextTree};
 use crate::util::partial_products::num_partial_products;
-use crate::util::timing::TimingTree;
-use crate::util::{log2_ceil, log2_strict, transpose, transpose_poly_values};
+use crate::util::{log2_ceil, log2_strict};
 
 /// Number of random coins needed for lookups (for each challenge).
 /// A coin is a randomly sampled extension field element from the verifier,
@@ -145,39 +142,51 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// A domain separator, which is included in the initial Fiat-Shamir seed. This is generally not
     /// needed, but can be used to ensure that proofs for one application are not valid for another.
     /// Defaults to the empty vector.
-    domain_separator: Option<Vec<F>>,
+    pub(crate) domain_separator: Option<Vec<F>>,
+
+    /// A semantic version of the circuit, e.g. `"1.2.0"`, stored in its verifier data. Defaults to
+    /// the empty string.
+    pub(crate) circuit_version: String,
 
     /// The types of gates used in this circuit.
     gates: HashSet<GateRef<F, D>>,
 
+    /// The gate types added with [`Self::add_gate`], by ID, so that all instances of a gate type
+    /// share a single [`GateRef`].
+    gate_refs_by_id: HashMap<String, GateRef<F, D>>,
+
     /// The concrete placement of each gate.
     pub(crate) gate_instances: Vec<GateInstance<F, D>>,
 
     /// Targets to be made public.
-    public_inputs: Vec<Target>,
+    pub(crate) public_inputs: Vec<Target>,
 
     /// The next available index for a `VirtualTarget`.
-    virtual_target_index: usize,
+    pub(crate) virtual_target_index: usize,
 
-    copy_constraints: Vec<CopyConstraint>,
+    pub(crate) copy_constraints: Vec<CopyConstraint>,
 
     /// A tree of named scopes, used for debugging.
     context_log: ContextTree,
 
     /// Generators used to generate the witness.
-    generators: Vec<WitnessGeneratorRef<F, D>>,
+    pub(crate) generators: Vec<WitnessGeneratorRef<F, D>>,
 
-    constants_to_targets: HashMap<F, Target>,
-    targets_to_constants: HashMap<Target, F>,
+    pub(crate) constants_to_targets: HashMap<F, Target>,
+    pub(crate) targets_to_constants: HashMap<Target, F>,
 
     /// Memoized results of `arithmetic` calls.
     pub(crate) base_arithmetic_results: HashMap<BaseArithmeticOperation<F>, Target>,
 
+    /// Base arithmetic operations whose placement is deferred to the optimization pass, along with
+    /// their output targets. Only used when [`CircuitConfig::optimize`] is set.
+    pub(crate) pending_base_arithmetic_operations: Vec<(BaseArithmeticOperation<F>, Target)>,
+
     /// Memoized results of `arithmetic_extension` calls.
     pub(crate) arithmetic_results: HashMap<ExtensionArithmeticOperation<F, D>, ExtensionTarget<D>>,
 
     /// Map between gate type and the current gate of this type with available slots.
-    current_slots: HashMap<GateRef<F, D>, CurrentSlot<F, D>>,
+    pub(crate) current_slots: HashMap<GateRef<F, D>, CurrentSlot<F, D>>,
 
     /// List of constant generators used to fill the constant wires.
     constant_generators: Vec<ConstantGenerator<F>>,
@@ -187,11 +196,18 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     lookup_rows: Vec<LookupWire>,
 
     /// For each LUT index, vector of `(looking_in, looking_out)` pairs.
-    lut_to_lookups: Vec<Lookup>,
+    pub(crate) lut_to_lookups: Vec<Lookup>,
 
     // Lookup tables in the form of `Vec<(input_value, output_value)>`.
     luts: Vec<LookupTable>,
 
+    /// Custom arguments taking part in the second round of the protocol.
+    pub(crate) custom_arguments: Vec<CustomArgumentRef<F, D>>,
+
+    /// Rows inlined from subcircuits, see [`Self::add_subcircuit`]. Their wires are set by the
+    /// subcircuit's own witness generation, so their gates' generators are not added.
+    pub(crate) subcircuit_rows: HashSet<usize>,
+
     /// Optional common data. When it is `Some(goal_data)`, the `build` function panics if the resulting
     /// common data doesn't equal `goal_data`.
     /// This is used in cyclic recursion.
@@ -200,6 +216,13 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// Optional verifier data that is registered as public inputs.
     /// This is used in cyclic recursion to hold the circuit's own verifier key.
     pub(crate) verifier_data_public_input: Option<VerifierCircuitTarget>,
+
+    /// Whether to omit any randomness from the circuit data. See [`Self::build_deterministic`].
+    deterministic: bool,
+
+    /// The values of the preprocessed columns added with [`Self::add_fixed_column`], which are
+    /// zero past their end.
+    pub(crate) fixed_columns: Vec<Vec<F>>,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
@@ -211,7 +234,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let builder = CircuitBuilder {
             config,
             domain_separator: None,
+            circuit_version: String::new(),
             gates: HashSet::new(),
+            gate_refs_by_id: HashMap::new(),
             gate_instances: Vec::new(),
             public_inputs: Vec::new(),
             virtual_target_index: 0,
@@ -221,14 +246,19 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             constants_to_targets: HashMap::new(),
             targets_to_constants: HashMap::new(),
             base_arithmetic_results: HashMap::new(),
+            pending_base_arithmetic_operations: Vec::new(),
             arithmetic_results: HashMap::new(),
             current_slots: HashMap::new(),
             constant_generators: Vec::new(),
             lookup_rows: Vec::new(),
             lut_to_lookups: Vec::new(),
             luts: Vec::new(),
+            custom_arguments: Vec::new(),
+            subcircuit_rows: HashSet::new(),
             goal_common_data: None,
             verifier_data_public_input: None,
+            deterministic: false,
+            fixed_columns: Vec::new(),
         };
         builder.check_config();
         builder
@@ -264,6 +294,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.domain_separator = Some(separator);
     }
 
+    /// Sets the version of the circuit stored in [`VerifierOnlyCircuitData::circuit_version`],
+    /// which is covered by [`VerifierOnlyCircuitData::verifier_data_hash`].
+    pub fn set_circuit_version(&mut self, version: impl Into<String>) {
+        self.circuit_version = version.into();
+    }
+
     /// Outputs the number of gates in this circuit.
     pub fn num_gates(&self) -> usize {
         self.gate_instances.len()
@@ -444,8 +480,44 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     /// Adds a gate to the circuit, and returns its index.
-    pub fn add_gate<G: Gate<F, D>>(&mut self, gate_type: G, mut constants: Vec<F>) -> usize {
-        self.check_gate_compatibility(&gate_type);
+    pub fn add_gate<G: Gate<F, D>>(&mut self, gate_type: G, constants: Vec<F>) -> usize {
+        // Register this gate type if we haven't seen it before.
+        let id = gate_type.id();
+        let gate_ref = match self.gate_refs_by_id.get(&id) {
+            Some(gate_ref) => gate_ref.clone(),
+            None => {
+                let gate_ref = GateRef::new(gate_type);
+                self.gates.insert(gate_ref.clone());
+                self.gate_refs_by_id.insert(id, gate_ref.clone());
+                gate_ref
+            }
+        };
+
+        self.add_gate_instance(gate_ref, constants)
+    }
+
+    /// Like [`Self::add_gate`], for a gate which is only known through a [`GateRef`].
+    pub(crate) fn add_gate_ref(&mut self, gate_ref: &GateRef<F, D>, constants: Vec<F>) -> usize {
+        let gate_ref = self.register_gate_ref(gate_ref);
+        self.add_gate_instance(gate_ref, constants)
+    }
+
+    /// Registers the type of `gate_ref` if we haven't seen it before, and returns the registered
+    /// reference to it.
+    fn register_gate_ref(&mut self, gate_ref: &GateRef<F, D>) -> GateRef<F, D> {
+        self.gate_refs_by_id
+            .entry(gate_ref.0.id())
+            .or_insert_with(|| {
+                self.gates.insert(gate_ref.clone());
+                gate_ref.clone()
+            })
+            .clone()
+    }
+
+    /// Adds a row holding the registered gate `gate_ref`, and returns its index.
+    fn add_gate_instance(&mut self, gate_ref: GateRef<F, D>, mut constants: Vec<F>) -> usize {
+        let gate_type = &*gate_ref.0;
+        self.check_gate_compatibility(gate_type);
 
         assert!(
             constants.len() <= gate_type.num_constants(),
@@ -469,10 +541,6 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         // could be modified later, i.e. in the case of `ConstantGate`. We will add them later in
         // `build` instead.
 
-        // Register this gate type if we haven't seen it before.
-        let gate_ref = GateRef::new(gate_type);
-        self.gates.insert(gate_ref.clone());
-
         self.gate_instances.push(GateInstance {
             gate_ref,
             constants,
@@ -481,7 +549,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         row
     }
 
-    fn check_gate_compatibility<G: Gate<F, D>>(&self, gate: &G) {
+    /// The constants used to configure the gate at the given row.
+    pub fn gate_constants(&self, row: usize) -> &[F] {
+        &self.gate_instances[row].constants
+    }
+
+    fn check_gate_compatibility<G: Gate<F, D> + ?Sized>(&self, gate: &G) {
         assert!(
             gate.num_wires() <= self.config.num_wires,
             "{:?} requires {} wires, but our CircuitConfig has only {}",
@@ -498,12 +571,50 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
+    /// Adds a row of a subcircuit, holding `gate_ref` with the given constants, and returns its
+    /// index. Unlike [`Self::add_gate`], the gate's extra constant wires are not made available to
+    /// this circuit's constants, and its generators are not added.
+    pub(crate) fn add_subcircuit_gate(
+        &mut self,
+        gate_ref: &GateRef<F, D>,
+        constants: &[F],
+    ) -> usize {
+        self.check_gate_compatibility(&*gate_ref.0);
+
+        let row = self.gate_instances.len();
+        let mut constants = constants.to_vec();
+        constants.resize(gate_ref.0.num_constants(), F::ZERO);
+
+        let gate_ref = self.register_gate_ref(gate_ref);
+        self.gate_instances.push(GateInstance {
+            gate_ref,
+            constants,
+        });
+        self.subcircuit_rows.insert(row);
+
+        row
+    }
+
     /// Adds a gate type to the set of gates to be used in this circuit. This can be useful
     /// in conditional recursion to uniformize the set of gates of the different circuits.
     pub fn add_gate_to_gate_set(&mut self, gate: GateRef<F, D>) {
         self.gates.insert(gate);
     }
 
+    /// Adds a custom argument to the circuit. Its polynomials are committed to along with the
+    /// permutation `Z`s, and its constraints are added to the vanishing polynomial.
+    /// See [`CustomArgument`] for more details.
+    pub fn add_custom_argument<A: CustomArgument<F, D>>(&mut self, argument: A) {
+        assert!(
+            argument.degree() <= self.config.max_quotient_degree_factor + 1,
+            "{} has degree {}, but our CircuitConfig supports at most {}",
+            argument.id(),
+            argument.degree(),
+            self.config.max_quotient_degree_factor + 1
+        );
+        self.custom_arguments.push(CustomArgumentRef::new(argument));
+    }
+
     /// Adds a generator which will copy `src` to `dst`.
     pub fn generate_copy(&mut self, src: Target, dst: Target) {
         self.add_simple_generator(CopyGenerator { src, dst });
@@ -522,8 +633,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             y.is_routable(&self.config),
             "Tried to route a wire that isn't routable"
         );
-        self.copy_constraints
-            .push(CopyConstraint::new((x, y), self.context_log.open_stack()));
+        self.copy_constraints.push(CopyConstraint::from((x, y)));
     }
 
     /// Enforces that the underlying values of two [`Target`] arrays are equal.
@@ -681,6 +791,46 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Like [`Self::constant_verifier_data`], but first checks that `verifier_data` has the shape
+    /// expected for proofs with the given common data, so that a mismatch fails here rather than
+    /// deep inside the recursive verifier.
+    pub fn constant_verifier_data_checked<C: GenericConfig<D, F = F>>(
+        &mut self,
+        verifier_data: &VerifierOnlyCircuitData<C, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> VerifierCircuitTarget
+    where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let cap_height = common_data
+            .fri_params
+            .oracle_cap_height(PlonkOracle::CONSTANTS_SIGMAS.index);
+        assert_eq!(
+            verifier_data.constants_sigmas_cap.height(),
+            cap_height,
+            "The verifier data is not that of a circuit with the given common data"
+        );
+        self.constant_verifier_data(verifier_data)
+    }
+
+    /// Asserts that `verifier_data`, e.g. read from the public inputs of a proof, is equal to the
+    /// given fixed verifier data, including its circuit digest.
+    pub fn connect_constant_verifier_data<C: GenericConfig<D, F = F>>(
+        &mut self,
+        verifier_data: &VerifierCircuitTarget,
+        expected: &VerifierOnlyCircuitData<C, D>,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        assert_eq!(
+            verifier_data.constants_sigmas_cap.0.len(),
+            expected.constants_sigmas_cap.len(),
+            "The verifier data caps have different heights"
+        );
+        let expected = self.constant_verifier_data(expected);
+        self.connect_verifier_data(verifier_data, &expected);
+    }
+
     /// If the given target is a constant (i.e. it was created by the `constant(F)` method), returns
     /// its constant value. Otherwise, returns `None`.
     pub fn target_as_constant(&self, target: Target) -> Option<F> {
@@ -706,11 +856,26 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     pub fn push_context(&mut self, level: log::Level, ctx: &str) {
-        self.context_log.push(ctx, level, self.num_gates());
+        self.context_log.push(ctx, level, self.context_counts());
     }
 
     pub fn pop_context(&mut self) {
-        self.context_log.pop(self.num_gates());
+        self.context_log.pop(self.context_counts());
+    }
+
+    fn context_counts(&self) -> ContextCounts {
+        ContextCounts {
+            gates: self.num_gates(),
+            copy_constraints: self.copy_constraints.len(),
+            generators: self.generators.len(),
+        }
+    }
+
+    /// Returns a [`ContextProfile`] reporting the gates, copy constraints and generators added in
+    /// each context so far.
+    pub fn context_profile(&self) -> ContextProfile {
+        self.context_log
+            .profile(self.context_counts(), &self.gate_instances)
     }
 
     /// Returns the total number of LUTs.
@@ -753,19 +918,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         inputs.iter().map(|&input| (input, f(input))).collect()
     }
 
-    /// Given a function `f: fn(u16) -> u16`, adds a LUT to the circuit builder.
-    pub fn update_luts_from_fn(&mut self, f: fn(u16) -> u16, inputs: &[u16]) -> usize {
-        let lut = Arc::new(Self::get_lut_from_fn::<u16>(f, inputs));
-
-        // If the LUT `lut` is already stored in `self.luts`, return its index. Otherwise, append `table` to `self.luts` and return its index.
-        if let Some(idx) = self.is_stored(lut.clone()) {
-            idx
-        } else {
-            self.luts.push(lut);
-            self.lut_to_lookups.push(vec![]);
-            assert!(self.luts.len() == self.lut_to_lookups.len());
-            self.luts.len() - 1
-        }
+    /// Given a function `f`, adds a LUT mapping each of `inputs` to its image to the circuit builder.
+    pub fn update_luts_from_fn(&mut self, f: impl Fn(u16) -> u16, inputs: &[u16]) -> usize {
+        let pairs = inputs.iter().map(|&input| (input, f(input))).collect();
+        self.update_luts_from_pairs(Arc::new(pairs))
     }
 
     /// Adds a table to the vector of LUTs in the circuit builder, given a list of inputs and table values.
@@ -781,21 +937,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             .copied()
             .zip_eq(table.iter().copied())
             .collect();
-        let lut: LookupTable = Arc::new(pairs);
-
-        // If the LUT `lut` is already stored in `self.luts`, return its index. Otherwise, append `table` to `self.luts` and return its index.
-        if let Some(idx) = self.is_stored(lut.clone()) {
-            idx
-        } else {
-            self.luts.push(lut);
-            self.lut_to_lookups.push(vec![]);
-            assert!(self.luts.len() == self.lut_to_lookups.len());
-            self.luts.len() - 1
-        }
+        self.update_luts_from_pairs(Arc::new(pairs))
     }
 
-    /// Adds a table to the vector of LUTs in the circuit builder.
+    /// Adds a table to the vector of LUTs in the circuit builder. Repeated `(input, output)` pairs
+    /// are only stored once, as they would only add rows to the lookup table gates.
     pub fn update_luts_from_pairs(&mut self, table: LookupTable) -> usize {
+        let mut seen = HashSet::new();
+        let pairs = table
+            .iter()
+            .copied()
+            .filter(|&pair| seen.insert(pair))
+            .collect::<Vec<_>>();
+        let table = if pairs.len() == table.len() {
+            table
+        } else {
+            Arc::new(pairs)
+        };
+
         // If the LUT `table` is already stored in `self.luts`, return its index. Otherwise, append `table` to `self.luts` and return its index.
         if let Some(idx) = self.is_stored(table.clone()) {
             idx
@@ -840,9 +999,21 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     fn fri_params(&self, degree_bits: usize) -> FriParams {
-        self.config
+        let mut fri_params = self
+            .config
             .fri_config
-            .fri_params(degree_bits, self.config.zero_knowledge)
+            .fri_params(degree_bits, self.config.zero_knowledge);
+        if self.config.optimize_cap_heights {
+            // The constants cap is part of the verifier data, so it keeps the configured height.
+            let cap_height = fri_params.optimal_oracle_cap_height();
+            fri_params.oracle_cap_heights = vec![
+                self.config.fri_config.cap_height,
+                cap_height,
+                cap_height,
+                cap_height,
+            ];
+        }
+        fri_params
     }
 
     /// The number of (base field) `arithmetic` operations that can be performed in a single gate.
@@ -918,6 +1089,21 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// The number of routed wires needed by the copy constraints added so far. It is always larger
+    /// than the quotient degree factor, as the partial products require.
+    fn num_used_routed_wires(&self) -> usize {
+        self.copy_constraints
+            .iter()
+            .flat_map(|c| [c.pair.0, c.pair.1])
+            .filter_map(|t| match t {
+                Target::Wire(Wire { column, .. }) => Some(column + 1),
+                Target::VirtualTarget { .. } => None,
+            })
+            .max()
+            .unwrap_or(0)
+            .max(self.config.max_quotient_degree_factor + 1)
+    }
+
     fn blind(&mut self) {
         let (regular_poly_openings, z_openings) = self.blinding_counts();
         info!(
@@ -974,25 +1160,21 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             .map(|g| g.0.num_constants())
             .max()
             .unwrap();
-        transpose(
-            &self
-                .gate_instances
-                .iter()
-                .map(|g| {
-                    let mut consts = g.constants.clone();
-                    consts.resize(max_constants, F::ZERO);
-                    consts
-                })
-                .collect::<Vec<_>>(),
-        )
-        .into_iter()
-        .map(PolynomialValues::new)
-        .collect()
+        (0..max_constants)
+            .map(|i| {
+                PolynomialValues::new(
+                    self.gate_instances
+                        .iter()
+                        .map(|g| g.constants.get(i).copied().unwrap_or(F::ZERO))
+                        .collect(),
+                )
+            })
+            .collect()
     }
 
-    fn sigma_vecs(&self, k_is: &[F], subgroup: &[F]) -> (Vec<PolynomialValues<F>>, Forest) {
+    /// Builds the disjoint-set forest of all targets, merged according to the copy constraints.
+    fn forest(&self) -> Forest {
         let degree = self.gate_instances.len();
-        let degree_log = log2_strict(degree);
         let config = &self.config;
         let mut forest = Forest::new(
             config.num_wires,
@@ -1019,12 +1201,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
 
         forest.compress_paths();
-
-        let wire_partition = forest.wire_partition();
-        (
-            wire_partition.get_sigma_polys(degree_log, k_is, subgroup),
-            forest,
-        )
+        forest
     }
 
     pub fn print_gate_counts(&self, min_delta: usize) {
@@ -1070,14 +1247,60 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     pub fn try_build_with_options<C: GenericConfig<D, F = F>>(
-        mut self,
+        self,
         commit_to_sigma: bool,
     ) -> (CircuitData<F, C, D>, bool) {
-        let mut timing = TimingTree::new("preprocess", Level::Trace);
+        let (circuit_data, success, _) = self.build_internal(commit_to_sigma, false);
+        (circuit_data, success)
+    }
 
+    /// Builds a "full circuit", along with a [`ContextProfile`] of the final circuit. Gates and
+    /// copy constraints added while building, e.g. to hash public inputs, place constants or pad
+    /// the circuit, are attributed to the root context.
+    pub fn build_with_profile<C: GenericConfig<D, F = F>>(
+        self,
+    ) -> (CircuitData<F, C, D>, ContextProfile) {
+        let (circuit_data, success, profile) = self.build_internal(true, true);
+        if !success {
+            panic!("Failed to build circuit");
+        }
+        (circuit_data, profile.expect("Profile was requested"))
+    }
+
+    fn build_internal<C: GenericConfig<D, F = F>>(
+        self,
+        commit_to_sigma: bool,
+        profile: bool,
+    ) -> (CircuitData<F, C, D>, bool, Option<ContextProfile>) {
         #[cfg(feature = "timing")]
         let start = Instant::now();
 
+        let (layout, success, context_profile) = self.layout_internal::<C>(profile);
+        let circuit_data = layout.commit_with_options(commit_to_sigma, None);
+
+        #[cfg(feature = "timing")]
+        debug!("Building circuit took {}s", start.elapsed().as_secs_f32());
+        (circuit_data, success, context_profile)
+    }
+
+    /// Lays out the circuit, i.e. places all remaining gates and computes the gate selectors,
+    /// constants and wiring, without computing the sigma polynomials nor committing to anything.
+    ///
+    /// This is much cheaper than [`Self::build`], so that the resulting [`CircuitLayout`] can be
+    /// used to inspect a circuit. [`CircuitLayout::commit`] then yields the same [`CircuitData`]
+    /// as [`Self::build`].
+    pub fn layout<C: GenericConfig<D, F = F>>(self) -> CircuitLayout<F, C, D> {
+        let (layout, success, _) = self.layout_internal(false);
+        if !success {
+            panic!("Failed to build circuit");
+        }
+        layout
+    }
+
+    fn layout_internal<C: GenericConfig<D, F = F>>(
+        mut self,
+        profile: bool,
+    ) -> (CircuitLayout<F, C, D>, bool, Option<ContextProfile>) {
         let rate_bits = self.config.fri_config.rate_bits;
         let cap_height = self.config.fri_config.cap_height;
         // Total number of LUTs.
@@ -1095,7 +1318,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         {
             self.connect(hash_part, Target::wire(pi_gate, wire))
         }
-        self.randomize_unused_pi_wires(pi_gate);
+        if !self.deterministic {
+            self.randomize_unused_pi_wires(pi_gate);
+        }
+
+        // Optimize and place the deferred base arithmetic operations, if any.
+        self.optimize_and_place_base_arithmetic();
 
         // Place LUT-related gates.
         self.add_all_lookups();
@@ -1129,6 +1357,16 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             self.add_simple_generator(const_gen);
         }
 
+        // Make sure the fixed columns fit in the circuit.
+        let fixed_columns_len = self.fixed_columns.iter().map(Vec::len).max().unwrap_or(0);
+        while self.gate_instances.len() < fixed_columns_len {
+            self.add_gate(NoopGate, vec![]);
+        }
+
+        if self.config.minimize_routed_wires {
+            self.config.num_routed_wires = self.num_used_routed_wires();
+        }
+
         debug!(
             "Degree before blinding & padding: {}",
             self.gate_instances.len()
@@ -1136,7 +1374,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.blind_and_pad();
         let degree = self.gate_instances.len();
         debug!("Degree after blinding & padding: {}", degree);
+        let context_profile = profile.then(|| self.context_profile());
         let degree_bits = log2_strict(degree);
+        self.config
+            .check_circuit_size::<F>(degree_bits)
+            .unwrap_or_else(|e| panic!("{e}"));
         let fri_params = self.fri_params(degree_bits);
         assert!(
             fri_params.total_arities() <= degree_bits + rate_bits - cap_height,
@@ -1164,34 +1406,17 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         };
 
         constant_vecs.extend(self.constant_polys());
+        // Fixed columns come last, in reverse order, see `FixedColumn::constant_index`.
+        constant_vecs.extend(self.fixed_columns.iter().rev().map(|values| {
+            let mut values = values.clone();
+            values.resize(degree, F::ZERO);
+            PolynomialValues::new(values)
+        }));
         let num_constants = constant_vecs.len();
 
-        let subgroup = F::two_adic_subgroup(degree_bits);
-
         let k_is = get_unique_coset_shifts(degree, self.config.num_routed_wires);
-        let (sigma_vecs, forest) = timed!(
-            timing,
-            "generate sigma polynomials",
-            self.sigma_vecs(&k_is, &subgroup)
-        );
-
-        // Precompute FFT roots.
-        let max_fft_points = 1 << (degree_bits + max(rate_bits, log2_ceil(quotient_degree_factor)));
-        let fft_root_table = fft_root_table(max_fft_points);
-
-        let constants_sigmas_commitment = if commit_to_sigma {
-            let constants_sigmas_vecs = [constant_vecs, sigma_vecs.clone()].concat();
-            PolynomialBatch::<F, C, D>::from_values(
-                constants_sigmas_vecs,
-                rate_bits,
-                PlonkOracle::CONSTANTS_SIGMAS.blinding,
-                cap_height,
-                &mut timing,
-                Some(&fft_root_table),
-            )
-        } else {
-            PolynomialBatch::<F, C, D>::default()
-        };
+        let mut forest = self.forest();
+        let wire_partition = forest.wire_partition();
 
         // Map between gates where not all generators are used and the gate's number of used generators.
         let incomplete_gates = self
@@ -1205,8 +1430,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             self.gate_instances
                 .iter()
                 .enumerate()
+                .filter(|(index, _)| !self.subcircuit_rows.contains(index))
                 .flat_map(|(index, gate)| {
-                    let mut gens = gate.gate_ref.0.generators(index, &gate.constants);
+                    let mut gens = gate
+                        .gate_ref
+                        .0
+                        .generators(index, self.gate_constants(index));
                     // Remove unused generators, if any.
                     if let Some(&op) = incomplete_gates.get(&index) {
                         gens.drain(op..);
@@ -1249,19 +1478,6 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             // There is 1 RE polynomial and multiple Sum/LDC polynomials.
             LookupGate::num_slots(&self.config).div_ceil(lookup_degree) + 1
         };
-        let constants_sigmas_cap = constants_sigmas_commitment.merkle_tree.cap.clone();
-        let domain_separator = self.domain_separator.unwrap_or_default();
-        let domain_separator_digest = C::Hasher::hash_pad(&domain_separator);
-        // TODO: This should also include an encoding of gate constraints.
-        let circuit_digest_parts = [
-            constants_sigmas_cap.flatten(),
-            domain_separator_digest.to_vec(),
-            vec![
-                F::from_canonical_usize(degree_bits),
-                /* Add other circuit data here */
-            ],
-        ];
-        let circuit_digest = C::Hasher::hash_no_pad(&circuit_digest_parts.concat());
 
         let common = CommonCircuitData {
             config: self.config,
@@ -1277,6 +1493,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             num_lookup_polys,
             num_lookup_selectors,
             luts: self.luts,
+            custom_arguments: self.custom_arguments,
         };
 
         let mut success = true;
@@ -1288,36 +1505,22 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             }
         }
 
-        let prover_only = ProverOnlyCircuitData::<F, C, D> {
+        let layout = CircuitLayout {
+            common,
+            gate_instances: self.gate_instances,
+            constants: constant_vecs,
+            representative_map: forest.parents,
+            wire_partition,
             generators: self.generators,
             generator_indices_by_watches,
-            constants_sigmas_commitment,
-            sigmas: transpose_poly_values(sigma_vecs),
-            subgroup,
             public_inputs: self.public_inputs,
-            representative_map: forest.parents,
-            fft_root_table: Some(fft_root_table),
-            circuit_digest,
-            lookup_rows: self.lookup_rows.clone(),
-            lut_to_lookups: self.lut_to_lookups.clone(),
-        };
-
-        let verifier_only = VerifierOnlyCircuitData::<C, D> {
-            constants_sigmas_cap,
-            circuit_digest,
+            lookup_rows: self.lookup_rows,
+            lut_to_lookups: self.lut_to_lookups,
+            domain_separator: self.domain_separator.unwrap_or_default(),
+            circuit_version: self.circuit_version,
+            _phantom: PhantomData,
         };
-
-        timing.print();
-        #[cfg(feature = "timing")]
-        debug!("Building circuit took {}s", start.elapsed().as_secs_f32());
-        (
-            CircuitData {
-                prover_only,
-                verifier_only,
-                common,
-            },
-            success,
-        )
+        (layout, success, context_profile)
     }
 
     /// Builds a "full circuit", with both prover and verifier data.
@@ -1325,6 +1528,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.build_with_options(true)
     }
 
+    /// Builds a "full circuit" whose data, including witness generators, is fully determined by
+    /// the gadgets added to the builder, so that the same witness always yields the same proof.
+    ///
+    /// This requires zero-knowledge to be disabled, and omits the random values that are otherwise
+    /// assigned to unused public input wires. These values only help recovering from the rare
+    /// division by zero in the permutation argument, so proving may then fail for some witnesses.
+    /// The resulting [`CircuitData::circuit_fingerprint`] is identical to that of [`Self::build`].
+    pub fn build_deterministic<C: GenericConfig<D, F = F>>(mut self) -> CircuitData<F, C, D> {
+        assert!(
+            !self.config.zero_knowledge,
+            "Deterministic builds require zero-knowledge to be disabled."
+        );
+        self.deterministic = true;
+        let data = self.build::<C>();
+        debug!("Circuit fingerprint: {:?}", data.circuit_fingerprint());
+        data
+    }
+
     pub fn mock_build<C: GenericConfig<D, F = F>>(self) -> MockCircuitData<F, C, D> {
         let circuit_data = self.build_with_options(false);
         MockCircuitData {
@@ -1340,9 +1561,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 
     /// Builds a "verifier circuit", with data needed to verify proofs but not generate them.
+    ///
+    /// This is cheaper than [`Self::build`], as the low-degree extensions of the constant and sigma
+    /// polynomials and their Merkle tree are only used to compute the Merkle cap, and never stored.
     pub fn build_verifier<C: GenericConfig<D, F = F>>(self) -> VerifierCircuitData<F, C, D> {
-        // TODO: Can skip parts of this.
-        let circuit_data = self.build::<C>();
-        circuit_data.verifier_data()
+        self.layout::<C>().commit_verifier()
     }
 }
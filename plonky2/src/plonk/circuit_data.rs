@@ -13,45 +13,62 @@
 //! This is useful to allow even small devices to verify plonky2 proofs.
 
 #[cfg(not(feature = "std"))]
-use alloc::{collections::BTreeMap, vec, vec::Vec};
-use core::ops::{Range, RangeFrom};
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use core::cmp::max;
+use core::marker::PhantomData;
+use core::mem::size_of;
+use core::ops::Range;
 #[cfg(feature = "std")]
 use std::collections::BTreeMap;
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
+use log::Level;
 use serde::Serialize;
 
 use super::circuit_builder::LookupWire;
 use crate::field::extension::Extendable;
-use crate::field::fft::FftRootTable;
+use crate::field::fft::{fft_root_table, FftRootTable};
+use crate::field::polynomial::PolynomialValues;
 use crate::field::types::Field;
-use crate::fri::oracle::PolynomialBatch;
+use crate::fri::lde_cache::LdeCache;
+use crate::fri::oracle::{PolynomialBatch, SALT_SIZE};
 use crate::fri::reduction_strategies::FriReductionStrategy;
 use crate::fri::structure::{
-    FriBatchInfo, FriBatchInfoTarget, FriInstanceInfo, FriInstanceInfoTarget, FriOracleInfo,
-    FriPolynomialInfo,
+    FriInstanceInfo, FriInstanceInfoTarget, FriOracleInfo, FriPolynomialInfo,
 };
 use crate::fri::{FriConfig, FriParams};
-use crate::gates::gate::GateRef;
+use crate::gates::gate::{GateInstance, GateRef};
 use crate::gates::lookup::Lookup;
 use crate::gates::lookup_table::LookupTable;
 use crate::gates::selectors::SelectorsInfo;
-use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hashing::PlonkyPermutation;
 use crate::hash::merkle_tree::MerkleCap;
+use crate::iop::challenger::{Challenger, Transcript};
 use crate::iop::ext_target::ExtensionTarget;
-use crate::iop::generator::{generate_partial_witness, WitnessGeneratorRef};
+use crate::iop::generator::{generate_partial_witness, IncrementalWitness, WitnessGeneratorRef};
 use crate::iop::target::Target;
 use crate::iop::witness::{PartialWitness, PartitionWitness};
 use crate::plonk::circuit_builder::CircuitBuilder;
-use crate::plonk::config::{GenericConfig, Hasher};
-use crate::plonk::plonk_common::PlonkOracle;
+use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::custom_argument::CustomArgumentRef;
+use crate::plonk::permutation_argument::WirePartition;
+use crate::plonk::plonk_common::{salt_size, PlonkOracle};
 use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
-use crate::plonk::prover::prove;
+use crate::plonk::prover::{
+    prove, prove_with_hooks, prove_with_options, prove_with_partition_witness,
+    prove_with_transcript, resume_proof, ProverCheckpoint, ProverHooks, ProverOptions,
+};
+use crate::plonk::vars::EvaluationVars;
 use crate::plonk::verifier::verify;
+use crate::plonk::witness_check::{check_witness, ConstraintViolation};
+use crate::timed;
+use crate::util::serialization::aligned::{AlignedBuffer, AlignedWriter};
 use crate::util::serialization::{
     Buffer, GateSerializer, IoResult, Read, WitnessGeneratorSerializer, Write,
 };
 use crate::util::timing::TimingTree;
+use crate::util::{log2_ceil, transpose_poly_values};
 
 /// Configuration to be used when building a circuit. This defines the shape of the circuit
 /// as well as its targeted security level and sub-protocol (e.g. FRI) parameters.
@@ -78,12 +95,40 @@ pub struct CircuitConfig {
     /// `degree / |F|`.
     pub num_challenges: usize,
     /// A boolean to activate the zero-knowledge property. When this is set to `false`, proofs *may*
-    /// leak additional information.
+    /// leak additional information. See [`ZkMode`] for what it enables.
     pub zero_knowledge: bool,
     /// A cap on the quotient polynomial's degree factor. The actual degree factor is derived
     /// systematically, but will never exceed this value.
     pub max_quotient_degree_factor: usize,
     pub fri_config: FriConfig,
+    /// Whether to run an optimization pass on base arithmetic operations before routing. When set,
+    /// constant operations are folded, duplicate ones are merged, those whose output is never
+    /// used are removed and the remaining ones are packed into as few gates as possible.
+    ///
+    /// This only affects how the circuit is built, so it isn't serialized, and is unset in
+    /// deserialized configs.
+    #[serde(skip_serializing)]
+    pub optimize: bool,
+    /// Whether to pick the Merkle cap height of the wires, Z and quotient oracles so as to minimize
+    /// the proof size given the number of FRI queries, rather than using `fri_config.cap_height`.
+    /// The constants oracle, whose cap is part of the verifier data, keeps the configured height.
+    ///
+    /// The chosen heights are recorded in the [`FriParams`], so this flag isn't serialized either,
+    /// and is unset in deserialized configs.
+    #[serde(skip_serializing)]
+    pub optimize_cap_heights: bool,
+    /// Whether to lower the number of routed wires, when building the circuit, to the number of
+    /// columns actually involved in copy constraints. Gates are still constructed with
+    /// `num_routed_wires` routed wires, so this never goes below what they require.
+    ///
+    /// This only affects how the circuit is built, so it isn't serialized, and is unset in
+    /// deserialized configs.
+    #[serde(skip_serializing)]
+    pub minimize_routed_wires: bool,
+    /// Whether to allow circuits with more than `2^MAX_STANDARD_DEGREE_BITS` rows. Such circuits
+    /// need tens of gigabytes for their LDEs, so building one without this flag is assumed to be a
+    /// mistake and fails.
+    pub large_circuit: bool,
 }
 
 impl Default for CircuitConfig {
@@ -115,6 +160,10 @@ impl CircuitConfig {
                 reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
                 num_query_rounds: 28,
             },
+            optimize: false,
+            optimize_cap_heights: false,
+            minimize_routed_wires: false,
+            large_circuit: false,
         }
     }
 
@@ -132,12 +181,182 @@ impl CircuitConfig {
         }
     }
 
+    /// The [`standard_recursion_config`](Self::standard_recursion_config), with enough wires for
+    /// the [`PoseidonSpongeGate`](crate::gates::poseidon_sponge::PoseidonSpongeGate), which
+    /// evaluates two Poseidon permutations per row when hashing long inputs.
+    pub fn wide_poseidon_config() -> Self {
+        Self {
+            num_wires: 248,
+            ..Self::standard_recursion_config()
+        }
+    }
+
+    /// The [`standard_recursion_config`](Self::standard_recursion_config), with zero-knowledge.
     pub fn standard_recursion_zk_config() -> Self {
         CircuitConfig {
             zero_knowledge: true,
             ..Self::standard_recursion_config()
         }
     }
+
+    /// The zero-knowledge mode set by [`Self::zero_knowledge`].
+    pub const fn zk_mode(&self) -> ZkMode {
+        if self.zero_knowledge {
+            ZkMode::Enabled
+        } else {
+            ZkMode::Disabled
+        }
+    }
+
+    /// A config with a higher FRI rate and fewer query rounds than the
+    /// [`standard_recursion_config`](Self::standard_recursion_config), targeting the same
+    /// security. Proofs are smaller and cheaper to verify recursively, at the cost of a larger
+    /// LDE for the prover.
+    pub fn high_rate_config() -> Self {
+        let standard = Self::standard_recursion_config();
+        Self {
+            fri_config: FriConfig {
+                rate_bits: 7,
+                proof_of_work_bits: 16,
+                num_query_rounds: 12,
+                ..standard.fri_config
+            },
+            ..standard
+        }
+    }
+
+    /// Selects a config for a circuit expected to have about `degree_estimate` gates, targeting
+    /// ~100 bit security and optimizing for the given objective.
+    ///
+    /// When optimizing for prover time, this is the
+    /// [`standard_recursion_config`](Self::standard_recursion_config), with a Merkle cap no
+    /// higher than the circuit and with proof-of-work bits lowered, in favor of more query rounds,
+    /// so that grinding costs at most about one hash per row of the LDE. Small circuits are
+    /// otherwise dominated by the `2^16` hashes of the standard proof of work.
+    ///
+    /// When optimizing for proof size, the FRI rate is raised as long as the LDE stays within
+    /// `2^MAX_PROOF_SIZE_LDE_BITS` rows, the number of query rounds and the Merkle cap height are
+    /// lowered accordingly, and the number of routed wires is lowered, when building the circuit,
+    /// to what its copy constraints use (see
+    /// [`minimize_routed_wires`](Self::minimize_routed_wires)).
+    pub fn auto_tune(degree_estimate: usize, objective: TuningObjective) -> Self {
+        let standard = Self::standard_recursion_config();
+        let degree_bits = log2_ceil(degree_estimate.max(1));
+        match objective {
+            TuningObjective::ProverTime => {
+                let rate_bits = standard.fri_config.rate_bits;
+                let proof_of_work_bits = standard
+                    .fri_config
+                    .proof_of_work_bits
+                    .min((degree_bits + rate_bits) as u32);
+                let num_query_rounds =
+                    (standard.security_bits - proof_of_work_bits as usize).div_ceil(rate_bits);
+                Self {
+                    fri_config: FriConfig {
+                        cap_height: standard.fri_config.cap_height.min(degree_bits),
+                        proof_of_work_bits,
+                        num_query_rounds,
+                        ..standard.fri_config
+                    },
+                    ..standard
+                }
+            }
+            TuningObjective::ProofSize => {
+                let rate_bits = Self::MAX_PROOF_SIZE_LDE_BITS
+                    .saturating_sub(degree_bits)
+                    .clamp(standard.fri_config.rate_bits, Self::MAX_RATE_BITS);
+                let proof_of_work_bits = 20;
+                let num_query_rounds =
+                    (standard.security_bits - proof_of_work_bits as usize).div_ceil(rate_bits);
+                let cap_height = log2_ceil(num_query_rounds)
+                    .saturating_sub(1)
+                    .min(degree_bits);
+                Self {
+                    minimize_routed_wires: true,
+                    fri_config: FriConfig {
+                        rate_bits,
+                        cap_height,
+                        proof_of_work_bits,
+                        reduction_strategy: FriReductionStrategy::MinSize(None),
+                        num_query_rounds,
+                    },
+                    ..standard
+                }
+            }
+        }
+    }
+
+    /// The maximum log2 of the LDE size that [`auto_tune`](Self::auto_tune) reaches when raising
+    /// the FRI rate to reduce the proof size.
+    pub const MAX_PROOF_SIZE_LDE_BITS: usize = 20;
+
+    /// The maximum FRI rate (in bits) used by [`auto_tune`](Self::auto_tune).
+    const MAX_RATE_BITS: usize = 8;
+
+    /// The maximum log2 of the number of rows of a circuit, unless
+    /// [`large_circuit`](Self::large_circuit) is set.
+    pub const MAX_STANDARD_DEGREE_BITS: usize = 26;
+
+    /// Checks that a circuit with `2^degree_bits` rows can be proven with this config: its LDE
+    /// must fit in the two-adic subgroup of `F`, and the size in bytes of its wire LDEs must fit
+    /// in a `usize`. Circuits with more than `2^MAX_STANDARD_DEGREE_BITS` rows are only allowed in
+    /// [`large_circuit`](Self::large_circuit) mode.
+    pub fn check_circuit_size<F: Field>(&self, degree_bits: usize) -> Result<()> {
+        ensure!(
+            self.large_circuit || degree_bits <= Self::MAX_STANDARD_DEGREE_BITS,
+            "The circuit has 2^{degree_bits} rows, more than 2^{}; set `large_circuit` to allow it",
+            Self::MAX_STANDARD_DEGREE_BITS
+        );
+        let lde_bits = degree_bits + self.fri_config.rate_bits;
+        ensure!(
+            lde_bits <= F::TWO_ADICITY,
+            "The LDE has 2^{lde_bits} rows, more than the 2^{} allowed by the field",
+            F::TWO_ADICITY
+        );
+        let salt_size = if self.zero_knowledge { SALT_SIZE } else { 0 };
+        let lde_size = 1usize
+            .checked_shl(lde_bits as u32)
+            .and_then(|size| size.checked_mul(self.num_wires + salt_size))
+            .and_then(|size| size.checked_mul(size_of::<F>()));
+        ensure!(
+            lde_size.is_some(),
+            "The wire LDEs of a circuit with 2^{degree_bits} rows do not fit in memory"
+        );
+        Ok(())
+    }
+}
+
+/// What [`CircuitConfig::auto_tune`] optimizes for.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TuningObjective {
+    /// Minimize the proof size, e.g. for a final proof to be verified on-chain.
+    ProofSize,
+    /// Minimize the prover time, e.g. for intermediate proofs of a recursion tree.
+    ProverTime,
+}
+
+/// Whether the proofs of a circuit have the zero-knowledge property, see
+/// [`CircuitConfig::zk_mode`]. The mode determines, in a single place, all the parts of the
+/// protocol involved in hiding the witness.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ZkMode {
+    /// Proofs *may* leak information about the witness.
+    #[default]
+    Disabled,
+    /// Blinding rows are added to the witness, the Merkle leaves of the wires, `Z`s and quotient
+    /// oracles are salted, and FRI runs in hiding mode.
+    Enabled,
+}
+
+impl ZkMode {
+    pub const fn is_enabled(self) -> bool {
+        matches!(self, Self::Enabled)
+    }
+
+    /// Whether the Merkle leaves of the given oracle are salted.
+    pub(crate) const fn salts(self, oracle: PlonkOracle) -> bool {
+        self.is_enabled() && oracle.blinding
+    }
 }
 
 /// Mock circuit data to only do witness generation without generating a proof.
@@ -195,10 +414,114 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         )
     }
 
+    /// Like [`Self::prove`], but reports progress to `hooks` and lets them cancel proving, see
+    /// [`ProverHooks`].
+    pub fn prove_with_hooks(
+        &self,
+        inputs: PartialWitness<F>,
+        hooks: &ProverHooks,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_hooks::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            hooks,
+        )
+    }
+
+    /// Like [`Self::prove_with_hooks`], but runs according to `options`, see
+    /// [`prove_with_options`].
+    pub fn prove_with_options(
+        &self,
+        inputs: PartialWitness<F>,
+        hooks: &ProverHooks,
+        options: &ProverOptions,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_options::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            hooks,
+            options,
+        )
+    }
+
+    /// Resumes a proof from a checkpoint saved by [`ProverHooks::on_checkpoint`], see
+    /// [`resume_proof`].
+    pub fn resume_proof(
+        &self,
+        checkpoint: &[u8],
+        hooks: &ProverHooks,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let checkpoint = ProverCheckpoint::from_bytes(checkpoint)
+            .map_err(|_| anyhow::anyhow!("Invalid prover checkpoint"))?;
+        resume_proof::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            checkpoint,
+            &mut TimingTree::default(),
+            hooks,
+        )
+    }
+
+    /// Like [`Self::prove`], but also returns the Fiat-Shamir transcript of the prover, see
+    /// [`prove_with_transcript`].
+    pub fn prove_with_transcript(
+        &self,
+        inputs: PartialWitness<F>,
+    ) -> Result<(ProofWithPublicInputs<F, C, D>, Transcript<F>)> {
+        prove_with_transcript::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+        )
+    }
+
+    /// Generates a witness for `inputs` which can be cheaply updated for new inputs, see
+    /// [`IncrementalWitness`].
+    pub fn generate_incremental_witness(
+        &self,
+        inputs: PartialWitness<F>,
+    ) -> Result<IncrementalWitness<'_, F, C, D>> {
+        IncrementalWitness::new(inputs, &self.prover_only, &self.common)
+    }
+
+    /// Proves the current state of an incremental witness, which is left untouched so that it can
+    /// be updated for the next proof.
+    pub fn prove_incremental(
+        &self,
+        witness: &IncrementalWitness<F, C, D>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_partition_witness::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            witness.witness().clone(),
+            &mut TimingTree::default(),
+        )
+    }
+
+    /// Generates the witness for `inputs` and checks it against the circuit's constraints without
+    /// proving, see [`check_witness`].
+    pub fn check_witness(&self, inputs: PartialWitness<F>) -> Result<(), ConstraintViolation> {
+        check_witness::<F, C, D>(&self.prover_only, &self.common, inputs)
+    }
+
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> Result<()> {
         verify::<F, C, D>(proof_with_pis, &self.verifier_only, &self.common)
     }
 
+    /// Replays the Fiat-Shamir transcript of a verifier of `proof_with_pis`, see
+    /// [`ProofWithPublicInputs::replay_transcript`].
+    pub fn replay_transcript(
+        &self,
+        proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<Transcript<F>> {
+        proof_with_pis.replay_transcript(&self.verifier_only.circuit_digest, &self.common)
+    }
+
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
@@ -220,6 +543,20 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         proof.decompress(&self.verifier_only.circuit_digest, &self.common)
     }
 
+    /// Returns a stable digest of this circuit, covering its gates and their constraints, its
+    /// wiring, its constants and its configuration. See
+    /// [`CommonCircuitData::circuit_fingerprint`] for more details.
+    pub fn circuit_fingerprint(&self) -> HashOut<F> {
+        self.common
+            .circuit_fingerprint::<C>(&self.verifier_only.circuit_digest)
+    }
+
+    /// Returns a stable key for this circuit's verifier. See
+    /// [`VerifierOnlyCircuitData::verifier_data_hash`] for more details.
+    pub fn verifier_data_hash(&self) -> HashOut<F> {
+        self.verifier_only.verifier_data_hash(&self.common)
+    }
+
     pub fn verifier_data(&self) -> VerifierCircuitData<F, C, D> {
         let CircuitData {
             verifier_only,
@@ -284,6 +621,30 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         buffer.read_prover_circuit_data(gate_serializer, generator_serializer)
     }
 
+    /// Like [`Self::to_bytes`], but in the aligned format, see [`aligned`](crate::util::serialization::aligned),
+    /// which [`Self::from_aligned_bytes`] loads much faster for large circuits.
+    pub fn to_aligned_bytes(
+        &self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> IoResult<Vec<u8>> {
+        let mut writer = AlignedWriter::new();
+        writer.write_prover_circuit_data(self, gate_serializer, generator_serializer)?;
+        Ok(writer.into_bytes())
+    }
+
+    /// Reads data written by [`Self::to_aligned_bytes`]. The bulk vectors are decoded in parallel
+    /// straight from `bytes`, which may e.g. be a memory-mapped file. They are copied into owned
+    /// vectors rather than borrowed, so `bytes` need not outlive the result.
+    pub fn from_aligned_bytes(
+        bytes: &[u8],
+        gate_serializer: &dyn GateSerializer<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> IoResult<Self> {
+        let mut buffer = AlignedBuffer::new(bytes)?;
+        buffer.read_prover_circuit_data(gate_serializer, generator_serializer)
+    }
+
     pub fn prove(&self, inputs: PartialWitness<F>) -> Result<ProofWithPublicInputs<F, C, D>> {
         prove::<F, C, D>(
             &self.prover_only,
@@ -292,6 +653,58 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             &mut TimingTree::default(),
         )
     }
+
+    /// Like [`Self::prove`], but reports progress to `hooks` and lets them cancel proving, see
+    /// [`ProverHooks`].
+    pub fn prove_with_hooks(
+        &self,
+        inputs: PartialWitness<F>,
+        hooks: &ProverHooks,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_hooks::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            hooks,
+        )
+    }
+
+    /// Like [`Self::prove_with_hooks`], but runs according to `options`, see
+    /// [`prove_with_options`].
+    pub fn prove_with_options(
+        &self,
+        inputs: PartialWitness<F>,
+        hooks: &ProverHooks,
+        options: &ProverOptions,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_options::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+            hooks,
+            options,
+        )
+    }
+
+    /// Resumes a proof from a checkpoint saved by [`ProverHooks::on_checkpoint`], see
+    /// [`resume_proof`].
+    pub fn resume_proof(
+        &self,
+        checkpoint: &[u8],
+        hooks: &ProverHooks,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let checkpoint = ProverCheckpoint::from_bytes(checkpoint)
+            .map_err(|_| anyhow::anyhow!("Invalid prover checkpoint"))?;
+        resume_proof::<F, C, D>(
+            &self.prover_only,
+            &self.common,
+            checkpoint,
+            &mut TimingTree::default(),
+            hooks,
+        )
+    }
 }
 
 /// Circuit data required by the prover.
@@ -332,6 +745,211 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     ) -> Result<()> {
         compressed_proof_with_pis.verify(&self.verifier_only, &self.common)
     }
+
+    /// Returns a stable digest of this circuit, covering its gates and their constraints, its
+    /// wiring, its constants and its configuration. See
+    /// [`CommonCircuitData::circuit_fingerprint`] for more details.
+    pub fn circuit_fingerprint(&self) -> HashOut<F> {
+        self.common
+            .circuit_fingerprint::<C>(&self.verifier_only.circuit_digest)
+    }
+
+    /// Returns a stable key for this circuit's verifier. See
+    /// [`VerifierOnlyCircuitData::verifier_data_hash`] for more details.
+    pub fn verifier_data_hash(&self) -> HashOut<F> {
+        self.verifier_only.verifier_data_hash(&self.common)
+    }
+}
+
+/// The layout of a circuit, i.e. its gates, constants and wiring, before the sigma polynomials
+/// are computed and the constant and sigma polynomials are committed to. It is obtained with
+/// [`CircuitBuilder::layout`], and turned into [`CircuitData`] with [`Self::commit`].
+#[derive(Debug)]
+pub struct CircuitLayout<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
+    /// The circuit data shared by the prover and the verifier, which doesn't depend on any
+    /// commitment.
+    pub common: CommonCircuitData<F, D>,
+    /// The gate placed at each row.
+    pub gate_instances: Vec<GateInstance<F, D>>,
+    /// The values of the selector polynomials, lookup selector polynomials and constant
+    /// polynomials, in the order in which they are committed to.
+    pub constants: Vec<PolynomialValues<F>>,
+    /// A map from each `Target`'s index to the index of its representative in the disjoint-set
+    /// forest.
+    pub representative_map: Vec<usize>,
+    pub(crate) wire_partition: WirePartition,
+    pub(crate) generators: Vec<WitnessGeneratorRef<F, D>>,
+    pub(crate) generator_indices_by_watches: BTreeMap<usize, Vec<usize>>,
+    pub(crate) public_inputs: Vec<Target>,
+    pub(crate) lookup_rows: Vec<LookupWire>,
+    pub(crate) lut_to_lookups: Vec<Lookup>,
+    pub(crate) domain_separator: Vec<F>,
+    pub(crate) circuit_version: String,
+    pub(crate) _phantom: PhantomData<C>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    CircuitLayout<F, C, D>
+{
+    pub const fn degree_bits(&self) -> usize {
+        self.common.degree_bits()
+    }
+
+    /// Targets to be made public.
+    pub fn public_inputs(&self) -> &[Target] {
+        &self.public_inputs
+    }
+
+    /// Computes the sigma polynomials and commits to the constant and sigma polynomials, yielding
+    /// the same [`CircuitData`] as [`CircuitBuilder::build`].
+    pub fn commit(self) -> CircuitData<F, C, D> {
+        self.commit_with_options(true, None)
+    }
+
+    /// Like [`Self::commit`], but reuses the low-degree extensions and Merkle tree of the constant
+    /// and sigma polynomials stored in `cache`, e.g. by a previous build of the same circuit.
+    pub fn commit_with_cache(self, cache: &mut LdeCache<F, C::Hasher>) -> CircuitData<F, C, D> {
+        self.commit_with_options(true, Some(cache))
+    }
+
+    pub(crate) fn commit_with_options(
+        self,
+        commit_to_sigma: bool,
+        lde_cache: Option<&mut LdeCache<F, C::Hasher>>,
+    ) -> CircuitData<F, C, D> {
+        let mut timing = TimingTree::new("preprocess", Level::Trace);
+
+        let rate_bits = self.common.config.fri_config.rate_bits;
+        let cap_height = self
+            .common
+            .fri_params
+            .oracle_cap_height(PlonkOracle::CONSTANTS_SIGMAS.index);
+        let degree_bits = self.degree_bits();
+        let subgroup = F::two_adic_subgroup(degree_bits);
+        let sigma_vecs = self.sigma_vecs(&subgroup, &mut timing);
+
+        // Precompute FFT roots.
+        let max_fft_points =
+            1 << (degree_bits + max(rate_bits, log2_ceil(self.common.quotient_degree_factor)));
+        let fft_root_table = fft_root_table(max_fft_points);
+
+        let constants_sigmas_commitment = if commit_to_sigma {
+            let constants_sigmas_vecs = [self.constants, sigma_vecs.clone()].concat();
+            match lde_cache {
+                Some(cache) => PolynomialBatch::<F, C, D>::from_values_with_cache(
+                    constants_sigmas_vecs,
+                    rate_bits,
+                    PlonkOracle::CONSTANTS_SIGMAS.blinding,
+                    cap_height,
+                    &mut timing,
+                    Some(&fft_root_table),
+                    cache,
+                ),
+                None => PolynomialBatch::<F, C, D>::from_values(
+                    constants_sigmas_vecs,
+                    rate_bits,
+                    PlonkOracle::CONSTANTS_SIGMAS.blinding,
+                    cap_height,
+                    &mut timing,
+                    Some(&fft_root_table),
+                ),
+            }
+        } else {
+            PolynomialBatch::<F, C, D>::default()
+        };
+
+        let constants_sigmas_cap = constants_sigmas_commitment.merkle_tree.cap.clone();
+        let circuit_digest =
+            Self::circuit_digest(&constants_sigmas_cap, &self.domain_separator, degree_bits);
+
+        let prover_only = ProverOnlyCircuitData {
+            generators: self.generators,
+            generator_indices_by_watches: self.generator_indices_by_watches,
+            constants_sigmas_commitment,
+            sigmas: transpose_poly_values(sigma_vecs),
+            subgroup,
+            public_inputs: self.public_inputs,
+            representative_map: self.representative_map,
+            fft_root_table: Some(fft_root_table),
+            circuit_digest,
+            lookup_rows: self.lookup_rows,
+            lut_to_lookups: self.lut_to_lookups,
+        };
+
+        let verifier_only = VerifierOnlyCircuitData {
+            constants_sigmas_cap,
+            circuit_digest,
+            circuit_version: self.circuit_version,
+        };
+
+        timing.print();
+        CircuitData {
+            prover_only,
+            verifier_only,
+            common: self.common,
+        }
+    }
+
+    /// Computes the commitment to the constant and sigma polynomials, yielding the same
+    /// [`VerifierCircuitData`] as [`CircuitBuilder::build_verifier`]. Only the Merkle cap of the
+    /// commitment is kept, so the low-degree extensions and Merkle tree the prover needs are never
+    /// stored.
+    pub fn commit_verifier(self) -> VerifierCircuitData<F, C, D> {
+        let mut timing = TimingTree::new("preprocess verifier", Level::Trace);
+
+        let degree_bits = self.degree_bits();
+        let sigma_vecs = self.sigma_vecs(&F::two_adic_subgroup(degree_bits), &mut timing);
+
+        let constants_sigmas_cap = PolynomialBatch::<F, C, D>::cap_from_values(
+            [self.constants, sigma_vecs].concat(),
+            self.common.config.fri_config.rate_bits,
+            PlonkOracle::CONSTANTS_SIGMAS.blinding,
+            self.common
+                .fri_params
+                .oracle_cap_height(PlonkOracle::CONSTANTS_SIGMAS.index),
+            &mut timing,
+            None,
+        );
+        let circuit_digest =
+            Self::circuit_digest(&constants_sigmas_cap, &self.domain_separator, degree_bits);
+
+        timing.print();
+        VerifierCircuitData {
+            verifier_only: VerifierOnlyCircuitData {
+                constants_sigmas_cap,
+                circuit_digest,
+                circuit_version: self.circuit_version,
+            },
+            common: self.common,
+        }
+    }
+
+    fn sigma_vecs(&self, subgroup: &[F], timing: &mut TimingTree) -> Vec<PolynomialValues<F>> {
+        timed!(
+            timing,
+            "generate sigma polynomials",
+            self.wire_partition
+                .get_sigma_polys(self.degree_bits(), &self.common.k_is, subgroup)
+        )
+    }
+
+    pub(crate) fn circuit_digest(
+        constants_sigmas_cap: &MerkleCap<F, C::Hasher>,
+        domain_separator: &[F],
+        degree_bits: usize,
+    ) -> <C::Hasher as Hasher<F>>::Hash {
+        let domain_separator_digest = C::Hasher::hash_pad(domain_separator);
+        // TODO: This should also include an encoding of gate constraints.
+        let circuit_digest_parts = [
+            constants_sigmas_cap.flatten(),
+            domain_separator_digest.to_vec(),
+            vec![
+                F::from_canonical_usize(degree_bits),
+                /* Add other circuit data here */
+            ],
+        ];
+        C::Hasher::hash_no_pad(&circuit_digest_parts.concat())
+    }
 }
 
 /// Circuit data required by the prover, but not the verifier.
@@ -388,6 +1006,27 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         let mut buffer = Buffer::new(bytes);
         buffer.read_prover_only_circuit_data(generator_serializer, common_data)
     }
+
+    /// Like [`Self::to_bytes`], but in the aligned format, see [`aligned`](crate::util::serialization::aligned).
+    pub fn to_aligned_bytes(
+        &self,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<Vec<u8>> {
+        let mut writer = AlignedWriter::new();
+        writer.write_prover_only_circuit_data(self, generator_serializer, common_data)?;
+        Ok(writer.into_bytes())
+    }
+
+    /// Reads data written by [`Self::to_aligned_bytes`].
+    pub fn from_aligned_bytes(
+        bytes: &[u8],
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<Self> {
+        let mut buffer = AlignedBuffer::new(bytes)?;
+        buffer.read_prover_only_circuit_data(generator_serializer, common_data)
+    }
 }
 
 /// Circuit data required by the verifier, but not the prover.
@@ -398,9 +1037,32 @@ pub struct VerifierOnlyCircuitData<C: GenericConfig<D>, const D: usize> {
     /// A digest of the "circuit" (i.e. the instance, minus public inputs), which can be used to
     /// seed Fiat-Shamir.
     pub circuit_digest: <<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
+    /// The semantic version of the circuit, set with [`CircuitBuilder::set_circuit_version`].
+    pub circuit_version: String,
 }
 
+/// The version of the format of verifier data. It is bumped whenever the serialization of
+/// [`VerifierOnlyCircuitData`] or the data covered by
+/// [`VerifierOnlyCircuitData::verifier_data_hash`] changes, so that verifiers with different
+/// formats never share a key.
+pub const VERIFIER_DATA_FORMAT_VERSION: u64 = 1;
+
 impl<C: GenericConfig<D>, const D: usize> VerifierOnlyCircuitData<C, D> {
+    /// Returns a stable key for this verifier, e.g. to maintain a registry of deployed verifiers.
+    /// It covers the [`VERIFIER_DATA_FORMAT_VERSION`], the circuit fingerprint, which includes the
+    /// circuit digest and the configuration, and the circuit version.
+    pub fn verifier_data_hash(&self, common: &CommonCircuitData<C::F, D>) -> HashOut<C::F> {
+        let mut challenger = Challenger::<C::F, C::Hasher>::new();
+        challenger.observe_element(C::F::from_canonical_u64(VERIFIER_DATA_FORMAT_VERSION));
+        challenger.observe_elements(
+            &common
+                .circuit_fingerprint::<C>(&self.circuit_digest)
+                .elements,
+        );
+        observe_string(&mut challenger, &self.circuit_version);
+        challenger.get_hash()
+    }
+
     pub fn to_bytes(&self) -> IoResult<Vec<u8>> {
         let mut buffer = Vec::new();
         buffer.write_verifier_only_circuit_data(self)?;
@@ -451,6 +1113,27 @@ pub struct CommonCircuitData<F: RichField + Extendable<D>, const D: usize> {
 
     /// The stored lookup tables.
     pub luts: Vec<LookupTable>,
+
+    /// The custom arguments used in this circuit.
+    pub custom_arguments: Vec<CustomArgumentRef<F, D>>,
+}
+
+/// Observes the length of `s` followed by its bytes, packed by 7 so that each chunk is canonical.
+fn observe_string<F: RichField, H: Hasher<F>>(challenger: &mut Challenger<F, H>, s: &str) {
+    challenger.observe_element(F::from_canonical_usize(s.len()));
+    challenger.observe_elements(
+        &s.as_bytes()
+            .chunks(7)
+            .map(|chunk| {
+                F::from_canonical_u64(
+                    chunk
+                        .iter()
+                        .rev()
+                        .fold(0, |acc, &byte| (acc << 8) | byte as u64),
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
@@ -472,6 +1155,260 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         self.fri_params.degree_bits
     }
 
+    /// Checks that the FRI parameters agree with the zero-knowledge mode of the config, which
+    /// together determine the blinding of the proof and the salting of its Merkle leaves.
+    pub fn check_zk_mode(&self) -> Result<()> {
+        ensure!(
+            self.fri_params.hiding == self.config.zero_knowledge,
+            "FRI hiding is {} under {:?}",
+            self.fri_params.hiding,
+            self.config.zk_mode()
+        );
+        Ok(())
+    }
+
+    /// Computes a digest of the circuit with the given `circuit_digest`, meant to detect any
+    /// change to a deployed circuit.
+    ///
+    /// On top of the `circuit_digest`, which commits to the constants and wiring (through the
+    /// constants and sigmas cap), the domain separator and the degree, this covers the circuit
+    /// configuration, the gates and the custom arguments used by the circuit. Each gate contributes its ID along with
+    /// evaluations of its constraints at a pseudo-random point, so that a change to the
+    /// constraints of a gate is detected even if its ID is unchanged.
+    ///
+    /// Gates are encoded in their canonical order, so the fingerprint does not depend on the order
+    /// in which gate types were first used. It does however depend on the placement of gates and
+    /// constants in the trace, as any change there results in a different circuit.
+    pub fn circuit_fingerprint<C: GenericConfig<D, F = F>>(
+        &self,
+        circuit_digest: &<C::Hasher as Hasher<F>>::Hash,
+    ) -> HashOut<F> {
+        let mut challenger = Challenger::<F, C::Hasher>::new();
+        challenger.observe_elements(&circuit_digest.to_vec());
+
+        let config = &self.config;
+        let fri_config = &config.fri_config;
+        challenger.observe_elements(
+            &[
+                config.num_wires,
+                config.num_routed_wires,
+                config.num_constants,
+                config.use_base_arithmetic_gate as usize,
+                config.security_bits,
+                config.num_challenges,
+                config.zero_knowledge as usize,
+                config.max_quotient_degree_factor,
+                fri_config.rate_bits,
+                fri_config.cap_height,
+                fri_config.proof_of_work_bits as usize,
+                fri_config.num_query_rounds,
+                self.fri_params.hiding as usize,
+                self.fri_params.degree_bits,
+                self.quotient_degree_factor,
+                self.num_gate_constraints,
+                self.num_constants,
+                self.num_public_inputs,
+                self.num_partial_products,
+                self.num_lookup_polys,
+                self.num_lookup_selectors,
+            ]
+            .map(F::from_canonical_usize),
+        );
+        challenger.observe_elements(
+            &self
+                .fri_params
+                .reduction_arity_bits
+                .iter()
+                .map(|&b| F::from_canonical_usize(b))
+                .collect::<Vec<_>>(),
+        );
+        challenger.observe_elements(
+            &self
+                .fri_params
+                .oracle_cap_heights
+                .iter()
+                .map(|&h| F::from_canonical_usize(h))
+                .collect::<Vec<_>>(),
+        );
+        challenger.observe_elements(&self.k_is);
+        challenger.observe_elements(
+            &self
+                .selectors_info
+                .selector_indices
+                .iter()
+                .copied()
+                .chain(
+                    self.selectors_info
+                        .groups
+                        .iter()
+                        .flat_map(|g| [g.start, g.end]),
+                )
+                .map(F::from_canonical_usize)
+                .collect::<Vec<_>>(),
+        );
+        for lut in &self.luts {
+            challenger.observe_element(F::from_canonical_usize(lut.len()));
+            challenger.observe_elements(
+                &lut.iter()
+                    .flat_map(|&(input, output)| [input, output])
+                    .map(F::from_canonical_u16)
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        for gate in &self.gates {
+            observe_string(&mut challenger, &gate.0.id());
+            challenger.observe_elements(
+                &[gate.0.degree(), gate.0.num_constraints()].map(F::from_canonical_usize),
+            );
+
+            // Evaluate the gate constraints at a point derived from the transcript so far.
+            let local_constants =
+                challenger.get_n_extension_challenges::<D>(gate.0.num_constants());
+            let local_wires = challenger.get_n_extension_challenges::<D>(config.num_wires);
+            let public_inputs_hash = challenger.get_hash();
+            let constraints = gate.0.eval_unfiltered(EvaluationVars {
+                local_constants: &local_constants,
+                local_wires: &local_wires,
+                public_inputs_hash: &public_inputs_hash,
+            });
+            challenger.observe_extension_elements::<D>(&constraints);
+        }
+
+        for argument in &self.custom_arguments {
+            observe_string(&mut challenger, &argument.0.id());
+            challenger.observe_elements(
+                &[
+                    argument.0.num_challenges(),
+                    argument.0.num_polys(),
+                    argument.0.num_constraints(),
+                    argument.0.degree(),
+                ]
+                .map(F::from_canonical_usize),
+            );
+        }
+
+        challenger.get_hash()
+    }
+
+    /// Computes the size in bytes of a proof for this circuit, as serialized by
+    /// [`ProofWithPublicInputs::to_bytes`]. This is exact for uncompressed proofs, whose shape is
+    /// fully determined by the circuit data.
+    pub fn estimate_proof_size<C: GenericConfig<D, F = F>>(&self) -> usize {
+        // Field elements and lengths are serialized as 8 bytes, Merkle proof lengths as 1 byte.
+        const FIELD_SIZE: usize = 8;
+        let ext_size = D * FIELD_SIZE;
+        let hash_size = C::Hasher::HASH_SIZE;
+        let params = &self.fri_params;
+        let cap_size = |cap_height: usize| (1 << cap_height) * hash_size;
+        let merkle_proof_size =
+            |tree_bits: usize, cap_height: usize| 1 + (tree_bits - cap_height) * hash_size;
+        let commit_phase_cap_height = params.config.cap_height;
+
+        // The `Z`s, lookup and custom argument polynomials are also opened at `g * zeta`.
+        let num_openings = self.num_preprocessed_polys()
+            + self.config.num_wires
+            + self.num_zs_partial_products_polys()
+            + self.num_quotient_polys()
+            + self.config.num_challenges
+            + 2 * (self.num_all_lookup_polys() + self.num_custom_argument_polys());
+
+        let initial_trees_proof_size = self
+            .fri_oracles()
+            .iter()
+            .enumerate()
+            .map(|(i, oracle)| {
+                (oracle.num_polys + salt_size(oracle.blinding && params.hiding)) * FIELD_SIZE
+                    + merkle_proof_size(params.lde_bits(), params.oracle_cap_height(i))
+            })
+            .sum::<usize>();
+        let mut codeword_bits = params.lde_bits();
+        let steps_size = params
+            .reduction_arity_bits
+            .iter()
+            .map(|&arity_bits| {
+                codeword_bits -= arity_bits;
+                (1 << arity_bits) * ext_size
+                    + merkle_proof_size(codeword_bits, commit_phase_cap_height)
+            })
+            .sum::<usize>();
+        let fri_proof_size = params.reduction_arity_bits.len() * cap_size(commit_phase_cap_height)
+            + params.config.num_query_rounds * (initial_trees_proof_size + steps_size)
+            + params.final_poly_len() * ext_size
+            + FIELD_SIZE;
+
+        // The wires, `Z`s and quotient caps, the openings, the FRI proof and the public inputs.
+        [
+            PlonkOracle::WIRES,
+            PlonkOracle::ZS_PARTIAL_PRODUCTS,
+            PlonkOracle::QUOTIENT,
+        ]
+        .iter()
+        .map(|oracle| cap_size(params.oracle_cap_height(oracle.index)))
+        .sum::<usize>()
+            + num_openings * ext_size
+            + fri_proof_size
+            + FIELD_SIZE * (1 + self.num_public_inputs)
+    }
+
+    /// Estimates the work done by the prover to generate a proof for this circuit once the witness
+    /// is generated, along with the memory it needs, without running it. See
+    /// [`ProverWorkEstimate`].
+    pub fn estimate_prover_work<C: GenericConfig<D, F = F>>(&self) -> ProverWorkEstimate {
+        let params = &self.fri_params;
+        let degree_bits = self.degree_bits();
+        let lde_bits = params.lde_bits();
+        let quotient_degree_bits = log2_ceil(self.quotient_degree_factor);
+        let field_size = size_of::<F>();
+
+        let mut work = ProverWorkEstimate::default();
+        let mut memory = 0;
+        for (index, oracle) in self.fri_oracles().iter().enumerate() {
+            let num_polys = oracle.num_polys;
+            let leaf_len = num_polys + salt_size(oracle.blinding && params.hiding);
+            // The preprocessed oracle is part of the prover data, so it only takes up memory.
+            if index != PlonkOracle::CONSTANTS_SIGMAS.index {
+                if index == PlonkOracle::QUOTIENT.index {
+                    // The quotient polynomials are interpolated from their values on a coset.
+                    work.add_ffts(
+                        self.config.num_challenges,
+                        degree_bits + quotient_degree_bits,
+                    );
+                } else {
+                    work.add_ffts(num_polys, degree_bits);
+                }
+                work.add_ffts(num_polys, lde_bits);
+            }
+            memory += num_polys * self.degree() * field_size
+                + work.add_merkle_tree::<F, C::Hasher>(
+                    lde_bits,
+                    leaf_len,
+                    params.oracle_cap_height(index),
+                );
+        }
+
+        // FRI starts from the LDE of the combined polynomial, in the extension field, then commits
+        // to each folded codeword.
+        work.add_ffts(D, lde_bits);
+        // Both its coefficients and values are kept.
+        memory += (2 * D * field_size) << lde_bits;
+        let mut codeword_bits = lde_bits;
+        for &arity_bits in &params.reduction_arity_bits {
+            memory += work.add_merkle_tree::<F, C::Hasher>(
+                codeword_bits - arity_bits,
+                D << arity_bits,
+                params.config.cap_height,
+            );
+            codeword_bits -= arity_bits;
+            work.add_ffts(D, codeword_bits);
+        }
+        // The expected number of attempts to find the proof-of-work witness.
+        work.num_hash_permutations += 1 << params.config.proof_of_work_bits;
+
+        work.peak_memory_bytes = memory;
+        work
+    }
+
     pub const fn degree(&self) -> usize {
         1 << self.degree_bits()
     }
@@ -517,8 +1454,15 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
     }
 
     /// Range of lookup polynomials in the `zs_partial_products_lookup_commitment`.
-    pub const fn lookup_range(&self) -> RangeFrom<usize> {
-        self.num_zs_partial_products_polys()..
+    pub const fn lookup_range(&self) -> Range<usize> {
+        self.num_zs_partial_products_polys()
+            ..self.num_zs_partial_products_polys() + self.num_all_lookup_polys()
+    }
+
+    /// Range of custom argument polynomials in the `zs_partial_products_lookup_commitment`.
+    pub fn custom_argument_range(&self) -> Range<usize> {
+        let start = self.lookup_range().end;
+        start..start + self.num_custom_argument_polys()
     }
 
     /// Range of lookup polynomials needed for evaluation at `g * zeta`.
@@ -528,25 +1472,16 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
     }
 
     pub(crate) fn get_fri_instance(&self, zeta: F::Extension) -> FriInstanceInfo<F, D> {
+        let mut instance = FriInstanceInfo::new(self.fri_oracles());
+
         // All polynomials are opened at zeta.
-        let zeta_batch = FriBatchInfo {
-            point: zeta,
-            polynomials: self.fri_all_polys(),
-        };
+        instance.open_at(zeta, self.fri_all_polys());
 
         // The Z polynomials are also opened at g * zeta.
         let g = F::Extension::primitive_root_of_unity(self.degree_bits());
-        let zeta_next = g * zeta;
-        let zeta_next_batch = FriBatchInfo {
-            point: zeta_next,
-            polynomials: self.fri_next_batch_polys(),
-        };
+        instance.open_at(g * zeta, self.fri_next_batch_polys());
 
-        let openings = vec![zeta_batch, zeta_next_batch];
-        FriInstanceInfo {
-            oracles: self.fri_oracles(),
-            batches: openings,
-        }
+        instance
     }
 
     pub(crate) fn get_fri_instance_target(
@@ -554,25 +1489,17 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         builder: &mut CircuitBuilder<F, D>,
         zeta: ExtensionTarget<D>,
     ) -> FriInstanceInfoTarget<D> {
+        let mut instance = FriInstanceInfoTarget::new(self.fri_oracles());
+
         // All polynomials are opened at zeta.
-        let zeta_batch = FriBatchInfoTarget {
-            point: zeta,
-            polynomials: self.fri_all_polys(),
-        };
+        instance.open_at(zeta, self.fri_all_polys());
 
         // The Z polynomials are also opened at g * zeta.
         let g = F::primitive_root_of_unity(self.degree_bits());
         let zeta_next = builder.mul_const_extension(g, zeta);
-        let zeta_next_batch = FriBatchInfoTarget {
-            point: zeta_next,
-            polynomials: self.fri_next_batch_polys(),
-        };
+        instance.open_at(zeta_next, self.fri_next_batch_polys());
 
-        let openings = vec![zeta_batch, zeta_next_batch];
-        FriInstanceInfoTarget {
-            oracles: self.fri_oracles(),
-            batches: openings,
-        }
+        instance
     }
 
     fn fri_oracles(&self) -> Vec<FriOracleInfo> {
@@ -586,7 +1513,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
                 blinding: PlonkOracle::WIRES.blinding,
             },
             FriOracleInfo {
-                num_polys: self.num_zs_partial_products_polys() + self.num_all_lookup_polys(),
+                num_polys: self.num_zs_partial_products_polys()
+                    + self.num_all_lookup_polys()
+                    + self.num_custom_argument_polys(),
                 blinding: PlonkOracle::ZS_PARTIAL_PRODUCTS.blinding,
             },
             FriOracleInfo {
@@ -627,13 +1556,32 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
     pub(crate) const fn num_all_lookup_polys(&self) -> usize {
         self.config.num_challenges * self.num_lookup_polys
     }
+
+    /// Returns the total number of custom argument polynomials.
+    pub(crate) fn num_custom_argument_polys(&self) -> usize {
+        self.custom_arguments.iter().map(|a| a.0.num_polys()).sum()
+    }
+
+    /// Returns the total number of challenges drawn for custom arguments.
+    pub(crate) fn num_custom_argument_challenges(&self) -> usize {
+        self.custom_arguments
+            .iter()
+            .map(|a| a.0.num_challenges())
+            .sum()
+    }
+
     fn fri_zs_polys(&self) -> Vec<FriPolynomialInfo> {
         FriPolynomialInfo::from_range(PlonkOracle::ZS_PARTIAL_PRODUCTS.index, self.zs_range())
     }
 
     /// Returns polynomials that require evaluation at `zeta` and `g * zeta`.
     fn fri_next_batch_polys(&self) -> Vec<FriPolynomialInfo> {
-        [self.fri_zs_polys(), self.fri_lookup_polys()].concat()
+        [
+            self.fri_zs_polys(),
+            self.fri_lookup_polys(),
+            self.fri_custom_argument_polys(),
+        ]
+        .concat()
     }
 
     fn fri_quotient_polys(&self) -> Vec<FriPolynomialInfo> {
@@ -642,10 +1590,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
 
     /// Returns the information for lookup polynomials, i.e. the index within the oracle and the indices of the polynomials within the commitment.
     fn fri_lookup_polys(&self) -> Vec<FriPolynomialInfo> {
+        FriPolynomialInfo::from_range(PlonkOracle::ZS_PARTIAL_PRODUCTS.index, self.lookup_range())
+    }
+
+    /// Returns the information for custom argument polynomials.
+    fn fri_custom_argument_polys(&self) -> Vec<FriPolynomialInfo> {
         FriPolynomialInfo::from_range(
             PlonkOracle::ZS_PARTIAL_PRODUCTS.index,
-            self.num_zs_partial_products_polys()
-                ..self.num_zs_partial_products_polys() + self.num_all_lookup_polys(),
+            self.custom_argument_range(),
         )
     }
     pub(crate) const fn num_quotient_polys(&self) -> usize {
@@ -659,11 +1611,54 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
             self.fri_zs_partial_products_polys(),
             self.fri_quotient_polys(),
             self.fri_lookup_polys(),
+            self.fri_custom_argument_polys(),
         ]
         .concat()
     }
 }
 
+/// An estimate of the work done by the prover, see [`CommonCircuitData::estimate_prover_work`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProverWorkEstimate {
+    /// The number of FFTs and inverse FFTs, where an FFT over the extension field counts as `D`
+    /// FFTs over the base field.
+    pub num_ffts: usize,
+    /// The number of butterflies of these FFTs, i.e. `n log2(n) / 2` for an FFT of size `n`.
+    pub fft_butterflies: usize,
+    /// The number of hash permutations, to build Merkle trees and (on average) to find the
+    /// proof-of-work witness.
+    pub num_hash_permutations: usize,
+    /// The memory taken by the polynomials and Merkle trees of all oracles, including the
+    /// preprocessed one, when they are all alive during FRI.
+    pub peak_memory_bytes: usize,
+}
+
+impl ProverWorkEstimate {
+    fn add_ffts(&mut self, count: usize, size_bits: usize) {
+        self.num_ffts += count;
+        self.fft_butterflies += count * ((size_bits << size_bits) / 2);
+    }
+
+    /// Adds the hashing of a Merkle tree, and returns the size in bytes of its leaves and digests.
+    fn add_merkle_tree<F: RichField, H: Hasher<F>>(
+        &mut self,
+        tree_bits: usize,
+        leaf_len: usize,
+        cap_height: usize,
+    ) -> usize {
+        let num_leaves = 1 << tree_bits;
+        let num_digests = num_leaves - (1 << cap_height);
+        // Leaves which fit in a digest aren't hashed, see `Hasher::hash_or_noop`.
+        let leaf_permutations = if leaf_len * 8 <= H::HASH_SIZE {
+            0
+        } else {
+            leaf_len.div_ceil(H::Permutation::RATE)
+        };
+        self.num_hash_permutations += num_leaves * leaf_permutations + num_digests;
+        num_leaves * leaf_len * size_of::<F>() + 2 * num_digests * H::HASH_SIZE
+    }
+}
+
 /// The `Target` version of `VerifierCircuitData`, for use inside recursive circuits. Note that this
 /// is intentionally missing certain fields, such as `CircuitConfig`, because we support only a
 /// limited form of dynamic inner circuits. We can't practically make things like the wire count
@@ -676,3 +1671,745 @@ pub struct VerifierCircuitTarget {
     /// seed Fiat-Shamir.
     pub circuit_digest: HashOutTarget,
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::gates::noop::NoopGate;
+    use crate::iop::challenger::TranscriptEvent;
+    use crate::iop::witness::{Witness, WitnessWrite};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::util::serialization::DefaultGateSerializer;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Builds a circuit computing `x * y + c`.
+    fn circuit(c: u64) -> (CircuitBuilder<F, D>, Target, Target) {
+        circuit_with_config(CircuitConfig::standard_recursion_config(), c)
+    }
+
+    fn circuit_with_config(
+        config: CircuitConfig,
+        c: u64,
+    ) -> (CircuitBuilder<F, D>, Target, Target) {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.add_virtual_public_input();
+        let c = builder.constant(F::from_canonical_u64(c));
+        let z = builder.mul_add(x, y, c);
+        builder.register_public_input(z);
+        (builder, x, y)
+    }
+
+    #[test]
+    fn test_circuit_fingerprint() {
+        let fingerprint = circuit(3).0.build::<C>().circuit_fingerprint();
+
+        // Building the same circuit again yields the same fingerprint.
+        let data = circuit(3).0.build::<C>();
+        assert_eq!(data.circuit_fingerprint(), fingerprint);
+        assert_eq!(data.verifier_data().circuit_fingerprint(), fingerprint);
+        assert_eq!(
+            circuit(3)
+                .0
+                .build_deterministic::<C>()
+                .circuit_fingerprint(),
+            fingerprint
+        );
+
+        // Changing a constant changes the fingerprint.
+        assert_ne!(circuit(4).0.build::<C>().circuit_fingerprint(), fingerprint);
+
+        // Changing the configuration changes the fingerprint.
+        let (mut builder, _, _) = circuit(3);
+        builder.config.security_bits += 1;
+        assert_ne!(builder.build::<C>().circuit_fingerprint(), fingerprint);
+
+        // Changing the wiring changes the fingerprint.
+        let (mut builder, x, y) = circuit(3);
+        builder.connect(x, y);
+        assert_ne!(builder.build::<C>().circuit_fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn test_verifier_data_hash() -> Result<()> {
+        let versioned = |version: &str| {
+            let (mut builder, _, _) = circuit(3);
+            builder.set_circuit_version(version);
+            builder.build::<C>()
+        };
+        let data = versioned("1.0.0");
+        let hash = data.verifier_data_hash();
+        assert_eq!(data.verifier_data().verifier_data_hash(), hash);
+        assert_eq!(versioned("1.0.0").verifier_data_hash(), hash);
+
+        // The key survives a round trip of the verifier data through its serialization.
+        let bytes = data.verifier_only.to_bytes().unwrap();
+        let verifier_only = VerifierOnlyCircuitData::<C, D>::from_bytes(bytes).unwrap();
+        assert_eq!(verifier_only.circuit_version, "1.0.0");
+        assert_eq!(verifier_only.verifier_data_hash(&data.common), hash);
+
+        // Changing the version, the circuit or its configuration changes the key.
+        assert_ne!(versioned("1.0.1").verifier_data_hash(), hash);
+        assert_ne!(circuit(3).0.build::<C>().verifier_data_hash(), hash);
+        let (mut builder, _, _) = circuit(3);
+        builder.set_circuit_version("1.0.0");
+        builder.config.security_bits += 1;
+        assert_ne!(builder.build::<C>().verifier_data_hash(), hash);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_circuit_size() {
+        let config = CircuitConfig::standard_recursion_config();
+        assert!(config.check_circuit_size::<F>(26).is_ok());
+        assert!(config.check_circuit_size::<F>(27).is_err());
+
+        let config = CircuitConfig {
+            large_circuit: true,
+            ..config
+        };
+        assert!(config.check_circuit_size::<F>(28).is_ok());
+        // With a rate of 1/8, the LDE of a circuit with 2^30 rows exceeds the two-adic subgroup.
+        assert!(config.check_circuit_size::<F>(29).is_ok());
+        assert!(config.check_circuit_size::<F>(30).is_err());
+    }
+
+    #[test]
+    fn test_layout_and_commit() -> Result<()> {
+        let (builder, x, y) = circuit(3);
+        let layout = builder.layout::<C>();
+        assert_eq!(layout.gate_instances.len(), layout.common.degree());
+        assert_eq!(layout.constants.len(), layout.common.num_constants);
+        assert_eq!(layout.public_inputs().len(), 3);
+
+        // Committing to the layout yields the same circuit as a regular build.
+        let data = layout.commit();
+        assert_eq!(
+            data.circuit_fingerprint(),
+            circuit(3).0.build::<C>().circuit_fingerprint()
+        );
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_commit_with_cache() -> Result<()> {
+        let mut cache = LdeCache::new();
+        let data = circuit(3).0.layout::<C>().commit_with_cache(&mut cache);
+        assert_eq!(
+            data.circuit_fingerprint(),
+            circuit(3).0.build::<C>().circuit_fingerprint()
+        );
+        assert_eq!(cache.num_trees(), 1);
+
+        // Rebuilding the circuit reuses the commitment to its constant and sigma polynomials.
+        let (builder, x, y) = circuit(3);
+        let rebuilt = builder.layout::<C>().commit_with_cache(&mut cache);
+        assert_eq!(cache.num_trees(), 1);
+        assert_eq!(
+            rebuilt.prover_only.constants_sigmas_commitment,
+            data.prover_only.constants_sigmas_commitment
+        );
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        let proof = rebuilt.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_build_verifier() -> Result<()> {
+        let verifier_data = circuit(3).0.build_verifier::<C>();
+        let (builder, x, y) = circuit(3);
+        let data = builder.build::<C>();
+        assert_eq!(verifier_data.verifier_only, data.verifier_only);
+        assert_eq!(verifier_data.common, data.common);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        verifier_data.verify(data.prove(pw)?)
+    }
+
+    #[test]
+    fn test_build_deterministic() -> Result<()> {
+        let (builder, x, y) = circuit(3);
+        let data = builder.build_deterministic::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        let proof = data.prove(pw.clone())?;
+        assert_eq!(proof.public_inputs[2], F::from_canonical_u64(38));
+        assert_eq!(data.prove(pw)?, proof);
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_incremental_witness() -> Result<()> {
+        let (mut builder, x, y) = circuit(3);
+        // A chain of squarings of `x`, which doesn't depend on `y`.
+        let mut x_power = x;
+        for _ in 0..8 {
+            x_power = builder.square(x_power);
+        }
+        builder.register_public_input(x_power);
+        let data = builder.build::<C>();
+
+        let inputs = |x_value: u64, y_value: u64| -> Result<PartialWitness<F>> {
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(x_value))?;
+            pw.set_target(y, F::from_canonical_u64(y_value))?;
+            Ok(pw)
+        };
+
+        let mut witness = data.generate_incremental_witness(inputs(2, 5)?)?;
+        let proof = data.prove_incremental(&witness)?;
+        data.verify(proof.clone())?;
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        let num_rerun = witness.update(pw)?;
+        assert!(num_rerun < data.prover_only.generators.len());
+
+        let updated_proof = data.prove_incremental(&witness)?;
+        assert_eq!(
+            updated_proof.public_inputs[2],
+            F::from_canonical_u64(2 * 7 + 3)
+        );
+        assert_eq!(proof.public_inputs[3], updated_proof.public_inputs[3]);
+        data.verify(updated_proof)?;
+
+        // Random values drawn by the generators differ, but everything else matches.
+        let fresh = generate_partial_witness(inputs(2, 7)?, &data.prover_only, &data.common)?;
+        let public_inputs = &data.prover_only.public_inputs;
+        assert_eq!(
+            witness.witness().get_targets(public_inputs),
+            fresh.get_targets(public_inputs)
+        );
+
+        // Updating to the same values reruns no generators but the sources.
+        let mut pw = PartialWitness::new();
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        assert_eq!(witness.update(pw)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zk_mode_blinds_proofs() -> Result<()> {
+        let (builder, x, y) = circuit_with_config(CircuitConfig::standard_recursion_zk_config(), 3);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        let proof = data.prove(pw.clone())?.proof;
+        let other_proof = data.prove(pw)?.proof;
+
+        // Each proof of the same witness commits to and opens differently blinded polynomials.
+        assert_ne!(proof.wires_cap, other_proof.wires_cap);
+        assert_ne!(
+            proof.plonk_zs_partial_products_cap,
+            other_proof.plonk_zs_partial_products_cap
+        );
+        assert_ne!(proof.quotient_polys_cap, other_proof.quotient_polys_cap);
+        assert_ne!(proof.openings.wires, other_proof.openings.wires);
+        assert_ne!(proof.openings.plonk_zs, other_proof.openings.plonk_zs);
+
+        // Only the leaves of the preprocessed oracle are opened without a salt.
+        let leaf_lens = [
+            data.common.num_preprocessed_polys(),
+            data.common.config.num_wires + SALT_SIZE,
+            data.common.num_zs_partial_products_polys() + SALT_SIZE,
+            data.common.num_quotient_polys() + SALT_SIZE,
+        ];
+        for round in &proof.opening_proof.query_round_proofs {
+            let evals_proofs = &round.initial_trees_proof.evals_proofs;
+            assert_eq!(evals_proofs.len(), leaf_lens.len());
+            for ((leaf, _), leaf_len) in evals_proofs.iter().zip(leaf_lens) {
+                assert_eq!(leaf.len(), leaf_len);
+            }
+        }
+
+        // Without zero-knowledge, the witness determines the wires commitment.
+        let (builder, x, y) = circuit(3);
+        let data = builder.build_deterministic::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        assert_eq!(
+            data.prove(pw.clone())?.proof.wires_cap,
+            data.prove(pw)?.proof.wires_cap
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zk_mode_mismatch() -> Result<()> {
+        let prove_circuit = |config| -> Result<_> {
+            let (builder, x, y) = circuit_with_config(config, 3);
+            let data = builder.build::<C>();
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(5))?;
+            pw.set_target(y, F::from_canonical_u64(7))?;
+            let proof = data.prove(pw.clone())?;
+            Ok((data, pw, proof))
+        };
+        let (zk_data, zk_pw, zk_proof) =
+            prove_circuit(CircuitConfig::standard_recursion_zk_config())?;
+        let (data, _, proof) = prove_circuit(CircuitConfig::standard_recursion_config())?;
+
+        // Common data whose config disagrees with its FRI parameters is rejected by both sides.
+        let mut inconsistent = zk_data.common.clone();
+        inconsistent.config.zero_knowledge = false;
+        assert!(inconsistent.check_zk_mode().is_err());
+        assert!(verify(zk_proof.clone(), &zk_data.verifier_only, &inconsistent).is_err());
+        assert!(prove::<F, C, D>(
+            &zk_data.prover_only,
+            &inconsistent,
+            zk_pw,
+            &mut TimingTree::default(),
+        )
+        .is_err());
+
+        // A proof without salted leaves doesn't pass as zero-knowledge, and conversely.
+        let mut claims_zk = data.common.clone();
+        claims_zk.config.zero_knowledge = true;
+        claims_zk.fri_params.hiding = true;
+        assert!(verify(proof.clone(), &data.verifier_only, &claims_zk).is_err());
+        let mut claims_no_zk = zk_data.common.clone();
+        claims_no_zk.config.zero_knowledge = false;
+        claims_no_zk.fri_params.hiding = false;
+        assert!(verify(zk_proof.clone(), &zk_data.verifier_only, &claims_no_zk).is_err());
+
+        data.verify(proof)?;
+        zk_data.verify(zk_proof)
+    }
+
+    #[test]
+    fn test_prove_with_transcript() -> Result<()> {
+        let (builder, x, y) = circuit(3);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        let (proof, transcript) = data.prove_with_transcript(pw)?;
+
+        // The verifier replays exactly the transcript of the prover.
+        assert_eq!(data.replay_transcript(&proof)?, transcript);
+        assert_eq!(
+            transcript
+                .phases
+                .iter()
+                .map(|phase| phase.label)
+                .collect::<Vec<_>>(),
+            [
+                "instance",
+                "wires",
+                "partial products",
+                "quotient",
+                "openings",
+                "fri commit phase",
+                "fri final polynomial",
+                "fri proof of work",
+                "fri queries",
+            ]
+        );
+
+        // A proof tampered with after the commit phase fails verification, and the transcripts
+        // first diverge on the tampered message.
+        let mut tampered = proof.clone();
+        tampered.proof.opening_proof.final_poly.coeffs[0] += <F as Extendable<D>>::Extension::ONE;
+        assert!(data.verify(tampered.clone()).is_err());
+        let divergence = transcript
+            .first_divergence(&data.replay_transcript(&tampered)?)
+            .unwrap();
+        assert!(matches!(
+            divergence.left,
+            Some(("fri final polynomial", TranscriptEvent::Observed(_)))
+        ));
+        assert!(divergence
+            .to_string()
+            .contains("in phase `fri final polynomial`"));
+
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_prove_with_hooks() -> Result<()> {
+        use std::sync::Mutex;
+
+        use crate::plonk::prover::{ProverPhase, ProvingCancelled};
+
+        let (builder, x, y) = circuit(3);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+
+        let phases = Mutex::new(Vec::new());
+        let progress = Mutex::new(Vec::new());
+        let hooks = ProverHooks::new()
+            .with_on_phase_start(|phase| phases.lock().unwrap().push(phase))
+            .with_on_progress(|done, total| progress.lock().unwrap().push((done, total)));
+        let proof = data.prove_with_hooks(pw.clone(), &hooks)?;
+        assert_eq!(*phases.lock().unwrap(), ProverPhase::ALL);
+        let total = ProverPhase::ALL.len();
+        assert_eq!(
+            *progress.lock().unwrap(),
+            (1..=total).map(|done| (done, total)).collect::<Vec<_>>()
+        );
+        data.verify(proof)?;
+
+        // Cancel once the quotient has been computed.
+        let phases = Mutex::new(Vec::new());
+        let hooks = ProverHooks::new()
+            .with_on_phase_start(|phase| phases.lock().unwrap().push(phase))
+            .with_should_cancel(|| {
+                phases
+                    .lock()
+                    .unwrap()
+                    .contains(&ProverPhase::ComputeQuotient)
+            });
+        let err = data.prove_with_hooks(pw, &hooks).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ProvingCancelled>(),
+            Some(&ProvingCancelled {
+                phase: ProverPhase::CommitQuotient
+            })
+        );
+        assert_eq!(*phases.lock().unwrap(), ProverPhase::ALL[..4]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_with_wire_shards() -> Result<()> {
+        use crate::plonk::prover::{
+            commit_wires_shard, prove_with_wire_shards, start_wire_sharding,
+        };
+
+        let (builder, x, y) = circuit(3);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+
+        let mut timing = TimingTree::default();
+        let job = start_wire_sharding(&data.prover_only, &data.common, pw, &mut timing)?;
+        // The same commitment, computed on a single machine.
+        let wires_commitment = PolynomialBatch::<F, C, D>::from_coeffs(
+            job.wire_polynomials.clone(),
+            data.common.config.fri_config.rate_bits,
+            data.common.config.zk_mode().salts(PlonkOracle::WIRES),
+            data.common
+                .fri_params
+                .oracle_cap_height(PlonkOracle::WIRES.index),
+            &mut timing,
+            None,
+        );
+        let num_shards = 4;
+        let shards = (0..num_shards)
+            .map(|shard| {
+                commit_wires_shard::<F, C, D>(
+                    &data.common,
+                    &job.wire_polynomials,
+                    shard,
+                    num_shards,
+                )
+            })
+            .collect();
+        let sharded_proof = prove_with_wire_shards(
+            &data.prover_only,
+            &data.common,
+            job,
+            shards,
+            &mut timing,
+            &ProverHooks::default(),
+        )?;
+        assert_eq!(
+            sharded_proof.proof.wires_cap,
+            wires_commitment.merkle_tree.cap
+        );
+        data.verify(sharded_proof)
+    }
+
+    #[test]
+    fn test_resume_proof() -> Result<()> {
+        use std::sync::Mutex;
+
+        use crate::plonk::prover::ProverPhase;
+
+        let (builder, x, y) = circuit(3);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+
+        let checkpoints = Mutex::new(Vec::new());
+        let hooks = ProverHooks::new().with_on_checkpoint(|phase, bytes| {
+            checkpoints.lock().unwrap().push((phase, bytes.to_vec()));
+            Ok(())
+        });
+        let proof = data.prove_with_hooks(pw, &hooks)?;
+        drop(hooks);
+        let checkpoints = checkpoints.into_inner().unwrap();
+        assert_eq!(
+            checkpoints
+                .iter()
+                .map(|(phase, _)| *phase)
+                .collect::<Vec<_>>(),
+            [
+                ProverPhase::CommitWires,
+                ProverPhase::CommitPartialProducts,
+                ProverPhase::CommitQuotient
+            ]
+        );
+
+        for (phase, bytes) in &checkpoints {
+            let checkpoint = ProverCheckpoint::<F, C, D>::from_bytes(bytes).unwrap();
+            assert_eq!(checkpoint.to_bytes(), *bytes);
+
+            // Only the remaining phases run.
+            let phases = Mutex::new(Vec::new());
+            let hooks =
+                ProverHooks::new().with_on_phase_start(|phase| phases.lock().unwrap().push(phase));
+            let resumed = data.resume_proof(bytes, &hooks)?;
+            assert_eq!(
+                *phases.lock().unwrap(),
+                ProverPhase::ALL[phase.index() + 1..]
+            );
+            assert_eq!(resumed.public_inputs, proof.public_inputs);
+            assert_eq!(resumed.proof.wires_cap, proof.proof.wires_cap);
+            if *phase == ProverPhase::CommitQuotient {
+                assert_eq!(
+                    resumed.proof.quotient_polys_cap,
+                    proof.proof.quotient_polys_cap
+                );
+            }
+            data.verify(resumed)?;
+        }
+        data.verify(proof)?;
+
+        // Checkpoints can't be resumed for another circuit, nor when truncated.
+        let other = circuit(4).0.build::<C>();
+        let bytes = &checkpoints[0].1;
+        assert!(other.resume_proof(bytes, &ProverHooks::new()).is_err());
+        assert!(data
+            .resume_proof(&bytes[..bytes.len() - 1], &ProverHooks::new())
+            .is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_prove_with_num_threads() -> Result<()> {
+        use std::sync::Mutex;
+
+        use plonky2_maybe_rayon::rayon;
+
+        let (builder, x, y) = circuit(3);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+
+        for num_threads in [1, 3] {
+            let pool_sizes = Mutex::new(Vec::new());
+            let hooks = ProverHooks::new().with_on_phase_start(|_| {
+                pool_sizes
+                    .lock()
+                    .unwrap()
+                    .push(rayon::current_num_threads())
+            });
+            let options = ProverOptions::default().with_num_threads(num_threads);
+            let proof = data.prove_with_options(pw.clone(), &hooks, &options)?;
+            assert!(pool_sizes.lock().unwrap().iter().all(|&n| n == num_threads));
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "rand_chacha")]
+    #[test]
+    fn test_prove_deterministic() -> Result<()> {
+        let (builder, x, y) = circuit_with_config(CircuitConfig::standard_recursion_zk_config(), 3);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+
+        let hooks = ProverHooks::default();
+        let prove = |seed| {
+            let options = ProverOptions::default()
+                .with_num_threads(4)
+                .with_deterministic(seed);
+            data.prove_with_options(pw.clone(), &hooks, &options)
+        };
+        let proof = prove([1; 32])?;
+        assert_eq!(prove([1; 32])?.to_bytes(), proof.to_bytes());
+        assert_ne!(prove([2; 32])?.to_bytes(), proof.to_bytes());
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_estimate_proof_size() -> Result<()> {
+        for config in [
+            CircuitConfig::standard_recursion_config(),
+            CircuitConfig::standard_recursion_zk_config(),
+            CircuitConfig::high_rate_config(),
+        ] {
+            let (builder, x, y) = circuit_with_config(config, 3);
+            let data = builder.build::<C>();
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(5))?;
+            pw.set_target(y, F::from_canonical_u64(7))?;
+            let proof = data.prove(pw)?;
+            assert_eq!(
+                data.common.estimate_proof_size::<C>(),
+                proof.to_bytes().len()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_optimize_cap_heights() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let common = circuit_with_config(config.clone(), 3).0.build::<C>().common;
+
+        let (builder, x, y) = circuit_with_config(
+            CircuitConfig {
+                optimize_cap_heights: true,
+                ..config
+            },
+            3,
+        );
+        let data = builder.build::<C>();
+        let fri_params = &data.common.fri_params;
+        assert_eq!(
+            fri_params.oracle_cap_height(PlonkOracle::CONSTANTS_SIGMAS.index),
+            fri_params.config.cap_height
+        );
+        assert_eq!(
+            fri_params.oracle_cap_height(PlonkOracle::WIRES.index),
+            fri_params.optimal_oracle_cap_height()
+        );
+        assert_ne!(
+            data.circuit_fingerprint(),
+            circuit(3).0.build::<C>().circuit_fingerprint()
+        );
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        let proof = data.prove(pw)?;
+        data.verify(proof.clone())?;
+        let proof_size = data.common.estimate_proof_size::<C>();
+        assert_eq!(proof_size, proof.to_bytes().len());
+        assert!(proof_size < common.estimate_proof_size::<C>());
+
+        // The flag isn't serialized, but the chosen heights are, so deserialized verifier data
+        // still accepts the proof.
+        let bytes = data
+            .verifier_data()
+            .to_bytes(&DefaultGateSerializer)
+            .unwrap();
+        let verifier_data =
+            VerifierCircuitData::<F, C, D>::from_bytes(bytes, &DefaultGateSerializer).unwrap();
+        assert!(!verifier_data.common.config.optimize_cap_heights);
+        assert_eq!(verifier_data.common.fri_params, data.common.fri_params);
+        verifier_data.verify(proof)
+    }
+
+    #[test]
+    fn test_estimate_prover_work() {
+        let common = circuit(3).0.build::<C>().common;
+        let work = common.estimate_prover_work::<C>();
+        // Each of the wires, `Z`s and quotient oracles needs an IFFT and an LDE per polynomial.
+        assert!(work.num_ffts > 2 * common.config.num_wires);
+        assert!(work.num_hash_permutations > 1 << common.config.fri_config.proof_of_work_bits);
+
+        // Zero-knowledge salts the leaves and adds blinding gates, which makes proving costlier.
+        let zk_common = circuit_with_config(CircuitConfig::standard_recursion_zk_config(), 3)
+            .0
+            .build::<C>()
+            .common;
+        let zk_work = zk_common.estimate_prover_work::<C>();
+        assert!(zk_work.fft_butterflies > work.fft_butterflies);
+        assert!(zk_work.num_hash_permutations > work.num_hash_permutations);
+        assert!(zk_work.peak_memory_bytes > work.peak_memory_bytes);
+    }
+
+    #[test]
+    fn test_auto_tune() -> Result<()> {
+        for degree_bits in [2, 10, 13, 16, 20, 24] {
+            for objective in [TuningObjective::ProofSize, TuningObjective::ProverTime] {
+                let config = CircuitConfig::auto_tune(1 << degree_bits, objective);
+                let fri_config = &config.fri_config;
+                assert!(
+                    fri_config.rate_bits * fri_config.num_query_rounds
+                        + fri_config.proof_of_work_bits as usize
+                        >= config.security_bits
+                );
+                assert!(fri_config.cap_height <= degree_bits);
+            }
+        }
+
+        // Optimizing for proof size yields smaller proofs than the standard config.
+        let proof_size = |config| -> Result<usize> {
+            let (builder, x, y) = circuit_with_config(config, 3);
+            let data = builder.build::<C>();
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(5))?;
+            pw.set_target(y, F::from_canonical_u64(7))?;
+            let proof = data.prove(pw)?;
+            let size = proof.to_bytes().len();
+            data.verify(proof)?;
+            Ok(size)
+        };
+        let num_gates = 1 << 3;
+        assert!(
+            proof_size(CircuitConfig::auto_tune(
+                num_gates,
+                TuningObjective::ProofSize
+            ))? < proof_size(CircuitConfig::auto_tune(
+                num_gates,
+                TuningObjective::ProverTime
+            ))?
+        );
+
+        // Small circuits grind less when optimizing for prover time.
+        let standard = CircuitConfig::standard_recursion_config();
+        let fri_config = |degree_bits: usize| {
+            CircuitConfig::auto_tune(1 << degree_bits, TuningObjective::ProverTime).fri_config
+        };
+        assert!(fri_config(3).proof_of_work_bits < standard.fri_config.proof_of_work_bits);
+        assert_eq!(fri_config(20), standard.fri_config);
+
+        // The number of routed wires follows the copy constraints, whatever gates they involve.
+        let config = CircuitConfig::auto_tune(num_gates, TuningObjective::ProofSize);
+        let common = circuit_with_config(config.clone(), 3).0.build::<C>().common;
+        assert!(common.config.num_routed_wires < standard.num_routed_wires);
+        let (mut builder, x, y) = circuit_with_config(config, 3);
+        let column = standard.num_routed_wires - 1;
+        let row = builder.add_gate(NoopGate, vec![]);
+        builder.connect(x, Target::wire(row, column));
+        let data = builder.build::<C>();
+        assert_eq!(data.common.config.num_routed_wires, column + 1);
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        data.verify(data.prove(pw)?)
+    }
+}
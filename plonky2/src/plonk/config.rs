@@ -4,7 +4,9 @@
 //! counterpart [`AlgebraicHasher`] for in-circuit hashing. It also
 //! provides concrete configurations, one fully recursive leveraging
 //! the Poseidon hash function both internally and natively, and one
-//! mixing Poseidon internally and truncated Keccak externally.
+//! mixing Poseidon internally and truncated Keccak externally. A Poseidon
+//! configuration over the quintic extension is available for users wanting a
+//! larger challenge field.
 
 #[cfg(not(feature = "std"))]
 use alloc::{vec, vec::Vec};
@@ -14,6 +16,7 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::field::extension::quadratic::QuadraticExtension;
+use crate::field::extension::quintic::QuinticExtension;
 use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::goldilocks_field::GoldilocksField;
 use crate::hash::hash_types::{HashOut, RichField};
@@ -29,6 +32,14 @@ pub trait GenericHashOut<F: RichField>:
     fn to_bytes(&self) -> Vec<u8>;
     fn from_bytes(bytes: &[u8]) -> Self;
 
+    /// Like [`Self::from_bytes`], but rejects any byte string other than the canonical encoding
+    /// returned by [`Self::to_bytes`], e.g. when decoding hashes received from another system.
+    fn try_from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let hash = Self::from_bytes(bytes);
+        anyhow::ensure!(hash.to_bytes() == bytes, "Non-canonical hash encoding");
+        Ok(hash)
+    }
+
     fn to_vec(&self) -> Vec<F>;
 }
 
@@ -74,6 +85,38 @@ pub trait Hasher<F: RichField>: Sized + Copy + Debug + Eq + PartialEq {
     }
 
     fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash;
+
+    /// Compresses consecutive pairs of digests of `inputs`, whose length must be even, with
+    /// `two_to_one`. Hashers may override this to compress several pairs at once.
+    fn two_to_one_batch(inputs: &[Self::Hash]) -> Vec<Self::Hash> {
+        assert!(
+            inputs.len().is_multiple_of(2),
+            "Cannot compress an odd number of digests"
+        );
+        inputs
+            .chunks_exact(2)
+            .map(|pair| Self::two_to_one(pair[0], pair[1]))
+            .collect()
+    }
+
+    /// Encodes a hash with `HASH_SIZE` bytes. Hashers whose digests are truncated, and thus
+    /// smaller than `Self::Hash`, only encode the elements they use.
+    fn hash_to_bytes(hash: &Self::Hash) -> Vec<u8> {
+        let mut bytes = hash.to_bytes();
+        bytes.truncate(Self::HASH_SIZE);
+        bytes
+    }
+
+    /// Decodes a hash encoded by [`Self::hash_to_bytes`], rejecting non-canonical encodings.
+    fn hash_from_bytes(bytes: &[u8]) -> anyhow::Result<Self::Hash> {
+        anyhow::ensure!(
+            bytes.len() == Self::HASH_SIZE,
+            "Expected a hash of {} bytes, got {} bytes",
+            Self::HASH_SIZE,
+            bytes.len()
+        );
+        Self::Hash::try_from_bytes(bytes)
+    }
 }
 
 /// Trait for algebraic hash functions, built from a permutation using the sponge construction.
@@ -89,6 +132,22 @@ pub trait AlgebraicHasher<F: RichField>: Hasher<F, Hash = HashOut<F>> {
     ) -> Self::AlgebraicPermutation
     where
         F: RichField + Extendable<D>;
+
+    /// Circuit to apply the permutation, overwrite the first elements of the state with `block`,
+    /// then apply the permutation again. Hashers may override this to use a gate evaluating both
+    /// permutations at once.
+    fn permute_absorb_permute<const D: usize>(
+        inputs: Self::AlgebraicPermutation,
+        block: &[Target],
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> Self::AlgebraicPermutation
+    where
+        F: RichField + Extendable<D>,
+    {
+        let mut state = builder.permute::<Self>(inputs);
+        state.set_from_slice(block, 0);
+        builder.permute::<Self>(state)
+    }
 }
 
 /// Generic configuration trait.
@@ -115,6 +174,18 @@ impl GenericConfig<2> for PoseidonGoldilocksConfig {
     type InnerHasher = PoseidonHash;
 }
 
+/// Configuration using Poseidon over the Goldilocks field, with challenges drawn from the quintic
+/// extension rather than the quadratic one. This trades larger proofs and recursive verifiers for
+/// a ~320-bit challenge space.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Serialize)]
+pub struct PoseidonGoldilocksQuinticConfig;
+impl GenericConfig<5> for PoseidonGoldilocksQuinticConfig {
+    type F = GoldilocksField;
+    type FE = QuinticExtension<Self::F>;
+    type Hasher = PoseidonHash;
+    type InnerHasher = PoseidonHash;
+}
+
 /// Configuration using truncated Keccak over the Goldilocks field.
 #[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
 pub struct KeccakGoldilocksConfig;
@@ -0,0 +1,389 @@
+//! Custom arguments, allowing user-defined gadgets to take part in the interactive protocol.
+//!
+//! Gates can only constrain the values of a single row of the witness, which was committed to
+//! before any challenge was drawn. Arguments such as lookups or permutation arguments instead
+//! need to commit to additional polynomials computed from verifier challenges, and to constrain
+//! consecutive values of these polynomials.
+//!
+//! A [`CustomArgument`] registered with [`CircuitBuilder::add_custom_argument`] takes part in the
+//! second round of the protocol, alongside the permutation and lookup arguments:
+//!
+//! 1. once the prover has committed to the wires, the challenger draws
+//!    [`CustomArgument::num_challenges`] challenges for each argument;
+//! 2. the prover computes the argument's polynomials with [`CustomArgument::generate_polys`] and
+//!    commits to them along with the permutation `Z`s and partial products;
+//! 3. the argument's constraints, which may involve the wires, the argument's polynomials at the
+//!    current and next rows, the challenges and the first Lagrange polynomial, are added to the
+//!    vanishing polynomial, both natively and in the recursive verifier.
+//!
+//! Only this single extra round is supported: all the challenges of an argument are drawn at once,
+//! right after the wires commitment, and its polynomials can't be committed to before further
+//! challenges are drawn. Arguments needing several interactive rounds, e.g. a challenge depending
+//! on a commitment to polynomials which themselves depend on an earlier challenge, can't be
+//! expressed as a [`CustomArgument`].
+//!
+//! [`CircuitBuilder::add_custom_argument`]: crate::plonk::circuit_builder::CircuitBuilder::add_custom_argument
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::any::Any;
+use core::fmt::{Debug, Error, Formatter};
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+use serde::{Serialize, Serializer};
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::polynomial::PolynomialValues;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::Target;
+use crate::iop::witness::MatrixWitness;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::util::serialization::{Buffer, IoResult};
+
+/// A user-defined argument, committing to additional polynomials after receiving challenges from
+/// the verifier. See the [module documentation](self).
+pub trait CustomArgument<F: RichField + Extendable<D>, const D: usize>:
+    'static + Send + Sync + Debug
+{
+    /// Defines a unique identifier for this custom argument.
+    fn id(&self) -> String;
+
+    /// Serializes this custom argument to the targeted byte buffer, with the provided
+    /// [`CommonCircuitData`].
+    fn serialize(&self, dst: &mut Vec<u8>, common_data: &CommonCircuitData<F, D>) -> IoResult<()>;
+
+    /// Deserializes the bytes in the provided buffer into this custom argument, given some
+    /// [`CommonCircuitData`].
+    fn deserialize(src: &mut Buffer, common_data: &CommonCircuitData<F, D>) -> IoResult<Self>
+    where
+        Self: Sized;
+
+    /// The number of challenges drawn for this argument after the wires commitment. Each
+    /// challenge is a base field element; arguments needing more soundness should use several.
+    /// All of them are drawn in the same round, see the [module documentation](self).
+    fn num_challenges(&self) -> usize;
+
+    /// The number of polynomials this argument commits to.
+    fn num_polys(&self) -> usize;
+
+    /// The number of constraints defined by this argument.
+    fn num_constraints(&self) -> usize;
+
+    /// The maximum degree among this argument's constraints, counting the wires, the argument's
+    /// polynomials and the first Lagrange polynomial as degree 1. It must not exceed
+    /// `max_quotient_degree_factor + 1`.
+    fn degree(&self) -> usize;
+
+    /// Computes the values of this argument's polynomials over the subgroup `H`, given the full
+    /// witness and the challenges drawn for this argument.
+    fn generate_polys(
+        &self,
+        witness: &MatrixWitness<F>,
+        challenges: &[F],
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Vec<PolynomialValues<F>>;
+
+    /// Evaluates the constraints of this argument, which must vanish on `H`.
+    fn eval(&self, vars: CustomArgumentVars<F::Extension>) -> Vec<F::Extension>;
+
+    /// Like `eval`, but specialized for points in the base field.
+    ///
+    /// By default, this just calls `eval`, which treats the point as an extension field element.
+    /// This isn't very efficient.
+    fn eval_base(&self, vars: CustomArgumentVars<F>) -> Vec<F> {
+        let lift = |values: &[F]| {
+            values
+                .iter()
+                .map(|&v| F::Extension::from_basefield(v))
+                .collect::<Vec<_>>()
+        };
+        let (local_wires, local_polys, next_polys, challenges) = (
+            lift(vars.local_wires),
+            lift(vars.local_polys),
+            lift(vars.next_polys),
+            lift(vars.challenges),
+        );
+        self.eval(CustomArgumentVars {
+            local_wires: &local_wires,
+            local_polys: &local_polys,
+            next_polys: &next_polys,
+            challenges: &challenges,
+            l_0: F::Extension::from_basefield(vars.l_0),
+        })
+        .into_iter()
+        .map(|value| {
+            // Each value should be in the base field, i.e. only the degree-zero part should be
+            // nonzero.
+            debug_assert!(F::Extension::is_in_basefield(&value));
+            value.to_basefield_array()[0]
+        })
+        .collect()
+    }
+
+    /// Defines the recursive constraints of this argument.
+    ///
+    /// **Note**: The order of the recursive constraints output by this method should match exactly
+    /// the order of the constraints obtained by the non-recursive [`CustomArgument::eval`] method.
+    fn eval_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: CustomArgumentVarsTarget<D>,
+    ) -> Vec<ExtensionTarget<D>>;
+}
+
+/// The values available to a [`CustomArgument`]'s constraints at a given point.
+#[derive(Debug, Copy, Clone)]
+pub struct CustomArgumentVars<'a, T> {
+    /// The wire values at the current point.
+    pub local_wires: &'a [T],
+    /// The values of the argument's polynomials at the current point.
+    pub local_polys: &'a [T],
+    /// The values of the argument's polynomials at the next point, i.e. `g` times the current one.
+    pub next_polys: &'a [T],
+    /// The challenges drawn for this argument.
+    pub challenges: &'a [T],
+    /// The first Lagrange polynomial evaluated at the current point.
+    pub l_0: T,
+}
+
+/// The `Target` version of [`CustomArgumentVars`], used in the recursive verifier.
+#[derive(Debug, Copy, Clone)]
+pub struct CustomArgumentVarsTarget<'a, const D: usize> {
+    pub local_wires: &'a [ExtensionTarget<D>],
+    pub local_polys: &'a [ExtensionTarget<D>],
+    pub next_polys: &'a [ExtensionTarget<D>],
+    pub challenges: &'a [Target],
+    pub l_0: ExtensionTarget<D>,
+}
+
+/// A wrapper trait over a `CustomArgument`, to allow for serialization.
+pub trait AnyCustomArgument<F: RichField + Extendable<D>, const D: usize>:
+    CustomArgument<F, D>
+{
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: CustomArgument<F, D>, F: RichField + Extendable<D>, const D: usize> AnyCustomArgument<F, D>
+    for T
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A wrapper around an `Arc<AnyCustomArgument>` which implements `PartialEq` and `Eq` based on
+/// argument IDs.
+#[derive(Clone)]
+pub struct CustomArgumentRef<F: RichField + Extendable<D>, const D: usize>(
+    pub Arc<dyn AnyCustomArgument<F, D>>,
+);
+
+impl<F: RichField + Extendable<D>, const D: usize> CustomArgumentRef<F, D> {
+    pub fn new<A: CustomArgument<F, D>>(argument: A) -> CustomArgumentRef<F, D> {
+        CustomArgumentRef(Arc::new(argument))
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PartialEq for CustomArgumentRef<F, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.id() == other.0.id()
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Eq for CustomArgumentRef<F, D> {}
+
+impl<F: RichField + Extendable<D>, const D: usize> Debug for CustomArgumentRef<F, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.0.id())
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Serialize for CustomArgumentRef<F, D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, vec, vec::Vec};
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::ProofWithPublicInputs;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Checks that the values of two advice columns are permutations of each other, with a grand
+    /// product `Z(g x) (b(x) + gamma) = Z(x) (a(x) + gamma)` starting at `Z(1) = 1`.
+    #[derive(Debug)]
+    struct MultisetEqualityArgument {
+        column_a: usize,
+        column_b: usize,
+    }
+
+    impl<F: RichField + Extendable<D>, const D: usize> CustomArgument<F, D>
+        for MultisetEqualityArgument
+    {
+        fn id(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn serialize(
+            &self,
+            dst: &mut Vec<u8>,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<()> {
+            use crate::util::serialization::Write;
+            dst.write_usize(self.column_a)?;
+            dst.write_usize(self.column_b)
+        }
+
+        fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+            use crate::util::serialization::Read;
+            Ok(Self {
+                column_a: src.read_usize()?,
+                column_b: src.read_usize()?,
+            })
+        }
+
+        fn num_challenges(&self) -> usize {
+            1
+        }
+
+        fn num_polys(&self) -> usize {
+            1
+        }
+
+        fn num_constraints(&self) -> usize {
+            2
+        }
+
+        fn degree(&self) -> usize {
+            2
+        }
+
+        fn generate_polys(
+            &self,
+            witness: &MatrixWitness<F>,
+            challenges: &[F],
+            common_data: &CommonCircuitData<F, D>,
+        ) -> Vec<PolynomialValues<F>> {
+            let gamma = challenges[0];
+            let mut z = Vec::with_capacity(common_data.degree());
+            let mut acc = F::ONE;
+            for row in 0..common_data.degree() {
+                z.push(acc);
+                acc *= (witness.get_wire(row, self.column_a) + gamma)
+                    / (witness.get_wire(row, self.column_b) + gamma);
+            }
+            vec![PolynomialValues::new(z)]
+        }
+
+        fn eval(&self, vars: CustomArgumentVars<F::Extension>) -> Vec<F::Extension> {
+            let gamma = vars.challenges[0];
+            let a = vars.local_wires[self.column_a];
+            let b = vars.local_wires[self.column_b];
+            let (z, z_next) = (vars.local_polys[0], vars.next_polys[0]);
+            vec![
+                z_next * (b + gamma) - z * (a + gamma),
+                vars.l_0 * (z - F::Extension::ONE),
+            ]
+        }
+
+        fn eval_circuit(
+            &self,
+            builder: &mut CircuitBuilder<F, D>,
+            vars: CustomArgumentVarsTarget<D>,
+        ) -> Vec<ExtensionTarget<D>> {
+            let gamma = builder.convert_to_ext(vars.challenges[0]);
+            let a = builder.add_extension(vars.local_wires[self.column_a], gamma);
+            let b = builder.add_extension(vars.local_wires[self.column_b], gamma);
+            let (z, z_next) = (vars.local_polys[0], vars.next_polys[0]);
+            let z_a = builder.mul_extension(z, a);
+            let transition = builder.mul_sub_extension(z_next, b, z_a);
+            let first_row = builder.mul_sub_extension(vars.l_0, z, vars.l_0);
+            vec![transition, first_row]
+        }
+    }
+
+    fn circuit_with_argument() -> (CircuitData<F, C, D>, Target) {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        // Don't use public inputs, as hashing them would use the advice columns.
+        let x = builder.add_virtual_target();
+        let y = builder.square(x);
+        let nine = builder.constant(F::from_canonical_u64(9));
+        builder.connect(y, nine);
+        builder.add_custom_argument(MultisetEqualityArgument {
+            column_a: config.num_wires - 2,
+            column_b: config.num_wires - 1,
+        });
+        (builder.build_deterministic::<C>(), x)
+    }
+
+    /// Proves the circuit, with column `b` set to `a` reversed, plus `delta` in the first row.
+    fn prove_with_argument(
+        data: &CircuitData<F, C, D>,
+        x: Target,
+        delta: F,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let num_wires = data.common.config.num_wires;
+        let n = data.common.degree();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3))?;
+        for row in 0..n {
+            pw.set_target(
+                Target::wire(row, num_wires - 2),
+                F::from_canonical_usize(row + 1),
+            )?;
+            let b = F::from_canonical_usize(n - row);
+            pw.set_target(
+                Target::wire(row, num_wires - 1),
+                if row == 0 { b + delta } else { b },
+            )?;
+        }
+        data.prove(pw)
+    }
+
+    #[test]
+    fn test_custom_argument() -> Result<()> {
+        let (data, x) = circuit_with_argument();
+        assert_eq!(data.common.custom_arguments.len(), 1);
+
+        let proof = prove_with_argument(&data, x, F::ZERO)?;
+        data.verify(proof.clone())?;
+
+        // Verify the proof recursively.
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let proof_t = builder.add_virtual_proof_with_pis(&data.common);
+        let verifier_t = builder.constant_verifier_data(&data.verifier_only);
+        builder.verify_proof::<C>(&proof_t, &verifier_t, &data.common);
+        let recursive_data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_t, &proof)?;
+        recursive_data.verify(recursive_data.prove(pw)?)
+    }
+
+    #[test]
+    fn test_custom_argument_unsatisfied() -> Result<()> {
+        let (data, x) = circuit_with_argument();
+        // The columns are no longer permutations of each other, so the grand product doesn't wrap
+        // around to 1 and the vanishing polynomial check fails.
+        let proof = prove_with_argument(&data, x, F::ONE)?;
+        assert!(data.verify(proof).is_err());
+        Ok(())
+    }
+}
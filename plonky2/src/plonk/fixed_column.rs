@@ -0,0 +1,182 @@
+//! Application-defined preprocessed columns, e.g. large static tables such as instruction
+//! decodings or S-boxes, which are committed to once when building the circuit, along with the
+//! constant and sigma polynomials, rather than occupying witness wires in every proof.
+//!
+//! A column is added with [`CircuitBuilder::add_fixed_column`], and gates read its value at their
+//! row from their local constants, with [`FixedColumn::constant_index`]. The columns are stored
+//! after all other constant polynomials, in reverse order, so that the position of a column
+//! relative to the end of the constants doesn't depend on the rest of the circuit.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::RichField;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+/// A handle to a preprocessed column, created by [`CircuitBuilder::add_fixed_column`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct FixedColumn(pub usize);
+
+impl FixedColumn {
+    /// Returns the index of this column in the local constants passed to a gate, which hold
+    /// `num_local_constants` values in total.
+    pub const fn constant_index(self, num_local_constants: usize) -> usize {
+        num_local_constants - 1 - self.0
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a preprocessed column holding `values` in its first rows, and zeros in the others. The
+    /// circuit is padded to at least `values.len()` rows.
+    pub fn add_fixed_column(&mut self, values: Vec<F>) -> FixedColumn {
+        self.fixed_columns.push(values);
+        FixedColumn(self.fixed_columns.len() - 1)
+    }
+
+    /// Sets the value of `column` at `row`.
+    pub fn set_fixed_column_value(&mut self, column: FixedColumn, row: usize, value: F) {
+        let values = &mut self.fixed_columns[column.0];
+        if values.len() <= row {
+            values.resize(row + 1, F::ZERO);
+        }
+        values[row] = value;
+    }
+
+    pub fn fixed_column_values(&self, column: FixedColumn) -> &[F] {
+        &self.fixed_columns[column.0]
+    }
+
+    pub fn num_fixed_columns(&self) -> usize {
+        self.fixed_columns.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{format, string::String, vec, vec::Vec};
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::gates::gate::Gate;
+    use crate::iop::ext_target::ExtensionTarget;
+    use crate::iop::generator::WitnessGeneratorRef;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+    use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// A gate asserting that its wire is equal to the value of a fixed column at its row.
+    #[derive(Debug)]
+    struct FixedColumnEqualityGate {
+        column: FixedColumn,
+    }
+
+    impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for FixedColumnEqualityGate {
+        fn id(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn serialize(
+            &self,
+            dst: &mut Vec<u8>,
+            _common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<()> {
+            dst.write_usize(self.column.0)
+        }
+
+        fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+            Ok(Self {
+                column: FixedColumn(src.read_usize()?),
+            })
+        }
+
+        fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+            let index = self.column.constant_index(vars.local_constants.len());
+            vec![vars.local_wires[0] - vars.local_constants[index]]
+        }
+
+        fn eval_unfiltered_base_one(
+            &self,
+            vars: EvaluationVarsBase<F>,
+            mut yield_constr: crate::gates::util::StridedConstraintConsumer<F>,
+        ) {
+            let index = self.column.constant_index(vars.local_constants.len());
+            yield_constr.one(vars.local_wires[0] - vars.local_constants[index]);
+        }
+
+        fn eval_unfiltered_circuit(
+            &self,
+            builder: &mut CircuitBuilder<F, D>,
+            vars: EvaluationTargets<D>,
+        ) -> Vec<ExtensionTarget<D>> {
+            let index = self.column.constant_index(vars.local_constants.len());
+            vec![builder.sub_extension(vars.local_wires[0], vars.local_constants[index])]
+        }
+
+        fn generators(
+            &self,
+            _row: usize,
+            _local_constants: &[F],
+        ) -> Vec<WitnessGeneratorRef<F, D>> {
+            vec![]
+        }
+
+        fn num_wires(&self) -> usize {
+            1
+        }
+
+        fn num_constants(&self) -> usize {
+            0
+        }
+
+        fn degree(&self) -> usize {
+            1
+        }
+
+        fn num_constraints(&self) -> usize {
+            1
+        }
+    }
+
+    #[test]
+    fn test_fixed_column() -> Result<()> {
+        let table = [7, 11, 13].map(F::from_canonical_u64);
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        // An unused column, to check that columns are told apart.
+        builder.add_fixed_column(vec![F::ONE; 100]);
+        let column = builder.add_fixed_column(Vec::new());
+        let inputs = builder.add_virtual_targets(table.len());
+        for (&input, &value) in inputs.iter().zip(&table) {
+            let row = builder.add_gate(FixedColumnEqualityGate { column }, Vec::new());
+            builder.set_fixed_column_value(column, row, value);
+            builder.connect(input, Target::wire(row, 0));
+        }
+        // The column is also readable past the gates, but isn't used there.
+        builder.set_fixed_column_value(column, 200, F::TWO);
+        assert_eq!(builder.num_fixed_columns(), 2);
+        assert_eq!(builder.fixed_column_values(column).len(), 201);
+        let data = builder.build::<C>();
+        assert!(data.common.degree() >= 256);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target_arr(&inputs, &table)?;
+        data.verify(data.prove(pw)?)?;
+
+        let mut pw = PartialWitness::new();
+        pw.set_target_arr(&inputs, &[table[0], table[1], table[1]])?;
+        assert!(data.prove(pw).and_then(|proof| data.verify(proof)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,372 @@
+//! A serializable intermediate representation of circuits under construction, see [`CircuitIr`].
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec, vec::Vec};
+
+use serde::{Deserialize, Serialize};
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::fri::FriParams;
+use crate::gadgets::arithmetic::BaseArithmeticOperation;
+use crate::gates::selectors::SelectorsInfo;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use crate::plonk::copy_constraint::CopyConstraint;
+use crate::util::serialization::{
+    Buffer, GateSerializer, IoError, IoResult, WitnessGeneratorSerializer,
+};
+
+/// A description of a circuit under construction, i.e. of the state of a [`CircuitBuilder`], which
+/// can be serialized with any `serde` format. External frontends can emit it to target plonky2
+/// without driving a [`CircuitBuilder`] themselves.
+///
+/// Gates and generators are stored as written by a [`GateSerializer`] and a
+/// [`WitnessGeneratorSerializer`]; with a [`GateRegistry`](crate::util::serialization::GateRegistry),
+/// a gate is its registered name followed by its parameters. These serializers only get to see
+/// the [`CircuitConfig`] of the circuit, so gates and generators whose serialization depends on
+/// the built circuit, e.g. on its lookup tables, can't be expressed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct CircuitIr<F: Field> {
+    /// The distinct gate types used by the circuit.
+    pub gate_types: Vec<Vec<u8>>,
+    /// The gate in each row of the circuit.
+    pub rows: Vec<RowIr<F>>,
+    /// The number of virtual targets allocated.
+    pub num_virtual_targets: usize,
+    pub public_inputs: Vec<Target>,
+    /// Pairs of targets which must hold the same value.
+    pub copy_constraints: Vec<(Target, Target)>,
+    /// Targets holding constant values.
+    pub constants: Vec<(Target, F)>,
+    /// The operations left unused in batched gates, which later operations can fill. Generators
+    /// of unused operations are dropped when building the circuit.
+    pub free_slots: Vec<FreeSlotIr<F>>,
+    /// Base field arithmetic operations which haven't been placed in gates yet, so that they can
+    /// be optimized when building the circuit.
+    pub arithmetic_operations: Vec<ArithmeticOperationIr<F>>,
+    /// The generators added to the circuit, besides those of its gates.
+    pub generators: Vec<Vec<u8>>,
+    pub domain_separator: Option<Vec<F>>,
+    #[serde(default)]
+    pub circuit_version: String,
+}
+
+/// A row of a [`CircuitIr`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct RowIr<F: Field> {
+    /// The index of the gate type in [`CircuitIr::gate_types`].
+    pub gate_type: usize,
+    pub constants: Vec<F>,
+}
+
+/// The first unused operation of the batched gate in `row`, to be filled by the next operation
+/// with the given parameters, see [`CircuitBuilder::find_slot`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct FreeSlotIr<F: Field> {
+    pub params: Vec<F>,
+    pub row: usize,
+    pub op: usize,
+}
+
+/// The constraint `output = const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ArithmeticOperationIr<F: Field> {
+    pub const_0: F,
+    pub const_1: F,
+    pub multiplicand_0: Target,
+    pub multiplicand_1: Target,
+    pub addend: Target,
+    pub output: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Describes the circuit built so far, see [`CircuitIr`]. Fails if the circuit uses features
+    /// which the IR can't express, i.e. lookups, custom arguments, subcircuits or cyclic
+    /// recursion, or a gate or generator unsupported by the given serializers.
+    pub fn to_ir(
+        &self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> IoResult<CircuitIr<F>> {
+        if self.get_luts_length() > 0
+            || !self.custom_arguments.is_empty()
+            || !self.subcircuit_rows.is_empty()
+            || self.verifier_data_public_input.is_some()
+        {
+            log::error!(
+                "attempted to export a circuit with lookups, custom arguments, subcircuits or \
+                 cyclic recursion to IR"
+            );
+            return Err(IoError);
+        }
+
+        let common_data = ir_common_data(&self.config);
+
+        let mut gate_types = Vec::<(String, Vec<u8>)>::new();
+        let mut rows = Vec::with_capacity(self.gate_instances.len());
+        for (row, instance) in self.gate_instances.iter().enumerate() {
+            let id = instance.gate_ref.0.id();
+            let gate_type = match gate_types.iter().position(|(gate_id, _)| *gate_id == id) {
+                Some(gate_type) => gate_type,
+                None => {
+                    let mut bytes = Vec::new();
+                    gate_serializer.write_gate(&mut bytes, &instance.gate_ref, &common_data)?;
+                    gate_types.push((id, bytes));
+                    gate_types.len() - 1
+                }
+            };
+            rows.push(RowIr {
+                gate_type,
+                constants: self.gate_constants(row).to_vec(),
+            });
+        }
+
+        let generators = self
+            .generators
+            .iter()
+            .map(|generator| {
+                let mut bytes = Vec::new();
+                generator_serializer.write_generator(&mut bytes, generator, &common_data)?;
+                Ok(bytes)
+            })
+            .collect::<IoResult<_>>()?;
+
+        let mut constants = self
+            .targets_to_constants
+            .iter()
+            .map(|(&t, &c)| (t, c))
+            .collect::<Vec<_>>();
+        constants.sort_by_key(|&(t, _)| t.index(self.config.num_wires, self.num_gates()));
+
+        let mut free_slots = self
+            .current_slots
+            .values()
+            .flat_map(|slots| &slots.current_slot)
+            .map(|(params, &(row, op))| FreeSlotIr {
+                params: params.clone(),
+                row,
+                op,
+            })
+            .collect::<Vec<_>>();
+        free_slots.sort_by_key(|slot| slot.row);
+
+        let arithmetic_operations = self
+            .pending_base_arithmetic_operations
+            .iter()
+            .map(|&(operation, output)| ArithmeticOperationIr {
+                const_0: operation.const_0,
+                const_1: operation.const_1,
+                multiplicand_0: operation.multiplicand_0,
+                multiplicand_1: operation.multiplicand_1,
+                addend: operation.addend,
+                output,
+            })
+            .collect();
+
+        Ok(CircuitIr {
+            gate_types: gate_types.into_iter().map(|(_, bytes)| bytes).collect(),
+            rows,
+            num_virtual_targets: self.virtual_target_index,
+            public_inputs: self.public_inputs.clone(),
+            copy_constraints: self
+                .copy_constraints
+                .iter()
+                .map(|&CopyConstraint { pair, .. }| pair)
+                .collect(),
+            constants,
+            free_slots,
+            arithmetic_operations,
+            generators,
+            domain_separator: self.domain_separator.clone(),
+            circuit_version: self.circuit_version.clone(),
+        })
+    }
+
+    /// Creates a builder holding the circuit described by `ir`, see [`CircuitIr`]. Panics if a
+    /// gate of `ir` doesn't fit in `config`.
+    pub fn from_ir(
+        config: CircuitConfig,
+        ir: &CircuitIr<F>,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> IoResult<Self> {
+        let common_data = ir_common_data(&config);
+        let mut builder = Self::new(config);
+
+        let gate_types = ir
+            .gate_types
+            .iter()
+            .map(|bytes| gate_serializer.read_gate(&mut Buffer::new(bytes), &common_data))
+            .collect::<IoResult<Vec<_>>>()?;
+        for row in &ir.rows {
+            let gate_ref = gate_types.get(row.gate_type).ok_or(IoError)?;
+            builder.add_gate_ref(gate_ref, row.constants.clone());
+        }
+
+        for slot in &ir.free_slots {
+            let gate_ref = builder
+                .gate_instances
+                .get(slot.row)
+                .ok_or(IoError)?
+                .gate_ref
+                .clone();
+            builder
+                .current_slots
+                .entry(gate_ref)
+                .or_default()
+                .current_slot
+                .insert(slot.params.clone(), (slot.row, slot.op));
+        }
+
+        builder.add_virtual_targets(ir.num_virtual_targets);
+        builder.register_public_inputs(&ir.public_inputs);
+        for &(x, y) in &ir.copy_constraints {
+            builder.connect(x, y);
+        }
+        for &(t, c) in &ir.constants {
+            builder.constants_to_targets.insert(c, t);
+            builder.targets_to_constants.insert(t, c);
+        }
+
+        for operation in &ir.arithmetic_operations {
+            let output = operation.output;
+            let operation = BaseArithmeticOperation {
+                const_0: operation.const_0,
+                const_1: operation.const_1,
+                multiplicand_0: operation.multiplicand_0,
+                multiplicand_1: operation.multiplicand_1,
+                addend: operation.addend,
+            };
+            if builder.config.optimize {
+                builder
+                    .pending_base_arithmetic_operations
+                    .push((operation, output));
+            } else {
+                let result = builder.add_base_arithmetic_operation(operation);
+                builder.connect(result, output);
+            }
+            builder.base_arithmetic_results.insert(operation, output);
+        }
+
+        for bytes in &ir.generators {
+            let generator =
+                generator_serializer.read_generator(&mut Buffer::new(bytes), &common_data)?;
+            builder.generators.push(generator);
+        }
+
+        builder.domain_separator = ir.domain_separator.clone();
+        builder.circuit_version = ir.circuit_version.clone();
+
+        Ok(builder)
+    }
+}
+
+/// The circuit data given to serializers when converting to and from IR, which only knows the
+/// configuration of the circuit.
+fn ir_common_data<F: RichField + Extendable<D>, const D: usize>(
+    config: &CircuitConfig,
+) -> CommonCircuitData<F, D> {
+    CommonCircuitData {
+        config: config.clone(),
+        fri_params: FriParams {
+            config: config.fri_config.clone(),
+            hiding: config.zero_knowledge,
+            degree_bits: 0,
+            reduction_arity_bits: vec![],
+            oracle_cap_heights: vec![],
+        },
+        gates: vec![],
+        selectors_info: SelectorsInfo {
+            selector_indices: vec![],
+            groups: vec![],
+        },
+        quotient_degree_factor: 0,
+        num_gate_constraints: 0,
+        num_constants: config.num_constants,
+        num_public_inputs: 0,
+        k_is: vec![],
+        num_partial_products: 0,
+        num_lookup_polys: 0,
+        num_lookup_selectors: 0,
+        luts: vec![],
+        custom_arguments: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+    use crate::util::serialization::{DefaultGeneratorSerializer, GateRegistry};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn test_ir_round_trip(config: CircuitConfig) -> Result<()> {
+        let gate_serializer = GateRegistry::<F, D>::default();
+        let generator_serializer = DefaultGeneratorSerializer::<C, D>::default();
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let [x, y] = builder.add_virtual_target_arr();
+        let z = builder.mul_add(x, y, x);
+        let z = builder.add_const(z, F::from_canonical_u64(7));
+        let quotient = builder.div(z, y);
+        let z_ext = builder.convert_to_ext(z);
+        let square = builder.square_extension(z_ext);
+        builder.register_public_inputs(&[z, quotient]);
+        builder.register_public_inputs(&square.0);
+
+        let ir = builder
+            .to_ir(&gate_serializer, &generator_serializer)
+            .unwrap();
+        let json = serde_json::to_string(&ir)?;
+        let ir: CircuitIr<F> = serde_json::from_str(&json)?;
+        let builder =
+            CircuitBuilder::from_ir(config, &ir, &gate_serializer, &generator_serializer).unwrap();
+        assert_eq!(
+            builder
+                .to_ir(&gate_serializer, &generator_serializer)
+                .unwrap(),
+            ir
+        );
+        let data = builder.build::<C>();
+
+        let (x_value, y_value) = (F::rand(), F::rand());
+        let z_value = x_value * y_value + x_value + F::from_canonical_u64(7);
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, x_value)?;
+        pw.set_target(y, y_value)?;
+        let proof = data.prove(pw)?;
+        assert_eq!(
+            proof.public_inputs[..3],
+            [z_value, z_value / y_value, z_value * z_value]
+        );
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_ir_round_trip_standard() -> Result<()> {
+        test_ir_round_trip(CircuitConfig::standard_recursion_config())
+    }
+
+    #[test]
+    fn test_ir_round_trip_optimized() -> Result<()> {
+        test_ir_round_trip(CircuitConfig {
+            optimize: true,
+            ..CircuitConfig::standard_recursion_config()
+        })
+    }
+}
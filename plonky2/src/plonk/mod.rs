@@ -7,12 +7,20 @@ pub mod circuit_builder;
 pub mod circuit_data;
 pub mod config;
 pub(crate) mod copy_constraint;
+pub mod custom_argument;
+pub mod fixed_column;
 mod get_challenges;
+pub mod ir;
+pub(crate) mod optimization;
 pub(crate) mod permutation_argument;
 pub mod plonk_common;
 pub mod proof;
 pub mod prover;
+mod subcircuit;
+#[cfg(any(feature = "gate_testing", test))]
+pub mod test_utils;
 mod validate_shape;
 pub(crate) mod vanishing_poly;
 pub mod vars;
 pub mod verifier;
+pub mod witness_check;
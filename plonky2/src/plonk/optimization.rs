@@ -0,0 +1,585 @@
+//! Circuit-level optimization pass, enabled through [`CircuitConfig::optimize`].
+//!
+//! When the optimization is enabled, base arithmetic operations are not placed in
+//! [`ArithmeticGate`](crate::gates::arithmetic_base::ArithmeticGate)s as soon as they are
+//! requested, but recorded until the circuit is built. At that point, and before any routing
+//! takes place, the recorded operations go through the following steps:
+//!
+//! - operations whose inputs are all known to be constant, either directly or through copy
+//!   constraints, are folded into a constant;
+//! - operations computing the same value from the same (up to copy constraints) inputs are
+//!   merged, their outputs being routed to a single constant or gate slot;
+//! - operations whose output is never used, i.e. neither routed, registered as a public input,
+//!   watched by a generator nor consumed by another remaining operation, are removed;
+//! - operations which would be left alone in a partially filled gate because of their constants
+//!   are rewritten, when possible, to share the gates of operations with constants `(1, 1)` or
+//!   `(1, 0)`.
+//!
+//! The remaining operations are then placed in gates as usual.
+//!
+//! [`CircuitConfig::optimize`]: crate::plonk::circuit_data::CircuitConfig::optimize
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use hashbrown::{HashMap, HashSet};
+
+use crate::field::extension::Extendable;
+use crate::field::types::{Field, Field64};
+use crate::gadgets::arithmetic::BaseArithmeticOperation;
+use crate::gates::arithmetic_base::ArithmeticGate;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::copy_constraint::CopyConstraint;
+
+/// A union-find structure over [`Target`]s, tracking which partitions are known to be constant.
+#[derive(Debug, Default)]
+struct TargetPartition<F: Field> {
+    parents: HashMap<Target, Target>,
+    /// Upper bounds on the heights of the trees rooted at each representative, missing entries
+    /// being zero.
+    ranks: HashMap<Target, usize>,
+    constants: HashMap<Target, F>,
+}
+
+impl<F: Field> TargetPartition<F> {
+    fn find(&mut self, mut target: Target) -> Target {
+        // Note: We avoid recursion here since the chains can be long, causing stack overflows.
+
+        // First, find the representative of the set containing `target`.
+        let mut root = target;
+        while let Some(&parent) = self.parents.get(&root) {
+            root = parent;
+        }
+
+        // Then, update each node in this chain to point directly to the representative.
+        while let Some(&parent) = self.parents.get(&target) {
+            if parent == root {
+                break;
+            }
+            self.parents.insert(target, root);
+            target = parent;
+        }
+
+        root
+    }
+
+    fn union(&mut self, x: Target, y: Target) {
+        let (x, y) = (self.find(x), self.find(y));
+        if x == y {
+            return;
+        }
+        // Attach the shallower tree below the deeper one, so that trees stay logarithmic.
+        let x_rank = self.ranks.get(&x).copied().unwrap_or(0);
+        let y_rank = self.ranks.get(&y).copied().unwrap_or(0);
+        let (child, root) = if x_rank < y_rank { (x, y) } else { (y, x) };
+        if x_rank == y_rank {
+            self.ranks.insert(root, x_rank + 1);
+        }
+        self.ranks.remove(&child);
+        self.parents.insert(child, root);
+        if let Some(c) = self.constants.remove(&child) {
+            // If both partitions hold different constants the circuit is unsatisfiable anyway, and
+            // proving will fail regardless of which constant we keep.
+            self.constants.entry(root).or_insert(c);
+        }
+    }
+
+    fn set_constant(&mut self, target: Target, c: F) {
+        let root = self.find(target);
+        self.constants.entry(root).or_insert(c);
+    }
+
+    fn as_constant(&mut self, target: Target) -> Option<F> {
+        let root = self.find(target);
+        self.constants.get(&root).copied()
+    }
+}
+
+/// A base arithmetic operation recorded by the builder, after constant folding and merging.
+#[derive(Debug)]
+enum PendingEntry<F: Field64> {
+    /// An operation to be placed in a gate, and its output.
+    Operation(BaseArithmeticOperation<F>, Target),
+    /// An output equal to the output of a previous operation.
+    Alias(Target, Target),
+    /// An output equal to a constant.
+    Constant(Target, F),
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Runs the optimization pass on the base arithmetic operations recorded so far, and places
+    /// the remaining ones in gates.
+    pub(crate) fn optimize_and_place_base_arithmetic(&mut self) {
+        let operations = core::mem::take(&mut self.pending_base_arithmetic_operations);
+        if operations.is_empty() {
+            return;
+        }
+
+        let mut partition = TargetPartition::default();
+        for &CopyConstraint { pair: (a, b), .. } in &self.copy_constraints {
+            partition.union(a, b);
+        }
+        for (&t, &c) in &self.targets_to_constants {
+            partition.set_constant(t, c);
+        }
+
+        // Forward pass: fold constant operations and merge duplicate ones.
+        let mut entries = Vec::with_capacity(operations.len());
+        let mut canonical_operations = HashMap::new();
+        for (operation, output) in operations {
+            let BaseArithmeticOperation {
+                const_0,
+                const_1,
+                multiplicand_0,
+                multiplicand_1,
+                addend,
+            } = operation;
+
+            let first_term = if const_0 == F::ZERO {
+                Some(F::ZERO)
+            } else {
+                match (
+                    partition.as_constant(multiplicand_0),
+                    partition.as_constant(multiplicand_1),
+                ) {
+                    (Some(x), _) | (_, Some(x)) if x == F::ZERO => Some(F::ZERO),
+                    (Some(x), Some(y)) => Some(const_0 * x * y),
+                    _ => None,
+                }
+            };
+            let second_term = if const_1 == F::ZERO {
+                Some(F::ZERO)
+            } else {
+                partition.as_constant(addend).map(|x| const_1 * x)
+            };
+            if let (Some(x), Some(y)) = (first_term, second_term) {
+                partition.set_constant(output, x + y);
+                entries.push(PendingEntry::Constant(output, x + y));
+                continue;
+            }
+
+            // Multiplication is commutative, so look the operation up with both multiplicand orders.
+            let (r0, r1) = (
+                partition.find(multiplicand_0),
+                partition.find(multiplicand_1),
+            );
+            let r2 = partition.find(addend);
+            let key = (const_0, const_1, r0, r1, r2);
+            if let Some(&previous_output) = canonical_operations
+                .get(&key)
+                .or_else(|| canonical_operations.get(&(const_0, const_1, r1, r0, r2)))
+            {
+                partition.union(output, previous_output);
+                entries.push(PendingEntry::Alias(output, previous_output));
+                continue;
+            }
+            canonical_operations.insert(key, output);
+            entries.push(PendingEntry::Operation(operation, output));
+        }
+
+        // Backward pass: drop entries whose output is not used. An entry can only depend on the
+        // outputs of entries recorded before it, so a single pass in reverse order suffices.
+        let mut used: HashSet<Target> = self
+            .copy_constraints
+            .iter()
+            .flat_map(|c| [c.pair.0, c.pair.1])
+            .chain(self.public_inputs.iter().copied())
+            .chain(self.generators.iter().flat_map(|g| g.0.watch_list()))
+            .chain(
+                self.lut_to_lookups
+                    .iter()
+                    .flatten()
+                    .flat_map(|&(looking_in, looking_out)| [looking_in, looking_out]),
+            )
+            .collect();
+        let num_entries = entries.len();
+        let num_folded = entries
+            .iter()
+            .filter(|e| matches!(e, PendingEntry::Constant(..)))
+            .count();
+        let num_merged = entries
+            .iter()
+            .filter(|e| matches!(e, PendingEntry::Alias(..)))
+            .count();
+        let mut live = Vec::with_capacity(num_entries);
+        for entry in entries.into_iter().rev() {
+            match entry {
+                PendingEntry::Operation(operation, output) if used.contains(&output) => {
+                    used.extend([
+                        operation.multiplicand_0,
+                        operation.multiplicand_1,
+                        operation.addend,
+                    ]);
+                    live.push(entry);
+                }
+                PendingEntry::Alias(output, previous_output) if used.contains(&output) => {
+                    used.insert(previous_output);
+                    live.push(entry);
+                }
+                PendingEntry::Constant(output, _) if used.contains(&output) => live.push(entry),
+                _ => (),
+            }
+        }
+
+        let num_placed = live
+            .iter()
+            .filter(|e| matches!(e, PendingEntry::Operation(..)))
+            .count();
+        log::debug!(
+            "Optimization pass: {} base arithmetic operations, {} folded, {} merged, {} unused, {} placed",
+            num_entries,
+            num_folded,
+            num_merged,
+            num_entries - num_folded - num_merged - num_placed,
+            num_placed
+        );
+
+        live.reverse();
+        let num_moved = self.pack_base_arithmetic_operations(&mut partition, &mut live);
+        log::debug!(
+            "Optimization pass: {} base arithmetic operations moved to shared rows",
+            num_moved
+        );
+
+        for entry in live {
+            match entry {
+                PendingEntry::Operation(operation, output) => {
+                    let result = self.add_base_arithmetic_operation(operation);
+                    self.connect(output, result);
+                }
+                PendingEntry::Alias(output, previous_output) => {
+                    self.connect(output, previous_output)
+                }
+                PendingEntry::Constant(output, c) => {
+                    let c = self.constant(c);
+                    self.connect(output, c);
+                }
+            }
+        }
+    }
+
+    /// Rewrites the operations which would otherwise be alone in a partially filled
+    /// `ArithmeticGate` row, because of their constants, so that they use the constants `(1, 1)`
+    /// or `(1, 0)` and share the rows of the most common operations. A group of operations is only
+    /// moved when the fraction of a row needed by the moved operations and by the constants they
+    /// introduce is less than the row they free. Returns the number of moved operations.
+    fn pack_base_arithmetic_operations(
+        &mut self,
+        partition: &mut TargetPartition<F>,
+        entries: &mut [PendingEntry<F>],
+    ) -> usize {
+        let ops_per_gate = ArithmeticGate::new_from_config(&self.config).num_ops;
+        let constants_per_gate = self.config.num_constants;
+
+        // Indices of the operations sharing the same constants, in order of first appearance.
+        let mut groups: Vec<((F, F), Vec<usize>)> = Vec::new();
+        let mut group_indices = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if let PendingEntry::Operation(operation, _) = entry {
+                let key = (operation.const_0, operation.const_1);
+                let group = *group_indices.entry(key).or_insert_with(|| {
+                    groups.push((key, Vec::new()));
+                    groups.len() - 1
+                });
+                groups[group].1.push(i);
+            }
+        }
+
+        let mut num_moved = 0;
+        for ((const_0, const_1), indices) in groups {
+            if const_0 == F::ONE && (const_1 == F::ONE || const_1 == F::ZERO) {
+                continue;
+            }
+            let remainder = indices.len() % ops_per_gate;
+            if remainder == 0 {
+                continue;
+            }
+
+            let tail = &indices[indices.len() - remainder..];
+            let Some(normalized) = tail
+                .iter()
+                .map(|&i| match &entries[i] {
+                    PendingEntry::Operation(operation, _) => {
+                        NormalizedOperation::new(partition, operation)
+                    }
+                    _ => unreachable!(),
+                })
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let new_constants = normalized
+                .iter()
+                .flat_map(NormalizedOperation::constants)
+                .filter(|c| !self.constants_to_targets.contains_key(c))
+                .collect::<HashSet<_>>();
+            if new_constants.len() * ops_per_gate + remainder * constants_per_gate
+                >= ops_per_gate * constants_per_gate
+            {
+                continue;
+            }
+
+            for (&i, normalized) in tail.iter().zip(normalized) {
+                if let PendingEntry::Operation(operation, _) = &mut entries[i] {
+                    *operation = normalized.to_operation(self);
+                }
+            }
+            num_moved += remainder;
+        }
+
+        num_moved
+    }
+}
+
+/// An operand of a [`NormalizedOperation`], which may be a constant not yet allocated a target.
+#[derive(Copy, Clone, Debug)]
+enum Operand<F: Field> {
+    Target(Target),
+    Constant(F),
+}
+
+impl<F: Field> Operand<F> {
+    fn to_target<const D: usize>(self, builder: &mut CircuitBuilder<F, D>) -> Target
+    where
+        F: RichField + Extendable<D>,
+    {
+        match self {
+            Self::Target(t) => t,
+            Self::Constant(c) => builder.constant(c),
+        }
+    }
+}
+
+/// A base arithmetic operation rewritten as `multiplicand_0 * multiplicand_1 + const_1 * addend`,
+/// with `const_1` either zero or one.
+#[derive(Copy, Clone, Debug)]
+struct NormalizedOperation<F: Field> {
+    const_1: F,
+    multiplicand_0: Operand<F>,
+    multiplicand_1: Operand<F>,
+    addend: Operand<F>,
+}
+
+impl<F: Field64> NormalizedOperation<F> {
+    /// Rewrites the given operation, by absorbing its constants into one of its multiplicands and
+    /// its addend when these are known to be constant. Returns `None` if this isn't possible.
+    fn new(
+        partition: &mut TargetPartition<F>,
+        operation: &BaseArithmeticOperation<F>,
+    ) -> Option<Self> {
+        let &BaseArithmeticOperation {
+            const_0,
+            const_1,
+            multiplicand_0,
+            multiplicand_1,
+            addend,
+        } = operation;
+
+        let (multiplicand_0, multiplicand_1) = if const_0 == F::ONE {
+            (
+                Operand::Target(multiplicand_0),
+                Operand::Target(multiplicand_1),
+            )
+        } else if const_0 == F::ZERO {
+            (Operand::Target(multiplicand_0), Operand::Constant(F::ZERO))
+        } else if let Some(c) = partition.as_constant(multiplicand_1) {
+            (
+                Operand::Target(multiplicand_0),
+                Operand::Constant(const_0 * c),
+            )
+        } else {
+            let c = partition.as_constant(multiplicand_0)?;
+            (
+                Operand::Constant(const_0 * c),
+                Operand::Target(multiplicand_1),
+            )
+        };
+
+        let (const_1, addend) = if const_1 == F::ZERO || const_1 == F::ONE {
+            (const_1, Operand::Target(addend))
+        } else {
+            let c = partition.as_constant(addend)?;
+            (F::ONE, Operand::Constant(const_1 * c))
+        };
+
+        Some(Self {
+            const_1,
+            multiplicand_0,
+            multiplicand_1,
+            addend,
+        })
+    }
+
+    /// The constants used by this operation's operands.
+    fn constants(&self) -> impl Iterator<Item = F> {
+        [self.multiplicand_0, self.multiplicand_1, self.addend]
+            .into_iter()
+            .filter_map(|operand| match operand {
+                Operand::Target(_) => None,
+                Operand::Constant(c) => Some(c),
+            })
+    }
+
+    fn to_operation<const D: usize>(
+        self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> BaseArithmeticOperation<F>
+    where
+        F: RichField + Extendable<D>,
+    {
+        BaseArithmeticOperation {
+            const_0: F::ONE,
+            const_1: self.const_1,
+            multiplicand_0: self.multiplicand_0.to_target(builder),
+            multiplicand_1: self.multiplicand_1.to_target(builder),
+            addend: self.addend.to_target(builder),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{vec, vec::Vec};
+
+    use anyhow::Result;
+
+    use crate::field::types::Field;
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::optimization::TargetPartition;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Builds a circuit with constant, duplicate and unused base arithmetic operations, and returns
+    /// the number of `ArithmeticGate`s it uses.
+    fn arithmetic_gates(optimize: bool) -> Result<usize> {
+        let config = CircuitConfig {
+            optimize,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.arithmetic(F::ONE, F::ONE, x, x, x);
+        let y_dup = builder.arithmetic(F::ONE, F::ONE, x, x, x);
+        builder.register_public_input(y);
+        builder.register_public_input(y_dup);
+
+        // Operations on targets that are only known to be constant through copy constraints.
+        let (a, b) = (builder.add_virtual_target(), builder.add_virtual_target());
+        let (two, three) = (builder.two(), builder.constant(F::from_canonical_u64(3)));
+        builder.connect(a, two);
+        builder.connect(b, three);
+        let ab = builder.mul(a, b);
+        let six = builder.constant(F::from_canonical_u64(6));
+        builder.connect(ab, six);
+
+        // Operations whose outputs are never used.
+        let mut acc = x;
+        for i in 0..50 {
+            acc = builder.arithmetic(F::from_canonical_usize(i), F::ONE, acc, x, x);
+        }
+
+        let (data, profile) = builder.build_with_profile::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(a, F::TWO)?;
+        pw.set_target(b, F::from_canonical_u64(3))?;
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs, vec![F::from_canonical_u64(30); 2]);
+        data.verify(proof)?;
+
+        Ok(profile
+            .gate_counts
+            .iter()
+            .filter(|(id, _)| id.starts_with("ArithmeticGate"))
+            .map(|(_, &n)| n)
+            .sum())
+    }
+
+    /// Builds a circuit with operations using many different constants, and returns the number of
+    /// `ArithmeticGate`s and `ConstantGate`s it uses.
+    fn arithmetic_and_constant_gates(optimize: bool) -> Result<(usize, usize)> {
+        let config = CircuitConfig {
+            optimize,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let three = builder.constant(F::from_canonical_u64(3));
+        let mut expected = Vec::new();
+        for i in 0..8 {
+            // `(i + 2) * x * 3 + y`, and `x * y + (i + 2) * 3`.
+            let c = F::from_canonical_usize(i + 2);
+            let r = builder.arithmetic(c, F::ONE, x, three, y);
+            builder.register_public_input(r);
+            expected.push(c * F::from_canonical_u64(3 * 5) + F::from_canonical_u64(7));
+            let r = builder.arithmetic(F::ONE, c, x, y, three);
+            builder.register_public_input(r);
+            expected.push(F::from_canonical_u64(5 * 7) + c * F::from_canonical_u64(3));
+        }
+
+        let (data, profile) = builder.build_with_profile::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(5))?;
+        pw.set_target(y, F::from_canonical_u64(7))?;
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs, expected);
+        data.verify(proof)?;
+
+        let count = |prefix: &str| {
+            profile
+                .gate_counts
+                .iter()
+                .filter(|(id, _)| id.starts_with(prefix))
+                .map(|(_, &n)| n)
+                .sum()
+        };
+        Ok((count("ArithmeticGate"), count("ConstantGate")))
+    }
+
+    #[test]
+    fn test_pack_arithmetic() -> Result<()> {
+        let (arithmetic, constant) = arithmetic_and_constant_gates(false)?;
+        let (packed_arithmetic, packed_constant) = arithmetic_and_constant_gates(true)?;
+        assert_eq!(packed_arithmetic, 1);
+        assert!(packed_arithmetic + packed_constant < arithmetic + constant);
+        Ok(())
+    }
+
+    #[test]
+    fn test_optimize() -> Result<()> {
+        let unoptimized = arithmetic_gates(false)?;
+        let optimized = arithmetic_gates(true)?;
+        assert_eq!(optimized, 1);
+        assert!(optimized < unoptimized);
+        Ok(())
+    }
+
+    #[test]
+    fn test_target_partition_long_chain() {
+        // Unions along a long chain of targets, which would overflow the stack with a recursive
+        // `find` and no balancing.
+        let n = 1 << 20;
+        let target = |index| Target::VirtualTarget { index };
+        let mut partition = TargetPartition::<F>::default();
+        for i in 0..n - 1 {
+            partition.union(target(i), target(i + 1));
+        }
+        partition.set_constant(target(n / 2), F::TWO);
+        assert_eq!(partition.as_constant(target(0)), Some(F::TWO));
+        assert_eq!(partition.as_constant(target(n - 1)), Some(F::TWO));
+        assert_eq!(partition.as_constant(target(n)), None);
+        assert!(partition.ranks.values().all(|&rank| rank <= 20));
+    }
+}
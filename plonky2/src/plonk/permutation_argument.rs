@@ -1,7 +1,6 @@
 #[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 
-use hashbrown::HashMap;
 use plonky2_maybe_rayon::*;
 
 use crate::field::polynomial::PolynomialValues;
@@ -88,25 +87,45 @@ impl Forest {
 
     /// Assumes `compress_paths` has already been called.
     pub fn wire_partition(&mut self) -> WirePartition {
-        let mut partition = HashMap::<_, Vec<_>>::new();
-
-        // Here we keep just the Wire targets, filtering out everything else.
+        // Link each routed wire to the next one of its partition, in row-major order, the last one
+        // looping back to the first. This is done with flat arrays rather than one `Vec` per
+        // partition, since most wires are alone in their partition and large circuits have many
+        // millions of them.
+        //
+        // `last[r]` is the last wire seen so far in the partition represented by `r`, and
+        // `neighbors[w]` temporarily points to the first wire of its partition while `w` is the
+        // last one seen.
+        let num_wires = self.degree * self.num_routed_wires;
+        let mut last = vec![usize::MAX; self.parents.len()];
+        let mut neighbors = Vec::with_capacity(num_wires);
         for row in 0..self.degree {
             for column in 0..self.num_routed_wires {
-                let w = Wire { row, column };
-                let t = Target::Wire(w);
+                let w = neighbors.len();
+                let t = Target::Wire(Wire { row, column });
                 let x_parent = self.parents[self.target_index(t)];
-                partition.entry(x_parent).or_default().push(w);
+                let prev = last[x_parent];
+                if prev == usize::MAX {
+                    neighbors.push(w);
+                } else {
+                    neighbors.push(neighbors[prev]);
+                    neighbors[prev] = w;
+                }
+                last[x_parent] = w;
             }
         }
 
-        let partition = partition.into_values().collect();
-        WirePartition { partition }
+        WirePartition {
+            neighbors,
+            num_routed_wires: self.num_routed_wires,
+        }
     }
 }
 
+#[derive(Debug)]
 pub struct WirePartition {
-    partition: Vec<Vec<Wire>>,
+    /// The next wire of each routed wire's partition, with wires indexed in row-major order.
+    neighbors: Vec<usize>,
+    num_routed_wires: usize,
 }
 
 impl WirePartition {
@@ -117,7 +136,7 @@ impl WirePartition {
         subgroup: &[F],
     ) -> Vec<PolynomialValues<F>> {
         let degree = 1 << degree_log;
-        let sigma = self.get_sigma_map(degree, k_is.len());
+        let sigma = self.get_sigma_map(degree);
 
         sigma
             .chunks(degree)
@@ -132,27 +151,55 @@ impl WirePartition {
     }
 
     /// Generates sigma in the context of Plonk, which is a map from `[kn]` to `[kn]`, where `k` is
-    /// the number of routed wires and `n` is the number of gates.
-    fn get_sigma_map(&self, degree: usize, num_routed_wires: usize) -> Vec<usize> {
-        // Find a wire's "neighbor" in the context of Plonk's "extended copy constraints" check. In
-        // other words, find the next wire in the given wire's partition. If the given wire is last in
-        // its partition, this will loop around. If the given wire has a partition all to itself, it
-        // is considered its own neighbor.
-        let mut neighbors = HashMap::with_capacity(self.partition.len());
-        for subset in &self.partition {
-            for n in 0..subset.len() {
-                neighbors.insert(subset[n], subset[(n + 1) % subset.len()]);
-            }
-        }
+    /// the number of routed wires and `n` is the number of gates. Each wire is mapped to its
+    /// "neighbor" in the context of Plonk's "extended copy constraints" check, i.e. the next wire
+    /// in its partition. A wire which has a partition all to itself is its own neighbor.
+    fn get_sigma_map(&self, degree: usize) -> Vec<usize> {
+        let num_routed_wires = self.num_routed_wires;
+        (0..num_routed_wires)
+            .flat_map(|column| (0..degree).map(move |row| row * num_routed_wires + column))
+            .map(|w| {
+                let neighbor = self.neighbors[w];
+                let (row, column) = (neighbor / num_routed_wires, neighbor % num_routed_wires);
+                column * degree + row
+            })
+            .collect()
+    }
+}
 
-        let mut sigma = Vec::with_capacity(num_routed_wires * degree);
-        for column in 0..num_routed_wires {
-            for row in 0..degree {
-                let wire = Wire { row, column };
-                let neighbor = neighbors[&wire];
-                sigma.push(neighbor.column * degree + neighbor.row);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sigma_map() {
+        let (num_wires, num_routed_wires, degree) = (3, 2, 4);
+        let mut forest = Forest::new(num_wires, num_routed_wires, degree, 1);
+        for row in 0..degree {
+            for column in 0..num_wires {
+                forest.add(Target::wire(row, column));
             }
         }
-        sigma
+        forest.add(Target::VirtualTarget { index: 0 });
+
+        // One partition of three routed wires, through a virtual target and an unrouted wire,
+        // one of two, and singletons.
+        forest.merge(Target::wire(3, 1), Target::VirtualTarget { index: 0 });
+        forest.merge(Target::VirtualTarget { index: 0 }, Target::wire(0, 0));
+        forest.merge(Target::wire(2, 2), Target::wire(3, 1));
+        forest.merge(Target::wire(1, 0), Target::wire(2, 2));
+        forest.merge(Target::wire(1, 1), Target::wire(2, 0));
+        forest.compress_paths();
+
+        let sigma = forest.wire_partition().get_sigma_map(degree);
+        let index = |row: usize, column: usize| column * degree + row;
+        let mut expected = (0..num_routed_wires * degree).collect::<Vec<_>>();
+        // Neighbors follow the row-major order of wires, looping back to the first one.
+        expected[index(0, 0)] = index(1, 0);
+        expected[index(1, 0)] = index(3, 1);
+        expected[index(3, 1)] = index(0, 0);
+        expected[index(1, 1)] = index(2, 0);
+        expected[index(2, 0)] = index(1, 1);
+        assert_eq!(sigma, expected);
     }
 }
@@ -11,7 +11,7 @@ use anyhow::ensure;
 use plonky2_maybe_rayon::*;
 use serde::{Deserialize, Serialize};
 
-use crate::field::extension::Extendable;
+use crate::field::extension::{Extendable, FieldExtension};
 use crate::fri::oracle::PolynomialBatch;
 use crate::fri::proof::{
     CompressedFriProof, FriChallenges, FriChallengesTarget, FriProof, FriProofTarget,
@@ -27,6 +27,7 @@ use crate::iop::target::Target;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::verifier::verify_with_challenges;
+use crate::util::encoding::{encode_field_elements, encode_ssz_container};
 use crate::util::serialization::{Buffer, Read, Write};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -101,12 +102,30 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         })
     }
 
+    /// The extension field element whose `D` components are the public inputs from index `start`
+    /// on, e.g. as registered by `CircuitBuilder::register_public_input_extension`.
+    pub fn public_input_extension(&self, start: usize) -> F::Extension {
+        F::Extension::from_basefield_array(core::array::from_fn(|i| self.public_inputs[start + i]))
+    }
+
     pub fn get_public_inputs_hash(
         &self,
     ) -> <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash {
         C::InnerHasher::hash_no_pad(&self.public_inputs)
     }
 
+    /// Encodes the commitments of the proof and its public inputs as the SSZ container
+    /// `{wires_cap, plonk_zs_partial_products_cap, quotient_polys_cap, public_inputs}`, e.g. for
+    /// other systems to check them against the ones of a proof verified elsewhere.
+    pub fn commitments_to_ssz(&self) -> Vec<u8> {
+        encode_ssz_container(&[
+            self.proof.wires_cap.to_bytes(),
+            self.proof.plonk_zs_partial_products_cap.to_bytes(),
+            self.proof.quotient_polys_cap.to_bytes(),
+            encode_field_elements(&self.public_inputs),
+        ])
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut buffer = Vec::new();
         buffer
@@ -166,7 +185,11 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             plonk_zs_partial_products_cap,
             quotient_polys_cap,
             openings,
-            opening_proof: opening_proof.decompress(challenges, fri_inferred_elements, params),
+            opening_proof: opening_proof.decompress(
+                &challenges.fri_challenges,
+                fri_inferred_elements,
+                params,
+            ),
         }
     }
 }
@@ -270,6 +293,9 @@ pub struct ProofChallenges<F: RichField + Extendable<D>, const D: usize> {
     /// Lookup challenges.
     pub plonk_deltas: Vec<F>,
 
+    /// Challenges drawn for the custom arguments, in order.
+    pub plonk_custom_argument_challenges: Vec<F>,
+
     /// Point at which the PLONK polynomials are opened.
     pub plonk_zeta: F::Extension,
 
@@ -281,14 +307,14 @@ pub(crate) struct ProofChallengesTarget<const D: usize> {
     pub plonk_gammas: Vec<Target>,
     pub plonk_alphas: Vec<Target>,
     pub plonk_deltas: Vec<Target>,
+    pub plonk_custom_argument_challenges: Vec<Target>,
     pub plonk_zeta: ExtensionTarget<D>,
     pub fri_challenges: FriChallengesTarget<D>,
 }
 
 /// Coset elements that can be inferred in the FRI reduction steps.
-pub(crate) struct FriInferredElements<F: RichField + Extendable<D>, const D: usize>(
-    pub Vec<F::Extension>,
-);
+#[derive(Debug)]
+pub struct FriInferredElements<F: RichField + Extendable<D>, const D: usize>(pub Vec<F::Extension>);
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ProofWithPublicInputsTarget<const D: usize> {
@@ -308,6 +334,8 @@ pub struct OpeningSet<F: RichField + Extendable<D>, const D: usize> {
     pub quotient_polys: Vec<F::Extension>,
     pub lookup_zs: Vec<F::Extension>,
     pub lookup_zs_next: Vec<F::Extension>,
+    pub custom_argument_polys: Vec<F::Extension>,
+    pub custom_argument_polys_next: Vec<F::Extension>,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
@@ -347,44 +375,36 @@ impl<F: RichField + Extendable<D>, const D: usize> OpeningSet<F, D> {
             lookup_zs: zs_partial_products_lookup_eval[common_data.lookup_range()].to_vec(),
             lookup_zs_next: zs_partial_products_lookup_next_eval[common_data.lookup_range()]
                 .to_vec(),
+            custom_argument_polys: zs_partial_products_lookup_eval
+                [common_data.custom_argument_range()]
+            .to_vec(),
+            custom_argument_polys_next: zs_partial_products_lookup_next_eval
+                [common_data.custom_argument_range()]
+            .to_vec(),
         }
     }
     pub(crate) fn to_fri_openings(&self) -> FriOpenings<F, D> {
-        let has_lookup = !self.lookup_zs.is_empty();
-        let zeta_batch = if has_lookup {
-            FriOpeningBatch {
-                values: [
-                    self.constants.as_slice(),
-                    self.plonk_sigmas.as_slice(),
-                    self.wires.as_slice(),
-                    self.plonk_zs.as_slice(),
-                    self.partial_products.as_slice(),
-                    self.quotient_polys.as_slice(),
-                    self.lookup_zs.as_slice(),
-                ]
-                .concat(),
-            }
-        } else {
-            FriOpeningBatch {
-                values: [
-                    self.constants.as_slice(),
-                    self.plonk_sigmas.as_slice(),
-                    self.wires.as_slice(),
-                    self.plonk_zs.as_slice(),
-                    self.partial_products.as_slice(),
-                    self.quotient_polys.as_slice(),
-                ]
-                .concat(),
-            }
+        // Lookup and custom argument openings are empty when the circuit has none.
+        let zeta_batch = FriOpeningBatch {
+            values: [
+                self.constants.as_slice(),
+                self.plonk_sigmas.as_slice(),
+                self.wires.as_slice(),
+                self.plonk_zs.as_slice(),
+                self.partial_products.as_slice(),
+                self.quotient_polys.as_slice(),
+                self.lookup_zs.as_slice(),
+                self.custom_argument_polys.as_slice(),
+            ]
+            .concat(),
         };
-        let zeta_next_batch = if has_lookup {
-            FriOpeningBatch {
-                values: [self.plonk_zs_next.clone(), self.lookup_zs_next.clone()].concat(),
-            }
-        } else {
-            FriOpeningBatch {
-                values: self.plonk_zs_next.clone(),
-            }
+        let zeta_next_batch = FriOpeningBatch {
+            values: [
+                self.plonk_zs_next.as_slice(),
+                self.lookup_zs_next.as_slice(),
+                self.custom_argument_polys_next.as_slice(),
+            ]
+            .concat(),
         };
         FriOpenings {
             batches: vec![zeta_batch, zeta_next_batch],
@@ -404,45 +424,33 @@ pub struct OpeningSetTarget<const D: usize> {
     pub next_lookup_zs: Vec<ExtensionTarget<D>>,
     pub partial_products: Vec<ExtensionTarget<D>>,
     pub quotient_polys: Vec<ExtensionTarget<D>>,
+    pub custom_argument_polys: Vec<ExtensionTarget<D>>,
+    pub custom_argument_polys_next: Vec<ExtensionTarget<D>>,
 }
 
 impl<const D: usize> OpeningSetTarget<D> {
     pub(crate) fn to_fri_openings(&self) -> FriOpeningsTarget<D> {
-        let has_lookup = !self.lookup_zs.is_empty();
-        let zeta_batch = if has_lookup {
-            FriOpeningBatchTarget {
-                values: [
-                    self.constants.as_slice(),
-                    self.plonk_sigmas.as_slice(),
-                    self.wires.as_slice(),
-                    self.plonk_zs.as_slice(),
-                    self.partial_products.as_slice(),
-                    self.quotient_polys.as_slice(),
-                    self.lookup_zs.as_slice(),
-                ]
-                .concat(),
-            }
-        } else {
-            FriOpeningBatchTarget {
-                values: [
-                    self.constants.as_slice(),
-                    self.plonk_sigmas.as_slice(),
-                    self.wires.as_slice(),
-                    self.plonk_zs.as_slice(),
-                    self.partial_products.as_slice(),
-                    self.quotient_polys.as_slice(),
-                ]
-                .concat(),
-            }
+        // Lookup and custom argument openings are empty when the circuit has none.
+        let zeta_batch = FriOpeningBatchTarget {
+            values: [
+                self.constants.as_slice(),
+                self.plonk_sigmas.as_slice(),
+                self.wires.as_slice(),
+                self.plonk_zs.as_slice(),
+                self.partial_products.as_slice(),
+                self.quotient_polys.as_slice(),
+                self.lookup_zs.as_slice(),
+                self.custom_argument_polys.as_slice(),
+            ]
+            .concat(),
         };
-        let zeta_next_batch = if has_lookup {
-            FriOpeningBatchTarget {
-                values: [self.plonk_zs_next.clone(), self.next_lookup_zs.clone()].concat(),
-            }
-        } else {
-            FriOpeningBatchTarget {
-                values: self.plonk_zs_next.clone(),
-            }
+        let zeta_next_batch = FriOpeningBatchTarget {
+            values: [
+                self.plonk_zs_next.as_slice(),
+                self.next_lookup_zs.as_slice(),
+                self.custom_argument_polys_next.as_slice(),
+            ]
+            .concat(),
         };
         FriOpeningsTarget {
             batches: vec![zeta_batch, zeta_next_batch],
@@ -465,11 +473,12 @@ mod tests {
     use crate::fri::reduction_strategies::FriReductionStrategy;
     use crate::gates::lookup_table::LookupTable;
     use crate::gates::noop::NoopGate;
-    use crate::iop::witness::PartialWitness;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::PoseidonGoldilocksConfig;
     use crate::plonk::verifier::verify;
+    use crate::util::encoding::{decode_field_elements, decode_ssz_container};
 
     #[test]
     fn test_proof_compression() -> Result<()> {
@@ -509,6 +518,41 @@ mod tests {
         data.verify_compressed(compressed_proof)
     }
 
+    #[test]
+    fn test_commitments_to_ssz() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.square(x);
+        builder.register_public_inputs(&[x, y]);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand())?;
+        let proof = data.prove(pw)?;
+
+        let bytes = proof.commitments_to_ssz();
+        let fields = decode_ssz_container(&bytes, 4)?;
+        assert_eq!(
+            MerkleCap::<F, H>::from_bytes(fields[0])?,
+            proof.proof.wires_cap
+        );
+        assert_eq!(
+            MerkleCap::<F, H>::from_bytes(fields[1])?,
+            proof.proof.plonk_zs_partial_products_cap
+        );
+        assert_eq!(
+            MerkleCap::<F, H>::from_bytes(fields[2])?,
+            proof.proof.quotient_polys_cap
+        );
+        assert_eq!(decode_field_elements::<F>(fields[3])?, proof.public_inputs);
+        Ok(())
+    }
+
     #[test]
     fn test_proof_compression_lookup() -> Result<()> {
         const D: usize = 2;
@@ -547,7 +591,7 @@ mod tests {
         let out = builder.constant(F::from_canonical_usize(26));
 
         let xt = builder.constant(x);
-        let look_out = builder.add_lookup_from_index(xt, lut_index);
+        let look_out = builder.lookup(lut_index, xt);
         builder.connect(look_out, out);
         for _ in 0..100 {
             builder.add_gate(NoopGate, vec![]);
@@ -1,8 +1,9 @@
 //! plonky2 prover implementation.
 
 #[cfg(not(feature = "std"))]
-use alloc::{format, vec, vec::Vec};
+use alloc::{boxed::Box, format, vec, vec::Vec};
 use core::cmp::min;
+use core::fmt;
 use core::mem::swap;
 
 use anyhow::{ensure, Result};
@@ -19,7 +20,8 @@ use crate::gates::lookup::LookupGate;
 use crate::gates::lookup_table::LookupTableGate;
 use crate::gates::selectors::LookupSelectors;
 use crate::hash::hash_types::RichField;
-use crate::iop::challenger::Challenger;
+use crate::hash::merkle_tree::MerkleTree;
+use crate::iop::challenger::{Challenger, Transcript};
 use crate::iop::generator::generate_partial_witness;
 use crate::iop::target::Target;
 use crate::iop::witness::{MatrixWitness, PartialWitness, PartitionWitness, Witness, WitnessWrite};
@@ -31,10 +33,289 @@ use crate::plonk::proof::{OpeningSet, Proof, ProofWithPublicInputs};
 use crate::plonk::vanishing_poly::{eval_vanishing_poly_base_batch, get_lut_poly};
 use crate::plonk::vars::EvaluationVarsBaseBatch;
 use crate::timed;
+#[cfg(all(feature = "std", feature = "rand_chacha"))]
+use crate::util::blinding::with_seeded_blinding;
+use crate::util::matrix::ColMajorMatrix;
 use crate::util::partial_products::{partial_products_and_z_gx, quotient_chunk_products};
+use crate::util::serialization::{Buffer, IoError, IoResult, Read, Write};
 use crate::util::timing::TimingTree;
 use crate::util::{log2_ceil, transpose};
 
+/// The phases of [`prove`], in the order in which they run.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ProverPhase {
+    /// Running the witness generators.
+    GenerateWitness,
+    /// Computing the full witness and committing to the wire polynomials.
+    CommitWires,
+    /// Computing and committing to the partial products, `Z`s, lookup and custom argument
+    /// polynomials.
+    CommitPartialProducts,
+    /// Computing the quotient polynomials.
+    ComputeQuotient,
+    /// Committing to the quotient polynomial chunks.
+    CommitQuotient,
+    /// Evaluating all committed polynomials at the opening points.
+    ComputeOpenings,
+    /// Computing the FRI opening proofs.
+    ProveOpenings,
+}
+
+impl ProverPhase {
+    /// All phases, in the order in which they run.
+    pub const ALL: [ProverPhase; 7] = [
+        ProverPhase::GenerateWitness,
+        ProverPhase::CommitWires,
+        ProverPhase::CommitPartialProducts,
+        ProverPhase::ComputeQuotient,
+        ProverPhase::CommitQuotient,
+        ProverPhase::ComputeOpenings,
+        ProverPhase::ProveOpenings,
+    ];
+
+    /// The position of this phase in [`ProverPhase::ALL`].
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The error returned by [`prove_with_hooks`] when [`ProverHooks::should_cancel`] asked for
+/// proving to stop. It can be recovered with `anyhow::Error::downcast_ref`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProvingCancelled {
+    /// The phase which was about to start when proving was cancelled.
+    pub phase: ProverPhase,
+}
+
+impl fmt::Display for ProvingCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "proving cancelled before phase {:?}", self.phase)
+    }
+}
+
+/// Callbacks letting the caller of [`prove_with_hooks`] follow the progress of a proof and
+/// cancel it.
+///
+/// Hooks are only invoked between phases, one at a time, from the thread driving the prover. A
+/// cancelled proof therefore never leaves a parallel computation half-done: the prover returns a
+/// [`ProvingCancelled`] error as soon as the current phase is complete. Hooks must be `Send` and
+/// `Sync` so that proofs can run on another thread pool, see [`prove_in_pool`].
+#[derive(Default)]
+pub struct ProverHooks<'a> {
+    /// Called when a phase starts.
+    pub on_phase_start: Option<Box<dyn Fn(ProverPhase) + Send + Sync + 'a>>,
+    /// Called when a phase ends, with the number of phases completed so far and the total number
+    /// of phases.
+    pub on_progress: Option<Box<dyn Fn(usize, usize) + Send + Sync + 'a>>,
+    /// Polled before each phase; proving stops if it returns `true`.
+    pub should_cancel: Option<Box<dyn Fn() -> bool + Send + Sync + 'a>>,
+    /// Called after the phases committing to polynomials, i.e. [`ProverPhase::CommitWires`],
+    /// [`ProverPhase::CommitPartialProducts`] and [`ProverPhase::CommitQuotient`], with the
+    /// completed phase and the serialized [`ProverCheckpoint`] from which proving can resume, see
+    /// [`resume_proof`]. It typically writes the checkpoint to disk; an error stops proving.
+    pub on_checkpoint: Option<CheckpointHook<'a>>,
+}
+
+type CheckpointHook<'a> = Box<dyn Fn(ProverPhase, &[u8]) -> Result<()> + Send + Sync + 'a>;
+
+impl<'a> ProverHooks<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_on_phase_start(mut self, f: impl Fn(ProverPhase) + Send + Sync + 'a) -> Self {
+        self.on_phase_start = Some(Box::new(f));
+        self
+    }
+
+    pub fn with_on_progress(mut self, f: impl Fn(usize, usize) + Send + Sync + 'a) -> Self {
+        self.on_progress = Some(Box::new(f));
+        self
+    }
+
+    pub fn with_should_cancel(mut self, f: impl Fn() -> bool + Send + Sync + 'a) -> Self {
+        self.should_cancel = Some(Box::new(f));
+        self
+    }
+
+    pub fn with_on_checkpoint(
+        mut self,
+        f: impl Fn(ProverPhase, &[u8]) -> Result<()> + Send + Sync + 'a,
+    ) -> Self {
+        self.on_checkpoint = Some(Box::new(f));
+        self
+    }
+
+    fn start_phase(&self, phase: ProverPhase) -> Result<()> {
+        if self.should_cancel.as_ref().is_some_and(|f| f()) {
+            return Err(anyhow::Error::msg(ProvingCancelled { phase }));
+        }
+        if let Some(f) = &self.on_phase_start {
+            f(phase);
+        }
+        Ok(())
+    }
+
+    fn end_phase(&self, phase: ProverPhase) {
+        if let Some(f) = &self.on_progress {
+            f(phase.index() + 1, ProverPhase::ALL.len());
+        }
+    }
+
+    fn save_checkpoint<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+        &self,
+        checkpoint: &ProverCheckpoint<F, C, D>,
+    ) -> Result<()> {
+        match &self.on_checkpoint {
+            Some(f) => f(checkpoint.phase, &checkpoint.to_bytes()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for ProverHooks<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProverHooks")
+            .field("on_phase_start", &self.on_phase_start.is_some())
+            .field("on_progress", &self.on_progress.is_some())
+            .field("should_cancel", &self.should_cancel.is_some())
+            .field("on_checkpoint", &self.on_checkpoint.is_some())
+            .finish()
+    }
+}
+
+/// The state of the prover after one of the phases committing to polynomials, from which proving
+/// can resume with [`resume_proof`]. Checkpoints are handed to [`ProverHooks::on_checkpoint`]
+/// serialized, and read back with [`ProverCheckpoint::from_bytes`].
+///
+/// A checkpoint holds the committed polynomials and their Merkle trees, so it is roughly as large
+/// as the prover's memory footprint at that point. It also holds the blinding values, so it must
+/// be kept as secret as the witness.
+#[derive(Debug, Eq, PartialEq)]
+pub struct ProverCheckpoint<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    /// The last completed phase.
+    pub phase: ProverPhase,
+    /// The digest of the circuit being proven.
+    pub circuit_digest: <C::Hasher as Hasher<F>>::Hash,
+    pub public_inputs: Vec<F>,
+    /// The full witness, which is only needed until the partial products are computed.
+    witness: Option<MatrixWitness<F>>,
+    wires_commitment: PolynomialBatch<F, C, D>,
+    partial_products_zs_and_lookup_commitment: Option<PolynomialBatch<F, C, D>>,
+    quotient_polys_commitment: Option<PolynomialBatch<F, C, D>>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    ProverCheckpoint<F, C, D>
+{
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.write(&mut buffer)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+        buffer.write_u8(self.phase.index() as u8)?;
+        buffer.write_hash::<F, C::Hasher>(self.circuit_digest)?;
+        buffer.write_usize(self.public_inputs.len())?;
+        buffer.write_field_vec(&self.public_inputs)?;
+        buffer.write_bool(self.witness.is_some())?;
+        if let Some(witness) = &self.witness {
+            buffer.write_usize(witness.wire_values.width())?;
+            for column in witness.wire_values.columns() {
+                buffer.write_usize(column.len())?;
+                buffer.write_field_vec(column)?;
+            }
+        }
+        buffer.write_polynomial_batch(&self.wires_commitment)?;
+        for batch in [
+            &self.partial_products_zs_and_lookup_commitment,
+            &self.quotient_polys_commitment,
+        ] {
+            buffer.write_bool(batch.is_some())?;
+            if let Some(batch) = batch {
+                buffer.write_polynomial_batch(batch)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> IoResult<Self> {
+        let mut buffer = Buffer::new(bytes);
+        let phase = *ProverPhase::ALL
+            .get(buffer.read_u8()? as usize)
+            .ok_or(IoError)?;
+        let circuit_digest = buffer.read_hash::<F, C::Hasher>()?;
+        let num_public_inputs = buffer.read_usize()?;
+        let public_inputs = buffer.read_field_vec(num_public_inputs)?;
+        let witness = if buffer.read_bool()? {
+            let num_columns = buffer.read_usize()?;
+            let columns = (0..num_columns)
+                .map(|_| {
+                    let len = buffer.read_usize()?;
+                    buffer.read_field_vec(len)
+                })
+                .collect::<IoResult<Vec<_>>>()?;
+            if columns
+                .windows(2)
+                .any(|pair| pair[0].len() != pair[1].len())
+            {
+                return Err(IoError);
+            }
+            Some(MatrixWitness {
+                wire_values: ColMajorMatrix::from_columns(columns),
+            })
+        } else {
+            None
+        };
+        let wires_commitment = buffer.read_polynomial_batch()?;
+        let mut read_batch = || -> IoResult<_> {
+            Ok(if buffer.read_bool()? {
+                Some(buffer.read_polynomial_batch()?)
+            } else {
+                None
+            })
+        };
+        let partial_products_zs_and_lookup_commitment = read_batch()?;
+        let quotient_polys_commitment = read_batch()?;
+        if !buffer.unread_bytes().is_empty() {
+            return Err(IoError);
+        }
+
+        // Check that the checkpoint has what resuming after its phase needs.
+        let expected = match phase {
+            ProverPhase::CommitWires => (true, false, false),
+            ProverPhase::CommitPartialProducts => (false, true, false),
+            ProverPhase::CommitQuotient => (false, true, true),
+            _ => return Err(IoError),
+        };
+        if expected
+            != (
+                witness.is_some(),
+                partial_products_zs_and_lookup_commitment.is_some(),
+                quotient_polys_commitment.is_some(),
+            )
+        {
+            return Err(IoError);
+        }
+
+        Ok(Self {
+            phase,
+            circuit_digest,
+            public_inputs,
+            witness,
+            wires_commitment,
+            partial_products_zs_and_lookup_commitment,
+            quotient_polys_commitment,
+        })
+    }
+}
+
 /// Set all the lookup gate wires (including multiplicities) and pad unused LU slots.
 /// Warning: rows are in descending order: the first gate to appear is the last LU gate, and
 /// the last gate to appear is the first LUT gate.
@@ -120,13 +401,149 @@ where
     C::Hasher: Hasher<F>,
     C::InnerHasher: Hasher<F>,
 {
+    prove_with_hooks(
+        prover_data,
+        common_data,
+        inputs,
+        timing,
+        &ProverHooks::default(),
+    )
+}
+
+/// Like [`prove`], but reports progress to `hooks` and stops with a [`ProvingCancelled`] error
+/// if they ask for it.
+pub fn prove_with_hooks<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+    hooks: &ProverHooks,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    hooks.start_phase(ProverPhase::GenerateWitness)?;
     let partition_witness = timed!(
         timing,
         &format!("run {} generators", prover_data.generators.len()),
         generate_partial_witness(inputs, prover_data, common_data)?
     );
+    hooks.end_phase(ProverPhase::GenerateWitness);
+
+    prove_with_partition_witness_and_hooks(
+        prover_data,
+        common_data,
+        partition_witness,
+        timing,
+        hooks,
+    )
+}
+
+/// Options controlling how [`prove_with_options`] runs.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProverOptions {
+    /// The number of threads to prove with. If `None`, the proof runs on the current rayon thread
+    /// pool, i.e. the global one unless the prover is called from within `ThreadPool::install`.
+    ///
+    /// This has no effect without the `parallel` feature, in which case proving is always
+    /// single-threaded.
+    pub num_threads: Option<usize>,
+    /// If set, the proof is made deterministically: the prover runs on a single thread, so that
+    /// all reductions and the proof-of-work search happen in a fixed order, and blinding values
+    /// are drawn from a ChaCha RNG seeded with this value, see
+    /// [`with_seeded_blinding`](crate::util::blinding::with_seeded_blinding). Proving the same
+    /// witness twice with the same seed then yields byte-identical proofs on any machine.
+    ///
+    /// This takes precedence over `num_threads`, and requires the `std` and `rand_chacha`
+    /// features. Zero-knowledge proofs made this way only hide the witness if the seed is secret.
+    pub deterministic: Option<[u8; 32]>,
+}
+
+impl ProverOptions {
+    pub const fn with_num_threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = Some(num_threads);
+        self
+    }
+
+    pub const fn with_deterministic(mut self, seed: [u8; 32]) -> Self {
+        self.deterministic = Some(seed);
+        self
+    }
+}
 
-    prove_with_partition_witness(prover_data, common_data, partition_witness, timing)
+/// Like [`prove_with_hooks`], but runs according to `options`. When a number of threads is set, a
+/// dedicated thread pool is created for the proof, so that its parallelism does not depend on, and
+/// does not starve, the global pool.
+pub fn prove_with_options<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+    hooks: &ProverHooks,
+    options: &ProverOptions,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    if let Some(seed) = options.deterministic {
+        #[cfg(all(feature = "std", feature = "rand_chacha"))]
+        {
+            let prove = || {
+                with_seeded_blinding(seed, || {
+                    prove_with_hooks(prover_data, common_data, inputs, timing, hooks)
+                })
+            };
+            #[cfg(feature = "parallel")]
+            return thread_pool(1)?.install(prove);
+            #[cfg(not(feature = "parallel"))]
+            return prove();
+        }
+        #[cfg(not(all(feature = "std", feature = "rand_chacha")))]
+        {
+            let _ = seed;
+            anyhow::bail!("deterministic proving requires the `std` and `rand_chacha` features");
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    if let Some(num_threads) = options.num_threads {
+        let pool = thread_pool(num_threads)?;
+        return prove_in_pool(&pool, prover_data, common_data, inputs, timing, hooks);
+    }
+
+    prove_with_hooks(prover_data, common_data, inputs, timing, hooks)
+}
+
+#[cfg(feature = "parallel")]
+fn thread_pool(num_threads: usize) -> Result<rayon::ThreadPool> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .map_err(anyhow::Error::msg)
+}
+
+/// Like [`prove_with_hooks`], but runs all parallel work of the prover on the given thread pool
+/// instead of the global one.
+#[cfg(feature = "parallel")]
+pub fn prove_in_pool<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    pool: &rayon::ThreadPool,
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+    hooks: &ProverHooks,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    pool.install(|| prove_with_hooks(prover_data, common_data, inputs, timing, hooks))
 }
 
 pub fn prove_with_partition_witness<
@@ -136,23 +553,154 @@ pub fn prove_with_partition_witness<
 >(
     prover_data: &ProverOnlyCircuitData<F, C, D>,
     common_data: &CommonCircuitData<F, D>,
-    mut partition_witness: PartitionWitness<F>,
+    partition_witness: PartitionWitness<F>,
     timing: &mut TimingTree,
 ) -> Result<ProofWithPublicInputs<F, C, D>>
 where
     C::Hasher: Hasher<F>,
     C::InnerHasher: Hasher<F>,
 {
-    let has_lookup = !common_data.luts.is_empty();
+    prove_with_partition_witness_and_hooks(
+        prover_data,
+        common_data,
+        partition_witness,
+        timing,
+        &ProverHooks::default(),
+    )
+}
+
+/// Like [`prove_with_partition_witness`], but reports progress to `hooks` and stops with a
+/// [`ProvingCancelled`] error if they ask for it.
+pub fn prove_with_partition_witness_and_hooks<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    partition_witness: PartitionWitness<F>,
+    timing: &mut TimingTree,
+    hooks: &ProverHooks,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    prove_with_challenger(
+        prover_data,
+        common_data,
+        partition_witness,
+        &mut Challenger::new(),
+        timing,
+        hooks,
+    )
+}
+
+/// Like [`prove`], but also returns the Fiat-Shamir transcript of the prover, labelled by phase.
+/// Comparing it with the transcript replayed by a verifier, see
+/// [`ProofWithPublicInputs::replay_transcript`], locates the first challenge on which they
+/// disagree.
+pub fn prove_with_transcript<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+) -> Result<(ProofWithPublicInputs<F, C, D>, Transcript<F>)>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    let partition_witness = timed!(
+        timing,
+        &format!("run {} generators", prover_data.generators.len()),
+        generate_partial_witness(inputs, prover_data, common_data)?
+    );
+
+    let mut challenger = Challenger::new();
+    challenger.record_transcript();
+    let proof = prove_with_challenger(
+        prover_data,
+        common_data,
+        partition_witness,
+        &mut challenger,
+        timing,
+        &ProverHooks::default(),
+    )?;
+    Ok((proof, challenger.take_transcript().unwrap()))
+}
+
+fn prove_with_challenger<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    partition_witness: PartitionWitness<F>,
+    challenger: &mut Challenger<F, C::Hasher>,
+    timing: &mut TimingTree,
+    hooks: &ProverHooks,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    common_data.check_zk_mode()?;
     let config = &common_data.config;
-    let num_challenges = config.num_challenges;
-    let quotient_degree = common_data.quotient_degree();
-    let degree = common_data.degree();
 
+    hooks.start_phase(ProverPhase::CommitWires)?;
+    let (public_inputs, witness, wires_values) =
+        wire_values(prover_data, common_data, partition_witness, timing)?;
+
+    let wires_commitment = timed!(
+        timing,
+        "compute wires commitment",
+        PolynomialBatch::<F, C, D>::from_values(
+            wires_values,
+            config.fri_config.rate_bits,
+            config.zk_mode().salts(PlonkOracle::WIRES),
+            common_data
+                .fri_params
+                .oracle_cap_height(PlonkOracle::WIRES.index),
+            timing,
+            prover_data.fft_root_table.as_ref(),
+        )
+    );
+
+    hooks.end_phase(ProverPhase::CommitWires);
+
+    let checkpoint = ProverCheckpoint {
+        phase: ProverPhase::CommitWires,
+        circuit_digest: prover_data.circuit_digest,
+        public_inputs,
+        witness: Some(witness),
+        wires_commitment,
+        partial_products_zs_and_lookup_commitment: None,
+        quotient_polys_commitment: None,
+    };
+    hooks.save_checkpoint(&checkpoint)?;
+
+    prove_from_checkpoint(
+        prover_data,
+        common_data,
+        checkpoint,
+        challenger,
+        timing,
+        hooks,
+    )
+}
+
+/// Computes the public inputs, the full witness and the values of the wire polynomials.
+#[allow(clippy::type_complexity)]
+fn wire_values<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    mut partition_witness: PartitionWitness<F>,
+    timing: &mut TimingTree,
+) -> Result<(Vec<F>, MatrixWitness<F>, Vec<PolynomialValues<F>>)> {
     set_lookup_wires(prover_data, common_data, &mut partition_witness)?;
 
     let public_inputs = partition_witness.get_targets(&prover_data.public_inputs);
-    let public_inputs_hash = C::InnerHasher::hash_no_pad(&public_inputs);
 
     let witness = timed!(
         timing,
@@ -160,36 +708,219 @@ where
         partition_witness.full_witness()
     );
 
-    let wires_values: Vec<PolynomialValues<F>> = timed!(
+    let wires_values = timed!(
         timing,
         "compute wire polynomials",
-        witness
-            .wire_values
-            .par_iter()
-            .map(|column| PolynomialValues::new(column.clone()))
+        (0..witness.wire_values.width())
+            .into_par_iter()
+            .map(|column| PolynomialValues::new(witness.wire_values.column(column).to_vec()))
             .collect()
     );
 
+    Ok((public_inputs, witness, wires_values))
+}
+
+/// The witness of a proof whose wires commitment, see [`ProverPhase::CommitWires`], is split
+/// across machines. Each worker commits to a shard of the wire polynomials with
+/// [`commit_wires_shard`], and [`prove_with_wire_shards`] assembles the shards and runs the rest
+/// of the prover, as [`prove`] would.
+#[derive(Debug)]
+pub struct WireShardingJob<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+{
+    circuit_digest: <C::Hasher as Hasher<F>>::Hash,
+    public_inputs: Vec<F>,
+    witness: MatrixWitness<F>,
+    /// The wire polynomials, which the workers need to commit to their shards.
+    pub wire_polynomials: Vec<PolynomialCoeffs<F>>,
+}
+
+/// Generates the witness of a proof whose wires commitment is split across machines, see
+/// [`WireShardingJob`].
+pub fn start_wire_sharding<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+) -> Result<WireShardingJob<F, C, D>> {
+    common_data.check_zk_mode()?;
+    let partition_witness = timed!(
+        timing,
+        &format!("run {} generators", prover_data.generators.len()),
+        generate_partial_witness(inputs, prover_data, common_data)?
+    );
+    let (public_inputs, witness, wires_values) =
+        wire_values(prover_data, common_data, partition_witness, timing)?;
+    let wire_polynomials = timed!(
+        timing,
+        "IFFT",
+        PolynomialBatch::<F, C, D>::interpolate(wires_values)
+    );
+
+    Ok(WireShardingJob {
+        circuit_digest: prover_data.circuit_digest,
+        public_inputs,
+        witness,
+        wire_polynomials,
+    })
+}
+
+/// Computes shard `shard` out of `num_shards` of the wires commitment of a proof, given the
+/// [`WireShardingJob::wire_polynomials`], see [`PolynomialBatch::commit_shard`]. This is the work
+/// a worker does, and it only needs the common circuit data. `num_shards` must be a power of two
+/// of at most the number of elements of the wires cap.
+pub fn commit_wires_shard<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    common_data: &CommonCircuitData<F, D>,
+    wire_polynomials: &[PolynomialCoeffs<F>],
+    shard: usize,
+    num_shards: usize,
+) -> MerkleTree<F, C::Hasher> {
+    let config = &common_data.config;
+    PolynomialBatch::<F, C, D>::commit_shard(
+        wire_polynomials,
+        config.fri_config.rate_bits,
+        config.zk_mode().salts(PlonkOracle::WIRES),
+        common_data
+            .fri_params
+            .oracle_cap_height(PlonkOracle::WIRES.index),
+        shard,
+        num_shards,
+    )
+}
+
+/// Assembles the wires commitment of `job` from the `shards` computed by [`commit_wires_shard`],
+/// in order, and proves the rest of the protocol. The shards aren't checked against the wire
+/// polynomials, so a faulty worker yields a proof which doesn't verify.
+pub fn prove_with_wire_shards<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    job: WireShardingJob<F, C, D>,
+    shards: Vec<MerkleTree<F, C::Hasher>>,
+    timing: &mut TimingTree,
+    hooks: &ProverHooks,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    ensure!(
+        job.circuit_digest == prover_data.circuit_digest,
+        "The wire sharding job was started while proving another circuit"
+    );
+    let config = &common_data.config;
+    let cap_height = common_data
+        .fri_params
+        .oracle_cap_height(PlonkOracle::WIRES.index);
+
+    hooks.start_phase(ProverPhase::CommitWires)?;
     let wires_commitment = timed!(
         timing,
-        "compute wires commitment",
-        PolynomialBatch::<F, C, D>::from_values(
-            wires_values,
+        "assemble wires commitment",
+        PolynomialBatch::<F, C, D>::from_shards(
+            job.wire_polynomials,
             config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::WIRES.blinding,
-            config.fri_config.cap_height,
-            timing,
-            prover_data.fft_root_table.as_ref(),
+            config.zk_mode().salts(PlonkOracle::WIRES),
+            shards,
         )
     );
+    ensure!(
+        wires_commitment.merkle_tree.cap.height() == cap_height,
+        "The shards have a cap of height {}, but the wires cap has height {}",
+        wires_commitment.merkle_tree.cap.height(),
+        cap_height
+    );
+    hooks.end_phase(ProverPhase::CommitWires);
+
+    let checkpoint = ProverCheckpoint {
+        phase: ProverPhase::CommitWires,
+        circuit_digest: prover_data.circuit_digest,
+        public_inputs: job.public_inputs,
+        witness: Some(job.witness),
+        wires_commitment,
+        partial_products_zs_and_lookup_commitment: None,
+        quotient_polys_commitment: None,
+    };
+    hooks.save_checkpoint(&checkpoint)?;
 
-    let mut challenger = Challenger::<F, C::Hasher>::new();
+    prove_from_checkpoint(
+        prover_data,
+        common_data,
+        checkpoint,
+        &mut Challenger::new(),
+        timing,
+        hooks,
+    )
+}
+
+/// Resumes a proof from a [`ProverCheckpoint`] saved by an earlier call to the prover, possibly
+/// in another process or on another machine, see [`ProverHooks::on_checkpoint`]. The phases
+/// completed before the checkpoint are skipped, and `hooks` are invoked for the remaining ones.
+pub fn resume_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    checkpoint: ProverCheckpoint<F, C, D>,
+    timing: &mut TimingTree,
+    hooks: &ProverHooks,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    common_data.check_zk_mode()?;
+    ensure!(
+        checkpoint.circuit_digest == prover_data.circuit_digest,
+        "The checkpoint was saved while proving another circuit"
+    );
+    prove_from_checkpoint(
+        prover_data,
+        common_data,
+        checkpoint,
+        &mut Challenger::new(),
+        timing,
+        hooks,
+    )
+}
+
+/// Runs the phases following `checkpoint.phase`. The challenger is fresh: the challenges of the
+/// phases already completed are derived again from the commitments in the checkpoint.
+fn prove_from_checkpoint<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    mut checkpoint: ProverCheckpoint<F, C, D>,
+    challenger: &mut Challenger<F, C::Hasher>,
+    timing: &mut TimingTree,
+    hooks: &ProverHooks,
+) -> Result<ProofWithPublicInputs<F, C, D>>
+where
+    C::Hasher: Hasher<F>,
+    C::InnerHasher: Hasher<F>,
+{
+    let has_lookup = !common_data.luts.is_empty();
+    let config = &common_data.config;
+    let num_challenges = config.num_challenges;
+    let quotient_degree = common_data.quotient_degree();
+    let degree = common_data.degree();
+
+    let public_inputs_hash = C::InnerHasher::hash_no_pad(&checkpoint.public_inputs);
 
     // Observe the instance.
+    challenger.set_phase("instance");
     challenger.observe_hash::<C::Hasher>(prover_data.circuit_digest);
     challenger.observe_hash::<C::InnerHasher>(public_inputs_hash);
 
-    challenger.observe_cap::<C::Hasher>(&wires_commitment.merkle_tree.cap);
+    challenger.set_phase("wires");
+    challenger.observe_cap::<C::Hasher>(&checkpoint.wires_commitment.merkle_tree.cap);
 
     // We need 4 values per challenge: 2 for the combos, 1 for (X-combo) in the accumulators and 1 to prove that the lookup table was computed correctly.
     // We can reuse betas and gammas for two of them.
@@ -210,94 +941,156 @@ where
         vec![]
     };
 
-    assert!(
-        common_data.quotient_degree_factor < common_data.config.num_routed_wires,
-        "When the number of routed wires is smaller that the degree, we should change the logic to avoid computing partial products."
-    );
-    let mut partial_products_and_zs = timed!(
-        timing,
-        "compute partial products",
-        all_wires_permutation_partial_products(&witness, &betas, &gammas, prover_data, common_data)
-    );
+    let custom_argument_challenges =
+        challenger.get_n_challenges(common_data.num_custom_argument_challenges());
 
-    // Z is expected at the front of our batch; see `zs_range` and `partial_products_range`.
-    let plonk_z_vecs = partial_products_and_zs
-        .iter_mut()
-        .map(|partial_products_and_z| partial_products_and_z.pop().unwrap())
-        .collect();
-    let zs_partial_products = [plonk_z_vecs, partial_products_and_zs.concat()].concat();
+    if checkpoint
+        .partial_products_zs_and_lookup_commitment
+        .is_none()
+    {
+        hooks.start_phase(ProverPhase::CommitPartialProducts)?;
+        let witness = checkpoint
+            .witness
+            .take()
+            .expect("Checkpoints before the partial products include the witness");
+        assert!(
+            common_data.quotient_degree_factor < common_data.config.num_routed_wires,
+            "When the number of routed wires is smaller that the degree, we should change the logic to avoid computing partial products."
+        );
+        let mut partial_products_and_zs = timed!(
+            timing,
+            "compute partial products",
+            all_wires_permutation_partial_products(
+                &witness,
+                &betas,
+                &gammas,
+                prover_data,
+                common_data
+            )
+        );
 
-    // All lookup polys: RE and partial SLDCs.
-    let lookup_polys =
-        compute_all_lookup_polys(&witness, &deltas, prover_data, common_data, has_lookup);
+        // Z is expected at the front of our batch; see `zs_range` and `partial_products_range`.
+        let plonk_z_vecs = partial_products_and_zs
+            .iter_mut()
+            .map(|partial_products_and_z| partial_products_and_z.pop().unwrap())
+            .collect();
+        let zs_partial_products = [plonk_z_vecs, partial_products_and_zs.concat()].concat();
 
-    let zs_partial_products_lookups = if has_lookup {
-        [zs_partial_products, lookup_polys].concat()
-    } else {
-        zs_partial_products
-    };
+        // All lookup polys: RE and partial SLDCs.
+        let lookup_polys =
+            compute_all_lookup_polys(&witness, &deltas, prover_data, common_data, has_lookup);
 
-    let partial_products_zs_and_lookup_commitment = timed!(
-        timing,
-        "commit to partial products, Z's and, if any, lookup polynomials",
-        PolynomialBatch::from_values(
-            zs_partial_products_lookups,
-            config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::ZS_PARTIAL_PRODUCTS.blinding,
-            config.fri_config.cap_height,
+        let custom_argument_polys = timed!(
             timing,
-            prover_data.fft_root_table.as_ref(),
-        )
-    );
+            "compute custom argument polys",
+            compute_custom_argument_polys(&witness, &custom_argument_challenges, common_data)?
+        );
+
+        let zs_partial_products_lookups =
+            [zs_partial_products, lookup_polys, custom_argument_polys].concat();
+
+        let partial_products_zs_and_lookup_commitment = timed!(
+            timing,
+            "commit to partial products, Z's and, if any, lookup and custom argument polynomials",
+            PolynomialBatch::from_values(
+                zs_partial_products_lookups,
+                config.fri_config.rate_bits,
+                config.zk_mode().salts(PlonkOracle::ZS_PARTIAL_PRODUCTS),
+                common_data
+                    .fri_params
+                    .oracle_cap_height(PlonkOracle::ZS_PARTIAL_PRODUCTS.index),
+                timing,
+                prover_data.fft_root_table.as_ref(),
+            )
+        );
+
+        hooks.end_phase(ProverPhase::CommitPartialProducts);
+
+        checkpoint.phase = ProverPhase::CommitPartialProducts;
+        checkpoint.partial_products_zs_and_lookup_commitment =
+            Some(partial_products_zs_and_lookup_commitment);
+        hooks.save_checkpoint(&checkpoint)?;
+    }
+    let partial_products_zs_and_lookup_commitment = checkpoint
+        .partial_products_zs_and_lookup_commitment
+        .as_ref()
+        .unwrap();
 
+    challenger.set_phase("partial products");
     challenger.observe_cap::<C::Hasher>(&partial_products_zs_and_lookup_commitment.merkle_tree.cap);
 
     let alphas = challenger.get_n_challenges(num_challenges);
 
-    let quotient_polys = timed!(
-        timing,
-        "compute quotient polys",
-        compute_quotient_polys::<F, C, D>(
-            common_data,
-            prover_data,
-            &public_inputs_hash,
-            &wires_commitment,
-            &partial_products_zs_and_lookup_commitment,
-            &betas,
-            &gammas,
-            &deltas,
-            &alphas,
-        )
-    );
+    if checkpoint.quotient_polys_commitment.is_none() {
+        hooks.start_phase(ProverPhase::ComputeQuotient)?;
+        let quotient_polys = timed!(
+            timing,
+            "compute quotient polys",
+            compute_quotient_polys::<F, C, D>(
+                common_data,
+                prover_data,
+                &public_inputs_hash,
+                &checkpoint.wires_commitment,
+                partial_products_zs_and_lookup_commitment,
+                &betas,
+                &gammas,
+                &deltas,
+                &alphas,
+                &custom_argument_challenges,
+            )
+        );
 
-    let all_quotient_poly_chunks: Vec<PolynomialCoeffs<F>> = timed!(
-        timing,
-        "split up quotient polys",
-        quotient_polys
-            .into_par_iter()
-            .flat_map(|mut quotient_poly| {
-                quotient_poly.trim_to_len(quotient_degree).expect(
-                    "Quotient has failed, the vanishing polynomial is not divisible by Z_H",
-                );
-                // Split quotient into degree-n chunks.
-                quotient_poly.chunks(degree)
-            })
-            .collect()
-    );
+        let all_quotient_poly_chunks: Vec<PolynomialCoeffs<F>> = timed!(
+            timing,
+            "split up quotient polys",
+            quotient_polys
+                .into_par_iter()
+                .flat_map(|mut quotient_poly| {
+                    quotient_poly.trim_to_len(quotient_degree).expect(
+                        "Quotient has failed, the vanishing polynomial is not divisible by Z_H",
+                    );
+                    // Split quotient into degree-n chunks.
+                    quotient_poly.chunks(degree)
+                })
+                .collect()
+        );
 
-    let quotient_polys_commitment = timed!(
-        timing,
-        "commit to quotient polys",
-        PolynomialBatch::<F, C, D>::from_coeffs(
-            all_quotient_poly_chunks,
-            config.fri_config.rate_bits,
-            config.zero_knowledge && PlonkOracle::QUOTIENT.blinding,
-            config.fri_config.cap_height,
+        hooks.end_phase(ProverPhase::ComputeQuotient);
+
+        hooks.start_phase(ProverPhase::CommitQuotient)?;
+        let quotient_polys_commitment = timed!(
             timing,
-            prover_data.fft_root_table.as_ref(),
-        )
-    );
+            "commit to quotient polys",
+            PolynomialBatch::<F, C, D>::from_coeffs(
+                all_quotient_poly_chunks,
+                config.fri_config.rate_bits,
+                config.zk_mode().salts(PlonkOracle::QUOTIENT),
+                common_data
+                    .fri_params
+                    .oracle_cap_height(PlonkOracle::QUOTIENT.index),
+                timing,
+                prover_data.fft_root_table.as_ref(),
+            )
+        );
 
+        hooks.end_phase(ProverPhase::CommitQuotient);
+
+        checkpoint.phase = ProverPhase::CommitQuotient;
+        checkpoint.quotient_polys_commitment = Some(quotient_polys_commitment);
+        hooks.save_checkpoint(&checkpoint)?;
+    }
+    let ProverCheckpoint {
+        public_inputs,
+        wires_commitment,
+        partial_products_zs_and_lookup_commitment,
+        quotient_polys_commitment,
+        ..
+    } = checkpoint;
+    let partial_products_zs_and_lookup_commitment =
+        partial_products_zs_and_lookup_commitment.unwrap();
+    let quotient_polys_commitment = quotient_polys_commitment.unwrap();
+
+    challenger.set_phase("quotient");
     challenger.observe_cap::<C::Hasher>(&quotient_polys_commitment.merkle_tree.cap);
 
     let zeta = challenger.get_extension_challenge::<D>();
@@ -310,6 +1103,7 @@ where
         "Opening point is in the subgroup."
     );
 
+    hooks.start_phase(ProverPhase::ComputeOpenings)?;
     let openings = timed!(
         timing,
         "construct the opening set, including lookups",
@@ -323,7 +1117,11 @@ where
             common_data
         )
     );
+    challenger.set_phase("openings");
     challenger.observe_openings(&openings.to_fri_openings());
+    hooks.end_phase(ProverPhase::ComputeOpenings);
+
+    hooks.start_phase(ProverPhase::ProveOpenings)?;
     let instance = common_data.get_fri_instance(zeta);
 
     let opening_proof = timed!(
@@ -337,7 +1135,7 @@ where
                 &partial_products_zs_and_lookup_commitment,
                 &quotient_polys_commitment,
             ],
-            &mut challenger,
+            challenger,
             &common_data.fri_params,
             None,
             None,
@@ -345,6 +1143,8 @@ where
         )
     );
 
+    hooks.end_phase(ProverPhase::ProveOpenings);
+
     let proof = Proof::<F, C, D> {
         wires_cap: wires_commitment.merkle_tree.cap,
         plonk_zs_partial_products_cap: partial_products_zs_and_lookup_commitment.merkle_tree.cap,
@@ -601,6 +1401,40 @@ fn compute_all_lookup_polys<
     }
 }
 
+/// Computes the polynomials of all custom arguments, given their challenges.
+fn compute_custom_argument_polys<F: RichField + Extendable<D>, const D: usize>(
+    witness: &MatrixWitness<F>,
+    challenges: &[F],
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<Vec<PolynomialValues<F>>> {
+    let degree = common_data.degree();
+    let mut challenge_start = 0;
+    let mut polys = Vec::with_capacity(common_data.num_custom_argument_polys());
+    for argument in &common_data.custom_arguments {
+        let challenge_end = challenge_start + argument.0.num_challenges();
+        let argument_polys = argument.0.generate_polys(
+            witness,
+            &challenges[challenge_start..challenge_end],
+            common_data,
+        );
+        ensure!(
+            argument_polys.len() == argument.0.num_polys(),
+            "Custom argument {} generated {} polynomials, expected {}.",
+            argument.0.id(),
+            argument_polys.len(),
+            argument.0.num_polys()
+        );
+        ensure!(
+            argument_polys.iter().all(|poly| poly.len() == degree),
+            "Custom argument {} generated polynomials of the wrong length.",
+            argument.0.id()
+        );
+        polys.extend(argument_polys);
+        challenge_start = challenge_end;
+    }
+    Ok(polys)
+}
+
 const BATCH_SIZE: usize = 32;
 
 fn compute_quotient_polys<
@@ -618,10 +1452,12 @@ fn compute_quotient_polys<
     gammas: &[F],
     deltas: &[F],
     alphas: &[F],
+    custom_argument_challenges: &[F],
 ) -> Vec<PolynomialCoeffs<F>> {
     let num_challenges = common_data.config.num_challenges;
 
     let has_lookup = common_data.num_lookup_polys != 0;
+    let has_custom_arguments = !common_data.custom_arguments.is_empty();
 
     let quotient_degree_bits = log2_ceil(common_data.quotient_degree_factor);
     assert!(
@@ -700,6 +1536,9 @@ fn compute_quotient_polys<
             let mut local_lookup_batch = Vec::with_capacity(xs_batch.len());
             let mut next_lookup_batch = Vec::with_capacity(xs_batch.len());
 
+            let mut local_custom_argument_batch = Vec::with_capacity(xs_batch.len());
+            let mut next_custom_argument_batch = Vec::with_capacity(xs_batch.len());
+
             let mut partial_products_batch = Vec::with_capacity(xs_batch.len());
             let mut s_sigmas_batch = Vec::with_capacity(xs_batch.len());
 
@@ -737,6 +1576,14 @@ fn compute_quotient_polys<
                     next_lookup_batch.push(next_lookup_zs);
                 }
 
+                if has_custom_arguments {
+                    let custom_argument_range = common_data.custom_argument_range();
+                    local_custom_argument_batch
+                        .push(&local_zs_partial_and_lookup[custom_argument_range.clone()]);
+                    next_custom_argument_batch
+                        .push(&next_zs_partial_and_lookup[custom_argument_range]);
+                }
+
                 debug_assert_eq!(local_wires.len(), common_data.config.num_wires);
                 debug_assert_eq!(local_zs.len(), num_challenges);
 
@@ -783,12 +1630,15 @@ fn compute_quotient_polys<
                 &next_zs_batch,
                 &local_lookup_batch,
                 &next_lookup_batch,
+                &local_custom_argument_batch,
+                &next_custom_argument_batch,
                 &partial_products_batch,
                 &s_sigmas_batch,
                 betas,
                 gammas,
                 deltas,
                 alphas,
+                custom_argument_challenges,
                 &z_h_on_coset,
                 &lut_re_poly_evals_refs,
             );
@@ -0,0 +1,277 @@
+//! Inlining of prebuilt circuits, see [`CircuitBuilder::add_subcircuit`].
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+use anyhow::Result;
+use hashbrown::HashMap;
+
+use crate::field::extension::Extendable;
+use crate::field::polynomial::PolynomialValues;
+use crate::gates::gate::Gate;
+use crate::gates::noop::NoopGate;
+use crate::gates::public_input::PublicInputGate;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{generate_partial_witness, GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{CircuitData, CommonCircuitData};
+use crate::plonk::config::GenericConfig;
+use crate::util::serialization::{Buffer, IoError, IoResult};
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Inlines the prebuilt circuit `subcircuit`, so that circuits can be assembled from
+    /// precompiled components, e.g. deserialized from bytes, without re-running the code which
+    /// built them. The first `inputs.len()` public inputs of `subcircuit` are connected to
+    /// `inputs`, and the targets holding its other public inputs are returned.
+    ///
+    /// The gates of `subcircuit` are copied row by row, except for its public input gate and its
+    /// no-op rows, along with its copy constraints. Its witness is generated by running its own
+    /// generators from the values of `inputs`, so it must be fully determined by its public
+    /// inputs. `subcircuit` must not use lookups or custom arguments, nor route more wires than
+    /// this circuit.
+    ///
+    /// Circuits with subcircuits can't be serialized along with their generators.
+    pub fn add_subcircuit<C: GenericConfig<D, F = F> + 'static>(
+        &mut self,
+        subcircuit: &Arc<CircuitData<F, C, D>>,
+        inputs: &[Target],
+    ) -> Vec<Target> {
+        let common = &subcircuit.common;
+        let prover_only = &subcircuit.prover_only;
+        assert!(
+            common.luts.is_empty() && common.custom_arguments.is_empty(),
+            "Subcircuits with lookups or custom arguments are not supported"
+        );
+        assert!(
+            common.config.num_routed_wires <= self.config.num_routed_wires,
+            "The subcircuit routes {} wires, but our CircuitConfig has only {}",
+            common.config.num_routed_wires,
+            self.config.num_routed_wires
+        );
+        assert!(
+            inputs.len() <= prover_only.public_inputs.len(),
+            "The subcircuit has only {} public inputs",
+            prover_only.public_inputs.len()
+        );
+
+        // A row holds gate `i` if the gate's selector evaluates to `i` there.
+        let constants: Vec<PolynomialValues<F>> =
+            prover_only.constants_sigmas_commitment.polynomials[common.constants_range()]
+                .iter()
+                .map(|poly| poly.clone().fft())
+                .collect();
+        let num_prefix_constants =
+            common.selectors_info.num_selectors() + common.num_lookup_selectors;
+        let mut row_gates = vec![None; common.degree()];
+        for (i, gate) in common.gates.iter().enumerate() {
+            let selector = &constants[common.selectors_info.selector_indices[i]];
+            for (row, &value) in selector.values.iter().enumerate() {
+                if value == F::from_canonical_usize(i) {
+                    row_gates[row] = Some(gate);
+                }
+            }
+        }
+
+        // The public input gate would constrain the hash of this circuit's public inputs, and
+        // no-op rows have no constraints, so there is no need to copy either.
+        let skipped_ids: [String; 2] = [
+            <PublicInputGate as Gate<F, D>>::id(&PublicInputGate),
+            <NoopGate as Gate<F, D>>::id(&NoopGate),
+        ];
+        let rows = row_gates
+            .into_iter()
+            .enumerate()
+            .map(|(row, gate)| {
+                let gate = gate.expect("No gate selected");
+                if skipped_ids.contains(&gate.0.id()) {
+                    return None;
+                }
+                let gate_constants = constants
+                    [num_prefix_constants..num_prefix_constants + gate.0.num_constants()]
+                    .iter()
+                    .map(|poly| poly.values[row])
+                    .collect::<Vec<_>>();
+                Some(self.add_subcircuit_gate(gate, &gate_constants))
+            })
+            .collect::<Vec<_>>();
+
+        // Wires of copied rows keep their column, and other targets become virtual targets, which
+        // are only allocated when routed.
+        let num_wires = common.config.num_wires;
+        let num_wire_targets = common.degree() * num_wires;
+        let mut virtual_targets = HashMap::new();
+        let mut map_target = |builder: &mut Self, index: usize| {
+            if index < num_wire_targets {
+                if let Some(row) = rows[index / num_wires] {
+                    return Target::wire(row, index % num_wires);
+                }
+            }
+            *virtual_targets
+                .entry(index)
+                .or_insert_with(|| builder.add_virtual_target())
+        };
+
+        for (index, &rep_index) in prover_only.representative_map.iter().enumerate() {
+            if index != rep_index {
+                let target = map_target(self, index);
+                let rep = map_target(self, rep_index);
+                self.connect(target, rep);
+            }
+        }
+
+        let public_inputs = prover_only
+            .public_inputs
+            .iter()
+            .map(|t| map_target(self, t.index(num_wires, common.degree())))
+            .collect::<Vec<_>>();
+        for (&input, &public_input) in inputs.iter().zip(&public_inputs) {
+            self.connect(input, public_input);
+        }
+
+        let virtual_targets = virtual_targets
+            .into_iter()
+            .map(|(index, target)| {
+                let subcircuit_target = Target::VirtualTarget {
+                    index: index - num_wire_targets,
+                };
+                (subcircuit_target, target)
+            })
+            .collect();
+        self.add_simple_generator(SubcircuitGenerator {
+            subcircuit: subcircuit.clone(),
+            inputs: inputs.to_vec(),
+            rows,
+            virtual_targets,
+        });
+
+        public_inputs[inputs.len()..].to_vec()
+    }
+}
+
+/// Generates the witness of an inlined subcircuit by running its own generators, and copies it
+/// to the targets it was mapped to.
+#[derive(Debug)]
+struct SubcircuitGenerator<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+{
+    subcircuit: Arc<CircuitData<F, C, D>>,
+    inputs: Vec<Target>,
+    /// The row in which each row of the subcircuit was copied, if any.
+    rows: Vec<Option<usize>>,
+    /// Pairs of targets of the subcircuit, and the virtual targets they were mapped to.
+    virtual_targets: Vec<(Target, Target)>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F> + 'static, const D: usize>
+    SimpleGenerator<F, D> for SubcircuitGenerator<F, C, D>
+{
+    fn id(&self) -> String {
+        "SubcircuitGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.inputs.clone()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let prover_only = &self.subcircuit.prover_only;
+        let mut inputs = PartialWitness::new();
+        for (&input, &target) in self.inputs.iter().zip(&prover_only.public_inputs) {
+            inputs.set_target(target, witness.get_target(input))?;
+        }
+        let subcircuit_witness =
+            generate_partial_witness(inputs, prover_only, &self.subcircuit.common)?;
+
+        for (row, &new_row) in self.rows.iter().enumerate() {
+            let Some(new_row) = new_row else {
+                continue;
+            };
+            for column in 0..self.subcircuit.common.config.num_wires {
+                if let Some(value) = subcircuit_witness.try_get_target(Target::wire(row, column)) {
+                    out_buffer.set_target(Target::wire(new_row, column), value)?;
+                }
+            }
+        }
+        for &(target, new_target) in &self.virtual_targets {
+            if let Some(value) = subcircuit_witness.try_get_target(target) {
+                out_buffer.set_target(new_target, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn serialize(
+        &self,
+        _dst: &mut Vec<u8>,
+        _common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        Err(IoError)
+    }
+
+    fn deserialize(_src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        Err(IoError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// A circuit with public inputs `[a, b, a * b + a, a / b]`.
+    fn subcircuit() -> Arc<CircuitData<F, C, D>> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let [a, b] = builder.add_virtual_public_input_arr();
+        let prod = builder.mul_add(a, b, a);
+        let quotient = builder.div(a, b);
+        builder.register_public_inputs(&[prod, quotient]);
+        Arc::new(builder.build::<C>())
+    }
+
+    #[test]
+    fn test_add_subcircuit() -> Result<()> {
+        let subcircuit = subcircuit();
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let [x, y] = builder.add_virtual_target_arr();
+        // Chain two instances, the second one consuming the outputs of the first.
+        let outputs = builder.add_subcircuit(&subcircuit, &[x, y]);
+        let outputs = builder.add_subcircuit(&subcircuit, &outputs);
+        builder.register_public_inputs(&outputs);
+        let data = builder.build::<C>();
+
+        let (x_value, y_value) = (F::rand(), F::rand());
+        let (a, b) = (x_value * y_value + x_value, x_value / y_value);
+        let expected = [a * b + a, a / b];
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, x_value)?;
+        pw.set_target(y, y_value)?;
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs, expected);
+        verify(proof, &data.verifier_only, &data.common)
+    }
+}
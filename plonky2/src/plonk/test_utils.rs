@@ -0,0 +1,246 @@
+//! Negative tests for soundness-critical code: a valid proof is mutated in every part a verifier
+//! must check, e.g. its public inputs, openings, Merkle paths or proof of work, and each mutated
+//! proof must be rejected. Gadget and gate authors can run these checks on proofs of their
+//! circuits, to make sure that no part of the proof is left unconstrained.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use anyhow::{bail, ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::hash::hash_types::RichField;
+use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::proof::{OpeningSet, ProofWithPublicInputs};
+use crate::plonk::verifier::verify;
+
+/// Returns copies of `proof`, each with one part mutated, along with a description of the
+/// mutation. Every value of the proof is checked by the verifier, so all of them should be
+/// rejected.
+pub fn proof_mutations<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    proof: &ProofWithPublicInputs<F, C, D>,
+) -> Vec<(String, ProofWithPublicInputs<F, C, D>)> {
+    let mut mutations = Vec::new();
+    let mut mutate = |description: &str, f: &dyn Fn(&mut ProofWithPublicInputs<F, C, D>)| {
+        let mut mutated = proof.clone();
+        f(&mut mutated);
+        mutations.push((description.to_string(), mutated));
+    };
+
+    for i in 0..proof.public_inputs.len() {
+        mutate(&format!("public input {i}"), &|p| {
+            p.public_inputs[i] += F::ONE;
+        });
+    }
+
+    mutate("wires cap", &|p| {
+        p.proof.wires_cap.0[0] = C::Hasher::hash_no_pad(&[F::ONE]);
+    });
+    mutate("Z's and partial products cap", &|p| {
+        p.proof.plonk_zs_partial_products_cap.0[0] = C::Hasher::hash_no_pad(&[F::ONE]);
+    });
+    mutate("quotient polynomials cap", &|p| {
+        p.proof.quotient_polys_cap.0[0] = C::Hasher::hash_no_pad(&[F::ONE]);
+    });
+
+    for (index, name) in OPENING_NAMES.iter().enumerate() {
+        if !opening_mut(&mut proof.proof.openings.clone(), index).is_empty() {
+            mutate(&format!("opening of the {name}"), &|p| {
+                opening_mut(&mut p.proof.openings, index)[0] += F::Extension::ONE;
+            });
+        }
+    }
+
+    let fri_proof = &proof.proof.opening_proof;
+    for i in 0..fri_proof.commit_phase_merkle_caps.len() {
+        mutate(&format!("FRI commit phase cap {i}"), &|p| {
+            p.proof.opening_proof.commit_phase_merkle_caps[i].0[0] =
+                C::Hasher::hash_no_pad(&[F::ONE]);
+        });
+    }
+    if let Some(round) = fri_proof.query_round_proofs.first() {
+        for (i, (_, merkle_proof)) in round.initial_trees_proof.evals_proofs.iter().enumerate() {
+            mutate(&format!("FRI initial tree {i} evaluation"), &|p| {
+                p.proof.opening_proof.query_round_proofs[0]
+                    .initial_trees_proof
+                    .evals_proofs[i]
+                    .0[0] += F::ONE;
+            });
+            if !merkle_proof.siblings.is_empty() {
+                mutate(
+                    &format!("FRI initial tree {i} truncated Merkle path"),
+                    &|p| {
+                        p.proof.opening_proof.query_round_proofs[0]
+                            .initial_trees_proof
+                            .evals_proofs[i]
+                            .1
+                            .siblings
+                            .pop();
+                    },
+                );
+            }
+        }
+        for (i, step) in round.steps.iter().enumerate() {
+            mutate(&format!("FRI query step {i} evaluation"), &|p| {
+                p.proof.opening_proof.query_round_proofs[0].steps[i].evals[0] += F::Extension::ONE;
+            });
+            if !step.merkle_proof.siblings.is_empty() {
+                mutate(&format!("FRI query step {i} truncated Merkle path"), &|p| {
+                    p.proof.opening_proof.query_round_proofs[0].steps[i]
+                        .merkle_proof
+                        .siblings
+                        .pop();
+                });
+            }
+        }
+    }
+    mutate("FRI final polynomial", &|p| {
+        p.proof.opening_proof.final_poly.coeffs[0] += F::Extension::ONE;
+    });
+    mutate("proof of work witness", &|p| {
+        p.proof.opening_proof.pow_witness += F::ONE;
+    });
+
+    mutations
+}
+
+const OPENING_NAMES: [&str; 11] = [
+    "constants",
+    "sigmas",
+    "wires",
+    "Z's",
+    "next Z's",
+    "partial products",
+    "quotient polynomials",
+    "lookup Z's",
+    "next lookup Z's",
+    "custom argument polynomials",
+    "next custom argument polynomials",
+];
+
+/// The opened values named `OPENING_NAMES[index]`.
+fn opening_mut<F: RichField + Extendable<D>, const D: usize>(
+    openings: &mut OpeningSet<F, D>,
+    index: usize,
+) -> &mut Vec<F::Extension> {
+    match index {
+        0 => &mut openings.constants,
+        1 => &mut openings.plonk_sigmas,
+        2 => &mut openings.wires,
+        3 => &mut openings.plonk_zs,
+        4 => &mut openings.plonk_zs_next,
+        5 => &mut openings.partial_products,
+        6 => &mut openings.quotient_polys,
+        7 => &mut openings.lookup_zs,
+        8 => &mut openings.lookup_zs_next,
+        9 => &mut openings.custom_argument_polys,
+        10 => &mut openings.custom_argument_polys_next,
+        _ => unreachable!(),
+    }
+}
+
+/// Checks that `proof` is valid, and that the verifier rejects each of its
+/// [`proof_mutations`]. The error lists the mutations which were accepted.
+pub fn test_proof_mutations<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof: &ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    verify(proof.clone(), verifier_data, common_data)?;
+    let accepted = proof_mutations(proof)
+        .into_iter()
+        .filter(|(_, mutated)| verify(mutated.clone(), verifier_data, common_data).is_ok())
+        .map(|(description, _)| description)
+        .collect::<Vec<_>>();
+    if !accepted.is_empty() {
+        bail!("Mutated proofs were accepted: {}", accepted.join(", "));
+    }
+    Ok(())
+}
+
+/// Checks that `proof` is valid, and that flipping any of `num_bit_flips` bits spread over its
+/// serialization makes it either fail to deserialize or be rejected by the verifier. A flipped
+/// bit changes the value of a field element by a power of two, so it is never a multiple of the
+/// order of the field, and every bit of a valid proof matters.
+pub fn test_serialized_proof_mutations<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proof: &ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    num_bit_flips: usize,
+) -> Result<()> {
+    verify(proof.clone(), verifier_data, common_data)?;
+    let bytes = proof.to_bytes();
+    let num_bits = 8 * bytes.len();
+    ensure!(
+        num_bit_flips <= num_bits,
+        "The proof has only {num_bits} bits"
+    );
+
+    let mut accepted = Vec::new();
+    for i in 0..num_bit_flips {
+        // Spread the flipped bits evenly over the proof, while varying their position in bytes.
+        let bit = i * num_bits / num_bit_flips.max(1) + i % 8;
+        let bit = bit.min(num_bits - 1);
+        let mut mutated = bytes.clone();
+        mutated[bit / 8] ^= 1 << (bit % 8);
+        let is_accepted = ProofWithPublicInputs::from_bytes(mutated, common_data)
+            .is_ok_and(|mutated| verify(mutated, verifier_data, common_data).is_ok());
+        if is_accepted {
+            accepted.push(bit);
+        }
+    }
+    if !accepted.is_empty() {
+        bail!("Proofs with flipped bits {accepted:?} were accepted");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::noop::NoopGate;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_mutations_rejected() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_zk_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3))?;
+        let proof = data.prove(pw)?;
+
+        let mutations = proof_mutations(&proof);
+        assert!(mutations.len() > 20);
+        test_proof_mutations(&proof, &data.verifier_only, &data.common)?;
+        test_serialized_proof_mutations(&proof, &data.verifier_only, &data.common, 64)
+    }
+}
@@ -4,6 +4,7 @@ use crate::field::extension::Extendable;
 use crate::hash::hash_types::RichField;
 use crate::plonk::circuit_data::CommonCircuitData;
 use crate::plonk::config::GenericConfig;
+use crate::plonk::plonk_common::PlonkOracle;
 use crate::plonk::proof::{OpeningSet, Proof, ProofWithPublicInputs};
 
 pub(crate) fn validate_proof_with_pis_shape<F, C, const D: usize>(
@@ -18,6 +19,9 @@ where
         proof,
         public_inputs,
     } = proof_with_pis;
+    // The salting of the Merkle leaves checked against the FRI parameters must be the one the
+    // zero-knowledge mode claims.
+    common_data.check_zk_mode()?;
     validate_proof_shape(proof, common_data)?;
     ensure!(
         public_inputs.len() == common_data.num_public_inputs,
@@ -54,11 +58,13 @@ where
         quotient_polys,
         lookup_zs,
         lookup_zs_next,
+        custom_argument_polys,
+        custom_argument_polys_next,
     } = openings;
-    let cap_height = common_data.fri_params.config.cap_height;
-    ensure!(wires_cap.height() == cap_height);
-    ensure!(plonk_zs_partial_products_cap.height() == cap_height);
-    ensure!(quotient_polys_cap.height() == cap_height);
+    let cap_height = |oracle: PlonkOracle| common_data.fri_params.oracle_cap_height(oracle.index);
+    ensure!(wires_cap.height() == cap_height(PlonkOracle::WIRES));
+    ensure!(plonk_zs_partial_products_cap.height() == cap_height(PlonkOracle::ZS_PARTIAL_PRODUCTS));
+    ensure!(quotient_polys_cap.height() == cap_height(PlonkOracle::QUOTIENT));
     ensure!(constants.len() == common_data.num_constants);
     ensure!(plonk_sigmas.len() == config.num_routed_wires);
     ensure!(wires.len() == config.num_wires);
@@ -68,5 +74,7 @@ where
     ensure!(quotient_polys.len() == common_data.num_quotient_polys());
     ensure!(lookup_zs.len() == common_data.num_all_lookup_polys());
     ensure!(lookup_zs_next.len() == common_data.num_all_lookup_polys());
+    ensure!(custom_argument_polys.len() == common_data.num_custom_argument_polys());
+    ensure!(custom_argument_polys_next.len() == common_data.num_custom_argument_polys());
     Ok(())
 }
@@ -18,6 +18,7 @@ use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::custom_argument::{CustomArgumentVars, CustomArgumentVarsTarget};
 use crate::plonk::plonk_common;
 use crate::plonk::plonk_common::eval_l_0_circuit;
 use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBaseBatch};
@@ -62,12 +63,15 @@ pub(crate) fn eval_vanishing_poly<F: RichField + Extendable<D>, const D: usize>(
     next_zs: &[F::Extension],
     local_lookup_zs: &[F::Extension],
     next_lookup_zs: &[F::Extension],
+    local_custom_argument_polys: &[F::Extension],
+    next_custom_argument_polys: &[F::Extension],
     partial_products: &[F::Extension],
     s_sigmas: &[F::Extension],
     betas: &[F],
     gammas: &[F],
     alphas: &[F],
     deltas: &[F],
+    custom_argument_challenges: &[F],
 ) -> Vec<F::Extension> {
     let has_lookup = common_data.num_lookup_polys != 0;
     let max_degree = common_data.quotient_degree_factor;
@@ -151,10 +155,21 @@ pub(crate) fn eval_vanishing_poly<F: RichField + Extendable<D>, const D: usize>(
         vanishing_partial_products_terms.extend(partial_product_checks);
     }
 
+    // The terms checking the custom arguments, if any.
+    let vanishing_custom_argument_terms = eval_custom_argument_constraints(
+        common_data,
+        vars.local_wires,
+        local_custom_argument_polys,
+        next_custom_argument_polys,
+        custom_argument_challenges,
+        l_0_x,
+    );
+
     let vanishing_terms = [
         vanishing_z_1_terms,
         vanishing_partial_products_terms,
         vanishing_all_lookup_terms,
+        vanishing_custom_argument_terms,
         constraint_terms,
     ]
     .concat();
@@ -173,16 +188,20 @@ pub(crate) fn eval_vanishing_poly_base_batch<F: RichField + Extendable<D>, const
     next_zs_batch: &[&[F]],
     local_lookup_zs_batch: &[&[F]],
     next_lookup_zs_batch: &[&[F]],
+    local_custom_argument_polys_batch: &[&[F]],
+    next_custom_argument_polys_batch: &[&[F]],
     partial_products_batch: &[&[F]],
     s_sigmas_batch: &[&[F]],
     betas: &[F],
     gammas: &[F],
     deltas: &[F],
     alphas: &[F],
+    custom_argument_challenges: &[F],
     z_h_on_coset: &ZeroPolyOnCoset<F>,
     lut_re_poly_evals: &[&[F]],
 ) -> Vec<Vec<F>> {
     let has_lookup = common_data.num_lookup_polys != 0;
+    let has_custom_arguments = !common_data.custom_arguments.is_empty();
 
     let n = indices_batch.len();
     assert_eq!(xs_batch.len(), n);
@@ -196,6 +215,10 @@ pub(crate) fn eval_vanishing_poly_base_batch<F: RichField + Extendable<D>, const
         assert_eq!(local_lookup_zs_batch.len(), 0);
         assert_eq!(next_lookup_zs_batch.len(), 0);
     }
+    if has_custom_arguments {
+        assert_eq!(local_custom_argument_polys_batch.len(), n);
+        assert_eq!(next_custom_argument_polys_batch.len(), n);
+    }
     assert_eq!(partial_products_batch.len(), n);
     assert_eq!(s_sigmas_batch.len(), n);
 
@@ -314,10 +337,26 @@ pub(crate) fn eval_vanishing_poly_base_batch<F: RichField + Extendable<D>, const
             denominator_values.clear();
         }
 
+        // If there are custom arguments in the circuit, then we add their constraints.
+        let vanishing_custom_argument_terms = if has_custom_arguments {
+            let local_wires = vars.local_wires.into_iter().copied().collect::<Vec<_>>();
+            eval_custom_argument_constraints_base(
+                common_data,
+                &local_wires,
+                local_custom_argument_polys_batch[k],
+                next_custom_argument_polys_batch[k],
+                custom_argument_challenges,
+                l_0_x,
+            )
+        } else {
+            Vec::new()
+        };
+
         let vanishing_terms = vanishing_z_1_terms
             .iter()
             .chain(vanishing_partial_products_terms.iter())
             .chain(vanishing_all_lookup_terms.iter())
+            .chain(vanishing_custom_argument_terms.iter())
             .chain(constraint_terms);
         let res = plonk_common::reduce_with_powers_multi(vanishing_terms, alphas);
         res_batch.push(res);
@@ -329,6 +368,95 @@ pub(crate) fn eval_vanishing_poly_base_batch<F: RichField + Extendable<D>, const
     res_batch
 }
 
+/// Evaluates the constraints of all custom arguments, in order.
+fn eval_custom_argument_constraints<F: RichField + Extendable<D>, const D: usize>(
+    common_data: &CommonCircuitData<F, D>,
+    local_wires: &[F::Extension],
+    local_polys: &[F::Extension],
+    next_polys: &[F::Extension],
+    challenges: &[F],
+    l_0_x: F::Extension,
+) -> Vec<F::Extension> {
+    let challenges = challenges
+        .iter()
+        .map(|&c| F::Extension::from_basefield(c))
+        .collect::<Vec<_>>();
+    let (mut poly_start, mut challenge_start) = (0, 0);
+    let mut constraints = Vec::new();
+    for argument in &common_data.custom_arguments {
+        let polys = poly_start..poly_start + argument.0.num_polys();
+        let argument_challenges = challenge_start..challenge_start + argument.0.num_challenges();
+        constraints.extend(argument.0.eval(CustomArgumentVars {
+            local_wires,
+            local_polys: &local_polys[polys.clone()],
+            next_polys: &next_polys[polys.clone()],
+            challenges: &challenges[argument_challenges.clone()],
+            l_0: l_0_x,
+        }));
+        poly_start = polys.end;
+        challenge_start = argument_challenges.end;
+    }
+    constraints
+}
+
+/// Like `eval_custom_argument_constraints`, but specialized for base field points.
+fn eval_custom_argument_constraints_base<F: RichField + Extendable<D>, const D: usize>(
+    common_data: &CommonCircuitData<F, D>,
+    local_wires: &[F],
+    local_polys: &[F],
+    next_polys: &[F],
+    challenges: &[F],
+    l_0_x: F,
+) -> Vec<F> {
+    let (mut poly_start, mut challenge_start) = (0, 0);
+    let mut constraints = Vec::new();
+    for argument in &common_data.custom_arguments {
+        let polys = poly_start..poly_start + argument.0.num_polys();
+        let argument_challenges = challenge_start..challenge_start + argument.0.num_challenges();
+        constraints.extend(argument.0.eval_base(CustomArgumentVars {
+            local_wires,
+            local_polys: &local_polys[polys.clone()],
+            next_polys: &next_polys[polys.clone()],
+            challenges: &challenges[argument_challenges.clone()],
+            l_0: l_0_x,
+        }));
+        poly_start = polys.end;
+        challenge_start = argument_challenges.end;
+    }
+    constraints
+}
+
+/// Same as `eval_custom_argument_constraints`, but for the recursive case.
+fn eval_custom_argument_constraints_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    common_data: &CommonCircuitData<F, D>,
+    local_wires: &[ExtensionTarget<D>],
+    local_polys: &[ExtensionTarget<D>],
+    next_polys: &[ExtensionTarget<D>],
+    challenges: &[Target],
+    l_0_x: ExtensionTarget<D>,
+) -> Vec<ExtensionTarget<D>> {
+    let (mut poly_start, mut challenge_start) = (0, 0);
+    let mut constraints = Vec::new();
+    for argument in &common_data.custom_arguments {
+        let polys = poly_start..poly_start + argument.0.num_polys();
+        let argument_challenges = challenge_start..challenge_start + argument.0.num_challenges();
+        constraints.extend(argument.0.eval_circuit(
+            builder,
+            CustomArgumentVarsTarget {
+                local_wires,
+                local_polys: &local_polys[polys.clone()],
+                next_polys: &next_polys[polys.clone()],
+                challenges: &challenges[argument_challenges.clone()],
+                l_0: l_0_x,
+            },
+        ));
+        poly_start = polys.end;
+        challenge_start = argument_challenges.end;
+    }
+    constraints
+}
+
 /// Evaluates all lookup constraints, based on the logarithmic derivatives paper (<https://eprint.iacr.org/2022/1530.pdf>),
 /// following the Tip5 paper's implementation (<https://eprint.iacr.org/2023/107.pdf>).
 ///
@@ -699,6 +827,12 @@ pub fn evaluate_gate_constraints<F: RichField + Extendable<D>, const D: usize>(
 /// Returns a vector of `num_gate_constraints * vars_batch.len()` field elements. The constraints
 /// corresponding to `vars_batch[i]` are found in `result[i], result[vars_batch.len() + i],
 /// result[2 * vars_batch.len() + i], ...`.
+///
+/// Every gate is evaluated at every point, even though each row of the trace holds a single gate.
+/// The filters only vanish on the subgroup the trace is defined on, while the prover evaluates the
+/// vanishing polynomial on a disjoint coset of the LDE, where filters are non-zero but for a
+/// negligible fraction of points. Skipping the gates a row of the trace doesn't hold would thus
+/// make the quotient wrong.
 pub fn evaluate_gate_constraints_base_batch<F: RichField + Extendable<D>, const D: usize>(
     common_data: &CommonCircuitData<F, D>,
     vars_batch: EvaluationVarsBaseBatch<F>,
@@ -809,12 +943,15 @@ pub(crate) fn eval_vanishing_poly_circuit<F: RichField + Extendable<D>, const D:
     next_zs: &[ExtensionTarget<D>],
     local_lookup_zs: &[ExtensionTarget<D>],
     next_lookup_zs: &[ExtensionTarget<D>],
+    local_custom_argument_polys: &[ExtensionTarget<D>],
+    next_custom_argument_polys: &[ExtensionTarget<D>],
     partial_products: &[ExtensionTarget<D>],
     s_sigmas: &[ExtensionTarget<D>],
     betas: &[Target],
     gammas: &[Target],
     alphas: &[Target],
     deltas: &[Target],
+    custom_argument_challenges: &[Target],
 ) -> Vec<ExtensionTarget<D>> {
     let has_lookup = common_data.num_lookup_polys != 0;
     let max_degree = common_data.quotient_degree_factor;
@@ -915,10 +1052,25 @@ pub(crate) fn eval_vanishing_poly_circuit<F: RichField + Extendable<D>, const D:
         vanishing_partial_products_terms.extend(partial_product_checks);
     }
 
+    let vanishing_custom_argument_terms = with_context!(
+        builder,
+        "evaluate custom argument constraints",
+        eval_custom_argument_constraints_circuit(
+            builder,
+            common_data,
+            vars.local_wires,
+            local_custom_argument_polys,
+            next_custom_argument_polys,
+            custom_argument_challenges,
+            l_0_x,
+        )
+    );
+
     let vanishing_terms = [
         vanishing_z_1_terms,
         vanishing_partial_products_terms,
         vanishing_all_lookup_terms,
+        vanishing_custom_argument_terms,
         constraint_terms,
     ]
     .concat();
@@ -3,6 +3,7 @@
 use core::ops::Range;
 
 use crate::field::extension::algebra::ExtensionAlgebra;
+use crate::field::extension::packed::PackedExtension;
 use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::packed::PackedField;
 use crate::field::types::Field;
@@ -37,8 +38,6 @@ pub struct EvaluationVarsBase<'a, F: Field> {
 }
 
 /// Like `EvaluationVarsBase`, but packed.
-// It's a separate struct because `EvaluationVarsBase` implements `get_local_ext` and we do not yet
-// have packed extension fields.
 #[derive(Debug, Copy, Clone)]
 pub struct EvaluationVarsBasePacked<'a, P: PackedField> {
     pub local_constants: PackedStridedView<'a, P>,
@@ -131,6 +130,17 @@ impl<F: Field> EvaluationVarsBase<'_, F> {
     }
 }
 
+impl<P: PackedField> EvaluationVarsBasePacked<'_, P> {
+    pub fn get_local_ext<const D: usize>(&self, wire_range: Range<usize>) -> PackedExtension<P, D>
+    where
+        P::Scalar: Extendable<D>,
+    {
+        debug_assert_eq!(wire_range.len(), D);
+        let arr = self.local_wires.view(wire_range).try_into().unwrap();
+        PackedExtension::from_basefield_array(arr)
+    }
+}
+
 /// Iterator of views (`EvaluationVarsBase`) into a `EvaluationVarsBaseBatch`.
 #[derive(Debug)]
 pub struct EvaluationVarsBaseBatchIter<'a, F: Field> {
@@ -71,12 +71,15 @@ pub(crate) fn verify_with_challenges<
         next_zs,
         local_lookup_zs,
         next_lookup_zs,
+        &proof.openings.custom_argument_polys,
+        &proof.openings.custom_argument_polys_next,
         partial_products,
         s_sigmas,
         &challenges.plonk_betas,
         &challenges.plonk_gammas,
         &challenges.plonk_alphas,
         &challenges.plonk_deltas,
+        &challenges.plonk_custom_argument_challenges,
     );
 
     // Check each polynomial identity, of the form `vanishing(x) = Z_H(x) quotient(x)`, at zeta.
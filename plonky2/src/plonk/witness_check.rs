@@ -0,0 +1,301 @@
+//! Native checking of a circuit's constraints against a witness, without proving.
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec::Vec};
+use core::fmt;
+
+use crate::field::extension::Extendable;
+use crate::field::polynomial::PolynomialValues;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::generate_partial_witness;
+use crate::iop::target::Target;
+use crate::iop::witness::{ConflictingAssignment, PartialWitness, Witness};
+use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
+use crate::plonk::config::{GenericConfig, Hasher};
+use crate::plonk::prover::set_lookup_wires;
+use crate::plonk::vars::EvaluationVarsBaseBatch;
+
+/// The first constraint found not to hold by [`check_witness`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintViolation {
+    /// Two connected targets were assigned different values, either by the inputs or by the
+    /// witness generators.
+    Copy {
+        /// The target whose assignment conflicted with the value of its partition.
+        target: Target,
+    },
+    /// Witness generation failed for another reason, e.g. because some generators never ran.
+    Generation(String),
+    /// A gate constraint doesn't vanish on the generated witness.
+    Gate {
+        /// The row of the gate.
+        row: usize,
+        /// The id of the gate.
+        gate: String,
+        /// The index of the constraint within the gate's constraints.
+        constraint: usize,
+    },
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Copy { target } => write!(
+                f,
+                "copy constraint violated: {target:?} was assigned two different values"
+            ),
+            Self::Generation(message) => write!(f, "witness generation failed: {message}"),
+            Self::Gate {
+                row,
+                gate,
+                constraint,
+            } => write!(
+                f,
+                "constraint {constraint} of {gate} doesn't hold at row {row}"
+            ),
+        }
+    }
+}
+
+/// Generates the witness for `inputs` and checks it against all the circuit's gate constraints,
+/// as the prover would before committing to it. Copy constraints are enforced while generating
+/// the witness, as connected targets share a single value.
+///
+/// Returns the violation in the lowest row, if any. Lookup and custom arguments are not checked.
+pub fn check_witness<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+) -> Result<(), ConstraintViolation> {
+    let generation_error = |e: anyhow::Error| match e.downcast_ref::<ConflictingAssignment<F>>() {
+        Some(conflict) => ConstraintViolation::Copy {
+            target: conflict.target,
+        },
+        None => ConstraintViolation::Generation(format!("{e}")),
+    };
+    let mut partition_witness =
+        generate_partial_witness(inputs, prover_data, common_data).map_err(generation_error)?;
+    set_lookup_wires(prover_data, common_data, &mut partition_witness).map_err(generation_error)?;
+
+    let public_inputs = partition_witness.get_targets(&prover_data.public_inputs);
+    let public_inputs_hash = C::InnerHasher::hash_no_pad(&public_inputs);
+    let witness = partition_witness.full_witness();
+    let constants: Vec<PolynomialValues<F>> = prover_data.constants_sigmas_commitment.polynomials
+        [common_data.constants_range()]
+    .iter()
+    .map(|poly| poly.clone().fft())
+    .collect();
+
+    let num_prefix_constants =
+        common_data.selectors_info.num_selectors() + common_data.num_lookup_selectors;
+    let mut first_violation: Option<ConstraintViolation> = None;
+    for (i, gate) in common_data.gates.iter().enumerate() {
+        // A row holds gate `i` if the gate's selector evaluates to `i` there.
+        let selector = &constants[common_data.selectors_info.selector_indices[i]];
+        let rows: Vec<usize> = (0..common_data.degree())
+            .filter(|&row| selector.values[row] == F::from_canonical_usize(i))
+            .collect();
+        if rows.is_empty() {
+            continue;
+        }
+
+        let local_constants: Vec<F> = constants[num_prefix_constants..]
+            .iter()
+            .flat_map(|poly| rows.iter().map(|&row| poly.values[row]))
+            .collect();
+        let local_wires: Vec<F> = witness
+            .wire_values
+            .columns()
+            .flat_map(|column| rows.iter().map(|&row| column[row]))
+            .collect();
+        let vars_batch = EvaluationVarsBaseBatch::new(
+            rows.len(),
+            &local_constants,
+            &local_wires,
+            &public_inputs_hash,
+        );
+
+        // Constraint `j` for the `k`-th row is at index `j * rows.len() + k`.
+        let constraints = gate.0.eval_unfiltered_base_batch(vars_batch);
+        let violation = constraints
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.is_zero())
+            .map(|(index, _)| (rows[index % rows.len()], index / rows.len()))
+            .min();
+        if let Some((row, constraint)) = violation {
+            let is_first = match &first_violation {
+                Some(ConstraintViolation::Gate { row: first_row, .. }) => row < *first_row,
+                _ => true,
+            };
+            if is_first {
+                first_violation = Some(ConstraintViolation::Gate {
+                    row,
+                    gate: gate.0.id(),
+                    constraint,
+                });
+            }
+        }
+    }
+
+    first_violation.map_or(Ok(()), Err)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::gates::arithmetic_base::ArithmeticGate;
+    use crate::gates::gate::Gate;
+    use crate::iop::ext_target::ExtensionTarget;
+    use crate::iop::generator::WitnessGeneratorRef;
+    use crate::iop::witness::WitnessWrite;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+    use crate::plonk::vars::{EvaluationTargets, EvaluationVars};
+    use crate::util::serialization::{Buffer, IoResult};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// An `ArithmeticGate` without generators, whose wires must all be set by the inputs.
+    #[derive(Debug)]
+    struct UngeneratedArithmeticGate(ArithmeticGate);
+
+    impl Gate<F, D> for UngeneratedArithmeticGate {
+        fn id(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn serialize(
+            &self,
+            dst: &mut Vec<u8>,
+            common_data: &CommonCircuitData<F, D>,
+        ) -> IoResult<()> {
+            Gate::<F, D>::serialize(&self.0, dst, common_data)
+        }
+
+        fn deserialize(src: &mut Buffer, common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+            Gate::<F, D>::deserialize(src, common_data).map(Self)
+        }
+
+        fn eval_unfiltered(
+            &self,
+            vars: EvaluationVars<F, D>,
+        ) -> Vec<<F as Extendable<D>>::Extension> {
+            Gate::<F, D>::eval_unfiltered(&self.0, vars)
+        }
+
+        fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+            Gate::<F, D>::eval_unfiltered_base_batch(&self.0, vars_base)
+        }
+
+        fn eval_unfiltered_circuit(
+            &self,
+            builder: &mut CircuitBuilder<F, D>,
+            vars: EvaluationTargets<D>,
+        ) -> Vec<ExtensionTarget<D>> {
+            self.0.eval_unfiltered_circuit(builder, vars)
+        }
+
+        fn generators(
+            &self,
+            _row: usize,
+            _local_constants: &[F],
+        ) -> Vec<WitnessGeneratorRef<F, D>> {
+            Vec::new()
+        }
+
+        fn num_wires(&self) -> usize {
+            Gate::<F, D>::num_wires(&self.0)
+        }
+
+        fn num_constants(&self) -> usize {
+            Gate::<F, D>::num_constants(&self.0)
+        }
+
+        fn degree(&self) -> usize {
+            Gate::<F, D>::degree(&self.0)
+        }
+
+        fn num_constraints(&self) -> usize {
+            Gate::<F, D>::num_constraints(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_check_witness() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let z = builder.mul(x, y);
+        builder.register_public_input(z);
+        let six = builder.constant(F::from_canonical_u64(6));
+        builder.connect(z, six);
+        let data = builder.build::<C>();
+
+        let inputs = |values: &[(Target, u64)]| -> Result<PartialWitness<F>> {
+            let mut pw = PartialWitness::new();
+            for &(t, v) in values {
+                pw.set_target(t, F::from_canonical_u64(v))?;
+            }
+            Ok(pw)
+        };
+
+        assert_eq!(data.check_witness(inputs(&[(x, 2), (y, 3)])?), Ok(()));
+        assert!(matches!(
+            data.check_witness(inputs(&[(x, 2), (y, 4)])?),
+            Err(ConstraintViolation::Copy { .. })
+        ));
+        assert!(matches!(
+            data.check_witness(inputs(&[(x, 2)])?),
+            Err(ConstraintViolation::Generation(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_witness_gate_violation() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let gate = UngeneratedArithmeticGate(ArithmeticGate::new_from_config(&config));
+        let row = builder.add_gate(gate, vec![F::ONE, F::ONE]);
+        let data = builder.build::<C>();
+
+        // Slot 1 computes `2 * 3 + 1`, which isn't 8.
+        let inputs = |output: u64| -> Result<PartialWitness<F>> {
+            let mut pw = PartialWitness::new();
+            for (column, value) in [(4, 2), (5, 3), (6, 1), (7, output)] {
+                pw.set_target(Target::wire(row, column), F::from_canonical_u64(value))?;
+            }
+            Ok(pw)
+        };
+
+        assert_eq!(data.check_witness(inputs(7)?), Ok(()));
+        let violation = data.check_witness(inputs(8)?).unwrap_err();
+        assert_eq!(
+            violation,
+            ConstraintViolation::Gate {
+                row,
+                gate: format!(
+                    "{:?}",
+                    UngeneratedArithmeticGate(ArithmeticGate::new_from_config(&config))
+                ),
+                constraint: 1,
+            }
+        );
+        assert_eq!(
+            violation.to_string(),
+            format!(
+                "constraint 1 of UngeneratedArithmeticGate(ArithmeticGate {{ num_ops: 20 }}) doesn't hold at row {row}"
+            )
+        );
+
+        Ok(())
+    }
+}
@@ -11,7 +11,7 @@ use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_proofs::MerkleProofTarget;
 use crate::iop::ext_target::ExtensionTarget;
-use crate::iop::target::{BoolTarget, Target};
+use crate::iop::target::BoolTarget;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierCircuitTarget};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
@@ -119,14 +119,6 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         })
     }
 
-    /// Computes `if b { v0 } else { v1 }`.
-    fn select_vec(&mut self, b: BoolTarget, v0: &[Target], v1: &[Target]) -> Vec<Target> {
-        v0.iter()
-            .zip_eq(v1)
-            .map(|(t0, t1)| self.select(b, *t0, *t1))
-            .collect()
-    }
-
     /// Computes `if b { h0 } else { h1 }`.
     pub(crate) fn select_hash(
         &mut self,
@@ -201,6 +193,16 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             plonk_zs_next: self.select_vec_ext(b, &os0.plonk_zs_next, &os1.plonk_zs_next),
             lookup_zs: self.select_vec_ext(b, &os0.lookup_zs, &os1.lookup_zs),
             next_lookup_zs: self.select_vec_ext(b, &os0.next_lookup_zs, &os1.next_lookup_zs),
+            custom_argument_polys: self.select_vec_ext(
+                b,
+                &os0.custom_argument_polys,
+                &os1.custom_argument_polys,
+            ),
+            custom_argument_polys_next: self.select_vec_ext(
+                b,
+                &os0.custom_argument_polys_next,
+                &os1.custom_argument_polys_next,
+            ),
             partial_products: self.select_vec_ext(b, &os0.partial_products, &os1.partial_products),
             quotient_polys: self.select_vec_ext(b, &os0.quotient_polys, &os1.quotient_polys),
         }
@@ -1,7 +1,7 @@
 #![allow(clippy::int_plus_one)] // Makes more sense for some inequalities below.
 
 #[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
 
 use anyhow::{ensure, Result};
 
@@ -36,9 +36,11 @@ impl<C: GenericConfig<D>, const D: usize> VerifierOnlyCircuitData<C, D> {
         let circuit_digest =
             HashOut::from_partial(&slice[len - 4 - 4 * cap_len..len - 4 * cap_len]);
 
+        // The circuit version isn't part of the public inputs.
         Ok(Self {
             circuit_digest,
             constants_sigmas_cap,
+            circuit_version: String::new(),
         })
     }
 }
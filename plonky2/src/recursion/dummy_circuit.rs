@@ -89,6 +89,21 @@ where
 /// Generate a circuit matching a given `CommonCircuitData`.
 pub fn dummy_circuit<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     common_data: &CommonCircuitData<F, D>,
+) -> CircuitData<F, C, D> {
+    let circuit = dummy_circuit_with_degree::<F, C, D>(common_data, common_data.degree_bits());
+    assert_eq!(&circuit.common, common_data);
+    circuit
+}
+
+/// Generate a circuit with the configuration, gates and number of public inputs of a given
+/// `CommonCircuitData`, but with `2^degree_bits` rows.
+pub(crate) fn dummy_circuit_with_degree<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    common_data: &CommonCircuitData<F, D>,
+    degree_bits: usize,
 ) -> CircuitData<F, C, D> {
     let config = common_data.config.clone();
     assert!(
@@ -98,7 +113,7 @@ pub fn dummy_circuit<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
 
     // Number of `NoopGate`s to add to get a circuit of size `degree` in the end.
     // Need to account for public input hashing, a `PublicInputGate` and a `ConstantGate`.
-    let degree = common_data.degree();
+    let degree = 1 << degree_bits;
     let num_noop_gate = degree - common_data.num_public_inputs.div_ceil(8) - 2;
 
     let mut builder = CircuitBuilder::<F, D>::new(config);
@@ -112,9 +127,7 @@ pub fn dummy_circuit<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
         builder.add_virtual_public_input();
     }
 
-    let circuit = builder.build::<C>();
-    assert_eq!(&circuit.common, common_data);
-    circuit
+    builder.build::<C>()
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
@@ -227,6 +240,7 @@ where
                     hiding: false,
                     degree_bits: 0,
                     reduction_arity_bits: vec![],
+                    oracle_cap_heights: vec![],
                 },
                 gates: vec![],
                 selectors_info: SelectorsInfo {
@@ -242,12 +256,14 @@ where
                 num_lookup_polys: 0,
                 num_lookup_selectors: 0,
                 luts: vec![],
+                custom_arguments: vec![],
             },
             verifier_only: VerifierOnlyCircuitData {
                 constants_sigmas_cap: MerkleCap(vec![]),
                 circuit_digest: <<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash::from_bytes(
                     &vec![0; <<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::HASH_SIZE],
                 ),
+                circuit_version: String::new(),
             },
         };
 
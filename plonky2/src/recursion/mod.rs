@@ -7,4 +7,10 @@
 pub mod conditional_recursive_verifier;
 pub mod cyclic_recursion;
 pub mod dummy_circuit;
+pub mod multi_degree_verifier;
+pub mod padded_public_inputs;
+pub mod public_input_accumulator;
 pub mod recursive_verifier;
+#[cfg(feature = "std")]
+pub mod scheduler;
+pub mod shrink;
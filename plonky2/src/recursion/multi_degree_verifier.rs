@@ -0,0 +1,300 @@
+//! A verifier circuit for proofs of any circuit built from a template, i.e. with its configuration,
+//! gates and number of public inputs, with a number of rows in a given range. Systems which can't
+//! rebuild their outer circuits whenever an inner circuit changes can use it as an outer layer, as
+//! long as the inner circuits keep to the template and to the supported degrees.
+//!
+//! The size of the inner circuit is a witness: a [`MultiDegreeVerifierCircuit`] contains a
+//! recursive verifier for each supported degree, and one-hot flags select the one verifying the
+//! inner proof, while the others verify dummy proofs. The size of the circuit is thus the sum of
+//! the sizes of recursive verifiers for each supported degree, i.e. it grows linearly with the
+//! number of supported degrees; it isn't a single verifier whose size is independent of the range.
+//!
+//! It is therefore not a universal verifier: it doesn't accept inner circuits with other gates or
+//! selectors than the template's, nor any degree up to a maximum at a fixed cost.
+//!
+//! The verifier data of the inner circuit is a witness as well. The circuit recomputes the inner
+//! circuit digest from the constants cap and the selected degree, and exposes it as its first public
+//! inputs, followed by the inner public inputs, so that a verifier of the outer proof knows which
+//! inner circuit was verified, and at which size.
+//!
+//! The selectors and the other parts of the common data of the inner circuits only depend on the
+//! template and on the degree, so they are fixed per verifier;
+//! [`MultiDegreeVerifierCircuit::prove`] checks that the inner circuit has exactly the common data
+//! of its degree. Inner circuits must use the default domain separator, as the circuit digest
+//! depends on it.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use anyhow::{ensure, Result};
+use hashbrown::HashMap;
+use itertools::Itertools;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::iop::target::BoolTarget;
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{
+    CircuitConfig, CircuitData, CircuitLayout, CommonCircuitData, VerifierCircuitData,
+    VerifierCircuitTarget,
+};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut, Hasher};
+use crate::plonk::plonk_common::PlonkOracle;
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use crate::recursion::dummy_circuit::{dummy_circuit_with_degree, dummy_proof};
+
+/// A circuit verifying proofs of any circuit built from a template with a supported number of
+/// rows, see the module documentation.
+#[derive(Debug)]
+pub struct MultiDegreeVerifierCircuit<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    pub data: CircuitData<F, C, D>,
+    min_degree_bits: usize,
+    /// The common data of the supported inner circuits, by degree.
+    inner_common: Vec<CommonCircuitData<F, D>>,
+    /// Proofs of dummy circuits, verified by the branches of the degrees other than the inner one.
+    dummy_proofs: Vec<ProofWithPublicInputs<F, C, D>>,
+    degree_flags: Vec<BoolTarget>,
+    inner_proofs: Vec<ProofWithPublicInputsTarget<D>>,
+    inner_constants_sigmas_cap: MerkleCapTarget,
+}
+
+impl<F, C, const D: usize> MultiDegreeVerifierCircuit<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    /// Builds a circuit with the given configuration, verifying proofs of circuits built from
+    /// `template` with `2^degree_bits` rows, for any `degree_bits` in the given range.
+    pub fn new(
+        template: &CommonCircuitData<F, D>,
+        degree_bits: RangeInclusive<usize>,
+        config: &CircuitConfig,
+    ) -> Result<Self> {
+        ensure!(!degree_bits.is_empty(), "No degree is supported");
+        ensure!(
+            !template.config.zero_knowledge,
+            "Universal verification of zero-knowledge circuits is not supported"
+        );
+        let min_degree_bits = *degree_bits.start();
+
+        let mut inner_common = Vec::new();
+        let mut dummy_verifier_data = Vec::new();
+        let mut dummy_proofs = Vec::new();
+        for degree_bits in degree_bits {
+            let dummy_circuit = dummy_circuit_with_degree::<F, C, D>(template, degree_bits);
+            ensure!(
+                dummy_circuit.common.degree_bits() == degree_bits,
+                "The template has too many public inputs for 2^{degree_bits} rows"
+            );
+            dummy_proofs.push(dummy_proof(&dummy_circuit, HashMap::new())?);
+            dummy_verifier_data.push(dummy_circuit.verifier_only);
+            inner_common.push(dummy_circuit.common);
+        }
+        let cap_height = |common: &CommonCircuitData<F, D>| {
+            common
+                .fri_params
+                .oracle_cap_height(PlonkOracle::CONSTANTS_SIGMAS.index)
+        };
+        ensure!(
+            inner_common.iter().map(cap_height).all_equal(),
+            "The constants caps of the supported inner circuits have different heights"
+        );
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let degree_flags = (0..inner_common.len())
+            .map(|_| builder.add_virtual_bool_target_safe())
+            .collect::<Vec<_>>();
+        let num_flags = builder.add_many(degree_flags.iter().map(|b| b.target));
+        builder.assert_one(num_flags);
+
+        let inner_constants_sigmas_cap = builder.add_virtual_cap(cap_height(&inner_common[0]));
+        let domain_separator_digest = builder.constants(&C::Hasher::hash_pad(&[]).to_vec());
+
+        let mut inner_proofs = Vec::new();
+        let mut inner_circuit_digest: Option<HashOutTarget> = None;
+        let mut inner_public_inputs: Option<Vec<_>> = None;
+        for (i, common) in inner_common.iter().enumerate() {
+            let flag = degree_flags[i];
+            let degree_bits = builder.constant(F::from_canonical_usize(common.degree_bits()));
+            let circuit_digest = builder.hash_n_to_hash_no_pad::<C::Hasher>(
+                inner_constants_sigmas_cap
+                    .0
+                    .iter()
+                    .flat_map(|h| h.elements)
+                    .chain(domain_separator_digest.iter().copied())
+                    .chain([degree_bits])
+                    .collect(),
+            );
+            let verifier_data = VerifierCircuitTarget {
+                constants_sigmas_cap: inner_constants_sigmas_cap.clone(),
+                circuit_digest,
+            };
+            let dummy_verifier_data = builder.constant_verifier_data(&dummy_verifier_data[i]);
+            let verifier_data =
+                builder.select_verifier_data(flag, &verifier_data, &dummy_verifier_data);
+
+            let proof = builder.add_virtual_proof_with_pis(common);
+            builder.verify_proof::<C>(&proof, &verifier_data, common);
+
+            inner_circuit_digest = Some(match inner_circuit_digest {
+                Some(selected) => builder.select_hash(flag, circuit_digest, selected),
+                None => circuit_digest,
+            });
+            inner_public_inputs = Some(match inner_public_inputs {
+                Some(selected) => builder.select_vec(flag, &proof.public_inputs, &selected),
+                None => proof.public_inputs.clone(),
+            });
+            inner_proofs.push(proof);
+        }
+        builder.register_public_inputs(&inner_circuit_digest.unwrap().elements);
+        builder.register_public_inputs(&inner_public_inputs.unwrap());
+
+        Ok(Self {
+            data: builder.build::<C>(),
+            min_degree_bits,
+            inner_common,
+            dummy_proofs,
+            degree_flags,
+            inner_proofs,
+            inner_constants_sigmas_cap,
+        })
+    }
+
+    /// The range of the degrees of the supported inner circuits, in bits.
+    pub fn degree_bits(&self) -> RangeInclusive<usize> {
+        self.min_degree_bits..=self.min_degree_bits + self.inner_common.len() - 1
+    }
+
+    /// The common data of the supported inner circuits with `2^degree_bits` rows.
+    pub fn inner_common_data(&self, degree_bits: usize) -> Option<&CommonCircuitData<F, D>> {
+        self.inner_common
+            .get(degree_bits.checked_sub(self.min_degree_bits)?)
+    }
+
+    /// Proves that `inner_proof` is a valid proof of the circuit `inner`.
+    pub fn prove(
+        &self,
+        inner_proof: &ProofWithPublicInputs<F, C, D>,
+        inner: &VerifierCircuitData<F, C, D>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let degree_bits = inner.common.degree_bits();
+        ensure!(
+            self.inner_common_data(degree_bits) == Some(&inner.common),
+            "The inner circuit isn't built from the template with a supported degree"
+        );
+        ensure!(
+            CircuitLayout::<F, C, D>::circuit_digest(
+                &inner.verifier_only.constants_sigmas_cap,
+                &[],
+                degree_bits
+            ) == inner.verifier_only.circuit_digest,
+            "The inner circuit has a custom domain separator"
+        );
+
+        let index = degree_bits - self.min_degree_bits;
+        let mut pw = PartialWitness::new();
+        for (i, (&flag, proof)) in self
+            .degree_flags
+            .iter()
+            .zip_eq(&self.inner_proofs)
+            .enumerate()
+        {
+            pw.set_bool_target(flag, i == index)?;
+            if i == index {
+                pw.set_proof_with_pis_target(proof, inner_proof)?;
+            } else {
+                pw.set_proof_with_pis_target(proof, &self.dummy_proofs[i])?;
+            }
+        }
+        pw.set_cap_target(
+            &self.inner_constants_sigmas_cap,
+            &inner.verifier_only.constants_sigmas_cap,
+        )?;
+        self.data.prove(pw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Field;
+    use crate::gates::noop::NoopGate;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Builds a circuit from `template` with `2^degree_bits` rows, exposing `x^k` for `k` in
+    /// `1..=num_public_inputs`, and proves it for `x = 3`.
+    fn inner_proof(
+        template: &CommonCircuitData<F, D>,
+        degree_bits: usize,
+    ) -> Result<(ProofWithPublicInputs<F, C, D>, VerifierCircuitData<F, C, D>)> {
+        let mut builder = CircuitBuilder::<F, D>::new(template.config.clone());
+        for gate in &template.gates {
+            builder.add_gate_to_gate_set(gate.clone());
+        }
+        let x = builder.add_virtual_target();
+        let mut y = x;
+        builder.register_public_input(y);
+        for _ in 1..template.num_public_inputs {
+            y = builder.mul(y, x);
+            builder.register_public_input(y);
+        }
+        while builder.num_gates() < (1 << (degree_bits - 1)) + 1 {
+            builder.add_gate(NoopGate, Vec::new());
+        }
+        let data = builder.build::<C>();
+        assert_eq!(data.common.degree_bits(), degree_bits);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3))?;
+        let proof = data.prove(pw)?;
+        Ok((proof, data.verifier_data()))
+    }
+
+    #[test]
+    fn test_multi_degree_verifier() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        // The template is the common data of one of the inner circuits.
+        let mut empty_common = CircuitBuilder::<F, D>::new(config.clone())
+            .build::<C>()
+            .common;
+        empty_common.num_public_inputs = 3;
+        let template = inner_proof(&empty_common, 5)?.1.common;
+
+        let verifier = MultiDegreeVerifierCircuit::<F, C, D>::new(&template, 5..=6, &config)?;
+        assert_eq!(verifier.degree_bits(), 5..=6);
+
+        // Proofs of different circuits, of both supported sizes, are verified by the same
+        // circuit, which exposes the inner circuit digests.
+        for degree_bits in [5, 6] {
+            let (proof, inner) = inner_proof(&template, degree_bits)?;
+            let outer_proof = verifier.prove(&proof, &inner)?;
+            assert_eq!(
+                outer_proof.public_inputs[..4],
+                inner.verifier_only.circuit_digest.elements
+            );
+            assert_eq!(outer_proof.public_inputs[4..], proof.public_inputs);
+            verifier.data.verify(outer_proof)?;
+        }
+
+        // Circuits which are too large, or which aren't built from the template, are rejected.
+        let (proof, inner) = inner_proof(&template, 7)?;
+        assert!(verifier.prove(&proof, &inner).is_err());
+        let mut other_template = template.clone();
+        other_template.num_public_inputs = 2;
+        let (proof, inner) = inner_proof(&other_template, 5)?;
+        assert!(verifier.prove(&proof, &inner).is_err());
+
+        Ok(())
+    }
+}
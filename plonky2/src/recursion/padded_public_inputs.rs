@@ -0,0 +1,175 @@
+//! A padding convention for public inputs, so that circuits with different numbers of public inputs
+//! can share the same [`CommonCircuitData`](crate::plonk::circuit_data::CommonCircuitData), and be
+//! verified by a single aggregation circuit.
+//!
+//! With a declared maximum of `max_len` values, a circuit exposes `max_len + 1` public inputs: the
+//! number `len` of actual values, the values, then `max_len - len` zeros. The prefix and the
+//! padding are constants of the circuit, so hashing all the public inputs, as the recursive
+//! verifier does, commits to the values and their number unambiguously.
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+use anyhow::{ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::field::types::{Field, PrimeField64};
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, Hasher};
+
+/// Returns the number of public inputs of a circuit padding its values to `max_len`.
+pub const fn num_padded_public_inputs(max_len: usize) -> usize {
+    max_len + 1
+}
+
+/// Returns the padded encoding of `values`.
+pub fn pad_public_inputs<F: Field>(values: &[F], max_len: usize) -> Result<Vec<F>> {
+    ensure!(
+        values.len() <= max_len,
+        "{} public inputs exceed the maximum of {}",
+        values.len(),
+        max_len
+    );
+    let mut padded = Vec::with_capacity(num_padded_public_inputs(max_len));
+    padded.push(F::from_canonical_usize(values.len()));
+    padded.extend_from_slice(values);
+    padded.resize(num_padded_public_inputs(max_len), F::ZERO);
+    Ok(padded)
+}
+
+/// Returns the values encoded by padded public inputs, checking the encoding.
+pub fn unpad_public_inputs<F: PrimeField64>(padded: &[F]) -> Result<&[F]> {
+    ensure!(!padded.is_empty(), "Missing the number of public inputs");
+    let len = padded[0].to_canonical_u64() as usize;
+    ensure!(
+        len < padded.len(),
+        "{} public inputs exceed the maximum of {}",
+        len,
+        padded.len() - 1
+    );
+    let (values, padding) = padded[1..].split_at(len);
+    ensure!(
+        padding.iter().all(|x| x.is_zero()),
+        "Nonzero public input padding"
+    );
+    Ok(values)
+}
+
+/// Returns the hash of padded public inputs, e.g. as exposed by an aggregation circuit.
+pub fn padded_public_inputs_hash<F: RichField, H: Hasher<F, Hash = HashOut<F>>>(
+    padded: &[F],
+) -> HashOut<F> {
+    H::hash_no_pad(padded)
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Registers `targets` as the public inputs of this circuit, padded to `max_len` values. This
+    /// must be the only registration of public inputs.
+    pub fn register_padded_public_inputs(&mut self, targets: &[Target], max_len: usize) {
+        assert!(
+            targets.len() <= max_len,
+            "{} public inputs exceed the maximum of {}",
+            targets.len(),
+            max_len
+        );
+        assert_eq!(
+            self.num_public_inputs(),
+            0,
+            "Padded public inputs must be the only public inputs"
+        );
+        let len = self.constant(F::from_canonical_usize(targets.len()));
+        let zero = self.zero();
+        self.register_public_input(len);
+        self.register_public_inputs(targets);
+        self.register_public_inputs(&vec![zero; max_len - targets.len()]);
+    }
+
+    /// Returns the hash of the padded public inputs of an inner proof, matching
+    /// [`padded_public_inputs_hash`].
+    pub fn padded_public_inputs_hash<H: AlgebraicHasher<F>>(
+        &mut self,
+        padded: &[Target],
+    ) -> HashOutTarget {
+        self.hash_n_to_hash_no_pad::<H>(padded.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+    const MAX_LEN: usize = 4;
+
+    /// A circuit exposing the products of `num_outputs` pairs of private inputs.
+    fn products_circuit(num_outputs: usize) -> (CircuitData<F, C, D>, Vec<Target>) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let inputs = builder.add_virtual_targets(2 * num_outputs);
+        let outputs = inputs
+            .chunks(2)
+            .map(|pair| builder.mul(pair[0], pair[1]))
+            .collect::<Vec<_>>();
+        builder.register_padded_public_inputs(&outputs, MAX_LEN);
+        (builder.build::<C>(), inputs)
+    }
+
+    #[test]
+    fn test_padded_public_inputs() -> Result<()> {
+        let (one_output, one_output_inputs) = products_circuit(1);
+        let (three_outputs, three_outputs_inputs) = products_circuit(3);
+        assert_eq!(one_output.common, three_outputs.common);
+        let common = &one_output.common;
+
+        // An aggregation circuit accepting proofs of either circuit.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let proof_t = builder.add_virtual_proof_with_pis(common);
+        let verifier_data_t =
+            builder.add_virtual_verifier_data(common.config.fri_config.cap_height);
+        builder.verify_proof::<C>(&proof_t, &verifier_data_t, common);
+        let hash = builder.padded_public_inputs_hash::<H>(&proof_t.public_inputs);
+        builder.register_public_inputs(&verifier_data_t.circuit_digest.elements);
+        builder.register_public_inputs(&hash.elements);
+        let aggregation = builder.build::<C>();
+
+        for (inner, inputs, expected) in [
+            (&one_output, &one_output_inputs, vec![6]),
+            (&three_outputs, &three_outputs_inputs, vec![6, 20, 42]),
+        ] {
+            let mut pw = PartialWitness::new();
+            let values = (2..2 + inputs.len() as u64)
+                .map(F::from_canonical_u64)
+                .collect::<Vec<_>>();
+            pw.set_target_arr(inputs, &values)?;
+            let proof = inner.prove(pw)?;
+            let expected = expected
+                .into_iter()
+                .map(F::from_canonical_u64)
+                .collect::<Vec<_>>();
+            assert_eq!(unpad_public_inputs(&proof.public_inputs)?, expected);
+            assert_eq!(proof.public_inputs, pad_public_inputs(&expected, MAX_LEN)?);
+
+            let mut pw = PartialWitness::new();
+            pw.set_proof_with_pis_target(&proof_t, &proof)?;
+            pw.set_verifier_data_target(&verifier_data_t, &inner.verifier_only)?;
+            let outer_proof = aggregation.prove(pw)?;
+            assert_eq!(
+                outer_proof.public_inputs[4..],
+                padded_public_inputs_hash::<F, H>(&proof.public_inputs).elements
+            );
+            aggregation.verify(outer_proof)?;
+        }
+
+        assert!(pad_public_inputs(&[F::ONE; MAX_LEN + 1], MAX_LEN).is_err());
+        assert!(unpad_public_inputs(&[F::TWO, F::ONE]).is_err());
+        assert!(unpad_public_inputs(&[F::ONE, F::ONE, F::ONE]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+//! A canonical accumulation of public inputs across recursion layers.
+//!
+//! Rather than forwarding the public inputs of all the proofs it verifies, a recursion layer can
+//! expose a single digest of them. The digests are defined as follows, with `H::hash_no_pad`
+//! hashing a sequence of field elements:
+//!
+//! - the digest of the public inputs `pis` of a leaf proof is `H::hash_no_pad([0, len(pis), pis])`,
+//! - the digest of a layer whose children have the digests `d_0, ..., d_{n-1}`, with `n > 0`, is
+//!   `H::hash_no_pad([1, n, root])`, where `root` is the root of the Merkle tree whose leaves are
+//!   the `d_i`, padded with zero digests to a power of two, as computed by a native
+//!   [`MerkleTree`] with a cap height of zero.
+//!
+//! The leading tag separates leaf digests from layer digests, and the lengths make the padding
+//! unambiguous. By convention, a layer registers its digest as its first public inputs.
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+
+use crate::field::extension::Extendable;
+use crate::hash::hash_types::{HashOut, HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
+use crate::hash::merkle_tree::MerkleTree;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::{AlgebraicHasher, Hasher};
+
+/// The tag of leaf digests.
+const LEAF_TAG: u64 = 0;
+/// The tag of layer digests.
+const LAYER_TAG: u64 = 1;
+
+/// Native computation of public input digests, see the module documentation.
+#[derive(Debug)]
+pub struct PublicInputAccumulator<F: RichField, H: Hasher<F, Hash = HashOut<F>>>(
+    PhantomData<(F, H)>,
+);
+
+impl<F: RichField, H: Hasher<F, Hash = HashOut<F>>> PublicInputAccumulator<F, H> {
+    /// Returns the digest of the public inputs of a leaf proof.
+    pub fn leaf_digest(public_inputs: &[F]) -> HashOut<F> {
+        let mut inputs = Vec::with_capacity(public_inputs.len() + 2);
+        inputs.push(F::from_canonical_u64(LEAF_TAG));
+        inputs.push(F::from_canonical_usize(public_inputs.len()));
+        inputs.extend_from_slice(public_inputs);
+        H::hash_no_pad(&inputs)
+    }
+
+    /// Returns the digest of a layer whose children have the given digests.
+    pub fn layer_digest(children: &[HashOut<F>]) -> HashOut<F> {
+        assert!(!children.is_empty(), "A layer must have children");
+        let mut leaves = children
+            .iter()
+            .map(|digest| digest.elements.to_vec())
+            .collect::<Vec<_>>();
+        leaves.resize(
+            children.len().next_power_of_two(),
+            HashOut::<F>::ZERO.elements.to_vec(),
+        );
+        let root = MerkleTree::<F, H>::new(leaves, 0).cap.0[0];
+
+        let mut inputs = Vec::with_capacity(NUM_HASH_OUT_ELTS + 2);
+        inputs.push(F::from_canonical_u64(LAYER_TAG));
+        inputs.push(F::from_canonical_usize(children.len()));
+        inputs.extend_from_slice(&root.elements);
+        H::hash_no_pad(&inputs)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Returns the digest of the public inputs of a leaf proof, matching
+    /// [`PublicInputAccumulator::leaf_digest`].
+    pub fn public_inputs_digest<H: AlgebraicHasher<F>>(
+        &mut self,
+        public_inputs: &[Target],
+    ) -> HashOutTarget {
+        let mut inputs = Vec::with_capacity(public_inputs.len() + 2);
+        inputs.push(self.constant(F::from_canonical_u64(LEAF_TAG)));
+        inputs.push(self.constant(F::from_canonical_usize(public_inputs.len())));
+        inputs.extend_from_slice(public_inputs);
+        self.hash_n_to_hash_no_pad::<H>(inputs)
+    }
+
+    /// Returns the digest of a layer whose children have the given digests, matching
+    /// [`PublicInputAccumulator::layer_digest`].
+    pub fn accumulate_public_input_digests<H: AlgebraicHasher<F>>(
+        &mut self,
+        children: &[HashOutTarget],
+    ) -> HashOutTarget {
+        assert!(!children.is_empty(), "A layer must have children");
+        let zero = self.zero();
+        let mut leaves = children
+            .iter()
+            .map(|digest| digest.elements.to_vec())
+            .collect::<Vec<_>>();
+        leaves.resize(
+            children.len().next_power_of_two(),
+            vec![zero; NUM_HASH_OUT_ELTS],
+        );
+        let root = self.merkle_root_from_leaves::<H>(leaves);
+
+        let mut inputs = Vec::with_capacity(NUM_HASH_OUT_ELTS + 2);
+        inputs.push(self.constant(F::from_canonical_u64(LAYER_TAG)));
+        inputs.push(self.constant(F::from_canonical_usize(children.len())));
+        inputs.extend_from_slice(&root.elements);
+        self.hash_n_to_hash_no_pad::<H>(inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::{Field, Sample};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+    type Accumulator = PublicInputAccumulator<F, H>;
+
+    #[test]
+    fn test_public_input_accumulator() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let leaf_inputs = builder.add_virtual_public_input_arr::<3>();
+        let sum = builder.add_many(leaf_inputs);
+        builder.register_public_input(sum);
+        let leaf = builder.build::<C>();
+        let prove_leaf = |values: [F; 3]| {
+            let mut pw = PartialWitness::new();
+            pw.set_target_arr(&leaf_inputs, &values)?;
+            leaf.prove(pw)
+        };
+
+        // A layer verifying three leaf proofs.
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let verifier_data = builder.constant_verifier_data(&leaf.verifier_only);
+        let (proofs, digests): (Vec<_>, Vec<_>) = (0..3)
+            .map(|_| {
+                let proof = builder.add_virtual_proof_with_pis(&leaf.common);
+                builder.verify_proof::<C>(&proof, &verifier_data, &leaf.common);
+                let digest = builder.public_inputs_digest::<H>(&proof.public_inputs);
+                (proof, digest)
+            })
+            .unzip();
+        let digest = builder.accumulate_public_input_digests::<H>(&digests);
+        builder.register_public_inputs(&digest.elements);
+        let layer = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        let mut leaf_digests = Vec::new();
+        for proof_t in &proofs {
+            let proof = prove_leaf(F::rand_array())?;
+            leaf_digests.push(Accumulator::leaf_digest(&proof.public_inputs));
+            pw.set_proof_with_pis_target(proof_t, &proof)?;
+        }
+        let proof = layer.prove(pw)?;
+        assert_eq!(
+            proof.public_inputs,
+            Accumulator::layer_digest(&leaf_digests).elements
+        );
+        layer.verify(proof)?;
+
+        // The encoding is unambiguous with respect to padding and tags.
+        assert_ne!(
+            Accumulator::layer_digest(&leaf_digests[..2]),
+            Accumulator::layer_digest(&[leaf_digests[0], leaf_digests[1], HashOut::ZERO])
+        );
+        assert_ne!(
+            Accumulator::leaf_digest(&leaf_digests[0].elements),
+            Accumulator::layer_digest(&leaf_digests[..1])
+        );
+        assert_ne!(
+            Accumulator::leaf_digest(&[]),
+            Accumulator::leaf_digest(&[F::ZERO])
+        );
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@ use crate::hash::hash_types::{HashOutTarget, RichField};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierCircuitTarget};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
-use crate::plonk::plonk_common::salt_size;
+use crate::plonk::plonk_common::{salt_size, PlonkOracle};
 use crate::plonk::proof::{
     OpeningSetTarget, ProofChallengesTarget, ProofTarget, ProofWithPublicInputsTarget,
 };
@@ -89,12 +89,15 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 next_zs,
                 local_lookup_zs,
                 next_lookup_zs,
+                &proof.openings.custom_argument_polys,
+                &proof.openings.custom_argument_polys_next,
                 partial_products,
                 s_sigmas,
                 &challenges.plonk_betas,
                 &challenges.plonk_gammas,
                 &challenges.plonk_alphas,
                 &challenges.plonk_deltas,
+                &challenges.plonk_custom_argument_challenges,
             )
         );
 
@@ -149,13 +152,19 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     fn add_virtual_proof(&mut self, common_data: &CommonCircuitData<F, D>) -> ProofTarget<D> {
         let config = &common_data.config;
         let fri_params = &common_data.fri_params;
-        let cap_height = fri_params.config.cap_height;
+        let cap_height = |oracle: PlonkOracle| fri_params.oracle_cap_height(oracle.index);
 
+        common_data
+            .check_zk_mode()
+            .expect("Inner circuit data is inconsistent");
         let salt = salt_size(common_data.fri_params.hiding);
         let num_leaves_per_oracle = &mut vec![
             common_data.num_preprocessed_polys(),
             config.num_wires + salt,
-            common_data.num_zs_partial_products_polys() + common_data.num_all_lookup_polys() + salt,
+            common_data.num_zs_partial_products_polys()
+                + common_data.num_all_lookup_polys()
+                + common_data.num_custom_argument_polys()
+                + salt,
         ];
 
         if common_data.num_quotient_polys() > 0 {
@@ -163,9 +172,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
 
         ProofTarget {
-            wires_cap: self.add_virtual_cap(cap_height),
-            plonk_zs_partial_products_cap: self.add_virtual_cap(cap_height),
-            quotient_polys_cap: self.add_virtual_cap(cap_height),
+            wires_cap: self.add_virtual_cap(cap_height(PlonkOracle::WIRES)),
+            plonk_zs_partial_products_cap: self
+                .add_virtual_cap(cap_height(PlonkOracle::ZS_PARTIAL_PRODUCTS)),
+            quotient_polys_cap: self.add_virtual_cap(cap_height(PlonkOracle::QUOTIENT)),
             openings: self.add_opening_set(common_data),
             opening_proof: self.add_virtual_fri_proof(num_leaves_per_oracle, fri_params),
         }
@@ -189,6 +199,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             plonk_zs_next: self.add_virtual_extension_targets(num_challenges),
             lookup_zs: self.add_virtual_extension_targets(num_lookups),
             next_lookup_zs: self.add_virtual_extension_targets(num_lookups),
+            custom_argument_polys: self
+                .add_virtual_extension_targets(common_data.num_custom_argument_polys()),
+            custom_argument_polys_next: self
+                .add_virtual_extension_targets(common_data.num_custom_argument_polys()),
             partial_products: self.add_virtual_extension_targets(total_partial_products),
             quotient_polys: self.add_virtual_extension_targets(common_data.num_quotient_polys()),
         }
@@ -207,14 +221,20 @@ mod tests {
     use log::{info, Level};
 
     use super::*;
+    use crate::field::extension::quadratic::QuadraticExtension;
+    use crate::field::goldilocks_field::GoldilocksField;
     use crate::fri::reduction_strategies::FriReductionStrategy;
     use crate::fri::FriConfig;
     use crate::gadgets::lookup::{OTHER_TABLE, TIP5_TABLE};
     use crate::gates::lookup_table::LookupTable;
     use crate::gates::noop::NoopGate;
+    use crate::hash::hash_types::HashOut;
+    use crate::hash::poseidon::{PoseidonHash, TruncatedPoseidonHash};
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_data::{CircuitConfig, VerifierOnlyCircuitData};
-    use crate::plonk::config::{KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::config::{
+        KeccakGoldilocksConfig, PoseidonGoldilocksConfig, PoseidonGoldilocksQuinticConfig,
+    };
     use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
     use crate::plonk::prover::prove;
     use crate::util::timing::TimingTree;
@@ -311,6 +331,39 @@ mod tests {
         Ok(())
     }
 
+    /// A configuration with ~128-bit Merkle digests.
+    #[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+    struct TruncatedPoseidonGoldilocksConfig;
+    impl GenericConfig<2> for TruncatedPoseidonGoldilocksConfig {
+        type F = GoldilocksField;
+        type FE = QuadraticExtension<Self::F>;
+        type Hasher = TruncatedPoseidonHash<2>;
+        type InnerHasher = PoseidonHash;
+    }
+
+    #[test]
+    fn test_recursive_verifier_truncated_hash() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type TC = TruncatedPoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+
+        let (proof, vd, common_data) = dummy_proof::<F, TC, D>(&config, 4_000)?;
+        let (full_proof, _, _) = dummy_proof::<F, C, D>(&config, 4_000)?;
+        let proof_size = proof.to_bytes().len();
+        assert_eq!(proof_size, common_data.estimate_proof_size::<TC>());
+        assert!(proof_size < full_proof.to_bytes().len());
+        test_serialization(&proof, &vd, &common_data)?;
+
+        let (proof, vd, common_data) =
+            recursive_proof::<F, C, TC, D>(proof, vd, common_data, &config, None, true, true)?;
+        test_serialization(&proof, &vd, &common_data)?;
+
+        Ok(())
+    }
+
     /// Creates a chain of recursive proofs where the last proof is made as small as reasonably
     /// possible, using a high rate, high PoW bits, etc.
     #[test]
@@ -341,15 +394,7 @@ mod tests {
         assert_eq!(common_data.degree_bits(), 12);
 
         // A high-rate recursive proof, designed to be verifiable with fewer routed wires.
-        let high_rate_config = CircuitConfig {
-            fri_config: FriConfig {
-                rate_bits: 7,
-                proof_of_work_bits: 16,
-                num_query_rounds: 12,
-                ..standard_config.fri_config.clone()
-            },
-            ..standard_config
-        };
+        let high_rate_config = CircuitConfig::high_rate_config();
         let (proof, vd, common_data) = recursive_proof::<F, C, C, D>(
             proof,
             vd,
@@ -389,6 +434,88 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_recursive_verifier_high_arity() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        // An inner proof using a FRI arity of 2^6, which is too large for a single
+        // `CosetInterpolationGate` in the standard recursion config.
+        let standard_config = CircuitConfig::standard_recursion_config();
+        let inner_config = CircuitConfig {
+            fri_config: FriConfig {
+                reduction_strategy: FriReductionStrategy::ConstantArityBits(6, 5),
+                ..standard_config.fri_config.clone()
+            },
+            ..standard_config.clone()
+        };
+        let (proof, vd, common_data) = dummy_proof::<F, C, D>(&inner_config, 4_000)?;
+        assert_eq!(common_data.fri_params.reduction_arity_bits, vec![6]);
+
+        let (proof, vd, common_data) = recursive_proof::<F, C, C, D>(
+            proof,
+            vd,
+            common_data,
+            &standard_config,
+            None,
+            false,
+            false,
+        )?;
+        test_serialization(&proof, &vd, &common_data)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_verifier_optimized_cap_heights() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        // The inner proof's wires, `Z`s and quotient caps are higher than its constants cap and
+        // the caps of the commit phase.
+        let standard_config = CircuitConfig::standard_recursion_config();
+        let inner_config = CircuitConfig {
+            optimize_cap_heights: true,
+            ..standard_config.clone()
+        };
+        let (proof, vd, common_data) = dummy_proof::<F, C, D>(&inner_config, 4_000)?;
+        assert_eq!(common_data.fri_params.oracle_cap_heights, vec![4, 5, 5, 5]);
+        test_serialization(&proof, &vd, &common_data)?;
+
+        let (proof, vd, common_data) = recursive_proof::<F, C, C, D>(
+            proof,
+            vd,
+            common_data,
+            &standard_config,
+            None,
+            false,
+            false,
+        )?;
+        test_serialization(&proof, &vd, &common_data)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_verifier_quintic() -> Result<()> {
+        init_logger();
+        const D: usize = 5;
+        type C = PoseidonGoldilocksQuinticConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+
+        let (proof, vd, common_data) = dummy_proof::<F, C, D>(&config, 4_000)?;
+        let (proof, vd, common_data) =
+            recursive_proof::<F, C, C, D>(proof, vd, common_data, &config, None, true, false)?;
+        test_serialization(&proof, &vd, &common_data)?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_recursive_verifier_multi_hash() -> Result<()> {
         init_logger();
@@ -411,6 +538,56 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_constant_verifier_data_checked() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let (proof, vd, common_data) = dummy_proof::<F, C, D>(&config, 4_000)?;
+
+        // The verifier data of the inner proof is also exposed as a witness, which is checked
+        // against the fixed one.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let pt = builder.add_virtual_proof_with_pis(&common_data);
+        let inner_data = builder.constant_verifier_data_checked(&vd, &common_data);
+        builder.verify_proof::<C>(&pt, &inner_data, &common_data);
+        let witness_data = builder.add_virtual_verifier_data(config.fri_config.cap_height);
+        builder.connect_constant_verifier_data(&witness_data, &vd);
+        let data = builder.build::<C>();
+
+        let prove = |circuit_digest| {
+            let mut pw = PartialWitness::new();
+            pw.set_proof_with_pis_target(&pt, &proof)?;
+            pw.set_cap_target(&witness_data.constants_sigmas_cap, &vd.constants_sigmas_cap)?;
+            pw.set_hash_target(witness_data.circuit_digest, circuit_digest)?;
+            data.prove(pw)
+        };
+        data.verify(prove(vd.circuit_digest)?)?;
+        assert!(prove(HashOut::ZERO).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The verifier data is not that of a circuit with the given common data"
+    )]
+    fn test_constant_verifier_data_checked_mismatch() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let (_, _, common_data) = dummy_proof::<F, C, D>(&config, 100).unwrap();
+        let mut other_config = config.clone();
+        other_config.fri_config.cap_height += 1;
+        let (_, other_vd, _) = dummy_proof::<F, C, D>(&other_config, 100).unwrap();
+
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        builder.constant_verifier_data_checked(&other_vd, &common_data);
+    }
+
     type Proof<F, C, const D: usize> = (
         ProofWithPublicInputs<F, C, D>,
         VerifierOnlyCircuitData<C, D>,
@@ -459,8 +636,8 @@ mod tests {
 
         let tip5_index = builder.add_lookup_table_from_pairs(table);
 
-        let output_a = builder.add_lookup_from_index(initial_a, tip5_index);
-        let output_b = builder.add_lookup_from_index(initial_b, tip5_index);
+        let output_a = builder.lookup(tip5_index, initial_a);
+        let output_b = builder.lookup(tip5_index, initial_b);
 
         for _ in 0..num_dummy_gates + 1 {
             builder.add_gate(NoopGate, vec![]);
@@ -518,9 +695,9 @@ mod tests {
         let other_table = OTHER_TABLE.to_vec();
 
         let tip5_index = builder.add_lookup_table_from_pairs(table);
-        let output_a = builder.add_lookup_from_index(initial_a, tip5_index);
+        let output_a = builder.lookup(tip5_index, initial_a);
 
-        let output_b = builder.add_lookup_from_index(initial_b, tip5_index);
+        let output_b = builder.lookup(tip5_index, initial_b);
         let sum = builder.add(output_a, output_b);
 
         let s = first_out + second_out;
@@ -529,7 +706,7 @@ mod tests {
         let table2: LookupTable = Arc::new((0..256).zip_eq(other_table).collect());
 
         let other_index = builder.add_lookup_table_from_pairs(table2);
-        let output_final = builder.add_lookup_from_index(sum, other_index);
+        let output_final = builder.lookup(other_index, sum);
 
         builder.register_public_input(initial_a);
         builder.register_public_input(initial_b);
@@ -593,10 +770,10 @@ mod tests {
         let out_b = table[look_val_b].1;
 
         let tip5_index = builder.add_lookup_table_from_pairs(table);
-        let output_b = builder.add_lookup_from_index(initial_b, tip5_index);
-        let mut output = builder.add_lookup_from_index(initial_a, tip5_index);
+        let output_b = builder.lookup(tip5_index, initial_b);
+        let mut output = builder.lookup(tip5_index, initial_a);
         for _ in 0..514 {
-            output = builder.add_lookup_from_index(initial_a, tip5_index);
+            output = builder.lookup(tip5_index, initial_a);
         }
 
         builder.register_public_input(initial_a);
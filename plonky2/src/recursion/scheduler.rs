@@ -0,0 +1,431 @@
+//! A scheduler running a graph of proving jobs, e.g. leaf proofs, the aggregation nodes verifying
+//! them recursively and a final wrapper proof, with bounded parallelism and memory usage.
+//!
+//! Each job is given the outputs of its dependencies, and only starts once they all succeeded.
+//! Jobs can only depend on previously added jobs, so the graph is acyclic by construction. A job
+//! which fails is retried up to [`RecursionScheduler::with_max_retries`] times, after which no
+//! other job is started and [`RecursionScheduler::run`] returns its error.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use std::{fmt, thread};
+
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+
+/// The index of a job in a [`RecursionScheduler`].
+pub type JobId = usize;
+
+type ProveFn<'a, T> = Box<dyn Fn(&[&T]) -> Result<T> + Send + Sync + 'a>;
+
+struct Job<'a, T> {
+    name: String,
+    dependencies: Vec<JobId>,
+    memory_estimate: usize,
+    prove: ProveFn<'a, T>,
+}
+
+impl<T> fmt::Debug for Job<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Job")
+            .field("name", &self.name)
+            .field("dependencies", &self.dependencies)
+            .field("memory_estimate", &self.memory_estimate)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs a graph of jobs producing values of type `T`, typically proofs.
+#[derive(Debug)]
+pub struct RecursionScheduler<'a, T> {
+    jobs: Vec<Job<'a, T>>,
+    max_parallelism: usize,
+    memory_budget: Option<usize>,
+    max_retries: usize,
+}
+
+/// The statistics of a successful job.
+#[derive(Clone, Debug)]
+pub struct JobReport {
+    pub name: String,
+    /// The number of times the job was run, including the successful one.
+    pub attempts: usize,
+    /// The time spent in the successful attempt.
+    pub duration: Duration,
+    /// The time spent in all attempts.
+    pub total_duration: Duration,
+}
+
+/// The outputs of all jobs, and their statistics, both indexed by [`JobId`].
+#[derive(Debug)]
+pub struct SchedulerReport<T> {
+    pub outputs: Vec<T>,
+    pub jobs: Vec<JobReport>,
+}
+
+impl<'a, T: Send + Sync> RecursionScheduler<'a, T> {
+    /// Creates a scheduler running at most `max_parallelism` jobs at once.
+    pub fn new(max_parallelism: usize) -> Self {
+        assert!(max_parallelism > 0, "At least one job must run at a time");
+        Self {
+            jobs: Vec::new(),
+            max_parallelism,
+            memory_budget: None,
+            max_retries: 0,
+        }
+    }
+
+    /// Only starts a job if the memory estimates of the running jobs, including itself, fit in
+    /// `memory_budget`. A job exceeding the budget on its own still runs, but alone.
+    pub const fn with_memory_budget(mut self, memory_budget: usize) -> Self {
+        self.memory_budget = Some(memory_budget);
+        self
+    }
+
+    /// Retries each failed job up to `max_retries` times.
+    pub const fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Adds a job which, given the outputs of `dependencies` in the same order, produces its own
+    /// output with `prove`, using about `memory_estimate` bytes.
+    pub fn add_job(
+        &mut self,
+        name: impl Into<String>,
+        dependencies: &[JobId],
+        memory_estimate: usize,
+        prove: impl Fn(&[&T]) -> Result<T> + Send + Sync + 'a,
+    ) -> JobId {
+        let id = self.jobs.len();
+        assert!(
+            dependencies.iter().all(|&d| d < id),
+            "Jobs can only depend on previously added jobs"
+        );
+        self.jobs.push(Job {
+            name: name.into(),
+            dependencies: dependencies.to_vec(),
+            memory_estimate,
+            prove: Box::new(prove),
+        });
+        id
+    }
+
+    pub fn num_jobs(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Runs all jobs, returning their outputs, or the error of the first job which failed after
+    /// all its retries.
+    pub fn run(self) -> Result<SchedulerReport<T>> {
+        let num_jobs = self.jobs.len();
+        let mut dependents = vec![Vec::new(); num_jobs];
+        for (id, job) in self.jobs.iter().enumerate() {
+            for &dependency in &job.dependencies {
+                dependents[dependency].push(id);
+            }
+        }
+
+        let state = Mutex::new(SchedulerState {
+            ready: (0..num_jobs)
+                .filter(|&id| self.jobs[id].dependencies.is_empty())
+                .collect(),
+            remaining_dependencies: self.jobs.iter().map(|j| j.dependencies.len()).collect(),
+            outputs: (0..num_jobs).map(|_| None).collect(),
+            reports: vec![None; num_jobs],
+            num_running: 0,
+            memory_in_use: 0,
+            num_finished: 0,
+            error: None,
+        });
+        let changed = Condvar::new();
+
+        let num_workers = self.max_parallelism.min(num_jobs);
+        thread::scope(|scope| {
+            for _ in 0..num_workers {
+                scope.spawn(|| self.work(&state, &changed, &dependents));
+            }
+        });
+
+        let state = state.into_inner().unwrap();
+        if let Some(error) = state.error {
+            return Err(error);
+        }
+        let outputs = state
+            .outputs
+            .into_iter()
+            .map(|output| {
+                // All workers are done, so no other reference to the outputs remains.
+                Arc::try_unwrap(output.unwrap()).unwrap_or_else(|_| unreachable!())
+            })
+            .collect();
+        let jobs = state.reports.into_iter().map(Option::unwrap).collect();
+        Ok(SchedulerReport { outputs, jobs })
+    }
+
+    /// The loop of a worker thread, which runs ready jobs until all are finished or one failed.
+    fn work(&self, state: &Mutex<SchedulerState<T>>, changed: &Condvar, dependents: &[Vec<JobId>]) {
+        loop {
+            let mut guard = state.lock().unwrap();
+            let id = loop {
+                if guard.error.is_some() || guard.num_finished == self.jobs.len() {
+                    return;
+                }
+                if let Some(id) = self.next_job(&mut guard) {
+                    break id;
+                }
+                guard = changed.wait(guard).unwrap();
+            };
+            let job = &self.jobs[id];
+            guard.num_running += 1;
+            guard.memory_in_use += job.memory_estimate;
+            let inputs = job
+                .dependencies
+                .iter()
+                .map(|&d| guard.outputs[d].clone().unwrap())
+                .collect::<Vec<_>>();
+            drop(guard);
+
+            let result = self.run_job(job, &inputs);
+            drop(inputs);
+
+            let mut guard = state.lock().unwrap();
+            guard.num_running -= 1;
+            guard.memory_in_use -= job.memory_estimate;
+            match result {
+                Ok((output, report)) => {
+                    guard.outputs[id] = Some(Arc::new(output));
+                    guard.reports[id] = Some(report);
+                    guard.num_finished += 1;
+                    for &dependent in &dependents[id] {
+                        guard.remaining_dependencies[dependent] -= 1;
+                        if guard.remaining_dependencies[dependent] == 0 {
+                            guard.ready.push_back(dependent);
+                        }
+                    }
+                }
+                Err(error) => {
+                    guard.error.get_or_insert(error);
+                }
+            }
+            changed.notify_all();
+        }
+    }
+
+    /// Removes the first ready job fitting in the memory budget from the queue.
+    fn next_job(&self, state: &mut SchedulerState<T>) -> Option<JobId> {
+        let position = state.ready.iter().position(|&id| {
+            let fits = self
+                .memory_budget
+                .is_none_or(|budget| state.memory_in_use + self.jobs[id].memory_estimate <= budget);
+            fits || state.num_running == 0
+        })?;
+        state.ready.remove(position)
+    }
+
+    fn run_job(&self, job: &Job<T>, inputs: &[Arc<T>]) -> Result<(T, JobReport)> {
+        let inputs = inputs.iter().map(|input| &**input).collect::<Vec<_>>();
+        let mut attempts = 0;
+        let mut total_duration = Duration::ZERO;
+        loop {
+            attempts += 1;
+            let start = Instant::now();
+            let result = (job.prove)(&inputs);
+            let duration = start.elapsed();
+            total_duration += duration;
+            match result {
+                Ok(output) => {
+                    info!(
+                        "Job {} succeeded in {:.4}s",
+                        job.name,
+                        duration.as_secs_f64()
+                    );
+                    let report = JobReport {
+                        name: job.name.clone(),
+                        attempts,
+                        duration,
+                        total_duration,
+                    };
+                    return Ok((output, report));
+                }
+                Err(error) if attempts <= self.max_retries => {
+                    warn!("Job {} failed on attempt {attempts}: {error}", job.name);
+                }
+                Err(error) => {
+                    return Err(anyhow!(
+                        "Job {} failed after {attempts} attempts: {error}",
+                        job.name
+                    ));
+                }
+            }
+        }
+    }
+}
+
+struct SchedulerState<T> {
+    /// The jobs whose dependencies all succeeded, in the order in which they became ready.
+    ready: VecDeque<JobId>,
+    remaining_dependencies: Vec<usize>,
+    outputs: Vec<Option<Arc<T>>>,
+    reports: Vec<Option<JobReport>>,
+    num_running: usize,
+    memory_in_use: usize,
+    num_finished: usize,
+    error: Option<anyhow::Error>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use anyhow::bail;
+
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::ProofWithPublicInputs;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Counts the jobs running at once, and keeps the maximum.
+    #[derive(Default)]
+    struct Concurrency {
+        current: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    impl Concurrency {
+        fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+            let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(current, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            let result = f();
+            self.current.fetch_sub(1, Ordering::SeqCst);
+            result
+        }
+    }
+
+    #[test]
+    fn test_scheduler_limits() -> Result<()> {
+        // A tree summing 8 leaves, with at most 3 jobs at once.
+        let concurrency = Concurrency::default();
+        let mut scheduler = RecursionScheduler::new(3);
+        let mut layer = (0..8u64)
+            .map(|i| scheduler.add_job(format!("leaf {i}"), &[], 1, move |_| Ok(i)))
+            .collect::<Vec<_>>();
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|pair| {
+                    scheduler.add_job("node", pair, 1, |inputs: &[&u64]| {
+                        Ok(concurrency.run(|| inputs.iter().copied().sum()))
+                    })
+                })
+                .collect();
+        }
+        let report = scheduler.run()?;
+        assert_eq!(report.outputs[layer[0]], 28);
+        assert_eq!(report.jobs.len(), 15);
+        assert!(concurrency.max.load(Ordering::SeqCst) <= 3);
+
+        // Jobs of 2 bytes each under a budget of 3 bytes run one at a time.
+        let concurrency = Concurrency::default();
+        let mut scheduler = RecursionScheduler::new(4).with_memory_budget(3);
+        let concurrency_ref = &concurrency;
+        for i in 0..4u64 {
+            scheduler.add_job("job", &[], 2, move |_| Ok(concurrency_ref.run(|| i)));
+        }
+        assert_eq!(scheduler.run()?.outputs, [0, 1, 2, 3]);
+        assert_eq!(concurrency.max.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scheduler_retries() -> Result<()> {
+        // A job failing once succeeds on its retry.
+        let failures = AtomicUsize::new(0);
+        let flaky = |_: &[&u64]| {
+            if failures.fetch_add(1, Ordering::SeqCst) == 0 {
+                bail!("transient failure");
+            }
+            Ok(1)
+        };
+        let mut scheduler = RecursionScheduler::new(2).with_max_retries(1);
+        let job = scheduler.add_job("flaky", &[], 0, flaky);
+        let report = scheduler.run()?;
+        assert_eq!(report.outputs[job], 1);
+        assert_eq!(report.jobs[job].attempts, 2);
+        assert!(report.jobs[job].total_duration >= report.jobs[job].duration);
+
+        // A job failing every time fails the run, and its dependents never start.
+        let started = AtomicUsize::new(0);
+        let mut scheduler = RecursionScheduler::new(2).with_max_retries(2);
+        let failing = scheduler.add_job("failing", &[], 0, |_: &[&u64]| bail!("failure"));
+        scheduler.add_job("dependent", &[failing], 0, |_| {
+            started.fetch_add(1, Ordering::SeqCst);
+            Ok(0)
+        });
+        let error = scheduler.run().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("failing failed after 3 attempts"));
+        assert_eq!(started.load(Ordering::SeqCst), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scheduler_aggregation() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+
+        // Leaves prove knowledge of a square root.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_target();
+        let square = builder.square(x);
+        builder.register_public_input(square);
+        let leaf = builder.build::<C>();
+
+        // Aggregation nodes verify two leaf proofs.
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let verifier_data = builder.constant_verifier_data(&leaf.verifier_only);
+        let proofs = [(); 2].map(|_| {
+            let proof = builder.add_virtual_proof_with_pis(&leaf.common);
+            builder.verify_proof::<C>(&proof, &verifier_data, &leaf.common);
+            builder.register_public_inputs(&proof.public_inputs);
+            proof
+        });
+        let aggregation = builder.build::<C>();
+
+        let prove_leaf = |x_value: F| {
+            let leaf = &leaf;
+            move |_: &[&ProofWithPublicInputs<F, C, D>]| {
+                let mut pw = PartialWitness::new();
+                pw.set_target(x, x_value)?;
+                leaf.prove(pw)
+            }
+        };
+        let [a, b] = F::rand_array();
+        let mut scheduler = RecursionScheduler::new(2);
+        let leaves = [
+            scheduler.add_job("leaf 0", &[], 0, prove_leaf(a)),
+            scheduler.add_job("leaf 1", &[], 0, prove_leaf(b)),
+        ];
+        let root = scheduler.add_job("aggregation", &leaves, 0, |inputs| {
+            let mut pw = PartialWitness::new();
+            for (target, &proof) in proofs.iter().zip(inputs) {
+                pw.set_proof_with_pis_target(target, proof)?;
+            }
+            aggregation.prove(pw)
+        });
+
+        let report = scheduler.run()?;
+        let root_proof = report.outputs[root].clone();
+        assert_eq!(root_proof.public_inputs, [a * a, b * b]);
+        aggregation.verify(root_proof)
+    }
+}
@@ -0,0 +1,236 @@
+//! Normalization of proofs to a standard [`CommonCircuitData`], so that proofs of heterogeneous
+//! circuits can enter a uniform aggregation tree, whose circuits all verify proofs with the same
+//! common data.
+//!
+//! A [`ShrinkCircuit`] verifies a proof of a given inner circuit, forwards its public inputs, and
+//! is padded so that its own common data is exactly the target one. This takes a single wrapping
+//! layer as long as verifying the inner proof fits in the target degree, and uses the gates of the
+//! target, which holds for inner circuits with the same configuration as the target.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use anyhow::{ensure, Result};
+
+use crate::field::extension::Extendable;
+use crate::gates::noop::NoopGate;
+use crate::hash::hash_types::RichField;
+use crate::iop::witness::{PartialWitness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::{
+    CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitData,
+};
+use crate::plonk::config::{AlgebraicHasher, GenericConfig};
+use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+
+/// Adds no-op gates to `builder` so that it is built with `2^degree_bits` rows.
+fn pad_to_degree<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    degree_bits: usize,
+) {
+    // Building adds a few gates, e.g. for public inputs and constants, so only pad to just above
+    // half of the target, and let the builder pad to the next power of two.
+    let min_gates = (1 << (degree_bits - 1)) + 1;
+    while builder.num_gates() < min_gates {
+        builder.add_gate(NoopGate, Vec::new());
+    }
+}
+
+/// Returns the common data of circuits with the given configuration, `2^degree_bits` rows and
+/// `num_public_inputs` public inputs, which have the gates needed to verify proofs of circuits
+/// with this configuration.
+pub fn standard_recursion_common_data<F, C, const D: usize>(
+    config: &CircuitConfig,
+    degree_bits: usize,
+    num_public_inputs: usize,
+) -> CommonCircuitData<F, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    // The gates needed to verify a proof depend on the size of the inner circuit, so recurse
+    // twice to reach a circuit verifying proofs of a recursive circuit.
+    let mut data = CircuitBuilder::<F, D>::new(config.clone())
+        .build::<C>()
+        .common;
+    for _ in 0..2 {
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let proof = builder.add_virtual_proof_with_pis(&data);
+        let verifier_data = builder.add_virtual_verifier_data(config.fri_config.cap_height);
+        builder.verify_proof::<C>(&proof, &verifier_data, &data);
+        data = builder.build::<C>().common;
+    }
+
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    for gate in data.gates {
+        builder.add_gate_to_gate_set(gate);
+    }
+    pad_to_degree(&mut builder, degree_bits);
+    let mut common = builder.build::<C>().common;
+    assert_eq!(
+        common.degree_bits(),
+        degree_bits,
+        "Degree too small for recursion"
+    );
+    common.num_public_inputs = num_public_inputs;
+    common
+}
+
+/// A circuit wrapping proofs of an inner circuit into proofs with the given common data, see the
+/// module documentation.
+#[derive(Debug)]
+pub struct ShrinkCircuit<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    pub data: CircuitData<F, C, D>,
+    inner_proof: ProofWithPublicInputsTarget<D>,
+}
+
+impl<F, C, const D: usize> ShrinkCircuit<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    /// Builds a circuit wrapping proofs of `inner` into proofs with the common data `target`. The
+    /// public inputs of the inner proofs are forwarded, followed by zeros up to the number of
+    /// public inputs of `target`.
+    pub fn new<InnerC>(
+        inner: &VerifierCircuitData<F, InnerC, D>,
+        target: &CommonCircuitData<F, D>,
+    ) -> Result<Self>
+    where
+        InnerC: GenericConfig<D, F = F>,
+        InnerC::Hasher: AlgebraicHasher<F>,
+    {
+        ensure!(
+            inner.common.num_public_inputs <= target.num_public_inputs,
+            "The inner circuit has {} public inputs, more than the {} of the target",
+            inner.common.num_public_inputs,
+            target.num_public_inputs
+        );
+
+        let mut builder = CircuitBuilder::<F, D>::new(target.config.clone());
+        let inner_proof = builder.add_virtual_proof_with_pis(&inner.common);
+        let verifier_data =
+            builder.constant_verifier_data_checked(&inner.verifier_only, &inner.common);
+        builder.verify_proof::<InnerC>(&inner_proof, &verifier_data, &inner.common);
+
+        builder.register_public_inputs(&inner_proof.public_inputs);
+        let zero = builder.zero();
+        while builder.num_public_inputs() < target.num_public_inputs {
+            builder.register_public_input(zero);
+        }
+        for gate in &target.gates {
+            builder.add_gate_to_gate_set(gate.clone());
+        }
+        pad_to_degree(&mut builder, target.degree_bits());
+
+        builder.goal_common_data = Some(target.clone());
+        let (data, success) = builder.try_build_with_options::<C>(true);
+        ensure!(
+            success,
+            "Proofs of the inner circuit can't be wrapped into the target common data"
+        );
+        Ok(Self { data, inner_proof })
+    }
+
+    /// Wraps `inner_proof`, a proof of the inner circuit.
+    pub fn prove<InnerC>(
+        &self,
+        inner_proof: &ProofWithPublicInputs<F, InnerC, D>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>>
+    where
+        InnerC: GenericConfig<D, F = F>,
+        InnerC::Hasher: AlgebraicHasher<F>,
+    {
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&self.inner_proof, inner_proof)?;
+        self.data.prove(pw)
+    }
+}
+
+/// Wraps `inner_proof`, a proof of `inner`, into a proof with the common data `target`, returning
+/// it along with the verifier data of the wrapping circuit. Use a [`ShrinkCircuit`] to wrap many
+/// proofs of the same circuit, which builds the wrapping circuit only once.
+pub fn shrink_to_common<F, C, InnerC, const D: usize>(
+    inner_proof: &ProofWithPublicInputs<F, InnerC, D>,
+    inner: &VerifierCircuitData<F, InnerC, D>,
+    target: &CommonCircuitData<F, D>,
+) -> Result<(ProofWithPublicInputs<F, C, D>, VerifierCircuitData<F, C, D>)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+    InnerC: GenericConfig<D, F = F>,
+    InnerC::Hasher: AlgebraicHasher<F>,
+{
+    let circuit = ShrinkCircuit::<F, C, D>::new(inner, target)?;
+    let proof = circuit.prove(inner_proof)?;
+    Ok((proof, circuit.data.verifier_data()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Field;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Builds a circuit with roughly `num_gates` gates and `num_public_inputs` public inputs, and
+    /// proves it.
+    fn inner_proof(
+        num_gates: usize,
+        num_public_inputs: usize,
+    ) -> Result<(ProofWithPublicInputs<F, C, D>, VerifierCircuitData<F, C, D>)> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let mut y = x;
+        for _ in 0..num_public_inputs {
+            y = builder.mul(y, x);
+            builder.register_public_input(y);
+        }
+        for _ in 0..num_gates {
+            builder.add_gate(NoopGate, Vec::new());
+        }
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::TWO)?;
+        let proof = data.prove(pw)?;
+        Ok((proof, data.verifier_data()))
+    }
+
+    #[test]
+    fn test_shrink_to_common() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let target = standard_recursion_common_data::<F, C, D>(&config, 13, 3);
+
+        let mut wrapped_verifier_data = Vec::new();
+        for (num_gates, num_public_inputs) in [(100, 1), (5_000, 3)] {
+            let (proof, inner) = inner_proof(num_gates, num_public_inputs)?;
+            let (wrapped, verifier_data) = shrink_to_common::<F, C, C, D>(&proof, &inner, &target)?;
+            assert_eq!(verifier_data.common, target);
+            assert_eq!(
+                wrapped.public_inputs[..num_public_inputs],
+                proof.public_inputs
+            );
+            assert!(wrapped.public_inputs[num_public_inputs..]
+                .iter()
+                .all(|x| x.is_zero()));
+            verifier_data.verify(wrapped)?;
+            wrapped_verifier_data.push(verifier_data.verifier_only);
+        }
+        assert_ne!(wrapped_verifier_data[0], wrapped_verifier_data[1]);
+
+        // The target must have enough public inputs.
+        let (_, inner) = inner_proof(100, 4)?;
+        assert!(ShrinkCircuit::<F, C, D>::new(&inner, &target).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+//! The source of the randomness used to blind proofs for zero-knowledge.
+//!
+//! Blinding values are sampled from the operating system's RNG by default.
+//! [`with_seeded_blinding`] replaces it on the current thread with a ChaCha RNG, which makes
+//! proofs reproducible when the prover is also single-threaded, see
+//! [`ProverOptions::deterministic`](crate::plonk::prover::ProverOptions::deterministic).
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(all(feature = "std", feature = "rand_chacha"))]
+use std::cell::RefCell;
+
+#[cfg(all(feature = "std", feature = "rand_chacha"))]
+use rand_chacha::rand_core::SeedableRng;
+#[cfg(all(feature = "std", feature = "rand_chacha"))]
+use rand_chacha::ChaCha20Rng;
+
+use crate::field::types::Sample;
+
+#[cfg(all(feature = "std", feature = "rand_chacha"))]
+std::thread_local! {
+    static SEEDED_RNG: RefCell<Option<ChaCha20Rng>> = const { RefCell::new(None) };
+}
+
+/// Samples a blinding value, from the seeded RNG of the current thread if there is one.
+pub fn blinding_rand<F: Sample>() -> F {
+    #[cfg(all(feature = "std", feature = "rand_chacha"))]
+    if let Some(value) = SEEDED_RNG.with(|rng| rng.borrow_mut().as_mut().map(F::sample)) {
+        return value;
+    }
+    F::rand()
+}
+
+/// Samples `n` blinding values, see [`blinding_rand`].
+pub fn blinding_rand_vec<F: Sample>(n: usize) -> Vec<F> {
+    (0..n).map(|_| blinding_rand()).collect()
+}
+
+/// Runs `f` with blinding values on the current thread drawn from a ChaCha RNG seeded with `seed`.
+///
+/// Blinding values sampled by other threads, e.g. by rayon workers, are not affected. Proofs made
+/// this way are only zero-knowledge if `seed` is kept secret.
+#[cfg(all(feature = "std", feature = "rand_chacha"))]
+pub fn with_seeded_blinding<R>(seed: [u8; 32], f: impl FnOnce() -> R) -> R {
+    /// Restores the previous RNG when dropped, even if `f` panics.
+    struct Restore(Option<ChaCha20Rng>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SEEDED_RNG.with(|rng| *rng.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(SEEDED_RNG.with(|rng| rng.replace(Some(ChaCha20Rng::from_seed(seed)))));
+    f()
+}
+
+#[cfg(all(test, feature = "std", feature = "rand_chacha"))]
+mod tests {
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_seeded_blinding() {
+        let a = with_seeded_blinding([1; 32], || blinding_rand_vec::<F>(8));
+        let b = with_seeded_blinding([1; 32], || blinding_rand_vec::<F>(8));
+        let c = with_seeded_blinding([2; 32], || blinding_rand_vec::<F>(8));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        // Seeds nest, and the previous RNG is restored afterwards.
+        let (d, e) = with_seeded_blinding([1; 32], || {
+            let d = blinding_rand_vec::<F>(4);
+            with_seeded_blinding([2; 32], blinding_rand::<F>);
+            (d, blinding_rand_vec::<F>(4))
+        });
+        assert_eq!([d, e].concat(), a);
+    }
+}
@@ -1,11 +1,41 @@
 #[cfg(not(feature = "std"))]
 use alloc::{
+    collections::BTreeMap,
+    format,
     string::{String, ToString},
     vec,
     vec::Vec,
 };
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
 
 use log::{log, Level};
+use serde::{Deserialize, Serialize};
+
+use crate::field::extension::Extendable;
+use crate::gates::gate::GateInstance;
+use crate::hash::hash_types::RichField;
+
+/// A snapshot of the builder counters tracked by a [`ContextTree`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ContextCounts {
+    /// The number of gate instances, i.e. of rows.
+    pub gates: usize,
+    /// The number of copy constraints.
+    pub copy_constraints: usize,
+    /// The number of witness generators explicitly added to the builder.
+    pub generators: usize,
+}
+
+impl ContextCounts {
+    fn delta(&self, enter: &Self) -> Self {
+        Self {
+            gates: self.gates - enter.gates,
+            copy_constraints: self.copy_constraints - enter.copy_constraints,
+            generators: self.generators - enter.generators,
+        }
+    }
+}
 
 /// The hierarchy of contexts, and the gate count contributed by each one. Useful for debugging.
 #[derive(Debug)]
@@ -14,10 +44,10 @@ pub(crate) struct ContextTree {
     name: String,
     /// The level at which to log this scope and its children.
     level: log::Level,
-    /// The gate count when this scope was created.
-    enter_gate_count: usize,
-    /// The gate count when this scope was destroyed, or None if it has not yet been destroyed.
-    exit_gate_count: Option<usize>,
+    /// The builder counters when this scope was created.
+    enter_counts: ContextCounts,
+    /// The builder counters when this scope was destroyed, or None if it has not yet been destroyed.
+    exit_counts: Option<ContextCounts>,
     /// Any child contexts.
     children: Vec<ContextTree>,
 }
@@ -27,34 +57,18 @@ impl ContextTree {
         Self {
             name: "root".to_string(),
             level: Level::Debug,
-            enter_gate_count: 0,
-            exit_gate_count: None,
+            enter_counts: ContextCounts::default(),
+            exit_counts: None,
             children: vec![],
         }
     }
 
     /// Whether this context is still in scope.
     const fn is_open(&self) -> bool {
-        self.exit_gate_count.is_none()
-    }
-
-    /// A description of the stack of currently-open scopes.
-    pub fn open_stack(&self) -> String {
-        let mut stack = Vec::new();
-        self.open_stack_helper(&mut stack);
-        stack.join(" > ")
-    }
-
-    fn open_stack_helper(&self, stack: &mut Vec<String>) {
-        if self.is_open() {
-            stack.push(self.name.clone());
-            if let Some(last_child) = self.children.last() {
-                last_child.open_stack_helper(stack);
-            }
-        }
+        self.exit_counts.is_none()
     }
 
-    pub fn push(&mut self, ctx: &str, mut level: log::Level, current_gate_count: usize) {
+    pub fn push(&mut self, ctx: &str, mut level: log::Level, current_counts: ContextCounts) {
         assert!(self.is_open());
 
         // We don't want a scope's log level to be stronger than that of its parent.
@@ -62,7 +76,7 @@ impl ContextTree {
 
         if let Some(last_child) = self.children.last_mut() {
             if last_child.is_open() {
-                last_child.push(ctx, level, current_gate_count);
+                last_child.push(ctx, level, current_counts);
                 return;
             }
         }
@@ -70,28 +84,34 @@ impl ContextTree {
         self.children.push(ContextTree {
             name: ctx.to_string(),
             level,
-            enter_gate_count: current_gate_count,
-            exit_gate_count: None,
+            enter_counts: current_counts,
+            exit_counts: None,
             children: vec![],
         })
     }
 
     /// Close the deepest open context from this tree.
-    pub fn pop(&mut self, current_gate_count: usize) {
+    pub fn pop(&mut self, current_counts: ContextCounts) {
         assert!(self.is_open());
 
         if let Some(last_child) = self.children.last_mut() {
             if last_child.is_open() {
-                last_child.pop(current_gate_count);
+                last_child.pop(current_counts);
                 return;
             }
         }
 
-        self.exit_gate_count = Some(current_gate_count);
+        self.exit_counts = Some(current_counts);
+    }
+
+    fn exit_counts(&self, current_counts: ContextCounts) -> ContextCounts {
+        self.exit_counts.unwrap_or(current_counts)
     }
 
     fn gate_count_delta(&self, current_gate_count: usize) -> usize {
-        self.exit_gate_count.unwrap_or(current_gate_count) - self.enter_gate_count
+        self.exit_counts
+            .map_or(current_gate_count, |counts| counts.gates)
+            - self.enter_counts.gates
     }
 
     /// Filter out children with a low gate count.
@@ -99,8 +119,8 @@ impl ContextTree {
         Self {
             name: self.name.clone(),
             level: self.level,
-            enter_gate_count: self.enter_gate_count,
-            exit_gate_count: self.exit_gate_count,
+            enter_counts: self.enter_counts,
+            exit_counts: self.exit_counts,
             children: self
                 .children
                 .iter()
@@ -127,6 +147,95 @@ impl ContextTree {
             child.print_helper(current_gate_count, depth + 1);
         }
     }
+
+    /// Builds a [`ContextProfile`] of this tree, attributing to each context the gate instances
+    /// in `gate_instances` that were added while it was in scope.
+    pub fn profile<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        current_counts: ContextCounts,
+        gate_instances: &[GateInstance<F, D>],
+    ) -> ContextProfile {
+        let exit_counts = self.exit_counts(current_counts);
+        let counts = exit_counts.delta(&self.enter_counts);
+
+        let mut gate_counts = BTreeMap::new();
+        for instance in &gate_instances[self.enter_counts.gates..exit_counts.gates] {
+            *gate_counts.entry(instance.gate_ref.0.id()).or_insert(0) += 1;
+        }
+
+        ContextProfile {
+            name: self.name.clone(),
+            num_gates: counts.gates,
+            num_copy_constraints: counts.copy_constraints,
+            num_generators: counts.generators,
+            gate_counts,
+            children: self
+                .children
+                .iter()
+                .map(|child| child.profile(current_counts, gate_instances))
+                .collect(),
+        }
+    }
+}
+
+/// A report of the resources contributed by a context, as delimited by
+/// [`push_context`](crate::plonk::circuit_builder::CircuitBuilder::push_context) and
+/// [`pop_context`](crate::plonk::circuit_builder::CircuitBuilder::pop_context), and by each
+/// of its sub-contexts. Counts of a context include those of its children.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ContextProfile {
+    /// The name of the context.
+    pub name: String,
+    /// The number of gate instances, i.e. of rows, added in this context.
+    pub num_gates: usize,
+    /// The number of copy constraints added in this context.
+    pub num_copy_constraints: usize,
+    /// The number of witness generators added in this context, excluding the generators
+    /// attached to gates.
+    pub num_generators: usize,
+    /// The number of instances of each gate type added in this context, indexed by gate ID.
+    pub gate_counts: BTreeMap<String, usize>,
+    /// The sub-contexts of this context.
+    pub children: Vec<ContextProfile>,
+}
+
+impl ContextProfile {
+    /// Returns the sub-context at the given path of names, if any.
+    pub fn find(&self, path: &[&str]) -> Option<&Self> {
+        match path.split_first() {
+            None => Some(self),
+            Some((name, rest)) => self
+                .children
+                .iter()
+                .find(|c| c.name == *name)
+                .and_then(|c| c.find(rest)),
+        }
+    }
+
+    /// Exports the gate counts of this profile in the folded stack format used by flamegraph
+    /// tools, with one `ctx;sub_ctx count` line per context. The count of each line is the
+    /// number of gates added by the context itself, excluding its children.
+    pub fn to_folded_stacks(&self) -> String {
+        let mut lines = Vec::new();
+        self.folded_stacks_helper("", &mut lines);
+        lines.join("\n")
+    }
+
+    fn folded_stacks_helper(&self, prefix: &str, lines: &mut Vec<String>) {
+        let stack = if prefix.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{};{}", prefix, self.name)
+        };
+        let children_gates = self.children.iter().map(|c| c.num_gates).sum::<usize>();
+        let self_gates = self.num_gates - children_gates;
+        if self_gates > 0 {
+            lines.push(format!("{} {}", stack, self_gates));
+        }
+        for child in &self.children {
+            child.folded_stacks_helper(&stack, lines);
+        }
+    }
 }
 
 /// Creates a named scope; useful for debugging.
@@ -146,3 +255,69 @@ macro_rules! with_context {
         res
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_context_profile() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+
+        with_context!(builder, "outer", {
+            let product = with_context!(builder, "hash", {
+                let hash = builder
+                    .hash_n_to_hash_no_pad::<<C as GenericConfig<D>>::InnerHasher>(vec![x, y]);
+                builder.mul(hash.elements[0], hash.elements[1])
+            });
+            with_context!(builder, "split", builder.split_le(product, 64));
+        });
+        let (data, profile) = builder.build_with_profile::<C>();
+
+        assert_eq!(profile.name, "root");
+        assert_eq!(profile.num_gates, data.common.degree());
+
+        let outer = profile.find(&["outer"]).unwrap();
+        let hash = profile.find(&["outer", "hash"]).unwrap();
+        let split = profile.find(&["outer", "split"]).unwrap();
+        assert_eq!(outer.num_gates, hash.num_gates + split.num_gates);
+        assert_eq!(
+            outer.num_copy_constraints,
+            hash.num_copy_constraints + split.num_copy_constraints
+        );
+        assert_eq!(hash.gate_counts.values().sum::<usize>(), hash.num_gates);
+        assert!(hash
+            .gate_counts
+            .keys()
+            .any(|id| id.starts_with("PoseidonGate")));
+        assert!(split.num_generators > 0);
+        assert!(profile.find(&["missing"]).is_none());
+
+        let folded = profile.to_folded_stacks();
+        assert!(folded.lines().any(|l| l.starts_with("root;outer;hash ")));
+        let total = folded
+            .lines()
+            .map(|l| l.rsplit(' ').next().unwrap().parse::<usize>().unwrap())
+            .sum::<usize>();
+        assert_eq!(total, profile.num_gates);
+
+        let json = serde_json::to_string(&profile)?;
+        assert_eq!(
+            serde_json::from_str::<super::ContextProfile>(&json)?,
+            profile
+        );
+
+        Ok(())
+    }
+}
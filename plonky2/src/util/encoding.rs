@@ -0,0 +1,161 @@
+//! Canonical byte encodings of commitments and public inputs, for exchanging them with other
+//! tooling, e.g. Ethereum contracts, without one-off encoders.
+//!
+//! Field elements are encoded as 8-byte little-endian canonical integers, and hashes as the
+//! concatenation of their elements, which are also their SSZ encodings as `uint64` and
+//! `Vector[uint64, 4]`. Sequences of fixed-size values, such as Merkle caps and public inputs, are
+//! the concatenation of their values, as SSZ `List`s. Structures made of several sequences are
+//! encoded as SSZ containers, with [`encode_ssz_container`].
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec::Vec};
+
+use anyhow::{bail, ensure, Result};
+
+use crate::field::types::PrimeField64;
+
+/// Returns the `0x`-prefixed, lowercase hexadecimal representation of `bytes`.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + 2 * bytes.len());
+    hex.push_str("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex
+}
+
+/// Parses a hexadecimal string, with or without a `0x` prefix.
+pub fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    ensure!(
+        hex.len().is_multiple_of(2),
+        "Hexadecimal string of odd length {}",
+        hex.len()
+    );
+    if let Some(i) = hex.bytes().position(|b| !b.is_ascii_hexdigit()) {
+        bail!("Invalid hexadecimal digit at position {i}");
+    }
+    Ok((0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect())
+}
+
+/// Encodes field elements as the SSZ `List[uint64]` of their canonical values.
+pub fn encode_field_elements<F: PrimeField64>(elements: &[F]) -> Vec<u8> {
+    elements
+        .iter()
+        .flat_map(|x| x.to_canonical_u64().to_le_bytes())
+        .collect()
+}
+
+/// Decodes field elements encoded by [`encode_field_elements`], rejecting non-canonical values.
+pub fn decode_field_elements<F: PrimeField64>(bytes: &[u8]) -> Result<Vec<F>> {
+    ensure!(
+        bytes.len().is_multiple_of(8),
+        "Field elements are encoded with 8 bytes, got {} bytes",
+        bytes.len()
+    );
+    bytes
+        .chunks_exact(8)
+        .map(|chunk| {
+            let value = u64::from_le_bytes(chunk.try_into().unwrap());
+            ensure!(value < F::ORDER, "Non-canonical field element {value}");
+            Ok(F::from_canonical_u64(value))
+        })
+        .collect()
+}
+
+/// Encodes an SSZ container of variable-size fields, given their encodings: a 4-byte
+/// little-endian offset for each field, followed by the fields.
+pub fn encode_ssz_container(fields: &[Vec<u8>]) -> Vec<u8> {
+    let mut offset = 4 * fields.len();
+    let mut bytes = Vec::with_capacity(offset + fields.iter().map(Vec::len).sum::<usize>());
+    for field in fields {
+        let encoded_offset = u32::try_from(offset).expect("SSZ offsets must fit in 4 bytes");
+        bytes.extend(encoded_offset.to_le_bytes());
+        offset += field.len();
+    }
+    for field in fields {
+        bytes.extend(field);
+    }
+    bytes
+}
+
+/// Splits an SSZ container of `num_fields` variable-size fields, encoded by
+/// [`encode_ssz_container`], into the encodings of its fields.
+pub fn decode_ssz_container(bytes: &[u8], num_fields: usize) -> Result<Vec<&[u8]>> {
+    let header_len = 4 * num_fields;
+    ensure!(bytes.len() >= header_len, "SSZ container is too short");
+    let mut offsets = bytes[..header_len]
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()) as usize)
+        .collect::<Vec<_>>();
+    ensure!(
+        offsets.first().is_none_or(|&first| first == header_len),
+        "The first SSZ offset must point right after the offsets"
+    );
+    offsets.push(bytes.len());
+    offsets
+        .windows(2)
+        .map(|w| {
+            ensure!(
+                w[0] <= w[1] && w[1] <= bytes.len(),
+                "Invalid SSZ offsets {} and {}",
+                w[0],
+                w[1]
+            );
+            Ok(&bytes[w[0]..w[1]])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Field64, Sample};
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_hex_round_trip() -> Result<()> {
+        let bytes = [0x00, 0x01, 0xab, 0xff];
+        assert_eq!(to_hex(&bytes), "0x0001abff");
+        assert_eq!(from_hex("0x0001abff")?, bytes);
+        assert_eq!(from_hex("0001ABFF")?, bytes);
+        assert!(from_hex("0x001").is_err());
+        assert!(from_hex("0x0g").is_err());
+        assert!(from_hex("+f").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_elements_round_trip() -> Result<()> {
+        let elements = F::rand_vec(5);
+        let bytes = encode_field_elements(&elements);
+        assert_eq!(bytes.len(), 40);
+        assert_eq!(decode_field_elements::<F>(&bytes)?, elements);
+
+        let non_canonical = F::ORDER.to_le_bytes();
+        assert!(decode_field_elements::<F>(&non_canonical).is_err());
+        assert!(decode_field_elements::<F>(&bytes[1..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ssz_container_round_trip() -> Result<()> {
+        let fields = vec![
+            encode_field_elements(&[F::ONE, F::TWO]),
+            vec![],
+            encode_field_elements(&[F::NEG_ONE]),
+        ];
+        let bytes = encode_ssz_container(&fields);
+        // The offsets of the fields, as in SSZ.
+        assert_eq!(&bytes[..12], [12, 0, 0, 0, 28, 0, 0, 0, 28, 0, 0, 0]);
+        assert_eq!(decode_ssz_container(&bytes, 3)?, fields);
+
+        assert!(decode_ssz_container(&bytes[..8], 3).is_err());
+        Ok(())
+    }
+}
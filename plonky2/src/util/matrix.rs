@@ -0,0 +1,342 @@
+//! Dense matrices stored in a single buffer, with tiled and multi-threaded transposes.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+
+use plonky2_maybe_rayon::*;
+
+use crate::hash::merkle_tree::capacity_up_to_mut;
+
+/// Side length of the square tiles in which transposes copy their input, so that both the input and
+/// the output are accessed in runs of consecutive elements.
+const TILE_SIZE: usize = 32;
+
+/// A dense matrix, stored as a single buffer in which each row is contiguous.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RowMajorMatrix<T> {
+    values: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T> Default for RowMajorMatrix<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            width: 0,
+            height: 0,
+        }
+    }
+}
+
+impl<T: Send + Sync + Copy> RowMajorMatrix<T> {
+    pub fn new(values: Vec<T>, width: usize) -> Self {
+        assert!(width > 0, "The width must be positive");
+        assert!(
+            values.len().is_multiple_of(width),
+            "The number of values {} is not a multiple of the width {}",
+            values.len(),
+            width
+        );
+        let height = values.len() / width;
+        Self {
+            values,
+            width,
+            height,
+        }
+    }
+
+    /// The matrix whose rows are `rows`, which must all have the same length.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Self {
+        let height = rows.len();
+        let width = rows.first().map_or(0, Vec::len);
+        assert!(
+            rows.iter().all(|row| row.len() == width),
+            "The rows must have the same length"
+        );
+        Self {
+            values: rows.concat(),
+            width,
+            height,
+        }
+    }
+
+    /// The matrix whose columns are `columns`. This is the transpose of `columns`, seen as rows,
+    /// which is how the prover turns LDEs into Merkle leaves.
+    pub fn from_columns(columns: &[Vec<T>]) -> Self {
+        let width = columns.len();
+        let height = columns[0].len();
+        assert!(columns.iter().all(|column| column.len() == height));
+        Self::from_fn_tiled(width, height, |i, j| columns[j][i])
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    pub fn row(&self, i: usize) -> &[T] {
+        &self.values[i * self.width..(i + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        (0..self.height).map(|i| self.row(i))
+    }
+
+    /// A view of the `j`th column, whose elements are `width` apart in the buffer.
+    pub fn column(&self, j: usize) -> impl Iterator<Item = &T> {
+        assert!(j < self.width);
+        self.values[j..].iter().step_by(self.width)
+    }
+
+    /// Splits the matrix into its rows.
+    pub fn into_rows(self) -> Vec<Vec<T>> {
+        (0..self.height)
+            .into_par_iter()
+            .map(|i| self.row(i).to_vec())
+            .collect()
+    }
+
+    pub fn transpose(&self) -> Self {
+        let width = self.width;
+        Self::from_fn_tiled(self.height, width, |i, j| self.values[j * width + i])
+    }
+
+    /// Transposes a square matrix without allocating.
+    pub fn transpose_in_place(&mut self) {
+        let n = self.width;
+        assert_eq!(
+            self.height, n,
+            "Only square matrices can be transposed in place"
+        );
+        for tile_i in (0..n).step_by(TILE_SIZE) {
+            for tile_j in (tile_i..n).step_by(TILE_SIZE) {
+                for i in tile_i..(tile_i + TILE_SIZE).min(n) {
+                    // In tiles on the diagonal, only swap the elements above it.
+                    let start_j = if tile_i == tile_j { i + 1 } else { tile_j };
+                    for j in start_j..(tile_j + TILE_SIZE).min(n) {
+                        self.values.swap(i * n + j, j * n + i);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The matrix with `height` rows of `width` elements, whose element `(i, j)` is `f(i, j)`.
+    /// Blocks of rows are filled in parallel, tile by tile.
+    fn from_fn_tiled<G>(width: usize, height: usize, f: G) -> Self
+    where
+        G: Fn(usize, usize) -> T + Sync,
+    {
+        let len = width.checked_mul(height).expect("The matrix is too large");
+        let mut values = Vec::with_capacity(len);
+        if len > 0 {
+            let buf = capacity_up_to_mut(&mut values, len);
+            buf.par_chunks_mut(TILE_SIZE * width).enumerate().for_each(
+                |(block, rows): (usize, &mut [MaybeUninit<T>])| {
+                    let start_i = block * TILE_SIZE;
+                    for start_j in (0..width).step_by(TILE_SIZE) {
+                        let end_j = (start_j + TILE_SIZE).min(width);
+                        for (i, row) in rows.chunks_exact_mut(width).enumerate() {
+                            for (j, value) in row[start_j..end_j].iter_mut().enumerate() {
+                                value.write(f(start_i + i, start_j + j));
+                            }
+                        }
+                    }
+                },
+            );
+            unsafe {
+                // SAFETY: Each of the `len` elements was written above.
+                values.set_len(len);
+            }
+        }
+        Self {
+            values,
+            width,
+            height,
+        }
+    }
+}
+
+/// A dense matrix, stored as a single buffer in which each column is contiguous.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ColMajorMatrix<T> {
+    values: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T: Send + Sync + Copy> ColMajorMatrix<T> {
+    pub fn new(values: Vec<T>, height: usize) -> Self {
+        assert!(height > 0, "The height must be positive");
+        assert!(
+            values.len().is_multiple_of(height),
+            "The number of values {} is not a multiple of the height {}",
+            values.len(),
+            height
+        );
+        let width = values.len() / height;
+        Self {
+            values,
+            width,
+            height,
+        }
+    }
+
+    /// The matrix whose columns are `columns`, which must all have the same length.
+    pub fn from_columns(columns: Vec<Vec<T>>) -> Self {
+        let width = columns.len();
+        let height = columns.first().map_or(0, Vec::len);
+        assert!(
+            columns.iter().all(|column| column.len() == height),
+            "The columns must have the same length"
+        );
+        Self {
+            values: columns.concat(),
+            width,
+            height,
+        }
+    }
+
+    /// The matrix with `width` columns of `height` elements, whose element `(i, j)` is `f(i, j)`.
+    /// Columns are filled in parallel.
+    pub fn from_fn<G>(width: usize, height: usize, f: G) -> Self
+    where
+        G: Fn(usize, usize) -> T + Sync,
+    {
+        let len = width.checked_mul(height).expect("The matrix is too large");
+        let mut values = Vec::with_capacity(len);
+        if len > 0 {
+            let buf = capacity_up_to_mut(&mut values, len);
+            buf.par_chunks_mut(height).enumerate().for_each(
+                |(j, column): (usize, &mut [MaybeUninit<T>])| {
+                    for (i, value) in column.iter_mut().enumerate() {
+                        value.write(f(i, j));
+                    }
+                },
+            );
+            unsafe {
+                // SAFETY: Each of the `len` elements was written above.
+                values.set_len(len);
+            }
+        }
+        Self {
+            values,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> T {
+        self.column(j)[i]
+    }
+
+    pub fn column(&self, j: usize) -> &[T] {
+        &self.values[j * self.height..(j + 1) * self.height]
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = &[T]> {
+        (0..self.width).map(|j| self.column(j))
+    }
+
+    /// A view of the `i`th row, whose elements are `height` apart in the buffer.
+    pub fn row(&self, i: usize) -> impl Iterator<Item = &T> {
+        assert!(i < self.height);
+        self.values[i..].iter().step_by(self.height)
+    }
+
+    /// The same matrix, stored in row-major order.
+    pub fn to_row_major(&self) -> RowMajorMatrix<T> {
+        let height = self.height;
+        RowMajorMatrix::from_fn_tiled(self.width, height, |i, j| self.values[j * height + i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::transpose;
+
+    fn matrix(width: usize, height: usize) -> RowMajorMatrix<usize> {
+        RowMajorMatrix::new((0..width * height).collect(), width)
+    }
+
+    #[test]
+    fn test_from_columns() {
+        let columns = (0..45)
+            .map(|j| (0..70).map(|i| 100 * i + j).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let m = RowMajorMatrix::from_columns(&columns);
+        assert_eq!((m.width(), m.height()), (45, 70));
+        assert_eq!(m.clone().into_rows(), transpose(&columns));
+        assert!(m.rows().enumerate().all(|(i, row)| row == m.row(i)));
+    }
+
+    #[test]
+    fn test_transpose() {
+        for (width, height) in [(1, 1), (1, 40), (33, 1), (45, 70), (64, 64)] {
+            let m = matrix(width, height);
+            let t = m.transpose();
+            assert_eq!((t.width(), t.height()), (height, width));
+            for i in 0..height {
+                for j in 0..width {
+                    assert_eq!(t.row(j)[i], m.row(i)[j]);
+                }
+            }
+            assert_eq!(t.transpose(), m);
+        }
+    }
+
+    #[test]
+    fn test_transpose_in_place() {
+        for n in [1, 31, 32, 70] {
+            let mut m = matrix(n, n);
+            let t = m.transpose();
+            m.transpose_in_place();
+            assert_eq!(m, t);
+        }
+    }
+
+    #[test]
+    fn test_col_major() {
+        let columns = (0..45)
+            .map(|j| (0..70).map(|i| 100 * i + j).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let m = ColMajorMatrix::from_columns(columns.clone());
+        assert_eq!(m, ColMajorMatrix::from_fn(45, 70, |i, j| 100 * i + j));
+        assert_eq!(m.get(3, 5), 305);
+
+        let r = m.to_row_major();
+        assert_eq!(r, RowMajorMatrix::from_columns(&columns));
+        assert!((0..70).all(|i| m.row(i).eq(r.row(i))));
+        assert!((0..45).all(|j| r.column(j).eq(m.column(j))));
+    }
+
+    #[test]
+    fn test_empty_rows() {
+        let m = RowMajorMatrix::<usize>::from_rows(vec![vec![]; 4]);
+        assert_eq!((m.width(), m.height()), (0, 4));
+        assert_eq!(m.rows().count(), 4);
+        assert_eq!(m.into_rows(), vec![Vec::<usize>::new(); 4]);
+    }
+}
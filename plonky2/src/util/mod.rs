@@ -1,7 +1,7 @@
 //! Utility module for helper methods and plonky2 serialization logic.
 
 #[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 
 use plonky2_maybe_rayon::*;
 #[doc(inline)]
@@ -10,7 +10,10 @@ pub use plonky2_util::*;
 use crate::field::polynomial::PolynomialValues;
 use crate::field::types::Field;
 
-pub(crate) mod context_tree;
+pub mod blinding;
+pub mod context_tree;
+pub mod encoding;
+pub mod matrix;
 pub(crate) mod partial_products;
 pub mod reducing;
 pub mod serialization;
@@ -22,12 +25,28 @@ pub(crate) fn transpose_poly_values<F: Field>(polys: Vec<PolynomialValues<F>>) -
     transpose(&poly_values)
 }
 
+/// Side length of the square tiles in which [`transpose`] copies its input. A tile of field
+/// elements fits in L1 cache, so each input row is read in runs of consecutive elements rather than
+/// one element at a time.
+const TRANSPOSE_BLOCK_SIZE: usize = 32;
+
 pub fn transpose<T: Send + Sync + Copy>(matrix: &[Vec<T>]) -> Vec<Vec<T>> {
     let len = matrix[0].len();
-    (0..len)
-        .into_par_iter()
-        .map(|i| matrix.iter().map(|row| row[i]).collect())
-        .collect()
+    let mut transposed = vec![Vec::new(); len];
+    transposed
+        .par_chunks_mut(TRANSPOSE_BLOCK_SIZE)
+        .enumerate()
+        .for_each(|(block, rows)| {
+            let start = block * TRANSPOSE_BLOCK_SIZE;
+            rows.iter_mut()
+                .for_each(|row| row.reserve_exact(matrix.len()));
+            for tile in matrix.chunks(TRANSPOSE_BLOCK_SIZE) {
+                for (i, row) in rows.iter_mut().enumerate() {
+                    row.extend(tile.iter().map(|col| col[start + i]));
+                }
+            }
+        });
+    transposed
 }
 
 pub(crate) const fn reverse_bits(n: usize, num_bits: usize) -> usize {
@@ -146,4 +165,19 @@ mod tests {
         let range: Vec<u64> = (0..1 << 17).collect();
         assert_eq!(arr, range);
     }
+
+    #[test]
+    fn test_transpose() {
+        // Sizes which are not multiples of the block size, in both directions.
+        for (height, width) in [(1, 1), (3, 100), (100, 3), (33, 65), (64, 64)] {
+            let matrix: Vec<Vec<usize>> = (0..height)
+                .map(|i| (0..width).map(|j| i * width + j).collect())
+                .collect();
+            let transposed = transpose(&matrix);
+            assert_eq!(transposed.len(), width);
+            for (j, row) in transposed.iter().enumerate() {
+                assert_eq!(row, &(0..height).map(|i| i * width + j).collect::<Vec<_>>());
+            }
+        }
+    }
 }
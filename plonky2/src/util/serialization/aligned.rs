@@ -0,0 +1,340 @@
+//! An aligned variant of the serialization format, used to persist prover circuit data.
+//!
+//! It encodes the same values as [`Write`] and [`Read`] on `Vec<u8>` and [`Buffer`], but starts
+//! every vector of field elements and of digests at an offset which is a multiple of
+//! [`ALIGNMENT`], and stores the leaves and digests of Merkle trees as single contiguous blocks.
+//! The bulk of the prover data, i.e. the low-degree extensions and Merkle digests of the constant
+//! and sigma polynomials, can thus be decoded in one parallel pass straight from the bytes, e.g.
+//! a memory-mapped file, instead of one element at a time.
+//!
+//! Reading is not zero-copy: the decoded data owns its vectors, so every value is copied out of
+//! the bytes, which can be dropped or unmapped afterwards. The format only makes this copy fast.
+//!
+//! Serialized data starts with [`ALIGNED_MAGIC`] and the [`ALIGNED_FORMAT_VERSION`], and data with
+//! another magic or version is rejected.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::convert::Infallible;
+
+use plonky2_maybe_rayon::*;
+
+use crate::field::extension::Extendable;
+use crate::field::types::{Field64, PrimeField64};
+use crate::gates::gate::GateRef;
+use crate::hash::hash_types::RichField;
+use crate::hash::merkle_tree::MerkleTree;
+use crate::iop::generator::WitnessGeneratorRef;
+use crate::plonk::circuit_data::CommonCircuitData;
+use crate::plonk::config::{GenericHashOut, Hasher};
+use crate::plonk::custom_argument::CustomArgumentRef;
+use crate::util::matrix::RowMajorMatrix;
+use crate::util::serialization::{
+    Buffer, GateSerializer, IoError, IoResult, Read, Remaining, WitnessGeneratorSerializer, Write,
+};
+
+/// The bytes every aligned serialization starts with.
+pub const ALIGNED_MAGIC: [u8; 8] = *b"PLONKY2A";
+
+/// The version of the aligned format. It is bumped whenever the layout of aligned serializations
+/// changes.
+pub const ALIGNED_FORMAT_VERSION: u64 = 1;
+
+/// The alignment in bytes of bulk vectors, relative to the start of the serialization.
+pub const ALIGNMENT: usize = 8;
+
+/// A [`Write`] producing the aligned format.
+#[derive(Debug)]
+pub struct AlignedWriter {
+    bytes: Vec<u8>,
+}
+
+impl Default for AlignedWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlignedWriter {
+    /// Starts an aligned serialization, writing its header.
+    pub fn new() -> Self {
+        let mut bytes = ALIGNED_MAGIC.to_vec();
+        bytes.extend_from_slice(&ALIGNED_FORMAT_VERSION.to_le_bytes());
+        Self { bytes }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    fn pad(&mut self) {
+        let len = self.bytes.len().next_multiple_of(ALIGNMENT);
+        self.bytes.resize(len, 0);
+    }
+}
+
+impl Write for AlignedWriter {
+    type Error = Infallible;
+
+    #[inline]
+    fn write_all(&mut self, bytes: &[u8]) -> IoResult<()> {
+        self.bytes.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn write_field_vec<F>(&mut self, v: &[F]) -> IoResult<()>
+    where
+        F: PrimeField64,
+    {
+        self.pad();
+        self.bytes.reserve(v.len() * 8);
+        for x in v {
+            self.bytes
+                .extend_from_slice(&x.to_canonical_u64().to_le_bytes());
+        }
+        Ok(())
+    }
+
+    fn write_merkle_tree<F, H>(&mut self, tree: &MerkleTree<F, H>) -> IoResult<()>
+    where
+        F: RichField,
+        H: Hasher<F>,
+    {
+        self.write_usize(tree.leaves.height())?;
+        self.write_usize(tree.leaves.width())?;
+        self.write_field_vec(tree.leaves.values())?;
+        self.write_usize(tree.digests.len())?;
+        self.pad();
+        for digest in &tree.digests {
+            self.write_hash::<F, H>(*digest)?;
+        }
+        self.write_usize(tree.cap.height())?;
+        self.write_merkle_cap(&tree.cap)?;
+        self.write_usize(tree.arity_bits)?;
+        self.write_leaf_hasher(tree.leaf_hasher)
+    }
+
+    fn write_gate<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        gate: &GateRef<F, D>,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        gate_serializer.write_gate(&mut self.bytes, gate, common_data)
+    }
+
+    fn write_custom_argument<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        argument: &CustomArgumentRef<F, D>,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        gate_serializer.write_custom_argument(&mut self.bytes, argument, common_data)
+    }
+
+    fn write_generator<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        generator: &WitnessGeneratorRef<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        generator_serializer.write_generator(&mut self.bytes, generator, common_data)
+    }
+}
+
+/// A [`Read`] over data in the aligned format.
+#[derive(Debug)]
+pub struct AlignedBuffer<'a> {
+    buffer: Buffer<'a>,
+}
+
+impl<'a> AlignedBuffer<'a> {
+    /// Reads the header of the aligned serialization `bytes`, failing if it has another magic or
+    /// version.
+    pub fn new(bytes: &'a [u8]) -> IoResult<Self> {
+        let mut buffer = Buffer::new(bytes);
+        let mut magic = [0; ALIGNED_MAGIC.len()];
+        buffer.read_exact(&mut magic)?;
+        if magic != ALIGNED_MAGIC || buffer.read_u64()? != ALIGNED_FORMAT_VERSION {
+            return Err(IoError);
+        }
+        Ok(Self { buffer })
+    }
+
+    /// Skips the zero padding up to the next aligned offset.
+    fn skip_padding(&mut self) -> IoResult<()> {
+        let padding = self.buffer.pos().next_multiple_of(ALIGNMENT) - self.buffer.pos();
+        let mut buf = [0; ALIGNMENT];
+        self.buffer.read_exact(&mut buf[..padding])?;
+        if buf.iter().any(|&b| b != 0) {
+            return Err(IoError);
+        }
+        Ok(())
+    }
+
+    /// Takes the next `len` bytes.
+    fn take(&mut self, len: usize) -> IoResult<&'a [u8]> {
+        let bytes = self.buffer.unread_bytes().get(..len).ok_or(IoError)?;
+        self.buffer.pos += len;
+        Ok(bytes)
+    }
+}
+
+impl Remaining for AlignedBuffer<'_> {
+    fn remaining(&self) -> usize {
+        self.buffer.remaining()
+    }
+}
+
+impl Read for AlignedBuffer<'_> {
+    #[inline]
+    fn read_exact(&mut self, bytes: &mut [u8]) -> IoResult<()> {
+        self.buffer.read_exact(bytes)
+    }
+
+    fn read_field_vec<F>(&mut self, length: usize) -> IoResult<Vec<F>>
+    where
+        F: Field64,
+    {
+        self.skip_padding()?;
+        let bytes = self.take(length.checked_mul(8).ok_or(IoError)?)?;
+        Ok(bytes
+            .par_chunks_exact(8)
+            .map(|chunk| F::from_canonical_u64(u64::from_le_bytes(chunk.try_into().unwrap())))
+            .collect())
+    }
+
+    fn read_merkle_tree<F, H>(&mut self) -> IoResult<MerkleTree<F, H>>
+    where
+        F: RichField,
+        H: Hasher<F>,
+    {
+        let height = self.read_usize()?;
+        let width = self.read_usize()?;
+        let values = self.read_field_vec(height.checked_mul(width).ok_or(IoError)?)?;
+        let leaves = match (width, height) {
+            (0, 0) => RowMajorMatrix::default(),
+            (0, _) => return Err(IoError),
+            _ => RowMajorMatrix::new(values, width),
+        };
+
+        let digests_len = self.read_usize()?;
+        self.skip_padding()?;
+        let bytes = self.take(digests_len.checked_mul(H::HASH_SIZE).ok_or(IoError)?)?;
+        let digests = bytes
+            .par_chunks_exact(H::HASH_SIZE)
+            .map(H::Hash::from_bytes)
+            .collect();
+
+        let cap_height = self.read_usize()?;
+        let cap = self.read_merkle_cap::<F, H>(cap_height)?;
+        let arity_bits = self.read_usize()?;
+        if arity_bits == 0 {
+            return Err(IoError);
+        }
+        let leaf_hasher = self.read_leaf_hasher()?;
+        Ok(MerkleTree {
+            leaves,
+            digests,
+            cap,
+            arity_bits,
+            leaf_hasher,
+        })
+    }
+
+    fn read_gate<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<GateRef<F, D>> {
+        gate_serializer.read_gate(&mut self.buffer, common_data)
+    }
+
+    fn read_custom_argument<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<CustomArgumentRef<F, D>> {
+        gate_serializer.read_custom_argument(&mut self.buffer, common_data)
+    }
+
+    fn read_generator<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<WitnessGeneratorRef<F, D>> {
+        generator_serializer.read_generator(&mut self.buffer, common_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::Field;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, ProverCircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::util::serialization::{DefaultGateSerializer, DefaultGeneratorSerializer};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn aligned_prover_circuit_data() -> Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        let x_cubed = builder.exp_u64(x, 3);
+        let y = builder.add_const(x_cubed, F::from_canonical_u64(5));
+        builder.register_public_input(y);
+        let data = builder.build_prover::<C>();
+
+        let generator_serializer = DefaultGeneratorSerializer::<C, D>::default();
+        let bytes = data
+            .to_aligned_bytes(&DefaultGateSerializer, &generator_serializer)
+            .unwrap();
+        let loaded = ProverCircuitData::from_aligned_bytes(
+            &bytes,
+            &DefaultGateSerializer,
+            &generator_serializer,
+        )
+        .unwrap();
+        assert_eq!(loaded.prover_only, data.prover_only);
+        assert_eq!(loaded.common, data.common);
+
+        // The Merkle digests of the constants and sigmas commitment start at an aligned offset.
+        let tree = &data.prover_only.constants_sigmas_commitment.merkle_tree;
+        let digest_bytes = <C as GenericConfig<D>>::Hasher::hash_to_bytes(&tree.digests[0]);
+        let offset = bytes
+            .windows(digest_bytes.len())
+            .position(|window| window == digest_bytes)
+            .unwrap();
+        assert_eq!(offset % ALIGNMENT, 0);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::TWO)?;
+        let proof = loaded.prove(pw)?;
+        assert_eq!(proof.public_inputs, [F::from_canonical_u64(13)]);
+
+        // Truncated data and data of another version are rejected.
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(ProverCircuitData::<F, C, D>::from_aligned_bytes(
+            truncated,
+            &DefaultGateSerializer,
+            &generator_serializer
+        )
+        .is_err());
+        let mut other_version = bytes;
+        other_version[ALIGNED_MAGIC.len()] += 1;
+        assert!(ProverCircuitData::<F, C, D>::from_aligned_bytes(
+            &other_version,
+            &DefaultGateSerializer,
+            &generator_serializer
+        )
+        .is_err());
+        Ok(())
+    }
+}
@@ -2,16 +2,21 @@
 
 #[cfg(not(feature = "std"))]
 pub use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec};
+use core::any::TypeId;
 #[cfg(feature = "std")]
 pub use std::vec::Vec; // For macros below
 
+use hashbrown::HashMap;
 pub use log;
 use plonky2_field::extension::Extendable;
 
-use crate::gates::gate::GateRef;
+use crate::gates::gate::{Gate, GateRef};
 use crate::hash::hash_types::RichField;
 use crate::plonk::circuit_data::CommonCircuitData;
-use crate::util::serialization::{Buffer, IoResult};
+use crate::plonk::custom_argument::{CustomArgument, CustomArgumentRef};
+use crate::util::serialization::{Buffer, IoError, IoResult, Read, Write};
 
 pub trait GateSerializer<F: RichField + Extendable<D>, const D: usize> {
     fn read_gate(
@@ -25,6 +30,31 @@ pub trait GateSerializer<F: RichField + Extendable<D>, const D: usize> {
         gate: &GateRef<F, D>,
         common_data: &CommonCircuitData<F, D>,
     ) -> IoResult<()>;
+
+    /// Reads a custom argument. Serializers for circuits using custom arguments must override
+    /// this method, as none are supported by default.
+    fn read_custom_argument(
+        &self,
+        _buf: &mut Buffer,
+        _common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<CustomArgumentRef<F, D>> {
+        Err(IoError)
+    }
+
+    /// Writes a custom argument. Serializers for circuits using custom arguments must override
+    /// this method, as none are supported by default.
+    fn write_custom_argument(
+        &self,
+        _buf: &mut Vec<u8>,
+        argument: &CustomArgumentRef<F, D>,
+        _common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        log::error!(
+            "attempted to serialize custom argument with id `{}` which is unsupported by this gate serializer",
+            argument.0.id()
+        );
+        Err(IoError)
+    }
 }
 
 #[macro_export]
@@ -93,25 +123,281 @@ macro_rules! impl_gate_serializer {
     };
 }
 
+/// Reads a gate of a type registered in a [`GateRegistry`].
+pub type GateDeserializer<F, const D: usize> =
+    fn(&mut Buffer, &CommonCircuitData<F, D>) -> IoResult<GateRef<F, D>>;
+
+/// Reads a custom argument of a type registered in a [`GateRegistry`].
+pub type CustomArgumentDeserializer<F, const D: usize> =
+    fn(&mut Buffer, &CommonCircuitData<F, D>) -> IoResult<CustomArgumentRef<F, D>>;
+
+fn deserialize_gate<F: RichField + Extendable<D>, G: Gate<F, D>, const D: usize>(
+    buf: &mut Buffer,
+    common_data: &CommonCircuitData<F, D>,
+) -> IoResult<GateRef<F, D>> {
+    Ok(GateRef::new(G::deserialize(buf, common_data)?))
+}
+
+fn deserialize_custom_argument<
+    F: RichField + Extendable<D>,
+    A: CustomArgument<F, D>,
+    const D: usize,
+>(
+    buf: &mut Buffer,
+    common_data: &CommonCircuitData<F, D>,
+) -> IoResult<CustomArgumentRef<F, D>> {
+    Ok(CustomArgumentRef::new(A::deserialize(buf, common_data)?))
+}
+
+/// A [`GateSerializer`] whose supported gates are registered at runtime, so that crates shipping
+/// their own gates don't need to define a serializer listing every gate of the library.
+///
+/// Each gate type is registered under a name, which is written before the gate itself. Unlike the
+/// tags of [`impl_gate_serializer`], names don't depend on the order of registration, so a registry
+/// can read circuits serialized by any other registry knowing the same names.
+///
+/// Once deserialized, a gate carries its own evaluators, which are used by both the native and
+/// the recursive verifiers. Custom arguments can be registered in the same way.
+///
+/// ```rust
+/// use plonky2::field::goldilocks_field::GoldilocksField;
+/// use plonky2::gates::noop::NoopGate;
+/// use plonky2::util::serialization::GateRegistry;
+///
+/// let mut registry = GateRegistry::<GoldilocksField, 2>::new();
+/// registry.register::<NoopGate>("NoopGate");
+/// ```
+#[derive(Debug)]
+pub struct GateRegistry<F: RichField + Extendable<D>, const D: usize> {
+    gate_names: HashMap<TypeId, String>,
+    gate_deserializers: HashMap<String, GateDeserializer<F, D>>,
+    custom_argument_names: HashMap<TypeId, String>,
+    custom_argument_deserializers: HashMap<String, CustomArgumentDeserializer<F, D>>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> GateRegistry<F, D> {
+    /// Creates a registry without any gate. See [`GateRegistry::default`] for a registry
+    /// supporting all the gates of this library.
+    pub fn new() -> Self {
+        Self {
+            gate_names: HashMap::new(),
+            gate_deserializers: HashMap::new(),
+            custom_argument_names: HashMap::new(),
+            custom_argument_deserializers: HashMap::new(),
+        }
+    }
+
+    /// Registers the gate type `G` under `name`. Panics if the name or the type is already
+    /// registered.
+    pub fn register<G: Gate<F, D>>(&mut self, name: &str) -> &mut Self {
+        assert!(
+            !self.gate_deserializers.contains_key(name),
+            "A gate is already registered under the name {name}"
+        );
+        assert!(
+            self.gate_names
+                .insert(TypeId::of::<G>(), name.into())
+                .is_none(),
+            "This gate type is already registered"
+        );
+        self.gate_deserializers
+            .insert(name.into(), deserialize_gate::<F, G, D>);
+        self
+    }
+
+    /// Registers the custom argument type `A` under `name`. Panics if the name or the type is
+    /// already registered.
+    pub fn register_custom_argument<A: CustomArgument<F, D>>(&mut self, name: &str) -> &mut Self {
+        assert!(
+            !self.custom_argument_deserializers.contains_key(name),
+            "A custom argument is already registered under the name {name}"
+        );
+        assert!(
+            self.custom_argument_names
+                .insert(TypeId::of::<A>(), name.into())
+                .is_none(),
+            "This custom argument type is already registered"
+        );
+        self.custom_argument_deserializers
+            .insert(name.into(), deserialize_custom_argument::<F, A, D>);
+        self
+    }
+
+    /// Returns whether the type of `gate` is registered.
+    pub fn contains(&self, gate: &GateRef<F, D>) -> bool {
+        self.gate_names.contains_key(&gate.0.as_any().type_id())
+    }
+
+    /// Returns the number of registered gate types.
+    pub fn num_gates(&self) -> usize {
+        self.gate_names.len()
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Default for GateRegistry<F, D> {
+    /// Creates a registry supporting all the gates of this library, like [`DefaultGateSerializer`].
+    ///
+    /// [`DefaultGateSerializer`]: default::DefaultGateSerializer
+    fn default() -> Self {
+        use crate::gates::arithmetic_base::ArithmeticGate;
+        use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
+        use crate::gates::base_sum::BaseSumGate;
+        use crate::gates::comparison::ComparisonGate;
+        use crate::gates::constant::ConstantGate;
+        use crate::gates::coset_interpolation::CosetInterpolationGate;
+        use crate::gates::coset_interpolation_chunk::CosetInterpolationChunkGate;
+        use crate::gates::exponentiation::ExponentiationGate;
+        use crate::gates::exponentiation_extension::ExponentiationExtensionGate;
+        use crate::gates::lookup::LookupGate;
+        use crate::gates::lookup_table::LookupTableGate;
+        use crate::gates::multiplication_extension::MulExtensionGate;
+        use crate::gates::multiplication_extension_algebra::MulExtensionAlgebraGate;
+        use crate::gates::noop::NoopGate;
+        use crate::gates::poseidon::PoseidonGate;
+        use crate::gates::poseidon_mds::PoseidonMdsGate;
+        use crate::gates::poseidon_sponge::PoseidonSpongeGate;
+        use crate::gates::public_input::PublicInputGate;
+        use crate::gates::random_access::RandomAccessGate;
+        use crate::gates::reducing::ReducingGate;
+        use crate::gates::reducing_extension::ReducingExtensionGate;
+        use crate::gates::select::SelectGate;
+
+        let mut registry = Self::new();
+        registry
+            .register::<ArithmeticGate>("ArithmeticGate")
+            .register::<ArithmeticExtensionGate<D>>("ArithmeticExtensionGate")
+            .register::<BaseSumGate<2>>("BaseSumGate<2>")
+            .register::<ComparisonGate>("ComparisonGate")
+            .register::<ConstantGate>("ConstantGate")
+            .register::<CosetInterpolationChunkGate<F, D>>("CosetInterpolationChunkGate")
+            .register::<CosetInterpolationGate<F, D>>("CosetInterpolationGate")
+            .register::<ExponentiationExtensionGate<D>>("ExponentiationExtensionGate")
+            .register::<ExponentiationGate<F, D>>("ExponentiationGate")
+            .register::<LookupGate>("LookupGate")
+            .register::<LookupTableGate>("LookupTableGate")
+            .register::<MulExtensionAlgebraGate<D>>("MulExtensionAlgebraGate")
+            .register::<MulExtensionGate<D>>("MulExtensionGate")
+            .register::<NoopGate>("NoopGate")
+            .register::<PoseidonMdsGate<F, D>>("PoseidonMdsGate")
+            .register::<PoseidonGate<F, D>>("PoseidonGate")
+            .register::<PoseidonSpongeGate<F, D>>("PoseidonSpongeGate")
+            .register::<PublicInputGate>("PublicInputGate")
+            .register::<RandomAccessGate<F, D>>("RandomAccessGate")
+            .register::<ReducingExtensionGate<D>>("ReducingExtensionGate")
+            .register::<ReducingGate<D>>("ReducingGate")
+            .register::<SelectGate>("SelectGate");
+        registry
+    }
+}
+
+fn read_name(buf: &mut Buffer) -> IoResult<String> {
+    let len = buf.read_usize()?;
+    let mut bytes = vec![0; len];
+    buf.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| IoError)
+}
+
+fn write_name(buf: &mut Vec<u8>, name: &str) -> IoResult<()> {
+    buf.write_usize(name.len())?;
+    buf.write_all(name.as_bytes())
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> GateSerializer<F, D> for GateRegistry<F, D> {
+    fn read_gate(
+        &self,
+        buf: &mut Buffer,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<GateRef<F, D>> {
+        let name = read_name(buf)?;
+        let deserializer = self.gate_deserializers.get(&name).ok_or_else(|| {
+            log::error!("attempted to deserialize unregistered gate `{name}`");
+            IoError
+        })?;
+        deserializer(buf, common_data)
+    }
+
+    fn write_gate(
+        &self,
+        buf: &mut Vec<u8>,
+        gate: &GateRef<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        let name = self
+            .gate_names
+            .get(&gate.0.as_any().type_id())
+            .ok_or_else(|| {
+                log::error!(
+                    "attempted to serialize gate with id `{}` which is not registered",
+                    gate.0.id()
+                );
+                IoError
+            })?;
+        write_name(buf, name)?;
+        gate.0.serialize(buf, common_data)
+    }
+
+    fn read_custom_argument(
+        &self,
+        buf: &mut Buffer,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<CustomArgumentRef<F, D>> {
+        let name = read_name(buf)?;
+        let deserializer = self
+            .custom_argument_deserializers
+            .get(&name)
+            .ok_or_else(|| {
+                log::error!("attempted to deserialize unregistered custom argument `{name}`");
+                IoError
+            })?;
+        deserializer(buf, common_data)
+    }
+
+    fn write_custom_argument(
+        &self,
+        buf: &mut Vec<u8>,
+        argument: &CustomArgumentRef<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        let name = self
+            .custom_argument_names
+            .get(&argument.0.as_any().type_id())
+            .ok_or_else(|| {
+                log::error!(
+                    "attempted to serialize custom argument with id `{}` which is not registered",
+                    argument.0.id()
+                );
+                IoError
+            })?;
+        write_name(buf, name)?;
+        argument.0.serialize(buf, common_data)
+    }
+}
+
 pub mod default {
     use plonky2_field::extension::Extendable;
 
     use crate::gates::arithmetic_base::ArithmeticGate;
     use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
     use crate::gates::base_sum::BaseSumGate;
+    use crate::gates::comparison::ComparisonGate;
     use crate::gates::constant::ConstantGate;
     use crate::gates::coset_interpolation::CosetInterpolationGate;
+    use crate::gates::coset_interpolation_chunk::CosetInterpolationChunkGate;
     use crate::gates::exponentiation::ExponentiationGate;
+    use crate::gates::exponentiation_extension::ExponentiationExtensionGate;
     use crate::gates::lookup::LookupGate;
     use crate::gates::lookup_table::LookupTableGate;
     use crate::gates::multiplication_extension::MulExtensionGate;
+    use crate::gates::multiplication_extension_algebra::MulExtensionAlgebraGate;
     use crate::gates::noop::NoopGate;
     use crate::gates::poseidon::PoseidonGate;
     use crate::gates::poseidon_mds::PoseidonMdsGate;
+    use crate::gates::poseidon_sponge::PoseidonSpongeGate;
     use crate::gates::public_input::PublicInputGate;
     use crate::gates::random_access::RandomAccessGate;
     use crate::gates::reducing::ReducingGate;
     use crate::gates::reducing_extension::ReducingExtensionGate;
+    use crate::gates::select::SelectGate;
     use crate::hash::hash_types::RichField;
     use crate::util::serialization::GateSerializer;
     /// A gate serializer that can be used to serialize all default gates supported
@@ -122,7 +408,8 @@ pub mod default {
     /// let gate_serializer = DefaultGateSerializer;
     /// ```
     /// Applications using custom gates should define their own serializer implementing
-    /// the `GateSerializer` trait. This can be easily done through the `impl_gate_serializer` macro.
+    /// the `GateSerializer` trait. This can be easily done through the `impl_gate_serializer` macro,
+    /// or by registering them in a [`GateRegistry`](super::GateRegistry).
     #[derive(Debug)]
     pub struct DefaultGateSerializer;
     impl<F: RichField + Extendable<D>, const D: usize> GateSerializer<F, D> for DefaultGateSerializer {
@@ -131,19 +418,92 @@ pub mod default {
             ArithmeticGate,
             ArithmeticExtensionGate<D>,
             BaseSumGate<2>,
+            ComparisonGate,
             ConstantGate,
+            CosetInterpolationChunkGate<F, D>,
             CosetInterpolationGate<F, D>,
+            ExponentiationExtensionGate<D>,
             ExponentiationGate<F, D>,
             LookupGate,
             LookupTableGate,
+            MulExtensionAlgebraGate<D>,
             MulExtensionGate<D>,
             NoopGate,
             PoseidonMdsGate<F, D>,
             PoseidonGate<F, D>,
+            PoseidonSpongeGate<F, D>,
             PublicInputGate,
             RandomAccessGate<F, D>,
             ReducingExtensionGate<D>,
-            ReducingGate<D>
+            ReducingGate<D>,
+            SelectGate
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Field;
+    use crate::gates::arithmetic_base::ArithmeticGate;
+    use crate::gates::constant::ConstantGate;
+    use crate::gates::exponentiation::ExponentiationGate;
+    use crate::gates::noop::NoopGate;
+    use crate::gates::poseidon::PoseidonGate;
+    use crate::gates::public_input::PublicInputGate;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, VerifierCircuitData};
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_gate_registry() -> IoResult<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.exp_u64(x, 5);
+        let z = builder.mul_const_add(F::from_canonical_u64(3), y, x);
+        builder.register_public_input(z);
+        let data = builder.build::<C>().verifier_data();
+
+        let registry = GateRegistry::<F, D>::default();
+        let bytes = data.to_bytes(&registry)?;
+        let deserialized = VerifierCircuitData::<F, C, D>::from_bytes(bytes, &registry)?;
+        assert_eq!(data, deserialized);
+
+        // A registry only needs the gates used by the circuit, under the same names.
+        let mut registry = GateRegistry::<F, D>::new();
+        registry
+            .register::<NoopGate>("NoopGate")
+            .register::<ConstantGate>("ConstantGate")
+            .register::<ArithmeticGate>("ArithmeticGate")
+            .register::<ExponentiationGate<F, D>>("ExponentiationGate")
+            .register::<PoseidonGate<F, D>>("PoseidonGate")
+            .register::<PublicInputGate>("PublicInputGate");
+        assert!(data.common.gates.iter().all(|gate| registry.contains(gate)));
+        let bytes = data.to_bytes(&registry)?;
+        let deserialized =
+            VerifierCircuitData::<F, C, D>::from_bytes(bytes.clone(), &GateRegistry::default())?;
+        assert_eq!(data, deserialized);
+
+        // Gates which aren't registered can be neither written nor read.
+        let mut registry = GateRegistry::<F, D>::new();
+        registry.register::<ConstantGate>("ConstantGate");
+        assert!(data.to_bytes(&registry).is_err());
+        assert!(VerifierCircuitData::<F, C, D>::from_bytes(bytes, &registry).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_gate_registry_duplicate_name() {
+        let mut registry = GateRegistry::<F, D>::new();
+        registry
+            .register::<NoopGate>("Gate")
+            .register::<ConstantGate>("Gate");
+    }
+}
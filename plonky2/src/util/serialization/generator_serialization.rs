@@ -103,22 +103,30 @@ pub mod default {
 
     use crate::gadgets::arithmetic::EqualityGenerator;
     use crate::gadgets::arithmetic_extension::QuotientGeneratorExtension;
+    use crate::gadgets::division::DivRemGenerator;
     use crate::gadgets::range_check::LowHighGenerator;
+    use crate::gadgets::shuffle::ShuffleGenerator;
     use crate::gadgets::split_base::BaseSumGenerator;
     use crate::gadgets::split_join::{SplitGenerator, WireSplitGenerator};
     use crate::gates::arithmetic_base::ArithmeticBaseGenerator;
     use crate::gates::arithmetic_extension::ArithmeticExtensionGenerator;
     use crate::gates::base_sum::BaseSplitGenerator;
+    use crate::gates::comparison::ComparisonGenerator;
     use crate::gates::coset_interpolation::InterpolationGenerator;
+    use crate::gates::coset_interpolation_chunk::InterpolationChunkGenerator;
     use crate::gates::exponentiation::ExponentiationGenerator;
+    use crate::gates::exponentiation_extension::ExponentiationExtensionGenerator;
     use crate::gates::lookup::LookupGenerator;
     use crate::gates::lookup_table::LookupTableGenerator;
     use crate::gates::multiplication_extension::MulExtensionGenerator;
+    use crate::gates::multiplication_extension_algebra::MulExtensionAlgebraGenerator;
     use crate::gates::poseidon::PoseidonGenerator;
     use crate::gates::poseidon_mds::PoseidonMdsGenerator;
+    use crate::gates::poseidon_sponge::PoseidonSpongeGenerator;
     use crate::gates::random_access::RandomAccessGenerator;
     use crate::gates::reducing::ReducingGenerator;
     use crate::gates::reducing_extension::ReducingGenerator as ReducingExtensionGenerator;
+    use crate::gates::select::SelectGenerator;
     use crate::hash::hash_types::RichField;
     use crate::iop::generator::{
         ConstantGenerator, CopyGenerator, NonzeroTestGenerator, RandomValueGenerator,
@@ -157,24 +165,32 @@ pub mod default {
             ArithmeticExtensionGenerator<F, D>,
             BaseSplitGenerator<2>,
             BaseSumGenerator<2>,
+            ComparisonGenerator,
             ConstantGenerator<F>,
             CopyGenerator,
+            DivRemGenerator,
             DummyProofGenerator<F, C, D>,
             EqualityGenerator,
+            ExponentiationExtensionGenerator<D>,
             ExponentiationGenerator<F, D>,
+            InterpolationChunkGenerator<F, D>,
             InterpolationGenerator<F, D>,
             LookupGenerator,
             LookupTableGenerator,
             LowHighGenerator,
+            MulExtensionAlgebraGenerator<D>,
             MulExtensionGenerator<F, D>,
             NonzeroTestGenerator,
             PoseidonGenerator<F, D>,
             PoseidonMdsGenerator<D>,
+            PoseidonSpongeGenerator<F, D>,
             QuotientGeneratorExtension<D>,
             RandomAccessGenerator<F, D>,
             RandomValueGenerator,
             ReducingGenerator<D>,
             ReducingExtensionGenerator<D>,
+            SelectGenerator,
+            ShuffleGenerator,
             SplitGenerator,
             WireSplitGenerator
         }
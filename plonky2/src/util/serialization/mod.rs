@@ -4,8 +4,10 @@ pub mod generator_serialization;
 #[macro_use]
 pub mod gate_serialization;
 
+pub mod aligned;
+
 #[cfg(not(feature = "std"))]
-use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec, vec::Vec};
 use core::convert::Infallible;
 use core::fmt::{Debug, Display, Formatter};
 use core::mem::size_of;
@@ -14,7 +16,7 @@ use core::ops::Range;
 use std::{collections::BTreeMap, sync::Arc};
 
 pub use gate_serialization::default::DefaultGateSerializer;
-pub use gate_serialization::GateSerializer;
+pub use gate_serialization::{GateRegistry, GateSerializer};
 pub use generator_serialization::default::DefaultGeneratorSerializer;
 pub use generator_serialization::WitnessGeneratorSerializer;
 use hashbrown::HashMap;
@@ -35,7 +37,7 @@ use crate::gates::lookup::Lookup;
 use crate::gates::selectors::SelectorsInfo;
 use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
-use crate::hash::merkle_tree::{MerkleCap, MerkleTree};
+use crate::hash::merkle_tree::{LeafHasher, MerkleCap, MerkleTree};
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::generator::WitnessGeneratorRef;
 use crate::iop::target::{BoolTarget, Target};
@@ -46,11 +48,13 @@ use crate::plonk::circuit_data::{
     VerifierCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
 };
 use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
-use crate::plonk::plonk_common::salt_size;
+use crate::plonk::custom_argument::CustomArgumentRef;
+use crate::plonk::plonk_common::{salt_size, PlonkOracle};
 use crate::plonk::proof::{
     CompressedProof, CompressedProofWithPublicInputs, OpeningSet, OpeningSetTarget, Proof,
     ProofTarget, ProofWithPublicInputs, ProofWithPublicInputsTarget,
 };
+use crate::util::matrix::RowMajorMatrix;
 
 /// A no_std compatible variant of `std::io::Error`
 #[derive(Debug)]
@@ -131,12 +135,20 @@ pub trait Read {
         Ok(u32::from_le_bytes(buf))
     }
 
+    /// Reads a `u64` value from `self`.
+    #[inline]
+    fn read_u64(&mut self) -> IoResult<u64> {
+        let mut buf = [0; size_of::<u64>()];
+        self.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
     /// Reads a `usize` value from `self`.
     #[inline]
     fn read_usize(&mut self) -> IoResult<usize> {
         let mut buf = [0; core::mem::size_of::<u64>()];
         self.read_exact(&mut buf)?;
-        Ok(u64::from_le_bytes(buf) as usize)
+        usize::try_from(u64::from_le_bytes(buf)).map_err(|_| IoError)
     }
 
     /// Reads a vector of `usize` value from `self`.
@@ -326,18 +338,40 @@ pub trait Read {
             let leaf_len = self.read_usize()?;
             leaves.push(self.read_field_vec(leaf_len)?);
         }
+        if leaves.windows(2).any(|pair| pair[0].len() != pair[1].len()) {
+            return Err(IoError);
+        }
+        let leaves = RowMajorMatrix::from_rows(leaves);
 
         let digests_len = self.read_usize()?;
         let digests = self.read_hash_vec::<F, H>(digests_len)?;
         let cap_height = self.read_usize()?;
         let cap = self.read_merkle_cap::<F, H>(cap_height)?;
+        let arity_bits = self.read_usize()?;
+        if arity_bits == 0 {
+            return Err(IoError);
+        }
+        let leaf_hasher = self.read_leaf_hasher()?;
         Ok(MerkleTree {
             leaves,
             digests,
             cap,
+            arity_bits,
+            leaf_hasher,
         })
     }
 
+    /// Reads a value of type [`LeafHasher`] from `self`.
+    #[inline]
+    fn read_leaf_hasher(&mut self) -> IoResult<LeafHasher> {
+        match self.read_u8()? {
+            0 => Ok(LeafHasher::Raw),
+            1 => Ok(LeafHasher::LengthPrefixed),
+            2 => Ok(LeafHasher::DomainSeparated(self.read_u64()?)),
+            _ => Err(IoError),
+        }
+    }
+
     /// Reads a value of type [`OpeningSet`] from `self` with the given `common_data`.
     #[inline]
     fn read_opening_set<F, C, const D: usize>(
@@ -356,6 +390,10 @@ pub trait Read {
         let plonk_zs_next = self.read_field_ext_vec::<F, D>(config.num_challenges)?;
         let lookup_zs = self.read_field_ext_vec::<F, D>(common_data.num_all_lookup_polys())?;
         let lookup_zs_next = self.read_field_ext_vec::<F, D>(common_data.num_all_lookup_polys())?;
+        let custom_argument_polys =
+            self.read_field_ext_vec::<F, D>(common_data.num_custom_argument_polys())?;
+        let custom_argument_polys_next =
+            self.read_field_ext_vec::<F, D>(common_data.num_custom_argument_polys())?;
         let partial_products = self
             .read_field_ext_vec::<F, D>(common_data.num_partial_products * config.num_challenges)?;
         let quotient_polys = self.read_field_ext_vec::<F, D>(
@@ -371,6 +409,8 @@ pub trait Read {
             quotient_polys,
             lookup_zs,
             lookup_zs_next,
+            custom_argument_polys,
+            custom_argument_polys_next,
         })
     }
 
@@ -384,6 +424,8 @@ pub trait Read {
         let plonk_zs_next = self.read_target_ext_vec::<D>()?;
         let lookup_zs = self.read_target_ext_vec::<D>()?;
         let next_lookup_zs = self.read_target_ext_vec::<D>()?;
+        let custom_argument_polys = self.read_target_ext_vec::<D>()?;
+        let custom_argument_polys_next = self.read_target_ext_vec::<D>()?;
         let partial_products = self.read_target_ext_vec::<D>()?;
         let quotient_polys = self.read_target_ext_vec::<D>()?;
 
@@ -395,6 +437,8 @@ pub trait Read {
             plonk_zs_next,
             lookup_zs,
             next_lookup_zs,
+            custom_argument_polys,
+            custom_argument_polys_next,
             partial_products,
             quotient_polys,
         })
@@ -452,6 +496,7 @@ pub trait Read {
         let zs_partial_v = self.read_field_vec(
             config.num_challenges
                 * (1 + common_data.num_partial_products + common_data.num_lookup_polys)
+                + common_data.num_custom_argument_polys()
                 + salt,
         )?;
         let zs_partial_p = self.read_merkle_proof()?;
@@ -661,6 +706,7 @@ pub trait Read {
         let max_quotient_degree_factor = self.read_usize()?;
         let use_base_arithmetic_gate = self.read_bool()?;
         let zero_knowledge = self.read_bool()?;
+        let large_circuit = self.read_bool()?;
         let fri_config = self.read_fri_config()?;
 
         Ok(CircuitConfig {
@@ -672,6 +718,11 @@ pub trait Read {
             max_quotient_degree_factor,
             use_base_arithmetic_gate,
             zero_knowledge,
+            // Only used while building the circuit, so not serialized.
+            optimize: false,
+            optimize_cap_heights: false,
+            minimize_routed_wires: false,
+            large_circuit,
             fri_config,
         })
     }
@@ -681,12 +732,14 @@ pub trait Read {
         let reduction_arity_bits = self.read_usize_vec()?;
         let degree_bits = self.read_usize()?;
         let hiding = self.read_bool()?;
+        let oracle_cap_heights = self.read_usize_vec()?;
 
         Ok(FriParams {
             config,
             reduction_arity_bits,
             degree_bits,
             hiding,
+            oracle_cap_heights,
         })
     }
 
@@ -696,6 +749,12 @@ pub trait Read {
         common_data: &CommonCircuitData<F, D>,
     ) -> IoResult<GateRef<F, D>>;
 
+    fn read_custom_argument<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<CustomArgumentRef<F, D>>;
+
     fn read_generator<F: RichField + Extendable<D>, const D: usize>(
         &mut self,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
@@ -729,7 +788,13 @@ pub trait Read {
         let mut polynomials = Vec::with_capacity(poly_len);
         for _ in 0..poly_len {
             let plen = self.read_usize()?;
-            polynomials.push(PolynomialCoeffs::new(self.read_field_vec(plen)?));
+            let num_stored = self.read_usize()?;
+            if num_stored > plen {
+                return Err(IoError);
+            }
+            let mut coeffs = self.read_field_vec(num_stored)?;
+            coeffs.resize(plen, F::ZERO);
+            polynomials.push(PolynomialCoeffs::new(coeffs));
         }
 
         let merkle_tree = self.read_merkle_tree()?;
@@ -792,6 +857,7 @@ pub trait Read {
             num_lookup_polys,
             num_lookup_selectors,
             luts,
+            custom_arguments: vec![],
         };
 
         for _ in 0..gates_len {
@@ -801,6 +867,15 @@ pub trait Read {
 
         common_data.gates = gates;
 
+        let custom_arguments_len = self.read_usize()?;
+        let mut custom_arguments = Vec::with_capacity(custom_arguments_len);
+        for _ in 0..custom_arguments_len {
+            let argument = self.read_custom_argument::<F, D>(gate_serializer, &common_data)?;
+            custom_arguments.push(argument);
+        }
+
+        common_data.custom_arguments = custom_arguments;
+
         Ok(common_data)
     }
 
@@ -933,9 +1008,13 @@ pub trait Read {
         let height = self.read_usize()?;
         let constants_sigmas_cap = self.read_merkle_cap(height)?;
         let circuit_digest = self.read_hash::<F, <C as GenericConfig<D>>::Hasher>()?;
+        let mut circuit_version = vec![0; self.read_usize()?];
+        self.read_exact(&mut circuit_version)?;
+        let circuit_version = String::from_utf8(circuit_version).map_err(|_| IoError)?;
         Ok(VerifierOnlyCircuitData {
             constants_sigmas_cap,
             circuit_digest,
+            circuit_version,
         })
     }
 
@@ -974,10 +1053,12 @@ pub trait Read {
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
     {
-        let config = &common_data.config;
-        let wires_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
-        let plonk_zs_partial_products_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
-        let quotient_polys_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
+        let cap_height =
+            |oracle: PlonkOracle| common_data.fri_params.oracle_cap_height(oracle.index);
+        let wires_cap = self.read_merkle_cap(cap_height(PlonkOracle::WIRES))?;
+        let plonk_zs_partial_products_cap =
+            self.read_merkle_cap(cap_height(PlonkOracle::ZS_PARTIAL_PRODUCTS))?;
+        let quotient_polys_cap = self.read_merkle_cap(cap_height(PlonkOracle::QUOTIENT))?;
         let openings = self.read_opening_set::<F, C, D>(common_data)?;
         let opening_proof = self.read_fri_proof::<F, C, D>(common_data)?;
         Ok(Proof {
@@ -1124,10 +1205,12 @@ pub trait Read {
         F: RichField + Extendable<D>,
         C: GenericConfig<D, F = F>,
     {
-        let config = &common_data.config;
-        let wires_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
-        let plonk_zs_partial_products_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
-        let quotient_polys_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
+        let cap_height =
+            |oracle: PlonkOracle| common_data.fri_params.oracle_cap_height(oracle.index);
+        let wires_cap = self.read_merkle_cap(cap_height(PlonkOracle::WIRES))?;
+        let plonk_zs_partial_products_cap =
+            self.read_merkle_cap(cap_height(PlonkOracle::ZS_PARTIAL_PRODUCTS))?;
+        let quotient_polys_cap = self.read_merkle_cap(cap_height(PlonkOracle::QUOTIENT))?;
         let openings = self.read_opening_set::<F, C, D>(common_data)?;
         let opening_proof = self.read_compressed_fri_proof::<F, C, D>(common_data)?;
         Ok(CompressedProof {
@@ -1232,6 +1315,12 @@ pub trait Write {
         self.write_all(&x.to_le_bytes())
     }
 
+    /// Writes a word `x` to `self.`
+    #[inline]
+    fn write_u64(&mut self, x: u64) -> IoResult<()> {
+        self.write_all(&x.to_le_bytes())
+    }
+
     /// Writes a word `x` to `self.`
     #[inline]
     fn write_usize(&mut self, x: usize) -> IoResult<()> {
@@ -1361,7 +1450,7 @@ pub trait Write {
         F: RichField,
         H: Hasher<F>,
     {
-        self.write_all(&h.to_bytes())
+        self.write_all(&H::hash_to_bytes(&h))
     }
 
     /// Writes a HashOutTarget `h` to `self`.
@@ -1419,18 +1508,33 @@ pub trait Write {
         F: RichField,
         H: Hasher<F>,
     {
-        self.write_usize(tree.leaves.len())?;
-        for i in 0..tree.leaves.len() {
-            self.write_usize(tree.leaves[i].len())?;
-            self.write_field_vec(&tree.leaves[i])?;
+        self.write_usize(tree.leaves.height())?;
+        for leaf in tree.leaves.rows() {
+            self.write_usize(leaf.len())?;
+            self.write_field_vec(leaf)?;
         }
         self.write_hash_vec::<F, H>(&tree.digests)?;
         self.write_usize(tree.cap.height())?;
         self.write_merkle_cap(&tree.cap)?;
+        self.write_usize(tree.arity_bits)?;
+        self.write_leaf_hasher(tree.leaf_hasher)?;
 
         Ok(())
     }
 
+    /// Writes `leaf_hasher`, a value of type [`LeafHasher`], to `self`.
+    #[inline]
+    fn write_leaf_hasher(&mut self, leaf_hasher: LeafHasher) -> IoResult<()> {
+        match leaf_hasher {
+            LeafHasher::Raw => self.write_u8(0),
+            LeafHasher::LengthPrefixed => self.write_u8(1),
+            LeafHasher::DomainSeparated(tag) => {
+                self.write_u8(2)?;
+                self.write_u64(tag)
+            }
+        }
+    }
+
     /// Writes a value `os` of type [`OpeningSet`] to `self.`
     #[inline]
     fn write_opening_set<F, const D: usize>(&mut self, os: &OpeningSet<F, D>) -> IoResult<()>
@@ -1444,6 +1548,8 @@ pub trait Write {
         self.write_field_ext_vec::<F, D>(&os.plonk_zs_next)?;
         self.write_field_ext_vec::<F, D>(&os.lookup_zs)?;
         self.write_field_ext_vec::<F, D>(&os.lookup_zs_next)?;
+        self.write_field_ext_vec::<F, D>(&os.custom_argument_polys)?;
+        self.write_field_ext_vec::<F, D>(&os.custom_argument_polys_next)?;
         self.write_field_ext_vec::<F, D>(&os.partial_products)?;
         self.write_field_ext_vec::<F, D>(&os.quotient_polys)
     }
@@ -1461,6 +1567,8 @@ pub trait Write {
         self.write_target_ext_vec::<D>(&os.plonk_zs_next)?;
         self.write_target_ext_vec::<D>(&os.lookup_zs)?;
         self.write_target_ext_vec::<D>(&os.next_lookup_zs)?;
+        self.write_target_ext_vec::<D>(&os.custom_argument_polys)?;
+        self.write_target_ext_vec::<D>(&os.custom_argument_polys_next)?;
         self.write_target_ext_vec::<D>(&os.partial_products)?;
         self.write_target_ext_vec::<D>(&os.quotient_polys)
     }
@@ -1676,12 +1784,14 @@ pub trait Write {
             reduction_arity_bits,
             degree_bits,
             hiding,
+            oracle_cap_heights,
         } = fri_params;
 
         self.write_fri_config(config)?;
         self.write_usize_vec(reduction_arity_bits.as_slice())?;
         self.write_usize(*degree_bits)?;
         self.write_bool(*hiding)?;
+        self.write_usize_vec(oracle_cap_heights.as_slice())?;
 
         Ok(())
     }
@@ -1696,6 +1806,10 @@ pub trait Write {
             max_quotient_degree_factor,
             use_base_arithmetic_gate,
             zero_knowledge,
+            optimize: _,
+            optimize_cap_heights: _,
+            minimize_routed_wires: _,
+            large_circuit,
             fri_config,
         } = config;
 
@@ -1707,6 +1821,7 @@ pub trait Write {
         self.write_usize(*max_quotient_degree_factor)?;
         self.write_bool(*use_base_arithmetic_gate)?;
         self.write_bool(*zero_knowledge)?;
+        self.write_bool(*large_circuit)?;
         self.write_fri_config(fri_config)?;
 
         Ok(())
@@ -1719,6 +1834,13 @@ pub trait Write {
         common_data: &CommonCircuitData<F, D>,
     ) -> IoResult<()>;
 
+    fn write_custom_argument<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        argument: &CustomArgumentRef<F, D>,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()>;
+
     fn write_generator<F: RichField + Extendable<D>, const D: usize>(
         &mut self,
         generator: &WitnessGeneratorRef<F, D>,
@@ -1750,9 +1872,13 @@ pub trait Write {
         poly_batch: &PolynomialBatch<F, C, D>,
     ) -> IoResult<()> {
         self.write_usize(poly_batch.polynomials.len())?;
-        for i in 0..poly_batch.polynomials.len() {
-            self.write_usize(poly_batch.polynomials[i].coeffs.len())?;
-            self.write_field_vec(&poly_batch.polynomials[i].coeffs)?;
+        // Trailing zero coefficients are omitted, so that constant and low-degree polynomials,
+        // e.g. most selectors, are stored compactly.
+        for poly in &poly_batch.polynomials {
+            let num_stored = poly.degree_plus_one();
+            self.write_usize(poly.len())?;
+            self.write_usize(num_stored)?;
+            self.write_field_vec(&poly.coeffs[..num_stored])?;
         }
         self.write_merkle_tree(&poly_batch.merkle_tree)?;
         self.write_usize(poly_batch.degree_log)?;
@@ -1781,6 +1907,7 @@ pub trait Write {
             num_lookup_polys,
             num_lookup_selectors,
             luts,
+            custom_arguments,
         } = common_data;
 
         self.write_circuit_config(config)?;
@@ -1809,6 +1936,11 @@ pub trait Write {
             self.write_gate::<F, D>(gate, gate_serializer, common_data)?;
         }
 
+        self.write_usize(custom_arguments.len())?;
+        for argument in custom_arguments.iter() {
+            self.write_custom_argument::<F, D>(argument, gate_serializer, common_data)?;
+        }
+
         Ok(())
     }
 
@@ -1935,11 +2067,14 @@ pub trait Write {
         let VerifierOnlyCircuitData {
             constants_sigmas_cap,
             circuit_digest,
+            circuit_version,
         } = verifier_only_circuit_data;
 
         self.write_usize(constants_sigmas_cap.height())?;
         self.write_merkle_cap(constants_sigmas_cap)?;
         self.write_hash::<F, <C as GenericConfig<D>>::Hasher>(*circuit_digest)?;
+        self.write_usize(circuit_version.len())?;
+        self.write_all(circuit_version.as_bytes())?;
 
         Ok(())
     }
@@ -2040,7 +2175,7 @@ pub trait Write {
         C: GenericConfig<D, F = F>,
     {
         for &i in &cfqrs.indices {
-            self.write_u32(i as u32)?;
+            self.write_u32(u32::try_from(i).map_err(|_| IoError)?)?;
         }
         let mut initial_trees_proofs = cfqrs.initial_trees_proofs.iter().collect::<Vec<_>>();
         initial_trees_proofs.sort_by_key(|&x| x.0);
@@ -2153,6 +2288,15 @@ impl Write for Vec<u8> {
         gate_serializer.write_gate(self, gate, common_data)
     }
 
+    fn write_custom_argument<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        argument: &CustomArgumentRef<F, D>,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<()> {
+        gate_serializer.write_custom_argument(self, argument, common_data)
+    }
+
     fn write_generator<F: RichField + Extendable<D>, const D: usize>(
         &mut self,
         generator: &WitnessGeneratorRef<F, D>,
@@ -2223,6 +2367,14 @@ impl Read for Buffer<'_> {
         gate_serializer.read_gate(self, common_data)
     }
 
+    fn read_custom_argument<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<CustomArgumentRef<F, D>> {
+        gate_serializer.read_custom_argument(self, common_data)
+    }
+
     fn read_generator<F: RichField + Extendable<D>, const D: usize>(
         &mut self,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
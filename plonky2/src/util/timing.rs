@@ -1,8 +1,68 @@
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Write;
+use core::time::Duration;
+#[cfg(feature = "timing")]
+use std::sync::OnceLock;
+
 use log::{log, Level};
 #[cfg(feature = "timing")]
-use web_time::{Duration, Instant};
+use web_time::Instant;
+
+/// The source of allocation statistics used by [`TimingTree`], see
+/// [`set_allocated_bytes_counter`].
+#[cfg(feature = "timing")]
+static ALLOCATED_BYTES_COUNTER: OnceLock<fn() -> u64> = OnceLock::new();
+
+/// Sets the function returning the total number of bytes allocated so far by the process, e.g.
+/// from a counting global allocator or from jemalloc's statistics. Once set, each scope of a
+/// [`TimingTree`] records the number of bytes allocated while it was open, across all threads.
+///
+/// The counter can only be set once, and this returns `false` if it was already set.
+#[cfg(feature = "timing")]
+pub fn set_allocated_bytes_counter(counter: fn() -> u64) -> bool {
+    ALLOCATED_BYTES_COUNTER.set(counter).is_ok()
+}
+
+#[cfg(feature = "timing")]
+fn allocated_bytes() -> Option<u64> {
+    ALLOCATED_BYTES_COUNTER.get().map(|counter| counter())
+}
+
+#[cfg(feature = "timing")]
+fn current_thread_name() -> String {
+    let thread = std::thread::current();
+    match thread.name() {
+        Some(name) => name.to_string(),
+        None => format!("{:?}", thread.id()),
+    }
+}
+
+/// A structured record of a scope of a [`TimingTree`], as returned by [`TimingTree::spans`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpanRecord {
+    /// The name of the scope.
+    pub name: String,
+    /// The depth of the scope in the tree, the root having depth 0.
+    pub depth: usize,
+    /// The name of the thread which opened the scope, or its ID if it is unnamed.
+    pub thread: String,
+    /// The time at which the scope was opened, relative to the opening of the root scope.
+    pub start: Duration,
+    /// The time spent in the scope so far.
+    pub duration: Duration,
+    /// The number of bytes allocated while the scope was open, if an allocation counter was set
+    /// with [`set_allocated_bytes_counter`].
+    pub bytes_allocated: Option<u64>,
+}
 
 /// The hierarchy of scopes, and the time consumed by each one. Useful for profiling.
+///
+/// Besides printing it, the tree can be exported as [`SpanRecord`]s, as JSON with
+/// [`to_json`](Self::to_json), or in the Chrome trace event format with
+/// [`to_chrome_trace`](Self::to_chrome_trace), which can be loaded in `chrome://tracing` or
+/// Perfetto. With the `tracing` feature, each scope is also mirrored as a `tracing` span named
+/// `timing`, whose `scope` field holds the scope's name.
 #[cfg(feature = "timing")]
 #[derive(Debug)]
 pub struct TimingTree {
@@ -14,6 +74,15 @@ pub struct TimingTree {
     enter_time: Instant,
     /// The time when this scope was destroyed, or None if it has not yet been destroyed.
     exit_time: Option<Instant>,
+    /// The name of the thread which created this scope.
+    thread: String,
+    /// The value of the allocation counter when this scope was created, if set.
+    enter_allocated: Option<u64>,
+    /// The value of the allocation counter when this scope was destroyed, if set.
+    exit_allocated: Option<u64>,
+    /// The `tracing` span mirroring this scope.
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
     /// Any child scopes.
     children: Vec<TimingTree>,
 }
@@ -39,20 +108,35 @@ impl Default for TimingTree {
 impl TimingTree {
     #[cfg(feature = "timing")]
     pub fn new(root_name: &str, level: Level) -> Self {
+        Self::new_scope(root_name, level, None)
+    }
+
+    #[cfg(not(feature = "timing"))]
+    pub fn new(_root_name: &str, level: Level) -> Self {
+        Self(level)
+    }
+
+    #[cfg(feature = "timing")]
+    fn new_scope(name: &str, level: Level, parent: Option<&Self>) -> Self {
+        #[cfg(feature = "tracing")]
+        let span = tracing_span(name, level, parent.map(|parent| &parent.span));
+        #[cfg(not(feature = "tracing"))]
+        let _ = parent;
+
         Self {
-            name: root_name.to_string(),
+            name: name.to_string(),
             level,
             enter_time: Instant::now(),
             exit_time: None,
+            thread: current_thread_name(),
+            enter_allocated: allocated_bytes(),
+            exit_allocated: None,
+            #[cfg(feature = "tracing")]
+            span,
             children: vec![],
         }
     }
 
-    #[cfg(not(feature = "timing"))]
-    pub fn new(_root_name: &str, level: Level) -> Self {
-        Self(level)
-    }
-
     /// Whether this scope is still in scope.
     #[cfg(feature = "timing")]
     const fn is_open(&self) -> bool {
@@ -91,13 +175,12 @@ impl TimingTree {
             }
         }
 
-        self.children.push(TimingTree {
-            name: ctx.to_string(),
-            level,
-            enter_time: Instant::now(),
-            exit_time: None,
-            children: vec![],
-        })
+        let child = Self::new_scope(ctx, level, Some(self));
+        #[cfg(feature = "tracing")]
+        child
+            .span
+            .with_subscriber(|(id, dispatch)| dispatch.enter(id));
+        self.children.push(child);
     }
 
     #[cfg(not(feature = "timing"))]
@@ -116,6 +199,10 @@ impl TimingTree {
         }
 
         self.exit_time = Some(Instant::now());
+        self.exit_allocated = allocated_bytes();
+        #[cfg(feature = "tracing")]
+        self.span
+            .with_subscriber(|(id, dispatch)| dispatch.exit(id));
     }
 
     #[cfg(not(feature = "timing"))]
@@ -128,6 +215,13 @@ impl TimingTree {
             .duration_since(self.enter_time)
     }
 
+    #[cfg(feature = "timing")]
+    fn bytes_allocated(&self) -> Option<u64> {
+        let enter = self.enter_allocated?;
+        let exit = self.exit_allocated.or_else(allocated_bytes)?;
+        Some(exit.saturating_sub(enter))
+    }
+
     /// Filter out children with a low duration.
     #[cfg(feature = "timing")]
     pub fn filter(&self, min_delta: Duration) -> Self {
@@ -136,6 +230,11 @@ impl TimingTree {
             level: self.level,
             enter_time: self.enter_time,
             exit_time: self.exit_time,
+            thread: self.thread.clone(),
+            enter_allocated: self.enter_allocated,
+            exit_allocated: self.exit_allocated,
+            #[cfg(feature = "tracing")]
+            span: self.span.clone(),
             children: self
                 .children
                 .iter()
@@ -145,6 +244,105 @@ impl TimingTree {
         }
     }
 
+    /// Returns a record of each scope of the tree, in depth-first order, starting with the root.
+    /// Scopes which are still open are reported with their duration so far.
+    #[cfg(feature = "timing")]
+    pub fn spans(&self) -> Vec<SpanRecord> {
+        let mut spans = Vec::new();
+        self.spans_helper(self.enter_time, 0, &mut spans);
+        spans
+    }
+
+    #[cfg(not(feature = "timing"))]
+    pub fn spans(&self) -> Vec<SpanRecord> {
+        Vec::new()
+    }
+
+    #[cfg(feature = "timing")]
+    fn spans_helper(&self, root_enter_time: Instant, depth: usize, spans: &mut Vec<SpanRecord>) {
+        spans.push(SpanRecord {
+            name: self.name.clone(),
+            depth,
+            thread: self.thread.clone(),
+            start: self.enter_time.duration_since(root_enter_time),
+            duration: self.duration(),
+            bytes_allocated: self.bytes_allocated(),
+        });
+        for child in &self.children {
+            child.spans_helper(root_enter_time, depth + 1, spans);
+        }
+    }
+
+    /// Returns the [`spans`](Self::spans) of the tree as a JSON array of objects with the fields
+    /// `name`, `depth`, `thread`, `start_us`, `duration_us` and `bytes_allocated`, the latter
+    /// being `null` if no allocation counter was set. Times are in microseconds.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("[");
+        for (i, span) in self.spans().iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str("{\"name\":");
+            write_json_string(&mut json, &span.name);
+            write!(json, ",\"depth\":{},\"thread\":", span.depth).unwrap();
+            write_json_string(&mut json, &span.thread);
+            write!(
+                json,
+                ",\"start_us\":{},\"duration_us\":{},\"bytes_allocated\":",
+                micros(span.start),
+                micros(span.duration)
+            )
+            .unwrap();
+            match span.bytes_allocated {
+                Some(bytes) => write!(json, "{bytes}").unwrap(),
+                None => json.push_str("null"),
+            }
+            json.push('}');
+        }
+        json.push(']');
+        json
+    }
+
+    /// Returns the [`spans`](Self::spans) of the tree in the Chrome trace event format, as
+    /// complete events with one track per thread.
+    pub fn to_chrome_trace(&self) -> String {
+        let spans = self.spans();
+        let mut threads: Vec<&str> = Vec::new();
+        let mut events = Vec::new();
+        for span in &spans {
+            let tid = match threads.iter().position(|&t| t == span.thread) {
+                Some(tid) => tid,
+                None => {
+                    threads.push(&span.thread);
+                    threads.len() - 1
+                }
+            };
+            let mut event = String::from("{\"name\":");
+            write_json_string(&mut event, &span.name);
+            write!(
+                event,
+                ",\"cat\":\"plonky2\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":0,\"tid\":{tid}",
+                micros(span.start),
+                micros(span.duration)
+            )
+            .unwrap();
+            if let Some(bytes) = span.bytes_allocated {
+                write!(event, ",\"args\":{{\"bytes_allocated\":{bytes}}}").unwrap();
+            }
+            event.push('}');
+            events.push(event);
+        }
+        for (tid, thread) in threads.iter().enumerate() {
+            let mut event = format!(
+                "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":{tid},\"args\":{{\"name\":"
+            );
+            write_json_string(&mut event, thread);
+            event.push_str("}}");
+            events.push(event);
+        }
+        format!("{{\"traceEvents\":[{}]}}", events.join(","))
+    }
+
     #[cfg(feature = "timing")]
     pub fn print(&self) {
         self.print_helper(0);
@@ -174,6 +372,45 @@ impl TimingTree {
     }
 }
 
+/// Creates the `tracing` span mirroring a scope. `tracing` needs the level of a span to be known
+/// statically, hence the match.
+#[cfg(feature = "tracing")]
+fn tracing_span(name: &str, level: Level, parent: Option<&tracing::Span>) -> tracing::Span {
+    let parent = parent.and_then(tracing::Span::id);
+    match level {
+        Level::Error => tracing::error_span!(parent: parent, "timing", scope = name),
+        Level::Warn => tracing::warn_span!(parent: parent, "timing", scope = name),
+        Level::Info => tracing::info_span!(parent: parent, "timing", scope = name),
+        Level::Debug => tracing::debug_span!(parent: parent, "timing", scope = name),
+        Level::Trace => tracing::trace_span!(parent: parent, "timing", scope = name),
+    }
+}
+
+/// Formats a duration in microseconds, with nanosecond precision.
+fn micros(duration: Duration) -> String {
+    format!(
+        "{}.{:03}",
+        duration.as_micros(),
+        duration.subsec_nanos() % 1000
+    )
+}
+
+fn write_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
 /// Creates a named scope; useful for debugging.
 #[macro_export]
 macro_rules! timed {
@@ -191,3 +428,58 @@ macro_rules! timed {
         res
     }};
 }
+
+#[cfg(all(test, feature = "timing"))]
+mod tests {
+    use log::Level;
+    use serde_json::Value;
+
+    use super::*;
+
+    fn sample_tree() -> TimingTree {
+        let mut timing = TimingTree::new("prove \"x\"", Level::Info);
+        timing.push("commit", Level::Debug);
+        timing.push("fft", Level::Debug);
+        timing.pop();
+        timing.pop();
+        timed!(timing, "open", ());
+        timing
+    }
+
+    #[test]
+    fn test_spans() {
+        let spans = sample_tree().spans();
+        let names_and_depths = spans
+            .iter()
+            .map(|span| (span.name.as_str(), span.depth))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names_and_depths,
+            [("prove \"x\"", 0), ("commit", 1), ("fft", 2), ("open", 1)]
+        );
+        // Children are nested within their parents.
+        let end = |span: &SpanRecord| span.start + span.duration;
+        assert!(spans[2].start >= spans[1].start && end(&spans[2]) <= end(&spans[1]));
+        assert!(spans[3].start >= end(&spans[1]));
+        assert!(spans.iter().all(|span| span.thread == spans[0].thread));
+    }
+
+    #[test]
+    fn test_json_export() {
+        let json: Value = serde_json::from_str(&sample_tree().to_json()).unwrap();
+        let spans = json.as_array().unwrap();
+        assert_eq!(spans.len(), 4);
+        assert_eq!(spans[0]["name"], "prove \"x\"");
+        assert_eq!(spans[2]["depth"], 2);
+        assert!(spans[3]["duration_us"].is_f64());
+    }
+
+    #[test]
+    fn test_chrome_trace_export() {
+        let trace: Value = serde_json::from_str(&sample_tree().to_chrome_trace()).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+        let complete = events.iter().filter(|e| e["ph"] == "X").count();
+        let metadata = events.iter().filter(|e| e["ph"] == "M").count();
+        assert_eq!((complete, metadata), (4, 1));
+    }
+}
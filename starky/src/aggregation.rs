@@ -0,0 +1,415 @@
+//! Utilities to aggregate one or more STARK proofs into a single plonky2
+//! proof, with the STARK public inputs routed to the outer circuit.
+//!
+//! A [`StarkAggregationBuilder`] wraps a [`CircuitBuilder`] and takes care of
+//! allocating the proof targets, regenerating the Fiat-Shamir challenges
+//! in-circuit and registering the selected STARK public inputs as public
+//! inputs of the outer circuit, in the order in which the STARKs are added.
+//!
+//! ```ignore
+//! let mut aggregator = StarkAggregationBuilder::<F, C, D>::new(CircuitConfig::standard_recursion_config());
+//! let fib = aggregator.add_stark_proof(fib_stark, &stark_config, fib_degree_bits);
+//! let other = aggregator.add_stark_proof_with_public_inputs(other_stark, &stark_config, other_degree_bits, &[2, 0]);
+//! aggregator.connect_public_inputs((fib, 2), (other, 0));
+//!
+//! let circuit = aggregator.build::<C>();
+//! let proof = circuit.prove(&[fib_proof, other_proof])?;
+//! circuit.verify(proof)?;
+//! ```
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use anyhow::{ensure, Result};
+use itertools::Itertools;
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::plonk::prover::ProverHooks;
+
+use crate::config::StarkConfig;
+use crate::proof::{StarkProofWithPublicInputs, StarkProofWithPublicInputsTarget};
+use crate::recursive_verifier::{
+    add_virtual_stark_proof_with_pis, set_stark_proof_with_pis_target, verify_stark_proof_circuit,
+};
+use crate::stark::Stark;
+
+/// A STARK proof verified inside a [`StarkAggregationBuilder`].
+#[derive(Clone, Debug)]
+struct StarkProofSlot<const D: usize> {
+    proof_with_pis: StarkProofWithPublicInputsTarget<D>,
+    config: StarkConfig,
+    degree_bits: usize,
+}
+
+/// Builder for a plonky2 circuit verifying one or more STARK proofs generated
+/// with the configuration `InnerC`.
+///
+/// Each call to [`add_stark_proof`](Self::add_stark_proof) (or
+/// [`add_stark_proof_with_public_inputs`](Self::add_stark_proof_with_public_inputs))
+/// allocates a new proof slot, encodes its verification in the circuit and
+/// returns the index of the slot. The same order must be followed when
+/// providing the STARK proofs to [`StarkAggregationCircuit::prove`].
+#[derive(Debug)]
+pub struct StarkAggregationBuilder<
+    F: RichField + Extendable<D>,
+    InnerC: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    builder: CircuitBuilder<F, D>,
+    slots: Vec<StarkProofSlot<D>>,
+    _phantom: PhantomData<InnerC>,
+}
+
+impl<F, InnerC, const D: usize> StarkAggregationBuilder<F, InnerC, D>
+where
+    F: RichField + Extendable<D>,
+    InnerC: GenericConfig<D, F = F>,
+    InnerC::Hasher: AlgebraicHasher<F>,
+{
+    /// Creates a new aggregation builder for an outer circuit with the given configuration.
+    pub fn new(config: CircuitConfig) -> Self {
+        Self {
+            builder: CircuitBuilder::new(config),
+            slots: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Gives access to the underlying [`CircuitBuilder`], to add custom logic
+    /// on top of the STARK verification (e.g. additional public inputs).
+    pub fn builder(&mut self) -> &mut CircuitBuilder<F, D> {
+        &mut self.builder
+    }
+
+    /// Returns the number of STARK proofs verified by this circuit.
+    pub fn num_proofs(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Adds the verification of a STARK proof of trace length `2^degree_bits`,
+    /// and registers all its public inputs, in order, as public inputs of the
+    /// outer circuit. Returns the index of the newly added proof.
+    pub fn add_stark_proof<S: Stark<F, D>>(
+        &mut self,
+        stark: S,
+        inner_config: &StarkConfig,
+        degree_bits: usize,
+    ) -> usize {
+        let public_inputs = (0..stark.num_public_inputs()).collect_vec();
+        self.add_stark_proof_with_public_inputs(stark, inner_config, degree_bits, &public_inputs)
+    }
+
+    /// Adds the verification of a STARK proof of trace length `2^degree_bits`,
+    /// and registers the STARK public inputs at `public_input_indices`, in the
+    /// given order, as public inputs of the outer circuit. Returns the index of
+    /// the newly added proof.
+    pub fn add_stark_proof_with_public_inputs<S: Stark<F, D>>(
+        &mut self,
+        stark: S,
+        inner_config: &StarkConfig,
+        degree_bits: usize,
+        public_input_indices: &[usize],
+    ) -> usize {
+        assert!(
+            public_input_indices
+                .iter()
+                .all(|&i| i < stark.num_public_inputs()),
+            "Public input index out of range for a STARK with {} public inputs.",
+            stark.num_public_inputs()
+        );
+
+        let proof_with_pis = add_virtual_stark_proof_with_pis(
+            &mut self.builder,
+            &stark,
+            inner_config,
+            degree_bits,
+            0,
+            0,
+        );
+        verify_stark_proof_circuit::<F, InnerC, S, D>(
+            &mut self.builder,
+            stark,
+            proof_with_pis.clone(),
+            inner_config,
+            None,
+        );
+
+        for &i in public_input_indices {
+            self.builder
+                .register_public_input(proof_with_pis.public_inputs[i]);
+        }
+
+        self.slots.push(StarkProofSlot {
+            proof_with_pis,
+            config: inner_config.clone(),
+            degree_bits,
+        });
+        self.slots.len() - 1
+    }
+
+    /// Returns the public input targets of the STARK proof at index `proof_index`.
+    pub fn public_inputs(&self, proof_index: usize) -> &[Target] {
+        &self.slots[proof_index].proof_with_pis.public_inputs
+    }
+
+    /// Enforces equality between two STARK public inputs, each given as a
+    /// pair `(proof_index, public_input_index)`.
+    pub fn connect_public_inputs(&mut self, a: (usize, usize), b: (usize, usize)) {
+        let x = self.public_inputs(a.0)[a.1];
+        let y = self.public_inputs(b.0)[b.1];
+        self.builder.connect(x, y);
+    }
+
+    /// Builds the aggregation circuit.
+    pub fn build<C: GenericConfig<D, F = F>>(self) -> StarkAggregationCircuit<F, C, InnerC, D> {
+        let Self {
+            mut builder, slots, ..
+        } = self;
+        let zero = builder.zero();
+        let circuit = builder.build::<C>();
+
+        StarkAggregationCircuit {
+            circuit,
+            slots,
+            zero,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// A plonky2 circuit verifying STARK proofs, as built by a [`StarkAggregationBuilder`].
+#[derive(Debug)]
+pub struct StarkAggregationCircuit<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    InnerC: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    /// The underlying plonky2 circuit.
+    pub circuit: CircuitData<F, C, D>,
+    slots: Vec<StarkProofSlot<D>>,
+    zero: Target,
+    _phantom: PhantomData<InnerC>,
+}
+
+impl<F, C, InnerC, const D: usize> StarkAggregationCircuit<F, C, InnerC, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    InnerC: GenericConfig<D, F = F>,
+    InnerC::Hasher: AlgebraicHasher<F>,
+{
+    /// Returns the number of STARK proofs verified by this circuit.
+    pub fn num_proofs(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Fills a [`PartialWitness`] with the given STARK proofs, provided in the
+    /// same order as they were added to the [`StarkAggregationBuilder`].
+    pub fn set_proofs(
+        &self,
+        witness: &mut PartialWitness<F>,
+        proofs: &[StarkProofWithPublicInputs<F, InnerC, D>],
+    ) -> Result<()> {
+        ensure!(
+            proofs.len() == self.slots.len(),
+            "Expected {} STARK proofs, got {}.",
+            self.slots.len(),
+            proofs.len()
+        );
+        for (i, (slot, proof)) in self.slots.iter().zip_eq(proofs).enumerate() {
+            let degree_bits = proof.proof.recover_degree_bits(&slot.config);
+            ensure!(
+                degree_bits == slot.degree_bits,
+                "STARK proof {} has degree bits {}, but its slot expects {}.",
+                i,
+                degree_bits,
+                slot.degree_bits
+            );
+            set_stark_proof_with_pis_target(
+                witness,
+                &slot.proof_with_pis,
+                proof,
+                degree_bits,
+                self.zero,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Generates a plonky2 proof attesting to the validity of the given STARK
+    /// proofs, provided in the same order as they were added to the
+    /// [`StarkAggregationBuilder`].
+    pub fn prove(
+        &self,
+        proofs: &[StarkProofWithPublicInputs<F, InnerC, D>],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut witness = PartialWitness::new();
+        self.set_proofs(&mut witness, proofs)?;
+        self.circuit.prove(witness)
+    }
+
+    /// Like [`Self::prove`], but reports progress to `hooks` and lets them cancel proving, see
+    /// [`ProverHooks`].
+    pub fn prove_with_hooks(
+        &self,
+        proofs: &[StarkProofWithPublicInputs<F, InnerC, D>],
+        hooks: &ProverHooks,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut witness = PartialWitness::new();
+        self.set_proofs(&mut witness, proofs)?;
+        self.circuit.prove_with_hooks(witness, hooks)
+    }
+
+    /// Verifies an aggregated proof.
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> Result<()> {
+        self.circuit.verify(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use anyhow::Result;
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::PartialWitness;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::plonk::prover::{ProverHooks, ProverPhase, ProvingCancelled};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::aggregation::StarkAggregationBuilder;
+    use crate::config::StarkConfig;
+    use crate::fibonacci_stark::FibonacciStark;
+    use crate::proof::StarkProofWithPublicInputs;
+    use crate::prover::prove;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = FibonacciStark<F, D>;
+
+    fn fibonacci<F: Field>(n: usize, x0: F, x1: F) -> F {
+        (0..n).fold((x0, x1), |x, _| (x.1, x.0 + x.1)).1
+    }
+
+    fn fibonacci_proof(
+        config: &StarkConfig,
+        degree_bits: usize,
+        x0: F,
+        x1: F,
+    ) -> Result<StarkProofWithPublicInputs<F, C, D>> {
+        let num_rows = 1 << degree_bits;
+        let public_inputs = [x0, x1, fibonacci(num_rows - 1, x0, x1)];
+        let stark = S::new(num_rows);
+        let trace = stark.generate_trace(x0, x1);
+        prove::<F, C, S, D>(
+            stark,
+            config,
+            trace,
+            &public_inputs,
+            None,
+            &mut TimingTree::default(),
+        )
+    }
+
+    #[test]
+    fn test_aggregate_stark_proofs() -> Result<()> {
+        let stark_config = StarkConfig::standard_fast_config();
+        let proof_a = fibonacci_proof(&stark_config, 5, F::ZERO, F::ONE)?;
+        let proof_b = fibonacci_proof(&stark_config, 6, F::ONE, F::TWO)?;
+
+        let mut aggregator =
+            StarkAggregationBuilder::<F, C, D>::new(CircuitConfig::standard_recursion_config());
+        aggregator.add_stark_proof(S::new(1 << 5), &stark_config, 5);
+        aggregator.add_stark_proof_with_public_inputs(S::new(1 << 6), &stark_config, 6, &[2, 0]);
+        let circuit = aggregator.build::<C>();
+
+        let proof = circuit.prove(&[proof_a.clone(), proof_b.clone()])?;
+        let expected_public_inputs = [
+            proof_a.public_inputs,
+            vec![proof_b.public_inputs[2], proof_b.public_inputs[0]],
+        ]
+        .concat();
+        assert_eq!(proof.public_inputs, expected_public_inputs);
+
+        circuit.verify(proof)
+    }
+
+    #[test]
+    fn test_aggregate_wrong_degree() -> Result<()> {
+        let stark_config = StarkConfig::standard_fast_config();
+        let proof = fibonacci_proof(&stark_config, 6, F::ZERO, F::ONE)?;
+
+        let mut aggregator =
+            StarkAggregationBuilder::<F, C, D>::new(CircuitConfig::standard_recursion_config());
+        aggregator.add_stark_proof(S::new(1 << 5), &stark_config, 5);
+        let circuit = aggregator.build::<C>();
+
+        let mut witness = PartialWitness::new();
+        let err = circuit.set_proofs(&mut witness, &[proof]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "STARK proof 0 has degree bits 6, but its slot expects 5."
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_connected_public_inputs() -> Result<()> {
+        let stark_config = StarkConfig::standard_fast_config();
+        let proof_a = fibonacci_proof(&stark_config, 5, F::ZERO, F::ONE)?;
+        let proof_b = fibonacci_proof(&stark_config, 5, F::ONE, F::TWO)?;
+
+        let mut aggregator =
+            StarkAggregationBuilder::<F, C, D>::new(CircuitConfig::standard_recursion_config());
+        let a =
+            aggregator.add_stark_proof_with_public_inputs(S::new(1 << 5), &stark_config, 5, &[]);
+        let b =
+            aggregator.add_stark_proof_with_public_inputs(S::new(1 << 5), &stark_config, 5, &[]);
+        // The second sequence must start with the second value of the first one.
+        aggregator.connect_public_inputs((a, 1), (b, 0));
+        let circuit = aggregator.build::<C>();
+
+        let proof = circuit.prove(&[proof_a.clone(), proof_b.clone()])?;
+        circuit.verify(proof)?;
+
+        // Swapping the proofs breaks the connection.
+        assert!(circuit.prove(&[proof_b, proof_a]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_with_hooks() -> Result<()> {
+        let stark_config = StarkConfig::standard_fast_config();
+        let proof = fibonacci_proof(&stark_config, 5, F::ZERO, F::ONE)?;
+
+        let mut aggregator =
+            StarkAggregationBuilder::<F, C, D>::new(CircuitConfig::standard_recursion_config());
+        aggregator.add_stark_proof(S::new(1 << 5), &stark_config, 5);
+        let circuit = aggregator.build::<C>();
+
+        let hooks = ProverHooks::new().with_should_cancel(|| true);
+        let err = circuit.prove_with_hooks(&[proof], &hooks).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ProvingCancelled>(),
+            Some(&ProvingCancelled {
+                phase: ProverPhase::GenerateWitness
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,426 @@
+//! A symbolic builder for the constraints of a STARK.
+//!
+//! Instead of writing [`Stark::eval_packed_generic`] and [`Stark::eval_ext_circuit`] by hand,
+//! the constraints of a STARK can be described once with an [`AirBuilder`], as [`Expr`]essions
+//! over the columns of two consecutive rows and the public inputs. The builder keeps track of
+//! the degree of each constraint, including the one added by its row filter, and rejects any
+//! constraint exceeding the maximal degree it was created with. Both evaluation methods of the
+//! [`Stark`] trait can then be delegated to [`AirBuilder::eval_packed_generic`] and
+//! [`AirBuilder::eval_ext_circuit`], and [`Stark::constraint_degree`] to
+//! [`AirBuilder::constraint_degree`].
+//!
+//! Columns are referred to by their index, `0..num_columns`, where the fixed columns of the
+//! STARK, if any, come after its trace columns as in the [`Stark`] evaluation frames.
+//!
+//! [`Stark`]: crate::stark::Stark
+//! [`Stark::eval_packed_generic`]: crate::stark::Stark::eval_packed_generic
+//! [`Stark::eval_ext_circuit`]: crate::stark::Stark::eval_ext_circuit
+//! [`Stark::constraint_degree`]: crate::stark::Stark::constraint_degree
+
+#[cfg(not(feature = "std"))]
+use alloc::{sync::Arc, vec::Vec};
+use core::fmt::{self, Display, Formatter};
+use core::iter::Sum;
+use core::ops::{Add, Mul, Neg, Sub};
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::evaluation_frame::StarkEvaluationFrame;
+
+/// A symbolic polynomial expression over the columns of two consecutive rows of a STARK trace
+/// and its public inputs, along with its degree.
+#[derive(Clone, Debug)]
+pub struct Expr<F: Field> {
+    node: Arc<Node<F>>,
+    degree: usize,
+}
+
+#[derive(Debug)]
+enum Node<F: Field> {
+    Constant(F),
+    Local(usize),
+    Next(usize),
+    PublicInput(usize),
+    Add(Expr<F>, Expr<F>),
+    Sub(Expr<F>, Expr<F>),
+    Mul(Expr<F>, Expr<F>),
+    Neg(Expr<F>),
+}
+
+impl<F: Field> Expr<F> {
+    fn new(node: Node<F>, degree: usize) -> Self {
+        Self {
+            node: Arc::new(node),
+            degree,
+        }
+    }
+
+    /// Returns a constant expression.
+    pub fn constant(c: F) -> Self {
+        Self::new(Node::Constant(c), 0)
+    }
+
+    /// Returns the degree of this expression in the trace columns. Public inputs are treated as
+    /// constants.
+    pub const fn degree(&self) -> usize {
+        self.degree
+    }
+
+    /// Evaluates this expression on the given evaluation frame.
+    pub fn eval<FE, P, const D2: usize, E>(&self, vars: &E) -> P
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+        E: StarkEvaluationFrame<P, FE>,
+    {
+        match &*self.node {
+            Node::Constant(c) => P::from(FE::from_basefield(*c)),
+            Node::Local(i) => vars.get_local_values()[*i],
+            Node::Next(i) => vars.get_next_values()[*i],
+            Node::PublicInput(i) => P::from(vars.get_public_inputs()[*i]),
+            Node::Add(a, b) => a.eval(vars) + b.eval(vars),
+            Node::Sub(a, b) => a.eval(vars) - b.eval(vars),
+            Node::Mul(a, b) => match (&*a.node, &*b.node) {
+                (Node::Constant(c), _) => b.eval(vars) * FE::from_basefield(*c),
+                (_, Node::Constant(c)) => a.eval(vars) * FE::from_basefield(*c),
+                _ => a.eval(vars) * b.eval(vars),
+            },
+            Node::Neg(a) => -a.eval(vars),
+        }
+    }
+
+    /// Evaluates this expression on the given evaluation frame, in a circuit.
+    pub fn eval_circuit<const D: usize, E>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &E,
+    ) -> ExtensionTarget<D>
+    where
+        F: RichField + Extendable<D>,
+        E: StarkEvaluationFrame<ExtensionTarget<D>, ExtensionTarget<D>>,
+    {
+        match &*self.node {
+            Node::Constant(c) => builder.constant_extension(F::Extension::from_basefield(*c)),
+            Node::Local(i) => vars.get_local_values()[*i],
+            Node::Next(i) => vars.get_next_values()[*i],
+            Node::PublicInput(i) => vars.get_public_inputs()[*i],
+            Node::Add(a, b) => match (&*a.node, &*b.node) {
+                (Node::Constant(c), _) => {
+                    let b = b.eval_circuit(builder, vars);
+                    builder.add_const_extension(b, *c)
+                }
+                (_, Node::Constant(c)) => {
+                    let a = a.eval_circuit(builder, vars);
+                    builder.add_const_extension(a, *c)
+                }
+                _ => {
+                    let a = a.eval_circuit(builder, vars);
+                    let b = b.eval_circuit(builder, vars);
+                    builder.add_extension(a, b)
+                }
+            },
+            Node::Sub(a, b) => match &*b.node {
+                Node::Constant(c) => {
+                    let a = a.eval_circuit(builder, vars);
+                    builder.add_const_extension(a, -*c)
+                }
+                _ => {
+                    let a = a.eval_circuit(builder, vars);
+                    let b = b.eval_circuit(builder, vars);
+                    builder.sub_extension(a, b)
+                }
+            },
+            Node::Mul(a, b) => match (&*a.node, &*b.node) {
+                (Node::Constant(c), _) => {
+                    let b = b.eval_circuit(builder, vars);
+                    builder.mul_const_extension(*c, b)
+                }
+                (_, Node::Constant(c)) => {
+                    let a = a.eval_circuit(builder, vars);
+                    builder.mul_const_extension(*c, a)
+                }
+                _ => {
+                    let a = a.eval_circuit(builder, vars);
+                    let b = b.eval_circuit(builder, vars);
+                    builder.mul_extension(a, b)
+                }
+            },
+            Node::Neg(a) => {
+                let a = a.eval_circuit(builder, vars);
+                builder.mul_const_extension(F::NEG_ONE, a)
+            }
+        }
+    }
+}
+
+impl<F: Field> Display for Expr<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &*self.node {
+            Node::Constant(c) => write!(f, "{c}"),
+            Node::Local(i) => write!(f, "local[{i}]"),
+            Node::Next(i) => write!(f, "next[{i}]"),
+            Node::PublicInput(i) => write!(f, "public_inputs[{i}]"),
+            Node::Add(a, b) => write!(f, "({a} + {b})"),
+            Node::Sub(a, b) => write!(f, "({a} - {b})"),
+            Node::Mul(a, b) => write!(f, "{a} * {b}"),
+            Node::Neg(a) => write!(f, "-{a}"),
+        }
+    }
+}
+
+impl<F: Field> From<F> for Expr<F> {
+    fn from(c: F) -> Self {
+        Self::constant(c)
+    }
+}
+
+impl<F: Field> Add for Expr<F> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let degree = self.degree.max(rhs.degree);
+        Self::new(Node::Add(self, rhs), degree)
+    }
+}
+
+impl<F: Field> Sub for Expr<F> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        let degree = self.degree.max(rhs.degree);
+        Self::new(Node::Sub(self, rhs), degree)
+    }
+}
+
+impl<F: Field> Mul for Expr<F> {
+    type Output = Self;
+
+    // The degree of a product is the sum of the degrees of its factors.
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, rhs: Self) -> Self {
+        let degree = self.degree + rhs.degree;
+        Self::new(Node::Mul(self, rhs), degree)
+    }
+}
+
+impl<F: Field> Neg for Expr<F> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let degree = self.degree;
+        Self::new(Node::Neg(self), degree)
+    }
+}
+
+impl<F: Field> Add<F> for Expr<F> {
+    type Output = Self;
+
+    fn add(self, rhs: F) -> Self {
+        self + Self::constant(rhs)
+    }
+}
+
+impl<F: Field> Sub<F> for Expr<F> {
+    type Output = Self;
+
+    fn sub(self, rhs: F) -> Self {
+        self - Self::constant(rhs)
+    }
+}
+
+impl<F: Field> Mul<F> for Expr<F> {
+    type Output = Self;
+
+    fn mul(self, rhs: F) -> Self {
+        self * Self::constant(rhs)
+    }
+}
+
+impl<F: Field> Sum for Expr<F> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|acc, x| acc + x)
+            .unwrap_or_else(|| Self::constant(F::ZERO))
+    }
+}
+
+/// The rows on which a constraint is enforced.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ConstraintFilter {
+    /// Every row.
+    All,
+    /// The first row only.
+    FirstRow,
+    /// The last row only.
+    LastRow,
+    /// Every row but the last one.
+    Transition,
+}
+
+impl ConstraintFilter {
+    /// The degree added to a constraint by the selector polynomial of this filter.
+    const fn degree(self) -> usize {
+        match self {
+            Self::All => 0,
+            Self::FirstRow | Self::LastRow | Self::Transition => 1,
+        }
+    }
+}
+
+/// A builder for the constraints of a STARK, written as symbolic [`Expr`]essions.
+///
+/// Adding a constraint whose degree, once filtered, exceeds the maximal degree of the builder
+/// panics, so that degree violations are caught when the STARK is constructed rather than when
+/// proving.
+#[derive(Clone, Debug)]
+pub struct AirBuilder<F: Field> {
+    num_columns: usize,
+    num_public_inputs: usize,
+    max_degree: usize,
+    constraints: Vec<(ConstraintFilter, Expr<F>)>,
+}
+
+impl<F: Field> AirBuilder<F> {
+    /// Creates a builder for a STARK with `num_columns` columns, including its fixed columns,
+    /// and `num_public_inputs` public inputs, whose constraints are of degree at most
+    /// `max_degree`.
+    pub const fn new(num_columns: usize, num_public_inputs: usize, max_degree: usize) -> Self {
+        Self {
+            num_columns,
+            num_public_inputs,
+            max_degree,
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Returns the value of the given column in the current row.
+    pub fn local(&self, column: usize) -> Expr<F> {
+        assert!(
+            column < self.num_columns,
+            "Column {column} out of range, the STARK has {} columns",
+            self.num_columns
+        );
+        Expr::new(Node::Local(column), 1)
+    }
+
+    /// Returns the value of the given column in the next row.
+    pub fn next(&self, column: usize) -> Expr<F> {
+        assert!(
+            column < self.num_columns,
+            "Column {column} out of range, the STARK has {} columns",
+            self.num_columns
+        );
+        Expr::new(Node::Next(column), 1)
+    }
+
+    /// Returns the public input at the given index.
+    pub fn public_input(&self, index: usize) -> Expr<F> {
+        assert!(
+            index < self.num_public_inputs,
+            "Public input {index} out of range, the STARK has {} public inputs",
+            self.num_public_inputs
+        );
+        Expr::new(Node::PublicInput(index), 0)
+    }
+
+    /// Adds a constraint enforced on every row.
+    pub fn constraint(&mut self, constraint: Expr<F>) {
+        self.add_constraint(ConstraintFilter::All, constraint);
+    }
+
+    /// Adds a constraint enforced on the first row only.
+    pub fn constraint_first_row(&mut self, constraint: Expr<F>) {
+        self.add_constraint(ConstraintFilter::FirstRow, constraint);
+    }
+
+    /// Adds a constraint enforced on the last row only.
+    pub fn constraint_last_row(&mut self, constraint: Expr<F>) {
+        self.add_constraint(ConstraintFilter::LastRow, constraint);
+    }
+
+    /// Adds a constraint enforced on every row but the last one, typically relating the
+    /// current and next rows.
+    pub fn constraint_transition(&mut self, constraint: Expr<F>) {
+        self.add_constraint(ConstraintFilter::Transition, constraint);
+    }
+
+    fn add_constraint(&mut self, filter: ConstraintFilter, constraint: Expr<F>) {
+        let degree = constraint.degree() + filter.degree();
+        assert!(
+            degree <= self.max_degree,
+            "Constraint {constraint} has degree {degree} (filter included), but the maximal degree is {}",
+            self.max_degree
+        );
+        self.constraints.push((filter, constraint));
+    }
+
+    /// Returns the number of constraints added so far.
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Returns the maximal degree of the constraints added so far, filters included. This is at
+    /// most the maximal degree the builder was created with.
+    pub fn constraint_degree(&self) -> usize {
+        self.constraints
+            .iter()
+            .map(|(filter, constraint)| constraint.degree() + filter.degree())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Evaluates all constraints on the given evaluation frame, see
+    /// [`Stark::eval_packed_generic`](crate::stark::Stark::eval_packed_generic).
+    pub fn eval_packed_generic<FE, P, const D2: usize, E>(
+        &self,
+        vars: &E,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+        E: StarkEvaluationFrame<P, FE>,
+    {
+        for (filter, constraint) in &self.constraints {
+            let constraint = constraint.eval(vars);
+            match filter {
+                ConstraintFilter::All => yield_constr.constraint(constraint),
+                ConstraintFilter::FirstRow => yield_constr.constraint_first_row(constraint),
+                ConstraintFilter::LastRow => yield_constr.constraint_last_row(constraint),
+                ConstraintFilter::Transition => yield_constr.constraint_transition(constraint),
+            }
+        }
+    }
+
+    /// Evaluates all constraints on the given evaluation frame, in a circuit, see
+    /// [`Stark::eval_ext_circuit`](crate::stark::Stark::eval_ext_circuit).
+    pub fn eval_ext_circuit<const D: usize, E>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &E,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) where
+        F: RichField + Extendable<D>,
+        E: StarkEvaluationFrame<ExtensionTarget<D>, ExtensionTarget<D>>,
+    {
+        for (filter, constraint) in &self.constraints {
+            let constraint = constraint.eval_circuit(builder, vars);
+            match filter {
+                ConstraintFilter::All => yield_constr.constraint(builder, constraint),
+                ConstraintFilter::FirstRow => {
+                    yield_constr.constraint_first_row(builder, constraint)
+                }
+                ConstraintFilter::LastRow => yield_constr.constraint_last_row(builder, constraint),
+                ConstraintFilter::Transition => {
+                    yield_constr.constraint_transition(builder, constraint)
+                }
+            }
+        }
+    }
+}
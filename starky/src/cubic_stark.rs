@@ -0,0 +1,229 @@
+//! An example of a STARK whose constraints are written with an [`AirBuilder`], iterating the
+//! cubic map `x -> x^3 + k`.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use crate::air_builder::AirBuilder;
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::evaluation_frame::StarkFrame;
+use crate::stark::Stark;
+use crate::util::trace_rows_to_poly_values;
+
+/// Toy STARK system used for testing.
+/// Computes the sequence `x' <- x^3 + k` with state `[x, y]`, where `y = x^2` keeps the
+/// transition constraint of degree 3.
+#[derive(Clone)]
+pub(crate) struct CubicStark<F: RichField + Extendable<D>, const D: usize> {
+    num_rows: usize,
+    air: AirBuilder<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CubicStark<F, D> {
+    const COL_X: usize = 0;
+    const COL_Y: usize = 1;
+    // The first public input is `x0`.
+    const PI_INDEX_X0: usize = 0;
+    // The second public input is the constant `k`.
+    const PI_INDEX_K: usize = 1;
+    // The third public input is the value of `x` in the last row.
+    const PI_INDEX_RES: usize = 2;
+
+    pub(crate) fn new(num_rows: usize) -> Self {
+        let mut air = AirBuilder::new(CUBIC_COLUMNS, CUBIC_PUBLIC_INPUTS, 3);
+        let x = air.local(Self::COL_X);
+        let y = air.local(Self::COL_Y);
+        let next_x = air.next(Self::COL_X);
+        let x0 = air.public_input(Self::PI_INDEX_X0);
+        let k = air.public_input(Self::PI_INDEX_K);
+        let res = air.public_input(Self::PI_INDEX_RES);
+
+        // Check public inputs.
+        air.constraint_first_row(x.clone() - x0);
+        air.constraint_last_row(x.clone() - res);
+
+        // y = x^2
+        air.constraint(y.clone() - x.clone() * x.clone());
+        // x' <- x * y + k
+        air.constraint_transition(next_x - x * y - k);
+
+        Self { num_rows, air }
+    }
+
+    /// Generate the trace using `x0` as initial value and `k` as constant.
+    pub(crate) fn generate_trace(&self, x0: F, k: F) -> Vec<PolynomialValues<F>> {
+        let trace_rows = (0..self.num_rows)
+            .scan(x0, |x, _| {
+                let row = [*x, x.square()];
+                *x = row[0] * row[1] + k;
+                Some(row)
+            })
+            .collect::<Vec<_>>();
+        trace_rows_to_poly_values(trace_rows)
+    }
+}
+
+const CUBIC_COLUMNS: usize = 2;
+const CUBIC_PUBLIC_INPUTS: usize = 3;
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for CubicStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize>
+        = StarkFrame<P, P::Scalar, CUBIC_COLUMNS, CUBIC_PUBLIC_INPUTS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget =
+        StarkFrame<ExtensionTarget<D>, ExtensionTarget<D>, CUBIC_COLUMNS, CUBIC_PUBLIC_INPUTS>;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        self.air.eval_packed_generic(vars, yield_constr);
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        self.air.eval_ext_circuit(builder, vars, yield_constr);
+    }
+
+    fn constraint_degree(&self) -> usize {
+        self.air.constraint_degree()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::PartialWitness;
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::air_builder::{AirBuilder, Expr};
+    use crate::config::StarkConfig;
+    use crate::cubic_stark::CubicStark;
+    use crate::prover::prove;
+    use crate::recursive_verifier::{
+        add_virtual_stark_proof_with_pis, set_stark_proof_with_pis_target,
+        verify_stark_proof_circuit,
+    };
+    use crate::stark::Stark;
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+    use crate::verifier::verify_stark_proof;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = CubicStark<F, D>;
+
+    fn cubic<F: Field>(n: usize, x0: F, k: F) -> F {
+        (0..n).fold(x0, |x, _| x.cube() + k)
+    }
+
+    #[test]
+    fn test_cubic_stark() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let num_rows = 1 << 5;
+        let (x0, k) = (F::TWO, F::from_canonical_u64(7));
+        let public_inputs = [x0, k, cubic(num_rows - 1, x0, k)];
+
+        let stark = S::new(num_rows);
+        assert_eq!(stark.constraint_degree(), 3);
+        let trace = stark.generate_trace(x0, k);
+        let proof = prove::<F, C, S, D>(
+            stark.clone(),
+            &config,
+            trace,
+            &public_inputs,
+            None,
+            &mut TimingTree::default(),
+        )?;
+        verify_stark_proof(stark.clone(), proof.clone(), &config, None)?;
+
+        // The proof doesn't verify against a wrong result.
+        let mut wrong_proof = proof.clone();
+        wrong_proof.public_inputs[2] += F::ONE;
+        assert!(verify_stark_proof(stark.clone(), wrong_proof, &config, None).is_err());
+
+        let circuit_config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(circuit_config);
+        let mut pw = PartialWitness::new();
+        let degree_bits = proof.proof.recover_degree_bits(&config);
+        let pt = add_virtual_stark_proof_with_pis(&mut builder, &stark, &config, degree_bits, 0, 0);
+        set_stark_proof_with_pis_target(&mut pw, &pt, &proof, degree_bits, builder.zero())?;
+        verify_stark_proof_circuit::<F, C, S, D>(&mut builder, stark, pt, &config, None);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_cubic_stark_degree() -> Result<()> {
+        let stark = S::new(1 << 5);
+        test_stark_low_degree(stark)
+    }
+
+    #[test]
+    fn test_cubic_stark_circuit() -> Result<()> {
+        let stark = S::new(1 << 5);
+        test_stark_circuit_constraints::<F, C, S, D>(stark)
+    }
+
+    #[test]
+    fn test_air_builder_degrees() {
+        let mut air = AirBuilder::<F>::new(2, 1, 3);
+        let x = air.local(0);
+        let c = Expr::constant(F::TWO);
+        assert_eq!((x.clone() * c.clone() + air.public_input(0)).degree(), 1);
+        assert_eq!((x.clone() * x.clone() - air.next(1)).degree(), 2);
+        assert_eq!(
+            (0..3)
+                .map(|_| x.clone())
+                .reduce(|a, b| a * b)
+                .unwrap()
+                .degree(),
+            3
+        );
+
+        assert_eq!(air.constraint_degree(), 0);
+        air.constraint(x.clone() * x.clone() * x.clone());
+        // Filters add one to the degree.
+        air.constraint_transition(x.clone() * x.clone());
+        assert_eq!(air.num_constraints(), 2);
+        assert_eq!(air.constraint_degree(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "has degree 4 (filter included), but the maximal degree is 3")]
+    fn test_air_builder_degree_violation() {
+        let mut air = AirBuilder::<F>::new(1, 0, 3);
+        let x = air.local(0);
+        air.constraint_transition(air.next(0) - x.clone() * x.clone() * x);
+    }
+
+    #[test]
+    #[should_panic(expected = "Column 2 out of range")]
+    fn test_air_builder_column_out_of_range() {
+        AirBuilder::<F>::new(2, 0, 3).next(2);
+    }
+}
@@ -0,0 +1,277 @@
+//! Helpers to delegate work from a verifier, possibly a plonky2 circuit, to a single STARK.
+//!
+//! The STARK exposes the operations it proves through a [`CrossTableLookup`] whose looked table
+//! is the STARK itself (with index 0), and which has no looking table. The looking side is
+//! instead provided by the verifier, as an extra looking sum over the values of the operations
+//! it delegated. The proof is then only valid if the STARK proves exactly the same multiset of
+//! operations as the ones known to the verifier.
+//!
+//! The delegated values are observed by the challenger right after the trace cap, before the CTL
+//! challenges are drawn, so the prover and the verifier must provide them in the same order.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use anyhow::Result;
+use hashbrown::HashMap;
+use plonky2::field::extension::Extendable;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::fri::oracle::PolynomialBatch;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::challenger::{Challenger, RecursiveChallenger};
+use plonky2::iop::target::Target;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::timed;
+use plonky2::util::timing::TimingTree;
+
+use crate::config::StarkConfig;
+use crate::cross_table_lookup::{
+    cross_table_lookup_data, verify_cross_table_lookups, verify_cross_table_lookups_circuit,
+    CrossTableLookup, CtlCheckVars, CtlCheckVarsTarget,
+};
+use crate::lookup::{get_grand_product_challenge_set, get_grand_product_challenge_set_target};
+use crate::proof::{StarkProofWithPublicInputs, StarkProofWithPublicInputsTarget};
+use crate::prover::prove_with_commitment;
+use crate::recursive_verifier::{
+    add_virtual_stark_proof_with_pis, verify_stark_proof_with_challenges_circuit,
+};
+use crate::stark::Stark;
+use crate::verifier::verify_stark_proof_with_challenges;
+
+/// Proves that `trace` contains exactly the delegated operations `looking_values`, through `ctl`.
+pub fn prove_delegated<F, C, S, V, const D: usize>(
+    stark: &S,
+    config: &StarkConfig,
+    trace: Vec<PolynomialValues<F>>,
+    ctl: CrossTableLookup<F>,
+    looking_values: &[V],
+    timing: &mut TimingTree,
+) -> Result<StarkProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: Stark<F, D>,
+    V: AsRef<[F]>,
+{
+    let trace_commitment = timed!(
+        timing,
+        "compute trace commitment",
+        PolynomialBatch::<F, C, D>::from_values(
+            trace.clone(),
+            config.fri_config.rate_bits,
+            false,
+            config.fri_config.cap_height,
+            timing,
+            None,
+        )
+    );
+
+    let mut challenger = Challenger::<F, C::Hasher>::new();
+    challenger.observe_cap(&trace_commitment.merkle_tree.cap);
+    for values in looking_values {
+        challenger.observe_elements(values.as_ref());
+    }
+    let ctl_challenges = get_grand_product_challenge_set(&mut challenger, config.num_challenges);
+
+    let ctls = [ctl];
+    let [ctl_data] = cross_table_lookup_data::<F, D, 1>(
+        core::array::from_ref(&trace),
+        &ctls,
+        &ctl_challenges,
+        stark.constraint_degree(),
+    );
+
+    prove_with_commitment(
+        stark,
+        config,
+        &trace,
+        &trace_commitment,
+        None,
+        Some(&ctl_data),
+        Some(&ctl_challenges),
+        &mut challenger,
+        &[],
+        None,
+        None,
+        timing,
+    )
+}
+
+/// Verifies a proof generated by [`prove_delegated`], for exactly the delegated operations
+/// `looking_values`.
+pub fn verify_delegated<F, C, S, V, const D: usize>(
+    stark: &S,
+    proof: &StarkProofWithPublicInputs<F, C, D>,
+    ctl: CrossTableLookup<F>,
+    looking_values: &[V],
+    config: &StarkConfig,
+) -> Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: Stark<F, D>,
+    V: AsRef<[F]>,
+{
+    let mut challenger = Challenger::<F, C::Hasher>::new();
+    challenger.observe_cap(&proof.proof.trace_cap);
+    for values in looking_values {
+        challenger.observe_elements(values.as_ref());
+    }
+    let ctl_challenges = get_grand_product_challenge_set(&mut challenger, config.num_challenges);
+    let challenges =
+        proof
+            .proof
+            .get_challenges(&mut challenger, Some(&ctl_challenges), true, config, None);
+
+    let ctls = [ctl];
+    let (num_ctl_helpers, _, num_helpers_by_ctl) = CrossTableLookup::num_ctl_helpers_zs_all(
+        &ctls,
+        0,
+        config.num_challenges,
+        stark.constraint_degree(),
+    );
+    let ctl_vars = CtlCheckVars::from_proof(
+        0,
+        &proof.proof,
+        &ctls,
+        &ctl_challenges,
+        stark.num_lookup_helper_columns(config),
+        num_ctl_helpers,
+        &num_helpers_by_ctl,
+    );
+    verify_stark_proof_with_challenges(
+        stark,
+        &proof.proof,
+        &challenges,
+        Some(&ctl_vars),
+        None,
+        &[],
+        config,
+    )?;
+
+    let extra_looking_sums = ctl_challenges
+        .challenges
+        .iter()
+        .map(|challenge| {
+            looking_values
+                .iter()
+                .map(|values| challenge.combine::<F, F, _, 1>(values.as_ref()).inverse())
+                .sum()
+        })
+        .collect();
+    let ctl_zs_first = proof
+        .proof
+        .openings
+        .ctl_zs_first
+        .clone()
+        .expect("CTL openings are required");
+    verify_cross_table_lookups::<F, D, 1>(
+        &ctls,
+        [ctl_zs_first],
+        &HashMap::from([(0, extra_looking_sums)]),
+        config,
+    )
+}
+
+/// Circuit version of [`verify_delegated`]. Adds a proof target for a trace of `2^degree_bits`
+/// rows, and returns it so that it can be set with the output of [`prove_delegated`].
+pub fn verify_delegated_circuit<F, C, S, V, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    stark: &S,
+    ctl: CrossTableLookup<F>,
+    looking_values: &[V],
+    degree_bits: usize,
+    config: &StarkConfig,
+) -> StarkProofWithPublicInputsTarget<D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+    S: Stark<F, D>,
+    V: AsRef<[Target]>,
+{
+    let ctls = [ctl];
+    let (num_ctl_helpers, num_ctl_zs, num_helpers_by_ctl) =
+        CrossTableLookup::num_ctl_helpers_zs_all(
+            &ctls,
+            0,
+            config.num_challenges,
+            stark.constraint_degree(),
+        );
+    let proof_with_pis = add_virtual_stark_proof_with_pis(
+        builder,
+        stark,
+        config,
+        degree_bits,
+        num_ctl_helpers + num_ctl_zs,
+        num_ctl_zs,
+    );
+    let proof = &proof_with_pis.proof;
+
+    let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(builder);
+    challenger.observe_cap(&proof.trace_cap);
+    for values in looking_values {
+        challenger.observe_elements(values.as_ref());
+    }
+    let ctl_challenges =
+        get_grand_product_challenge_set_target(builder, &mut challenger, config.num_challenges);
+    let challenges = proof_with_pis.get_challenges::<F, C>(
+        builder,
+        &mut challenger,
+        Some(&ctl_challenges),
+        true,
+        config,
+    );
+
+    let ctl_vars = CtlCheckVarsTarget::from_proof(
+        0,
+        proof,
+        &ctls,
+        &ctl_challenges,
+        stark.num_lookup_helper_columns(config),
+        num_ctl_helpers,
+        &num_helpers_by_ctl,
+    );
+    verify_stark_proof_with_challenges_circuit::<F, C, S, D>(
+        builder,
+        stark,
+        proof,
+        &[],
+        challenges,
+        Some(&ctl_vars),
+        None,
+        config,
+        degree_bits,
+        None,
+    );
+
+    let extra_looking_sums = ctl_challenges
+        .challenges
+        .iter()
+        .map(|challenge| {
+            let terms = looking_values
+                .iter()
+                .map(|values| {
+                    let combined = challenge.combine_base_circuit(builder, values.as_ref());
+                    builder.inverse(combined)
+                })
+                .collect::<Vec<_>>();
+            builder.add_many(terms)
+        })
+        .collect();
+    let ctl_zs_first = proof
+        .openings
+        .ctl_zs_first
+        .clone()
+        .expect("CTL openings are required");
+    verify_cross_table_lookups_circuit::<F, D, 1>(
+        builder,
+        ctls.into(),
+        [ctl_zs_first],
+        &HashMap::from([(0, extra_looking_sums)]),
+        config,
+    );
+
+    proof_with_pis
+}
@@ -0,0 +1,2263 @@
+//! A STARK verifying batches of secp256k1 ECDSA signatures, to which plonky2 circuits can
+//! delegate signature verification.
+//!
+//! Verifying a single signature with plonky2 gates requires a huge amount of non-native
+//! arithmetic. The [`EcdsaStark`] instead verifies a whole batch of signatures, which a plonky2
+//! circuit links to through a cross-table lookup on the tuple `(z, r, s, Q.x, Q.y, valid)`, where
+//! `z` is the message hash, `(r, s)` the signature, `Q` the public key, and `valid` is 1 if the
+//! signature is valid and 0 otherwise. 256-bit values are given as 8 little-endian 32-bit limbs.
+//! Invalid signatures are proven as such, so a circuit can act on the validity flag, e.g. to count
+//! the valid signatures of a multisig.
+//!
+//! # Delegating signature verification from a plonky2 circuit
+//!
+//! This follows the pattern of the [`delegation`](crate::delegation) module:
+//!
+//! 1. While building the circuit, delegate verifications with
+//!    [`EcdsaDelegationTarget::verify_signature`]. The returned flags are unconstrained until
+//!    step 2.
+//! 2. Call [`EcdsaDelegationTarget::verify`], which adds a STARK proof target, verifies it
+//!    recursively and connects the delegated verifications to the STARK's CTL.
+//! 3. When proving, prove the same verifications, in the same order, with
+//!    [`prove_ecdsa_verifications`], and set the returned proof in the witness with
+//!    [`set_stark_proof_with_pis_target`](crate::recursive_verifier::set_stark_proof_with_pis_target).
+//!
+//! The witness generator used by [`EcdsaDelegationTarget`] is not part of plonky2's default
+//! generator serializer, so circuits using it need a custom one to be serialized.
+//!
+//! # Validity
+//!
+//! A signature `(r, s)` of the message hash `z` under the public key `Q` is valid if and only if:
+//! - `1 <= r < n` and `1 <= s < n`, where `n` is the order of the curve,
+//! - `Q` is on the curve, its coordinates being taken modulo `p`, the order of the base field,
+//! - `u1 * G + u2 * Q` is not the point at infinity, where `u1 = z / s` and `u2 = r / s` modulo
+//!   `n`,
+//! - the x coordinate of `u1 * G + u2 * Q` is equal to `r` modulo `n`.
+//!
+//! This is computed natively by [`EcdsaVerification::is_valid`].
+//!
+//! # Trace layout
+//!
+//! Each verification uses 262 consecutive rows: 4 setup rows, 256 ladder rows and 2 final rows,
+//! marked by row flags. Values modulo `p` or `n` are stored as 16 little-endian 16-bit limbs,
+//! either directly or as pairs of bytes when they need to be range-checked, which is done with a
+//! logUp lookup against a `0..256` table.
+//!
+//! Non-native arithmetic is done in 6 identity slots per row. Each slot holds a value `v`, a
+//! quotient `q` and carries `c`, and proves that a degree 2 expression `E` of `v` and of other
+//! values of the row is 0 modulo `m`, which is `p`, `n`, or no modulus at all. Seeing every value
+//! as the polynomial in `x` whose coefficients are its limbs, the slot checks the polynomial
+//! identity `E(x) - (q(x) - 2^260) m(x) = (x - 2^16) c(x)`. All coefficients are small enough
+//! for it to hold over the integers, so evaluating it at `2^16` shows that `E = 0 mod m`. The
+//! expression of each slot depends on the row type.
+//!
+//! The setup rows check the ranges of `r` and `s`, compute `u1` and `u2`, check that `Q` is on
+//! the curve, and compute `Q + H` and `G + Q + H`, where `H` is a fixed point with no known
+//! discrete logarithm: its x coordinate is the SHA-256 hash of `"plonky2 ECDSA STARK offset
+//! point"`. Each ladder row then processes one bit of both `u1` and `u2`, from the most
+//! significant one, by doubling the accumulator and adding one of `H`, `G + H`, `Q + H` or
+//! `G + Q + H` with a single double-and-add formula. Starting from `2H`, the accumulator ends up
+//! at `u1 * G + u2 * Q + (3 * 2^256 - 1) * H`, from which the final rows remove the offset and
+//! compare the x coordinate with `r`.
+//!
+//! The affine addition formulas don't hold for points with the same x coordinate, and would leave
+//! the slope unconstrained when adding a point to itself. The additions computing `Q + H`,
+//! `G + Q + H` and the sum of the accumulator and the addend of each ladder row thus also prove
+//! that the difference of the x coordinates is invertible. The doubling of each ladder row needs
+//! no such check, as it could only fail at a point of order 2, which secp256k1 doesn't have.
+//! Thanks to the offset, an honest key only hits an exceptional case given a discrete logarithm
+//! relation between `G`, `Q` and `H`, which is infeasible to find. A key chosen to hit one, such
+//! as `Q = H`, can't have any of its verifications proven. The only exception that can actually
+//! be reached, when `u1 * G + u2 * Q` is the point at infinity, is handled explicitly.
+//!
+//! The trace is padded with zero rows, which satisfy all the constraints.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use anyhow::Result;
+use num_bigint::{BigInt, BigUint};
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::secp256k1_base::Secp256K1Base;
+use plonky2::field::secp256k1_scalar::Secp256K1Scalar;
+use plonky2::field::types::{Field, PrimeField};
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::util::log2_strict;
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+use plonky2::util::timing::TimingTree;
+
+use crate::config::StarkConfig;
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::cross_table_lookup::{CrossTableLookup, TableWithColumns};
+use crate::delegation::{prove_delegated, verify_delegated, verify_delegated_circuit};
+use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+use crate::lookup::{Column, Filter, Lookup};
+use crate::proof::{StarkProofWithPublicInputs, StarkProofWithPublicInputsTarget};
+use crate::stark::Stark;
+use crate::util::trace_rows_to_poly_values;
+
+/// The generator of the secp256k1 group.
+const G_X: [u64; 4] = [
+    0x59F2815B16F81798,
+    0x029BFCDB2DCE28D9,
+    0x55A06295CE870B07,
+    0x79BE667EF9DCBBAC,
+];
+const G_Y: [u64; 4] = [
+    0x9C47D08FFB10D4B8,
+    0xFD17B448A6855419,
+    0x5DA4FBFC0E1108A8,
+    0x483ADA7726A3C465,
+];
+/// The offset point `H`, whose x coordinate is the SHA-256 hash of
+/// `"plonky2 ECDSA STARK offset point"`, and whose y coordinate is even.
+const H_X: [u64; 4] = [
+    0x77CF14FD0B3D88E1,
+    0xF3554DB9BC5162CD,
+    0x381DA3DED4677B47,
+    0xDFC0B3EDD7465F0A,
+];
+const H_Y: [u64; 4] = [
+    0x36BDFC91E5D683D2,
+    0x7CDA351509C2C9EF,
+    0x81E86D7F69E41833,
+    0xD95DA04BAD345F74,
+];
+/// `2 * H`, the initial value of the accumulator.
+const H2_X: [u64; 4] = [
+    0x5961DC1AEC761BC7,
+    0x8EF3F6E0CF7FF14E,
+    0x709B6A942983547D,
+    0x0F6BDA054E0EFB3D,
+];
+const H2_Y: [u64; 4] = [
+    0xAE13E5EBFE921DD6,
+    0x7F7FB93AE222FB08,
+    0x08E7392EFA444A62,
+    0x2BE371F3A58EA303,
+];
+/// `G + H`.
+const GH_X: [u64; 4] = [
+    0x40639E9FC0C2F21E,
+    0x2443BA65BC93707D,
+    0xD6AF6A75E2DB7F2E,
+    0x32F222BBF7DDDBC3,
+];
+const GH_Y: [u64; 4] = [
+    0x3357408962A444E3,
+    0xA7E007C4E56015C3,
+    0x99C2AA0F3F57EDC3,
+    0xCD855F85A4AB4938,
+];
+/// `-(3 * 2^256 - 1) * H`, which removes the offset from the final accumulator.
+const NEG_OFFSET_X: [u64; 4] = [
+    0xFBC36FCB735AEE07,
+    0x44163002EB6791CF,
+    0xBF6C2E0D07A485B8,
+    0x9143CFE93D9A7847,
+];
+const NEG_OFFSET_Y: [u64; 4] = [
+    0xD86EDA56B84EBFAD,
+    0xCE70C57B7BE0E427,
+    0x67D0699F8A5FC21D,
+    0x355C0765DC2E4BA5,
+];
+const P_MINUS_ONE: [u64; 4] = [
+    0xFFFFFFFEFFFFFC2E,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+    0xFFFFFFFFFFFFFFFF,
+];
+const N_MINUS_ONE: [u64; 4] = [
+    0xBFD25E8CD0364140,
+    0xBAAEDCE6AF48A03B,
+    0xFFFFFFFFFFFFFFFE,
+    0xFFFFFFFFFFFFFFFF,
+];
+
+const NUM_LIMBS: usize = 16;
+const LIMB_BITS: usize = 16;
+const NUM_VALUE_BYTES: usize = 2 * NUM_LIMBS;
+const NUM_QUOTIENT_LIMBS: usize = NUM_LIMBS + 1;
+/// The number of coefficients of the polynomial identity proven by a slot.
+const NUM_COEFFS: usize = NUM_QUOTIENT_LIMBS + NUM_LIMBS - 1;
+const NUM_CARRIES: usize = NUM_COEFFS - 1;
+const CARRY_BYTES: usize = 3;
+/// Carries are signed, and stored with this offset.
+const CARRY_OFFSET: i64 = 1 << 23;
+/// Quotients may be negative, and are stored with an offset of `2^260 = 16 * (2^16)^16`.
+const QUOTIENT_OFFSET: u64 = 16;
+
+const NUM_SLOTS: usize = 6;
+const SLOT_WIDTH: usize = NUM_VALUE_BYTES + 2 * NUM_QUOTIENT_LIMBS + CARRY_BYTES * NUM_CARRIES;
+
+const NUM_SETUP_ROWS: usize = 4;
+const NUM_LADDER_ROWS: usize = 256;
+const ROWS_PER_VERIFICATION: usize = NUM_SETUP_ROWS + NUM_LADDER_ROWS + 2;
+
+/// Flag set on the setup row checking `r` and `s` and computing `u1` and `u2`.
+const SETUP_SCALARS: usize = 0;
+/// Flag set on the setup row checking that `Q` is on the curve.
+const SETUP_KEY: usize = 1;
+/// Flag set on the setup row computing `Q + H`.
+const SETUP_QH: usize = 2;
+/// Flag set on the setup row computing `G + Q + H`.
+const SETUP_GQH: usize = 3;
+/// Flag set on the final row removing the offset from the accumulator.
+const FINAL_SUM: usize = 4;
+/// Flag set on the final row comparing the x coordinate of `u1 * G + u2 * Q` with `r`.
+const FINAL_CHECK: usize = 5;
+/// One-hot flags marking the position, within its limb, of the bit processed by a ladder row.
+const BIT_FLAGS: Range<usize> = 6..6 + LIMB_BITS;
+/// One-hot flags marking the limb of the bits processed by a ladder row.
+const LIMB_FLAGS: Range<usize> = BIT_FLAGS.end..BIT_FLAGS.end + NUM_LIMBS;
+
+/// The bytes of the inputs `z`, `r`, `s`, `Q.x` and `Q.y`, constant within a verification.
+const INPUTS: Range<usize> = LIMB_FLAGS.end..LIMB_FLAGS.end + 5 * NUM_VALUE_BYTES;
+const MSG_HASH: usize = INPUTS.start;
+const SIG_R: usize = MSG_HASH + NUM_VALUE_BYTES;
+const SIG_S: usize = SIG_R + NUM_VALUE_BYTES;
+const KEY_X: usize = SIG_S + NUM_VALUE_BYTES;
+const KEY_Y: usize = KEY_X + NUM_VALUE_BYTES;
+/// The limbs of `u1` and `u2`, constant within a verification.
+const U1: usize = INPUTS.end;
+const U2: usize = U1 + NUM_LIMBS;
+/// The limbs of `Q + H` and `G + Q + H`, constant within a verification.
+const QH_X: usize = U2 + NUM_LIMBS;
+const QH_Y: usize = QH_X + NUM_LIMBS;
+const GQH_X: usize = QH_Y + NUM_LIMBS;
+const GQH_Y: usize = GQH_X + NUM_LIMBS;
+/// The limbs of the x coordinate of `u1 * G + u2 * Q`, constant within a verification.
+const SUM_X: usize = GQH_Y + NUM_LIMBS;
+/// Whether `1 <= r < n`, constant within a verification.
+const R_OK: usize = SUM_X + NUM_LIMBS;
+/// Whether `1 <= s < n`, constant within a verification.
+const S_OK: usize = R_OK + 1;
+/// Whether `Q` is on the curve, constant within a verification.
+const KEY_OK: usize = S_OK + 1;
+/// Whether `u1 * G + u2 * Q` is not the point at infinity, constant within a verification.
+const SUM_NONZERO: usize = KEY_OK + 1;
+/// All the columns which are constant within a verification.
+const VERIFICATION_CONSTANTS: Range<usize> = INPUTS.start..SUM_NONZERO + 1;
+
+/// Whether `r >= n`, on the [`SETUP_SCALARS`] row.
+const R_BORROW: usize = VERIFICATION_CONSTANTS.end;
+/// Whether `s >= n`, on the [`SETUP_SCALARS`] row.
+const S_BORROW: usize = R_BORROW + 1;
+/// The inverse of the sum of the bytes of `r`, if nonzero, on the [`SETUP_SCALARS`] row.
+const R_BYTE_SUM_INV: usize = S_BORROW + 1;
+/// The inverse of the sum of the bytes of `s`, if nonzero, on the [`SETUP_SCALARS`] row.
+const S_BYTE_SUM_INV: usize = R_BYTE_SUM_INV + 1;
+/// Whether `r != 0`, on the [`SETUP_SCALARS`] row.
+const R_NONZERO: usize = S_BYTE_SUM_INV + 1;
+/// Whether `s != 0`, on the [`SETUP_SCALARS`] row.
+const S_NONZERO: usize = R_NONZERO + 1;
+/// The result of the zero test done by the [`SETUP_KEY`], [`FINAL_SUM`] and [`FINAL_CHECK`]
+/// rows.
+const IS_NONZERO: usize = S_NONZERO + 1;
+/// The limbs of the point added on the [`SETUP_QH`] and ladder rows.
+const ADDEND_X: usize = IS_NONZERO + 1;
+const ADDEND_Y: usize = ADDEND_X + NUM_LIMBS;
+/// The limbs of the accumulator at the start of the ladder and [`FINAL_SUM`] rows.
+const ACC_X: usize = ADDEND_Y + NUM_LIMBS;
+const ACC_Y: usize = ACC_X + NUM_LIMBS;
+/// The bits of `u1` and `u2` processed by a ladder row.
+const U1_BIT: usize = ACC_Y + NUM_LIMBS;
+const U2_BIT: usize = U1_BIT + 1;
+/// `U1_BIT * U2_BIT`.
+const BOTH_BITS: usize = U2_BIT + 1;
+/// The bits of the current limbs of `u1` and `u2` processed so far, including this row's.
+const U1_LIMB_ACC: usize = BOTH_BITS + 1;
+const U2_LIMB_ACC: usize = U1_LIMB_ACC + 1;
+/// Partial products of the validity conditions, on the [`FINAL_CHECK`] row.
+const OK_RS: usize = U2_LIMB_ACC + 1;
+const OK_RS_KEY: usize = OK_RS + 1;
+const OK_ALL: usize = OK_RS_KEY + 1;
+/// The validity flag, on the [`FINAL_CHECK`] row.
+const VALID: usize = OK_ALL + 1;
+/// The identity slots.
+const SLOTS: usize = VALID + 1;
+/// The `0..256` table that all bytes are looked up in.
+const RANGE_COUNTER: usize = SLOTS + NUM_SLOTS * SLOT_WIDTH;
+/// Number of times each value of `RANGE_COUNTER` is looked up.
+const RANGE_FREQUENCIES: usize = RANGE_COUNTER + 1;
+
+const ECDSA_COLUMNS: usize = RANGE_FREQUENCIES + 1;
+const ECDSA_PUBLIC_INPUTS: usize = 0;
+
+const RANGE_MAX: usize = u8::MAX as usize;
+
+/// The number of row types, each of which proves different identities in its slots.
+const NUM_ROW_TYPES: usize = 7;
+/// The index of the ladder rows among the row types, the other ones being their flag columns.
+const LADDER: usize = 6;
+
+const fn slot_value(slot: usize) -> usize {
+    SLOTS + slot * SLOT_WIDTH
+}
+
+const fn slot_quotient(slot: usize) -> usize {
+    slot_value(slot) + NUM_VALUE_BYTES
+}
+
+const fn slot_carries(slot: usize) -> usize {
+    slot_quotient(slot) + 2 * NUM_QUOTIENT_LIMBS
+}
+
+const fn to_limbs(value: [u64; 4]) -> [u16; NUM_LIMBS] {
+    let mut limbs = [0; NUM_LIMBS];
+    let mut i = 0;
+    while i < NUM_LIMBS {
+        limbs[i] = (value[i / 4] >> (LIMB_BITS * (i % 4))) as u16;
+        i += 1;
+    }
+    limbs
+}
+
+/// A 256-bit operand of an identity, seen as a polynomial whose evaluation at `2^16` is its value.
+#[derive(Copy, Clone, Debug)]
+enum Operand {
+    /// A value stored as 32 little-endian byte columns.
+    Bytes(usize),
+    /// A value stored as 16 little-endian 16-bit limb columns.
+    Limbs(usize),
+    /// A single boolean column.
+    Column(usize),
+    /// A single boolean column, scaled by `2^256`.
+    Overflow(usize),
+    /// A constant.
+    Constant([u64; 4]),
+    /// The constant 1.
+    One,
+}
+
+use Operand::*;
+
+/// The value of a slot of the current row.
+const fn value(slot: usize) -> Operand {
+    Bytes(slot_value(slot))
+}
+
+/// A term `coeff * a * b` of an identity.
+#[derive(Copy, Clone, Debug)]
+struct Term {
+    coeff: i64,
+    a: Operand,
+    b: Operand,
+}
+
+impl Term {
+    const fn product(coeff: i64, a: Operand, b: Operand) -> Self {
+        Self { coeff, a, b }
+    }
+
+    const fn linear(coeff: i64, a: Operand) -> Self {
+        Self { coeff, a, b: One }
+    }
+}
+
+/// The modulus of an identity.
+#[derive(Copy, Clone, Debug)]
+enum Modulus {
+    /// The order `p` of the secp256k1 base field.
+    Base,
+    /// The order `n` of the secp256k1 group.
+    Scalar,
+    /// The identity holds over the integers.
+    Exact,
+}
+
+impl Modulus {
+    fn limbs(self) -> Option<[u16; NUM_LIMBS]> {
+        match self {
+            Self::Base => Some(P_MINUS_ONE),
+            Self::Scalar => Some(N_MINUS_ONE),
+            Self::Exact => None,
+        }
+        .map(|minus_one| {
+            // The low limbs of p - 1 and n - 1 are not 0xFFFF, so this doesn't carry.
+            let mut limbs = to_limbs(minus_one);
+            limbs[0] += 1;
+            limbs
+        })
+    }
+
+    fn value(self) -> Option<BigUint> {
+        match self {
+            Self::Base => Some(Secp256K1Base::order()),
+            Self::Scalar => Some(Secp256K1Scalar::order()),
+            Self::Exact => None,
+        }
+    }
+}
+
+/// An identity `sum(terms) = 0 mod modulus`, proven by a slot.
+#[derive(Copy, Clone, Debug)]
+struct Identity {
+    modulus: Modulus,
+    terms: &'static [Term],
+}
+
+/// Checks the ranges of `r` and `s`, and computes `w = 1 / s`, `u1 = z * w` and `u2 = r * w`.
+/// If `s` is out of range, `s + 1` is inverted instead, which is never 0 modulo `n`.
+const SETUP_SCALARS_IDENTITIES: [Identity; 5] = [
+    // n - 1 - r + 2^256 * borrow = e, with e >= 0.
+    Identity {
+        modulus: Modulus::Exact,
+        terms: &[
+            Term::linear(1, Constant(N_MINUS_ONE)),
+            Term::linear(-1, Bytes(SIG_R)),
+            Term::linear(1, Overflow(R_BORROW)),
+            Term::linear(-1, value(0)),
+        ],
+    },
+    // n - 1 - s + 2^256 * borrow = e, with e >= 0.
+    Identity {
+        modulus: Modulus::Exact,
+        terms: &[
+            Term::linear(1, Constant(N_MINUS_ONE)),
+            Term::linear(-1, Bytes(SIG_S)),
+            Term::linear(1, Overflow(S_BORROW)),
+            Term::linear(-1, value(1)),
+        ],
+    },
+    // (s + 1 - s_ok) * w = 1.
+    Identity {
+        modulus: Modulus::Scalar,
+        terms: &[
+            Term::product(1, Bytes(SIG_S), value(2)),
+            Term::linear(1, value(2)),
+            Term::product(-1, Column(S_OK), value(2)),
+            Term::linear(-1, One),
+        ],
+    },
+    // u1 = z * w.
+    Identity {
+        modulus: Modulus::Scalar,
+        terms: &[
+            Term::product(1, Bytes(MSG_HASH), value(2)),
+            Term::linear(-1, value(3)),
+        ],
+    },
+    // u2 = r * w.
+    Identity {
+        modulus: Modulus::Scalar,
+        terms: &[
+            Term::product(1, Bytes(SIG_R), value(2)),
+            Term::linear(-1, value(4)),
+        ],
+    },
+];
+
+/// Computes `d = Q.y^2 - Q.x^3 - 7`, and tests whether it is zero.
+const SETUP_KEY_IDENTITIES: [Identity; 4] = [
+    // Q.x^2.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, Bytes(KEY_X), Bytes(KEY_X)),
+            Term::linear(-1, value(0)),
+        ],
+    },
+    // d = Q.y^2 - Q.x^2 * Q.x - 7.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, Bytes(KEY_Y), Bytes(KEY_Y)),
+            Term::product(-1, value(0), Bytes(KEY_X)),
+            Term::linear(-7, One),
+            Term::linear(-1, value(1)),
+        ],
+    },
+    // d * inv = is_nonzero.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(1), value(2)),
+            Term::linear(-1, Column(IS_NONZERO)),
+        ],
+    },
+    // (1 - is_nonzero) * d = 0.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::linear(1, value(1)),
+            Term::product(-1, Column(IS_NONZERO), value(1)),
+        ],
+    },
+];
+
+/// Computes `Q + H`, where `Q` is replaced by `G` if it is not on the curve.
+const SETUP_QH_IDENTITIES: [Identity; 4] = [
+    // lambda * (H.x - Q.x) = H.y - Q.y.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(0), Constant(H_X)),
+            Term::product(-1, value(0), Limbs(ADDEND_X)),
+            Term::linear(-1, Constant(H_Y)),
+            Term::linear(1, Limbs(ADDEND_Y)),
+        ],
+    },
+    // x = lambda^2 - Q.x - H.x.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(0), value(0)),
+            Term::linear(-1, Limbs(ADDEND_X)),
+            Term::linear(-1, Constant(H_X)),
+            Term::linear(-1, value(1)),
+        ],
+    },
+    // y = lambda * (Q.x - x) - Q.y.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(0), Limbs(ADDEND_X)),
+            Term::product(-1, value(0), value(1)),
+            Term::linear(-1, Limbs(ADDEND_Y)),
+            Term::linear(-1, value(2)),
+        ],
+    },
+    // inv * (H.x - Q.x) = 1.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(3), Constant(H_X)),
+            Term::product(-1, value(3), Limbs(ADDEND_X)),
+            Term::linear(-1, One),
+        ],
+    },
+];
+
+/// Computes `(Q + H) + G`.
+const SETUP_GQH_IDENTITIES: [Identity; 4] = [
+    // lambda * (G.x - QH.x) = G.y - QH.y.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(0), Constant(G_X)),
+            Term::product(-1, value(0), Limbs(QH_X)),
+            Term::linear(-1, Constant(G_Y)),
+            Term::linear(1, Limbs(QH_Y)),
+        ],
+    },
+    // x = lambda^2 - QH.x - G.x.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(0), value(0)),
+            Term::linear(-1, Limbs(QH_X)),
+            Term::linear(-1, Constant(G_X)),
+            Term::linear(-1, value(1)),
+        ],
+    },
+    // y = lambda * (QH.x - x) - QH.y.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(0), Limbs(QH_X)),
+            Term::product(-1, value(0), value(1)),
+            Term::linear(-1, Limbs(QH_Y)),
+            Term::linear(-1, value(2)),
+        ],
+    },
+    // inv * (G.x - QH.x) = 1.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(3), Constant(G_X)),
+            Term::product(-1, value(3), Limbs(QH_X)),
+            Term::linear(-1, One),
+        ],
+    },
+];
+
+/// Computes `2 * A + S = (A + S) + A`, where `A` is the accumulator and `S` the addend, sharing
+/// the y coordinate of `A + S` between both additions.
+const LADDER_IDENTITIES: [Identity; 6] = [
+    // lambda1 * (S.x - A.x) = S.y - A.y.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(0), Limbs(ADDEND_X)),
+            Term::product(-1, value(0), Limbs(ACC_X)),
+            Term::linear(-1, Limbs(ADDEND_Y)),
+            Term::linear(1, Limbs(ACC_Y)),
+        ],
+    },
+    // x3 = lambda1^2 - A.x - S.x.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(0), value(0)),
+            Term::linear(-1, Limbs(ACC_X)),
+            Term::linear(-1, Limbs(ADDEND_X)),
+            Term::linear(-1, value(1)),
+        ],
+    },
+    // (lambda2 + lambda1) * (x3 - A.x) + 2 * A.y = 0.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(2), value(1)),
+            Term::product(-1, value(2), Limbs(ACC_X)),
+            Term::product(1, value(0), value(1)),
+            Term::product(-1, value(0), Limbs(ACC_X)),
+            Term::linear(2, Limbs(ACC_Y)),
+        ],
+    },
+    // x4 = lambda2^2 - A.x - x3.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(2), value(2)),
+            Term::linear(-1, Limbs(ACC_X)),
+            Term::linear(-1, value(1)),
+            Term::linear(-1, value(3)),
+        ],
+    },
+    // y4 = lambda2 * (A.x - x4) - A.y.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(2), Limbs(ACC_X)),
+            Term::product(-1, value(2), value(3)),
+            Term::linear(-1, Limbs(ACC_Y)),
+            Term::linear(-1, value(4)),
+        ],
+    },
+    // inv * (S.x - A.x) = 1.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(5), Limbs(ADDEND_X)),
+            Term::product(-1, value(5), Limbs(ACC_X)),
+            Term::linear(-1, One),
+        ],
+    },
+];
+
+/// Computes the x coordinate of `A - (3 * 2^256 - 1) * H`, where `A` is the final accumulator,
+/// unless both points have the same x coordinate.
+const FINAL_SUM_IDENTITIES: [Identity; 4] = [
+    // (O.x - A.x) * inv = is_nonzero.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, Constant(NEG_OFFSET_X), value(0)),
+            Term::product(-1, Limbs(ACC_X), value(0)),
+            Term::linear(-1, Column(IS_NONZERO)),
+        ],
+    },
+    // (1 - is_nonzero) * (O.x - A.x) = 0.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::linear(1, Constant(NEG_OFFSET_X)),
+            Term::linear(-1, Limbs(ACC_X)),
+            Term::product(-1, Column(IS_NONZERO), Constant(NEG_OFFSET_X)),
+            Term::product(1, Column(IS_NONZERO), Limbs(ACC_X)),
+        ],
+    },
+    // lambda * (O.x - A.x) = is_nonzero * (O.y - A.y).
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(2), Constant(NEG_OFFSET_X)),
+            Term::product(-1, value(2), Limbs(ACC_X)),
+            Term::product(-1, Column(IS_NONZERO), Constant(NEG_OFFSET_Y)),
+            Term::product(1, Column(IS_NONZERO), Limbs(ACC_Y)),
+        ],
+    },
+    // x = lambda^2 - A.x - O.x.
+    Identity {
+        modulus: Modulus::Base,
+        terms: &[
+            Term::product(1, value(2), value(2)),
+            Term::linear(-1, Limbs(ACC_X)),
+            Term::linear(-1, Constant(NEG_OFFSET_X)),
+            Term::linear(-1, value(3)),
+        ],
+    },
+];
+
+/// Checks that the x coordinate of `u1 * G + u2 * Q` is canonical, and tests whether it is
+/// equal to `r` modulo `n`.
+const FINAL_CHECK_IDENTITIES: [Identity; 3] = [
+    // p - 1 - x = e, with e >= 0.
+    Identity {
+        modulus: Modulus::Exact,
+        terms: &[
+            Term::linear(1, Constant(P_MINUS_ONE)),
+            Term::linear(-1, Limbs(SUM_X)),
+            Term::linear(-1, value(0)),
+        ],
+    },
+    // (x - r) * inv = is_nonzero.
+    Identity {
+        modulus: Modulus::Scalar,
+        terms: &[
+            Term::product(1, Limbs(SUM_X), value(1)),
+            Term::product(-1, Bytes(SIG_R), value(1)),
+            Term::linear(-1, Column(IS_NONZERO)),
+        ],
+    },
+    // (1 - is_nonzero) * (x - r) = 0.
+    Identity {
+        modulus: Modulus::Scalar,
+        terms: &[
+            Term::linear(1, Limbs(SUM_X)),
+            Term::linear(-1, Bytes(SIG_R)),
+            Term::product(-1, Column(IS_NONZERO), Limbs(SUM_X)),
+            Term::product(1, Column(IS_NONZERO), Bytes(SIG_R)),
+        ],
+    },
+];
+
+/// The identities proven by each row type, indexed like the row flags, with the ladder rows
+/// last.
+const IDENTITIES: [&[Identity]; NUM_ROW_TYPES] = [
+    &SETUP_SCALARS_IDENTITIES,
+    &SETUP_KEY_IDENTITIES,
+    &SETUP_QH_IDENTITIES,
+    &SETUP_GQH_IDENTITIES,
+    &FINAL_SUM_IDENTITIES,
+    &FINAL_CHECK_IDENTITIES,
+    &LADDER_IDENTITIES,
+];
+
+type AffinePoint = (Secp256K1Base, Secp256K1Base);
+
+const G: AffinePoint = (Secp256K1Base(G_X), Secp256K1Base(G_Y));
+const H: AffinePoint = (Secp256K1Base(H_X), Secp256K1Base(H_Y));
+
+/// Adds two points, either of which may be the point at infinity, represented by `None`.
+fn point_add(a: Option<AffinePoint>, b: Option<AffinePoint>) -> Option<AffinePoint> {
+    let (Some((x1, y1)), Some((x2, y2))) = (a, b) else {
+        return a.or(b);
+    };
+    let lambda = if x1 == x2 {
+        if y1 + y2 == Secp256K1Base::ZERO {
+            return None;
+        }
+        x1 * x1 * Secp256K1Base::from_canonical_u8(3) / (y1 + y1)
+    } else {
+        (y2 - y1) / (x2 - x1)
+    };
+    let x3 = lambda * lambda - x1 - x2;
+    Some((x3, lambda * (x1 - x3) - y1))
+}
+
+fn scalar_mul(k: &BigUint, point: AffinePoint) -> Option<AffinePoint> {
+    (0..k.bits()).rev().fold(None, |acc, i| {
+        let acc = point_add(acc, acc);
+        if k.bit(i) {
+            point_add(acc, Some(point))
+        } else {
+            acc
+        }
+    })
+}
+
+fn is_on_curve((x, y): AffinePoint) -> bool {
+    y * y == x * x * x + Secp256K1Base::from_canonical_u8(7)
+}
+
+fn biguint_from_limbs(limbs: &[u32; 8]) -> BigUint {
+    BigUint::from_slice(limbs)
+}
+
+fn base_from_limbs(limbs: &[u32; 8]) -> Secp256K1Base {
+    Secp256K1Base::from_noncanonical_biguint(biguint_from_limbs(limbs))
+}
+
+fn scalar_from_biguint(value: &BigUint) -> Secp256K1Scalar {
+    Secp256K1Scalar::from_noncanonical_biguint(value.clone())
+}
+
+/// A single secp256k1 ECDSA signature verification proven by the [`EcdsaStark`]. All values are
+/// given as 8 little-endian 32-bit limbs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EcdsaVerification {
+    /// The hash `z` of the signed message.
+    pub msg_hash: [u32; 8],
+    /// The `r` component of the signature.
+    pub r: [u32; 8],
+    /// The `s` component of the signature.
+    pub s: [u32; 8],
+    /// The x coordinate of the public key.
+    pub key_x: [u32; 8],
+    /// The y coordinate of the public key.
+    pub key_y: [u32; 8],
+}
+
+impl EcdsaVerification {
+    /// Returns whether the signature is valid, see the [module documentation](self).
+    pub fn is_valid(&self) -> bool {
+        let n = Secp256K1Scalar::order();
+        let r = biguint_from_limbs(&self.r);
+        let s = biguint_from_limbs(&self.s);
+        let zero = BigUint::from(0u32);
+        if r == zero || r >= n || s == zero || s >= n {
+            return false;
+        }
+        let key = (base_from_limbs(&self.key_x), base_from_limbs(&self.key_y));
+        if !is_on_curve(key) {
+            return false;
+        }
+
+        let w = scalar_from_biguint(&s).inverse();
+        let u1 = scalar_from_biguint(&biguint_from_limbs(&self.msg_hash)) * w;
+        let u2 = scalar_from_biguint(&r) * w;
+        let sum = point_add(
+            scalar_mul(&u1.to_canonical_biguint(), G),
+            scalar_mul(&u2.to_canonical_biguint(), key),
+        );
+        sum.is_some_and(|(x, _)| x.to_canonical_biguint() % n == r)
+    }
+
+    /// Returns the `(z, r, s, Q.x, Q.y, valid)` tuple representing this verification in the
+    /// cross-table lookup.
+    pub fn values<F: Field>(&self) -> Vec<F> {
+        [self.msg_hash, self.r, self.s, self.key_x, self.key_y]
+            .into_iter()
+            .flatten()
+            .map(F::from_canonical_u32)
+            .chain([F::from_bool(self.is_valid())])
+            .collect()
+    }
+}
+
+/// A STARK proving a batch of [`EcdsaVerification`]s.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct EcdsaStark<F: RichField + Extendable<D>, const D: usize> {
+    _phantom: PhantomData<F>,
+}
+
+fn write_bytes<F: Field>(row: &mut [F], start: usize, value: &BigUint, num_bytes: usize) {
+    let bytes = value.to_bytes_le();
+    assert!(bytes.len() <= num_bytes);
+    for (col, byte) in
+        (start..start + num_bytes).zip(bytes.into_iter().chain(core::iter::repeat(0)))
+    {
+        row[col] = F::from_canonical_u8(byte);
+    }
+}
+
+fn write_limbs<F: Field>(row: &mut [F], start: usize, value: &BigUint) {
+    let mut bytes = value.to_bytes_le();
+    assert!(bytes.len() <= NUM_VALUE_BYTES);
+    bytes.resize(NUM_VALUE_BYTES, 0);
+    for (i, limb) in bytes.chunks_exact(2).enumerate() {
+        row[start + i] = F::from_canonical_u16(u16::from_le_bytes([limb[0], limb[1]]));
+    }
+}
+
+fn write_point<F: Field>(row: &mut [F], x_start: usize, y_start: usize, (x, y): AffinePoint) {
+    write_limbs(row, x_start, &x.to_canonical_biguint());
+    write_limbs(row, y_start, &y.to_canonical_biguint());
+}
+
+fn write_slot<F: Field, T: PrimeField>(row: &mut [F], slot: usize, value: T) {
+    write_bytes(
+        row,
+        slot_value(slot),
+        &value.to_canonical_biguint(),
+        NUM_VALUE_BYTES,
+    );
+}
+
+/// Returns `x` as a signed integer, assuming that it is small.
+fn to_signed<F: RichField>(x: F) -> i64 {
+    let x = x.to_canonical_u64();
+    if x > F::ORDER / 2 {
+        -((F::ORDER - x) as i64)
+    } else {
+        x as i64
+    }
+}
+
+fn signed<F: Field>(x: i64) -> F {
+    if x >= 0 {
+        F::from_canonical_u64(x as u64)
+    } else {
+        -F::from_canonical_u64(x.unsigned_abs())
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> EcdsaStark<F, D> {
+    /// Creates a new [`EcdsaStark`].
+    pub const fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of rows of a trace proving `num_verifications` verifications. The trace
+    /// must be large enough to hold both the verifications and the `0..256` byte table.
+    pub fn num_rows(num_verifications: usize) -> usize {
+        (num_verifications * ROWS_PER_VERIFICATION)
+            .next_power_of_two()
+            .max(RANGE_MAX + 1)
+    }
+
+    /// Generates the trace proving the provided verifications, in order.
+    pub fn generate_trace(&self, verifications: &[EcdsaVerification]) -> Vec<PolynomialValues<F>> {
+        let num_rows = Self::num_rows(verifications.len());
+        let mut rows = vec![[F::ZERO; ECDSA_COLUMNS]; num_rows];
+        for (verification, rows) in verifications
+            .iter()
+            .zip(rows.chunks_exact_mut(ROWS_PER_VERIFICATION))
+        {
+            Self::generate_verification(verification, rows);
+        }
+
+        let mut frequencies = [0usize; RANGE_MAX + 1];
+        for row in &rows {
+            for col in byte_columns() {
+                frequencies[row[col].to_canonical_u64() as usize] += 1;
+            }
+        }
+        for (i, row) in rows.iter_mut().enumerate() {
+            row[RANGE_COUNTER] = F::from_canonical_usize(i.min(RANGE_MAX));
+            if i <= RANGE_MAX {
+                row[RANGE_FREQUENCIES] = F::from_canonical_usize(frequencies[i]);
+            }
+        }
+
+        trace_rows_to_poly_values(rows)
+    }
+
+    fn generate_verification(verification: &EcdsaVerification, rows: &mut [[F; ECDSA_COLUMNS]]) {
+        let n = Secp256K1Scalar::order();
+        let zero = BigUint::from(0u32);
+        let z = biguint_from_limbs(&verification.msg_hash);
+        let r = biguint_from_limbs(&verification.r);
+        let s = biguint_from_limbs(&verification.s);
+        let key_x = biguint_from_limbs(&verification.key_x);
+        let key_y = biguint_from_limbs(&verification.key_y);
+
+        // Constants of the verification, written to all of its rows at the end.
+        let mut constants = [F::ZERO; ECDSA_COLUMNS];
+        for (start, value) in [(MSG_HASH, &z), (SIG_R, &r), (SIG_S, &s), (KEY_X, &key_x)] {
+            write_bytes(&mut constants, start, value, NUM_VALUE_BYTES);
+        }
+        write_bytes(&mut constants, KEY_Y, &key_y, NUM_VALUE_BYTES);
+
+        // Check the ranges of r and s, and compute u1 and u2.
+        let row = &mut rows[0];
+        row[SETUP_SCALARS] = F::ONE;
+        let n_minus_one = &n - 1u32;
+        let two_256 = BigUint::from(1u32) << 256;
+        let mut scalar_ok = [false; 2];
+        for (i, (value, borrow, sum_inv, nonzero, ok)) in [
+            (&r, R_BORROW, R_BYTE_SUM_INV, R_NONZERO, R_OK),
+            (&s, S_BORROW, S_BYTE_SUM_INV, S_NONZERO, S_OK),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let is_borrow = *value >= n;
+            let e = &n_minus_one + if is_borrow { &two_256 } else { &zero } - value;
+            write_bytes(row, slot_value(i), &e, NUM_VALUE_BYTES);
+            row[borrow] = F::from_bool(is_borrow);
+            let byte_sum =
+                F::from_canonical_u32(value.to_bytes_le().into_iter().map(u32::from).sum());
+            row[sum_inv] = byte_sum.try_inverse().unwrap_or(F::ZERO);
+            row[nonzero] = F::from_bool(*value != zero);
+            scalar_ok[i] = *value != zero && !is_borrow;
+            constants[ok] = F::from_bool(scalar_ok[i]);
+        }
+        let [r_ok, s_ok] = scalar_ok;
+        let s_used = scalar_from_biguint(&s)
+            + if s_ok {
+                Secp256K1Scalar::ZERO
+            } else {
+                Secp256K1Scalar::ONE
+            };
+        let w = s_used.inverse();
+        let u1 = scalar_from_biguint(&z) * w;
+        let u2 = scalar_from_biguint(&r) * w;
+        write_slot(row, 2, w);
+        write_slot(row, 3, u1);
+        write_slot(row, 4, u2);
+        let u1 = u1.to_canonical_biguint();
+        let u2 = u2.to_canonical_biguint();
+        write_limbs(&mut constants, U1, &u1);
+        write_limbs(&mut constants, U2, &u2);
+
+        // Check that Q is on the curve.
+        let row = &mut rows[1];
+        row[SETUP_KEY] = F::ONE;
+        let key = (
+            Secp256K1Base::from_noncanonical_biguint(key_x.clone()),
+            Secp256K1Base::from_noncanonical_biguint(key_y.clone()),
+        );
+        let key_x_squared = key.0 * key.0;
+        let d = key.1 * key.1 - key_x_squared * key.0 - Secp256K1Base::from_canonical_u8(7);
+        let key_ok = d == Secp256K1Base::ZERO;
+        write_slot(row, 0, key_x_squared);
+        write_slot(row, 1, d);
+        write_slot(row, 2, d.try_inverse().unwrap_or(Secp256K1Base::ZERO));
+        row[IS_NONZERO] = F::from_bool(!key_ok);
+        constants[KEY_OK] = F::from_bool(key_ok);
+
+        // Compute Q + H and G + Q + H, using G instead of Q if Q is not on the curve. The addend
+        // holds the limbs of Q as given, which may not be canonical.
+        let (key_used, addend_x, addend_y) = if key_ok {
+            (key, key_x, key_y)
+        } else {
+            (G, G.0.to_canonical_biguint(), G.1.to_canonical_biguint())
+        };
+        write_limbs(&mut rows[2], ADDEND_X, &addend_x);
+        write_limbs(&mut rows[2], ADDEND_Y, &addend_y);
+        let add_row = |row: &mut [F; ECDSA_COLUMNS], flag, p: AffinePoint, q: AffinePoint| {
+            row[flag] = F::ONE;
+            let inv = (q.0 - p.0).inverse();
+            let lambda = (q.1 - p.1) * inv;
+            let x = lambda * lambda - p.0 - q.0;
+            let y = lambda * (p.0 - x) - p.1;
+            write_slot(row, 0, lambda);
+            write_slot(row, 1, x);
+            write_slot(row, 2, y);
+            write_slot(row, 3, inv);
+            (x, y)
+        };
+        let qh = add_row(&mut rows[2], SETUP_QH, key_used, H);
+        let gqh = add_row(&mut rows[3], SETUP_GQH, qh, G);
+        write_point(&mut constants, QH_X, QH_Y, qh);
+        write_point(&mut constants, GQH_X, GQH_Y, gqh);
+
+        // Double-and-add ladder, processing the bits of u1 and u2 from the most significant one.
+        let table = [H, point_add(Some(G), Some(H)).unwrap(), qh, gqh];
+        let mut acc = point_add(Some(H), Some(H)).unwrap();
+        for (i, row) in rows[NUM_SETUP_ROWS..NUM_SETUP_ROWS + NUM_LADDER_ROWS]
+            .iter_mut()
+            .enumerate()
+        {
+            let bit = NUM_LADDER_ROWS - 1 - i;
+            let (limb, bit_in_limb) = (bit / LIMB_BITS, bit % LIMB_BITS);
+            row[BIT_FLAGS.start + bit_in_limb] = F::ONE;
+            row[LIMB_FLAGS.start + limb] = F::ONE;
+            let (b1, b2) = (u1.bit(bit as u64), u2.bit(bit as u64));
+            row[U1_BIT] = F::from_bool(b1);
+            row[U2_BIT] = F::from_bool(b2);
+            row[BOTH_BITS] = F::from_bool(b1 && b2);
+            for (col, scalar) in [(U1_LIMB_ACC, &u1), (U2_LIMB_ACC, &u2)] {
+                let limb_value = scalar.to_u32_digits().get(limb / 2).copied().unwrap_or(0)
+                    >> (LIMB_BITS * (limb % 2))
+                    & 0xFFFF;
+                row[col] = F::from_canonical_u32(limb_value >> bit_in_limb);
+            }
+
+            let addend = table[b1 as usize + 2 * b2 as usize];
+            write_point(row, ACC_X, ACC_Y, acc);
+            write_point(row, ADDEND_X, ADDEND_Y, addend);
+            let inv = (addend.0 - acc.0).inverse();
+            let lambda1 = (addend.1 - acc.1) * inv;
+            let x3 = lambda1 * lambda1 - acc.0 - addend.0;
+            let lambda2 = (acc.1 + acc.1) / (acc.0 - x3) - lambda1;
+            let x4 = lambda2 * lambda2 - acc.0 - x3;
+            let y4 = lambda2 * (acc.0 - x4) - acc.1;
+            for (slot, value) in [lambda1, x3, lambda2, x4, y4, inv].into_iter().enumerate() {
+                write_slot(row, slot, value);
+            }
+            acc = (x4, y4);
+        }
+
+        // Remove the offset from the accumulator.
+        let row = &mut rows[NUM_SETUP_ROWS + NUM_LADDER_ROWS];
+        row[FINAL_SUM] = F::ONE;
+        write_point(row, ACC_X, ACC_Y, acc);
+        let neg_offset = (Secp256K1Base(NEG_OFFSET_X), Secp256K1Base(NEG_OFFSET_Y));
+        let dx = neg_offset.0 - acc.0;
+        let sum_nonzero = dx != Secp256K1Base::ZERO;
+        let lambda = if sum_nonzero {
+            (neg_offset.1 - acc.1) / dx
+        } else {
+            Secp256K1Base::ZERO
+        };
+        let sum_x = lambda * lambda - acc.0 - neg_offset.0;
+        write_slot(row, 0, dx.try_inverse().unwrap_or(Secp256K1Base::ZERO));
+        write_slot(row, 2, lambda);
+        write_slot(row, 3, sum_x);
+        row[IS_NONZERO] = F::from_bool(sum_nonzero);
+        constants[SUM_NONZERO] = F::from_bool(sum_nonzero);
+        let sum_x = sum_x.to_canonical_biguint();
+        write_limbs(&mut constants, SUM_X, &sum_x);
+
+        // Compare the x coordinate of the sum with r.
+        let row = &mut rows[NUM_SETUP_ROWS + NUM_LADDER_ROWS + 1];
+        row[FINAL_CHECK] = F::ONE;
+        write_bytes(
+            row,
+            slot_value(0),
+            &(Secp256K1Base::order() - 1u32 - &sum_x),
+            NUM_VALUE_BYTES,
+        );
+        let diff = scalar_from_biguint(&sum_x) - scalar_from_biguint(&r);
+        let x_matches = diff == Secp256K1Scalar::ZERO;
+        write_slot(row, 1, diff.try_inverse().unwrap_or(Secp256K1Scalar::ZERO));
+        row[IS_NONZERO] = F::from_bool(!x_matches);
+        let ok_rs = r_ok && s_ok;
+        let ok_rs_key = ok_rs && key_ok;
+        let ok_all = ok_rs_key && sum_nonzero;
+        row[OK_RS] = F::from_bool(ok_rs);
+        row[OK_RS_KEY] = F::from_bool(ok_rs_key);
+        row[OK_ALL] = F::from_bool(ok_all);
+        row[VALID] = F::from_bool(ok_all && x_matches);
+
+        for (i, row) in rows.iter_mut().enumerate() {
+            row[VERIFICATION_CONSTANTS].copy_from_slice(&constants[VERIFICATION_CONSTANTS]);
+            let row_type = match i {
+                0..NUM_SETUP_ROWS => i,
+                _ if i < NUM_SETUP_ROWS + NUM_LADDER_ROWS => LADDER,
+                _ => i - NUM_LADDER_ROWS,
+            };
+            for (slot, identity) in IDENTITIES[row_type].iter().enumerate() {
+                Self::generate_slot(row, slot, identity);
+            }
+        }
+    }
+
+    /// Fills in the quotient and carries of `slot`, whose value and operands are already set.
+    fn generate_slot(row: &mut [F; ECDSA_COLUMNS], slot: usize, identity: &Identity) {
+        let mut coeffs = identity_poly::<F>(identity, row).map(|c| to_signed(c) as i128);
+
+        if let (Some(modulus), Some(modulus_limbs)) =
+            (identity.modulus.value(), identity.modulus.limbs())
+        {
+            let value = coeffs
+                .iter()
+                .rev()
+                .fold(BigInt::from(0), |acc, &c| (acc << LIMB_BITS) + c);
+            let modulus = BigInt::from(modulus);
+            debug_assert_eq!(&value % &modulus, BigInt::from(0));
+            let quotient =
+                value / modulus + (BigInt::from(QUOTIENT_OFFSET) << (LIMB_BITS * NUM_LIMBS));
+            let quotient = quotient
+                .to_biguint()
+                .expect("The quotient offset is large enough");
+            write_bytes(row, slot_quotient(slot), &quotient, 2 * NUM_QUOTIENT_LIMBS);
+
+            let mut quotient_bytes = quotient.to_bytes_le();
+            quotient_bytes.resize(2 * NUM_QUOTIENT_LIMBS, 0);
+            for (i, &m) in modulus_limbs.iter().enumerate() {
+                coeffs[i + NUM_LIMBS] += QUOTIENT_OFFSET as i128 * m as i128;
+                for (j, q) in quotient_bytes.chunks_exact(2).enumerate() {
+                    coeffs[i + j] -= u16::from_le_bytes([q[0], q[1]]) as i128 * m as i128;
+                }
+            }
+        }
+
+        // coeffs(x) = (x - 2^16) * carries(x).
+        let mut carry = 0i128;
+        for k in 0..NUM_CARRIES {
+            let numerator = carry - coeffs[k];
+            debug_assert_eq!(numerator % (1 << LIMB_BITS), 0);
+            carry = numerator >> LIMB_BITS;
+            let stored = carry + CARRY_OFFSET as i128;
+            assert!((0..1 << (8 * CARRY_BYTES)).contains(&stored));
+            write_bytes(
+                row,
+                slot_carries(slot) + CARRY_BYTES * k,
+                &BigUint::from(stored as u32),
+                CARRY_BYTES,
+            );
+        }
+        debug_assert_eq!(carry, coeffs[NUM_COEFFS - 1]);
+    }
+}
+
+/// Returns all the columns holding bytes, which are range-checked.
+fn byte_columns() -> impl Iterator<Item = usize> {
+    INPUTS.chain(SLOTS..RANGE_COUNTER)
+}
+
+/// Returns the cross-table lookup exposing the `(z, r, s, Q.x, Q.y, valid)` tuples of all the
+/// verifications in the [`EcdsaStark`] trace. It has no looking table: the looking side is
+/// provided as an extra looking sum over the delegated verifications.
+pub fn ecdsa_ctl<F: Field>() -> CrossTableLookup<F> {
+    let columns = INPUTS
+        .step_by(4)
+        .map(|start| Column::le_bytes(start..start + 4))
+        .chain([Column::single(VALID)])
+        .collect();
+    let filter = Filter::new_simple(Column::single(FINAL_CHECK));
+    CrossTableLookup::new(vec![], TableWithColumns::new(0, columns, filter))
+}
+
+/// Returns the limbs of the value stored as little-endian bytes in `bytes`.
+fn limbs_from_bytes<P: PackedField>(bytes: &[P]) -> Vec<P> {
+    bytes
+        .chunks_exact(2)
+        .map(|b| b[0] + b[1] * P::Scalar::from_canonical_u16(256))
+        .collect()
+}
+
+fn operand_poly<P: PackedField>(operand: Operand, lv: &[P]) -> Vec<P> {
+    match operand {
+        Bytes(start) => limbs_from_bytes(&lv[start..start + NUM_VALUE_BYTES]),
+        Limbs(start) => lv[start..start + NUM_LIMBS].to_vec(),
+        Column(col) => vec![lv[col]],
+        Overflow(col) => {
+            let mut poly = vec![P::ZEROS; NUM_LIMBS + 1];
+            poly[NUM_LIMBS] = lv[col];
+            poly
+        }
+        Constant(value) => to_limbs(value)
+            .into_iter()
+            .map(|limb| P::from(P::Scalar::from_canonical_u16(limb)))
+            .collect(),
+        One => vec![P::ONES],
+    }
+}
+
+/// Evaluates the polynomial `sum(terms)` of `identity` on the row `lv`.
+fn identity_poly<P: PackedField>(identity: &Identity, lv: &[P]) -> [P; NUM_COEFFS] {
+    let mut poly = [P::ZEROS; NUM_COEFFS];
+    for term in identity.terms {
+        let coeff = signed::<P::Scalar>(term.coeff);
+        let a = operand_poly(term.a, lv);
+        let b = operand_poly(term.b, lv);
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                poly[i + j] += x * y * coeff;
+            }
+        }
+    }
+    poly
+}
+
+fn limbs_from_bytes_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    bytes: &[ExtensionTarget<D>],
+) -> Vec<ExtensionTarget<D>> {
+    bytes
+        .chunks_exact(2)
+        .map(|b| builder.mul_const_add_extension(F::from_canonical_u16(256), b[1], b[0]))
+        .collect()
+}
+
+fn operand_poly_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    operand: Operand,
+    lv: &[ExtensionTarget<D>],
+) -> Vec<ExtensionTarget<D>> {
+    match operand {
+        Bytes(start) => limbs_from_bytes_circuit(builder, &lv[start..start + NUM_VALUE_BYTES]),
+        Limbs(start) => lv[start..start + NUM_LIMBS].to_vec(),
+        Column(col) => vec![lv[col]],
+        Overflow(col) => {
+            let mut poly = vec![builder.zero_extension(); NUM_LIMBS + 1];
+            poly[NUM_LIMBS] = lv[col];
+            poly
+        }
+        Constant(value) => to_limbs(value)
+            .into_iter()
+            .map(|limb| builder.constant_extension(F::Extension::from_canonical_u16(limb)))
+            .collect(),
+        One => vec![builder.one_extension()],
+    }
+}
+
+fn identity_poly_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    identity: &Identity,
+    lv: &[ExtensionTarget<D>],
+) -> Vec<ExtensionTarget<D>> {
+    let mut poly = vec![builder.zero_extension(); NUM_COEFFS];
+    for term in identity.terms {
+        let coeff = signed::<F>(term.coeff);
+        let a = operand_poly_circuit(builder, term.a, lv);
+        let b = operand_poly_circuit(builder, term.b, lv);
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                poly[i + j] = builder.arithmetic_extension(coeff, F::ONE, x, y, poly[i + j]);
+            }
+        }
+    }
+    poly
+}
+
+/// Returns the flags of all row types, indexed like [`IDENTITIES`].
+fn row_type_flags<P: PackedField>(lv: &[P]) -> [P; NUM_ROW_TYPES] {
+    let mut flags = [P::ZEROS; NUM_ROW_TYPES];
+    flags[..LADDER].copy_from_slice(&lv[SETUP_SCALARS..LADDER]);
+    flags[LADDER] = lv[BIT_FLAGS].iter().copied().sum();
+    flags
+}
+
+fn row_type_flags_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    lv: &[ExtensionTarget<D>],
+) -> [ExtensionTarget<D>; NUM_ROW_TYPES] {
+    let mut flags = [builder.zero_extension(); NUM_ROW_TYPES];
+    flags[..LADDER].copy_from_slice(&lv[SETUP_SCALARS..LADDER]);
+    flags[LADDER] = builder.add_many_extension(&lv[BIT_FLAGS]);
+    flags
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for EcdsaStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize>
+        = StarkFrame<P, P::Scalar, ECDSA_COLUMNS, ECDSA_PUBLIC_INPUTS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget =
+        StarkFrame<ExtensionTarget<D>, ExtensionTarget<D>, ECDSA_COLUMNS, ECDSA_PUBLIC_INPUTS>;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let lv = vars.get_local_values();
+        let nv = vars.get_next_values();
+
+        // Row types are one-hot, and the ladder rows process each bit of each limb exactly once.
+        let flags = row_type_flags(lv);
+        let next_flags = row_type_flags(nv);
+        let ladder = flags[LADDER];
+        let active: P = flags.iter().copied().sum();
+        let next_active: P = next_flags.iter().copied().sum();
+        for col in (SETUP_SCALARS..LADDER).chain(BIT_FLAGS).chain(LIMB_FLAGS) {
+            yield_constr.constraint(lv[col] * (lv[col] - P::ONES));
+        }
+        yield_constr.constraint(active * (active - P::ONES));
+        yield_constr.constraint(ladder - lv[LIMB_FLAGS].iter().copied().sum::<P>());
+
+        // Each verification goes through all its rows in order, and can only start on the first
+        // row, or right after another verification or a padding row.
+        let bit0 = lv[BIT_FLAGS.start];
+        let limb0 = lv[LIMB_FLAGS.start];
+        yield_constr.constraint_first_row(active - lv[SETUP_SCALARS]);
+        yield_constr.constraint_transition(nv[SETUP_SCALARS] * (active - lv[FINAL_CHECK]));
+        for row_type in SETUP_KEY..=SETUP_GQH {
+            yield_constr.constraint_transition(nv[row_type] - lv[row_type - 1]);
+        }
+        for j in 1..LIMB_BITS {
+            yield_constr
+                .constraint_transition(nv[BIT_FLAGS.start + j - 1] - lv[BIT_FLAGS.start + j]);
+        }
+        yield_constr.constraint_transition(
+            nv[BIT_FLAGS.end - 1] - lv[SETUP_GQH] - bit0 * (P::ONES - limb0),
+        );
+        for l in 0..NUM_LIMBS - 1 {
+            let limb = lv[LIMB_FLAGS.start + l];
+            let next_limb = lv[LIMB_FLAGS.start + l + 1];
+            yield_constr.constraint_transition(
+                nv[LIMB_FLAGS.start + l] - limb * (P::ONES - bit0) - next_limb * bit0,
+            );
+        }
+        let last_limb = lv[LIMB_FLAGS.end - 1];
+        yield_constr.constraint_transition(
+            nv[LIMB_FLAGS.end - 1] - last_limb * (P::ONES - bit0) - lv[SETUP_GQH],
+        );
+        yield_constr.constraint_transition(nv[FINAL_SUM] - bit0 * limb0);
+        yield_constr.constraint_transition(nv[FINAL_CHECK] - lv[FINAL_SUM]);
+
+        // Verification constants don't change until the next verification.
+        let same_verification = next_active - nv[SETUP_SCALARS];
+        for col in VERIFICATION_CONSTANTS {
+            yield_constr.constraint_transition(same_verification * (nv[col] - lv[col]));
+        }
+
+        // All boolean columns used as operands of identities.
+        let uses_is_nonzero = lv[SETUP_KEY] + lv[FINAL_SUM] + lv[FINAL_CHECK];
+        let is_nonzero = lv[IS_NONZERO];
+        yield_constr.constraint(uses_is_nonzero * is_nonzero * (is_nonzero - P::ONES));
+
+        // Check the ranges of r and s, and set u1 and u2.
+        let setup = lv[SETUP_SCALARS];
+        for (input, borrow, sum_inv, nonzero, ok) in [
+            (SIG_R, R_BORROW, R_BYTE_SUM_INV, R_NONZERO, R_OK),
+            (SIG_S, S_BORROW, S_BYTE_SUM_INV, S_NONZERO, S_OK),
+        ] {
+            let borrow = lv[borrow];
+            yield_constr.constraint(setup * borrow * (borrow - P::ONES));
+            let byte_sum: P = lv[input..input + NUM_VALUE_BYTES].iter().copied().sum();
+            yield_constr.constraint(setup * (lv[nonzero] - byte_sum * lv[sum_inv]));
+            yield_constr.constraint(setup * byte_sum * (P::ONES - lv[nonzero]));
+            yield_constr.constraint(setup * (lv[ok] - lv[nonzero] * (P::ONES - borrow)));
+        }
+        for (scalar, slot) in [(U1, 3), (U2, 4)] {
+            let value = limbs_from_bytes(&lv[slot_value(slot)..slot_quotient(slot)]);
+            for (i, limb) in value.into_iter().enumerate() {
+                yield_constr.constraint(setup * (lv[scalar + i] - limb));
+            }
+        }
+
+        // Set whether the key is on the curve.
+        yield_constr.constraint(lv[SETUP_KEY] * (lv[KEY_OK] - P::ONES + is_nonzero));
+
+        // Set the addend of the first table addition, and the table points.
+        let key_x = limbs_from_bytes(&lv[KEY_X..KEY_X + NUM_VALUE_BYTES]);
+        let key_y = limbs_from_bytes(&lv[KEY_Y..KEY_Y + NUM_VALUE_BYTES]);
+        for (addend, key, generator) in [(ADDEND_X, key_x, G_X), (ADDEND_Y, key_y, G_Y)] {
+            for (i, (key, generator)) in key.into_iter().zip(to_limbs(generator)).enumerate() {
+                let generator = FE::from_canonical_u16(generator);
+                yield_constr.constraint(
+                    lv[SETUP_QH] * (lv[addend + i] - lv[KEY_OK] * (key - generator) - generator),
+                );
+            }
+        }
+        for (flag, x, y) in [(SETUP_QH, QH_X, QH_Y), (SETUP_GQH, GQH_X, GQH_Y)] {
+            for (start, slot) in [(x, 1), (y, 2)] {
+                let value = limbs_from_bytes(&lv[slot_value(slot)..slot_quotient(slot)]);
+                for (i, limb) in value.into_iter().enumerate() {
+                    yield_constr.constraint(lv[flag] * (lv[start + i] - limb));
+                }
+            }
+        }
+
+        // The accumulator starts at 2H, and is updated by each ladder row.
+        for (acc, initial, slot) in [(ACC_X, H2_X, 3), (ACC_Y, H2_Y, 4)] {
+            let value = limbs_from_bytes(&lv[slot_value(slot)..slot_quotient(slot)]);
+            for (i, (initial, limb)) in to_limbs(initial).into_iter().zip(value).enumerate() {
+                let initial = FE::from_canonical_u16(initial);
+                yield_constr.constraint_transition(lv[SETUP_GQH] * (nv[acc + i] - initial));
+                yield_constr.constraint_transition(ladder * (nv[acc + i] - limb));
+            }
+        }
+
+        // The addend of each ladder row is selected by the bits of u1 and u2.
+        let (b1, b2, both) = (lv[U1_BIT], lv[U2_BIT], lv[BOTH_BITS]);
+        yield_constr.constraint(ladder * b1 * (b1 - P::ONES));
+        yield_constr.constraint(ladder * b2 * (b2 - P::ONES));
+        yield_constr.constraint(ladder * (both - b1 * b2));
+        for (addend, h, gh, qh, gqh) in [
+            (ADDEND_X, H_X, GH_X, QH_X, GQH_X),
+            (ADDEND_Y, H_Y, GH_Y, QH_Y, GQH_Y),
+        ] {
+            for (i, (h, gh)) in to_limbs(h).into_iter().zip(to_limbs(gh)).enumerate() {
+                let (h, gh) = (FE::from_canonical_u16(h), FE::from_canonical_u16(gh));
+                let (qh, gqh) = (lv[qh + i], lv[gqh + i]);
+                let selected =
+                    (b1 - both) * (gh - h) + (b2 - both) * (qh - h) + both * (gqh - h) + h;
+                yield_constr.constraint(ladder * (lv[addend + i] - selected));
+            }
+        }
+
+        // The bits of u1 and u2 are accumulated limb by limb, and checked at the end of each limb.
+        for (scalar, bit, limb_acc) in [(U1, U1_BIT, U1_LIMB_ACC), (U2, U2_BIT, U2_LIMB_ACC)] {
+            yield_constr.constraint_transition(
+                next_flags[LADDER]
+                    * (nv[limb_acc] - nv[bit] - lv[limb_acc] * (ladder - bit0) * FE::TWO),
+            );
+            let limb: P = (0..NUM_LIMBS)
+                .map(|l| lv[LIMB_FLAGS.start + l] * lv[scalar + l])
+                .sum();
+            yield_constr.constraint(bit0 * (lv[limb_acc] - limb));
+        }
+
+        // Set the result of the final rows.
+        let final_sum = lv[FINAL_SUM];
+        yield_constr.constraint(final_sum * (lv[SUM_NONZERO] - is_nonzero));
+        let sum_x = limbs_from_bytes(&lv[slot_value(3)..slot_quotient(3)]);
+        for (i, limb) in sum_x.into_iter().enumerate() {
+            yield_constr.constraint(final_sum * (lv[SUM_X + i] - limb));
+        }
+        let final_check = lv[FINAL_CHECK];
+        yield_constr.constraint(final_check * (lv[OK_RS] - lv[R_OK] * lv[S_OK]));
+        yield_constr.constraint(final_check * (lv[OK_RS_KEY] - lv[OK_RS] * lv[KEY_OK]));
+        yield_constr.constraint(final_check * (lv[OK_ALL] - lv[OK_RS_KEY] * lv[SUM_NONZERO]));
+        yield_constr.constraint(final_check * (lv[VALID] - lv[OK_ALL] * (P::ONES - is_nonzero)));
+
+        // The identities of each slot, for the current row type:
+        // sum(terms)(x) - (q(x) - 2^260) * m(x) = (x - 2^16) * c(x).
+        let base = FE::from_canonical_u64(1 << LIMB_BITS);
+        for slot in 0..NUM_SLOTS {
+            let quotient = limbs_from_bytes(&lv[slot_quotient(slot)..slot_carries(slot)]);
+            let carries = lv[slot_carries(slot)..slot_carries(slot) + CARRY_BYTES * NUM_CARRIES]
+                .chunks_exact(CARRY_BYTES)
+                .map(|bytes| {
+                    bytes.iter().rev().fold(P::ZEROS, |acc, &byte| {
+                        acc * FE::from_canonical_u16(256) + byte
+                    }) - FE::from_canonical_u64(CARRY_OFFSET as u64)
+                })
+                .collect::<Vec<_>>();
+
+            let mut sum = [P::ZEROS; NUM_COEFFS];
+            let mut slot_active = P::ZEROS;
+            for (flag, identities) in flags.iter().zip(IDENTITIES) {
+                let Some(identity) = identities.get(slot) else {
+                    continue;
+                };
+                slot_active += *flag;
+                let mut poly = identity_poly(identity, lv);
+                if let Some(modulus) = identity.modulus.limbs() {
+                    for (i, m) in modulus.into_iter().enumerate() {
+                        let m = FE::from_canonical_u16(m);
+                        poly[i + NUM_LIMBS] += m * FE::from_canonical_u64(QUOTIENT_OFFSET);
+                        for (j, &q) in quotient.iter().enumerate() {
+                            poly[i + j] -= q * m;
+                        }
+                    }
+                }
+                for (s, p) in sum.iter_mut().zip(poly) {
+                    *s += p * *flag;
+                }
+            }
+
+            for (k, s) in sum.into_iter().enumerate() {
+                let prev = if k > 0 { carries[k - 1] } else { P::ZEROS };
+                let cur = carries.get(k).copied().unwrap_or(P::ZEROS);
+                yield_constr.constraint(s - slot_active * (prev - cur * base));
+            }
+        }
+
+        // The range counter goes from 0 to 255, in steps of 0 or 1.
+        let counter = lv[RANGE_COUNTER];
+        yield_constr.constraint_first_row(counter);
+        let delta = nv[RANGE_COUNTER] - counter;
+        yield_constr.constraint_transition(delta * (delta - P::ONES));
+        yield_constr.constraint_last_row(counter - FE::from_canonical_usize(RANGE_MAX));
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let lv = vars.get_local_values();
+        let nv = vars.get_next_values();
+        let one = builder.one_extension();
+
+        let flags = row_type_flags_circuit(builder, lv);
+        let next_flags = row_type_flags_circuit(builder, nv);
+        let ladder = flags[LADDER];
+        let active = builder.add_many_extension(flags);
+        let next_active = builder.add_many_extension(next_flags);
+        for col in (SETUP_SCALARS..LADDER).chain(BIT_FLAGS).chain(LIMB_FLAGS) {
+            let constraint = builder.mul_sub_extension(lv[col], lv[col], lv[col]);
+            yield_constr.constraint(builder, constraint);
+        }
+        let constraint = builder.mul_sub_extension(active, active, active);
+        yield_constr.constraint(builder, constraint);
+        let limb_sum = builder.add_many_extension(&lv[LIMB_FLAGS]);
+        let constraint = builder.sub_extension(ladder, limb_sum);
+        yield_constr.constraint(builder, constraint);
+
+        let bit0 = lv[BIT_FLAGS.start];
+        let limb0 = lv[LIMB_FLAGS.start];
+        let constraint = builder.sub_extension(active, lv[SETUP_SCALARS]);
+        yield_constr.constraint_first_row(builder, constraint);
+        let diff = builder.sub_extension(active, lv[FINAL_CHECK]);
+        let constraint = builder.mul_extension(nv[SETUP_SCALARS], diff);
+        yield_constr.constraint_transition(builder, constraint);
+        for row_type in SETUP_KEY..=SETUP_GQH {
+            let constraint = builder.sub_extension(nv[row_type], lv[row_type - 1]);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+        for j in 1..LIMB_BITS {
+            let constraint =
+                builder.sub_extension(nv[BIT_FLAGS.start + j - 1], lv[BIT_FLAGS.start + j]);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+        let not_limb0 = builder.sub_extension(one, limb0);
+        let continued = builder.mul_add_extension(bit0, not_limb0, lv[SETUP_GQH]);
+        let constraint = builder.sub_extension(nv[BIT_FLAGS.end - 1], continued);
+        yield_constr.constraint_transition(builder, constraint);
+        let not_bit0 = builder.sub_extension(one, bit0);
+        for l in 0..NUM_LIMBS - 1 {
+            let limb = lv[LIMB_FLAGS.start + l];
+            let next_limb = lv[LIMB_FLAGS.start + l + 1];
+            let same = builder.mul_extension(limb, not_bit0);
+            let expected = builder.mul_add_extension(next_limb, bit0, same);
+            let constraint = builder.sub_extension(nv[LIMB_FLAGS.start + l], expected);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+        let last_limb = lv[LIMB_FLAGS.end - 1];
+        let expected = builder.mul_add_extension(last_limb, not_bit0, lv[SETUP_GQH]);
+        let constraint = builder.sub_extension(nv[LIMB_FLAGS.end - 1], expected);
+        yield_constr.constraint_transition(builder, constraint);
+        let expected = builder.mul_extension(bit0, limb0);
+        let constraint = builder.sub_extension(nv[FINAL_SUM], expected);
+        yield_constr.constraint_transition(builder, constraint);
+        let constraint = builder.sub_extension(nv[FINAL_CHECK], lv[FINAL_SUM]);
+        yield_constr.constraint_transition(builder, constraint);
+
+        let same_verification = builder.sub_extension(next_active, nv[SETUP_SCALARS]);
+        for col in VERIFICATION_CONSTANTS {
+            let diff = builder.sub_extension(nv[col], lv[col]);
+            let constraint = builder.mul_extension(same_verification, diff);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+
+        let uses_is_nonzero =
+            builder.add_many_extension([lv[SETUP_KEY], lv[FINAL_SUM], lv[FINAL_CHECK]]);
+        let is_nonzero = lv[IS_NONZERO];
+        let is_nonzero_bool = builder.mul_sub_extension(is_nonzero, is_nonzero, is_nonzero);
+        let constraint = builder.mul_extension(uses_is_nonzero, is_nonzero_bool);
+        yield_constr.constraint(builder, constraint);
+
+        let setup = lv[SETUP_SCALARS];
+        for (input, borrow, sum_inv, nonzero, ok) in [
+            (SIG_R, R_BORROW, R_BYTE_SUM_INV, R_NONZERO, R_OK),
+            (SIG_S, S_BORROW, S_BYTE_SUM_INV, S_NONZERO, S_OK),
+        ] {
+            let borrow = lv[borrow];
+            let borrow_bool = builder.mul_sub_extension(borrow, borrow, borrow);
+            let constraint = builder.mul_extension(setup, borrow_bool);
+            yield_constr.constraint(builder, constraint);
+            let byte_sum = builder.add_many_extension(&lv[input..input + NUM_VALUE_BYTES]);
+            let diff = builder.arithmetic_extension(
+                F::NEG_ONE,
+                F::ONE,
+                byte_sum,
+                lv[sum_inv],
+                lv[nonzero],
+            );
+            let constraint = builder.mul_extension(setup, diff);
+            yield_constr.constraint(builder, constraint);
+            let not_nonzero = builder.sub_extension(one, lv[nonzero]);
+            let constraint = builder.mul_many_extension([setup, byte_sum, not_nonzero]);
+            yield_constr.constraint(builder, constraint);
+            let not_borrow = builder.sub_extension(one, borrow);
+            let expected = builder.mul_extension(lv[nonzero], not_borrow);
+            let diff = builder.sub_extension(lv[ok], expected);
+            let constraint = builder.mul_extension(setup, diff);
+            yield_constr.constraint(builder, constraint);
+        }
+        for (scalar, slot) in [(U1, 3), (U2, 4)] {
+            let value =
+                limbs_from_bytes_circuit(builder, &lv[slot_value(slot)..slot_quotient(slot)]);
+            for (i, limb) in value.into_iter().enumerate() {
+                let diff = builder.sub_extension(lv[scalar + i], limb);
+                let constraint = builder.mul_extension(setup, diff);
+                yield_constr.constraint(builder, constraint);
+            }
+        }
+
+        let key_ok = builder.add_extension(lv[KEY_OK], is_nonzero);
+        let constraint = builder.mul_sub_extension(lv[SETUP_KEY], key_ok, lv[SETUP_KEY]);
+        yield_constr.constraint(builder, constraint);
+
+        let key_x = limbs_from_bytes_circuit(builder, &lv[KEY_X..KEY_X + NUM_VALUE_BYTES]);
+        let key_y = limbs_from_bytes_circuit(builder, &lv[KEY_Y..KEY_Y + NUM_VALUE_BYTES]);
+        for (addend, key, generator) in [(ADDEND_X, key_x, G_X), (ADDEND_Y, key_y, G_Y)] {
+            for (i, (key, generator)) in key.into_iter().zip(to_limbs(generator)).enumerate() {
+                let generator =
+                    builder.constant_extension(F::Extension::from_canonical_u16(generator));
+                let key_diff = builder.sub_extension(key, generator);
+                let selected = builder.mul_add_extension(lv[KEY_OK], key_diff, generator);
+                let diff = builder.sub_extension(lv[addend + i], selected);
+                let constraint = builder.mul_extension(lv[SETUP_QH], diff);
+                yield_constr.constraint(builder, constraint);
+            }
+        }
+        for (flag, x, y) in [(SETUP_QH, QH_X, QH_Y), (SETUP_GQH, GQH_X, GQH_Y)] {
+            for (start, slot) in [(x, 1), (y, 2)] {
+                let value =
+                    limbs_from_bytes_circuit(builder, &lv[slot_value(slot)..slot_quotient(slot)]);
+                for (i, limb) in value.into_iter().enumerate() {
+                    let diff = builder.sub_extension(lv[start + i], limb);
+                    let constraint = builder.mul_extension(lv[flag], diff);
+                    yield_constr.constraint(builder, constraint);
+                }
+            }
+        }
+
+        for (acc, initial, slot) in [(ACC_X, H2_X, 3), (ACC_Y, H2_Y, 4)] {
+            let value =
+                limbs_from_bytes_circuit(builder, &lv[slot_value(slot)..slot_quotient(slot)]);
+            for (i, (initial, limb)) in to_limbs(initial).into_iter().zip(value).enumerate() {
+                let diff =
+                    builder.add_const_extension(nv[acc + i], -F::from_canonical_u16(initial));
+                let constraint = builder.mul_extension(lv[SETUP_GQH], diff);
+                yield_constr.constraint_transition(builder, constraint);
+                let diff = builder.sub_extension(nv[acc + i], limb);
+                let constraint = builder.mul_extension(ladder, diff);
+                yield_constr.constraint_transition(builder, constraint);
+            }
+        }
+
+        let (b1, b2, both) = (lv[U1_BIT], lv[U2_BIT], lv[BOTH_BITS]);
+        for b in [b1, b2] {
+            let b_bool = builder.mul_sub_extension(b, b, b);
+            let constraint = builder.mul_extension(ladder, b_bool);
+            yield_constr.constraint(builder, constraint);
+        }
+        let diff = builder.arithmetic_extension(F::NEG_ONE, F::ONE, b1, b2, both);
+        let constraint = builder.mul_extension(ladder, diff);
+        yield_constr.constraint(builder, constraint);
+        let b1_only = builder.sub_extension(b1, both);
+        let b2_only = builder.sub_extension(b2, both);
+        for (addend, h, gh, qh, gqh) in [
+            (ADDEND_X, H_X, GH_X, QH_X, GQH_X),
+            (ADDEND_Y, H_Y, GH_Y, QH_Y, GQH_Y),
+        ] {
+            for (i, (h, gh)) in to_limbs(h).into_iter().zip(to_limbs(gh)).enumerate() {
+                let h = F::from_canonical_u16(h);
+                let gh_minus_h = builder.constant_extension((F::from_canonical_u16(gh) - h).into());
+                let qh_minus_h = builder.add_const_extension(lv[qh + i], -h);
+                let gqh_minus_h = builder.add_const_extension(lv[gqh + i], -h);
+                let selected = builder.mul_extension(b1_only, gh_minus_h);
+                let selected = builder.mul_add_extension(b2_only, qh_minus_h, selected);
+                let selected = builder.mul_add_extension(both, gqh_minus_h, selected);
+                let selected = builder.add_const_extension(selected, h);
+                let diff = builder.sub_extension(lv[addend + i], selected);
+                let constraint = builder.mul_extension(ladder, diff);
+                yield_constr.constraint(builder, constraint);
+            }
+        }
+
+        let continues_limb = builder.sub_extension(ladder, bit0);
+        for (scalar, bit, limb_acc) in [(U1, U1_BIT, U1_LIMB_ACC), (U2, U2_BIT, U2_LIMB_ACC)] {
+            let prev = builder.mul_extension(lv[limb_acc], continues_limb);
+            let expected = builder.mul_const_add_extension(F::TWO, prev, nv[bit]);
+            let diff = builder.sub_extension(nv[limb_acc], expected);
+            let constraint = builder.mul_extension(next_flags[LADDER], diff);
+            yield_constr.constraint_transition(builder, constraint);
+            let pairs = (0..NUM_LIMBS)
+                .map(|l| (lv[LIMB_FLAGS.start + l], lv[scalar + l]))
+                .collect();
+            let zero = builder.zero_extension();
+            let limb = builder.inner_product_extension(F::ONE, zero, pairs);
+            let diff = builder.sub_extension(lv[limb_acc], limb);
+            let constraint = builder.mul_extension(bit0, diff);
+            yield_constr.constraint(builder, constraint);
+        }
+
+        let final_sum = lv[FINAL_SUM];
+        let diff = builder.sub_extension(lv[SUM_NONZERO], is_nonzero);
+        let constraint = builder.mul_extension(final_sum, diff);
+        yield_constr.constraint(builder, constraint);
+        let sum_x = limbs_from_bytes_circuit(builder, &lv[slot_value(3)..slot_quotient(3)]);
+        for (i, limb) in sum_x.into_iter().enumerate() {
+            let diff = builder.sub_extension(lv[SUM_X + i], limb);
+            let constraint = builder.mul_extension(final_sum, diff);
+            yield_constr.constraint(builder, constraint);
+        }
+        let final_check = lv[FINAL_CHECK];
+        let is_zero = builder.sub_extension(one, is_nonzero);
+        for (result, x, y) in [
+            (OK_RS, lv[R_OK], lv[S_OK]),
+            (OK_RS_KEY, lv[OK_RS], lv[KEY_OK]),
+            (OK_ALL, lv[OK_RS_KEY], lv[SUM_NONZERO]),
+            (VALID, lv[OK_ALL], is_zero),
+        ] {
+            let diff = builder.arithmetic_extension(F::NEG_ONE, F::ONE, x, y, lv[result]);
+            let constraint = builder.mul_extension(final_check, diff);
+            yield_constr.constraint(builder, constraint);
+        }
+
+        let base = F::from_canonical_u64(1 << LIMB_BITS);
+        for slot in 0..NUM_SLOTS {
+            let quotient =
+                limbs_from_bytes_circuit(builder, &lv[slot_quotient(slot)..slot_carries(slot)]);
+            let carries = lv[slot_carries(slot)..slot_carries(slot) + CARRY_BYTES * NUM_CARRIES]
+                .chunks_exact(CARRY_BYTES)
+                .map(|bytes| {
+                    let zero = builder.zero_extension();
+                    let carry = bytes.iter().rev().fold(zero, |acc, &byte| {
+                        builder.mul_const_add_extension(F::from_canonical_u16(256), acc, byte)
+                    });
+                    builder.add_const_extension(carry, -F::from_canonical_u64(CARRY_OFFSET as u64))
+                })
+                .collect::<Vec<_>>();
+
+            let mut sum = vec![builder.zero_extension(); NUM_COEFFS];
+            let mut slot_active = builder.zero_extension();
+            for (&flag, identities) in flags.iter().zip(IDENTITIES) {
+                let Some(identity) = identities.get(slot) else {
+                    continue;
+                };
+                slot_active = builder.add_extension(slot_active, flag);
+                let mut poly = identity_poly_circuit(builder, identity, lv);
+                if let Some(modulus) = identity.modulus.limbs() {
+                    for (i, m) in modulus.into_iter().enumerate() {
+                        let m = F::from_canonical_u16(m);
+                        poly[i + NUM_LIMBS] = builder.add_const_extension(
+                            poly[i + NUM_LIMBS],
+                            m * F::from_canonical_u64(QUOTIENT_OFFSET),
+                        );
+                        for (j, &q) in quotient.iter().enumerate() {
+                            poly[i + j] = builder.mul_const_add_extension(-m, q, poly[i + j]);
+                        }
+                    }
+                }
+                for (s, p) in sum.iter_mut().zip(poly) {
+                    *s = builder.mul_add_extension(p, flag, *s);
+                }
+            }
+
+            for (k, s) in sum.into_iter().enumerate() {
+                let prev = if k > 0 {
+                    carries[k - 1]
+                } else {
+                    builder.zero_extension()
+                };
+                let expected = match carries.get(k) {
+                    Some(&cur) => builder.mul_const_add_extension(-base, cur, prev),
+                    None => prev,
+                };
+                let constraint =
+                    builder.arithmetic_extension(F::NEG_ONE, F::ONE, slot_active, expected, s);
+                yield_constr.constraint(builder, constraint);
+            }
+        }
+
+        let counter = lv[RANGE_COUNTER];
+        yield_constr.constraint_first_row(builder, counter);
+        let delta = builder.sub_extension(nv[RANGE_COUNTER], counter);
+        let constraint = builder.mul_sub_extension(delta, delta, delta);
+        yield_constr.constraint_transition(builder, constraint);
+        let constraint = builder.add_const_extension(counter, -F::from_canonical_usize(RANGE_MAX));
+        yield_constr.constraint_last_row(builder, constraint);
+    }
+
+    fn constraint_degree(&self) -> usize {
+        3
+    }
+
+    fn lookups(&self) -> Vec<Lookup<F>> {
+        let columns = Column::singles(byte_columns()).collect::<Vec<_>>();
+        let num_columns = columns.len();
+        vec![Lookup {
+            columns,
+            table_column: Column::single(RANGE_COUNTER),
+            frequencies_column: Column::single(RANGE_FREQUENCIES),
+            filter_columns: vec![Default::default(); num_columns],
+        }]
+    }
+
+    fn requires_ctls(&self) -> bool {
+        true
+    }
+}
+
+/// Proves the provided verifications with the [`EcdsaStark`]. The verifications must be in the
+/// order in which they were delegated, see the [module documentation](self).
+pub fn prove_ecdsa_verifications<F, C, const D: usize>(
+    stark: &EcdsaStark<F, D>,
+    config: &StarkConfig,
+    verifications: &[EcdsaVerification],
+    timing: &mut TimingTree,
+) -> Result<StarkProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let values = verifications
+        .iter()
+        .map(EcdsaVerification::values::<F>)
+        .collect::<Vec<_>>();
+    prove_delegated(
+        stark,
+        config,
+        stark.generate_trace(verifications),
+        ecdsa_ctl(),
+        &values,
+        timing,
+    )
+}
+
+/// Verifies an [`EcdsaStark`] proof of exactly the provided verifications.
+pub fn verify_ecdsa_verifications<F, C, const D: usize>(
+    stark: &EcdsaStark<F, D>,
+    proof: &StarkProofWithPublicInputs<F, C, D>,
+    verifications: &[EcdsaVerification],
+    config: &StarkConfig,
+) -> Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let values = verifications
+        .iter()
+        .map(EcdsaVerification::values::<F>)
+        .collect::<Vec<_>>();
+    verify_delegated(stark, proof, ecdsa_ctl(), &values, config)
+}
+
+/// Collects the signature verifications delegated by a plonky2 circuit to an [`EcdsaStark`], and
+/// checks them against a recursively verified proof. See the [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct EcdsaDelegationTarget {
+    verifications: Vec<Vec<Target>>,
+}
+
+impl EcdsaDelegationTarget {
+    /// Creates a new [`EcdsaDelegationTarget`], with no delegated verification.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of verifications delegated so far.
+    pub fn num_verifications(&self) -> usize {
+        self.verifications.len()
+    }
+
+    /// Delegates the verification of the signature `(r, s)` of `msg_hash` under the public key
+    /// `(key_x, key_y)`, all given as 8 little-endian 32-bit limbs, and returns whether it is
+    /// valid. All limbs must be 32-bit values, otherwise the proof will not verify.
+    pub fn verify_signature<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        msg_hash: [Target; 8],
+        r: [Target; 8],
+        s: [Target; 8],
+        key_x: [Target; 8],
+        key_y: [Target; 8],
+    ) -> BoolTarget {
+        // The flag is checked to be boolean by the STARK.
+        let valid = builder.add_virtual_bool_target_unsafe();
+        let inputs = [msg_hash, r, s, key_x, key_y].concat();
+        builder.add_simple_generator(EcdsaVerificationGenerator {
+            inputs: inputs.clone(),
+            valid: valid.target,
+        });
+        self.verifications
+            .push(inputs.into_iter().chain([valid.target]).collect());
+        valid
+    }
+
+    /// Adds an [`EcdsaStark`] proof target, verifies it recursively, and checks that it proves
+    /// exactly the delegated verifications. Returns the proof target, to be set with the output
+    /// of [`prove_ecdsa_verifications`].
+    pub fn verify<F, C, const D: usize>(
+        self,
+        builder: &mut CircuitBuilder<F, D>,
+        stark: &EcdsaStark<F, D>,
+        config: &StarkConfig,
+    ) -> StarkProofWithPublicInputsTarget<D>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let degree_bits = log2_strict(EcdsaStark::<F, D>::num_rows(self.verifications.len()));
+        verify_delegated_circuit::<F, C, _, _, D>(
+            builder,
+            stark,
+            ecdsa_ctl(),
+            &self.verifications,
+            degree_bits,
+            config,
+        )
+    }
+}
+
+/// Computes the validity flag of a delegated verification.
+#[derive(Debug, Default)]
+pub struct EcdsaVerificationGenerator {
+    inputs: Vec<Target>,
+    valid: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for EcdsaVerificationGenerator
+{
+    fn id(&self) -> String {
+        "EcdsaVerificationGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.inputs.clone()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let inputs = witness
+            .get_targets(&self.inputs)
+            .into_iter()
+            .map(|x| x.to_canonical_u64() as u32)
+            .collect::<Vec<_>>();
+        let limbs = |i: usize| -> [u32; 8] { inputs[8 * i..8 * (i + 1)].try_into().unwrap() };
+        let verification = EcdsaVerification {
+            msg_hash: limbs(0),
+            r: limbs(1),
+            s: limbs(2),
+            key_x: limbs(3),
+            key_y: limbs(4),
+        };
+        out_buffer.set_target(self.valid, F::from_bool(verification.is_valid()))
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.inputs)?;
+        dst.write_target(self.valid)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let inputs = src.read_target_vec()?;
+        let valid = src.read_target()?;
+        Ok(Self { inputs, valid })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{vec, vec::Vec};
+
+    use anyhow::Result;
+    use num_bigint::{BigInt, BigUint};
+    use plonky2::field::secp256k1_base::Secp256K1Base;
+    use plonky2::field::secp256k1_scalar::Secp256K1Scalar;
+    use plonky2::field::types::{Field, PrimeField, Sample};
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::config::StarkConfig;
+    use crate::ecdsa::{
+        identity_poly, is_on_curve, point_add, prove_ecdsa_verifications, scalar_mul, to_signed,
+        verify_ecdsa_verifications, write_point, write_slot, AffinePoint, EcdsaDelegationTarget,
+        EcdsaStark, EcdsaVerification, Identity, ACC_X, ACC_Y, ADDEND_X, ADDEND_Y, ECDSA_COLUMNS,
+        G, GH_X, GH_Y, H, H2_X, H2_Y, LADDER_IDENTITIES, LIMB_BITS, NEG_OFFSET_X, NEG_OFFSET_Y,
+        SETUP_QH_IDENTITIES,
+    };
+    use crate::recursive_verifier::set_stark_proof_with_pis_target;
+    use crate::sha256::{sha256_compress, SHA256_IV};
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = EcdsaStark<F, D>;
+
+    fn to_u32_limbs(value: &BigUint) -> [u32; 8] {
+        let mut limbs = value.to_u32_digits();
+        limbs.resize(8, 0);
+        limbs.try_into().unwrap()
+    }
+
+    fn point(x: [u64; 4], y: [u64; 4]) -> AffinePoint {
+        (Secp256K1Base(x), Secp256K1Base(y))
+    }
+
+    /// Signs `msg_hash` with a random key and nonce.
+    fn sign(msg_hash: Secp256K1Scalar) -> EcdsaVerification {
+        let secret_key = Secp256K1Scalar::rand();
+        let (key_x, key_y) = scalar_mul(&secret_key.to_canonical_biguint(), G).unwrap();
+        let nonce = Secp256K1Scalar::rand();
+        let (nonce_x, _) = scalar_mul(&nonce.to_canonical_biguint(), G).unwrap();
+        let r = Secp256K1Scalar::from_noncanonical_biguint(nonce_x.to_canonical_biguint());
+        let s = (msg_hash + r * secret_key) / nonce;
+        EcdsaVerification {
+            msg_hash: to_u32_limbs(&msg_hash.to_canonical_biguint()),
+            r: to_u32_limbs(&r.to_canonical_biguint()),
+            s: to_u32_limbs(&s.to_canonical_biguint()),
+            key_x: to_u32_limbs(&key_x.to_canonical_biguint()),
+            key_y: to_u32_limbs(&key_y.to_canonical_biguint()),
+        }
+    }
+
+    /// A valid signature, and invalid ones covering all the validity conditions.
+    fn test_verifications() -> Vec<EcdsaVerification> {
+        let valid = sign(Secp256K1Scalar::rand());
+
+        let mut wrong_hash = sign(Secp256K1Scalar::rand());
+        wrong_hash.msg_hash[3] ^= 1;
+        let mut zero_r = sign(Secp256K1Scalar::rand());
+        zero_r.r = [0; 8];
+        let mut large_s = sign(Secp256K1Scalar::rand());
+        large_s.s = to_u32_limbs(&Secp256K1Scalar::order());
+        let mut off_curve = sign(Secp256K1Scalar::rand());
+        off_curve.key_y[0] ^= 1;
+
+        // z = -r * d, so that u1 * G + u2 * Q is the point at infinity.
+        let secret_key = Secp256K1Scalar::rand();
+        let (key_x, key_y) = scalar_mul(&secret_key.to_canonical_biguint(), G).unwrap();
+        let r = Secp256K1Scalar::rand();
+        let at_infinity = EcdsaVerification {
+            msg_hash: to_u32_limbs(&(-r * secret_key).to_canonical_biguint()),
+            r: to_u32_limbs(&r.to_canonical_biguint()),
+            s: to_u32_limbs(&Secp256K1Scalar::rand().to_canonical_biguint()),
+            key_x: to_u32_limbs(&key_x.to_canonical_biguint()),
+            key_y: to_u32_limbs(&key_y.to_canonical_biguint()),
+        };
+
+        vec![valid, wrong_hash, zero_r, large_s, off_curve, at_infinity]
+    }
+
+    #[test]
+    fn test_ecdsa_native() {
+        let verifications = test_verifications();
+        assert!(verifications[0].is_valid());
+        for verification in &verifications[1..] {
+            assert!(!verification.is_valid());
+        }
+    }
+
+    #[test]
+    fn test_offset_points() {
+        // SHA-256 of the 32-byte string, as a single padded block.
+        let message = b"plonky2 ECDSA STARK offset point";
+        let mut block = [0; 16];
+        for (word, bytes) in block.iter_mut().zip(message.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        block[8] = 0x80000000;
+        block[15] = 8 * message.len() as u32;
+        let digest = sha256_compress(SHA256_IV, block);
+        let mut x = digest;
+        x.reverse();
+        assert_eq!(H.0.to_canonical_biguint(), BigUint::from_slice(&x));
+        assert!(is_on_curve(H));
+        assert!(!H.1.to_canonical_biguint().bit(0));
+
+        assert_eq!(point_add(Some(H), Some(H)), Some(point(H2_X, H2_Y)));
+        assert_eq!(point_add(Some(G), Some(H)), Some(point(GH_X, GH_Y)));
+        let offset = (BigUint::from(3u32) << 256) - 1u32;
+        let (x, y) = scalar_mul(&offset, H).unwrap();
+        assert_eq!(point(NEG_OFFSET_X, NEG_OFFSET_Y), (x, -y));
+    }
+
+    /// Returns whether `identity` holds on `row`.
+    fn identity_holds(identity: &Identity, row: &[F]) -> bool {
+        let value = identity_poly(identity, row)
+            .iter()
+            .rev()
+            .fold(BigInt::from(0), |acc, &c| (acc << LIMB_BITS) + to_signed(c));
+        match identity.modulus.value() {
+            Some(modulus) => value % BigInt::from(modulus) == BigInt::from(0),
+            None => value == BigInt::from(0),
+        }
+    }
+
+    /// Adding a point to itself with the addition formulas leaves the slope unconstrained, which
+    /// would let a key `Q = H` forge signatures. Check that the inverse witnesses reject it.
+    #[test]
+    fn test_exceptional_additions() {
+        let lambda = Secp256K1Base::rand();
+        let x = lambda * lambda - H.0 - H.0;
+        let y = lambda * (H.0 - x) - H.1;
+
+        // Q + H, with Q = H.
+        let mut row = [F::ZERO; ECDSA_COLUMNS];
+        write_point(&mut row, ADDEND_X, ADDEND_Y, H);
+        for (slot, value) in [lambda, x, y, Secp256K1Base::rand()]
+            .into_iter()
+            .enumerate()
+        {
+            write_slot(&mut row, slot, value);
+        }
+        let (inverse, additions) = SETUP_QH_IDENTITIES.split_last().unwrap();
+        assert!(additions
+            .iter()
+            .all(|identity| identity_holds(identity, &row)));
+        assert!(!identity_holds(inverse, &row));
+
+        // 2 * A + S, with S = A = H.
+        let mut row = [F::ZERO; ECDSA_COLUMNS];
+        write_point(&mut row, ACC_X, ACC_Y, H);
+        write_point(&mut row, ADDEND_X, ADDEND_Y, H);
+        let lambda2 = (H.1 + H.1) / (H.0 - x) - lambda;
+        let x4 = lambda2 * lambda2 - H.0 - x;
+        let y4 = lambda2 * (H.0 - x4) - H.1;
+        let values = [lambda, x, lambda2, x4, y4, Secp256K1Base::rand()];
+        for (slot, value) in values.into_iter().enumerate() {
+            write_slot(&mut row, slot, value);
+        }
+        let (inverse, additions) = LADDER_IDENTITIES.split_last().unwrap();
+        assert!(additions
+            .iter()
+            .all(|identity| identity_holds(identity, &row)));
+        assert!(!identity_holds(inverse, &row));
+    }
+
+    #[test]
+    fn test_ecdsa_stark() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let verifications = test_verifications();
+
+        let proof = prove_ecdsa_verifications::<F, C, D>(
+            &stark,
+            &config,
+            &verifications,
+            &mut TimingTree::default(),
+        )?;
+        verify_ecdsa_verifications(&stark, &proof, &verifications, &config)?;
+
+        let mut other_verifications = verifications.clone();
+        other_verifications[0].msg_hash[0] ^= 1;
+        assert!(verify_ecdsa_verifications(&stark, &proof, &other_verifications, &config).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ecdsa_stark_degree() -> Result<()> {
+        test_stark_low_degree(S::new())
+    }
+
+    #[test]
+    fn test_ecdsa_stark_circuit() -> Result<()> {
+        test_stark_circuit_constraints::<F, C, S, D>(S::new())
+    }
+
+    #[test]
+    fn test_ecdsa_delegation() -> Result<()> {
+        let stark_config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut delegation = EcdsaDelegationTarget::new();
+        let mut pw = PartialWitness::new();
+
+        let verifications = test_verifications()[..2].to_vec();
+        for verification in &verifications {
+            let mut add_limbs = |limbs: [u32; 8]| -> Result<[_; 8]> {
+                let targets = builder.add_virtual_target_arr::<8>();
+                for (&t, limb) in targets.iter().zip(limbs) {
+                    pw.set_target(t, F::from_canonical_u32(limb))?;
+                }
+                Ok(targets)
+            };
+            let msg_hash = add_limbs(verification.msg_hash)?;
+            let r = add_limbs(verification.r)?;
+            let s = add_limbs(verification.s)?;
+            let key_x = add_limbs(verification.key_x)?;
+            let key_y = add_limbs(verification.key_y)?;
+
+            let valid = delegation.verify_signature(&mut builder, msg_hash, r, s, key_x, key_y);
+            let expected = builder.constant_bool(verification.is_valid());
+            builder.connect(valid.target, expected.target);
+        }
+
+        let proof_target = delegation.verify::<F, C, D>(&mut builder, &stark, &stark_config);
+        let zero = builder.zero();
+        let data = builder.build::<C>();
+
+        let proof = prove_ecdsa_verifications::<F, C, D>(
+            &stark,
+            &stark_config,
+            &verifications,
+            &mut TimingTree::default(),
+        )?;
+        let degree_bits = proof.proof.recover_degree_bits(&stark_config);
+        set_stark_proof_with_pis_target(&mut pw, &proof_target, &proof, degree_bits, zero)?;
+
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
@@ -1,5 +1,8 @@
 //! Implementation of constraint evaluation frames for STARKs.
 
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
 /// A trait for viewing an evaluation frame of a STARK table.
 ///
 /// It allows to access the current and next rows at a given step
@@ -72,3 +75,47 @@ impl<T: Copy + Clone + Default, U: Copy + Clone + Default, const N: usize, const
         }
     }
 }
+
+/// An evaluation frame whose width is only known at runtime, e.g. for STARKs of generic virtual
+/// machines whose number of registers is configurable, that implements the
+/// [`StarkEvaluationFrame`] trait.
+///
+/// Its `COLUMNS` and `PUBLIC_INPUTS` are zero, so STARKs using it must override
+/// [`Stark::num_columns`](crate::stark::Stark::num_columns) and
+/// [`Stark::num_public_inputs`](crate::stark::Stark::num_public_inputs), against which the
+/// prover and verifiers check the widths of the values they build frames from.
+#[derive(Debug)]
+pub struct DynamicStarkFrame<T: Copy + Clone + Default, U: Copy + Clone + Default> {
+    local_values: Vec<T>,
+    next_values: Vec<T>,
+    public_inputs: Vec<U>,
+}
+
+impl<T: Copy + Clone + Default, U: Copy + Clone + Default> StarkEvaluationFrame<T, U>
+    for DynamicStarkFrame<T, U>
+{
+    const COLUMNS: usize = 0;
+    const PUBLIC_INPUTS: usize = 0;
+
+    fn get_local_values(&self) -> &[T] {
+        &self.local_values
+    }
+
+    fn get_next_values(&self) -> &[T] {
+        &self.next_values
+    }
+
+    fn get_public_inputs(&self) -> &[U] {
+        &self.public_inputs
+    }
+
+    fn from_values(lv: &[T], nv: &[T], pis: &[U]) -> Self {
+        assert_eq!(lv.len(), nv.len());
+
+        Self {
+            local_values: lv.to_vec(),
+            next_values: nv.to_vec(),
+            public_inputs: pis.to_vec(),
+        }
+    }
+}
@@ -22,7 +22,7 @@ use crate::util::trace_rows_to_poly_values;
 /// Computes a Fibonacci sequence with state `[x0, x1]` using the state transition
 /// `x0' <- x1, x1' <- x0 + x1.
 #[derive(Copy, Clone)]
-struct FibonacciStark<F: RichField + Extendable<D>, const D: usize> {
+pub(crate) struct FibonacciStark<F: RichField + Extendable<D>, const D: usize> {
     num_rows: usize,
     _phantom: PhantomData<F>,
 }
@@ -36,7 +36,7 @@ impl<F: RichField + Extendable<D>, const D: usize> FibonacciStark<F, D> {
     // `num_rows`-th Fibonacci number.
     const PI_INDEX_RES: usize = 2;
 
-    const fn new(num_rows: usize) -> Self {
+    pub(crate) const fn new(num_rows: usize) -> Self {
         Self {
             num_rows,
             _phantom: PhantomData,
@@ -44,7 +44,7 @@ impl<F: RichField + Extendable<D>, const D: usize> FibonacciStark<F, D> {
     }
 
     /// Generate the trace using `x0, x1` as initial state values.
-    fn generate_trace(&self, x0: F, x1: F) -> Vec<PolynomialValues<F>> {
+    pub(crate) fn generate_trace(&self, x0: F, x1: F) -> Vec<PolynomialValues<F>> {
         let trace_rows = (0..self.num_rows)
             .scan([x0, x1], |acc, _| {
                 let tmp = *acc;
@@ -130,6 +130,11 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for FibonacciStar
     fn constraint_degree(&self) -> usize {
         2
     }
+
+    fn trace_len_cofactor(&self) -> usize {
+        // The odd part of the number of rows.
+        self.num_rows >> self.num_rows.trailing_zeros()
+    }
 }
 
 #[cfg(test)]
@@ -150,7 +155,7 @@ mod tests {
 
     use crate::config::StarkConfig;
     use crate::fibonacci_stark::FibonacciStark;
-    use crate::proof::StarkProofWithPublicInputs;
+    use crate::proof::{CompressedStarkProofWithPublicInputs, StarkProofWithPublicInputs};
     use crate::prover::prove;
     use crate::recursive_verifier::{
         add_virtual_stark_proof_with_pis, set_stark_proof_with_pis_target,
@@ -189,6 +194,46 @@ mod tests {
         verify_stark_proof(stark, proof, &config, None)
     }
 
+    #[test]
+    fn test_fibonacci_stark_mixed_radix() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        // Traces of `3 * 2^4` and `5 * 2^3` rows, which would otherwise be padded to `2^6` rows.
+        for num_rows in [3 << 4, 5 << 3] {
+            let public_inputs = [F::ZERO, F::ONE, fibonacci(num_rows - 1, F::ZERO, F::ONE)];
+            let stark = S::new(num_rows);
+            let trace = stark.generate_trace(public_inputs[0], public_inputs[1]);
+            let proof = prove::<F, C, S, D>(
+                stark,
+                &config,
+                trace,
+                &public_inputs,
+                None,
+                &mut TimingTree::default(),
+            )?;
+            assert_eq!(proof.proof.recover_degree_bits(&config), 6);
+            verify_stark_proof(stark, proof.clone(), &config, None)?;
+
+            // The proof doesn't verify for a trace whose length is a power of two.
+            assert!(verify_stark_proof(S::new(1 << 6), proof.clone(), &config, None).is_err());
+
+            recursive_proof::<F, C, S, C, D>(stark, proof, &config, false)?;
+        }
+
+        // The trace length must be a power of two times the cofactor.
+        let stark = S::new(3 << 4);
+        let trace = S::new(5 << 3).generate_trace(F::ZERO, F::ONE);
+        assert!(prove::<F, C, S, D>(
+            stark,
+            &config,
+            trace,
+            &[F::ZERO, F::ONE, F::ZERO],
+            None,
+            &mut TimingTree::default()
+        )
+        .is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_fibonacci_stark_degree() -> Result<()> {
         let num_rows = 1 << 5;
@@ -203,6 +248,40 @@ mod tests {
         test_stark_circuit_constraints::<F, C, S, D>(stark)
     }
 
+    #[test]
+    fn test_fibonacci_stark_proof_serialization() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let num_rows = 1 << 5;
+        let public_inputs = [F::ZERO, F::ONE, fibonacci(num_rows - 1, F::ZERO, F::ONE)];
+
+        let stark = S::new(num_rows);
+        let trace = stark.generate_trace(public_inputs[0], public_inputs[1]);
+        let proof = prove::<F, C, S, D>(
+            stark,
+            &config,
+            trace,
+            &public_inputs,
+            None,
+            &mut TimingTree::default(),
+        )?;
+
+        let proof_bytes = proof.to_bytes();
+        assert_eq!(
+            StarkProofWithPublicInputs::from_bytes(proof_bytes.clone(), &config)?,
+            proof
+        );
+
+        let compressed_proof = proof.clone().compress(&config, None);
+        let compressed_proof_bytes = compressed_proof.to_bytes();
+        assert!(compressed_proof_bytes.len() < proof_bytes.len());
+        let compressed_proof =
+            CompressedStarkProofWithPublicInputs::from_bytes(compressed_proof_bytes, &config)?;
+        let decompressed_proof = compressed_proof.decompress(&stark, &config, None)?;
+        assert_eq!(decompressed_proof, proof);
+
+        verify_stark_proof(stark, decompressed_proof, &config, None)
+    }
+
     #[test]
     fn test_recursive_stark_verifier() -> Result<()> {
         init_logger();
@@ -0,0 +1,305 @@
+//! An example of generating and verifying a STARK with a fixed column, committed to once in a
+//! proving key, which is used both in constraints and as a lookup table.
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+use crate::lookup::{Column, Lookup};
+use crate::stark::Stark;
+use crate::util::trace_rows_to_poly_values;
+
+/// Range-checks values against the fixed table `0, 1, ..., num_rows - 1`, and checks that the
+/// second column holds the squares of the table.
+#[derive(Copy, Clone)]
+struct FixedColumnStark<F: RichField + Extendable<D>, const D: usize> {
+    num_rows: usize,
+    _phantom: PhantomData<F>,
+}
+
+/// The range-checked values.
+const VALUE: usize = 0;
+/// The squares of the fixed table.
+const SQUARE: usize = 1;
+/// The frequencies of the table in the range-checked values.
+const FREQUENCY: usize = 2;
+/// The fixed table, after the trace columns.
+const TABLE: usize = 3;
+
+const TRACE_COLUMNS: usize = 3;
+const NUM_FIXED_COLUMNS: usize = 1;
+/// Evaluation frames hold the trace columns, followed by the fixed columns.
+const FRAME_COLUMNS: usize = TRACE_COLUMNS + NUM_FIXED_COLUMNS;
+const NUM_PUBLIC_INPUTS: usize = 0;
+
+impl<F: RichField + Extendable<D>, const D: usize> FixedColumnStark<F, D> {
+    const fn new(num_rows: usize) -> Self {
+        Self {
+            num_rows,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Generate the trace range-checking `values`, which must be smaller than `num_rows`, padded
+    /// with zeros.
+    fn generate_trace(&self, values: &[u64]) -> Vec<PolynomialValues<F>> {
+        let mut values = values.to_vec();
+        values.resize(self.num_rows, 0);
+        let mut frequencies = vec![0; self.num_rows];
+        for &value in &values {
+            frequencies[value as usize] += 1;
+        }
+        let trace_rows = (0..self.num_rows)
+            .map(|i| {
+                [
+                    F::from_canonical_u64(values[i]),
+                    F::from_canonical_usize(i * i),
+                    F::from_canonical_usize(frequencies[i]),
+                ]
+            })
+            .collect::<Vec<_>>();
+        trace_rows_to_poly_values(trace_rows)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for FixedColumnStark<F, D> {
+    const FIXED_COLUMNS: usize = NUM_FIXED_COLUMNS;
+
+    type EvaluationFrame<FE, P, const D2: usize>
+        = StarkFrame<P, P::Scalar, FRAME_COLUMNS, NUM_PUBLIC_INPUTS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget =
+        StarkFrame<ExtensionTarget<D>, ExtensionTarget<D>, FRAME_COLUMNS, NUM_PUBLIC_INPUTS>;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let local_values = vars.get_local_values();
+        yield_constr.constraint(local_values[TABLE] * local_values[TABLE] - local_values[SQUARE]);
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let local_values = vars.get_local_values();
+        let constraint = builder.mul_sub_extension(
+            local_values[TABLE],
+            local_values[TABLE],
+            local_values[SQUARE],
+        );
+        yield_constr.constraint(builder, constraint);
+    }
+
+    fn constraint_degree(&self) -> usize {
+        2
+    }
+
+    fn trace_len_cofactor(&self) -> usize {
+        // The odd part of the number of rows.
+        self.num_rows >> self.num_rows.trailing_zeros()
+    }
+
+    fn fixed_columns(&self) -> Vec<PolynomialValues<F>> {
+        vec![PolynomialValues::new(
+            (0..self.num_rows).map(F::from_canonical_usize).collect(),
+        )]
+    }
+
+    fn lookups(&self) -> Vec<Lookup<F>> {
+        vec![Lookup {
+            columns: vec![Column::single(VALUE)],
+            table_column: Column::single(TABLE),
+            frequencies_column: Column::single(FREQUENCY),
+            filter_columns: vec![Default::default()],
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2::hash::hash_types::HashOut;
+    use plonky2::iop::witness::PartialWitness;
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use super::*;
+    use crate::config::StarkConfig;
+    use crate::keys::StarkProvingKey;
+    use crate::proof::CompressedStarkProofWithPublicInputs;
+    use crate::prover::{prove, prove_with_key};
+    use crate::recursive_verifier::{
+        add_virtual_stark_proof_with_pis, set_stark_proof_with_pis_target,
+        verify_stark_proof_with_key_circuit,
+    };
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+    use crate::verifier::{verify_stark_proof, verify_stark_proof_with_key};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = FixedColumnStark<F, D>;
+
+    const NUM_ROWS: usize = 1 << 5;
+
+    #[test]
+    fn test_fixed_column_stark() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new(NUM_ROWS);
+        let proving_key =
+            StarkProvingKey::<F, C, D>::new(&stark, &config, &mut TimingTree::default());
+        let verifying_key = proving_key.verifying_key();
+
+        // The same key is used for several traces.
+        let mut proofs = Vec::new();
+        for values in [vec![3, 1, 4, 1, 5], vec![31; NUM_ROWS]] {
+            let trace = stark.generate_trace(&values);
+            let proof = prove_with_key::<F, C, S, D>(
+                stark,
+                &proving_key,
+                &config,
+                trace,
+                &[],
+                None,
+                &mut TimingTree::default(),
+            )?;
+            verify_stark_proof_with_key(stark, &verifying_key, proof.clone(), &config, None)?;
+            proofs.push(proof);
+        }
+
+        // Proofs don't verify without the key, or with another key.
+        assert!(verify_stark_proof(stark, proofs[0].clone(), &config, None).is_err());
+        let mut wrong_key = verifying_key.clone();
+        wrong_key.fixed_cap.0[0] = HashOut::ZERO;
+        assert!(
+            verify_stark_proof_with_key(stark, &wrong_key, proofs[0].clone(), &config, None)
+                .is_err()
+        );
+
+        // A key is needed to prove.
+        let trace = stark.generate_trace(&[0]);
+        assert!(
+            prove::<F, C, S, D>(stark, &config, trace, &[], None, &mut TimingTree::default())
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed_column_stark_mixed_radix() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        // A table of `3 * 2^4` rows.
+        let stark = S::new(3 << 4);
+        let proving_key =
+            StarkProvingKey::<F, C, D>::new(&stark, &config, &mut TimingTree::default());
+        let verifying_key = proving_key.verifying_key();
+        assert_eq!(verifying_key.degree_bits, 6);
+
+        let trace = stark.generate_trace(&[47, 1, 4, 1, 5]);
+        let proof = prove_with_key::<F, C, S, D>(
+            stark,
+            &proving_key,
+            &config,
+            trace,
+            &[],
+            None,
+            &mut TimingTree::default(),
+        )?;
+        verify_stark_proof_with_key(stark, &verifying_key, proof, &config, None)
+    }
+
+    #[test]
+    fn test_fixed_column_stark_proof_compression() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new(NUM_ROWS);
+        let proving_key =
+            StarkProvingKey::<F, C, D>::new(&stark, &config, &mut TimingTree::default());
+        let verifying_key = proving_key.verifying_key();
+        let trace = stark.generate_trace(&[1, 4, 1, 4, 2, 1]);
+        let proof = prove_with_key::<F, C, S, D>(
+            stark,
+            &proving_key,
+            &config,
+            trace,
+            &[],
+            None,
+            &mut TimingTree::default(),
+        )?;
+
+        let compressed_proof = proof.clone().compress(&config, Some(&verifying_key));
+        let compressed_proof =
+            CompressedStarkProofWithPublicInputs::from_bytes(compressed_proof.to_bytes(), &config)?;
+        let decompressed_proof =
+            compressed_proof.decompress(&stark, &config, Some(&verifying_key))?;
+        assert_eq!(decompressed_proof, proof);
+        verify_stark_proof_with_key(stark, &verifying_key, decompressed_proof, &config, None)
+    }
+
+    #[test]
+    fn test_fixed_column_stark_degree() -> Result<()> {
+        test_stark_low_degree(S::new(NUM_ROWS))
+    }
+
+    #[test]
+    fn test_fixed_column_stark_circuit() -> Result<()> {
+        test_stark_circuit_constraints::<F, C, S, D>(S::new(NUM_ROWS))
+    }
+
+    #[test]
+    fn test_recursive_fixed_column_stark_verifier() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new(NUM_ROWS);
+        let proving_key =
+            StarkProvingKey::<F, C, D>::new(&stark, &config, &mut TimingTree::default());
+        let verifying_key = proving_key.verifying_key();
+        let trace = stark.generate_trace(&[2, 7, 1, 8]);
+        let proof = prove_with_key::<F, C, S, D>(
+            stark,
+            &proving_key,
+            &config,
+            trace,
+            &[],
+            None,
+            &mut TimingTree::default(),
+        )?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut pw = PartialWitness::new();
+        let degree_bits = verifying_key.degree_bits;
+        let pt = add_virtual_stark_proof_with_pis(&mut builder, &stark, &config, degree_bits, 0, 0);
+        set_stark_proof_with_pis_target(&mut pw, &pt, &proof, degree_bits, builder.zero())?;
+        verify_stark_proof_with_key_circuit::<F, C, S, D>(
+            &mut builder,
+            stark,
+            &verifying_key,
+            pt,
+            &config,
+        );
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
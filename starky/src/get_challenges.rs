@@ -1,6 +1,9 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+
 use plonky2::field::extension::Extendable;
 use plonky2::field::polynomial::PolynomialCoeffs;
-use plonky2::fri::proof::{FriProof, FriProofTarget};
+use plonky2::fri::proof::{CompressedFriProof, FriProof, FriProofTarget};
 use plonky2::fri::prover::final_poly_coeff_len;
 use plonky2::fri::FriParams;
 use plonky2::gadgets::polynomial::PolynomialCoeffsExtTarget;
@@ -10,13 +13,16 @@ use plonky2::iop::challenger::{Challenger, RecursiveChallenger};
 use plonky2::iop::target::Target;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::plonk::proof::FriInferredElements;
 
 use crate::config::StarkConfig;
+use crate::keys::StarkVerifyingKey;
 use crate::lookup::{
     get_grand_product_challenge_set, get_grand_product_challenge_set_target,
     GrandProductChallengeSet,
 };
 use crate::proof::*;
+use crate::stark::Stark;
 
 /// Generates challenges for a STARK proof from a challenger and given
 /// all the arguments needed to update the challenger state.
@@ -189,6 +195,78 @@ where
     }
 }
 
+impl<F, C, const D: usize> CompressedStarkProofWithPublicInputs<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    /// Computes all Fiat-Shamir challenges used in the proof of a standalone STARK, whose fixed
+    /// columns, if any, are committed to in `verifying_key`.
+    pub(crate) fn get_challenges(
+        &self,
+        config: &StarkConfig,
+        verifying_key: Option<&StarkVerifyingKey<F, C, D>>,
+    ) -> StarkProofChallenges<F, D> {
+        let mut challenger = Challenger::<F, C::Hasher>::new();
+        if let Some(verifying_key) = verifying_key {
+            challenger.observe_cap(&verifying_key.fixed_cap);
+        }
+        challenger.observe_elements(&self.public_inputs);
+
+        let CompressedStarkProof {
+            degree_bits,
+            trace_cap,
+            auxiliary_polys_cap,
+            quotient_polys_cap,
+            openings,
+            opening_proof:
+                CompressedFriProof {
+                    commit_phase_merkle_caps,
+                    final_poly,
+                    pow_witness,
+                    ..
+                },
+        } = &self.proof;
+
+        get_challenges::<F, C, D>(
+            &mut challenger,
+            None,
+            Some(trace_cap),
+            auxiliary_polys_cap.as_ref(),
+            quotient_polys_cap.as_ref(),
+            openings,
+            commit_phase_merkle_caps,
+            final_poly,
+            *pow_witness,
+            config,
+            *degree_bits,
+            None,
+        )
+    }
+
+    /// Computes all coset elements that can be inferred in the FRI reduction steps.
+    pub(crate) fn get_inferred_elements<S: Stark<F, D>>(
+        &self,
+        stark: &S,
+        challenges: &StarkProofChallenges<F, D>,
+        config: &StarkConfig,
+    ) -> FriInferredElements<F, D> {
+        let degree_bits = self.proof.degree_bits;
+        self.proof.opening_proof.get_inferred_elements::<C>(
+            &stark.fri_instance(
+                challenges.stark_zeta,
+                F::primitive_root_of_unity(degree_bits),
+                0,
+                vec![],
+                config,
+            ),
+            &self.proof.openings.to_fri_openings(),
+            &challenges.fri_challenges,
+            &config.fri_params(degree_bits),
+        )
+    }
+}
+
 /// Circuit version of `get_challenges`, with the same flexibility around
 /// `trace_cap` being passed as an `Option`.
 fn get_challenges_target<F, C, const D: usize>(
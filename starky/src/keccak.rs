@@ -0,0 +1,1068 @@
+//! A general-purpose STARK proving Keccak-f\[1600\] permutations, to which plonky2 circuits can
+//! delegate Keccak hashing.
+//!
+//! Each permutation is exposed through a cross-table lookup on the tuple `(input, output)`, where
+//! both states are given as 50 little-endian 32-bit limbs: lane `x + 5 * y` of the state is made
+//! of limbs `2 * (x + 5 * y)` (low half) and `2 * (x + 5 * y) + 1` (high half). The STARK knows
+//! nothing about the sponge construction built on top of it, nor about the tables looking into
+//! it, so it can be reused by any project needing Keccak.
+//!
+//! # Sponge layer
+//!
+//! Hashing a message with Keccak-256 absorbs it in blocks of [`KECCAK_RATE_BYTES`] bytes, each of
+//! which is XORed into the state before permuting it, and then squeezes the digest out of the
+//! first 32 bytes of the final state. This layer is implemented outside the STARK:
+//! - natively by [`KeccakPermutationLog::keccak256`], which records the permutations it performs,
+//! - in circuits by [`KeccakDelegationTarget::keccak256`], which does the absorption and padding
+//!   with gates, and delegates the permutations.
+//!
+//! Other sponge-based constructions can use [`KeccakPermutationLog::permute`] and
+//! [`KeccakDelegationTarget::permute`] directly.
+//!
+//! # Delegating permutations from a plonky2 circuit
+//!
+//! This follows the pattern of the [`delegation`](crate::delegation) module:
+//!
+//! 1. While building the circuit, delegate permutations with a [`KeccakDelegationTarget`]. Its
+//!    outputs are unconstrained until step 2.
+//! 2. Call [`KeccakDelegationTarget::verify`], which adds a STARK proof target, verifies it
+//!    recursively and connects the delegated permutations to the STARK's CTL.
+//! 3. When proving, record the same permutations in the same order with a
+//!    [`KeccakPermutationLog`], prove them with [`prove_keccak_permutations`], and set the
+//!    returned proof in the witness with
+//!    [`set_stark_proof_with_pis_target`](crate::recursive_verifier::set_stark_proof_with_pis_target).
+//!
+//! The witness generator used by [`KeccakDelegationTarget`] is not part of plonky2's default
+//! generator serializer, so circuits using it need a custom one to be serialized.
+//!
+//! # Trace layout
+//!
+//! Each permutation uses 24 consecutive rows, one per round, marked by one-hot round flags. Each
+//! row holds the state `A` at the start of the round as limbs, along with the bits of the
+//! intermediate values of the θ step, the limbs of the state `A''` after the ρ, π and χ steps,
+//! and the result of the ι step. The trace is padded with zero rows, which satisfy all the
+//! constraints.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use anyhow::Result;
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::util::log2_strict;
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+use plonky2::util::timing::TimingTree;
+
+use crate::config::StarkConfig;
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::cross_table_lookup::{CrossTableLookup, TableWithColumns};
+use crate::delegation::{prove_delegated, verify_delegated, verify_delegated_circuit};
+use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+use crate::lookup::{Column, Filter};
+use crate::proof::{StarkProofWithPublicInputs, StarkProofWithPublicInputsTarget};
+use crate::stark::Stark;
+use crate::util::trace_rows_to_poly_values;
+
+/// The number of bytes absorbed per permutation by Keccak-256.
+pub const KECCAK_RATE_BYTES: usize = 136;
+/// The number of 32-bit limbs of a Keccak state.
+pub const KECCAK_STATE_LIMBS: usize = 50;
+
+const NUM_ROUNDS: usize = 24;
+const LANE_BITS: usize = 64;
+
+/// The rotation offsets of the ρ step, indexed by `x` then `y`.
+const RHO_OFFSETS: [[u32; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// The round constants of the ι step.
+const ROUND_CONSTANTS: [u64; NUM_ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808A,
+    0x8000000080008000,
+    0x000000000000808B,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008A,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000A,
+    0x000000008000808B,
+    0x800000000000008B,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800A,
+    0x800000008000000A,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// One-hot flags marking the round of a row within its permutation.
+const ROUND_FLAGS: Range<usize> = 0..NUM_ROUNDS;
+/// The input of the permutation, constant within a permutation.
+const PREIMAGE: Range<usize> = ROUND_FLAGS.end..ROUND_FLAGS.end + KECCAK_STATE_LIMBS;
+/// The state `A` at the start of the round.
+const A: Range<usize> = PREIMAGE.end..PREIMAGE.end + KECCAK_STATE_LIMBS;
+/// Bits of `C[x] = xor_y A[x, y]`.
+const C_START: usize = A.end;
+/// Bits of `C'[x] = xor(C[x], C[x - 1], rotl(C[x + 1], 1))`.
+const C_PRIME_START: usize = C_START + 5 * LANE_BITS;
+/// Bits of `A'[x, y] = xor(A[x, y], C[x - 1], rotl(C[x + 1], 1))`, the state after the θ step.
+const A_PRIME_START: usize = C_PRIME_START + 5 * LANE_BITS;
+/// The state `A''` after the ρ, π and χ steps.
+const A_PRIME_PRIME: Range<usize> =
+    A_PRIME_START + 25 * LANE_BITS..A_PRIME_START + 25 * LANE_BITS + KECCAK_STATE_LIMBS;
+/// Bits of `A''[0, 0]`.
+const A_PRIME_PRIME_0_0_BITS: Range<usize> = A_PRIME_PRIME.end..A_PRIME_PRIME.end + LANE_BITS;
+/// `A'''[0, 0] = A''[0, 0] ^ RC`, the only lane modified by the ι step.
+const A_PRIME_PRIME_PRIME_0_0: Range<usize> =
+    A_PRIME_PRIME_0_0_BITS.end..A_PRIME_PRIME_0_0_BITS.end + 2;
+
+const KECCAK_COLUMNS: usize = A_PRIME_PRIME_PRIME_0_0.end;
+const KECCAK_PUBLIC_INPUTS: usize = 0;
+
+const fn c(x: usize, z: usize) -> usize {
+    C_START + x * LANE_BITS + z
+}
+
+const fn c_prime(x: usize, z: usize) -> usize {
+    C_PRIME_START + x * LANE_BITS + z
+}
+
+const fn a_prime(x: usize, y: usize, z: usize) -> usize {
+    A_PRIME_START + (x + 5 * y) * LANE_BITS + z
+}
+
+/// Returns the column of the low limb of lane `(x, y)` in a state starting at `start`.
+const fn lane(start: usize, x: usize, y: usize) -> usize {
+    start + 2 * (x + 5 * y)
+}
+
+/// Returns the `A'` bit column holding bit `z` of `B[x, y]`, the state after the ρ and π steps.
+const fn b(x: usize, y: usize, z: usize) -> usize {
+    let a = (x + 3 * y) % 5;
+    let rot = RHO_OFFSETS[a][x] as usize;
+    a_prime(a, x, (z + LANE_BITS - rot) % LANE_BITS)
+}
+
+/// Returns the column holding the low limb of lane `(x, y)` of the output of the round.
+const fn round_output(x: usize, y: usize) -> usize {
+    if x == 0 && y == 0 {
+        A_PRIME_PRIME_PRIME_0_0.start
+    } else {
+        lane(A_PRIME_PRIME.start, x, y)
+    }
+}
+
+/// Applies the Keccak-f\[1600\] permutation to `state`, where lane `(x, y)` is `state[x + 5 * y]`.
+pub fn keccakf(state: [u64; 25]) -> [u64; 25] {
+    (0..NUM_ROUNDS).fold(state, |a, round| keccakf_round(a, round).3)
+}
+
+/// Applies a round of Keccak-f\[1600\], returning `C`, `A'`, `A''` and the output of the round.
+fn keccakf_round(a: [u64; 25], round: usize) -> ([u64; 5], [u64; 25], [u64; 25], [u64; 25]) {
+    let c: [u64; 5] = core::array::from_fn(|x| (0..5).fold(0, |acc, y| acc ^ a[x + 5 * y]));
+    let a_prime: [u64; 25] = core::array::from_fn(|i| {
+        let x = i % 5;
+        a[i] ^ c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1)
+    });
+    let b: [u64; 25] = core::array::from_fn(|i| {
+        let (x, y) = (i % 5, i / 5);
+        let a = (x + 3 * y) % 5;
+        a_prime[a + 5 * x].rotate_left(RHO_OFFSETS[a][x])
+    });
+    let a_prime_prime: [u64; 25] = core::array::from_fn(|i| {
+        let (x, y) = (i % 5, i / 5);
+        b[i] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y])
+    });
+    let mut output = a_prime_prime;
+    output[0] ^= ROUND_CONSTANTS[round];
+    (c, a_prime, a_prime_prime, output)
+}
+
+fn lanes_to_limbs<F: Field>(lanes: &[u64; 25]) -> impl Iterator<Item = F> + '_ {
+    lanes
+        .iter()
+        .flat_map(|&lane| [lane as u32, (lane >> 32) as u32])
+        .map(F::from_canonical_u32)
+}
+
+fn limbs_to_lanes(limbs: &[u32]) -> [u64; 25] {
+    core::array::from_fn(|i| limbs[2 * i] as u64 | ((limbs[2 * i + 1] as u64) << 32))
+}
+
+/// A single Keccak-f\[1600\] permutation proven by the [`KeccakPermutationStark`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeccakPermutation {
+    /// The input state, where lane `(x, y)` is `input[x + 5 * y]`.
+    pub input: [u64; 25],
+}
+
+impl KeccakPermutation {
+    /// Returns the output state of this permutation.
+    pub fn output(&self) -> [u64; 25] {
+        keccakf(self.input)
+    }
+
+    /// Returns the `(input, output)` tuple representing this permutation in the cross-table
+    /// lookup, as 32-bit limbs.
+    pub fn values<F: Field>(&self) -> Vec<F> {
+        let output = self.output();
+        lanes_to_limbs(&self.input)
+            .chain(lanes_to_limbs(&output))
+            .collect()
+    }
+}
+
+/// Records Keccak-f\[1600\] permutations natively, mirroring [`KeccakDelegationTarget`] so that the
+/// resulting permutations match the ones delegated by a circuit.
+#[derive(Clone, Debug, Default)]
+pub struct KeccakPermutationLog {
+    permutations: Vec<KeccakPermutation>,
+}
+
+impl KeccakPermutationLog {
+    /// Creates an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a permutation of `state`, returning its output.
+    pub fn permute(&mut self, state: [u64; 25]) -> [u64; 25] {
+        let permutation = KeccakPermutation { input: state };
+        self.permutations.push(permutation);
+        permutation.output()
+    }
+
+    /// Hashes `input` with Keccak-256, recording the permutations of the sponge.
+    pub fn keccak256(&mut self, input: &[u8]) -> [u8; 32] {
+        let mut state = [0u64; 25];
+        for block in keccak_padded_blocks(input) {
+            for (lane, bytes) in state.iter_mut().zip(block.chunks_exact(8)) {
+                *lane ^= u64::from_le_bytes(bytes.try_into().unwrap());
+            }
+            state = self.permute(state);
+        }
+        core::array::from_fn(|i| (state[i / 8] >> (8 * (i % 8))) as u8)
+    }
+
+    /// Returns the recorded permutations, in the order in which they must be proven.
+    pub fn into_permutations(self) -> Vec<KeccakPermutation> {
+        self.permutations
+    }
+}
+
+/// Pads `input` with the Keccak `10*1` rule, and splits it into blocks of [`KECCAK_RATE_BYTES`].
+fn keccak_padded_blocks(input: &[u8]) -> impl Iterator<Item = [u8; KECCAK_RATE_BYTES]> + '_ {
+    let num_blocks = input.len() / KECCAK_RATE_BYTES + 1;
+    (0..num_blocks).map(move |i| {
+        let start = i * KECCAK_RATE_BYTES;
+        let end = input.len().min(start + KECCAK_RATE_BYTES);
+        let mut block = [0; KECCAK_RATE_BYTES];
+        block[..end - start].copy_from_slice(&input[start..end]);
+        if i == num_blocks - 1 {
+            block[end - start] |= 0x01;
+            block[KECCAK_RATE_BYTES - 1] |= 0x80;
+        }
+        block
+    })
+}
+
+/// A STARK proving a batch of [`KeccakPermutation`]s.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct KeccakPermutationStark<F: RichField + Extendable<D>, const D: usize> {
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> KeccakPermutationStark<F, D> {
+    /// Creates a new [`KeccakPermutationStark`].
+    pub const fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of rows of a trace proving `num_permutations` permutations.
+    pub fn num_rows(num_permutations: usize) -> usize {
+        (num_permutations * NUM_ROUNDS)
+            .next_power_of_two()
+            .max(NUM_ROUNDS.next_power_of_two())
+    }
+
+    /// Generates the trace proving the provided permutations, in order.
+    pub fn generate_trace(&self, permutations: &[KeccakPermutation]) -> Vec<PolynomialValues<F>> {
+        let num_rows = Self::num_rows(permutations.len());
+        let mut rows = vec![[F::ZERO; KECCAK_COLUMNS]; num_rows];
+        for (permutation, rows) in permutations.iter().zip(rows.chunks_exact_mut(NUM_ROUNDS)) {
+            Self::generate_permutation(permutation, rows);
+        }
+        trace_rows_to_poly_values(rows)
+    }
+
+    fn generate_permutation(permutation: &KeccakPermutation, rows: &mut [[F; KECCAK_COLUMNS]]) {
+        let mut state = permutation.input;
+        for (round, row) in rows.iter_mut().enumerate() {
+            row[ROUND_FLAGS.start + round] = F::ONE;
+            for (col, limb) in PREIMAGE.zip(lanes_to_limbs(&permutation.input)) {
+                row[col] = limb;
+            }
+            for (col, limb) in A.zip(lanes_to_limbs(&state)) {
+                row[col] = limb;
+            }
+
+            let (c_values, a_prime_values, a_prime_prime_values, output) =
+                keccakf_round(state, round);
+            for x in 0..5 {
+                let c_prime_value =
+                    c_values[x] ^ c_values[(x + 4) % 5] ^ c_values[(x + 1) % 5].rotate_left(1);
+                for z in 0..LANE_BITS {
+                    row[c(x, z)] = F::from_bool((c_values[x] >> z) & 1 == 1);
+                    row[c_prime(x, z)] = F::from_bool((c_prime_value >> z) & 1 == 1);
+                    for y in 0..5 {
+                        row[a_prime(x, y, z)] =
+                            F::from_bool((a_prime_values[x + 5 * y] >> z) & 1 == 1);
+                    }
+                }
+            }
+            for (col, limb) in A_PRIME_PRIME.zip(lanes_to_limbs(&a_prime_prime_values)) {
+                row[col] = limb;
+            }
+            for (z, col) in A_PRIME_PRIME_0_0_BITS.enumerate() {
+                row[col] = F::from_bool((a_prime_prime_values[0] >> z) & 1 == 1);
+            }
+            row[A_PRIME_PRIME_PRIME_0_0.start] = F::from_canonical_u32(output[0] as u32);
+            row[A_PRIME_PRIME_PRIME_0_0.start + 1] =
+                F::from_canonical_u32((output[0] >> 32) as u32);
+
+            state = output;
+        }
+    }
+}
+
+/// Returns the cross-table lookup exposing the `(input, output)` tuples of all the permutations
+/// in the [`KeccakPermutationStark`] trace. It has no looking table: the looking side is provided
+/// as an extra looking sum over the delegated permutations.
+pub fn keccak_ctl<F: Field>() -> CrossTableLookup<F> {
+    let output_columns = (0..25).flat_map(|i| {
+        let col = round_output(i % 5, i / 5);
+        [Column::single(col), Column::single(col + 1)]
+    });
+    let columns = Column::singles(PREIMAGE).chain(output_columns).collect();
+    let filter = Filter::new_simple(Column::single(ROUND_FLAGS.end - 1));
+    CrossTableLookup::new(vec![], TableWithColumns::new(0, columns, filter))
+}
+
+fn xor<P: PackedField>(x: P, y: P) -> P {
+    x + y - x * y * P::Scalar::TWO
+}
+
+fn xor3<P: PackedField>(x: P, y: P, z: P) -> P {
+    xor(xor(x, y), z)
+}
+
+/// Computes `x & !y`.
+fn andn<P: PackedField>(x: P, y: P) -> P {
+    (P::ONES - x) * y
+}
+
+/// Returns the low and high limbs of the lane whose bit `z` is given by `get_bit(z)`.
+fn limbs_from_bits<P: PackedField>(get_bit: impl Fn(usize) -> P) -> [P; 2] {
+    [0, 1].map(|half| {
+        (0..32).rev().fold(P::ZEROS, |acc, z| {
+            acc * P::Scalar::TWO + get_bit(32 * half + z)
+        })
+    })
+}
+
+fn xor_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: ExtensionTarget<D>,
+    y: ExtensionTarget<D>,
+) -> ExtensionTarget<D> {
+    let sum = builder.add_extension(x, y);
+    builder.arithmetic_extension(-F::TWO, F::ONE, x, y, sum)
+}
+
+fn xor3_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: ExtensionTarget<D>,
+    y: ExtensionTarget<D>,
+    z: ExtensionTarget<D>,
+) -> ExtensionTarget<D> {
+    let x_xor_y = xor_circuit(builder, x, y);
+    xor_circuit(builder, x_xor_y, z)
+}
+
+fn andn_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: ExtensionTarget<D>,
+    y: ExtensionTarget<D>,
+) -> ExtensionTarget<D> {
+    // y - x * y
+    builder.arithmetic_extension(F::NEG_ONE, F::ONE, x, y, y)
+}
+
+fn limbs_from_bits_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    mut get_bit: impl FnMut(&mut CircuitBuilder<F, D>, usize) -> ExtensionTarget<D>,
+) -> [ExtensionTarget<D>; 2] {
+    [0, 1].map(|half| {
+        let zero = builder.zero_extension();
+        (0..32).rev().fold(zero, |acc, z| {
+            let bit = get_bit(builder, 32 * half + z);
+            builder.mul_const_add_extension(F::TWO, acc, bit)
+        })
+    })
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for KeccakPermutationStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize>
+        = StarkFrame<P, P::Scalar, KECCAK_COLUMNS, KECCAK_PUBLIC_INPUTS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget =
+        StarkFrame<ExtensionTarget<D>, ExtensionTarget<D>, KECCAK_COLUMNS, KECCAK_PUBLIC_INPUTS>;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let lv = vars.get_local_values();
+        let nv = vars.get_next_values();
+
+        let bits = ROUND_FLAGS
+            .chain(C_START..A_PRIME_PRIME.start)
+            .chain(A_PRIME_PRIME_0_0_BITS);
+        for col in bits {
+            yield_constr.constraint(lv[col] * (lv[col] - P::ONES));
+        }
+
+        // Round flags are one-hot within permutations, and zero on padding rows. A permutation
+        // can only start on the first row, or right after another permutation or padding row.
+        let flags = &lv[ROUND_FLAGS];
+        let any_flag = flags.iter().fold(P::ZEROS, |acc, &f| acc + f);
+        yield_constr.constraint(any_flag * (any_flag - P::ONES));
+        yield_constr.constraint_first_row(any_flag - flags[0]);
+        for round in 0..NUM_ROUNDS - 1 {
+            yield_constr.constraint_transition(nv[ROUND_FLAGS.start + round + 1] - flags[round]);
+        }
+        let not_last_round = any_flag - flags[NUM_ROUNDS - 1];
+
+        // The input is loaded on the first round, and stays constant within a permutation.
+        for (a, preimage) in A.zip(PREIMAGE) {
+            yield_constr.constraint(flags[0] * (lv[a] - lv[preimage]));
+            yield_constr.constraint_transition(not_last_round * (nv[preimage] - lv[preimage]));
+        }
+
+        // C'[x, z] = xor(C[x, z], C[x - 1, z], C[x + 1, z - 1]).
+        for x in 0..5 {
+            for z in 0..LANE_BITS {
+                let computed = xor3(
+                    lv[c(x, z)],
+                    lv[c((x + 4) % 5, z)],
+                    lv[c((x + 1) % 5, (z + LANE_BITS - 1) % LANE_BITS)],
+                );
+                yield_constr.constraint(lv[c_prime(x, z)] - computed);
+            }
+        }
+
+        // A[x, y, z] = xor(A'[x, y, z], C[x - 1, z], C[x + 1, z - 1])
+        //            = xor(A'[x, y, z], C[x, z], C'[x, z]).
+        for x in 0..5 {
+            for y in 0..5 {
+                let computed =
+                    limbs_from_bits(|z| xor3(lv[a_prime(x, y, z)], lv[c(x, z)], lv[c_prime(x, z)]));
+                let a = lane(A.start, x, y);
+                yield_constr.constraint(computed[0] - lv[a]);
+                yield_constr.constraint(computed[1] - lv[a + 1]);
+            }
+        }
+
+        // xor_y A'[x, y, z] = C'[x, z], which together with the two checks above implies that
+        // C[x] is indeed the parity of the column x of A. Since all values are bits, this is
+        // equivalent to `diff * (diff - 2) * (diff - 4) = 0`, where
+        // `diff = sum_y A'[x, y, z] - C'[x, z]`.
+        for x in 0..5 {
+            for z in 0..LANE_BITS {
+                let sum = (0..5).fold(P::ZEROS, |acc, y| acc + lv[a_prime(x, y, z)]);
+                let diff = sum - lv[c_prime(x, z)];
+                yield_constr
+                    .constraint(diff * (diff - FE::TWO) * (diff - FE::from_canonical_u8(4)));
+            }
+        }
+
+        // A''[x, y] = xor(B[x, y], andn(B[x + 1, y], B[x + 2, y])).
+        for x in 0..5 {
+            for y in 0..5 {
+                let computed = limbs_from_bits(|z| {
+                    xor(
+                        lv[b(x, y, z)],
+                        andn(lv[b((x + 1) % 5, y, z)], lv[b((x + 2) % 5, y, z)]),
+                    )
+                });
+                let a_prime_prime = lane(A_PRIME_PRIME.start, x, y);
+                yield_constr.constraint(computed[0] - lv[a_prime_prime]);
+                yield_constr.constraint(computed[1] - lv[a_prime_prime + 1]);
+            }
+        }
+
+        // A'''[0, 0] = A''[0, 0] ^ RC.
+        let bits_0_0 = &lv[A_PRIME_PRIME_0_0_BITS];
+        let computed = limbs_from_bits(|z| bits_0_0[z]);
+        yield_constr.constraint(computed[0] - lv[A_PRIME_PRIME.start]);
+        yield_constr.constraint(computed[1] - lv[A_PRIME_PRIME.start + 1]);
+        let computed = limbs_from_bits(|z| {
+            let rc_bit = flags
+                .iter()
+                .zip(ROUND_CONSTANTS)
+                .filter(|(_, rc)| (rc >> z) & 1 == 1)
+                .fold(P::ZEROS, |acc, (&flag, _)| acc + flag);
+            xor(bits_0_0[z], rc_bit)
+        });
+        yield_constr.constraint(computed[0] - lv[A_PRIME_PRIME_PRIME_0_0.start]);
+        yield_constr.constraint(computed[1] - lv[A_PRIME_PRIME_PRIME_0_0.start + 1]);
+
+        // The output of each round is the input of the next one.
+        for x in 0..5 {
+            for y in 0..5 {
+                let output = round_output(x, y);
+                let next_a = lane(A.start, x, y);
+                for i in 0..2 {
+                    yield_constr
+                        .constraint_transition(not_last_round * (nv[next_a + i] - lv[output + i]));
+                }
+            }
+        }
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let lv = vars.get_local_values();
+        let nv = vars.get_next_values();
+
+        let bits = ROUND_FLAGS
+            .chain(C_START..A_PRIME_PRIME.start)
+            .chain(A_PRIME_PRIME_0_0_BITS);
+        for col in bits {
+            let constraint = builder.mul_sub_extension(lv[col], lv[col], lv[col]);
+            yield_constr.constraint(builder, constraint);
+        }
+
+        let flags = &lv[ROUND_FLAGS];
+        let any_flag = builder.add_many_extension(flags);
+        let constraint = builder.mul_sub_extension(any_flag, any_flag, any_flag);
+        yield_constr.constraint(builder, constraint);
+        let constraint = builder.sub_extension(any_flag, flags[0]);
+        yield_constr.constraint_first_row(builder, constraint);
+        for round in 0..NUM_ROUNDS - 1 {
+            let constraint = builder.sub_extension(nv[ROUND_FLAGS.start + round + 1], flags[round]);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+        let not_last_round = builder.sub_extension(any_flag, flags[NUM_ROUNDS - 1]);
+
+        for (a, preimage) in A.zip(PREIMAGE) {
+            let diff = builder.sub_extension(lv[a], lv[preimage]);
+            let constraint = builder.mul_extension(flags[0], diff);
+            yield_constr.constraint(builder, constraint);
+            let diff = builder.sub_extension(nv[preimage], lv[preimage]);
+            let constraint = builder.mul_extension(not_last_round, diff);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+
+        for x in 0..5 {
+            for z in 0..LANE_BITS {
+                let computed = xor3_circuit(
+                    builder,
+                    lv[c(x, z)],
+                    lv[c((x + 4) % 5, z)],
+                    lv[c((x + 1) % 5, (z + LANE_BITS - 1) % LANE_BITS)],
+                );
+                let constraint = builder.sub_extension(lv[c_prime(x, z)], computed);
+                yield_constr.constraint(builder, constraint);
+            }
+        }
+
+        for x in 0..5 {
+            for y in 0..5 {
+                let computed = limbs_from_bits_circuit(builder, |builder, z| {
+                    xor3_circuit(
+                        builder,
+                        lv[a_prime(x, y, z)],
+                        lv[c(x, z)],
+                        lv[c_prime(x, z)],
+                    )
+                });
+                let a = lane(A.start, x, y);
+                for i in 0..2 {
+                    let constraint = builder.sub_extension(computed[i], lv[a + i]);
+                    yield_constr.constraint(builder, constraint);
+                }
+            }
+        }
+
+        let two = builder.two_extension();
+        let four = builder.constant_extension(F::Extension::from_canonical_u8(4));
+        for x in 0..5 {
+            for z in 0..LANE_BITS {
+                let sum = builder.add_many_extension((0..5).map(|y| lv[a_prime(x, y, z)]));
+                let diff = builder.sub_extension(sum, lv[c_prime(x, z)]);
+                let diff_minus_two = builder.sub_extension(diff, two);
+                let diff_minus_four = builder.sub_extension(diff, four);
+                let constraint =
+                    builder.mul_many_extension([diff, diff_minus_two, diff_minus_four]);
+                yield_constr.constraint(builder, constraint);
+            }
+        }
+
+        for x in 0..5 {
+            for y in 0..5 {
+                let computed = limbs_from_bits_circuit(builder, |builder, z| {
+                    let andn =
+                        andn_circuit(builder, lv[b((x + 1) % 5, y, z)], lv[b((x + 2) % 5, y, z)]);
+                    xor_circuit(builder, lv[b(x, y, z)], andn)
+                });
+                let a_prime_prime = lane(A_PRIME_PRIME.start, x, y);
+                for i in 0..2 {
+                    let constraint = builder.sub_extension(computed[i], lv[a_prime_prime + i]);
+                    yield_constr.constraint(builder, constraint);
+                }
+            }
+        }
+
+        let bits_0_0 = &lv[A_PRIME_PRIME_0_0_BITS];
+        let computed = limbs_from_bits_circuit(builder, |_, z| bits_0_0[z]);
+        for i in 0..2 {
+            let constraint = builder.sub_extension(computed[i], lv[A_PRIME_PRIME.start + i]);
+            yield_constr.constraint(builder, constraint);
+        }
+        let computed = limbs_from_bits_circuit(builder, |builder, z| {
+            let rc_flags = flags
+                .iter()
+                .zip(ROUND_CONSTANTS)
+                .filter(|(_, rc)| (rc >> z) & 1 == 1)
+                .map(|(&flag, _)| flag)
+                .collect::<Vec<_>>();
+            let rc_bit = builder.add_many_extension(rc_flags);
+            xor_circuit(builder, bits_0_0[z], rc_bit)
+        });
+        for i in 0..2 {
+            let constraint =
+                builder.sub_extension(computed[i], lv[A_PRIME_PRIME_PRIME_0_0.start + i]);
+            yield_constr.constraint(builder, constraint);
+        }
+
+        for x in 0..5 {
+            for y in 0..5 {
+                let output = round_output(x, y);
+                let next_a = lane(A.start, x, y);
+                for i in 0..2 {
+                    let diff = builder.sub_extension(nv[next_a + i], lv[output + i]);
+                    let constraint = builder.mul_extension(not_last_round, diff);
+                    yield_constr.constraint_transition(builder, constraint);
+                }
+            }
+        }
+    }
+
+    fn constraint_degree(&self) -> usize {
+        3
+    }
+
+    fn requires_ctls(&self) -> bool {
+        true
+    }
+}
+
+/// Proves the provided permutations with the [`KeccakPermutationStark`]. The permutations must be
+/// in the order in which they were delegated, see the [module documentation](self).
+pub fn prove_keccak_permutations<F, C, const D: usize>(
+    stark: &KeccakPermutationStark<F, D>,
+    config: &StarkConfig,
+    permutations: &[KeccakPermutation],
+    timing: &mut TimingTree,
+) -> Result<StarkProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let values = permutations
+        .iter()
+        .map(KeccakPermutation::values::<F>)
+        .collect::<Vec<_>>();
+    prove_delegated(
+        stark,
+        config,
+        stark.generate_trace(permutations),
+        keccak_ctl(),
+        &values,
+        timing,
+    )
+}
+
+/// Verifies a [`KeccakPermutationStark`] proof of exactly the provided permutations.
+pub fn verify_keccak_permutations<F, C, const D: usize>(
+    stark: &KeccakPermutationStark<F, D>,
+    proof: &StarkProofWithPublicInputs<F, C, D>,
+    permutations: &[KeccakPermutation],
+    config: &StarkConfig,
+) -> Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let values = permutations
+        .iter()
+        .map(KeccakPermutation::values::<F>)
+        .collect::<Vec<_>>();
+    verify_delegated(stark, proof, keccak_ctl(), &values, config)
+}
+
+/// Collects the Keccak-f\[1600\] permutations delegated by a plonky2 circuit to a
+/// [`KeccakPermutationStark`], and checks them against a recursively verified proof. See the
+/// [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct KeccakDelegationTarget {
+    permutations: Vec<Vec<Target>>,
+}
+
+impl KeccakDelegationTarget {
+    /// Creates a new [`KeccakDelegationTarget`], with no delegated permutation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of permutations delegated so far.
+    pub fn num_permutations(&self) -> usize {
+        self.permutations.len()
+    }
+
+    /// Delegates the permutation of `state`, given as 32-bit limbs, returning the output state.
+    /// All limbs must be 32-bit values, otherwise the proof will not verify.
+    pub fn permute<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        state: [Target; KECCAK_STATE_LIMBS],
+    ) -> [Target; KECCAK_STATE_LIMBS] {
+        let output = builder.add_virtual_target_arr::<KECCAK_STATE_LIMBS>();
+        builder.add_simple_generator(KeccakPermutationGenerator {
+            input: state.to_vec(),
+            output: output.to_vec(),
+        });
+        self.permutations
+            .push(state.into_iter().chain(output).collect());
+        output
+    }
+
+    /// Hashes `input` with Keccak-256, delegating the permutations of the sponge, and returns the
+    /// 32 bytes of the digest. The bytes of `input` are range-checked.
+    ///
+    /// The first block is loaded into the zero initial state directly, while absorbing the next
+    /// ones requires decomposing the state into bits to XOR them, so the cost of absorption grows
+    /// with the number of blocks.
+    pub fn keccak256<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        input: &[Target],
+    ) -> [Target; 32] {
+        let zero = builder.zero();
+        let num_blocks = input.len() / KECCAK_RATE_BYTES + 1;
+        let mut padded = input.to_vec();
+        padded.resize(num_blocks * KECCAK_RATE_BYTES, zero);
+        let first_padding_byte = if input.len() == padded.len() - 1 {
+            0x81
+        } else {
+            0x01
+        };
+        padded[input.len()] = builder.constant(F::from_canonical_u8(first_padding_byte));
+        let last = padded.len() - 1;
+        if input.len() < last {
+            padded[last] = builder.constant(F::from_canonical_u8(0x80));
+        }
+
+        let mut state = [zero; KECCAK_STATE_LIMBS];
+        for (i, block) in padded.chunks_exact(KECCAK_RATE_BYTES).enumerate() {
+            for (limb, bytes) in state.iter_mut().zip(block.chunks_exact(4)) {
+                *limb = if i == 0 {
+                    for &byte in bytes {
+                        builder.range_check(byte, 8);
+                    }
+                    bytes.iter().rev().fold(zero, |acc, &byte| {
+                        builder.mul_const_add(F::from_canonical_u16(256), acc, byte)
+                    })
+                } else {
+                    let limb_bits = builder.split_le(*limb, 32);
+                    let block_bits = bytes
+                        .iter()
+                        .flat_map(|&byte| builder.split_le(byte, 8))
+                        .collect::<Vec<_>>();
+                    let xored_bits = block_bits
+                        .into_iter()
+                        .zip(limb_bits)
+                        .map(|(x, y)| builder.xor(x, y))
+                        .collect::<Vec<_>>();
+                    builder.le_sum(xored_bits.into_iter())
+                };
+            }
+            state = self.permute(builder, state);
+        }
+
+        let digest_bits = state[..8]
+            .iter()
+            .flat_map(|&limb| builder.split_le(limb, 32))
+            .collect::<Vec<_>>();
+        let digest = digest_bits
+            .chunks_exact(8)
+            .map(|bits| builder.le_sum(bits.iter()))
+            .collect::<Vec<_>>();
+        digest.try_into().unwrap()
+    }
+
+    /// Adds a [`KeccakPermutationStark`] proof target, verifies it recursively, and checks that
+    /// it proves exactly the delegated permutations. Returns the proof target, to be set with the
+    /// output of [`prove_keccak_permutations`].
+    pub fn verify<F, C, const D: usize>(
+        self,
+        builder: &mut CircuitBuilder<F, D>,
+        stark: &KeccakPermutationStark<F, D>,
+        config: &StarkConfig,
+    ) -> StarkProofWithPublicInputsTarget<D>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let degree_bits = log2_strict(KeccakPermutationStark::<F, D>::num_rows(
+            self.permutations.len(),
+        ));
+        verify_delegated_circuit::<F, C, _, _, D>(
+            builder,
+            stark,
+            keccak_ctl(),
+            &self.permutations,
+            degree_bits,
+            config,
+        )
+    }
+}
+
+/// Computes the output state of a delegated permutation.
+#[derive(Debug, Default)]
+pub struct KeccakPermutationGenerator {
+    input: Vec<Target>,
+    output: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for KeccakPermutationGenerator
+{
+    fn id(&self) -> String {
+        "KeccakPermutationGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.input.clone()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let input = witness
+            .get_targets(&self.input)
+            .into_iter()
+            .map(|x| x.to_canonical_u64() as u32)
+            .collect::<Vec<_>>();
+        let output = keccakf(limbs_to_lanes(&input));
+
+        for (&target, limb) in self.output.iter().zip(lanes_to_limbs(&output)) {
+            out_buffer.set_target(target, limb)?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.input)?;
+        dst.write_target_vec(&self.output)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let input = src.read_target_vec()?;
+        let output = src.read_target_vec()?;
+        Ok(Self { input, output })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{vec, vec::Vec};
+
+    use anyhow::Result;
+    use plonky2::field::types::{Field, PrimeField64, Sample};
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::config::StarkConfig;
+    use crate::keccak::{
+        keccakf, prove_keccak_permutations, verify_keccak_permutations, KeccakDelegationTarget,
+        KeccakPermutation, KeccakPermutationLog, KeccakPermutationStark, KECCAK_RATE_BYTES,
+    };
+    use crate::recursive_verifier::set_stark_proof_with_pis_target;
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = KeccakPermutationStark<F, D>;
+
+    fn random_permutation() -> KeccakPermutation {
+        KeccakPermutation {
+            input: core::array::from_fn(|_| F::rand().to_canonical_u64()),
+        }
+    }
+
+    fn from_hex(s: &str) -> [u8; 32] {
+        core::array::from_fn(|i| u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap())
+    }
+
+    #[test]
+    fn test_keccakf() {
+        let output = keccakf([0; 25]);
+        assert_eq!(output[0], 0xF1258F7940E1DDE7);
+        assert_eq!(output[1], 0x84D5CCF933C0478A);
+    }
+
+    #[test]
+    fn test_keccak256() {
+        let mut log = KeccakPermutationLog::new();
+        assert_eq!(
+            log.keccak256(b""),
+            from_hex("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+        assert_eq!(
+            log.keccak256(b"abc"),
+            from_hex("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45")
+        );
+        assert_eq!(log.into_permutations().len(), 2);
+    }
+
+    #[test]
+    fn test_keccak_stark() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let permutations = vec![
+            KeccakPermutation { input: [0; 25] },
+            random_permutation(),
+            KeccakPermutation {
+                input: [u64::MAX; 25],
+            },
+        ];
+
+        let proof = prove_keccak_permutations::<F, C, D>(
+            &stark,
+            &config,
+            &permutations,
+            &mut TimingTree::default(),
+        )?;
+        verify_keccak_permutations(&stark, &proof, &permutations, &config)?;
+
+        let mut other_permutations = permutations.clone();
+        other_permutations[1].input[7] ^= 1 << 40;
+        assert!(verify_keccak_permutations(&stark, &proof, &other_permutations, &config).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_keccak_stark_degree() -> Result<()> {
+        test_stark_low_degree(S::new())
+    }
+
+    #[test]
+    fn test_keccak_stark_circuit() -> Result<()> {
+        test_stark_circuit_constraints::<F, C, S, D>(S::new())
+    }
+
+    #[test]
+    fn test_keccak_delegation() -> Result<()> {
+        let stark_config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut delegation = KeccakDelegationTarget::new();
+        let mut log = KeccakPermutationLog::new();
+        let mut pw = PartialWitness::new();
+
+        // One single-block message, and one spanning two blocks.
+        for len in [3, KECCAK_RATE_BYTES + 5] {
+            let message = (0..len)
+                .map(|_| F::rand().to_canonical_u64() as u8)
+                .collect::<Vec<_>>();
+            let message_targets = builder.add_virtual_targets(len);
+            for (&t, &byte) in message_targets.iter().zip(&message) {
+                pw.set_target(t, F::from_canonical_u8(byte))?;
+            }
+
+            let digest = delegation.keccak256(&mut builder, &message_targets);
+            for (t, byte) in digest.into_iter().zip(log.keccak256(&message)) {
+                let expected = builder.constant(F::from_canonical_u8(byte));
+                builder.connect(t, expected);
+            }
+        }
+
+        let proof_target = delegation.verify::<F, C, D>(&mut builder, &stark, &stark_config);
+        let zero = builder.zero();
+        let data = builder.build::<C>();
+
+        let proof = prove_keccak_permutations::<F, C, D>(
+            &stark,
+            &stark_config,
+            &log.into_permutations(),
+            &mut TimingTree::default(),
+        )?;
+        let degree_bits = proof.proof.recover_degree_bits(&stark_config);
+        set_stark_proof_with_pis_target(&mut pw, &proof_target, &proof, degree_bits, zero)?;
+
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
@@ -0,0 +1,108 @@
+//! Proving and verifying keys for STARKs with fixed columns, see [`Stark::fixed_columns`].
+//!
+//! The fixed columns of a STARK are committed to once when creating its [`StarkProvingKey`], and
+//! the resulting Merkle cap, held by the [`StarkVerifyingKey`], is reused by every proof instead of
+//! being recomputed from the trace.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use plonky2::field::extension::Extendable;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::fri::oracle::PolynomialBatch;
+use plonky2::hash::hash_types::RichField;
+use plonky2::hash::merkle_tree::MerkleCap;
+use plonky2::plonk::config::GenericConfig;
+use plonky2::timed;
+use plonky2::util::timing::TimingTree;
+use serde::{Deserialize, Serialize};
+
+use crate::config::StarkConfig;
+use crate::stark::Stark;
+use crate::util::{commit_trace_columns, trace_degree_bits};
+
+/// The data needed to prove statements of a STARK with fixed columns.
+#[derive(Debug)]
+pub struct StarkProvingKey<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+{
+    /// Values of the fixed columns.
+    pub fixed_values: Vec<PolynomialValues<F>>,
+    /// Commitment to the LDEs of the fixed columns.
+    pub fixed_commitment: PolynomialBatch<F, C, D>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    StarkProvingKey<F, C, D>
+{
+    /// Commits to the fixed columns of `stark`.
+    pub fn new<S: Stark<F, D>>(stark: &S, config: &StarkConfig, timing: &mut TimingTree) -> Self {
+        assert!(
+            stark.num_fixed_columns() > 0,
+            "The STARK has no fixed columns"
+        );
+        let fixed_values = stark.fixed_columns();
+        assert_eq!(
+            fixed_values.len(),
+            stark.num_fixed_columns(),
+            "Mismatch between the number of fixed columns and `FIXED_COLUMNS`"
+        );
+        let degree = fixed_values[0].len();
+        assert!(
+            fixed_values.iter().all(|column| column.len() == degree),
+            "Fixed columns must all have the same length"
+        );
+
+        let degree_bits =
+            trace_degree_bits(degree, stark.trace_len_cofactor()).unwrap_or_else(|| {
+                panic!(
+                    "The length of the fixed columns is not {} times a power of two",
+                    stark.trace_len_cofactor()
+                )
+            });
+
+        let fixed_commitment = timed!(
+            timing,
+            "compute fixed columns commitment",
+            commit_trace_columns::<F, C, D>(
+                fixed_values.clone(),
+                degree_bits,
+                config.fri_config.rate_bits,
+                config.fri_config.cap_height,
+                timing,
+            )
+        );
+
+        Self {
+            fixed_values,
+            fixed_commitment,
+        }
+    }
+
+    /// Returns the log of the degree bound of the polynomials of the traces proven with this key,
+    /// which is the log of their length when it is a power of two.
+    pub fn degree_bits(&self) -> usize {
+        self.fixed_commitment.degree_log
+    }
+
+    /// Returns the corresponding verifying key.
+    pub fn verifying_key(&self) -> StarkVerifyingKey<F, C, D> {
+        StarkVerifyingKey {
+            fixed_cap: self.fixed_commitment.merkle_tree.cap.clone(),
+            degree_bits: self.degree_bits(),
+        }
+    }
+}
+
+/// The data needed to verify proofs of a STARK with fixed columns.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(bound = "")]
+pub struct StarkVerifyingKey<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    /// Merkle cap of the LDEs of the fixed columns.
+    pub fixed_cap: MerkleCap<F, C::Hasher>,
+    /// Log of the degree bound of the polynomials of the traces proven with this key.
+    pub degree_bits: usize,
+}
@@ -201,6 +201,13 @@
 //! }
 //! ```
 //!
+//! # Usage without the standard library
+//!
+//! With its default features disabled, this crate builds without the standard library, e.g. for
+//! embedded light clients or `wasm32-unknown-unknown` targets, which can then verify STARK proofs
+//! directly with [`verify_stark_proof`][crate::verifier::verify_stark_proof], or within a plonky2
+//! circuit with [`verify_stark_proof_circuit`][crate::recursive_verifier::verify_stark_proof_circuit].
+//!
 
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::needless_range_loop)]
@@ -215,23 +222,39 @@ extern crate alloc;
 
 mod get_challenges;
 
+pub mod aggregation;
+pub mod air_builder;
 pub mod config;
 pub mod constraint_consumer;
 pub mod cross_table_lookup;
+pub mod delegation;
+pub mod ecdsa;
 pub mod evaluation_frame;
+pub mod keccak;
+pub mod keys;
 pub mod lookup;
+pub mod poseidon;
 pub mod proof;
 pub mod prover;
 pub mod recursive_verifier;
+pub mod segmentation;
+pub mod sha256;
 pub mod stark;
 pub mod stark_testing;
+pub mod u32_arithmetic;
 pub mod util;
 mod vanishing_poly;
 pub mod verifier;
 
+#[cfg(test)]
+pub mod cubic_stark;
 #[cfg(test)]
 pub mod fibonacci_stark;
 #[cfg(test)]
+pub mod fixed_column_stark;
+#[cfg(test)]
 pub mod permutation_stark;
 #[cfg(test)]
+pub mod registers_stark;
+#[cfg(test)]
 pub mod unconstrained_stark;
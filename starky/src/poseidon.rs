@@ -0,0 +1,823 @@
+//! A STARK proving Poseidon permutations, to which plonky2 circuits can delegate Poseidon hashing,
+//! e.g. to verify large numbers of Merkle openings.
+//!
+//! Each permutation is exposed through a cross-table lookup on the tuple `(input, output)`, where
+//! both are [`SPONGE_WIDTH`] field elements. Since the permutation is the one used by
+//! [`PoseidonHash`], delegated hashes are interchangeable with the ones computed by
+//! plonky2's Poseidon gates, and Merkle trees built natively by plonky2 can be opened in a circuit
+//! at the cost of a few selections and a CTL tuple per layer.
+//!
+//! # Sponge layer
+//!
+//! The hashing modes of [`PoseidonHash`] are implemented outside the STARK:
+//! - natively by [`PoseidonPermutationLog`], which records the permutations it performs,
+//! - in circuits by [`PoseidonDelegationTarget`], which delegates them.
+//!
+//! Both provide [`hash_no_pad`](PoseidonPermutationLog::hash_no_pad),
+//! [`hash_or_noop`](PoseidonPermutationLog::hash_or_noop),
+//! [`two_to_one`](PoseidonPermutationLog::two_to_one) and
+//! [`verify_merkle_proof_to_cap`](PoseidonPermutationLog::verify_merkle_proof_to_cap), which
+//! perform the same permutations in the same order as long as they are called in the same order.
+//!
+//! # Delegating permutations from a plonky2 circuit
+//!
+//! This follows the pattern of the [`delegation`](crate::delegation) module:
+//!
+//! 1. While building the circuit, delegate permutations with a [`PoseidonDelegationTarget`]. Its
+//!    outputs are unconstrained until step 2.
+//! 2. Call [`PoseidonDelegationTarget::verify`], which adds a STARK proof target, verifies it
+//!    recursively and connects the delegated permutations to the STARK's CTL.
+//! 3. When proving, record the same permutations in the same order with a
+//!    [`PoseidonPermutationLog`], prove them with [`prove_poseidon_permutations`], and set the
+//!    returned proof in the witness with
+//!    [`set_stark_proof_with_pis_target`](crate::recursive_verifier::set_stark_proof_with_pis_target).
+//!
+//! The witness generator used by [`PoseidonDelegationTarget`] is not part of plonky2's default
+//! generator serializer, so circuits using it need a custom one to be serialized.
+//!
+//! # Trace layout
+//!
+//! Each permutation uses a single row, holding its input and output along with the input `x` and
+//! the cube `x^3` of each of its S-boxes, so that `x^7 = (x^3)^2 * x` has degree 3. The rounds
+//! are computed symbolically between S-boxes, as all other layers are linear. Unused rows hold
+//! the permutation of the zero state and are not exported.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use anyhow::{ensure, Result};
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::{
+    HashOut, HashOutTarget, MerkleCapTarget, RichField, NUM_HASH_OUT_ELTS,
+};
+use plonky2::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
+use plonky2::hash::merkle_tree::MerkleCap;
+use plonky2::hash::poseidon::{
+    Poseidon, PoseidonHash, HALF_N_FULL_ROUNDS, N_PARTIAL_ROUNDS, SPONGE_RATE, SPONGE_WIDTH,
+};
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::util::log2_strict;
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+use plonky2::util::timing::TimingTree;
+
+use crate::config::StarkConfig;
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::cross_table_lookup::{CrossTableLookup, TableWithColumns};
+use crate::delegation::{prove_delegated, verify_delegated, verify_delegated_circuit};
+use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+use crate::lookup::{Column, Filter};
+use crate::proof::{StarkProofWithPublicInputs, StarkProofWithPublicInputsTarget};
+use crate::stark::Stark;
+use crate::util::trace_rows_to_poly_values;
+
+const NUM_FULL_ROUNDS: usize = 2 * HALF_N_FULL_ROUNDS;
+const NUM_FULL_SBOXES: usize = NUM_FULL_ROUNDS * SPONGE_WIDTH;
+
+/// Whether the row holds a permutation exported through the CTL.
+const FILTER: usize = 0;
+/// The input of the permutation.
+const INPUT: Range<usize> = FILTER + 1..FILTER + 1 + SPONGE_WIDTH;
+/// The inputs of the S-boxes of the full rounds, round by round.
+const FULL_SBOX_INPUTS: usize = INPUT.end;
+/// The cubes of the inputs of the S-boxes of the full rounds.
+const FULL_SBOX_CUBES: usize = FULL_SBOX_INPUTS + NUM_FULL_SBOXES;
+/// The inputs of the S-boxes of the partial rounds.
+const PARTIAL_SBOX_INPUTS: usize = FULL_SBOX_CUBES + NUM_FULL_SBOXES;
+/// The cubes of the inputs of the S-boxes of the partial rounds.
+const PARTIAL_SBOX_CUBES: usize = PARTIAL_SBOX_INPUTS + N_PARTIAL_ROUNDS;
+/// The output of the permutation.
+const OUTPUT: Range<usize> =
+    PARTIAL_SBOX_CUBES + N_PARTIAL_ROUNDS..PARTIAL_SBOX_CUBES + N_PARTIAL_ROUNDS + SPONGE_WIDTH;
+
+const POSEIDON_COLUMNS: usize = OUTPUT.end;
+const POSEIDON_PUBLIC_INPUTS: usize = 0;
+
+/// The minimum number of rows of a trace.
+const MIN_ROWS: usize = 32;
+
+/// Returns the offset of the S-box of lane `i` of full round `round`, among the full round
+/// S-boxes. Full rounds are numbered from 0 to `2 * HALF_N_FULL_ROUNDS`, skipping partial rounds.
+const fn full_sbox(round: usize, i: usize) -> usize {
+    round * SPONGE_WIDTH + i
+}
+
+/// A single Poseidon permutation proven by the [`PoseidonPermutationStark`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PoseidonPermutation<F: RichField> {
+    /// The input state.
+    pub input: [F; SPONGE_WIDTH],
+}
+
+impl<F: RichField> PoseidonPermutation<F> {
+    /// Returns the output state of this permutation.
+    pub fn output(&self) -> [F; SPONGE_WIDTH] {
+        F::poseidon(self.input)
+    }
+
+    /// Returns the `(input, output)` tuple representing this permutation in the cross-table
+    /// lookup.
+    pub fn values(&self) -> Vec<F> {
+        self.input.into_iter().chain(self.output()).collect()
+    }
+}
+
+/// Records the Poseidon permutations performed natively, to be proven by the
+/// [`PoseidonPermutationStark`]. The hashing methods match those of [`PoseidonHash`].
+#[derive(Clone, Debug, Default)]
+pub struct PoseidonPermutationLog<F: RichField> {
+    permutations: Vec<PoseidonPermutation<F>>,
+}
+
+impl<F: RichField> PoseidonPermutationLog<F> {
+    /// Creates an empty log.
+    pub fn new() -> Self {
+        Self {
+            permutations: Vec::new(),
+        }
+    }
+
+    /// Permutes `state`, recording the permutation.
+    pub fn permute(&mut self, state: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
+        let permutation = PoseidonPermutation { input: state };
+        self.permutations.push(permutation);
+        permutation.output()
+    }
+
+    /// Hashes `inputs` without padding, like
+    /// [`PoseidonHash::hash_no_pad`](plonky2::plonk::config::Hasher::hash_no_pad).
+    pub fn hash_no_pad(&mut self, inputs: &[F]) -> HashOut<F> {
+        let mut state = [F::ZERO; SPONGE_WIDTH];
+        for chunk in inputs.chunks(SPONGE_RATE) {
+            state[..chunk.len()].copy_from_slice(chunk);
+            state = self.permute(state);
+        }
+        HashOut::from_partial(&state[..NUM_HASH_OUT_ELTS])
+    }
+
+    /// Hashes `inputs` if they don't fit in a digest, or pads them with zeros otherwise, like
+    /// [`PoseidonHash::hash_or_noop`](plonky2::plonk::config::Hasher::hash_or_noop).
+    pub fn hash_or_noop(&mut self, inputs: &[F]) -> HashOut<F> {
+        if inputs.len() <= NUM_HASH_OUT_ELTS {
+            HashOut::from_partial(inputs)
+        } else {
+            self.hash_no_pad(inputs)
+        }
+    }
+
+    /// Compresses two digests, like
+    /// [`PoseidonHash::two_to_one`](plonky2::plonk::config::Hasher::two_to_one).
+    pub fn two_to_one(&mut self, left: HashOut<F>, right: HashOut<F>) -> HashOut<F> {
+        let mut state = [F::ZERO; SPONGE_WIDTH];
+        state[..NUM_HASH_OUT_ELTS].copy_from_slice(&left.elements);
+        state[NUM_HASH_OUT_ELTS..2 * NUM_HASH_OUT_ELTS].copy_from_slice(&right.elements);
+        HashOut::from_partial(&self.permute(state)[..NUM_HASH_OUT_ELTS])
+    }
+
+    /// Verifies that `leaf_data` is present at `leaf_index` in the Merkle tree with the given cap,
+    /// like [`verify_merkle_proof_to_cap`](plonky2::hash::merkle_proofs::verify_merkle_proof_to_cap).
+    pub fn verify_merkle_proof_to_cap(
+        &mut self,
+        leaf_data: &[F],
+        mut leaf_index: usize,
+        merkle_cap: &MerkleCap<F, PoseidonHash>,
+        proof: &MerkleProof<F, PoseidonHash>,
+    ) -> Result<()> {
+        let mut digest = self.hash_or_noop(leaf_data);
+        for &sibling in &proof.siblings {
+            digest = if leaf_index & 1 == 1 {
+                self.two_to_one(sibling, digest)
+            } else {
+                self.two_to_one(digest, sibling)
+            };
+            leaf_index >>= 1;
+        }
+        ensure!(
+            merkle_cap.0.get(leaf_index) == Some(&digest),
+            "Invalid Merkle proof."
+        );
+        Ok(())
+    }
+
+    /// Returns the recorded permutations, in order.
+    pub fn into_permutations(self) -> Vec<PoseidonPermutation<F>> {
+        self.permutations
+    }
+}
+
+/// A STARK proving Poseidon permutations.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PoseidonPermutationStark<F: RichField + Extendable<D>, const D: usize> {
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PoseidonPermutationStark<F, D> {
+    /// Creates a new [`PoseidonPermutationStark`].
+    pub const fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of rows of a trace proving `num_permutations` permutations.
+    pub fn num_rows(num_permutations: usize) -> usize {
+        num_permutations.next_power_of_two().max(MIN_ROWS)
+    }
+
+    /// Generates the trace proving the provided permutations, in order.
+    pub fn generate_trace(
+        &self,
+        permutations: &[PoseidonPermutation<F>],
+    ) -> Vec<PolynomialValues<F>> {
+        let num_rows = Self::num_rows(permutations.len());
+        let padding = Self::generate_row([F::ZERO; SPONGE_WIDTH]);
+        let mut rows = vec![padding; num_rows];
+        for (permutation, row) in permutations.iter().zip(&mut rows) {
+            *row = Self::generate_row(permutation.input);
+            row[FILTER] = F::ONE;
+        }
+        trace_rows_to_poly_values(rows)
+    }
+
+    /// Generates an unfiltered row permuting `input`.
+    fn generate_row(input: [F; SPONGE_WIDTH]) -> [F; POSEIDON_COLUMNS] {
+        let mut row = [F::ZERO; POSEIDON_COLUMNS];
+        row[INPUT].copy_from_slice(&input);
+
+        let mut sbox = |x: F, input_col: usize, cube_col: usize| {
+            row[input_col] = x;
+            row[cube_col] = x.cube();
+            x.exp_u64(7)
+        };
+
+        let mut state = input;
+        let mut round = 0;
+        for full_round in 0..NUM_FULL_ROUNDS {
+            if full_round == HALF_N_FULL_ROUNDS {
+                for i in 0..N_PARTIAL_ROUNDS {
+                    <F as Poseidon>::constant_layer(&mut state, round);
+                    state[0] = sbox(state[0], PARTIAL_SBOX_INPUTS + i, PARTIAL_SBOX_CUBES + i);
+                    state = <F as Poseidon>::mds_layer(&state);
+                    round += 1;
+                }
+            }
+            <F as Poseidon>::constant_layer(&mut state, round);
+            for (i, x) in state.iter_mut().enumerate() {
+                let offset = full_sbox(full_round, i);
+                *x = sbox(*x, FULL_SBOX_INPUTS + offset, FULL_SBOX_CUBES + offset);
+            }
+            state = <F as Poseidon>::mds_layer(&state);
+            round += 1;
+        }
+        debug_assert_eq!(state, F::poseidon(input));
+
+        row[OUTPUT].copy_from_slice(&state);
+        row
+    }
+}
+
+/// Returns the cross-table lookup exposing the `(input, output)` tuples of all the permutations
+/// in the [`PoseidonPermutationStark`] trace. It has no looking table: the looking side is
+/// provided as an extra looking sum over the delegated permutations.
+pub fn poseidon_ctl<F: Field>() -> CrossTableLookup<F> {
+    let columns = Column::singles(INPUT.chain(OUTPUT)).collect();
+    let filter = Filter::new_simple(Column::single(FILTER));
+    CrossTableLookup::new(vec![], TableWithColumns::new(0, columns, filter))
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for PoseidonPermutationStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize>
+        = StarkFrame<P, P::Scalar, POSEIDON_COLUMNS, POSEIDON_PUBLIC_INPUTS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget = StarkFrame<
+        ExtensionTarget<D>,
+        ExtensionTarget<D>,
+        POSEIDON_COLUMNS,
+        POSEIDON_PUBLIC_INPUTS,
+    >;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let lv = vars.get_local_values();
+
+        let filter = lv[FILTER];
+        yield_constr.constraint(filter * (filter - P::ONES));
+
+        // Each S-box input is checked against the symbolic state, and replaced by its power 7,
+        // computed from the cube.
+        let mut sbox = |x: P, input_col: usize, cube_col: usize| {
+            let (input, cube) = (lv[input_col], lv[cube_col]);
+            yield_constr.constraint(input - x);
+            yield_constr.constraint(cube - input * input * input);
+            cube * cube * input
+        };
+
+        let mut state: [P; SPONGE_WIDTH] = lv[INPUT].try_into().unwrap();
+        let mut round = 0;
+        for full_round in 0..NUM_FULL_ROUNDS {
+            if full_round == HALF_N_FULL_ROUNDS {
+                for i in 0..N_PARTIAL_ROUNDS {
+                    <F as Poseidon>::constant_layer_packed_field(&mut state, round);
+                    state[0] = sbox(state[0], PARTIAL_SBOX_INPUTS + i, PARTIAL_SBOX_CUBES + i);
+                    state = <F as Poseidon>::mds_layer_packed_field(&state);
+                    round += 1;
+                }
+            }
+            <F as Poseidon>::constant_layer_packed_field(&mut state, round);
+            for (i, x) in state.iter_mut().enumerate() {
+                let offset = full_sbox(full_round, i);
+                *x = sbox(*x, FULL_SBOX_INPUTS + offset, FULL_SBOX_CUBES + offset);
+            }
+            state = <F as Poseidon>::mds_layer_packed_field(&state);
+            round += 1;
+        }
+
+        for (output, x) in OUTPUT.zip(state) {
+            yield_constr.constraint(lv[output] - x);
+        }
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let lv = vars.get_local_values();
+
+        let filter = lv[FILTER];
+        let constraint = builder.mul_sub_extension(filter, filter, filter);
+        yield_constr.constraint(builder, constraint);
+
+        let mut sbox = |builder: &mut CircuitBuilder<F, D>,
+                        x: ExtensionTarget<D>,
+                        input_col: usize,
+                        cube_col: usize| {
+            let (input, cube) = (lv[input_col], lv[cube_col]);
+            let constraint = builder.sub_extension(input, x);
+            yield_constr.constraint(builder, constraint);
+            let input_cube = builder.cube_extension(input);
+            let constraint = builder.sub_extension(cube, input_cube);
+            yield_constr.constraint(builder, constraint);
+            builder.mul_many_extension([cube, cube, input])
+        };
+
+        let mut state: [ExtensionTarget<D>; SPONGE_WIDTH] = lv[INPUT].try_into().unwrap();
+        let mut round = 0;
+        for full_round in 0..NUM_FULL_ROUNDS {
+            if full_round == HALF_N_FULL_ROUNDS {
+                for i in 0..N_PARTIAL_ROUNDS {
+                    <F as Poseidon>::constant_layer_circuit(builder, &mut state, round);
+                    state[0] = sbox(
+                        builder,
+                        state[0],
+                        PARTIAL_SBOX_INPUTS + i,
+                        PARTIAL_SBOX_CUBES + i,
+                    );
+                    state = <F as Poseidon>::mds_layer_circuit(builder, &state);
+                    round += 1;
+                }
+            }
+            <F as Poseidon>::constant_layer_circuit(builder, &mut state, round);
+            for (i, x) in state.iter_mut().enumerate() {
+                let offset = full_sbox(full_round, i);
+                *x = sbox(
+                    builder,
+                    *x,
+                    FULL_SBOX_INPUTS + offset,
+                    FULL_SBOX_CUBES + offset,
+                );
+            }
+            state = <F as Poseidon>::mds_layer_circuit(builder, &state);
+            round += 1;
+        }
+
+        for (output, x) in OUTPUT.zip(state) {
+            let constraint = builder.sub_extension(lv[output], x);
+            yield_constr.constraint(builder, constraint);
+        }
+    }
+
+    fn constraint_degree(&self) -> usize {
+        3
+    }
+
+    fn requires_ctls(&self) -> bool {
+        true
+    }
+}
+
+/// Proves the provided permutations with the [`PoseidonPermutationStark`]. The permutations must
+/// be in the order in which they were delegated, see the [module documentation](self).
+pub fn prove_poseidon_permutations<F, C, const D: usize>(
+    stark: &PoseidonPermutationStark<F, D>,
+    config: &StarkConfig,
+    permutations: &[PoseidonPermutation<F>],
+    timing: &mut TimingTree,
+) -> Result<StarkProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let values = permutations
+        .iter()
+        .map(PoseidonPermutation::values)
+        .collect::<Vec<_>>();
+    prove_delegated(
+        stark,
+        config,
+        stark.generate_trace(permutations),
+        poseidon_ctl(),
+        &values,
+        timing,
+    )
+}
+
+/// Verifies a [`PoseidonPermutationStark`] proof of exactly the provided permutations.
+pub fn verify_poseidon_permutations<F, C, const D: usize>(
+    stark: &PoseidonPermutationStark<F, D>,
+    proof: &StarkProofWithPublicInputs<F, C, D>,
+    permutations: &[PoseidonPermutation<F>],
+    config: &StarkConfig,
+) -> Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let values = permutations
+        .iter()
+        .map(PoseidonPermutation::values)
+        .collect::<Vec<_>>();
+    verify_delegated(stark, proof, poseidon_ctl(), &values, config)
+}
+
+/// Collects the permutations delegated by a plonky2 circuit to a [`PoseidonPermutationStark`],
+/// and checks them against a recursively verified proof. See the
+/// [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct PoseidonDelegationTarget {
+    permutations: Vec<Vec<Target>>,
+}
+
+impl PoseidonDelegationTarget {
+    /// Creates a new [`PoseidonDelegationTarget`], with no delegated permutation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of permutations delegated so far.
+    pub fn num_permutations(&self) -> usize {
+        self.permutations.len()
+    }
+
+    /// Delegates the permutation of `state`, returning the output state.
+    pub fn permute<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        state: [Target; SPONGE_WIDTH],
+    ) -> [Target; SPONGE_WIDTH] {
+        let output = builder.add_virtual_target_arr::<SPONGE_WIDTH>();
+        builder.add_simple_generator(PoseidonPermutationGenerator {
+            input: state.to_vec(),
+            output: output.to_vec(),
+        });
+        self.permutations
+            .push(state.into_iter().chain(output).collect());
+        output
+    }
+
+    /// Hashes `inputs` without padding, delegating the permutations. Matches
+    /// [`PoseidonPermutationLog::hash_no_pad`].
+    pub fn hash_no_pad<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        inputs: &[Target],
+    ) -> HashOutTarget {
+        let zero = builder.zero();
+        let mut state = [zero; SPONGE_WIDTH];
+        for chunk in inputs.chunks(SPONGE_RATE) {
+            state[..chunk.len()].copy_from_slice(chunk);
+            state = self.permute(builder, state);
+        }
+        HashOutTarget::from_partial(&state[..NUM_HASH_OUT_ELTS], zero)
+    }
+
+    /// Hashes `inputs` if they don't fit in a digest, or pads them with zeros otherwise,
+    /// delegating the permutations. Matches [`PoseidonPermutationLog::hash_or_noop`].
+    pub fn hash_or_noop<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        inputs: &[Target],
+    ) -> HashOutTarget {
+        if inputs.len() <= NUM_HASH_OUT_ELTS {
+            let zero = builder.zero();
+            HashOutTarget::from_partial(inputs, zero)
+        } else {
+            self.hash_no_pad(builder, inputs)
+        }
+    }
+
+    /// Compresses two digests, delegating the permutation. Matches
+    /// [`PoseidonPermutationLog::two_to_one`].
+    pub fn two_to_one<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        left: HashOutTarget,
+        right: HashOutTarget,
+    ) -> HashOutTarget {
+        let zero = builder.zero();
+        let mut state = [zero; SPONGE_WIDTH];
+        state[..NUM_HASH_OUT_ELTS].copy_from_slice(&left.elements);
+        state[NUM_HASH_OUT_ELTS..2 * NUM_HASH_OUT_ELTS].copy_from_slice(&right.elements);
+        let output = self.permute(builder, state);
+        HashOutTarget::from_partial(&output[..NUM_HASH_OUT_ELTS], zero)
+    }
+
+    /// Verifies that `leaf_data` is present at the index given by its little-endian bits in the
+    /// Merkle tree with the given cap, delegating the permutations. Matches
+    /// [`PoseidonPermutationLog::verify_merkle_proof_to_cap`] and
+    /// [`CircuitBuilder::verify_merkle_proof_to_cap`].
+    pub fn verify_merkle_proof_to_cap<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        leaf_data: &[Target],
+        leaf_index_bits: &[BoolTarget],
+        merkle_cap: &MerkleCapTarget,
+        proof: &MerkleProofTarget,
+    ) {
+        let mut digest = self.hash_or_noop(builder, leaf_data);
+        for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
+            let mut left = digest;
+            let mut right = sibling;
+            for i in 0..NUM_HASH_OUT_ELTS {
+                left.elements[i] = builder.select(bit, sibling.elements[i], digest.elements[i]);
+                right.elements[i] = builder.select(bit, digest.elements[i], sibling.elements[i]);
+            }
+            digest = self.two_to_one(builder, left, right);
+        }
+
+        let cap_index = builder.le_sum(leaf_index_bits[proof.siblings.len()..].iter());
+        for i in 0..NUM_HASH_OUT_ELTS {
+            let expected = builder.random_access(
+                cap_index,
+                merkle_cap.0.iter().map(|h| h.elements[i]).collect(),
+            );
+            builder.connect(expected, digest.elements[i]);
+        }
+    }
+
+    /// Adds a [`PoseidonPermutationStark`] proof target, verifies it recursively, and checks that
+    /// it proves exactly the delegated permutations. Returns the proof target, to be set with the
+    /// output of [`prove_poseidon_permutations`].
+    pub fn verify<F, C, const D: usize>(
+        self,
+        builder: &mut CircuitBuilder<F, D>,
+        stark: &PoseidonPermutationStark<F, D>,
+        config: &StarkConfig,
+    ) -> StarkProofWithPublicInputsTarget<D>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let degree_bits = log2_strict(PoseidonPermutationStark::<F, D>::num_rows(
+            self.permutations.len(),
+        ));
+        verify_delegated_circuit::<F, C, _, _, D>(
+            builder,
+            stark,
+            poseidon_ctl(),
+            &self.permutations,
+            degree_bits,
+            config,
+        )
+    }
+}
+
+/// Computes the output state of a delegated permutation.
+#[derive(Debug, Default)]
+pub struct PoseidonPermutationGenerator {
+    input: Vec<Target>,
+    output: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for PoseidonPermutationGenerator
+{
+    fn id(&self) -> String {
+        "PoseidonPermutationGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.input.clone()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let input = witness.get_targets(&self.input).try_into().unwrap();
+        let output = F::poseidon(input);
+
+        for (&target, value) in self.output.iter().zip(output) {
+            out_buffer.set_target(target, value)?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.input)?;
+        dst.write_target_vec(&self.output)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let input = src.read_target_vec()?;
+        let output = src.read_target_vec()?;
+        Ok(Self { input, output })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+
+    use anyhow::Result;
+    use plonky2::field::types::{Field, Sample};
+    use plonky2::hash::hash_types::{HashOutTarget, MerkleCapTarget};
+    use plonky2::hash::merkle_proofs::MerkleProofTarget;
+    use plonky2::hash::merkle_tree::MerkleTree;
+    use plonky2::hash::poseidon::PoseidonHash;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::config::StarkConfig;
+    use crate::poseidon::{
+        prove_poseidon_permutations, verify_poseidon_permutations, PoseidonDelegationTarget,
+        PoseidonPermutation, PoseidonPermutationLog, PoseidonPermutationStark,
+    };
+    use crate::recursive_verifier::set_stark_proof_with_pis_target;
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = PoseidonPermutationStark<F, D>;
+
+    #[test]
+    fn test_poseidon_log() {
+        let mut log = PoseidonPermutationLog::new();
+        for len in [0, 3, 8, 21] {
+            let inputs = F::rand_vec(len);
+            assert_eq!(log.hash_no_pad(&inputs), PoseidonHash::hash_no_pad(&inputs));
+            assert_eq!(
+                log.hash_or_noop(&inputs),
+                PoseidonHash::hash_or_noop(&inputs)
+            );
+        }
+        let (left, right) = (
+            PoseidonHash::hash_no_pad(&[F::ONE]),
+            PoseidonHash::hash_no_pad(&[F::TWO]),
+        );
+        assert_eq!(
+            log.two_to_one(left, right),
+            PoseidonHash::two_to_one(left, right)
+        );
+
+        let leaves = (0..16).map(|_| F::rand_vec(7)).collect::<Vec<_>>();
+        let tree = MerkleTree::<F, PoseidonHash>::new(leaves.clone(), 1);
+        log.verify_merkle_proof_to_cap(&leaves[11], 11, &tree.cap, &tree.prove(11))
+            .unwrap();
+        assert!(log
+            .verify_merkle_proof_to_cap(&leaves[11], 10, &tree.cap, &tree.prove(11))
+            .is_err());
+    }
+
+    #[test]
+    fn test_poseidon_stark() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let permutations = (0..40)
+            .map(|_| PoseidonPermutation {
+                input: F::rand_array(),
+            })
+            .collect::<Vec<_>>();
+
+        let proof = prove_poseidon_permutations::<F, C, D>(
+            &stark,
+            &config,
+            &permutations,
+            &mut TimingTree::default(),
+        )?;
+        verify_poseidon_permutations(&stark, &proof, &permutations, &config)?;
+
+        let mut other_permutations = permutations.clone();
+        other_permutations[7].input[3] += F::ONE;
+        assert!(
+            verify_poseidon_permutations(&stark, &proof, &other_permutations, &config).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_poseidon_stark_degree() -> Result<()> {
+        test_stark_low_degree(S::new())
+    }
+
+    #[test]
+    fn test_poseidon_stark_circuit() -> Result<()> {
+        test_stark_circuit_constraints::<F, C, S, D>(S::new())
+    }
+
+    #[test]
+    fn test_poseidon_delegation() -> Result<()> {
+        let stark_config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut delegation = PoseidonDelegationTarget::new();
+        let mut log = PoseidonPermutationLog::new();
+        let mut pw = PartialWitness::new();
+
+        // Open a few leaves of a Merkle tree with a cap of height 2.
+        let (depth, cap_height) = (6, 2);
+        let leaves = (0..1 << depth).map(|_| F::rand_vec(9)).collect::<Vec<_>>();
+        let tree = MerkleTree::<F, PoseidonHash>::new(leaves.clone(), cap_height);
+        let cap_target = MerkleCapTarget(
+            (0..1 << cap_height)
+                .map(|_| builder.add_virtual_hash())
+                .collect(),
+        );
+        for (target, &hash) in cap_target.0.iter().zip(&tree.cap.0) {
+            pw.set_hash_target(*target, hash)?;
+        }
+
+        for index in [0, 13, 63] {
+            let proof = tree.prove(index);
+            log.verify_merkle_proof_to_cap(&leaves[index], index, &tree.cap, &proof)?;
+
+            let leaf_target = builder.add_virtual_targets(leaves[index].len());
+            pw.set_target_arr(&leaf_target, &leaves[index])?;
+            let index_target = builder.add_virtual_target();
+            pw.set_target(index_target, F::from_canonical_usize(index))?;
+            let index_bits = builder.split_le(index_target, depth);
+            let proof_target = MerkleProofTarget {
+                siblings: (0..depth - cap_height)
+                    .map(|_| builder.add_virtual_hash())
+                    .collect::<Vec<HashOutTarget>>(),
+            };
+            for (target, &hash) in proof_target.siblings.iter().zip(&proof.siblings) {
+                pw.set_hash_target(*target, hash)?;
+            }
+            delegation.verify_merkle_proof_to_cap(
+                &mut builder,
+                &leaf_target,
+                &index_bits,
+                &cap_target,
+                &proof_target,
+            );
+        }
+
+        let proof_target = delegation.verify::<F, C, D>(&mut builder, &stark, &stark_config);
+        let zero = builder.zero();
+        let data = builder.build::<C>();
+
+        let proof = prove_poseidon_permutations::<F, C, D>(
+            &stark,
+            &stark_config,
+            &log.into_permutations(),
+            &mut TimingTree::default(),
+        )?;
+        let degree_bits = proof.proof.recover_degree_bits(&stark_config);
+        set_stark_proof_with_pis_target(&mut pw, &proof_target, &proof, degree_bits, zero)?;
+
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
@@ -1,31 +1,47 @@
 //! All the different proof types and their associated `circuit` versions
 //! to be used when proving (recursive) [`Stark`][crate::stark::Stark]
-//! statements
+//! statements.
+//!
+//! Proofs of standalone STARKs can be compressed to reduce their size, into the
+//! [`CompressedStarkProofWithPublicInputs`] format, and both formats can be written to and read
+//! from bytes.
 
 #[cfg(not(feature = "std"))]
 use alloc::{vec, vec::Vec};
+use core::mem::size_of;
 
+use anyhow::ensure;
+use hashbrown::HashMap;
 use itertools::Itertools;
 use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::polynomial::PolynomialCoeffs;
 use plonky2::fri::oracle::PolynomialBatch;
-use plonky2::fri::proof::{FriChallenges, FriChallengesTarget, FriProof, FriProofTarget};
+use plonky2::fri::proof::{
+    CompressedFriProof, CompressedFriQueryRounds, FriChallenges, FriChallengesTarget,
+    FriInitialTreeProof, FriProof, FriProofTarget, FriQueryRound, FriQueryStep,
+};
 use plonky2::fri::structure::{
     FriOpeningBatch, FriOpeningBatchTarget, FriOpenings, FriOpeningsTarget,
 };
+use plonky2::fri::FriParams;
 use plonky2::hash::hash_types::{MerkleCapTarget, RichField};
 use plonky2::hash::merkle_tree::MerkleCap;
+use plonky2::iop::challenger::Challenger;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::iop::target::Target;
 use plonky2::plonk::config::{GenericConfig, Hasher};
-use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+use plonky2::plonk::proof::FriInferredElements;
+use plonky2::util::serialization::{Buffer, IoError, IoResult, Read, Remaining, Write};
 use plonky2_maybe_rayon::*;
 use serde::{Deserialize, Serialize};
 
 use crate::config::StarkConfig;
+use crate::keys::StarkVerifyingKey;
 use crate::lookup::GrandProductChallengeSet;
+use crate::stark::Stark;
 
 /// Merkle caps and openings that form the proof of a single STARK.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(bound = "")]
 pub struct StarkProof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
     /// Merkle cap of LDEs of trace values.
@@ -50,6 +66,166 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> S
         let lde_bits = config.fri_config.cap_height + initial_merkle_proof.siblings.len();
         lde_bits - config.fri_config.rate_bits
     }
+
+    /// Compress the proof, given the FRI query indices of its challenges.
+    pub fn compress(self, indices: &[usize], params: &FriParams) -> CompressedStarkProof<F, C, D> {
+        let StarkProof {
+            trace_cap,
+            auxiliary_polys_cap,
+            quotient_polys_cap,
+            openings,
+            opening_proof,
+        } = self;
+
+        CompressedStarkProof {
+            degree_bits: params.degree_bits,
+            trace_cap,
+            auxiliary_polys_cap,
+            quotient_polys_cap,
+            openings,
+            opening_proof: opening_proof.compress(indices, params),
+        }
+    }
+
+    /// Serializes a STARK proof.
+    pub fn to_buffer(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+        buffer.write_merkle_cap(&self.trace_cap)?;
+        buffer.write_bool(self.auxiliary_polys_cap.is_some())?;
+        if let Some(cap) = &self.auxiliary_polys_cap {
+            buffer.write_merkle_cap(cap)?;
+        }
+        buffer.write_bool(self.quotient_polys_cap.is_some())?;
+        if let Some(cap) = &self.quotient_polys_cap {
+            buffer.write_merkle_cap(cap)?;
+        }
+        self.openings.to_buffer(buffer)?;
+        write_fri_proof(buffer, &self.opening_proof)
+    }
+
+    /// Deserializes a STARK proof, whose Merkle caps have the height given by `config`.
+    pub fn from_buffer(buffer: &mut Buffer, config: &StarkConfig) -> IoResult<Self> {
+        let cap_height = config.fri_config.cap_height;
+        let trace_cap = buffer.read_merkle_cap(cap_height)?;
+        let auxiliary_polys_cap = if buffer.read_bool()? {
+            Some(buffer.read_merkle_cap(cap_height)?)
+        } else {
+            None
+        };
+        let quotient_polys_cap = if buffer.read_bool()? {
+            Some(buffer.read_merkle_cap(cap_height)?)
+        } else {
+            None
+        };
+        let openings = StarkOpeningSet::from_buffer(buffer)?;
+        let opening_proof = read_fri_proof(buffer, cap_height)?;
+
+        Ok(Self {
+            trace_cap,
+            auxiliary_polys_cap,
+            quotient_polys_cap,
+            openings,
+            opening_proof,
+        })
+    }
+}
+
+/// A [`StarkProof`] whose FRI argument is compressed, see [`CompressedFriProof`].
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(bound = "")]
+pub struct CompressedStarkProof<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    /// Log of the length of the trace, which can't be recovered from the compressed Merkle proofs.
+    pub degree_bits: usize,
+    /// Merkle cap of LDEs of trace values.
+    pub trace_cap: MerkleCap<F, C::Hasher>,
+    /// Optional merkle cap of LDEs of permutation Z values, if any.
+    pub auxiliary_polys_cap: Option<MerkleCap<F, C::Hasher>>,
+    /// Merkle cap of LDEs of quotient polynomial values.
+    pub quotient_polys_cap: Option<MerkleCap<F, C::Hasher>>,
+    /// Purported values of each polynomial at the challenge point.
+    pub openings: StarkOpeningSet<F, D>,
+    /// A compressed batch FRI argument for all openings.
+    pub opening_proof: CompressedFriProof<F, C::Hasher, D>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    CompressedStarkProof<F, C, D>
+{
+    /// Decompress the proof.
+    pub(crate) fn decompress(
+        self,
+        challenges: &StarkProofChallenges<F, D>,
+        fri_inferred_elements: FriInferredElements<F, D>,
+        params: &FriParams,
+    ) -> StarkProof<F, C, D> {
+        let CompressedStarkProof {
+            trace_cap,
+            auxiliary_polys_cap,
+            quotient_polys_cap,
+            openings,
+            opening_proof,
+            ..
+        } = self;
+
+        StarkProof {
+            trace_cap,
+            auxiliary_polys_cap,
+            quotient_polys_cap,
+            openings,
+            opening_proof: opening_proof.decompress(
+                &challenges.fri_challenges,
+                fri_inferred_elements,
+                params,
+            ),
+        }
+    }
+
+    /// Serializes a compressed STARK proof.
+    pub fn to_buffer(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+        buffer.write_usize(self.degree_bits)?;
+        buffer.write_merkle_cap(&self.trace_cap)?;
+        buffer.write_bool(self.auxiliary_polys_cap.is_some())?;
+        if let Some(cap) = &self.auxiliary_polys_cap {
+            buffer.write_merkle_cap(cap)?;
+        }
+        buffer.write_bool(self.quotient_polys_cap.is_some())?;
+        if let Some(cap) = &self.quotient_polys_cap {
+            buffer.write_merkle_cap(cap)?;
+        }
+        self.openings.to_buffer(buffer)?;
+        write_compressed_fri_proof(buffer, &self.opening_proof)
+    }
+
+    /// Deserializes a compressed STARK proof, whose Merkle caps have the height given by `config`.
+    pub fn from_buffer(buffer: &mut Buffer, config: &StarkConfig) -> IoResult<Self> {
+        let cap_height = config.fri_config.cap_height;
+        let degree_bits = buffer.read_usize()?;
+        let trace_cap = buffer.read_merkle_cap(cap_height)?;
+        let auxiliary_polys_cap = if buffer.read_bool()? {
+            Some(buffer.read_merkle_cap(cap_height)?)
+        } else {
+            None
+        };
+        let quotient_polys_cap = if buffer.read_bool()? {
+            Some(buffer.read_merkle_cap(cap_height)?)
+        } else {
+            None
+        };
+        let openings = StarkOpeningSet::from_buffer(buffer)?;
+        let opening_proof = read_compressed_fri_proof(buffer, cap_height)?;
+
+        Ok(Self {
+            degree_bits,
+            trace_cap,
+            auxiliary_polys_cap,
+            quotient_polys_cap,
+            openings,
+            opening_proof,
+        })
+    }
 }
 
 /// Circuit version of [`StarkProof`].
@@ -127,7 +303,7 @@ impl<const D: usize> StarkProofTarget<D> {
 }
 
 /// Merkle caps and openings that form the proof of a single STARK, along with its public inputs.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(bound = "")]
 pub struct StarkProofWithPublicInputs<
     F: RichField + Extendable<D>,
@@ -141,6 +317,128 @@ pub struct StarkProofWithPublicInputs<
     pub public_inputs: Vec<F>,
 }
 
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    StarkProofWithPublicInputs<F, C, D>
+{
+    /// Compresses the proof of a standalone STARK, i.e. not part of a multi-STARK system.
+    /// `verifying_key` must be provided if the STARK has fixed columns.
+    pub fn compress(
+        self,
+        config: &StarkConfig,
+        verifying_key: Option<&StarkVerifyingKey<F, C, D>>,
+    ) -> CompressedStarkProofWithPublicInputs<F, C, D> {
+        let mut challenger = Challenger::<F, C::Hasher>::new();
+        if let Some(verifying_key) = verifying_key {
+            challenger.observe_cap(&verifying_key.fixed_cap);
+        }
+        let challenges = self.get_challenges(&mut challenger, None, false, config, None);
+        let degree_bits = self.proof.recover_degree_bits(config);
+        let compressed_proof = self.proof.compress(
+            &challenges.fri_challenges.fri_query_indices,
+            &config.fri_params(degree_bits),
+        );
+        CompressedStarkProofWithPublicInputs {
+            proof: compressed_proof,
+            public_inputs: self.public_inputs,
+        }
+    }
+
+    /// Serializes the proof, followed by its public inputs.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.proof
+            .to_buffer(&mut buffer)
+            .and_then(|()| buffer.write_field_vec(&self.public_inputs))
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Deserializes a proof written with [`Self::to_bytes`], generated with `config`.
+    pub fn from_bytes(bytes: Vec<u8>, config: &StarkConfig) -> anyhow::Result<Self> {
+        let mut buffer = Buffer::new(&bytes);
+        let proof = StarkProof::from_buffer(&mut buffer, config).map_err(anyhow::Error::msg)?;
+        let public_inputs = buffer
+            .read_field_vec(buffer.remaining() / size_of::<u64>())
+            .map_err(anyhow::Error::msg)?;
+        Ok(Self {
+            proof,
+            public_inputs,
+        })
+    }
+}
+
+/// A [`CompressedStarkProof`] along with its public inputs.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(bound = "")]
+pub struct CompressedStarkProofWithPublicInputs<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+> {
+    /// A compressed STARK proof.
+    pub proof: CompressedStarkProof<F, C, D>,
+    /// Public inputs associated to this STARK proof.
+    pub public_inputs: Vec<F>,
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    CompressedStarkProofWithPublicInputs<F, C, D>
+{
+    /// Decompresses the proof of the standalone STARK `stark`, compressed with
+    /// [`StarkProofWithPublicInputs::compress`] with the same `verifying_key`.
+    pub fn decompress<S: Stark<F, D>>(
+        self,
+        stark: &S,
+        config: &StarkConfig,
+        verifying_key: Option<&StarkVerifyingKey<F, C, D>>,
+    ) -> anyhow::Result<StarkProofWithPublicInputs<F, C, D>> {
+        ensure!(
+            self.public_inputs.len() == stark.num_public_inputs(),
+            "Number of public inputs doesn't match the STARK."
+        );
+        if let Some(verifying_key) = verifying_key {
+            ensure!(
+                self.proof.degree_bits == verifying_key.degree_bits,
+                "The trace and the fixed columns have different lengths"
+            );
+        }
+        let challenges = self.get_challenges(config, verifying_key);
+        let fri_inferred_elements = self.get_inferred_elements(stark, &challenges, config);
+        let params = config.fri_params(self.proof.degree_bits);
+        let decompressed_proof = self
+            .proof
+            .decompress(&challenges, fri_inferred_elements, &params);
+        Ok(StarkProofWithPublicInputs {
+            proof: decompressed_proof,
+            public_inputs: self.public_inputs,
+        })
+    }
+
+    /// Serializes the compressed proof, followed by its public inputs.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.proof
+            .to_buffer(&mut buffer)
+            .and_then(|()| buffer.write_field_vec(&self.public_inputs))
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Deserializes a compressed proof written with [`Self::to_bytes`], generated with `config`.
+    pub fn from_bytes(bytes: Vec<u8>, config: &StarkConfig) -> anyhow::Result<Self> {
+        let mut buffer = Buffer::new(&bytes);
+        let proof =
+            CompressedStarkProof::from_buffer(&mut buffer, config).map_err(anyhow::Error::msg)?;
+        let public_inputs = buffer
+            .read_field_vec(buffer.remaining() / size_of::<u64>())
+            .map_err(anyhow::Error::msg)?;
+        Ok(Self {
+            proof,
+            public_inputs,
+        })
+    }
+}
+
 /// Circuit version of [`StarkProofWithPublicInputs`].
 #[derive(Debug, Clone)]
 pub struct StarkProofWithPublicInputsTarget<const D: usize> {
@@ -200,12 +498,12 @@ pub struct MultiProofChallenges<F: RichField + Extendable<D>, const D: usize, co
 }
 
 /// Purported values of each polynomial at the challenge point.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(bound = "")]
 pub struct StarkOpeningSet<F: RichField + Extendable<D>, const D: usize> {
-    /// Openings of trace polynomials at `zeta`.
+    /// Openings of trace polynomials, followed by fixed polynomials, at `zeta`.
     pub local_values: Vec<F::Extension>,
-    /// Openings of trace polynomials at `g * zeta`.
+    /// Openings of trace polynomials, followed by fixed polynomials, at `g * zeta`.
     pub next_values: Vec<F::Extension>,
     /// Openings of lookups and cross-table lookups `Z` polynomials at `zeta`.
     pub auxiliary_polys: Option<Vec<F::Extension>>,
@@ -226,6 +524,7 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
         zeta: F::Extension,
         g: F,
         trace_commitment: &PolynomialBatch<F, C, D>,
+        fixed_commitment: Option<&PolynomialBatch<F, C, D>>,
         auxiliary_polys_commitment: Option<&PolynomialBatch<F, C, D>>,
         quotient_commitment: Option<&PolynomialBatch<F, C, D>>,
         num_lookup_columns: usize,
@@ -239,6 +538,14 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
                 .map(|p| p.to_extension().eval(z))
                 .collect::<Vec<_>>()
         };
+        // Batch evaluates trace polynomials, followed by fixed polynomials, at a point `z`.
+        let eval_trace_and_fixed = |z: F::Extension| {
+            let mut values = eval_commitment(z, trace_commitment);
+            if let Some(c) = fixed_commitment {
+                values.extend(eval_commitment(z, c));
+            }
+            values
+        };
         // Batch evaluates polynomials at a base field point `z`.
         let eval_commitment_base = |z: F, c: &PolynomialBatch<F, C, D>| {
             c.polynomials
@@ -251,8 +558,8 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
         // `g * zeta`.
         let zeta_next = zeta.scalar_mul(g);
         Self {
-            local_values: eval_commitment(zeta, trace_commitment),
-            next_values: eval_commitment(zeta_next, trace_commitment),
+            local_values: eval_trace_and_fixed(zeta),
+            next_values: eval_trace_and_fixed(zeta_next),
             auxiliary_polys: auxiliary_polys_commitment.map(|c| eval_commitment(zeta, c)),
             auxiliary_polys_next: auxiliary_polys_commitment.map(|c| eval_commitment(zeta_next, c)),
             ctl_zs_first: requires_ctl.then(|| {
@@ -263,6 +570,66 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
         }
     }
 
+    /// Serializes a STARK's opening set.
+    pub(crate) fn to_buffer(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+        write_field_ext_vec::<F, D>(buffer, &self.local_values)?;
+        write_field_ext_vec::<F, D>(buffer, &self.next_values)?;
+        buffer.write_bool(self.auxiliary_polys.is_some())?;
+        if let Some(poly) = &self.auxiliary_polys {
+            write_field_ext_vec::<F, D>(buffer, poly)?;
+        }
+        buffer.write_bool(self.auxiliary_polys_next.is_some())?;
+        if let Some(poly_next) = &self.auxiliary_polys_next {
+            write_field_ext_vec::<F, D>(buffer, poly_next)?;
+        }
+        buffer.write_bool(self.ctl_zs_first.is_some())?;
+        if let Some(ctl_zs_first) = &self.ctl_zs_first {
+            buffer.write_usize(ctl_zs_first.len())?;
+            buffer.write_field_vec(ctl_zs_first)?;
+        }
+        buffer.write_bool(self.quotient_polys.is_some())?;
+        if let Some(quotient_polys) = &self.quotient_polys {
+            write_field_ext_vec::<F, D>(buffer, quotient_polys)?;
+        }
+        Ok(())
+    }
+
+    /// Deserializes a STARK's opening set.
+    pub(crate) fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
+        let local_values = read_field_ext_vec::<F, D>(buffer)?;
+        let next_values = read_field_ext_vec::<F, D>(buffer)?;
+        let auxiliary_polys = if buffer.read_bool()? {
+            Some(read_field_ext_vec::<F, D>(buffer)?)
+        } else {
+            None
+        };
+        let auxiliary_polys_next = if buffer.read_bool()? {
+            Some(read_field_ext_vec::<F, D>(buffer)?)
+        } else {
+            None
+        };
+        let ctl_zs_first = if buffer.read_bool()? {
+            let len = buffer.read_usize()?;
+            Some(buffer.read_field_vec(len)?)
+        } else {
+            None
+        };
+        let quotient_polys = if buffer.read_bool()? {
+            Some(read_field_ext_vec::<F, D>(buffer)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            local_values,
+            next_values,
+            auxiliary_polys,
+            auxiliary_polys_next,
+            ctl_zs_first,
+            quotient_polys,
+        })
+    }
+
     /// Constructs the openings required by FRI.
     /// All openings but `ctl_zs_first` are grouped together.
     pub(crate) fn to_fri_openings(&self) -> FriOpenings<F, D> {
@@ -310,9 +677,9 @@ impl<F: RichField + Extendable<D>, const D: usize> StarkOpeningSet<F, D> {
 /// `Target`s for the purported values of each polynomial at the challenge point.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct StarkOpeningSetTarget<const D: usize> {
-    /// `ExtensionTarget`s for the openings of trace polynomials at `zeta`.
+    /// `ExtensionTarget`s for the openings of trace polynomials, followed by fixed polynomials, at `zeta`.
     pub local_values: Vec<ExtensionTarget<D>>,
-    /// `ExtensionTarget`s for the opening of trace polynomials at `g * zeta`.
+    /// `ExtensionTarget`s for the opening of trace polynomials, followed by fixed polynomials, at `g * zeta`.
     pub next_values: Vec<ExtensionTarget<D>>,
     /// `ExtensionTarget`s for the opening of lookups and cross-table lookups `Z` polynomials at `zeta`.
     pub auxiliary_polys: Option<Vec<ExtensionTarget<D>>>,
@@ -429,3 +796,205 @@ impl<const D: usize> StarkOpeningSetTarget<D> {
         FriOpeningsTarget { batches }
     }
 }
+
+// The number of polynomials opened in a STARK proof depends on the STARK, and on the other STARKs
+// of a multi-STARK system, so unlike in plonky2 proofs, all lengths are written along with the
+// values.
+
+fn write_field_ext_vec<F: RichField + Extendable<D>, const D: usize>(
+    buffer: &mut Vec<u8>,
+    v: &[F::Extension],
+) -> IoResult<()> {
+    buffer.write_usize(v.len())?;
+    buffer.write_field_ext_vec::<F, D>(v)
+}
+
+fn read_field_ext_vec<F: RichField + Extendable<D>, const D: usize>(
+    buffer: &mut Buffer,
+) -> IoResult<Vec<F::Extension>> {
+    let len = buffer.read_usize()?;
+    buffer.read_field_ext_vec::<F, D>(len)
+}
+
+fn write_fri_initial_proof<F: RichField, H: Hasher<F>>(
+    buffer: &mut Vec<u8>,
+    proof: &FriInitialTreeProof<F, H>,
+) -> IoResult<()> {
+    buffer.write_usize(proof.evals_proofs.len())?;
+    for (evals, merkle_proof) in &proof.evals_proofs {
+        buffer.write_usize(evals.len())?;
+        buffer.write_field_vec(evals)?;
+        buffer.write_merkle_proof(merkle_proof)?;
+    }
+    Ok(())
+}
+
+fn read_fri_initial_proof<F: RichField, H: Hasher<F>>(
+    buffer: &mut Buffer,
+) -> IoResult<FriInitialTreeProof<F, H>> {
+    let num_trees = buffer.read_usize()?;
+    let evals_proofs = (0..num_trees)
+        .map(|_| {
+            let len = buffer.read_usize()?;
+            Ok((buffer.read_field_vec(len)?, buffer.read_merkle_proof()?))
+        })
+        .collect::<IoResult<_>>()?;
+    Ok(FriInitialTreeProof { evals_proofs })
+}
+
+fn write_fri_query_step<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    buffer: &mut Vec<u8>,
+    step: &FriQueryStep<F, H, D>,
+) -> IoResult<()> {
+    write_field_ext_vec::<F, D>(buffer, &step.evals)?;
+    buffer.write_merkle_proof(&step.merkle_proof)
+}
+
+fn read_fri_query_step<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    buffer: &mut Buffer,
+) -> IoResult<FriQueryStep<F, H, D>> {
+    let evals = read_field_ext_vec::<F, D>(buffer)?;
+    let merkle_proof = buffer.read_merkle_proof()?;
+    Ok(FriQueryStep {
+        evals,
+        merkle_proof,
+    })
+}
+
+fn write_fri_proof<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    buffer: &mut Vec<u8>,
+    proof: &FriProof<F, H, D>,
+) -> IoResult<()> {
+    buffer.write_usize(proof.commit_phase_merkle_caps.len())?;
+    for cap in &proof.commit_phase_merkle_caps {
+        buffer.write_merkle_cap(cap)?;
+    }
+    buffer.write_usize(proof.query_round_proofs.len())?;
+    for round in &proof.query_round_proofs {
+        write_fri_initial_proof(buffer, &round.initial_trees_proof)?;
+        buffer.write_usize(round.steps.len())?;
+        for step in &round.steps {
+            write_fri_query_step(buffer, step)?;
+        }
+    }
+    write_field_ext_vec::<F, D>(buffer, &proof.final_poly.coeffs)?;
+    buffer.write_field(proof.pow_witness)
+}
+
+fn read_fri_proof<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    buffer: &mut Buffer,
+    cap_height: usize,
+) -> IoResult<FriProof<F, H, D>> {
+    let num_caps = buffer.read_usize()?;
+    let commit_phase_merkle_caps = (0..num_caps)
+        .map(|_| buffer.read_merkle_cap(cap_height))
+        .collect::<IoResult<_>>()?;
+    let num_rounds = buffer.read_usize()?;
+    let query_round_proofs = (0..num_rounds)
+        .map(|_| {
+            let initial_trees_proof = read_fri_initial_proof(buffer)?;
+            let num_steps = buffer.read_usize()?;
+            let steps = (0..num_steps)
+                .map(|_| read_fri_query_step(buffer))
+                .collect::<IoResult<_>>()?;
+            Ok(FriQueryRound {
+                initial_trees_proof,
+                steps,
+            })
+        })
+        .collect::<IoResult<_>>()?;
+    let final_poly = PolynomialCoeffs::new(read_field_ext_vec::<F, D>(buffer)?);
+    let pow_witness = buffer.read_field()?;
+    Ok(FriProof {
+        commit_phase_merkle_caps,
+        query_round_proofs,
+        final_poly,
+        pow_witness,
+    })
+}
+
+fn write_index(buffer: &mut Vec<u8>, index: usize) -> IoResult<()> {
+    buffer.write_u32(u32::try_from(index).map_err(|_| IoError)?)
+}
+
+fn read_index(buffer: &mut Buffer) -> IoResult<usize> {
+    buffer.read_u32().map(|i| i as usize)
+}
+
+fn write_compressed_fri_proof<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    buffer: &mut Vec<u8>,
+    proof: &CompressedFriProof<F, H, D>,
+) -> IoResult<()> {
+    buffer.write_usize(proof.commit_phase_merkle_caps.len())?;
+    for cap in &proof.commit_phase_merkle_caps {
+        buffer.write_merkle_cap(cap)?;
+    }
+
+    let query_rounds = &proof.query_round_proofs;
+    buffer.write_usize(query_rounds.indices.len())?;
+    for &index in &query_rounds.indices {
+        write_index(buffer, index)?;
+    }
+    // The maps are written sorted by index, so that the encoding is deterministic.
+    buffer.write_usize(query_rounds.initial_trees_proofs.len())?;
+    for (&index, initial_trees_proof) in query_rounds
+        .initial_trees_proofs
+        .iter()
+        .sorted_by_key(|&(&i, _)| i)
+    {
+        write_index(buffer, index)?;
+        write_fri_initial_proof(buffer, initial_trees_proof)?;
+    }
+    buffer.write_usize(query_rounds.steps.len())?;
+    for steps in &query_rounds.steps {
+        buffer.write_usize(steps.len())?;
+        for (&index, step) in steps.iter().sorted_by_key(|&(&i, _)| i) {
+            write_index(buffer, index)?;
+            write_fri_query_step(buffer, step)?;
+        }
+    }
+
+    write_field_ext_vec::<F, D>(buffer, &proof.final_poly.coeffs)?;
+    buffer.write_field(proof.pow_witness)
+}
+
+fn read_compressed_fri_proof<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize>(
+    buffer: &mut Buffer,
+    cap_height: usize,
+) -> IoResult<CompressedFriProof<F, H, D>> {
+    let num_caps = buffer.read_usize()?;
+    let commit_phase_merkle_caps = (0..num_caps)
+        .map(|_| buffer.read_merkle_cap(cap_height))
+        .collect::<IoResult<_>>()?;
+
+    let num_indices = buffer.read_usize()?;
+    let indices = (0..num_indices)
+        .map(|_| read_index(buffer))
+        .collect::<IoResult<_>>()?;
+    let num_initial_trees_proofs = buffer.read_usize()?;
+    let initial_trees_proofs = (0..num_initial_trees_proofs)
+        .map(|_| Ok((read_index(buffer)?, read_fri_initial_proof(buffer)?)))
+        .collect::<IoResult<HashMap<_, _>>>()?;
+    let num_reductions = buffer.read_usize()?;
+    let steps = (0..num_reductions)
+        .map(|_| {
+            let num_steps = buffer.read_usize()?;
+            (0..num_steps)
+                .map(|_| Ok((read_index(buffer)?, read_fri_query_step(buffer)?)))
+                .collect::<IoResult<HashMap<_, _>>>()
+        })
+        .collect::<IoResult<_>>()?;
+
+    let final_poly = PolynomialCoeffs::new(read_field_ext_vec::<F, D>(buffer)?);
+    let pow_witness = buffer.read_field()?;
+    Ok(CompressedFriProof {
+        commit_phase_merkle_caps,
+        query_round_proofs: CompressedFriQueryRounds {
+            indices,
+            initial_trees_proofs,
+            steps,
+        },
+        final_poly,
+        pow_witness,
+    })
+}
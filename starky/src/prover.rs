@@ -4,7 +4,7 @@
 use alloc::vec::Vec;
 use core::iter::once;
 
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
 use itertools::Itertools;
 use plonky2::field::extension::Extendable;
 use plonky2::field::packable::Packable;
@@ -21,19 +21,21 @@ use plonky2::iop::challenger::Challenger;
 use plonky2::plonk::config::GenericConfig;
 use plonky2::timed;
 use plonky2::util::timing::TimingTree;
-use plonky2::util::{log2_ceil, log2_strict, transpose};
+use plonky2::util::{log2_ceil, transpose};
 use plonky2_maybe_rayon::*;
 
 use crate::config::StarkConfig;
 use crate::constraint_consumer::ConstraintConsumer;
 use crate::cross_table_lookup::{get_ctl_auxiliary_polys, CtlCheckVars, CtlData};
 use crate::evaluation_frame::StarkEvaluationFrame;
+use crate::keys::StarkProvingKey;
 use crate::lookup::{
     get_grand_product_challenge_set, lookup_helper_columns, GrandProductChallengeSet, Lookup,
     LookupCheckVars,
 };
 use crate::proof::{StarkOpeningSet, StarkProof, StarkProofWithPublicInputs};
 use crate::stark::Stark;
+use crate::util::{commit_trace_columns, trace_degree_bits, trace_len};
 use crate::vanishing_poly::eval_vanishing_poly;
 
 /// From a STARK trace, computes a STARK proof to attest its correctness.
@@ -50,8 +52,71 @@ where
     C: GenericConfig<D, F = F>,
     S: Stark<F, D>,
 {
-    let degree = trace_poly_values[0].len();
-    let degree_bits = log2_strict(degree);
+    ensure!(
+        stark.num_fixed_columns() == 0,
+        "STARKs with fixed columns must be proven with `prove_with_key`"
+    );
+    prove_with_optional_key(
+        stark,
+        None,
+        config,
+        trace_poly_values,
+        public_inputs,
+        verifier_circuit_fri_params,
+        timing,
+    )
+}
+
+/// From a STARK trace, computes a STARK proof to attest its correctness, using the fixed columns
+/// committed to in `proving_key`.
+pub fn prove_with_key<F, C, S, const D: usize>(
+    stark: S,
+    proving_key: &StarkProvingKey<F, C, D>,
+    config: &StarkConfig,
+    trace_poly_values: Vec<PolynomialValues<F>>,
+    public_inputs: &[F],
+    verifier_circuit_fri_params: Option<FriParams>,
+    timing: &mut TimingTree,
+) -> Result<StarkProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: Stark<F, D>,
+{
+    ensure!(
+        trace_poly_values[0].len() == proving_key.fixed_values[0].len(),
+        "The trace and the fixed columns have different lengths"
+    );
+    prove_with_optional_key(
+        stark,
+        Some(proving_key),
+        config,
+        trace_poly_values,
+        public_inputs,
+        verifier_circuit_fri_params,
+        timing,
+    )
+}
+
+fn prove_with_optional_key<F, C, S, const D: usize>(
+    stark: S,
+    proving_key: Option<&StarkProvingKey<F, C, D>>,
+    config: &StarkConfig,
+    trace_poly_values: Vec<PolynomialValues<F>>,
+    public_inputs: &[F],
+    verifier_circuit_fri_params: Option<FriParams>,
+    timing: &mut TimingTree,
+) -> Result<StarkProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: Stark<F, D>,
+{
+    let cofactor = stark.trace_len_cofactor();
+    let trace_len = trace_poly_values[0].len();
+    let degree_bits = trace_degree_bits(trace_len, cofactor).ok_or_else(|| {
+        anyhow!("The trace length {trace_len} is not {cofactor} times a power of two")
+    })?;
     let fri_params = config.fri_params(degree_bits);
     let rate_bits = config.fri_config.rate_bits;
     let cap_height = config.fri_config.cap_height;
@@ -83,18 +148,20 @@ where
     let trace_commitment = timed!(
         timing,
         "compute trace commitment",
-        PolynomialBatch::<F, C, D>::from_values(
+        commit_trace_columns::<F, C, D>(
             trace_poly_values.clone(),
+            degree_bits,
             rate_bits,
-            false,
             cap_height,
             timing,
-            None,
         )
     );
 
     let trace_cap = trace_commitment.merkle_tree.cap.clone();
     let mut challenger = Challenger::new();
+    if let Some(pk) = proving_key {
+        challenger.observe_cap(&pk.fixed_commitment.merkle_tree.cap);
+    }
     challenger.observe_elements(public_inputs);
     challenger.observe_cap(&trace_cap);
     prove_with_commitment(
@@ -102,6 +169,7 @@ where
         config,
         &trace_poly_values,
         &trace_commitment,
+        proving_key,
         None,
         None,
         &mut challenger,
@@ -119,11 +187,13 @@ where
 /// - all the required polynomial and FRI argument openings.
 /// - individual `ctl_data` and common `ctl_challenges` if the STARK is part
 ///   of a multi-STARK system.
+/// - the `proving_key` holding the fixed columns, if the STARK has any.
 pub fn prove_with_commitment<F, C, S, const D: usize>(
     stark: &S,
     config: &StarkConfig,
     trace_poly_values: &[PolynomialValues<F>],
     trace_commitment: &PolynomialBatch<F, C, D>,
+    proving_key: Option<&StarkProvingKey<F, C, D>>,
     ctl_data: Option<&CtlData<F>>,
     ctl_challenges: Option<&GrandProductChallengeSet<F>>,
     challenger: &mut Challenger<F, C::Hasher>,
@@ -137,8 +207,17 @@ where
     C: GenericConfig<D, F = F>,
     S: Stark<F, D>,
 {
-    let degree = trace_poly_values[0].len();
-    let degree_bits = log2_strict(degree);
+    let cofactor = stark.trace_len_cofactor();
+    let trace_len = trace_poly_values[0].len();
+    let degree_bits = trace_degree_bits(trace_len, cofactor).ok_or_else(|| {
+        anyhow!("The trace length {trace_len} is not {cofactor} times a power of two")
+    })?;
+    ensure!(
+        cofactor == 1 || (ctl_data.is_none() && !stark.requires_ctls()),
+        "Traces whose length is not a power of two cannot take part in cross-table lookups"
+    );
+    // The degree bound of the trace polynomials, which is also the size of the quotient chunks.
+    let degree = 1 << degree_bits;
     let fri_params = config.fri_params(degree_bits);
     let rate_bits = config.fri_config.rate_bits;
     let cap_height = config.fri_config.cap_height;
@@ -152,6 +231,17 @@ where
         constraint_degree <= (1 << rate_bits) + 1,
         "The degree of the Stark constraints must be <= blowup_factor + 1"
     );
+    ensure!(
+        proving_key.is_some() == (stark.num_fixed_columns() > 0),
+        "A proving key must be provided if and only if the STARK has fixed columns"
+    );
+    let fixed_commitment = proving_key.map(|pk| &pk.fixed_commitment);
+    ensure!(
+        trace_poly_values.len() == stark.num_columns(),
+        "The trace has {} columns, expected {}",
+        trace_poly_values.len(),
+        stark.num_columns()
+    );
 
     // Permutation arguments.
     let lookup_challenges = stark.uses_lookups().then(|| {
@@ -167,6 +257,15 @@ where
     });
 
     let lookups = stark.lookups();
+    // Lookups may refer to fixed columns, which follow the trace columns.
+    let trace_and_fixed_values;
+    let lookup_columns = match proving_key {
+        Some(pk) if stark.uses_lookups() => {
+            trace_and_fixed_values = [trace_poly_values, &pk.fixed_values].concat();
+            &trace_and_fixed_values[..]
+        }
+        _ => trace_poly_values,
+    };
     let lookup_helper_columns = timed!(
         timing,
         "compute lookup helper columns",
@@ -176,7 +275,7 @@ where
                 for &challenge in challenges {
                     columns.extend(lookup_helper_columns(
                         lookup,
-                        trace_poly_values,
+                        lookup_columns,
                         challenge,
                         constraint_degree,
                     ));
@@ -210,13 +309,12 @@ where
         timed!(
             timing,
             "compute auxiliary polynomials commitment",
-            PolynomialBatch::from_values(
+            commit_trace_columns(
                 aux_polys,
+                degree_bits,
                 rate_bits,
-                false,
                 config.fri_config.cap_height,
                 timing,
-                None,
             )
         )
     });
@@ -240,6 +338,7 @@ where
         check_constraints(
             stark,
             trace_commitment,
+            fixed_commitment,
             public_inputs,
             &auxiliary_polys_commitment,
             lookup_challenges.as_ref(),
@@ -258,6 +357,7 @@ where
         compute_quotient_polys::<F, <F as Packable>::Packing, C, S, D>(
             stark,
             trace_commitment,
+            fixed_commitment,
             &auxiliary_polys_commitment,
             lookup_challenges.as_ref(),
             &lookups,
@@ -313,9 +413,9 @@ where
     // To avoid leaking witness data, we want to ensure that our opening locations, `zeta` and
     // `g * zeta`, are not in our subgroup `H`. It suffices to check `zeta` only, since
     // `(g * zeta)^n = zeta^n`, where `n` is the order of `g`.
-    let g = F::primitive_root_of_unity(degree_bits);
+    let g = F::primitive_root_of_unity_of_order(trace_len);
     ensure!(
-        zeta.exp_power_of_2(degree_bits) != F::Extension::ONE,
+        zeta.exp_u64(trace_len as u64) != F::Extension::ONE,
         "Opening point is in the subgroup."
     );
 
@@ -324,6 +424,7 @@ where
         zeta,
         g,
         trace_commitment,
+        fixed_commitment,
         auxiliary_polys_commitment.as_ref(),
         quotient_commitment.as_ref(),
         stark.num_lookup_helper_columns(config),
@@ -336,6 +437,7 @@ where
     let initial_merkle_trees = once(trace_commitment)
         .chain(&auxiliary_polys_commitment)
         .chain(&quotient_commitment)
+        .chain(fixed_commitment)
         .collect_vec();
 
     let opening_proof = timed!(
@@ -371,6 +473,7 @@ where
 fn compute_quotient_polys<'a, F, P, C, S, const D: usize>(
     stark: &S,
     trace_commitment: &'a PolynomialBatch<F, C, D>,
+    fixed_commitment: Option<&'a PolynomialBatch<F, C, D>>,
     auxiliary_polys_commitment: &'a Option<PolynomialBatch<F, C, D>>,
     lookup_challenges: Option<&'a Vec<F>>,
     lookups: &[Lookup<F>],
@@ -393,6 +496,7 @@ where
     }
 
     let degree = 1 << degree_bits;
+    let trace_len = trace_len(degree_bits, stark.trace_len_cofactor());
     let rate_bits = config.fri_config.rate_bits;
     let total_num_helper_cols: usize = num_ctl_columns.iter().sum();
 
@@ -405,21 +509,77 @@ where
     // When opening the `Z`s polys at the "next" point, need to look at the point `next_step` steps away.
     let next_step = 1 << quotient_degree_bits;
 
-    // Evaluation of the first Lagrange polynomial on the LDE domain.
-    let lagrange_first = PolynomialValues::selector(degree, 0).lde_onto_coset(quotient_degree_bits);
-    // Evaluation of the last Lagrange polynomial on the LDE domain.
-    let lagrange_last =
-        PolynomialValues::selector(degree, degree - 1).lde_onto_coset(quotient_degree_bits);
+    // Evaluation of the Lagrange polynomial of the given row on the LDE domain.
+    let lagrange_on_coset = |row| {
+        if trace_len == degree {
+            PolynomialValues::selector(degree, row).lde_onto_coset(quotient_degree_bits)
+        } else {
+            PolynomialValues::selector(trace_len, row)
+                .mixed_radix_ifft()
+                .padded(degree)
+                .lde(quotient_degree_bits)
+                .coset_fft(F::coset_shift())
+        }
+    };
+    let lagrange_first = lagrange_on_coset(0);
+    let lagrange_last = lagrange_on_coset(trace_len - 1);
+
+    let z_h_on_coset = ZeroPolyOnCoset::<F>::new_with_subgroup_order(
+        trace_len,
+        degree_bits + quotient_degree_bits,
+    );
 
-    let z_h_on_coset = ZeroPolyOnCoset::<F>::new(degree_bits, quotient_degree_bits);
+    let g = F::primitive_root_of_unity_of_order(trace_len);
+    let size = degree << quotient_degree_bits;
+    // When the trace length is a power of two, the next row of a point of the LDE domain is
+    // `next_step` points away. Otherwise, `g * x` is not in the LDE domain, so the polynomials
+    // shifted by `g` are evaluated on it instead.
+    let shifted_ldes = |commitment: &PolynomialBatch<F, C, D>| {
+        (trace_len != degree).then(|| shifted_coset_ldes(commitment, g, quotient_degree_bits))
+    };
+    let trace_next_ldes = shifted_ldes(trace_commitment);
+    let fixed_next_ldes = fixed_commitment.map(shifted_ldes);
+    let auxiliary_next_ldes = auxiliary_polys_commitment.as_ref().map(shifted_ldes);
+    let get_next_values_packed = |commitment: &PolynomialBatch<F, C, D>,
+                                  next_ldes: &Option<Vec<PolynomialValues<F>>>,
+                                  i_start: usize|
+     -> Vec<P> {
+        match next_ldes {
+            Some(ldes) => ldes
+                .iter()
+                .map(|lde| *P::from_slice(&lde.values[i_start..i_start + P::WIDTH]))
+                .collect(),
+            None => commitment.get_lde_values_packed((i_start + next_step) % size, step),
+        }
+    };
 
-    // Retrieve the LDE values at index `i`.
-    let get_trace_values_packed =
-        |i_start| -> Vec<P> { trace_commitment.get_lde_values_packed(i_start, step) };
+    // Retrieve the LDE values of the trace, followed by the fixed columns, at index `i`, or at the
+    // next row if `next` is set.
+    let get_trace_values_packed = |i_start, next: bool| -> Vec<P> {
+        let mut values = if next {
+            get_next_values_packed(trace_commitment, &trace_next_ldes, i_start)
+        } else {
+            trace_commitment.get_lde_values_packed(i_start, step)
+        };
+        if let (Some(c), Some(next_ldes)) = (fixed_commitment, &fixed_next_ldes) {
+            values.extend(if next {
+                get_next_values_packed(c, next_ldes, i_start)
+            } else {
+                c.get_lde_values_packed(i_start, step)
+            });
+        }
+        values
+    };
+    let get_auxiliary_next_values_packed = |i_start| -> Vec<P> {
+        get_next_values_packed(
+            auxiliary_polys_commitment.as_ref().unwrap(),
+            auxiliary_next_ldes.as_ref().unwrap(),
+            i_start,
+        )
+    };
 
     // Last element of the subgroup.
-    let last = F::primitive_root_of_unity(degree_bits).inverse();
-    let size = degree << quotient_degree_bits;
+    let last = g.inverse();
     let coset = F::cyclic_subgroup_coset_known_order(
         F::primitive_root_of_unity(degree_bits + quotient_degree_bits),
         F::coset_shift(),
@@ -432,7 +592,6 @@ where
         .into_par_iter()
         .step_by(P::WIDTH)
         .flat_map_iter(|i_start| {
-            let i_next_start = (i_start + next_step) % size;
             let i_range = i_start..i_start + P::WIDTH;
 
             let x = *P::from_slice(&coset[i_range.clone()]);
@@ -449,8 +608,8 @@ where
             // Get the local and next row evaluations for the current STARK,
             // as well as the public inputs.
             let vars = S::EvaluationFrame::from_values(
-                &get_trace_values_packed(i_start),
-                &get_trace_values_packed(i_next_start),
+                &get_trace_values_packed(i_start, false),
+                &get_trace_values_packed(i_start, true),
                 public_inputs,
             );
             // Get the local and next row evaluations for the permutation argument,
@@ -461,10 +620,7 @@ where
                     .unwrap()
                     .get_lde_values_packed(i_start, step)[..num_lookup_columns]
                     .to_vec(),
-                next_values: auxiliary_polys_commitment
-                    .as_ref()
-                    .unwrap()
-                    .get_lde_values_packed(i_next_start, step)[..num_lookup_columns]
+                next_values: get_auxiliary_next_values_packed(i_start)[..num_lookup_columns]
                     .to_vec(),
                 challenges: challenges.to_vec(),
             });
@@ -498,10 +654,7 @@ where
                                 .unwrap()
                                 .get_lde_values_packed(i_start, step)
                                 [num_lookup_columns + total_num_helper_cols + i],
-                            next_z: auxiliary_polys_commitment
-                                .as_ref()
-                                .unwrap()
-                                .get_lde_values_packed(i_next_start, step)
+                            next_z: get_auxiliary_next_values_packed(i_start)
                                 [num_lookup_columns + total_num_helper_cols + i],
                             challenges: zs_columns.challenge,
                             columns: zs_columns.columns.clone(),
@@ -553,6 +706,35 @@ where
     )
 }
 
+/// Evaluates the polynomials of `commitment` shifted by `g`, i.e. `P(g * X)`, on the coset of size
+/// `2^(degree_log + quotient_degree_bits)` on which the quotient polynomials are computed.
+fn shifted_coset_ldes<F, C, const D: usize>(
+    commitment: &PolynomialBatch<F, C, D>,
+    g: F,
+    quotient_degree_bits: usize,
+) -> Vec<PolynomialValues<F>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    commitment
+        .polynomials
+        .par_iter()
+        .map(|poly| {
+            let shifted = PolynomialCoeffs::new(
+                poly.coeffs
+                    .iter()
+                    .zip(g.powers())
+                    .map(|(&c, g_pow)| c * g_pow)
+                    .collect(),
+            );
+            shifted
+                .lde(quotient_degree_bits)
+                .coset_fft(F::coset_shift())
+        })
+        .collect()
+}
+
 /// Check that all constraints evaluate to zero on `H`.
 /// Can also be used to check the degree of the constraints by evaluating on a larger subgroup.
 ///
@@ -565,6 +747,7 @@ where
 fn check_constraints<'a, F, C, S, const D: usize>(
     stark: &S,
     trace_commitment: &'a PolynomialBatch<F, C, D>,
+    fixed_commitment: Option<&'a PolynomialBatch<F, C, D>>,
     public_inputs: &[F],
     auxiliary_commitment: &'a Option<PolynomialBatch<F, C, D>>,
     lookup_challenges: Option<&'a Vec<F>>,
@@ -581,7 +764,7 @@ fn check_constraints<'a, F, C, S, const D: usize>(
 {
     use core::any::type_name;
 
-    let degree = 1 << degree_bits;
+    let degree = trace_len(degree_bits, stark.trace_len_cofactor());
     let rate_bits = 0; // Set this to higher value to check constraint degree.
     let total_num_helper_cols: usize = num_ctl_helper_cols.iter().sum();
 
@@ -589,28 +772,46 @@ fn check_constraints<'a, F, C, S, const D: usize>(
     let step = 1 << rate_bits;
 
     // Evaluation of the first Lagrange polynomial.
-    let lagrange_first = PolynomialValues::selector(degree, 0).lde(rate_bits);
+    let lagrange_first = PolynomialValues::selector(degree, 0)
+        .mixed_radix_ifft()
+        .padded(size)
+        .mixed_radix_fft();
     // Evaluation of the last Lagrange polynomial.
-    let lagrange_last = PolynomialValues::selector(degree, degree - 1).lde(rate_bits);
+    let lagrange_last = PolynomialValues::selector(degree, degree - 1)
+        .mixed_radix_ifft()
+        .padded(size)
+        .mixed_radix_fft();
 
-    let subgroup = F::two_adic_subgroup(degree_bits + rate_bits);
+    let subgroup = F::cyclic_subgroup_known_order(F::primitive_root_of_unity_of_order(size), size);
 
     // Get the evaluations of a batch of polynomials over our subgroup.
     let get_subgroup_evals = |comm: &PolynomialBatch<F, C, D>| -> Vec<Vec<F>> {
         let values = comm
             .polynomials
             .par_iter()
-            .map(|coeffs| coeffs.clone().fft().values)
+            .map(|coeffs| {
+                // The coefficients are padded to a power of two, which may exceed `size` when the
+                // trace length is not a power of two. The extra coefficients are then zero.
+                let mut coeffs = coeffs.clone();
+                coeffs.coeffs.resize(size, F::ZERO);
+                coeffs.mixed_radix_fft().values
+            })
             .collect::<Vec<_>>();
         transpose(&values)
     };
 
     // Get batch evaluations of the trace and permutation polynomials over our subgroup.
-    let trace_subgroup_evals = get_subgroup_evals(trace_commitment);
+    let mut trace_subgroup_evals = get_subgroup_evals(trace_commitment);
+    // The fixed columns follow the trace columns.
+    if let Some(c) = fixed_commitment {
+        for (row, fixed_row) in trace_subgroup_evals.iter_mut().zip(get_subgroup_evals(c)) {
+            row.extend(fixed_row);
+        }
+    }
     let auxiliary_subgroup_evals = auxiliary_commitment.as_ref().map(get_subgroup_evals);
 
     // Last element of the subgroup.
-    let last = F::primitive_root_of_unity(degree_bits).inverse();
+    let last = F::primitive_root_of_unity_of_order(degree).inverse();
 
     let constraint_values = (0..size)
         .map(|i| {
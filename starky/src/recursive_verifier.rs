@@ -9,7 +9,7 @@ use anyhow::{ensure, Result};
 use itertools::Itertools;
 use plonky2::field::extension::Extendable;
 use plonky2::fri::witness_util::set_fri_proof_target;
-use plonky2::hash::hash_types::RichField;
+use plonky2::hash::hash_types::{MerkleCapTarget, RichField};
 use plonky2::iop::challenger::RecursiveChallenger;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::iop::target::Target;
@@ -23,12 +23,14 @@ use crate::config::StarkConfig;
 use crate::constraint_consumer::RecursiveConstraintConsumer;
 use crate::cross_table_lookup::CtlCheckVarsTarget;
 use crate::evaluation_frame::StarkEvaluationFrame;
+use crate::keys::StarkVerifyingKey;
 use crate::lookup::LookupCheckVarsTarget;
 use crate::proof::{
     StarkOpeningSetTarget, StarkProof, StarkProofChallengesTarget, StarkProofTarget,
     StarkProofWithPublicInputs, StarkProofWithPublicInputsTarget,
 };
 use crate::stark::Stark;
+use crate::util::trace_len;
 use crate::vanishing_poly::eval_vanishing_poly_circuit;
 
 /// Encodes the verification of a [`StarkProofWithPublicInputsTarget`]
@@ -47,7 +49,10 @@ pub fn verify_stark_proof_circuit<
 ) where
     C::Hasher: AlgebraicHasher<F>,
 {
-    assert_eq!(proof_with_pis.public_inputs.len(), S::PUBLIC_INPUTS);
+    assert_eq!(
+        proof_with_pis.public_inputs.len(),
+        stark.num_public_inputs()
+    );
     let max_degree_bits_to_support = proof_with_pis.proof.recover_degree_bits(inner_config);
 
     let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(builder);
@@ -64,12 +69,65 @@ pub fn verify_stark_proof_circuit<
         &proof_with_pis.public_inputs,
         challenges,
         None,
+        None,
         inner_config,
         max_degree_bits_to_support,
         min_degree_bits_to_support,
     );
 }
 
+/// Encodes the verification of a [`StarkProofWithPublicInputsTarget`]
+/// for some statement with fixed columns, whose commitment is held by
+/// `verifying_key`, in a circuit.
+pub fn verify_stark_proof_with_key_circuit<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: Stark<F, D>,
+    const D: usize,
+>(
+    builder: &mut CircuitBuilder<F, D>,
+    stark: S,
+    verifying_key: &StarkVerifyingKey<F, C, D>,
+    proof_with_pis: StarkProofWithPublicInputsTarget<D>,
+    inner_config: &StarkConfig,
+) where
+    C::Hasher: AlgebraicHasher<F>,
+{
+    assert_eq!(
+        proof_with_pis.public_inputs.len(),
+        stark.num_public_inputs()
+    );
+    let degree_bits = verifying_key.degree_bits;
+    assert_eq!(
+        proof_with_pis.proof.recover_degree_bits(inner_config),
+        degree_bits
+    );
+    let expected_degree_bits = builder.constant(F::from_canonical_usize(degree_bits));
+    builder.connect(proof_with_pis.proof.degree_bits, expected_degree_bits);
+
+    let fixed_cap = builder.constant_merkle_cap(&verifying_key.fixed_cap);
+    let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(builder);
+    challenger.observe_cap(&fixed_cap);
+    let challenges = with_context!(
+        builder,
+        "compute challenges",
+        proof_with_pis.get_challenges::<F, C>(builder, &mut challenger, None, false, inner_config)
+    );
+
+    verify_stark_proof_with_challenges_circuit::<F, C, S, D>(
+        builder,
+        &stark,
+        &proof_with_pis.proof,
+        &proof_with_pis.public_inputs,
+        challenges,
+        None,
+        Some(&fixed_cap),
+        inner_config,
+        degree_bits,
+        None,
+    );
+}
+
 /// Recursively verifies an inner STARK proof.
 pub fn verify_stark_proof_with_challenges_circuit<
     F: RichField + Extendable<D>,
@@ -83,6 +141,7 @@ pub fn verify_stark_proof_with_challenges_circuit<
     public_inputs: &[Target],
     challenges: StarkProofChallengesTarget<D>,
     ctl_vars: Option<&[CtlCheckVarsTarget<F, D>]>,
+    fixed_cap: Option<&MerkleCapTarget>,
     inner_config: &StarkConfig,
     degree_bits: usize,
     min_degree_bits_to_support: Option<usize>,
@@ -90,6 +149,11 @@ pub fn verify_stark_proof_with_challenges_circuit<
     C::Hasher: AlgebraicHasher<F>,
 {
     check_lookup_options(stark, proof, &challenges).unwrap();
+    assert_eq!(
+        fixed_cap.is_some(),
+        stark.num_fixed_columns() > 0,
+        "A fixed cap must be provided if and only if the STARK has fixed columns"
+    );
 
     let zero = builder.zero();
     let one = builder.one_extension();
@@ -120,21 +184,44 @@ pub fn verify_stark_proof_with_challenges_circuit<
     // degree_bits should be nonzero.
     let _ = builder.inverse(proof.degree_bits);
 
-    let max_num_of_bits_in_degree = degree_bits + 1;
-    let degree = builder.exp(two, proof.degree_bits, max_num_of_bits_in_degree);
-    let degree_bits_vec = builder.split_le(degree, max_num_of_bits_in_degree);
-
-    let zeta_pow_deg = builder.exp_extension_from_bits(challenges.stark_zeta, &degree_bits_vec);
-    let z_h_zeta = builder.sub_extension(zeta_pow_deg, one);
-    let degree_ext = builder.convert_to_ext(degree);
-
-    // Calculate primitive_root_of_unity(degree_bits)
-    let two_adicity = builder.constant(F::from_canonical_usize(F::TWO_ADICITY));
-    let two_adicity_sub_degree_bits = builder.sub(two_adicity, proof.degree_bits);
-    let two_exp_two_adicity_sub_degree_bits =
-        builder.exp(two, two_adicity_sub_degree_bits, F::TWO_ADICITY);
-    let base = builder.constant(F::POWER_OF_TWO_GENERATOR);
-    let g = builder.exp(base, two_exp_two_adicity_sub_degree_bits, F::TWO_ADICITY);
+    let cofactor = stark.trace_len_cofactor();
+    let (degree, zeta_pow_deg, z_h_zeta, degree_ext, g) = if cofactor == 1 {
+        let max_num_of_bits_in_degree = degree_bits + 1;
+        let degree = builder.exp(two, proof.degree_bits, max_num_of_bits_in_degree);
+        let degree_bits_vec = builder.split_le(degree, max_num_of_bits_in_degree);
+
+        let zeta_pow_deg = builder.exp_extension_from_bits(challenges.stark_zeta, &degree_bits_vec);
+        let z_h_zeta = builder.sub_extension(zeta_pow_deg, one);
+        let degree_ext = builder.convert_to_ext(degree);
+
+        // Calculate primitive_root_of_unity(degree_bits)
+        let two_adicity = builder.constant(F::from_canonical_usize(F::TWO_ADICITY));
+        let two_adicity_sub_degree_bits = builder.sub(two_adicity, proof.degree_bits);
+        let two_exp_two_adicity_sub_degree_bits =
+            builder.exp(two, two_adicity_sub_degree_bits, F::TWO_ADICITY);
+        let base = builder.constant(F::POWER_OF_TWO_GENERATOR);
+        let g = builder.exp(base, two_exp_two_adicity_sub_degree_bits, F::TWO_ADICITY);
+        (degree, zeta_pow_deg, z_h_zeta, degree_ext, g)
+    } else {
+        // The trace length `n` is not a power of two, so the circuit only supports proofs of
+        // degree `degree_bits`, for which `n` and the generator of `H` are constants.
+        assert!(
+            min_degree_bits_to_support.is_none(),
+            "Traces whose length is not a power of two only support a single degree"
+        );
+        let expected_degree_bits = builder.constant(F::from_canonical_usize(degree_bits));
+        builder.connect(proof.degree_bits, expected_degree_bits);
+
+        let trace_len = trace_len(degree_bits, cofactor);
+        let degree = builder.constant(F::from_canonical_usize(1 << degree_bits));
+        let zeta_pow_deg = builder.exp_power_of_2_extension(challenges.stark_zeta, degree_bits);
+        let zeta_pow_n = builder.exp_u64_extension(challenges.stark_zeta, trace_len as u64);
+        let z_h_zeta = builder.sub_extension(zeta_pow_n, one);
+        let n = builder.constant(F::from_canonical_usize(trace_len));
+        let degree_ext = builder.convert_to_ext(n);
+        let g = builder.constant(F::primitive_root_of_unity_of_order(trace_len));
+        (degree, zeta_pow_deg, z_h_zeta, degree_ext, g)
+    };
     let g_ext = builder.convert_to_ext(g);
 
     let (l_0, l_last) =
@@ -198,6 +285,7 @@ pub fn verify_stark_proof_with_challenges_circuit<
     let merkle_caps = once(proof.trace_cap.clone())
         .chain(proof.auxiliary_polys_cap.clone())
         .chain(proof.quotient_polys_cap.clone())
+        .chain(fixed_cap.cloned())
         .collect_vec();
 
     let fri_instance = stark.fri_instance_target(
@@ -277,7 +365,7 @@ pub fn add_virtual_stark_proof_with_pis<
         num_ctl_helper_zs,
         num_ctl_zs,
     );
-    let public_inputs = builder.add_virtual_targets(S::PUBLIC_INPUTS);
+    let public_inputs = builder.add_virtual_targets(stark.num_public_inputs());
     StarkProofWithPublicInputsTarget {
         proof,
         public_inputs,
@@ -296,7 +384,7 @@ pub fn add_virtual_stark_proof<F: RichField + Extendable<D>, S: Stark<F, D>, con
     let fri_params = config.fri_params(degree_bits);
     let cap_height = fri_params.config.cap_height;
 
-    let num_leaves_per_oracle = once(S::COLUMNS)
+    let num_leaves_per_oracle = once(stark.num_columns())
         .chain(
             (stark.uses_lookups() || stark.requires_ctls())
                 .then(|| stark.num_lookup_helper_columns(config) + num_ctl_helper_zs),
@@ -305,6 +393,7 @@ pub fn add_virtual_stark_proof<F: RichField + Extendable<D>, S: Stark<F, D>, con
             (stark.quotient_degree_factor() > 0)
                 .then(|| stark.quotient_degree_factor() * config.num_challenges),
         )
+        .chain((stark.num_fixed_columns() > 0).then_some(stark.num_fixed_columns()))
         .collect_vec();
 
     let auxiliary_polys_cap = (stark.uses_lookups() || stark.requires_ctls())
@@ -337,8 +426,10 @@ fn add_virtual_stark_opening_set<F: RichField + Extendable<D>, S: Stark<F, D>, c
     config: &StarkConfig,
 ) -> StarkOpeningSetTarget<D> {
     StarkOpeningSetTarget {
-        local_values: builder.add_virtual_extension_targets(S::COLUMNS),
-        next_values: builder.add_virtual_extension_targets(S::COLUMNS),
+        local_values: builder
+            .add_virtual_extension_targets(stark.num_columns() + stark.num_fixed_columns()),
+        next_values: builder
+            .add_virtual_extension_targets(stark.num_columns() + stark.num_fixed_columns()),
         auxiliary_polys: (stark.uses_lookups() || stark.requires_ctls()).then(|| {
             builder.add_virtual_extension_targets(
                 stark.num_lookup_helper_columns(config) + num_ctl_helper_zs,
@@ -0,0 +1,222 @@
+//! An example of generating and verifying STARK proofs for a trace whose width is only known at
+//! runtime, using a [`DynamicStarkFrame`].
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::evaluation_frame::{DynamicStarkFrame, StarkEvaluationFrame};
+use crate::stark::Stark;
+
+/// Toy STARK system with a configurable number of registers, each of which is incremented at each
+/// row. The public inputs are the initial values of the registers, followed by their final values.
+#[derive(Copy, Clone)]
+struct RegistersStark<F: RichField + Extendable<D>, const D: usize> {
+    num_registers: usize,
+    num_rows: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> RegistersStark<F, D> {
+    const fn new(num_registers: usize, num_rows: usize) -> Self {
+        Self {
+            num_registers,
+            num_rows,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Generate the trace from the initial values of the registers, along with the public inputs.
+    fn generate_trace(&self, initial_values: &[F]) -> (Vec<PolynomialValues<F>>, Vec<F>) {
+        assert_eq!(initial_values.len(), self.num_registers);
+        let trace = initial_values
+            .iter()
+            .map(|&x| {
+                PolynomialValues::new(
+                    (0..self.num_rows)
+                        .map(|i| x + F::from_canonical_usize(i))
+                        .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let public_inputs = initial_values
+            .iter()
+            .copied()
+            .chain(trace.iter().map(|column| column.values[self.num_rows - 1]))
+            .collect();
+        (trace, public_inputs)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for RegistersStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize>
+        = DynamicStarkFrame<P, P::Scalar>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget = DynamicStarkFrame<ExtensionTarget<D>, ExtensionTarget<D>>;
+
+    fn num_columns(&self) -> usize {
+        self.num_registers
+    }
+
+    fn num_public_inputs(&self) -> usize {
+        2 * self.num_registers
+    }
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let local_values = vars.get_local_values();
+        let next_values = vars.get_next_values();
+        let public_inputs = vars.get_public_inputs();
+
+        for i in 0..self.num_registers {
+            yield_constr.constraint_first_row(local_values[i] - public_inputs[i]);
+            yield_constr
+                .constraint_last_row(local_values[i] - public_inputs[self.num_registers + i]);
+            yield_constr.constraint_transition(next_values[i] - local_values[i] - P::ONES);
+        }
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let local_values = vars.get_local_values();
+        let next_values = vars.get_next_values();
+        let public_inputs = vars.get_public_inputs();
+        let one = builder.one_extension();
+
+        for i in 0..self.num_registers {
+            let first = builder.sub_extension(local_values[i], public_inputs[i]);
+            yield_constr.constraint_first_row(builder, first);
+            let last =
+                builder.sub_extension(local_values[i], public_inputs[self.num_registers + i]);
+            yield_constr.constraint_last_row(builder, last);
+            let increment = builder.sub_extension(next_values[i], local_values[i]);
+            let transition = builder.sub_extension(increment, one);
+            yield_constr.constraint_transition(builder, transition);
+        }
+    }
+
+    fn constraint_degree(&self) -> usize {
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use plonky2::field::types::Sample;
+    use plonky2::iop::witness::PartialWitness;
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use super::*;
+    use crate::config::StarkConfig;
+    use crate::prover::prove;
+    use crate::recursive_verifier::{
+        add_virtual_stark_proof_with_pis, set_stark_proof_with_pis_target,
+        verify_stark_proof_circuit,
+    };
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+    use crate::verifier::verify_stark_proof;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = RegistersStark<F, D>;
+
+    const NUM_ROWS: usize = 1 << 5;
+
+    #[test]
+    fn test_registers_stark() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+
+        for num_registers in [1, 5] {
+            let stark = S::new(num_registers, NUM_ROWS);
+            let (trace, public_inputs) = stark.generate_trace(&F::rand_vec(num_registers));
+            let proof = prove::<F, C, S, D>(
+                stark,
+                &config,
+                trace,
+                &public_inputs,
+                None,
+                &mut TimingTree::default(),
+            )?;
+            verify_stark_proof(stark, proof.clone(), &config, None)?;
+
+            // The width is checked against the STARK the proof is verified for.
+            let other_stark = S::new(num_registers + 1, NUM_ROWS);
+            assert!(verify_stark_proof(other_stark, proof, &config, None).is_err());
+        }
+
+        // The width of the trace is checked against the STARK.
+        let (trace, public_inputs) = S::new(2, NUM_ROWS).generate_trace(&F::rand_vec(2));
+        assert!(prove::<F, C, S, D>(
+            S::new(3, NUM_ROWS),
+            &config,
+            trace,
+            &public_inputs,
+            None,
+            &mut TimingTree::default()
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_registers_stark_degree() -> Result<()> {
+        test_stark_low_degree(S::new(3, NUM_ROWS))
+    }
+
+    #[test]
+    fn test_registers_stark_circuit() -> Result<()> {
+        test_stark_circuit_constraints::<F, C, S, D>(S::new(3, NUM_ROWS))
+    }
+
+    #[test]
+    fn test_recursive_registers_stark_verifier() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new(4, NUM_ROWS);
+        let (trace, public_inputs) = stark.generate_trace(&F::rand_vec(4));
+        let proof = prove::<F, C, S, D>(
+            stark,
+            &config,
+            trace,
+            &public_inputs,
+            None,
+            &mut TimingTree::default(),
+        )?;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut pw = PartialWitness::new();
+        let degree_bits = proof.proof.recover_degree_bits(&config);
+        let pt = add_virtual_stark_proof_with_pis(&mut builder, &stark, &config, degree_bits, 0, 0);
+        set_stark_proof_with_pis_target(&mut pw, &pt, &proof, degree_bits, builder.zero())?;
+        verify_stark_proof_circuit::<F, C, S, D>(&mut builder, stark, pt, &config, None);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
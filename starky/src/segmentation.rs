@@ -0,0 +1,669 @@
+//! Support for segmented proving, a.k.a. continuations.
+//!
+//! Long computations can be split into segments of bounded length, each one
+//! proven independently by the same [`Stark`]. Consecutive segments are linked
+//! through their *boundary state*, i.e. a set of public inputs exposing the
+//! state at the start and at the end of each segment: the final state of a
+//! segment must match the initial state of the next one.
+//!
+//! A [`SegmentedStark`] declares where its boundary state lives in its public
+//! inputs. Segment proofs can then be generated with [`prove_segments`],
+//! checked natively with [`verify_segment_proofs`], or folded one at a time
+//! into a single recursive plonky2 proof with a [`SegmentChainCircuit`], whose
+//! public inputs expose the initial state of the first segment and the final
+//! state of the last one.
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, vec, vec::Vec};
+use core::marker::PhantomData;
+
+use anyhow::{ensure, Result};
+use plonky2::field::extension::Extendable;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::gates::constant::ConstantGate;
+use plonky2::gates::gate::GateRef;
+use plonky2::gates::noop::NoopGate;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{
+    CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget,
+};
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use plonky2::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
+use plonky2::recursion::dummy_circuit::cyclic_base_proof;
+use plonky2::timed;
+use plonky2::util::timing::TimingTree;
+
+use crate::config::StarkConfig;
+use crate::proof::{StarkProofWithPublicInputs, StarkProofWithPublicInputsTarget};
+use crate::prover::prove;
+use crate::recursive_verifier::{
+    add_virtual_stark_proof_with_pis, set_stark_proof_with_pis_target, verify_stark_proof_circuit,
+};
+use crate::stark::Stark;
+use crate::verifier::verify_stark_proof;
+
+/// A [`Stark`] whose execution can be split into linked segments.
+///
+/// The boundary state of a segment is exported through two disjoint ranges of
+/// its public inputs, of length [`BOUNDARY_STATE_LEN`](Self::BOUNDARY_STATE_LEN),
+/// starting respectively at [`INITIAL_STATE_OFFSET`](Self::INITIAL_STATE_OFFSET)
+/// and [`FINAL_STATE_OFFSET`](Self::FINAL_STATE_OFFSET). It is the responsibility
+/// of the STARK constraints to bind these public inputs to the trace.
+pub trait SegmentedStark<F: RichField + Extendable<D>, const D: usize>: Stark<F, D> {
+    /// The number of field elements in the boundary state.
+    const BOUNDARY_STATE_LEN: usize;
+    /// The index of the first public input of the initial boundary state.
+    const INITIAL_STATE_OFFSET: usize;
+    /// The index of the first public input of the final boundary state.
+    const FINAL_STATE_OFFSET: usize;
+
+    /// Returns the initial boundary state of a segment, given its public inputs.
+    fn initial_state<T>(public_inputs: &[T]) -> &[T] {
+        &public_inputs
+            [Self::INITIAL_STATE_OFFSET..Self::INITIAL_STATE_OFFSET + Self::BOUNDARY_STATE_LEN]
+    }
+
+    /// Returns the final boundary state of a segment, given its public inputs.
+    fn final_state<T>(public_inputs: &[T]) -> &[T] {
+        &public_inputs
+            [Self::FINAL_STATE_OFFSET..Self::FINAL_STATE_OFFSET + Self::BOUNDARY_STATE_LEN]
+    }
+}
+
+/// The witness of a single segment, as provided to [`prove_segments`].
+#[derive(Clone, Debug)]
+pub struct SegmentTrace<F: RichField> {
+    /// The segment trace, in column-major form.
+    pub trace: Vec<PolynomialValues<F>>,
+    /// The public inputs of the segment, including its boundary states.
+    pub public_inputs: Vec<F>,
+}
+
+/// Proves a sequence of segments of the same STARK, checking beforehand that
+/// each segment starts from the final state of the previous one.
+///
+/// Segments are consumed lazily, so that only one segment trace needs to be
+/// kept in memory at a time when `segments` is a lazy iterator.
+pub fn prove_segments<F, C, S, I, const D: usize>(
+    stark: S,
+    config: &StarkConfig,
+    segments: I,
+    timing: &mut TimingTree,
+) -> Result<Vec<StarkProofWithPublicInputs<F, C, D>>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: SegmentedStark<F, D> + Copy,
+    I: IntoIterator<Item = SegmentTrace<F>>,
+{
+    let mut proofs: Vec<StarkProofWithPublicInputs<F, C, D>> = Vec::new();
+    for (i, segment) in segments.into_iter().enumerate() {
+        ensure!(
+            segment.public_inputs.len() == stark.num_public_inputs(),
+            "Segment {} has {} public inputs, expected {}.",
+            i,
+            segment.public_inputs.len(),
+            stark.num_public_inputs()
+        );
+        if let Some(previous) = proofs.last() {
+            ensure!(
+                S::final_state(&previous.public_inputs) == S::initial_state(&segment.public_inputs),
+                "Segment {} does not start from the final state of segment {}.",
+                i,
+                i - 1
+            );
+        }
+
+        let proof = timed!(
+            timing,
+            &format!("prove segment {i}"),
+            prove::<F, C, S, D>(
+                stark,
+                config,
+                segment.trace,
+                &segment.public_inputs,
+                None,
+                timing,
+            )?
+        );
+        proofs.push(proof);
+    }
+
+    Ok(proofs)
+}
+
+/// Verifies a sequence of segment proofs, as well as the linking of their
+/// boundary states.
+pub fn verify_segment_proofs<F, C, S, const D: usize>(
+    stark: S,
+    proofs: &[StarkProofWithPublicInputs<F, C, D>],
+    config: &StarkConfig,
+) -> Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: SegmentedStark<F, D> + Copy,
+{
+    ensure!(!proofs.is_empty(), "No segment proof to verify.");
+    for (i, pair) in proofs.windows(2).enumerate() {
+        ensure!(
+            S::final_state(&pair[0].public_inputs) == S::initial_state(&pair[1].public_inputs),
+            "Segment {} does not start from the final state of segment {}.",
+            i + 1,
+            i
+        );
+    }
+    for proof in proofs {
+        verify_stark_proof(stark, proof.clone(), config, None)?;
+    }
+
+    Ok(())
+}
+
+/// A plonky2 circuit proving a chain of segments recursively, one segment at
+/// a time.
+///
+/// Each step verifies one segment proof, of trace length `2^degree_bits`, and,
+/// except for the first step, the proof produced by the previous step, using
+/// cyclic recursion. A step enforces that its segment starts from the final
+/// state exposed by the previous step, so that a single proof of constant
+/// size attests to the whole chain.
+///
+/// The public inputs of a step proof are the initial state of the first
+/// segment, the final state of the last one, the number of segments proven so
+/// far, and finally the verifier data of the circuit itself, as required by
+/// cyclic recursion.
+#[derive(Debug)]
+pub struct SegmentChainCircuit<F, C, InnerC, S, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    InnerC: GenericConfig<D, F = F>,
+    S: SegmentedStark<F, D>,
+{
+    /// The underlying plonky2 circuit.
+    pub circuit: CircuitData<F, C, D>,
+    segment_config: StarkConfig,
+    segment_degree_bits: usize,
+    segment_proof: StarkProofWithPublicInputsTarget<D>,
+    has_previous: BoolTarget,
+    previous_proof: ProofWithPublicInputsTarget<D>,
+    verifier_data: VerifierCircuitTarget,
+    zero: Target,
+    _phantom: PhantomData<(InnerC, S)>,
+}
+
+/// The targets of a step of a [`SegmentChainCircuit`].
+struct SegmentChainStepTargets<const D: usize> {
+    segment_proof: StarkProofWithPublicInputsTarget<D>,
+    has_previous: BoolTarget,
+    previous_proof: ProofWithPublicInputsTarget<D>,
+    verifier_data: VerifierCircuitTarget,
+    zero: Target,
+}
+
+impl<F, C, InnerC, S, const D: usize> SegmentChainCircuit<F, C, InnerC, S, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+    InnerC: GenericConfig<D, F = F>,
+    InnerC::Hasher: AlgebraicHasher<F>,
+    S: SegmentedStark<F, D> + Copy,
+{
+    /// Builds the step circuit for segments of trace length `2^degree_bits`,
+    /// proven with `inner_config`.
+    pub fn new(
+        stark: S,
+        inner_config: &StarkConfig,
+        degree_bits: usize,
+        circuit_config: CircuitConfig,
+    ) -> Result<Self> {
+        // The step circuit verifies a proof of itself, so its common data must be known
+        // before it is built. Starting from the common data of a circuit verifying a
+        // proof of an empty circuit, we repeatedly lay out the step circuit, verifying a
+        // proof with the current guess, until its common data stabilizes.
+        let empty_common_data = CircuitBuilder::<F, D>::new(circuit_config.clone())
+            .build::<C>()
+            .common;
+        let mut builder = CircuitBuilder::<F, D>::new(circuit_config.clone());
+        let proof = builder.add_virtual_proof_with_pis(&empty_common_data);
+        let verifier_data = builder.add_virtual_verifier_data(circuit_config.fri_config.cap_height);
+        builder.verify_proof::<C>(&proof, &verifier_data, &empty_common_data);
+        // Dummy circuits, used to fill in for the missing previous proof, need a
+        // `ConstantGate`.
+        builder.add_gate_to_gate_set(GateRef::new(ConstantGate::new(
+            circuit_config.num_constants,
+        )));
+        let mut common_data = builder.layout::<C>().common;
+        loop {
+            let (builder, _) = Self::build_step(
+                stark,
+                inner_config,
+                degree_bits,
+                &circuit_config,
+                &mut common_data,
+                false,
+            )?;
+            let next_common_data = builder.layout::<C>().common;
+            if next_common_data == common_data {
+                break;
+            }
+            common_data = next_common_data;
+        }
+
+        let (builder, targets) = Self::build_step(
+            stark,
+            inner_config,
+            degree_bits,
+            &circuit_config,
+            &mut common_data,
+            true,
+        )?;
+        let circuit = builder.build::<C>();
+
+        Ok(Self {
+            circuit,
+            segment_config: inner_config.clone(),
+            segment_degree_bits: degree_bits,
+            segment_proof: targets.segment_proof,
+            has_previous: targets.has_previous,
+            previous_proof: targets.previous_proof,
+            verifier_data: targets.verifier_data,
+            zero: targets.zero,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Adds the gates of a step to a new builder, verifying the previous step with
+    /// `common_data`. Unless `cyclic` is set, the previous step is verified as an
+    /// ordinary inner proof, which is enough to lay out the circuit.
+    fn build_step(
+        stark: S,
+        inner_config: &StarkConfig,
+        degree_bits: usize,
+        circuit_config: &CircuitConfig,
+        common_data: &mut CommonCircuitData<F, D>,
+        cyclic: bool,
+    ) -> Result<(CircuitBuilder<F, D>, SegmentChainStepTargets<D>)> {
+        let len = S::BOUNDARY_STATE_LEN;
+        let mut builder = CircuitBuilder::<F, D>::new(circuit_config.clone());
+        let one = builder.one();
+        let zero = builder.zero();
+
+        let segment_proof =
+            add_virtual_stark_proof_with_pis(&mut builder, &stark, inner_config, degree_bits, 0, 0);
+        verify_stark_proof_circuit::<F, InnerC, S, D>(
+            &mut builder,
+            stark,
+            segment_proof.clone(),
+            inner_config,
+            None,
+        );
+        let segment_pis = &segment_proof.public_inputs;
+
+        let initial_state = builder.add_virtual_targets(len);
+        builder.register_public_inputs(&initial_state);
+        builder.register_public_inputs(S::final_state(segment_pis));
+        let num_segments = builder.add_virtual_public_input();
+        let verifier_data = builder.add_verifier_data_public_inputs();
+        common_data.num_public_inputs = builder.num_public_inputs();
+
+        let has_previous = builder.add_virtual_bool_target_safe();
+        let previous_proof = builder.add_virtual_proof_with_pis(common_data);
+        let previous_pis = &previous_proof.public_inputs;
+        // The initial state is carried over from the previous step. For the first step,
+        // it is set in the dummy base proof.
+        for i in 0..len {
+            builder.connect(initial_state[i], previous_pis[i]);
+            let expected = builder.select(has_previous, previous_pis[len + i], initial_state[i]);
+            builder.connect(S::initial_state(segment_pis)[i], expected);
+        }
+        let count = builder.mul_add(has_previous.target, previous_pis[2 * len], one);
+        builder.connect(num_segments, count);
+
+        if cyclic {
+            builder.conditionally_verify_cyclic_proof_or_dummy::<C>(
+                has_previous,
+                &previous_proof,
+                common_data,
+            )?;
+        } else {
+            builder.conditionally_verify_proof_or_dummy::<C>(
+                has_previous,
+                &previous_proof,
+                &verifier_data,
+                common_data,
+            )?;
+            for gate in &common_data.gates {
+                builder.add_gate_to_gate_set(gate.clone());
+            }
+        }
+        // Pad the circuit so that it has at least the degree of `common_data`.
+        while builder.num_gates() <= common_data.degree() / 2 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+
+        Ok((
+            builder,
+            SegmentChainStepTargets {
+                segment_proof,
+                has_previous,
+                previous_proof,
+                verifier_data,
+                zero,
+            },
+        ))
+    }
+
+    /// Proves one more segment of the chain, on top of the proof of the previous
+    /// step, or as the first segment of the chain if `previous` is `None`.
+    pub fn prove_step(
+        &self,
+        previous: Option<&ProofWithPublicInputs<F, C, D>>,
+        segment: &StarkProofWithPublicInputs<F, InnerC, D>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let degree_bits = segment.proof.recover_degree_bits(&self.segment_config);
+        ensure!(
+            degree_bits == self.segment_degree_bits,
+            "Segment proof has degree bits {}, but the chain expects {}.",
+            degree_bits,
+            self.segment_degree_bits
+        );
+
+        let mut pw = PartialWitness::new();
+        set_stark_proof_with_pis_target(
+            &mut pw,
+            &self.segment_proof,
+            segment,
+            degree_bits,
+            self.zero,
+        )?;
+        pw.set_verifier_data_target(&self.verifier_data, &self.circuit.verifier_only)?;
+        match previous {
+            Some(previous) => {
+                ensure!(
+                    self.final_state(previous) == S::initial_state(&segment.public_inputs),
+                    "Segment does not start from the final state of the chain."
+                );
+                pw.set_bool_target(self.has_previous, true)?;
+                pw.set_proof_with_pis_target(&self.previous_proof, previous)?;
+            }
+            None => {
+                let initial_state = S::initial_state(&segment.public_inputs);
+                let base_proof = cyclic_base_proof(
+                    &self.circuit.common,
+                    &self.circuit.verifier_only,
+                    initial_state.iter().copied().enumerate().collect(),
+                );
+                pw.set_bool_target(self.has_previous, false)?;
+                pw.set_proof_with_pis_target(&self.previous_proof, &base_proof)?;
+            }
+        }
+
+        self.circuit.prove(pw)
+    }
+
+    /// Proves a whole chain of segments, provided in order, one step at a time.
+    pub fn prove(
+        &self,
+        segments: &[StarkProofWithPublicInputs<F, InnerC, D>],
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        ensure!(!segments.is_empty(), "No segment proof to chain.");
+        let mut proof = self.prove_step(None, &segments[0])?;
+        for segment in &segments[1..] {
+            proof = self.prove_step(Some(&proof), segment)?;
+        }
+
+        Ok(proof)
+    }
+
+    /// Verifies a proof produced by this circuit, including the verifier data it
+    /// claims to have been recursively verified against.
+    pub fn verify(&self, proof: ProofWithPublicInputs<F, C, D>) -> Result<()> {
+        check_cyclic_proof_verifier_data(
+            &proof,
+            &self.circuit.verifier_only,
+            &self.circuit.common,
+        )?;
+        self.circuit.verify(proof)
+    }
+
+    /// Returns the initial state of the first segment of a chain proof.
+    pub fn initial_state<'a>(&self, proof: &'a ProofWithPublicInputs<F, C, D>) -> &'a [F] {
+        &proof.public_inputs[..S::BOUNDARY_STATE_LEN]
+    }
+
+    /// Returns the final state of the last segment of a chain proof.
+    pub fn final_state<'a>(&self, proof: &'a ProofWithPublicInputs<F, C, D>) -> &'a [F] {
+        &proof.public_inputs[S::BOUNDARY_STATE_LEN..2 * S::BOUNDARY_STATE_LEN]
+    }
+
+    /// Returns the number of segments proven by a chain proof.
+    pub fn num_segments(&self, proof: &ProofWithPublicInputs<F, C, D>) -> usize {
+        proof.public_inputs[2 * S::BOUNDARY_STATE_LEN].to_canonical_u64() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{vec, vec::Vec};
+    use core::marker::PhantomData;
+
+    use anyhow::Result;
+    use plonky2::field::extension::{Extendable, FieldExtension};
+    use plonky2::field::packed::PackedField;
+    use plonky2::field::types::Field;
+    use plonky2::hash::hash_types::RichField;
+    use plonky2::iop::ext_target::ExtensionTarget;
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::config::StarkConfig;
+    use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+    use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+    use crate::segmentation::{
+        prove_segments, verify_segment_proofs, SegmentChainCircuit, SegmentTrace, SegmentedStark,
+    };
+    use crate::stark::Stark;
+    use crate::util::trace_rows_to_poly_values;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Fibonacci STARK exposing both its first and last rows as public inputs,
+    /// so that a long sequence can be split across segments. Consecutive
+    /// segments overlap on one row.
+    #[derive(Copy, Clone)]
+    struct FibonacciSegmentStark<F: RichField + Extendable<D>, const D: usize> {
+        _phantom: PhantomData<F>,
+    }
+
+    const COLUMNS: usize = 2;
+    const PUBLIC_INPUTS: usize = 4;
+
+    impl<F: RichField + Extendable<D>, const D: usize> FibonacciSegmentStark<F, D> {
+        const fn new() -> Self {
+            Self {
+                _phantom: PhantomData,
+            }
+        }
+
+        fn generate_segment(&self, num_rows: usize, initial_state: [F; 2]) -> SegmentTrace<F> {
+            let rows = (0..num_rows)
+                .scan(initial_state, |acc, _| {
+                    let tmp = *acc;
+                    *acc = [tmp[1], tmp[0] + tmp[1]];
+                    Some(tmp)
+                })
+                .collect::<Vec<_>>();
+            let last = rows[num_rows - 1];
+            SegmentTrace {
+                trace: trace_rows_to_poly_values(rows),
+                public_inputs: vec![initial_state[0], initial_state[1], last[0], last[1]],
+            }
+        }
+    }
+
+    impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for FibonacciSegmentStark<F, D> {
+        type EvaluationFrame<FE, P, const D2: usize>
+            = StarkFrame<P, P::Scalar, COLUMNS, PUBLIC_INPUTS>
+        where
+            FE: FieldExtension<D2, BaseField = F>,
+            P: PackedField<Scalar = FE>;
+
+        type EvaluationFrameTarget =
+            StarkFrame<ExtensionTarget<D>, ExtensionTarget<D>, COLUMNS, PUBLIC_INPUTS>;
+
+        fn eval_packed_generic<FE, P, const D2: usize>(
+            &self,
+            vars: &Self::EvaluationFrame<FE, P, D2>,
+            yield_constr: &mut ConstraintConsumer<P>,
+        ) where
+            FE: FieldExtension<D2, BaseField = F>,
+            P: PackedField<Scalar = FE>,
+        {
+            let local_values = vars.get_local_values();
+            let next_values = vars.get_next_values();
+            let public_inputs = vars.get_public_inputs();
+
+            for i in 0..2 {
+                yield_constr.constraint_first_row(local_values[i] - public_inputs[i]);
+                yield_constr.constraint_last_row(local_values[i] - public_inputs[2 + i]);
+            }
+            yield_constr.constraint_transition(next_values[0] - local_values[1]);
+            yield_constr.constraint_transition(next_values[1] - local_values[0] - local_values[1]);
+        }
+
+        fn eval_ext_circuit(
+            &self,
+            builder: &mut CircuitBuilder<F, D>,
+            vars: &Self::EvaluationFrameTarget,
+            yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+        ) {
+            let local_values = vars.get_local_values();
+            let next_values = vars.get_next_values();
+            let public_inputs = vars.get_public_inputs();
+
+            for i in 0..2 {
+                let first = builder.sub_extension(local_values[i], public_inputs[i]);
+                yield_constr.constraint_first_row(builder, first);
+                let last = builder.sub_extension(local_values[i], public_inputs[2 + i]);
+                yield_constr.constraint_last_row(builder, last);
+            }
+            let first_col_constraint = builder.sub_extension(next_values[0], local_values[1]);
+            yield_constr.constraint_transition(builder, first_col_constraint);
+            let second_col_constraint = {
+                let tmp = builder.sub_extension(next_values[1], local_values[0]);
+                builder.sub_extension(tmp, local_values[1])
+            };
+            yield_constr.constraint_transition(builder, second_col_constraint);
+        }
+
+        fn constraint_degree(&self) -> usize {
+            2
+        }
+    }
+
+    impl<F: RichField + Extendable<D>, const D: usize> SegmentedStark<F, D>
+        for FibonacciSegmentStark<F, D>
+    {
+        const BOUNDARY_STATE_LEN: usize = 2;
+        const INITIAL_STATE_OFFSET: usize = 0;
+        const FINAL_STATE_OFFSET: usize = 2;
+    }
+
+    type S = FibonacciSegmentStark<F, D>;
+
+    fn segments(num_segments: usize, num_rows: usize) -> Vec<SegmentTrace<F>> {
+        let stark = S::new();
+        let mut state = [F::ZERO, F::ONE];
+        (0..num_segments)
+            .map(|_| {
+                let segment = stark.generate_segment(num_rows, state);
+                state = [segment.public_inputs[2], segment.public_inputs[3]];
+                segment
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_segmented_proving() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let proofs = prove_segments::<F, C, S, _, D>(
+            stark,
+            &config,
+            segments(3, 1 << 5),
+            &mut TimingTree::default(),
+        )?;
+        verify_segment_proofs(stark, &proofs, &config)?;
+
+        // Reordering the segments breaks the chain.
+        let swapped = [proofs[1].clone(), proofs[0].clone(), proofs[2].clone()];
+        assert!(verify_segment_proofs(stark, &swapped, &config).is_err());
+
+        // Unlinked segments are rejected before proving.
+        let mut unlinked = segments(2, 1 << 5);
+        unlinked[1].public_inputs[0] += F::ONE;
+        assert!(prove_segments::<F, C, S, _, D>(
+            stark,
+            &config,
+            unlinked,
+            &mut TimingTree::default()
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_segment_chain_circuit() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let degree_bits = 5;
+        let stark = S::new();
+        let proofs = prove_segments::<F, C, S, _, D>(
+            stark,
+            &config,
+            segments(3, 1 << degree_bits),
+            &mut TimingTree::default(),
+        )?;
+
+        let circuit = SegmentChainCircuit::<F, C, C, S, D>::new(
+            stark,
+            &config,
+            degree_bits,
+            CircuitConfig::standard_recursion_config(),
+        )?;
+
+        // Each step verifies the proof of the previous one.
+        let first = circuit.prove_step(None, &proofs[0])?;
+        assert_eq!(circuit.num_segments(&first), 1);
+        circuit.verify(first.clone())?;
+        let second = circuit.prove_step(Some(&first), &proofs[1])?;
+        let third = circuit.prove_step(Some(&second), &proofs[2])?;
+        assert_eq!(circuit.num_segments(&third), 3);
+        assert_eq!(
+            circuit.initial_state(&third),
+            S::initial_state(&proofs[0].public_inputs)
+        );
+        assert_eq!(
+            circuit.final_state(&third),
+            S::final_state(&proofs[2].public_inputs)
+        );
+        circuit.verify(third.clone())?;
+
+        // A segment which doesn't continue the chain is rejected.
+        assert!(circuit.prove_step(Some(&first), &proofs[2]).is_err());
+
+        Ok(())
+    }
+}
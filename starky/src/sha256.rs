@@ -0,0 +1,1019 @@
+//! A STARK proving SHA-256 compressions, to which plonky2 circuits can delegate hashing.
+//!
+//! Each compression takes a chaining state `h_in` of 8 words and a message block of 16 words,
+//! and outputs the next chaining state `h_out`. Compressions are exposed through a cross-table
+//! lookup on the tuple `(h_in, block, h_out)`, so that a plonky2 circuit can check thousands of
+//! them by verifying a single STARK proof, following the pattern of the
+//! [`delegation`](crate::delegation) module:
+//!
+//! 1. While building the circuit, call [`Sha256DelegationTarget::compress`] for each compression,
+//!    instead of hashing with gates. Its outputs are unconstrained until step 2.
+//! 2. Call [`Sha256DelegationTarget::verify`], which adds a STARK proof target, verifies it
+//!    recursively and connects the delegated compressions to the STARK's CTL.
+//! 3. When proving, prove the same compressions, in the same order, with
+//!    [`prove_sha256_compressions`], and set the returned proof in the witness with
+//!    [`set_stark_proof_with_pis_target`](crate::recursive_verifier::set_stark_proof_with_pis_target).
+//!
+//! Padding and splitting messages into blocks is left to the caller, who should start from
+//! [`SHA256_IV`].
+//!
+//! # Trace layout
+//!
+//! Each compression uses 68 consecutive rows, marked by one-hot row flags: 64 round rows
+//! followed by 4 output rows. Round row `i` holds the working variables `a..h` before round `i`,
+//! and the window `W[i..i + 16]` of the message schedule, whose next word is computed on each
+//! transition. The working variables are bit-decomposed, as are `W[i]` and `W[i + 14]`, which
+//! both range-checks them and lets the bitwise functions of SHA-256 be computed with low-degree
+//! constraints. After the last round, the working variables keep shifting, so that the output
+//! row `k` adds the final values of `d` and `h` to words `3 - k` and `7 - k` of `h_in`. The
+//! bit columns of `W[i]` and `W[i + 14]` hold the corresponding output words on these rows.
+//!
+//! The trace is padded with zero rows, which satisfy all the constraints.
+//!
+//! The witness generator used by [`Sha256DelegationTarget`] is not part of plonky2's default
+//! generator serializer, so circuits using it need a custom one to be serialized.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use anyhow::Result;
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::util::log2_strict;
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+use plonky2::util::timing::TimingTree;
+
+use crate::config::StarkConfig;
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::cross_table_lookup::{CrossTableLookup, TableWithColumns};
+use crate::delegation::{prove_delegated, verify_delegated, verify_delegated_circuit};
+use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+use crate::lookup::{Column, Filter};
+use crate::proof::{StarkProofWithPublicInputs, StarkProofWithPublicInputsTarget};
+use crate::stark::Stark;
+use crate::util::trace_rows_to_poly_values;
+
+/// The initial chaining state of SHA-256.
+pub const SHA256_IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The SHA-256 round constants.
+const SHA256_K: [u32; NUM_ROUNDS] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const NUM_ROUNDS: usize = 64;
+const NUM_OUTPUT_ROWS: usize = 4;
+const ROWS_PER_COMPRESSION: usize = NUM_ROUNDS + NUM_OUTPUT_ROWS;
+const WORD_BITS: usize = 32;
+
+/// One-hot flags marking the position of a row within its compression.
+const ROW_FLAGS: Range<usize> = 0..ROWS_PER_COMPRESSION;
+/// Bits of the working variables `a..h`.
+const STATE_BITS_START: usize = ROW_FLAGS.end;
+/// Bits of `W[i]` on round rows, and of output word `3 - k` on output row `k`.
+const W0_BITS: Range<usize> = STATE_BITS_START + 8 * WORD_BITS..STATE_BITS_START + 9 * WORD_BITS;
+/// Bits of `W[i + 14]` on round rows, and of output word `7 - k` on output row `k`.
+const W14_BITS: Range<usize> = W0_BITS.end..W0_BITS.end + WORD_BITS;
+/// The message schedule window `W[i..i + 16]`.
+const W_WINDOW: Range<usize> = W14_BITS.end..W14_BITS.end + 16;
+/// The input chaining state, constant within a compression.
+const H_IN: Range<usize> = W_WINDOW.end..W_WINDOW.end + 8;
+/// The message block, constant within a compression.
+const BLOCK: Range<usize> = H_IN.end..H_IN.end + 16;
+/// The output chaining state, constant within a compression.
+const H_OUT: Range<usize> = BLOCK.end..BLOCK.end + 8;
+/// Bits of the carry of the new value of `a`, or of the output word `3 - k`.
+const CARRY_A: Range<usize> = H_OUT.end..H_OUT.end + 3;
+/// Bits of the carry of the new value of `e`, or of the output word `7 - k`.
+const CARRY_E: Range<usize> = CARRY_A.end..CARRY_A.end + 3;
+/// Bits of the carry of the next word of the message schedule.
+const CARRY_W: Range<usize> = CARRY_E.end..CARRY_E.end + 2;
+/// `Σ0(a)`.
+const BIG_SIGMA0_A: usize = CARRY_W.end;
+/// `Σ1(e)`.
+const BIG_SIGMA1_E: usize = BIG_SIGMA0_A + 1;
+/// `Maj(a, b, c)`.
+const MAJ_ABC: usize = BIG_SIGMA1_E + 1;
+/// `Ch(e, f, g)`.
+const CH_EFG: usize = MAJ_ABC + 1;
+/// `σ0` of the word in `W0_BITS`.
+const SMALL_SIGMA0_W0: usize = CH_EFG + 1;
+/// `σ1` of the word in `W14_BITS`.
+const SMALL_SIGMA1_W14: usize = SMALL_SIGMA0_W0 + 1;
+
+const SHA256_COLUMNS: usize = SMALL_SIGMA1_W14 + 1;
+const SHA256_PUBLIC_INPUTS: usize = 0;
+
+/// Returns the bit columns of the working variable `a..h` with the given index.
+const fn state_bits(word: usize) -> Range<usize> {
+    STATE_BITS_START + word * WORD_BITS..STATE_BITS_START + (word + 1) * WORD_BITS
+}
+
+/// A component of the `Σ` and `σ` functions of SHA-256.
+#[derive(Copy, Clone, Debug)]
+enum Shift {
+    Rotr(usize),
+    Shr(usize),
+}
+
+impl Shift {
+    const fn apply(self, x: u32) -> u32 {
+        match self {
+            Self::Rotr(r) => x.rotate_right(r as u32),
+            Self::Shr(r) => x >> r,
+        }
+    }
+
+    /// Returns the index of the input bit that ends up at index `bit`, if any.
+    const fn source(self, bit: usize) -> Option<usize> {
+        match self {
+            Self::Rotr(r) => Some((bit + r) % WORD_BITS),
+            Self::Shr(r) if bit + r < WORD_BITS => Some(bit + r),
+            Self::Shr(_) => None,
+        }
+    }
+}
+
+const BIG_SIGMA0: [Shift; 3] = [Shift::Rotr(2), Shift::Rotr(13), Shift::Rotr(22)];
+const BIG_SIGMA1: [Shift; 3] = [Shift::Rotr(6), Shift::Rotr(11), Shift::Rotr(25)];
+const SMALL_SIGMA0: [Shift; 3] = [Shift::Rotr(7), Shift::Rotr(18), Shift::Shr(3)];
+const SMALL_SIGMA1: [Shift; 3] = [Shift::Rotr(17), Shift::Rotr(19), Shift::Shr(10)];
+
+fn sigma(x: u32, shifts: [Shift; 3]) -> u32 {
+    shifts.iter().fold(0, |acc, s| acc ^ s.apply(x))
+}
+
+const fn maj(a: u32, b: u32, c: u32) -> u32 {
+    (a & b) ^ (a & c) ^ (b & c)
+}
+
+const fn ch(e: u32, f: u32, g: u32) -> u32 {
+    (e & f) ^ (!e & g)
+}
+
+/// Computes the extended message schedule `W[0..79]`. Only the first 64 words are used by the
+/// compression, but the trace keeps computing the window until the last round.
+fn message_schedule(block: [u32; 16]) -> [u32; NUM_ROUNDS + 15] {
+    let mut w = [0; NUM_ROUNDS + 15];
+    w[..16].copy_from_slice(&block);
+    for i in 16..w.len() {
+        w[i] = sigma(w[i - 2], SMALL_SIGMA1)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(sigma(w[i - 15], SMALL_SIGMA0))
+            .wrapping_add(w[i - 16]);
+    }
+    w
+}
+
+/// Applies the SHA-256 compression function to the chaining state `state` and the message block
+/// `block`, returning the next chaining state.
+pub fn sha256_compress(state: [u32; 8], block: [u32; 16]) -> [u32; 8] {
+    let w = message_schedule(block);
+    let mut s = state;
+    for i in 0..NUM_ROUNDS {
+        let [a, b, c, d, e, f, g, h] = s;
+        let t1 = h
+            .wrapping_add(sigma(e, BIG_SIGMA1))
+            .wrapping_add(ch(e, f, g))
+            .wrapping_add(SHA256_K[i])
+            .wrapping_add(w[i]);
+        let t2 = sigma(a, BIG_SIGMA0).wrapping_add(maj(a, b, c));
+        s = [t1.wrapping_add(t2), a, b, c, d.wrapping_add(t1), e, f, g];
+    }
+    core::array::from_fn(|i| state[i].wrapping_add(s[i]))
+}
+
+/// A single SHA-256 compression proven by the [`Sha256CompressionStark`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sha256Compression {
+    /// The input chaining state.
+    pub state: [u32; 8],
+    /// The message block.
+    pub block: [u32; 16],
+}
+
+impl Sha256Compression {
+    /// Returns the output chaining state of this compression.
+    pub fn output(&self) -> [u32; 8] {
+        sha256_compress(self.state, self.block)
+    }
+
+    /// Returns the `(h_in, block, h_out)` tuple representing this compression in the
+    /// cross-table lookup.
+    pub fn values<F: Field>(&self) -> Vec<F> {
+        self.state
+            .into_iter()
+            .chain(self.block)
+            .chain(self.output())
+            .map(F::from_canonical_u32)
+            .collect()
+    }
+}
+
+/// A STARK proving a batch of [`Sha256Compression`]s.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Sha256CompressionStark<F: RichField + Extendable<D>, const D: usize> {
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Sha256CompressionStark<F, D> {
+    /// Creates a new [`Sha256CompressionStark`].
+    pub const fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of rows of a trace proving `num_compressions` compressions.
+    pub fn num_rows(num_compressions: usize) -> usize {
+        (num_compressions * ROWS_PER_COMPRESSION)
+            .next_power_of_two()
+            .max(ROWS_PER_COMPRESSION.next_power_of_two())
+    }
+
+    /// Generates the trace proving the provided compressions, in order.
+    pub fn generate_trace(&self, compressions: &[Sha256Compression]) -> Vec<PolynomialValues<F>> {
+        let num_rows = Self::num_rows(compressions.len());
+        let mut rows = vec![[F::ZERO; SHA256_COLUMNS]; num_rows];
+        for (compression, rows) in compressions
+            .iter()
+            .zip(rows.chunks_exact_mut(ROWS_PER_COMPRESSION))
+        {
+            Self::generate_compression(compression, rows);
+        }
+        trace_rows_to_poly_values(rows)
+    }
+
+    fn generate_compression(compression: &Sha256Compression, rows: &mut [[F; SHA256_COLUMNS]]) {
+        let h_in = compression.state;
+        let h_out = compression.output();
+        let w = message_schedule(compression.block);
+
+        let mut state = h_in;
+        for (i, row) in rows.iter_mut().enumerate() {
+            row[ROW_FLAGS.start + i] = F::ONE;
+            for (cols, words) in [
+                (H_IN, &h_in[..]),
+                (BLOCK, &compression.block),
+                (H_OUT, &h_out),
+            ] {
+                for (col, &word) in cols.zip(words) {
+                    row[col] = F::from_canonical_u32(word);
+                }
+            }
+
+            let [a, b, c, d, e, f, g, h] = state.map(u64::from);
+            let (w0, w14, carries, next_state) = if i < NUM_ROUNDS {
+                for (j, col) in W_WINDOW.enumerate() {
+                    row[col] = F::from_canonical_u32(w[i + j]);
+                }
+
+                let t1 = h
+                    + sigma(state[4], BIG_SIGMA1) as u64
+                    + ch(state[4], state[5], state[6]) as u64
+                    + SHA256_K[i] as u64
+                    + w[i] as u64;
+                let t2 =
+                    sigma(state[0], BIG_SIGMA0) as u64 + maj(state[0], state[1], state[2]) as u64;
+                let carry_w = if i < NUM_ROUNDS - 1 {
+                    (sigma(w[i + 14], SMALL_SIGMA1) as u64
+                        + w[i + 9] as u64
+                        + sigma(w[i + 1], SMALL_SIGMA0) as u64
+                        + w[i] as u64)
+                        >> 32
+                } else {
+                    0
+                };
+                let next_state = [t1 + t2, a, b, c, d + t1, e, f, g];
+                let carries = [(t1 + t2) >> 32, (d + t1) >> 32, carry_w];
+                (w[i], w[i + 14], carries, next_state)
+            } else {
+                let k = i - NUM_ROUNDS;
+                let carries = [
+                    (h_in[3 - k] as u64 + d) >> 32,
+                    (h_in[7 - k] as u64 + h) >> 32,
+                    0,
+                ];
+                (
+                    h_out[3 - k],
+                    h_out[7 - k],
+                    carries,
+                    [0, a, b, c, 0, e, f, g],
+                )
+            };
+
+            for (word, &value) in state.iter().enumerate() {
+                write_bits(row, state_bits(word), value as u64);
+            }
+            write_bits(row, W0_BITS, w0 as u64);
+            write_bits(row, W14_BITS, w14 as u64);
+            for (cols, carry) in [CARRY_A, CARRY_E, CARRY_W].into_iter().zip(carries) {
+                write_bits(row, cols, carry);
+            }
+
+            row[BIG_SIGMA0_A] = F::from_canonical_u32(sigma(state[0], BIG_SIGMA0));
+            row[BIG_SIGMA1_E] = F::from_canonical_u32(sigma(state[4], BIG_SIGMA1));
+            row[MAJ_ABC] = F::from_canonical_u32(maj(state[0], state[1], state[2]));
+            row[CH_EFG] = F::from_canonical_u32(ch(state[4], state[5], state[6]));
+            row[SMALL_SIGMA0_W0] = F::from_canonical_u32(sigma(w0, SMALL_SIGMA0));
+            row[SMALL_SIGMA1_W14] = F::from_canonical_u32(sigma(w14, SMALL_SIGMA1));
+
+            state = next_state.map(|x| x as u32);
+        }
+    }
+}
+
+fn write_bits<F: Field>(row: &mut [F], cols: Range<usize>, value: u64) {
+    for (i, col) in cols.enumerate() {
+        row[col] = F::from_bool((value >> i) & 1 == 1);
+    }
+}
+
+/// Returns the cross-table lookup exposing the `(h_in, block, h_out)` tuples of all the
+/// compressions in the [`Sha256CompressionStark`] trace. It has no looking table: the looking
+/// side is provided as an extra looking sum over the delegated compressions.
+pub fn sha256_ctl<F: Field>() -> CrossTableLookup<F> {
+    let columns = Column::singles(H_IN)
+        .chain(Column::singles(BLOCK))
+        .chain(Column::singles(H_OUT))
+        .collect();
+    let filter = Filter::new_simple(Column::single(ROW_FLAGS.end - 1));
+    CrossTableLookup::new(vec![], TableWithColumns::new(0, columns, filter))
+}
+
+fn bits_to_value<P: PackedField>(bits: &[P]) -> P {
+    bits.iter()
+        .rev()
+        .fold(P::ZEROS, |acc, &bit| acc * P::Scalar::TWO + bit)
+}
+
+fn xor<P: PackedField>(x: P, y: P) -> P {
+    x + y - x * y * P::Scalar::TWO
+}
+
+/// Evaluates `Σ` or `σ` on the bits of a word, as a degree 3 polynomial.
+fn sigma_packed<P: PackedField>(bits: &[P], shifts: [Shift; 3]) -> P {
+    (0..WORD_BITS).rev().fold(P::ZEROS, |acc, i| {
+        let bit = shifts
+            .iter()
+            .filter_map(|s| s.source(i))
+            .map(|j| bits[j])
+            .reduce(xor)
+            .unwrap();
+        acc * P::Scalar::TWO + bit
+    })
+}
+
+fn sum_packed<P: PackedField>(values: &[P]) -> P {
+    values.iter().fold(P::ZEROS, |acc, &x| acc + x)
+}
+
+fn bits_to_value_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    bits: &[ExtensionTarget<D>],
+) -> ExtensionTarget<D> {
+    let zero = builder.zero_extension();
+    bits.iter().rev().fold(zero, |acc, &bit| {
+        builder.mul_const_add_extension(F::TWO, acc, bit)
+    })
+}
+
+fn xor_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: ExtensionTarget<D>,
+    y: ExtensionTarget<D>,
+) -> ExtensionTarget<D> {
+    let sum = builder.add_extension(x, y);
+    builder.arithmetic_extension(-F::TWO, F::ONE, x, y, sum)
+}
+
+fn sigma_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    bits: &[ExtensionTarget<D>],
+    shifts: [Shift; 3],
+) -> ExtensionTarget<D> {
+    let out_bits = (0..WORD_BITS)
+        .map(|i| {
+            let mut sources = shifts.iter().filter_map(|s| s.source(i)).map(|j| bits[j]);
+            let first = sources.next().unwrap();
+            sources.fold(first, |acc, bit| xor_circuit(builder, acc, bit))
+        })
+        .collect::<Vec<_>>();
+    bits_to_value_circuit(builder, &out_bits)
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for Sha256CompressionStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize>
+        = StarkFrame<P, P::Scalar, SHA256_COLUMNS, SHA256_PUBLIC_INPUTS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget =
+        StarkFrame<ExtensionTarget<D>, ExtensionTarget<D>, SHA256_COLUMNS, SHA256_PUBLIC_INPUTS>;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let lv = vars.get_local_values();
+        let nv = vars.get_next_values();
+        let two_32 = FE::from_canonical_u64(1 << 32);
+
+        for &bit in lv[..W14_BITS.end]
+            .iter()
+            .chain(&lv[CARRY_A.start..CARRY_W.end])
+        {
+            yield_constr.constraint(bit * (bit - P::ONES));
+        }
+
+        // Row flags are one-hot within compressions, and zero on padding rows. A compression
+        // can only start on the first row, or right after another compression or padding row.
+        let flags = &lv[ROW_FLAGS];
+        let any_flag = sum_packed(flags);
+        yield_constr.constraint(any_flag * (any_flag - P::ONES));
+        yield_constr.constraint_first_row(sum_packed(&flags[1..]));
+        for i in 0..ROWS_PER_COMPRESSION - 1 {
+            yield_constr.constraint_transition(nv[ROW_FLAGS.start + i + 1] - flags[i]);
+        }
+        let is_round = sum_packed(&flags[..NUM_ROUNDS]);
+        let is_shift = sum_packed(&flags[..ROWS_PER_COMPRESSION - 1]);
+        let is_schedule = sum_packed(&flags[..NUM_ROUNDS - 1]);
+
+        // Bitwise functions, evaluated on every row.
+        let state_bits_lv = |word| &lv[state_bits(word)];
+        let bitwise = [
+            (BIG_SIGMA0_A, sigma_packed(state_bits_lv(0), BIG_SIGMA0)),
+            (BIG_SIGMA1_E, sigma_packed(state_bits_lv(4), BIG_SIGMA1)),
+            (SMALL_SIGMA0_W0, sigma_packed(&lv[W0_BITS], SMALL_SIGMA0)),
+            (SMALL_SIGMA1_W14, sigma_packed(&lv[W14_BITS], SMALL_SIGMA1)),
+        ];
+        for (col, value) in bitwise {
+            yield_constr.constraint(lv[col] - value);
+        }
+        let maj = (0..WORD_BITS).rev().fold(P::ZEROS, |acc, i| {
+            let [a, b, c] = [0, 1, 2].map(|word| state_bits_lv(word)[i]);
+            let ab = a * b;
+            acc * FE::TWO + ab + c * (a + b - ab * FE::TWO)
+        });
+        yield_constr.constraint(lv[MAJ_ABC] - maj);
+        let ch = (0..WORD_BITS).rev().fold(P::ZEROS, |acc, i| {
+            let [e, f, g] = [4, 5, 6].map(|word| state_bits_lv(word)[i]);
+            acc * FE::TWO + e * (f - g) + g
+        });
+        yield_constr.constraint(lv[CH_EFG] - ch);
+
+        let state: [P; 8] = core::array::from_fn(|word| bits_to_value(state_bits_lv(word)));
+        let next_state: [P; 8] = core::array::from_fn(|word| bits_to_value(&nv[state_bits(word)]));
+        let [a, b, c, d, e, f, g, h] = state;
+        let w = &lv[W_WINDOW];
+        let w0 = bits_to_value(&lv[W0_BITS]);
+        let w14 = bits_to_value(&lv[W14_BITS]);
+        let carry_a = bits_to_value(&lv[CARRY_A]);
+        let carry_e = bits_to_value(&lv[CARRY_E]);
+        let carry_w = bits_to_value(&lv[CARRY_W]);
+
+        // Inputs, loaded on the first row of each compression.
+        for (word, h_in) in state.into_iter().zip(&lv[H_IN]) {
+            yield_constr.constraint(flags[0] * (word - *h_in));
+        }
+        for (&w, &block) in w.iter().zip(&lv[BLOCK]) {
+            yield_constr.constraint(flags[0] * (w - block));
+        }
+
+        // Rounds.
+        yield_constr.constraint(is_round * (w[0] - w0));
+        yield_constr.constraint(is_round * (w[14] - w14));
+        let k = flags[..NUM_ROUNDS]
+            .iter()
+            .zip(SHA256_K)
+            .fold(P::ZEROS, |acc, (&flag, k)| {
+                acc + flag * FE::from_canonical_u32(k)
+            });
+        let t1 = h + lv[BIG_SIGMA1_E] + lv[CH_EFG] + k + w[0];
+        let t2 = lv[BIG_SIGMA0_A] + lv[MAJ_ABC];
+        yield_constr.constraint_transition(is_round * (next_state[0] + carry_a * two_32 - t1 - t2));
+        yield_constr.constraint_transition(is_round * (next_state[4] + carry_e * two_32 - d - t1));
+        for (next, prev) in [(1, a), (2, b), (3, c), (5, e), (6, f), (7, g)] {
+            yield_constr.constraint_transition(is_shift * (next_state[next] - prev));
+        }
+
+        // Message schedule.
+        for j in 0..15 {
+            yield_constr.constraint_transition(is_schedule * (nv[W_WINDOW.start + j] - w[j + 1]));
+        }
+        let next_w = lv[SMALL_SIGMA1_W14] + w[9] + nv[SMALL_SIGMA0_W0] + w[0];
+        yield_constr.constraint_transition(
+            is_schedule * (nv[W_WINDOW.end - 1] + carry_w * two_32 - next_w),
+        );
+
+        // Inputs and outputs are constant within a compression.
+        for col in H_IN.start..H_OUT.end {
+            yield_constr.constraint_transition(is_shift * (nv[col] - lv[col]));
+        }
+
+        // Outputs.
+        for k in 0..NUM_OUTPUT_ROWS {
+            let flag = flags[NUM_ROUNDS + k];
+            yield_constr.constraint(flag * (w0 - lv[H_OUT.start + 3 - k]));
+            yield_constr.constraint(flag * (w0 + carry_a * two_32 - lv[H_IN.start + 3 - k] - d));
+            yield_constr.constraint(flag * (w14 - lv[H_OUT.start + 7 - k]));
+            yield_constr.constraint(flag * (w14 + carry_e * two_32 - lv[H_IN.start + 7 - k] - h));
+        }
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let lv = vars.get_local_values();
+        let nv = vars.get_next_values();
+        let two_32 = F::from_canonical_u64(1 << 32);
+
+        for &bit in lv[..W14_BITS.end]
+            .iter()
+            .chain(&lv[CARRY_A.start..CARRY_W.end])
+        {
+            let constraint = builder.mul_sub_extension(bit, bit, bit);
+            yield_constr.constraint(builder, constraint);
+        }
+
+        let flags = &lv[ROW_FLAGS];
+        let any_flag = builder.add_many_extension(flags);
+        let constraint = builder.mul_sub_extension(any_flag, any_flag, any_flag);
+        yield_constr.constraint(builder, constraint);
+        let constraint = builder.add_many_extension(&flags[1..]);
+        yield_constr.constraint_first_row(builder, constraint);
+        for i in 0..ROWS_PER_COMPRESSION - 1 {
+            let constraint = builder.sub_extension(nv[ROW_FLAGS.start + i + 1], flags[i]);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+        let is_round = builder.add_many_extension(&flags[..NUM_ROUNDS]);
+        let is_shift = builder.add_many_extension(&flags[..ROWS_PER_COMPRESSION - 1]);
+        let is_schedule = builder.add_many_extension(&flags[..NUM_ROUNDS - 1]);
+
+        let bitwise = [
+            (BIG_SIGMA0_A, &lv[state_bits(0)], BIG_SIGMA0),
+            (BIG_SIGMA1_E, &lv[state_bits(4)], BIG_SIGMA1),
+            (SMALL_SIGMA0_W0, &lv[W0_BITS], SMALL_SIGMA0),
+            (SMALL_SIGMA1_W14, &lv[W14_BITS], SMALL_SIGMA1),
+        ];
+        for (col, bits, shifts) in bitwise {
+            let value = sigma_circuit(builder, bits, shifts);
+            let constraint = builder.sub_extension(lv[col], value);
+            yield_constr.constraint(builder, constraint);
+        }
+        let maj_bits = (0..WORD_BITS)
+            .map(|i| {
+                let [a, b, c] = [0, 1, 2].map(|word| lv[state_bits(word)][i]);
+                let ab = builder.mul_extension(a, b);
+                let a_plus_b = builder.add_extension(a, b);
+                let a_xor_b = builder.arithmetic_extension(-F::TWO, F::ONE, a, b, a_plus_b);
+                builder.mul_add_extension(c, a_xor_b, ab)
+            })
+            .collect::<Vec<_>>();
+        let maj = bits_to_value_circuit(builder, &maj_bits);
+        let constraint = builder.sub_extension(lv[MAJ_ABC], maj);
+        yield_constr.constraint(builder, constraint);
+        let ch_bits = (0..WORD_BITS)
+            .map(|i| {
+                let [e, f, g] = [4, 5, 6].map(|word| lv[state_bits(word)][i]);
+                let f_minus_g = builder.sub_extension(f, g);
+                builder.mul_add_extension(e, f_minus_g, g)
+            })
+            .collect::<Vec<_>>();
+        let ch = bits_to_value_circuit(builder, &ch_bits);
+        let constraint = builder.sub_extension(lv[CH_EFG], ch);
+        yield_constr.constraint(builder, constraint);
+
+        let state: [ExtensionTarget<D>; 8] =
+            core::array::from_fn(|word| bits_to_value_circuit(builder, &lv[state_bits(word)]));
+        let next_state: [ExtensionTarget<D>; 8] =
+            core::array::from_fn(|word| bits_to_value_circuit(builder, &nv[state_bits(word)]));
+        let [a, b, c, d, e, f, g, h] = state;
+        let w = &lv[W_WINDOW];
+        let w0 = bits_to_value_circuit(builder, &lv[W0_BITS]);
+        let w14 = bits_to_value_circuit(builder, &lv[W14_BITS]);
+        let carry_a = bits_to_value_circuit(builder, &lv[CARRY_A]);
+        let carry_e = bits_to_value_circuit(builder, &lv[CARRY_E]);
+        let carry_w = bits_to_value_circuit(builder, &lv[CARRY_W]);
+
+        for (word, &h_in) in state.into_iter().zip(&lv[H_IN]) {
+            let diff = builder.sub_extension(word, h_in);
+            let constraint = builder.mul_extension(flags[0], diff);
+            yield_constr.constraint(builder, constraint);
+        }
+        for (&w, &block) in w.iter().zip(&lv[BLOCK]) {
+            let diff = builder.sub_extension(w, block);
+            let constraint = builder.mul_extension(flags[0], diff);
+            yield_constr.constraint(builder, constraint);
+        }
+
+        for (word, bits) in [(w[0], w0), (w[14], w14)] {
+            let diff = builder.sub_extension(word, bits);
+            let constraint = builder.mul_extension(is_round, diff);
+            yield_constr.constraint(builder, constraint);
+        }
+        let zero = builder.zero_extension();
+        let k = flags[..NUM_ROUNDS]
+            .iter()
+            .zip(SHA256_K)
+            .fold(zero, |acc, (&flag, k)| {
+                builder.mul_const_add_extension(F::from_canonical_u32(k), flag, acc)
+            });
+        let t1 = builder.add_many_extension([h, lv[BIG_SIGMA1_E], lv[CH_EFG], k, w[0]]);
+        let t2 = builder.add_extension(lv[BIG_SIGMA0_A], lv[MAJ_ABC]);
+        let new_a = builder.mul_const_add_extension(two_32, carry_a, next_state[0]);
+        let t1_plus_t2 = builder.add_extension(t1, t2);
+        let diff = builder.sub_extension(new_a, t1_plus_t2);
+        let constraint = builder.mul_extension(is_round, diff);
+        yield_constr.constraint_transition(builder, constraint);
+        let new_e = builder.mul_const_add_extension(two_32, carry_e, next_state[4]);
+        let d_plus_t1 = builder.add_extension(d, t1);
+        let diff = builder.sub_extension(new_e, d_plus_t1);
+        let constraint = builder.mul_extension(is_round, diff);
+        yield_constr.constraint_transition(builder, constraint);
+        for (next, prev) in [(1, a), (2, b), (3, c), (5, e), (6, f), (7, g)] {
+            let diff = builder.sub_extension(next_state[next], prev);
+            let constraint = builder.mul_extension(is_shift, diff);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+
+        for j in 0..15 {
+            let diff = builder.sub_extension(nv[W_WINDOW.start + j], w[j + 1]);
+            let constraint = builder.mul_extension(is_schedule, diff);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+        let next_w =
+            builder.add_many_extension([lv[SMALL_SIGMA1_W14], w[9], nv[SMALL_SIGMA0_W0], w[0]]);
+        let computed_w = builder.mul_const_add_extension(two_32, carry_w, nv[W_WINDOW.end - 1]);
+        let diff = builder.sub_extension(computed_w, next_w);
+        let constraint = builder.mul_extension(is_schedule, diff);
+        yield_constr.constraint_transition(builder, constraint);
+
+        for col in H_IN.start..H_OUT.end {
+            let diff = builder.sub_extension(nv[col], lv[col]);
+            let constraint = builder.mul_extension(is_shift, diff);
+            yield_constr.constraint_transition(builder, constraint);
+        }
+
+        for k in 0..NUM_OUTPUT_ROWS {
+            let flag = flags[NUM_ROUNDS + k];
+            for (out, carry, word, out_idx) in [(w0, carry_a, d, 3 - k), (w14, carry_e, h, 7 - k)] {
+                let diff = builder.sub_extension(out, lv[H_OUT.start + out_idx]);
+                let constraint = builder.mul_extension(flag, diff);
+                yield_constr.constraint(builder, constraint);
+
+                let sum = builder.mul_const_add_extension(two_32, carry, out);
+                let expected = builder.add_extension(lv[H_IN.start + out_idx], word);
+                let diff = builder.sub_extension(sum, expected);
+                let constraint = builder.mul_extension(flag, diff);
+                yield_constr.constraint(builder, constraint);
+            }
+        }
+    }
+
+    fn constraint_degree(&self) -> usize {
+        3
+    }
+
+    fn requires_ctls(&self) -> bool {
+        true
+    }
+}
+
+/// Proves the provided compressions with the [`Sha256CompressionStark`]. The compressions must be
+/// in the order in which they were delegated, see the [module documentation](self).
+pub fn prove_sha256_compressions<F, C, const D: usize>(
+    stark: &Sha256CompressionStark<F, D>,
+    config: &StarkConfig,
+    compressions: &[Sha256Compression],
+    timing: &mut TimingTree,
+) -> Result<StarkProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let values = compressions
+        .iter()
+        .map(Sha256Compression::values::<F>)
+        .collect::<Vec<_>>();
+    prove_delegated(
+        stark,
+        config,
+        stark.generate_trace(compressions),
+        sha256_ctl(),
+        &values,
+        timing,
+    )
+}
+
+/// Verifies a [`Sha256CompressionStark`] proof of exactly the provided compressions.
+pub fn verify_sha256_compressions<F, C, const D: usize>(
+    stark: &Sha256CompressionStark<F, D>,
+    proof: &StarkProofWithPublicInputs<F, C, D>,
+    compressions: &[Sha256Compression],
+    config: &StarkConfig,
+) -> Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let values = compressions
+        .iter()
+        .map(Sha256Compression::values::<F>)
+        .collect::<Vec<_>>();
+    verify_delegated(stark, proof, sha256_ctl(), &values, config)
+}
+
+/// Collects the SHA-256 compressions delegated by a plonky2 circuit to a
+/// [`Sha256CompressionStark`], and checks them against a recursively verified proof. See the
+/// [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct Sha256DelegationTarget {
+    compressions: Vec<Vec<Target>>,
+}
+
+impl Sha256DelegationTarget {
+    /// Creates a new [`Sha256DelegationTarget`], with no delegated compression.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of compressions delegated so far.
+    pub fn num_compressions(&self) -> usize {
+        self.compressions.len()
+    }
+
+    /// Delegates the compression of `block` into the chaining state `state`, returning the next
+    /// chaining state. All words must be 32-bit values, otherwise the proof will not verify.
+    pub fn compress<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        state: [Target; 8],
+        block: [Target; 16],
+    ) -> [Target; 8] {
+        let output = builder.add_virtual_target_arr::<8>();
+        let inputs = state.into_iter().chain(block).collect::<Vec<_>>();
+        builder.add_simple_generator(Sha256CompressionGenerator {
+            inputs: inputs.clone(),
+            outputs: output.to_vec(),
+        });
+        self.compressions
+            .push(inputs.into_iter().chain(output).collect());
+        output
+    }
+
+    /// Adds a [`Sha256CompressionStark`] proof target, verifies it recursively, and checks that
+    /// it proves exactly the delegated compressions. Returns the proof target, to be set with the
+    /// output of [`prove_sha256_compressions`].
+    pub fn verify<F, C, const D: usize>(
+        self,
+        builder: &mut CircuitBuilder<F, D>,
+        stark: &Sha256CompressionStark<F, D>,
+        config: &StarkConfig,
+    ) -> StarkProofWithPublicInputsTarget<D>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        let degree_bits = log2_strict(Sha256CompressionStark::<F, D>::num_rows(
+            self.compressions.len(),
+        ));
+        verify_delegated_circuit::<F, C, _, _, D>(
+            builder,
+            stark,
+            sha256_ctl(),
+            &self.compressions,
+            degree_bits,
+            config,
+        )
+    }
+}
+
+/// Computes the output chaining state of a delegated compression.
+#[derive(Debug, Default)]
+pub struct Sha256CompressionGenerator {
+    inputs: Vec<Target>,
+    outputs: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for Sha256CompressionGenerator
+{
+    fn id(&self) -> String {
+        "Sha256CompressionGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.inputs.clone()
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let inputs = witness
+            .get_targets(&self.inputs)
+            .into_iter()
+            .map(|x| x.to_canonical_u64() as u32)
+            .collect::<Vec<_>>();
+        let state = core::array::from_fn(|i| inputs[i]);
+        let block = core::array::from_fn(|i| inputs[8 + i]);
+        let output = sha256_compress(state, block);
+
+        for (&target, word) in self.outputs.iter().zip(output) {
+            out_buffer.set_target(target, F::from_canonical_u32(word))?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.inputs)?;
+        dst.write_target_vec(&self.outputs)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let inputs = src.read_target_vec()?;
+        let outputs = src.read_target_vec()?;
+        Ok(Self { inputs, outputs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    use anyhow::Result;
+    use plonky2::field::types::{Field, PrimeField64, Sample};
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::config::StarkConfig;
+    use crate::recursive_verifier::set_stark_proof_with_pis_target;
+    use crate::sha256::{
+        prove_sha256_compressions, sha256_compress, verify_sha256_compressions, Sha256Compression,
+        Sha256CompressionStark, Sha256DelegationTarget, SHA256_IV,
+    };
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = Sha256CompressionStark<F, D>;
+
+    /// The single padded block of the message "abc".
+    fn abc_block() -> [u32; 16] {
+        let mut block = [0; 16];
+        block[0] = 0x61626380;
+        block[15] = 24;
+        block
+    }
+
+    const ABC_DIGEST: [u32; 8] = [
+        0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+        0xf20015ad,
+    ];
+
+    fn random_compression() -> Sha256Compression {
+        let random_word = || F::rand().to_canonical_u64() as u32;
+        Sha256Compression {
+            state: core::array::from_fn(|_| random_word()),
+            block: core::array::from_fn(|_| random_word()),
+        }
+    }
+
+    #[test]
+    fn test_sha256_compress() {
+        assert_eq!(sha256_compress(SHA256_IV, abc_block()), ABC_DIGEST);
+    }
+
+    #[test]
+    fn test_sha256_stark() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let compressions = vec![
+            Sha256Compression {
+                state: SHA256_IV,
+                block: abc_block(),
+            },
+            random_compression(),
+            Sha256Compression {
+                state: [u32::MAX; 8],
+                block: [u32::MAX; 16],
+            },
+        ];
+
+        let proof = prove_sha256_compressions::<F, C, D>(
+            &stark,
+            &config,
+            &compressions,
+            &mut TimingTree::default(),
+        )?;
+        verify_sha256_compressions(&stark, &proof, &compressions, &config)?;
+
+        let mut other_compressions = compressions.clone();
+        other_compressions[1].block[3] ^= 1;
+        assert!(verify_sha256_compressions(&stark, &proof, &other_compressions, &config).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sha256_stark_degree() -> Result<()> {
+        test_stark_low_degree(S::new())
+    }
+
+    #[test]
+    fn test_sha256_stark_circuit() -> Result<()> {
+        test_stark_circuit_constraints::<F, C, S, D>(S::new())
+    }
+
+    #[test]
+    fn test_sha256_delegation() -> Result<()> {
+        let stark_config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut delegation = Sha256DelegationTarget::new();
+        let mut pw = PartialWitness::new();
+
+        // Hash "abc", then compress a second block into the resulting state.
+        let second_block = random_compression().block;
+        let mut compressions = vec![];
+        let mut state = SHA256_IV.map(|x| builder.constant(F::from_canonical_u32(x)));
+        let mut native_state = SHA256_IV;
+        for block in [abc_block(), second_block] {
+            let block_targets = builder.add_virtual_target_arr::<16>();
+            for (&t, x) in block_targets.iter().zip(block) {
+                pw.set_target(t, F::from_canonical_u32(x))?;
+            }
+            state = delegation.compress(&mut builder, state, block_targets);
+
+            let compression = Sha256Compression {
+                state: native_state,
+                block,
+            };
+            native_state = compression.output();
+            compressions.push(compression);
+            if compressions.len() == 1 {
+                assert_eq!(native_state, ABC_DIGEST);
+            }
+        }
+        for (t, x) in state.into_iter().zip(native_state) {
+            let expected = builder.constant(F::from_canonical_u32(x));
+            builder.connect(t, expected);
+        }
+
+        let proof_target = delegation.verify::<F, C, D>(&mut builder, &stark, &stark_config);
+        let zero = builder.zero();
+        let data = builder.build::<C>();
+
+        let proof = prove_sha256_compressions::<F, C, D>(
+            &stark,
+            &stark_config,
+            &compressions,
+            &mut TimingTree::default(),
+        )?;
+        let degree_bits = proof.proof.recover_degree_bits(&stark_config);
+        set_stark_proof_with_pis_target(&mut pw, &proof_target, &proof, degree_bits, zero)?;
+
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
@@ -6,10 +6,10 @@ use alloc::{vec, vec::Vec};
 
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
 use plonky2::field::types::Field;
 use plonky2::fri::structure::{
-    FriBatchInfo, FriBatchInfoTarget, FriInstanceInfo, FriInstanceInfoTarget, FriOracleInfo,
-    FriPolynomialInfo,
+    FriInstanceInfo, FriInstanceInfoTarget, FriOracleInfo, FriPolynomialInfo,
 };
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
@@ -23,8 +23,10 @@ use crate::lookup::Lookup;
 
 /// Represents a STARK system.
 pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
+    /// The number of fixed columns, see [`Stark::fixed_columns`].
+    const FIXED_COLUMNS: usize = 0;
     /// The total number of columns in the trace.
-    const COLUMNS: usize = Self::EvaluationFrameTarget::COLUMNS;
+    const COLUMNS: usize = Self::EvaluationFrameTarget::COLUMNS - Self::FIXED_COLUMNS;
     /// The total number of public inputs.
     const PUBLIC_INPUTS: usize = Self::EvaluationFrameTarget::PUBLIC_INPUTS;
 
@@ -37,6 +39,37 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
     /// The `Target` version of `Self::EvaluationFrame`, used to evaluate constraints recursively.
     type EvaluationFrameTarget: StarkEvaluationFrame<ExtensionTarget<D>, ExtensionTarget<D>>;
 
+    /// Outputs the number of columns in the trace of this [`Stark`], `COLUMNS` by default.
+    ///
+    /// STARKs whose width is only known at runtime, e.g. from the number of registers of a VM,
+    /// use a [`DynamicStarkFrame`](crate::evaluation_frame::DynamicStarkFrame) and override this
+    /// method, along with [`Stark::num_public_inputs`].
+    fn num_columns(&self) -> usize {
+        Self::COLUMNS
+    }
+
+    /// Outputs the number of fixed columns of this [`Stark`], `FIXED_COLUMNS` by default.
+    fn num_fixed_columns(&self) -> usize {
+        Self::FIXED_COLUMNS
+    }
+
+    /// Outputs the number of public inputs of this [`Stark`], `PUBLIC_INPUTS` by default.
+    fn num_public_inputs(&self) -> usize {
+        Self::PUBLIC_INPUTS
+    }
+
+    /// Outputs the odd cofactor `c` of the length `c * 2^k` of the traces of this [`Stark`], `1` by
+    /// default, i.e. for traces whose length is a power of two.
+    ///
+    /// A small cofactor such as `3` or `5` lets a trace of `2^k + 1` rows be padded to `3 * 2^(k-1)`
+    /// rows rather than `2^(k+1)`. The trace is then interpolated over the subgroup generated by
+    /// [`Field::primitive_root_of_unity_of_order`], and its polynomials have degree bound
+    /// `2^(k + log2_ceil(c))`. STARKs whose trace length is not a power of two cannot take part in
+    /// cross-table lookups.
+    fn trace_len_cofactor(&self) -> usize {
+        1
+    }
+
     /// Evaluates constraints at a vector of points.
     ///
     /// The points are elements of a field `FE`, a degree `D2` extension of `F`. This lets us
@@ -107,9 +140,9 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         config: &StarkConfig,
     ) -> FriInstanceInfo<F, D> {
         let mut oracles = vec![];
-        let trace_info = FriPolynomialInfo::from_range(oracles.len(), 0..Self::COLUMNS);
+        let trace_info = FriPolynomialInfo::from_range(oracles.len(), 0..self.num_columns());
         oracles.push(FriOracleInfo {
-            num_polys: Self::COLUMNS,
+            num_polys: self.num_columns(),
             blinding: false,
         });
 
@@ -139,36 +172,45 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
             vec![]
         };
 
-        let zeta_batch = FriBatchInfo {
-            point: zeta,
-            polynomials: [
+        // The fixed columns are committed to last, so that the other oracles keep their indices,
+        // and are opened along with the trace.
+        let fixed_info = if self.num_fixed_columns() > 0 {
+            let fixed_polys =
+                FriPolynomialInfo::from_range(oracles.len(), 0..self.num_fixed_columns());
+            oracles.push(FriOracleInfo {
+                num_polys: self.num_fixed_columns(),
+                blinding: false,
+            });
+            fixed_polys
+        } else {
+            vec![]
+        };
+        let trace_info = [trace_info, fixed_info].concat();
+
+        let mut instance = FriInstanceInfo::new(oracles);
+        instance.open_at(
+            zeta,
+            [
                 trace_info.clone(),
                 auxiliary_polys_info.clone(),
                 quotient_info,
             ]
             .concat(),
-        };
-        let zeta_next_batch = FriBatchInfo {
-            point: zeta.scalar_mul(g),
-            polynomials: [trace_info, auxiliary_polys_info].concat(),
-        };
-
-        let mut batches = vec![zeta_batch, zeta_next_batch];
+        );
+        instance.open_at(
+            zeta.scalar_mul(g),
+            [trace_info, auxiliary_polys_info].concat(),
+        );
 
         if self.requires_ctls() {
             let ctl_zs_info = FriPolynomialInfo::from_range(
                 1, // auxiliary oracle index
                 num_lookup_columns + num_ctl_helpers..num_auxiliary_polys,
             );
-            let ctl_first_batch = FriBatchInfo {
-                point: F::Extension::ONE,
-                polynomials: ctl_zs_info,
-            };
-
-            batches.push(ctl_first_batch);
+            instance.open_at(F::Extension::ONE, ctl_zs_info);
         }
 
-        FriInstanceInfo { oracles, batches }
+        instance
     }
 
     /// Computes the FRI instance used to prove this Stark.
@@ -182,9 +224,9 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
         config: &StarkConfig,
     ) -> FriInstanceInfoTarget<D> {
         let mut oracles = vec![];
-        let trace_info = FriPolynomialInfo::from_range(oracles.len(), 0..Self::COLUMNS);
+        let trace_info = FriPolynomialInfo::from_range(oracles.len(), 0..self.num_columns());
         oracles.push(FriOracleInfo {
-            num_polys: Self::COLUMNS,
+            num_polys: self.num_columns(),
             blinding: false,
         });
 
@@ -214,38 +256,53 @@ pub trait Stark<F: RichField + Extendable<D>, const D: usize>: Sync {
             vec![]
         };
 
-        let zeta_batch = FriBatchInfoTarget {
-            point: zeta,
-            polynomials: [
+        let fixed_info = if self.num_fixed_columns() > 0 {
+            let fixed_polys =
+                FriPolynomialInfo::from_range(oracles.len(), 0..self.num_fixed_columns());
+            oracles.push(FriOracleInfo {
+                num_polys: self.num_fixed_columns(),
+                blinding: false,
+            });
+            fixed_polys
+        } else {
+            vec![]
+        };
+        let trace_info = [trace_info, fixed_info].concat();
+
+        let mut instance = FriInstanceInfoTarget::new(oracles);
+        instance.open_at(
+            zeta,
+            [
                 trace_info.clone(),
                 auxiliary_polys_info.clone(),
                 quotient_info,
             ]
             .concat(),
-        };
+        );
         let g_ext = builder.convert_to_ext(g);
         let zeta_next = builder.mul_extension(g_ext, zeta);
-        let zeta_next_batch = FriBatchInfoTarget {
-            point: zeta_next,
-            polynomials: [trace_info, auxiliary_polys_info].concat(),
-        };
-
-        let mut batches = vec![zeta_batch, zeta_next_batch];
+        instance.open_at(zeta_next, [trace_info, auxiliary_polys_info].concat());
 
         if self.requires_ctls() {
             let ctl_zs_info = FriPolynomialInfo::from_range(
                 1, // auxiliary oracle index
                 num_lookup_columns + num_ctl_helper_polys..num_auxiliary_polys,
             );
-            let ctl_first_batch = FriBatchInfoTarget {
-                point: builder.one_extension(),
-                polynomials: ctl_zs_info,
-            };
-
-            batches.push(ctl_first_batch);
+            let one = builder.one_extension();
+            instance.open_at(one, ctl_zs_info);
         }
 
-        FriInstanceInfoTarget { oracles, batches }
+        instance
+    }
+
+    /// Generates the values of the fixed columns of this STARK, e.g. static lookup tables, which
+    /// are committed to once in a [`StarkProvingKey`](crate::keys::StarkProvingKey) rather than in
+    /// every trace, and fix the length of the trace.
+    ///
+    /// Evaluation frames hold the fixed columns after the trace columns, so constraints and lookups
+    /// refer to them with indices starting at [`Stark::num_columns`].
+    fn fixed_columns(&self) -> Vec<PolynomialValues<F>> {
+        vec![]
     }
 
     /// Outputs all the [`Lookup`] this STARK table needs to perform across its columns.
@@ -27,9 +27,10 @@ pub fn test_stark_low_degree<F: RichField + Extendable<D>, S: Stark<F, D>, const
 ) -> Result<()> {
     let rate_bits = log2_ceil(stark.constraint_degree() + 1);
 
-    let trace_ldes = random_low_degree_matrix::<F>(S::COLUMNS, rate_bits);
+    let trace_ldes =
+        random_low_degree_matrix::<F>(stark.num_columns() + stark.num_fixed_columns(), rate_bits);
     let size = trace_ldes.len();
-    let public_inputs = F::rand_vec(S::PUBLIC_INPUTS);
+    let public_inputs = F::rand_vec(stark.num_public_inputs());
 
     let lagrange_first = PolynomialValues::selector(WITNESS_SIZE, 0).lde(rate_bits);
     let lagrange_last = PolynomialValues::selector(WITNESS_SIZE, WITNESS_SIZE - 1).lde(rate_bits);
@@ -83,9 +84,9 @@ pub fn test_stark_circuit_constraints<
 ) -> Result<()> {
     // Compute native constraint evaluation on random values.
     let vars = S::EvaluationFrame::from_values(
-        &F::Extension::rand_vec(S::COLUMNS),
-        &F::Extension::rand_vec(S::COLUMNS),
-        &F::Extension::rand_vec(S::PUBLIC_INPUTS),
+        &F::Extension::rand_vec(stark.num_columns() + stark.num_fixed_columns()),
+        &F::Extension::rand_vec(stark.num_columns() + stark.num_fixed_columns()),
+        &F::Extension::rand_vec(stark.num_public_inputs()),
     );
     let alphas = F::rand_vec(1);
     let z_last = F::Extension::rand();
@@ -108,11 +109,13 @@ pub fn test_stark_circuit_constraints<
     let mut builder = CircuitBuilder::<F, D>::new(circuit_config);
     let mut pw = PartialWitness::<F>::new();
 
-    let locals_t = builder.add_virtual_extension_targets(S::COLUMNS);
+    let locals_t =
+        builder.add_virtual_extension_targets(stark.num_columns() + stark.num_fixed_columns());
     pw.set_extension_targets(&locals_t, vars.get_local_values())?;
-    let nexts_t = builder.add_virtual_extension_targets(S::COLUMNS);
+    let nexts_t =
+        builder.add_virtual_extension_targets(stark.num_columns() + stark.num_fixed_columns());
     pw.set_extension_targets(&nexts_t, vars.get_next_values())?;
-    let pis_t = builder.add_virtual_extension_targets(S::PUBLIC_INPUTS);
+    let pis_t = builder.add_virtual_extension_targets(stark.num_public_inputs());
     pw.set_extension_targets(&pis_t, vars.get_public_inputs())?;
     let alphas_t = builder.add_virtual_targets(1);
     pw.set_target(alphas_t[0], alphas[0])?;
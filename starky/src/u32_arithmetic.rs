@@ -0,0 +1,761 @@
+//! An auxiliary STARK proving batches of 32-bit additions, multiplications and range checks, to
+//! which plonky2 circuits can delegate bulk u32 work.
+//!
+//! Range-checking a value in a plonky2 circuit costs a handful of gates per limb, which adds up
+//! quickly for circuits doing lots of u32 arithmetic. The [`U32ArithmeticStark`] instead proves
+//! all of these operations at once, by decomposing every operand and result into bytes that are
+//! range-checked through a single logUp lookup against a `0..256` table. A plonky2 circuit then
+//! only has to recursively verify the STARK proof, and check that the operations it delegated
+//! are exactly the ones proven by the STARK.
+//!
+//! Each operation is represented as a tuple `(opcode, x, y, lo, hi)`:
+//! - `Add(x, y)` proves that `x + y = lo + 2^32 * hi`, with `hi` a carry bit,
+//! - `Mul(x, y)` proves that `x * y = lo + 2^32 * hi`,
+//! - `RangeCheck(x, y)` proves that both `x` and `y` fit in 32 bits, with `lo = hi = 0`.
+//!
+//! In all cases, `x`, `y`, `lo` and `hi` are guaranteed to be 32-bit values.
+//!
+//! # Delegating u32 work from a plonky2 circuit
+//!
+//! The operations are linked to the circuit through a cross-table lookup with no looking table,
+//! as described in the [`delegation`](crate::delegation) module. The pattern is:
+//!
+//! 1. While building the circuit, record operations with a [`U32DelegationTarget`] instead of
+//!    calling the native u32 gadgets. Its results are unconstrained until step 2.
+//! 2. Call [`U32DelegationTarget::verify`], which adds a STARK proof target, verifies it
+//!    recursively and connects the delegated tuples to the STARK's CTL.
+//! 3. When proving, record the same operations in the same order with a [`U32OperationLog`], and
+//!    prove them with [`prove_u32_arithmetic`]. Set the returned proof in the witness with
+//!    [`set_stark_proof_with_pis_target`](crate::recursive_verifier::set_stark_proof_with_pis_target).
+//!
+//! The delegated tuples are observed by the challenger before the CTL challenges are drawn, so
+//! the native operations must be passed in the same order as they were recorded in the circuit,
+//! and the circuit is only satisfiable if the STARK proves exactly the same multiset of tuples.
+//! Range checks are paired up two at a time, in recording order, by both
+//! [`U32DelegationTarget`] and [`U32OperationLog`].
+//!
+//! The number of delegated operations is fixed by the circuit, which determines the STARK's
+//! degree, see [`U32ArithmeticStark::num_rows`].
+//!
+//! The witness generator used by [`U32DelegationTarget`] is not part of plonky2's default
+//! generator serializer, so circuits using it need a custom one to be serialized.
+//!
+//! The constraints assume the Goldilocks field, for which the product of two 32-bit values has a
+//! unique canonical decomposition once `hi = 2^32 - 1` is excluded.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use anyhow::Result;
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::util::log2_strict;
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+use plonky2::util::timing::TimingTree;
+
+use crate::config::StarkConfig;
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::cross_table_lookup::{CrossTableLookup, TableWithColumns};
+use crate::delegation::{prove_delegated, verify_delegated, verify_delegated_circuit};
+use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+use crate::lookup::{Column, Filter, Lookup};
+use crate::proof::{StarkProofWithPublicInputs, StarkProofWithPublicInputsTarget};
+use crate::stark::Stark;
+use crate::util::trace_rows_to_poly_values;
+
+/// Flag set on rows proving an addition.
+const IS_ADD: usize = 0;
+/// Flag set on rows proving a multiplication.
+const IS_MUL: usize = 1;
+/// Flag set on rows proving a pair of range checks.
+const IS_RANGE: usize = 2;
+/// Little-endian bytes of the first operand.
+const X_BYTES: core::ops::Range<usize> = 3..7;
+/// Little-endian bytes of the second operand.
+const Y_BYTES: core::ops::Range<usize> = 7..11;
+/// Little-endian bytes of the low 32 bits of the result.
+const LO_BYTES: core::ops::Range<usize> = 11..15;
+/// Little-endian bytes of the high 32 bits of the result.
+const HI_BYTES: core::ops::Range<usize> = 15..19;
+/// Inverse of `hi - (2^32 - 1)` on multiplication rows, proving that `hi != 2^32 - 1`.
+const HI_DIFF_INV: usize = 19;
+/// The `0..256` table that all bytes are looked up in.
+const RANGE_COUNTER: usize = 20;
+/// Number of times each value of `RANGE_COUNTER` is looked up.
+const RANGE_FREQUENCIES: usize = 21;
+
+const NUM_BYTE_COLUMNS: usize = 16;
+const U32_ARITHMETIC_COLUMNS: usize = 22;
+const U32_ARITHMETIC_PUBLIC_INPUTS: usize = 0;
+
+const RANGE_MAX: usize = u8::MAX as usize;
+
+const OPCODE_ADD: u64 = 1;
+const OPCODE_MUL: u64 = 2;
+const OPCODE_RANGE: u64 = 3;
+
+/// A single operation proven by the [`U32ArithmeticStark`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum U32Operation {
+    /// Adds two 32-bit values, producing a 32-bit result and a carry bit.
+    Add(u32, u32),
+    /// Multiplies two 32-bit values, producing the low and high 32 bits of the result.
+    Mul(u32, u32),
+    /// Checks that two values fit in 32 bits.
+    RangeCheck(u32, u32),
+}
+
+impl U32Operation {
+    /// Returns the `(lo, hi)` result of this operation.
+    pub const fn result(&self) -> (u32, u32) {
+        let value = match *self {
+            Self::Add(x, y) => x as u64 + y as u64,
+            Self::Mul(x, y) => x as u64 * y as u64,
+            Self::RangeCheck(_, _) => 0,
+        };
+        (value as u32, (value >> 32) as u32)
+    }
+
+    /// Returns the `(opcode, x, y, lo, hi)` tuple representing this operation in the
+    /// cross-table lookup.
+    pub fn values<F: Field>(&self) -> [F; 5] {
+        let (opcode, x, y) = match *self {
+            Self::Add(x, y) => (OPCODE_ADD, x, y),
+            Self::Mul(x, y) => (OPCODE_MUL, x, y),
+            Self::RangeCheck(x, y) => (OPCODE_RANGE, x, y),
+        };
+        let (lo, hi) = self.result();
+        [
+            F::from_canonical_u64(opcode),
+            F::from_canonical_u32(x),
+            F::from_canonical_u32(y),
+            F::from_canonical_u32(lo),
+            F::from_canonical_u32(hi),
+        ]
+    }
+}
+
+/// Records u32 operations natively, mirroring [`U32DelegationTarget`] so that the resulting
+/// operations match the ones delegated by a circuit.
+#[derive(Clone, Debug, Default)]
+pub struct U32OperationLog {
+    operations: Vec<U32Operation>,
+    pending_range_check: Option<u32>,
+}
+
+impl U32OperationLog {
+    /// Creates an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an addition, returning the 32-bit sum and the carry bit.
+    pub fn add(&mut self, x: u32, y: u32) -> (u32, u32) {
+        self.push(U32Operation::Add(x, y))
+    }
+
+    /// Records a multiplication, returning the low and high 32 bits of the product.
+    pub fn mul(&mut self, x: u32, y: u32) -> (u32, u32) {
+        self.push(U32Operation::Mul(x, y))
+    }
+
+    /// Records a range check of `x`.
+    pub fn range_check(&mut self, x: u32) {
+        match self.pending_range_check.take() {
+            Some(prev) => {
+                self.operations.push(U32Operation::RangeCheck(prev, x));
+            }
+            None => self.pending_range_check = Some(x),
+        }
+    }
+
+    /// Returns the recorded operations, in the order in which they must be proven.
+    pub fn into_operations(mut self) -> Vec<U32Operation> {
+        if let Some(x) = self.pending_range_check.take() {
+            self.operations.push(U32Operation::RangeCheck(x, 0));
+        }
+        self.operations
+    }
+
+    fn push(&mut self, op: U32Operation) -> (u32, u32) {
+        self.operations.push(op);
+        op.result()
+    }
+}
+
+/// A STARK proving a batch of [`U32Operation`]s, with one operation per row.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct U32ArithmeticStark<F: RichField + Extendable<D>, const D: usize> {
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> U32ArithmeticStark<F, D> {
+    /// Creates a new [`U32ArithmeticStark`].
+    pub const fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of rows of a trace proving `num_operations` operations. The trace must
+    /// be large enough to hold both the operations and the `0..256` byte table.
+    pub fn num_rows(num_operations: usize) -> usize {
+        num_operations.next_power_of_two().max(RANGE_MAX + 1)
+    }
+
+    /// Generates the trace proving the provided operations, in order.
+    pub fn generate_trace(&self, operations: &[U32Operation]) -> Vec<PolynomialValues<F>> {
+        let num_rows = Self::num_rows(operations.len());
+        let mut rows = vec![[F::ZERO; U32_ARITHMETIC_COLUMNS]; num_rows];
+
+        for (row, op) in rows.iter_mut().zip(operations) {
+            let flag = match op {
+                U32Operation::Add(_, _) => IS_ADD,
+                U32Operation::Mul(_, _) => IS_MUL,
+                U32Operation::RangeCheck(_, _) => IS_RANGE,
+            };
+            row[flag] = F::ONE;
+
+            let [_, x, y, lo, hi] = op.values::<F>();
+            for (value, cols) in [(x, X_BYTES), (y, Y_BYTES), (lo, LO_BYTES), (hi, HI_BYTES)] {
+                let bytes = (value.to_canonical_u64() as u32).to_le_bytes();
+                for (col, byte) in cols.zip(bytes) {
+                    row[col] = F::from_canonical_u8(byte);
+                }
+            }
+
+            if flag == IS_MUL {
+                row[HI_DIFF_INV] = (hi - F::from_canonical_u32(u32::MAX)).inverse();
+            }
+        }
+
+        let mut frequencies = [0usize; RANGE_MAX + 1];
+        for row in &rows {
+            for col in X_BYTES.start..HI_BYTES.end {
+                frequencies[row[col].to_canonical_u64() as usize] += 1;
+            }
+        }
+        for (i, row) in rows.iter_mut().enumerate() {
+            row[RANGE_COUNTER] = F::from_canonical_usize(i.min(RANGE_MAX));
+            if i <= RANGE_MAX {
+                row[RANGE_FREQUENCIES] = F::from_canonical_usize(frequencies[i]);
+            }
+        }
+
+        trace_rows_to_poly_values(rows)
+    }
+}
+
+/// Returns the cross-table lookup exposing the `(opcode, x, y, lo, hi)` tuples of all the
+/// operations in the [`U32ArithmeticStark`] trace. It has no looking table: the looking side is
+/// provided as an extra looking sum over the delegated operations.
+pub fn u32_arithmetic_ctl<F: Field>() -> CrossTableLookup<F> {
+    let columns = vec![
+        Column::linear_combination([
+            (IS_ADD, F::from_canonical_u64(OPCODE_ADD)),
+            (IS_MUL, F::from_canonical_u64(OPCODE_MUL)),
+            (IS_RANGE, F::from_canonical_u64(OPCODE_RANGE)),
+        ]),
+        Column::le_bytes(X_BYTES),
+        Column::le_bytes(Y_BYTES),
+        Column::le_bytes(LO_BYTES),
+        Column::le_bytes(HI_BYTES),
+    ];
+    let filter = Filter::new_simple(Column::sum([IS_ADD, IS_MUL, IS_RANGE]));
+    CrossTableLookup::new(vec![], TableWithColumns::new(0, columns, filter))
+}
+
+/// Combines little-endian bytes into the value they represent.
+fn from_le_bytes<P: PackedField>(bytes: &[P]) -> P {
+    bytes.iter().rev().fold(P::ZEROS, |acc, &byte| {
+        acc * P::Scalar::from_canonical_u16(256) + byte
+    })
+}
+
+/// Circuit version of `from_le_bytes`.
+fn from_le_bytes_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    bytes: &[ExtensionTarget<D>],
+) -> ExtensionTarget<D> {
+    let zero = builder.zero_extension();
+    bytes.iter().rev().fold(zero, |acc, &byte| {
+        builder.mul_const_add_extension(F::from_canonical_u16(256), acc, byte)
+    })
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for U32ArithmeticStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize>
+        = StarkFrame<P, P::Scalar, U32_ARITHMETIC_COLUMNS, U32_ARITHMETIC_PUBLIC_INPUTS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget = StarkFrame<
+        ExtensionTarget<D>,
+        ExtensionTarget<D>,
+        U32_ARITHMETIC_COLUMNS,
+        U32_ARITHMETIC_PUBLIC_INPUTS,
+    >;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let lv = vars.get_local_values();
+        let nv = vars.get_next_values();
+
+        // Each row proves at most one operation.
+        let (is_add, is_mul, is_range) = (lv[IS_ADD], lv[IS_MUL], lv[IS_RANGE]);
+        for flag in [is_add, is_mul, is_range] {
+            yield_constr.constraint(flag * (flag - P::ONES));
+        }
+        let is_op = is_add + is_mul + is_range;
+        yield_constr.constraint(is_op * (is_op - P::ONES));
+
+        // All bytes are range-checked by the lookup, so `x`, `y`, `lo` and `hi` are 32-bit values.
+        let x = from_le_bytes(&lv[X_BYTES]);
+        let y = from_le_bytes(&lv[Y_BYTES]);
+        let lo = from_le_bytes(&lv[LO_BYTES]);
+        let hi = from_le_bytes(&lv[HI_BYTES]);
+        let result = lo + hi * FE::from_canonical_u64(1 << 32);
+
+        yield_constr.constraint(is_add * (x + y - result));
+        yield_constr.constraint(is_add * hi * (hi - P::ONES));
+
+        // Excluding `hi = 2^32 - 1` makes the decomposition of the product unique, as it can no
+        // longer wrap around the field.
+        yield_constr.constraint(is_mul * (x * y - result));
+        let hi_diff = hi - FE::from_canonical_u32(u32::MAX);
+        yield_constr.constraint(is_mul * (hi_diff * lv[HI_DIFF_INV] - P::ONES));
+
+        // The range counter goes from 0 to 255, in steps of 0 or 1.
+        let counter = lv[RANGE_COUNTER];
+        yield_constr.constraint_first_row(counter);
+        let delta = nv[RANGE_COUNTER] - counter;
+        yield_constr.constraint_transition(delta * (delta - P::ONES));
+        yield_constr.constraint_last_row(counter - FE::from_canonical_usize(RANGE_MAX));
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let lv = vars.get_local_values();
+        let nv = vars.get_next_values();
+        let one = builder.one_extension();
+
+        let (is_add, is_mul, is_range) = (lv[IS_ADD], lv[IS_MUL], lv[IS_RANGE]);
+        for flag in [is_add, is_mul, is_range] {
+            let constraint = builder.mul_sub_extension(flag, flag, flag);
+            yield_constr.constraint(builder, constraint);
+        }
+        let is_op = builder.add_many_extension([is_add, is_mul, is_range]);
+        let constraint = builder.mul_sub_extension(is_op, is_op, is_op);
+        yield_constr.constraint(builder, constraint);
+
+        let x = from_le_bytes_circuit(builder, &lv[X_BYTES]);
+        let y = from_le_bytes_circuit(builder, &lv[Y_BYTES]);
+        let lo = from_le_bytes_circuit(builder, &lv[LO_BYTES]);
+        let hi = from_le_bytes_circuit(builder, &lv[HI_BYTES]);
+        let result = builder.mul_const_add_extension(F::from_canonical_u64(1 << 32), hi, lo);
+
+        let sum = builder.add_extension(x, y);
+        let diff = builder.sub_extension(sum, result);
+        let constraint = builder.mul_extension(is_add, diff);
+        yield_constr.constraint(builder, constraint);
+        let hi_bool = builder.mul_sub_extension(hi, hi, hi);
+        let constraint = builder.mul_extension(is_add, hi_bool);
+        yield_constr.constraint(builder, constraint);
+
+        let diff = builder.mul_sub_extension(x, y, result);
+        let constraint = builder.mul_extension(is_mul, diff);
+        yield_constr.constraint(builder, constraint);
+        let hi_diff = builder.add_const_extension(hi, -F::from_canonical_u32(u32::MAX));
+        let hi_diff_check = builder.mul_sub_extension(hi_diff, lv[HI_DIFF_INV], one);
+        let constraint = builder.mul_extension(is_mul, hi_diff_check);
+        yield_constr.constraint(builder, constraint);
+
+        let counter = lv[RANGE_COUNTER];
+        yield_constr.constraint_first_row(builder, counter);
+        let delta = builder.sub_extension(nv[RANGE_COUNTER], counter);
+        let constraint = builder.mul_sub_extension(delta, delta, delta);
+        yield_constr.constraint_transition(builder, constraint);
+        let constraint = builder.add_const_extension(counter, -F::from_canonical_usize(RANGE_MAX));
+        yield_constr.constraint_last_row(builder, constraint);
+    }
+
+    fn constraint_degree(&self) -> usize {
+        3
+    }
+
+    fn lookups(&self) -> Vec<Lookup<F>> {
+        vec![Lookup {
+            columns: Column::singles(X_BYTES.start..HI_BYTES.end).collect(),
+            table_column: Column::single(RANGE_COUNTER),
+            frequencies_column: Column::single(RANGE_FREQUENCIES),
+            filter_columns: vec![Default::default(); NUM_BYTE_COLUMNS],
+        }]
+    }
+
+    fn requires_ctls(&self) -> bool {
+        true
+    }
+}
+
+/// Proves the provided operations with the [`U32ArithmeticStark`]. The operations must be in the
+/// order in which they were delegated, see the [module documentation](self).
+pub fn prove_u32_arithmetic<F, C, const D: usize>(
+    stark: &U32ArithmeticStark<F, D>,
+    config: &StarkConfig,
+    operations: &[U32Operation],
+    timing: &mut TimingTree,
+) -> Result<StarkProofWithPublicInputs<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let values = operations
+        .iter()
+        .map(U32Operation::values::<F>)
+        .collect::<Vec<_>>();
+    prove_delegated(
+        stark,
+        config,
+        stark.generate_trace(operations),
+        u32_arithmetic_ctl(),
+        &values,
+        timing,
+    )
+}
+
+/// Verifies a [`U32ArithmeticStark`] proof of exactly the provided operations.
+pub fn verify_u32_arithmetic_proof<F, C, const D: usize>(
+    stark: &U32ArithmeticStark<F, D>,
+    proof: &StarkProofWithPublicInputs<F, C, D>,
+    operations: &[U32Operation],
+    config: &StarkConfig,
+) -> Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let values = operations
+        .iter()
+        .map(U32Operation::values::<F>)
+        .collect::<Vec<_>>();
+    verify_delegated(stark, proof, u32_arithmetic_ctl(), &values, config)
+}
+
+/// Collects the u32 operations delegated by a plonky2 circuit to a [`U32ArithmeticStark`], and
+/// checks them against a recursively verified proof. See the [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct U32DelegationTarget {
+    operations: Vec<[Target; 5]>,
+    pending_range_check: Option<Target>,
+}
+
+impl U32DelegationTarget {
+    /// Creates a new [`U32DelegationTarget`], with no delegated operation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of operations delegated so far, counting a pending range check.
+    pub fn num_operations(&self) -> usize {
+        self.operations.len() + self.pending_range_check.is_some() as usize
+    }
+
+    /// Delegates the addition of two 32-bit values, returning the 32-bit sum and the carry bit.
+    pub fn add<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        x: Target,
+        y: Target,
+    ) -> (Target, Target) {
+        self.push_operation(builder, OPCODE_ADD, x, y)
+    }
+
+    /// Delegates the multiplication of two 32-bit values, returning the low and high 32 bits of
+    /// the product.
+    pub fn mul<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        x: Target,
+        y: Target,
+    ) -> (Target, Target) {
+        self.push_operation(builder, OPCODE_MUL, x, y)
+    }
+
+    /// Delegates a range check of `x` to 32 bits.
+    pub fn range_check<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        x: Target,
+    ) {
+        match self.pending_range_check.take() {
+            Some(prev) => self.push_range_check(builder, prev, x),
+            None => self.pending_range_check = Some(x),
+        }
+    }
+
+    /// Adds a [`U32ArithmeticStark`] proof target, verifies it recursively, and checks that it
+    /// proves exactly the delegated operations. Returns the proof target, to be set with the
+    /// output of [`prove_u32_arithmetic`].
+    pub fn verify<F, C, const D: usize>(
+        mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        stark: &U32ArithmeticStark<F, D>,
+        config: &StarkConfig,
+    ) -> StarkProofWithPublicInputsTarget<D>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        if let Some(x) = self.pending_range_check.take() {
+            let zero = builder.zero();
+            self.push_range_check(builder, x, zero);
+        }
+
+        let degree_bits = log2_strict(U32ArithmeticStark::<F, D>::num_rows(self.operations.len()));
+        verify_delegated_circuit::<F, C, _, _, D>(
+            builder,
+            stark,
+            u32_arithmetic_ctl(),
+            &self.operations,
+            degree_bits,
+            config,
+        )
+    }
+
+    fn push_operation<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        opcode: u64,
+        x: Target,
+        y: Target,
+    ) -> (Target, Target) {
+        let lo = builder.add_virtual_target();
+        let hi = builder.add_virtual_target();
+        builder.add_simple_generator(U32OperationGenerator {
+            opcode,
+            x,
+            y,
+            lo,
+            hi,
+        });
+        let opcode = builder.constant(F::from_canonical_u64(opcode));
+        self.operations.push([opcode, x, y, lo, hi]);
+        (lo, hi)
+    }
+
+    fn push_range_check<F: RichField + Extendable<D>, const D: usize>(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        x: Target,
+        y: Target,
+    ) {
+        let opcode = builder.constant(F::from_canonical_u64(OPCODE_RANGE));
+        let zero = builder.zero();
+        self.operations.push([opcode, x, y, zero, zero]);
+    }
+}
+
+/// Computes the `(lo, hi)` result of a delegated addition or multiplication.
+#[derive(Debug, Default)]
+pub struct U32OperationGenerator {
+    opcode: u64,
+    x: Target,
+    y: Target,
+    lo: Target,
+    hi: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for U32OperationGenerator {
+    fn id(&self) -> String {
+        "U32OperationGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.x, self.y]
+    }
+
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> Result<()> {
+        let x = witness.get_target(self.x).to_canonical_u64() as u128;
+        let y = witness.get_target(self.y).to_canonical_u64() as u128;
+        let value = if self.opcode == OPCODE_ADD {
+            x + y
+        } else {
+            x * y
+        };
+
+        out_buffer.set_target(self.lo, F::from_canonical_u64(value as u32 as u64))?;
+        out_buffer.set_target(self.hi, F::from_noncanonical_u128(value >> 32))
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_usize(self.opcode as usize)?;
+        dst.write_target(self.x)?;
+        dst.write_target(self.y)?;
+        dst.write_target(self.lo)?;
+        dst.write_target(self.hi)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let opcode = src.read_usize()? as u64;
+        let x = src.read_target()?;
+        let y = src.read_target()?;
+        let lo = src.read_target()?;
+        let hi = src.read_target()?;
+        Ok(Self {
+            opcode,
+            x,
+            y,
+            lo,
+            hi,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    use alloc::{vec, vec::Vec};
+
+    use anyhow::Result;
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::config::StarkConfig;
+    use crate::recursive_verifier::set_stark_proof_with_pis_target;
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+    use crate::u32_arithmetic::{
+        prove_u32_arithmetic, verify_u32_arithmetic_proof, U32ArithmeticStark, U32DelegationTarget,
+        U32Operation, U32OperationLog,
+    };
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type S = U32ArithmeticStark<F, D>;
+
+    fn sample_operations() -> Vec<U32Operation> {
+        vec![
+            U32Operation::Add(u32::MAX, u32::MAX),
+            U32Operation::Add(3, 4),
+            U32Operation::Mul(u32::MAX, u32::MAX),
+            U32Operation::Mul(0, 12345),
+            U32Operation::Mul(65536, 65536),
+            U32Operation::RangeCheck(0, u32::MAX),
+        ]
+    }
+
+    #[test]
+    fn test_u32_arithmetic_stark() -> Result<()> {
+        let config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let ops = sample_operations();
+
+        let proof =
+            prove_u32_arithmetic::<F, C, D>(&stark, &config, &ops, &mut TimingTree::default())?;
+        verify_u32_arithmetic_proof(&stark, &proof, &ops, &config)?;
+
+        // The proof does not attest to a different set of operations.
+        let mut other_ops = ops.clone();
+        other_ops[1] = U32Operation::Add(3, 5);
+        assert!(verify_u32_arithmetic_proof(&stark, &proof, &other_ops, &config).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_u32_arithmetic_stark_degree() -> Result<()> {
+        test_stark_low_degree(S::new())
+    }
+
+    #[test]
+    fn test_u32_arithmetic_stark_circuit() -> Result<()> {
+        test_stark_circuit_constraints::<F, C, S, D>(S::new())
+    }
+
+    #[test]
+    fn test_u32_delegation() -> Result<()> {
+        let inputs = [(u32::MAX, 2), (123456789, 987654321), (0, 1 << 31)];
+
+        let stark_config = StarkConfig::standard_fast_config();
+        let stark = S::new();
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut delegation = U32DelegationTarget::new();
+        let mut log = U32OperationLog::new();
+        let mut pw = PartialWitness::new();
+
+        for (x, y) in inputs {
+            let xt = builder.add_virtual_target();
+            let yt = builder.add_virtual_target();
+            pw.set_target(xt, F::from_canonical_u32(x))?;
+            pw.set_target(yt, F::from_canonical_u32(y))?;
+
+            let (sum, carry) = delegation.add(&mut builder, xt, yt);
+            let (lo, hi) = delegation.mul(&mut builder, xt, yt);
+            delegation.range_check(&mut builder, sum);
+            let expected_sum = log.add(x, y);
+            let expected_product = log.mul(x, y);
+            log.range_check(expected_sum.0);
+
+            for (t, v) in [
+                (sum, expected_sum.0),
+                (carry, expected_sum.1),
+                (lo, expected_product.0),
+                (hi, expected_product.1),
+            ] {
+                let expected = builder.constant(F::from_canonical_u32(v));
+                builder.connect(t, expected);
+            }
+        }
+
+        let proof_target = delegation.verify::<F, C, D>(&mut builder, &stark, &stark_config);
+        let zero = builder.zero();
+        let data = builder.build::<C>();
+
+        let ops = log.into_operations();
+        let proof = prove_u32_arithmetic::<F, C, D>(
+            &stark,
+            &stark_config,
+            &ops,
+            &mut TimingTree::default(),
+        )?;
+        let degree_bits = proof.proof.recover_degree_bits(&stark_config);
+        set_stark_proof_with_pis_target(&mut pw, &proof_target, &proof, degree_bits, zero)?;
+
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
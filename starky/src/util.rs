@@ -4,9 +4,15 @@
 use alloc::vec::Vec;
 
 use itertools::Itertools;
-use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::extension::Extendable;
+use plonky2::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use plonky2::field::types::Field;
-use plonky2::util::transpose;
+use plonky2::fri::oracle::PolynomialBatch;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::config::GenericConfig;
+use plonky2::util::timing::TimingTree;
+use plonky2::util::{log2_ceil, transpose};
+use plonky2_maybe_rayon::*;
 
 /// A helper function to transpose a row-wise trace and put it in the format that `prove` expects.
 pub fn trace_rows_to_poly_values<F: Field, const COLUMNS: usize>(
@@ -19,3 +25,58 @@ pub fn trace_rows_to_poly_values<F: Field, const COLUMNS: usize>(
         .map(|column| PolynomialValues::new(column))
         .collect()
 }
+
+/// Returns the log of the degree bound of the polynomials interpolating a trace of length `len`,
+/// i.e. `k + log2_ceil(cofactor)` when `len = cofactor * 2^k`, or `None` if `len` is not of this
+/// form. See [`Stark::trace_len_cofactor`](crate::stark::Stark::trace_len_cofactor).
+pub fn trace_degree_bits(len: usize, cofactor: usize) -> Option<usize> {
+    if cofactor == 0 || !len.is_multiple_of(cofactor) || !(len / cofactor).is_power_of_two() {
+        return None;
+    }
+    Some((len / cofactor).trailing_zeros() as usize + log2_ceil(cofactor))
+}
+
+/// Returns the length of the traces whose polynomials have degree bound `2^degree_bits`, for the
+/// given cofactor. This is the inverse of [`trace_degree_bits`].
+pub fn trace_len(degree_bits: usize, cofactor: usize) -> usize {
+    cofactor << (degree_bits - log2_ceil(cofactor))
+}
+
+/// Interpolates the given trace columns, of length `cofactor * 2^k`, and pads their coefficients to
+/// the degree bound `2^degree_bits`.
+pub(crate) fn interpolate_trace_columns<F: Field>(
+    values: Vec<PolynomialValues<F>>,
+    degree_bits: usize,
+) -> Vec<PolynomialCoeffs<F>> {
+    values
+        .into_par_iter()
+        .map(|column| column.mixed_radix_ifft().padded(1 << degree_bits))
+        .collect()
+}
+
+/// Commits to the given trace columns, interpolating them first if their length is not a power of
+/// two.
+pub(crate) fn commit_trace_columns<F, C, const D: usize>(
+    values: Vec<PolynomialValues<F>>,
+    degree_bits: usize,
+    rate_bits: usize,
+    cap_height: usize,
+    timing: &mut TimingTree,
+) -> PolynomialBatch<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    if values[0].len() == 1 << degree_bits {
+        PolynomialBatch::from_values(values, rate_bits, false, cap_height, timing, None)
+    } else {
+        PolynomialBatch::from_coeffs(
+            interpolate_trace_columns(values, degree_bits),
+            rate_bits,
+            false,
+            cap_height,
+            timing,
+            None,
+        )
+    }
+}
@@ -16,14 +16,17 @@ use plonky2::hash::merkle_tree::MerkleCap;
 use plonky2::iop::challenger::Challenger;
 use plonky2::plonk::config::GenericConfig;
 use plonky2::plonk::plonk_common::reduce_with_powers;
+use plonky2::util::log2_ceil;
 
 use crate::config::StarkConfig;
 use crate::constraint_consumer::ConstraintConsumer;
 use crate::cross_table_lookup::CtlCheckVars;
 use crate::evaluation_frame::StarkEvaluationFrame;
+use crate::keys::StarkVerifyingKey;
 use crate::lookup::LookupCheckVars;
 use crate::proof::{StarkOpeningSet, StarkProof, StarkProofChallenges, StarkProofWithPublicInputs};
 use crate::stark::Stark;
+use crate::util::trace_len;
 use crate::vanishing_poly::eval_vanishing_poly;
 
 /// Verifies a [`StarkProofWithPublicInputs`] against a STARK statement.
@@ -38,7 +41,7 @@ pub fn verify_stark_proof<
     config: &StarkConfig,
     verifier_circuit_fri_params: Option<FriParams>,
 ) -> Result<()> {
-    ensure!(proof_with_pis.public_inputs.len() == S::PUBLIC_INPUTS);
+    ensure!(proof_with_pis.public_inputs.len() == stark.num_public_inputs());
     let mut challenger = Challenger::<F, C::Hasher>::new();
 
     let challenges = proof_with_pis.get_challenges(
@@ -54,6 +57,48 @@ pub fn verify_stark_proof<
         &proof_with_pis.proof,
         &challenges,
         None,
+        None,
+        &proof_with_pis.public_inputs,
+        config,
+    )
+}
+
+/// Verifies a [`StarkProofWithPublicInputs`] against a STARK statement with fixed columns, whose
+/// commitment is held by `verifying_key`.
+pub fn verify_stark_proof_with_key<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    S: Stark<F, D>,
+    const D: usize,
+>(
+    stark: S,
+    verifying_key: &StarkVerifyingKey<F, C, D>,
+    proof_with_pis: StarkProofWithPublicInputs<F, C, D>,
+    config: &StarkConfig,
+    verifier_circuit_fri_params: Option<FriParams>,
+) -> Result<()> {
+    ensure!(proof_with_pis.public_inputs.len() == stark.num_public_inputs());
+    ensure!(
+        proof_with_pis.proof.recover_degree_bits(config) == verifying_key.degree_bits,
+        "The trace and the fixed columns have different lengths"
+    );
+    let mut challenger = Challenger::<F, C::Hasher>::new();
+    challenger.observe_cap(&verifying_key.fixed_cap);
+
+    let challenges = proof_with_pis.get_challenges(
+        &mut challenger,
+        None,
+        false,
+        config,
+        verifier_circuit_fri_params,
+    );
+
+    verify_stark_proof_with_challenges(
+        &stark,
+        &proof_with_pis.proof,
+        &challenges,
+        None,
+        Some(&verifying_key.fixed_cap),
         &proof_with_pis.public_inputs,
         config,
     )
@@ -62,12 +107,14 @@ pub fn verify_stark_proof<
 /// Verifies a [`StarkProofWithPublicInputs`] against a STARK statement,
 /// with the provided [`StarkProofChallenges`].
 /// It also supports optional cross-table lookups data and challenges,
-/// in case this proof is part of a multi-STARK system.
+/// in case this proof is part of a multi-STARK system, and the Merkle cap
+/// of the fixed columns, if the STARK has any.
 pub fn verify_stark_proof_with_challenges<F, C, S, const D: usize>(
     stark: &S,
     proof: &StarkProof<F, C, D>,
     challenges: &StarkProofChallenges<F, D>,
     ctl_vars: Option<&[CtlCheckVars<F, F::Extension, F::Extension, D>]>,
+    fixed_cap: Option<&MerkleCap<F, C::Hasher>>,
     public_inputs: &[F],
     config: &StarkConfig,
 ) -> Result<()>
@@ -90,6 +137,7 @@ where
     validate_proof_shape(
         stark,
         proof,
+        fixed_cap,
         public_inputs,
         config,
         num_ctl_polys,
@@ -116,8 +164,10 @@ where
     );
 
     let degree_bits = proof.recover_degree_bits(config);
-    let (l_0, l_last) = eval_l_0_and_l_last(degree_bits, challenges.stark_zeta);
-    let last = F::primitive_root_of_unity(degree_bits).inverse();
+    let trace_len = trace_len(degree_bits, stark.trace_len_cofactor());
+    let g = F::primitive_root_of_unity_of_order(trace_len);
+    let (l_0, l_last) = eval_l_0_and_l_last(trace_len, g.into(), challenges.stark_zeta);
+    let last = g.inverse();
     let z_last = challenges.stark_zeta - last.into();
 
     let mut consumer = ConstraintConsumer::<F::Extension>::new(
@@ -166,11 +216,13 @@ where
 
     // Check each polynomial identity, of the form `vanishing(x) = Z_H(x) quotient(x)`, at zeta.
     let zeta_pow_deg = challenges.stark_zeta.exp_power_of_2(degree_bits);
-    let z_h_zeta = zeta_pow_deg - F::Extension::ONE;
+    let z_h_zeta = challenges.stark_zeta.exp_u64(trace_len as u64) - F::Extension::ONE;
     // `quotient_polys_zeta` holds `num_challenges * quotient_degree_factor` evaluations.
     // Each chunk of `quotient_degree_factor` holds the evaluations of `t_0(zeta),...,t_{quotient_degree_factor-1}(zeta)`
-    // where the "real" quotient polynomial is `t(X) = t_0(X) + t_1(X)*X^n + t_2(X)*X^{2n} + ...`.
-    // So to reconstruct `t(zeta)` we can compute `reduce_with_powers(chunk, zeta^n)` for each
+    // where the "real" quotient polynomial is `t(X) = t_0(X) + t_1(X)*X^d + t_2(X)*X^{2d} + ...`,
+    // with `d = 2^degree_bits` the degree bound of the trace polynomials, which is the trace length
+    // `n` unless the trace length is not a power of two.
+    // So to reconstruct `t(zeta)` we can compute `reduce_with_powers(chunk, zeta^d)` for each
     // `quotient_degree_factor`-sized chunk of the original evaluations.
 
     for (i, chunk) in quotient_polys
@@ -187,6 +239,7 @@ where
     let merkle_caps = once(proof.trace_cap.clone())
         .chain(proof.auxiliary_polys_cap.clone())
         .chain(proof.quotient_polys_cap.clone())
+        .chain(fixed_cap.cloned())
         .collect_vec();
 
     let num_ctl_zs = ctl_vars
@@ -198,13 +251,7 @@ where
         .unwrap_or_default();
 
     verify_fri_proof::<F, C, D>(
-        &stark.fri_instance(
-            challenges.stark_zeta,
-            F::primitive_root_of_unity(degree_bits),
-            num_ctl_polys,
-            num_ctl_zs,
-            config,
-        ),
+        &stark.fri_instance(challenges.stark_zeta, g, num_ctl_polys, num_ctl_zs, config),
         &proof.openings.to_fri_openings(),
         &challenges.fri_challenges,
         &merkle_caps,
@@ -218,6 +265,7 @@ where
 fn validate_proof_shape<F, C, S, const D: usize>(
     stark: &S,
     proof: &StarkProof<F, C, D>,
+    fixed_cap: Option<&MerkleCap<F, C::Hasher>>,
     public_inputs: &[F],
     config: &StarkConfig,
     num_ctl_helpers: usize,
@@ -249,7 +297,10 @@ where
         quotient_polys,
     } = openings;
 
-    ensure!(public_inputs.len() == S::PUBLIC_INPUTS);
+    ensure!(public_inputs.len() == stark.num_public_inputs());
+    let cofactor = stark.trace_len_cofactor();
+    ensure!(cofactor % 2 == 1 && degree_bits >= log2_ceil(cofactor));
+    ensure!(cofactor == 1 || num_ctl_zs == 0);
 
     let fri_params = config.fri_params(degree_bits);
     let cap_height = fri_params.config.cap_height;
@@ -260,8 +311,13 @@ where
             || quotient_polys_cap.as_ref().map(|q| q.height()) == Some(cap_height)
     );
 
-    ensure!(local_values.len() == S::COLUMNS);
-    ensure!(next_values.len() == S::COLUMNS);
+    ensure!(
+        fixed_cap.map(|cap| cap.height()) == (stark.num_fixed_columns() > 0).then_some(cap_height),
+        "A fixed cap must be provided if and only if the STARK has fixed columns"
+    );
+
+    ensure!(local_values.len() == stark.num_columns() + stark.num_fixed_columns());
+    ensure!(next_values.len() == stark.num_columns() + stark.num_fixed_columns());
     ensure!(if let Some(quotient_polys) = quotient_polys {
         quotient_polys.len() == stark.num_quotient_polys(config)
     } else {
@@ -284,11 +340,10 @@ where
 
 /// Evaluate the Lagrange polynomials `L_0` and `L_(n-1)` at a point `x`.
 /// `L_0(x) = (x^n - 1)/(n * (x - 1))`
-/// `L_(n-1)(x) = (x^n - 1)/(n * (g * x - 1))`, with `g` the first element of the subgroup.
-fn eval_l_0_and_l_last<F: Field>(log_n: usize, x: F) -> (F, F) {
-    let n = F::from_canonical_usize(1 << log_n);
-    let g = F::primitive_root_of_unity(log_n);
-    let z_x = x.exp_power_of_2(log_n) - F::ONE;
+/// `L_(n-1)(x) = (x^n - 1)/(n * (g * x - 1))`, with `g` the generator of the subgroup of order `n`.
+fn eval_l_0_and_l_last<F: Field>(n: usize, g: F, x: F) -> (F, F) {
+    let z_x = x.exp_u64(n as u64) - F::ONE;
+    let n = F::from_canonical_usize(n);
     let invs = F::batch_multiplicative_inverse(&[n * (x - F::ONE), n * (g * x - F::ONE)]);
 
     (z_x * invs[0], z_x * invs[1])
@@ -345,22 +400,25 @@ where
 mod tests {
     use plonky2::field::goldilocks_field::GoldilocksField;
     use plonky2::field::polynomial::PolynomialValues;
-    use plonky2::field::types::Sample;
+    use plonky2::field::types::{Field, Sample};
 
     use crate::verifier::eval_l_0_and_l_last;
 
     #[test]
     fn test_eval_l_0_and_l_last() {
         type F = GoldilocksField;
-        let log_n = 5;
-        let n = 1 << log_n;
-
-        let x = F::rand(); // challenge point
-        let expected_l_first_x = PolynomialValues::selector(n, 0).ifft().eval(x);
-        let expected_l_last_x = PolynomialValues::selector(n, n - 1).ifft().eval(x);
-
-        let (l_first_x, l_last_x) = eval_l_0_and_l_last(log_n, x);
-        assert_eq!(l_first_x, expected_l_first_x);
-        assert_eq!(l_last_x, expected_l_last_x);
+        // Subgroups of order a power of two, and of order `3 * 2^4`.
+        for n in [1 << 5, 3 << 4] {
+            let x = F::rand(); // challenge point
+            let expected_l_first_x = PolynomialValues::selector(n, 0).mixed_radix_ifft().eval(x);
+            let expected_l_last_x = PolynomialValues::selector(n, n - 1)
+                .mixed_radix_ifft()
+                .eval(x);
+
+            let g = F::primitive_root_of_unity_of_order(n);
+            let (l_first_x, l_last_x) = eval_l_0_and_l_last(n, g, x);
+            assert_eq!(l_first_x, expected_l_first_x);
+            assert_eq!(l_last_x, expected_l_last_x);
+        }
     }
 }